@@ -22,6 +22,11 @@ pub struct Cli {
     /// Suppress all output except errors
     #[arg(short, long, global = true)]
     pub quiet: bool,
+
+    /// Write JSON events (progress, per-file results, summary) to stdout, one per line;
+    /// the usual output goes to stderr
+    #[arg(long, global = true)]
+    pub json: bool,
 }
 
 #[derive(Subcommand)]
@@ -60,6 +65,24 @@ pub enum Commands {
         options: AtlasOptions,
     },
 
+    /// Slice a large texture into a virtual texture tile pyramid
+    Tiles {
+        /// Input texture path
+        input: PathBuf,
+
+        #[command(flatten)]
+        options: TileOptions,
+    },
+
+    /// Pack a directory of same-sized images into the layers of one KTX2 texture array
+    Array {
+        /// Input directory containing the layer images
+        input: PathBuf,
+
+        #[command(flatten)]
+        options: ArrayOptions,
+    },
+
     /// Watch for file changes and automatically process assets
     Watch {
         /// Directory to watch
@@ -87,12 +110,75 @@ pub enum Commands {
         options: AudioOptions,
     },
 
+    /// Concatenate a directory of short sounds into one audio file with a JSON map of offsets
+    Audiosprite {
+        /// Input directory containing the sounds
+        input: PathBuf,
+
+        #[command(flatten)]
+        options: AudioSpriteOptions,
+    },
+
     /// Show information about an asset file
     Info {
         /// Input file path
         input: PathBuf,
     },
 
+    /// Inspect how config rules apply to assets
+    Rules {
+        #[command(subcommand)]
+        command: RulesCommand,
+    },
+
+    /// Pack grayscale maps into the channels of one texture (e.g. occlusion/roughness/metallic)
+    Pack {
+        #[command(flatten)]
+        options: PackOptions,
+    },
+
+    /// Check assets for problems that don't break a build
+    Audit {
+        #[command(subcommand)]
+        command: AuditCommand,
+    },
+
+    /// Download or copy build outputs listed in a manifest, verifying their hashes
+    Restore {
+        #[command(flatten)]
+        options: RestoreOptions,
+    },
+
+    /// Inspect or unpack a build bundle
+    Bundle {
+        #[command(subcommand)]
+        command: BundleCommand,
+    },
+
+    /// Upgrade the config, build cache and manifest written by older versions
+    Migrate {
+        #[command(flatten)]
+        options: MigrateOptions,
+    },
+
+    /// Move or rename a source asset, updating the manifest, cache, sidecar and atlases
+    Mv {
+        /// Source file or directory to move
+        from: PathBuf,
+
+        /// New path (an existing directory moves it inside)
+        to: PathBuf,
+
+        #[command(flatten)]
+        options: MoveOptions,
+    },
+
+    /// Inspect, check or trim the build cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+
     /// Clear the build cache
     Clean {
         /// Cache directory (default: .cache in output dir)
@@ -105,6 +191,115 @@ pub enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+pub enum RulesCommand {
+    /// Show the preset, rules and sidecar matching a file and the resolved settings
+    Test {
+        /// Asset file path
+        path: PathBuf,
+
+        #[command(flatten)]
+        options: RulesTestOptions,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BundleCommand {
+    /// List the prefetch groups and assets in a bundle with their offsets
+    Ls {
+        /// Bundle file path
+        bundle: PathBuf,
+    },
+    /// Write assets from a bundle back to files
+    Extract {
+        /// Bundle file path
+        bundle: PathBuf,
+
+        /// Output or source paths of the assets to extract (default: all)
+        assets: Vec<String>,
+
+        /// Directory to extract into
+        #[arg(short, long, default_value = ".")]
+        output: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    /// Entry counts, size and age of the build cache
+    Stats {
+        #[command(flatten)]
+        options: CacheOptions,
+    },
+    /// Check every entry against its input and output; fails if any input or output is gone
+    Verify {
+        #[command(flatten)]
+        options: CacheOptions,
+    },
+    /// Drop entries whose input or output is gone, keeping the rest
+    Prune {
+        #[command(flatten)]
+        options: CacheOptions,
+
+        /// Show what would be removed without writing the cache
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum AuditCommand {
+    /// Find textures with touching colors that color-blind players can't tell apart
+    Colorblind {
+        /// Directory to audit
+        input: PathBuf,
+
+        #[command(flatten)]
+        options: ColorblindAuditOptions,
+    },
+    /// Find loops that click at the seam, overlong sound effects and clips with DC offset
+    Audio {
+        /// Directory to audit
+        input: PathBuf,
+
+        #[command(flatten)]
+        options: AudioAuditOptions,
+    },
+}
+
+#[derive(Args, Clone)]
+pub struct AudioAuditOptions {
+    /// Configuration file path
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+}
+
+#[derive(Args, Clone)]
+pub struct ColorblindAuditOptions {
+    /// Configuration file path
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+
+    /// Write simulated previews of offending textures to this directory
+    #[arg(long, value_name = "DIR")]
+    pub previews: Option<PathBuf>,
+
+    /// Audit every image, not just the configured folders
+    #[arg(long)]
+    pub all: bool,
+}
+
+#[derive(Args, Clone)]
+pub struct RulesTestOptions {
+    /// Configuration file path
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+
+    /// Platform preset
+    #[arg(short, long)]
+    pub preset: Option<PlatformPreset>,
+}
+
 #[derive(Args, Clone)]
 pub struct OptimizeOptions {
     /// Output file path (default: overwrites input)
@@ -116,12 +311,113 @@ pub struct OptimizeOptions {
     pub format: Option<OutputFormat>,
 
     /// Quality preset
-    #[arg(short, long, default_value = "balanced")]
+    #[arg(short = 'Q', long, default_value = "balanced")]
     pub quality: QualityPreset,
 
     /// Generate mipmaps (for textures)
     #[arg(long)]
     pub mipmap: bool,
+
+    /// Downsampling filter for generated mip levels
+    #[arg(long, value_enum, value_name = "FILTER", default_value = "kaiser")]
+    pub mip_filter: MipFilter,
+
+    /// Keep the alpha-test coverage of the base level at this threshold (0-1) in every mip level
+    #[arg(long, value_name = "THRESHOLD")]
+    pub alpha_coverage: Option<f32>,
+
+    /// Zstandard supercompression level for KTX2 output (1-22)
+    #[arg(long, value_name = "LEVEL", value_parser = clap::value_parser!(i32).range(1..=22))]
+    pub zstd: Option<i32>,
+
+    /// ASTC block size for astc output, or to store native ASTC instead of UASTC in ktx2
+    /// (default for astc: 8x8 fast, 6x6 balanced, 4x4 high/ultra)
+    #[arg(long, value_enum, value_name = "SIZE")]
+    pub astc_block: Option<AstcBlockSize>,
+
+    /// How HDR (.hdr/.exr) inputs are stored; rgbe writes PNG, half and bc6h write KTX2
+    #[arg(long = "hdr", value_enum, value_name = "ENCODING", default_value = "tonemap")]
+    pub hdr_encoding: HdrEncoding,
+
+    /// Tonemapping curve for HDR inputs written to 8-bit formats
+    #[arg(long, value_enum, default_value = "aces")]
+    pub tonemap: Tonemap,
+
+    /// Exposure adjustment in stops applied before tonemapping
+    #[arg(long, default_value = "0", allow_hyphen_values = true)]
+    pub exposure: f32,
+
+    /// What the texture holds; normal maps are treated as linear XY(Z) vectors
+    #[arg(long = "type", value_enum, value_name = "TYPE", default_value = "color")]
+    pub texture_type: TextureType,
+
+    /// Rescale normal map vectors to unit length after resizing and in every mip level
+    #[arg(long)]
+    pub renormalize: bool,
+
+    /// GPU encoding of normal maps in ktx2: UASTC with X in RGB and Y in alpha, or BC5 (RG)
+    #[arg(long, value_enum, value_name = "ENCODING", default_value = "uastc")]
+    pub normal_encoding: NormalEncoding,
+
+    /// Reduce PNG output to a 256-color palette (128 with fast quality) before oxipng
+    #[arg(long)]
+    pub quantize: bool,
+
+    /// Dither gradients before lossy encoding (palette PNG, JPEG, JPEG XL, KTX2, ASTC) to hide banding
+    #[arg(long)]
+    pub dither: bool,
+
+    /// Dither amplitude as a fraction of the output format's quantization step
+    #[arg(long, value_name = "STRENGTH", default_value = "1.0")]
+    pub dither_strength: f32,
+
+    /// Dither threshold pattern
+    #[arg(long, value_enum, value_name = "PATTERN", default_value = "blue-noise")]
+    pub dither_pattern: DitherPattern,
+
+    /// Multiply color by alpha before encoding, for renderers that blend premultiplied
+    #[arg(long)]
+    pub premultiply_alpha: bool,
+
+    /// Transfer function of the texture (default: sRGB for color, linear for normal maps and masks)
+    #[arg(long, value_enum, value_name = "COLORSPACE")]
+    pub colorspace: Option<TextureColorSpace>,
+
+    /// Write one variant per scale factor instead of a single output, e.g. 1,0.5,0.25
+    #[arg(long, value_name = "SCALES", value_delimiter = ',')]
+    pub variants: Vec<f32>,
+
+    /// File name template of variants; {name} is the output file stem
+    #[arg(long, value_name = "TEMPLATE", default_value = "{name}@{scale}x")]
+    pub variant_name: String,
+
+    /// Crop fully transparent borders and write the original size and offset to `<output>.trim.json`
+    #[arg(long)]
+    pub trim: bool,
+
+    /// Pick the smallest JPEG quality (or JPEG XL precision) whose output reaches this SSIM
+    #[arg(long, value_name = "SSIM")]
+    pub target_ssim: Option<f64>,
+
+    /// Remove EXIF, XMP and text metadata from PNG output (JPEG and WebP never carry it)
+    #[arg(long)]
+    pub strip_metadata: bool,
+
+    /// Bits per channel: 16 keeps 16-bit sources (16-bit PNG, R16/RGBA16 KTX2 for linear textures), 8 reduces them
+    #[arg(long, value_name = "BITS", value_parser = clap::value_parser!(u8).range(8..=16))]
+    pub bit_depth: Option<u8>,
+}
+
+/// Worker pool sizes shared by `build` and `watch`
+#[derive(Args, Clone)]
+pub struct PoolOptions {
+    /// Number of parallel jobs
+    #[arg(short, long)]
+    pub jobs: Option<usize>,
+
+    /// Number of threads in the dedicated audio encode pool (default: same as --jobs)
+    #[arg(long)]
+    pub audio_jobs: Option<usize>,
 }
 
 #[derive(Args, Clone)]
@@ -130,7 +426,7 @@ pub struct BuildOptions {
     #[arg(short, long)]
     pub output: Option<PathBuf>,
 
-    /// Platform preset (mobile, desktop, web)
+    /// Platform preset (mobile, desktop, web, switch, playstation, xbox)
     #[arg(short, long)]
     pub preset: Option<PlatformPreset>,
 
@@ -138,17 +434,70 @@ pub struct BuildOptions {
     #[arg(short, long)]
     pub config: Option<PathBuf>,
 
+    /// Build profile layered over the preset: dev, release, or one from [profiles] in the config
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+
     /// Force rebuild all assets (ignore cache)
     #[arg(long)]
     pub force: bool,
 
-    /// Number of parallel jobs
-    #[arg(short, long)]
-    pub jobs: Option<usize>,
+    /// Stop at the first asset that fails to build
+    #[arg(long, conflicts_with = "max_errors")]
+    pub fail_fast: bool,
+
+    /// Stop once this many assets have failed to build
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_errors: Option<u64>,
+
+    #[command(flatten)]
+    pub pool: PoolOptions,
 
     /// Dry run - show what would be processed without actually processing
     #[arg(long)]
     pub dry_run: bool,
+
+    /// Show per-file processing time and encode throughput
+    #[arg(long)]
+    pub timings: bool,
+
+    /// Write a Markdown report of size changes vs the previous manifest (for PR comments)
+    #[arg(long, value_name = "PATH")]
+    pub report_markdown: Option<PathBuf>,
+
+    /// Write a JSON report of per-asset sizes, timings, cache hits and errors
+    #[arg(long, value_name = "PATH")]
+    pub report_json: Option<PathBuf>,
+
+    /// Write an HTML summary of the build with the largest and slowest assets
+    #[arg(long, value_name = "PATH")]
+    pub report_html: Option<PathBuf>,
+
+    /// Manifest to compare against in the Markdown report (default: the existing output manifest)
+    #[arg(long, value_name = "PATH")]
+    pub report_baseline: Option<PathBuf>,
+
+    /// List of expected assets; missing ones get generated placeholders
+    #[arg(long, value_name = "PATH")]
+    pub placeholders: Option<PathBuf>,
+
+    /// Also pack the output into a bundle with a random-access index (see [bundle] in the config)
+    #[arg(long)]
+    pub bundle: bool,
+
+    /// Re-open every output with its decoder after the build and fail if any can't be loaded
+    #[arg(long)]
+    pub verify_outputs: bool,
+
+    /// Build files with identical contents and settings once; the copies' manifest entries
+    /// share the first one's output (`alias_of`)
+    #[arg(long)]
+    pub dedup: bool,
+
+    /// Rename outputs to `name.<hash>.<ext>` after their contents, for web builds served with
+    /// long-lived cache headers
+    #[arg(long)]
+    pub fingerprint: bool,
 }
 
 #[derive(Args, Clone)]
@@ -157,9 +506,42 @@ pub struct AtlasOptions {
     #[arg(short, long, default_value = "atlas.png")]
     pub output: PathBuf,
 
-    /// Output JSON metadata path
+    /// Output metadata path (default: the atlas path with the format's extension)
+    #[arg(long, value_name = "PATH")]
+    pub metadata: Option<PathBuf>,
+
+    /// Metadata of an earlier run whose frames stay in place; only new and resized sprites move
+    #[arg(long, value_name = "JSON")]
+    pub update: Option<PathBuf>,
+
+    /// Also write downscaled copies of the pages and metadata at these scales, e.g. 0.5,0.25
+    #[arg(long, value_name = "SCALES", value_delimiter = ',')]
+    pub variants: Vec<f32>,
+
+    /// File name template of the scaled copies; {name} is the output file stem
+    #[arg(long, value_name = "TEMPLATE", default_value = "{name}@{scale}x")]
+    pub variant_name: String,
+
+    /// Include images in subdirectories
     #[arg(long)]
-    pub json: Option<PathBuf>,
+    pub recursive: bool,
+
+    /// Leave out images matching a glob relative to the input directory (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// Emit one atlas per subfolder (`atlas_<folder>.png`) with keys prefixed by `<folder>/`;
+    /// implies --recursive
+    #[arg(long, value_enum)]
+    pub group_by: Option<AtlasGroupBy>,
+
+    /// Metadata schema
+    #[arg(long, value_enum, default_value = "native")]
+    pub metadata_format: AtlasMetadataFormat,
+
+    /// Sprite pixels per world unit, recorded in Unity metadata
+    #[arg(long, default_value = "100")]
+    pub pixels_per_unit: f32,
 
     /// Maximum atlas width
     #[arg(long, default_value = "2048")]
@@ -169,17 +551,284 @@ pub struct AtlasOptions {
     #[arg(long, default_value = "2048")]
     pub max_height: u32,
 
+    /// Round page dimensions up to powers of two, repacking into the smallest that fits
+    #[arg(long)]
+    pub pot: bool,
+
+    /// Round page dimensions up to a multiple of N (4 for block-compressed formats)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub multiple_of: Option<u32>,
+
     /// Padding between sprites
     #[arg(long, default_value = "2")]
     pub padding: u32,
 
+    /// Repeat each sprite's edge pixels this far outward, against bilinear filtering seams
+    #[arg(long, default_value = "0")]
+    pub extrude: u32,
+
+    /// Packing algorithm
+    #[arg(long, value_enum, default_value = "skyline")]
+    pub packer: AtlasPacker,
+
+    /// Break ties between equally sized sprites in maxrects/guillotine packing; the same seed
+    /// and sprites always give the same atlas
+    #[arg(long, default_value = "0")]
+    pub seed: u64,
+
+    /// Rotate sprites 90° clockwise where that packs tighter (frames record `rotated`)
+    #[arg(long)]
+    pub allow_rotation: bool,
+
     /// Trim transparent pixels from sprites
     #[arg(long)]
     pub trim: bool,
 
+    /// Alpha values up to this count as transparent when trimming
+    #[arg(long, value_name = "0-255", default_value = "0")]
+    pub trim_threshold: u8,
+
     /// Output format for the atlas
     #[arg(short, long)]
     pub format: Option<OutputFormat>,
+
+    /// Quality preset for compressed pages (WebP, JPEG, KTX2, ASTC, ...)
+    #[arg(long, default_value = "balanced")]
+    pub quality: QualityPreset,
+
+    /// Generate mipmaps of each page (KTX2/ASTC/DDS output)
+    #[arg(long)]
+    pub mipmaps: bool,
+
+    /// Zstandard supercompression level for KTX2 pages (1-22)
+    #[arg(long, value_name = "LEVEL", value_parser = clap::value_parser!(i32).range(1..=22))]
+    pub zstd: Option<i32>,
+
+    /// ASTC block size for astc pages, or to store native ASTC instead of UASTC in ktx2
+    #[arg(long, value_enum, value_name = "SIZE")]
+    pub astc_block: Option<AstcBlockSize>,
+
+    /// Maximum encoded size per atlas page (e.g. 4MB, 512KB); larger atlases are split into pages
+    #[arg(long, value_parser = parse_byte_size)]
+    pub max_page_size: Option<u64>,
+
+    /// How frame keys are derived from sprite paths
+    #[arg(long, value_enum, default_value = "stem")]
+    pub key_format: FrameKeyFormat,
+
+    /// Prefix added to every frame key
+    #[arg(long, default_value = "")]
+    pub key_prefix: String,
+
+    /// Suffix added to every frame key
+    #[arg(long, default_value = "")]
+    pub key_suffix: String,
+
+    /// Write an animated preview of each animation (numbered frames like `walk_01`) to this directory
+    #[arg(long)]
+    pub previews: Option<PathBuf>,
+
+    /// Animation preview format
+    #[arg(long, value_enum, default_value = "webp")]
+    pub preview_format: PreviewFormat,
+
+    /// Frame duration in milliseconds (a sprite's `frame_duration` sidecar overrides it)
+    #[arg(long, default_value = "100")]
+    pub frame_duration: u32,
+
+    /// Multiply page colors by alpha, for renderers that blend premultiplied
+    #[arg(long)]
+    pub premultiply_alpha: bool,
+
+    /// Detect nine-slice borders of every sprite (a sprite's `nine_slice` or `slice_borders`
+    /// sidecar overrides it)
+    #[arg(long)]
+    pub nine_slice: bool,
+}
+
+#[derive(Args, Clone)]
+pub struct TileOptions {
+    /// Output directory for tiles and `index.json` (default: `<input>.tiles`)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Texels per tile side, excluding the border
+    #[arg(long, default_value = "256")]
+    pub tile_size: u32,
+
+    /// Texels repeated from neighbouring tiles on each side
+    #[arg(long, default_value = "4")]
+    pub border: u32,
+
+    /// Tile format
+    #[arg(short, long, default_value = "png")]
+    pub format: OutputFormat,
+
+    /// Quality preset
+    #[arg(long, default_value = "balanced")]
+    pub quality: QualityPreset,
+
+    /// Zstandard supercompression level for KTX2 tiles (1-22)
+    #[arg(long, value_name = "LEVEL", value_parser = clap::value_parser!(i32).range(1..=22))]
+    pub zstd: Option<i32>,
+
+    /// ASTC block size for astc tiles, or to store native ASTC instead of UASTC in ktx2
+    #[arg(long, value_enum, value_name = "SIZE")]
+    pub astc_block: Option<AstcBlockSize>,
+
+    /// Re-encode every tile, ignoring the cache
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Args, Clone)]
+pub struct ArrayOptions {
+    /// Output KTX2 path; layer names are written next to it as JSON
+    #[arg(short, long, default_value = "array.ktx2")]
+    pub output: PathBuf,
+
+    /// What the layers hold; normal maps are treated as linear XY(Z) vectors
+    #[arg(long = "type", value_enum, value_name = "TYPE", default_value = "color")]
+    pub texture_type: TextureType,
+
+    /// Quality preset
+    #[arg(long, default_value = "balanced")]
+    pub quality: QualityPreset,
+
+    /// Maximum layer dimension
+    #[arg(long)]
+    pub max_size: Option<u32>,
+
+    /// Generate mipmaps for every layer
+    #[arg(long)]
+    pub mipmaps: bool,
+
+    /// Downsampling filter for generated mip levels
+    #[arg(long, value_enum, value_name = "FILTER", default_value = "kaiser")]
+    pub mip_filter: MipFilter,
+
+    /// Zstandard supercompression level (1-22)
+    #[arg(long, value_name = "LEVEL", value_parser = clap::value_parser!(i32).range(1..=22))]
+    pub zstd: Option<i32>,
+}
+
+#[derive(Args, Clone)]
+pub struct PackOptions {
+    /// Output texture path
+    #[arg(short, long)]
+    pub output: PathBuf,
+
+    /// Red channel source: `<image>`, `<image>:<r|g|b|a|l>` or a constant 0-1
+    #[arg(long, value_name = "SOURCE")]
+    pub red: Option<String>,
+
+    /// Green channel source
+    #[arg(long, value_name = "SOURCE")]
+    pub green: Option<String>,
+
+    /// Blue channel source
+    #[arg(long, value_name = "SOURCE")]
+    pub blue: Option<String>,
+
+    /// Alpha channel source (default: opaque)
+    #[arg(long, value_name = "SOURCE")]
+    pub alpha: Option<String>,
+
+    /// Channels to invert, e.g. `g` to turn a gloss map into roughness
+    #[arg(long, value_name = "CHANNELS")]
+    pub invert: Option<String>,
+
+    /// Output format (default: from the output extension)
+    #[arg(short, long)]
+    pub format: Option<OutputFormat>,
+
+    /// Quality preset
+    #[arg(long, default_value = "balanced")]
+    pub quality: QualityPreset,
+
+    /// Maximum texture dimension
+    #[arg(long)]
+    pub max_size: Option<u32>,
+
+    /// Generate mipmaps (KTX2/ASTC output)
+    #[arg(long)]
+    pub mipmaps: bool,
+
+    /// Zstandard supercompression level for KTX2 output (1-22)
+    #[arg(long, value_name = "LEVEL", value_parser = clap::value_parser!(i32).range(1..=22))]
+    pub zstd: Option<i32>,
+}
+
+#[derive(Args, Clone)]
+pub struct RestoreOptions {
+    /// Manifest listing the outputs to restore
+    #[arg(long)]
+    pub manifest: PathBuf,
+
+    /// Directory or http(s) URL holding the build outputs (e.g. a CI artifact)
+    #[arg(long)]
+    pub from: String,
+
+    /// Output directory (default: the project output directory)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Configuration file path
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+
+    /// Fetch every output, even when the local copy already matches
+    #[arg(long)]
+    pub force: bool,
+
+    /// Number of parallel downloads
+    #[arg(short, long)]
+    pub jobs: Option<usize>,
+}
+
+#[derive(Args, Clone)]
+pub struct MigrateOptions {
+    /// Configuration file path (default: the nearest asset-forge.toml)
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+
+    /// Build output directory holding the manifest and cache (default: the project output directory)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Show what would change without writing anything
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args, Clone)]
+pub struct CacheOptions {
+    /// Configuration file path (default: the nearest asset-forge.toml)
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+
+    /// Build output directory holding the cache (default: the project output directory)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Clone)]
+pub struct MoveOptions {
+    /// Configuration file path (default: the nearest asset-forge.toml)
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+
+    /// Source directory the asset lives in (default: the project source directory)
+    #[arg(short, long)]
+    pub source: Option<PathBuf>,
+
+    /// Build output directory holding the manifest and cache (default: the project output directory)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Show what would change without moving or writing anything
+    #[arg(long)]
+    pub dry_run: bool,
 }
 
 #[derive(Args, Clone)]
@@ -196,9 +845,16 @@ pub struct WatchOptions {
     #[arg(short, long)]
     pub preset: Option<PlatformPreset>,
 
+    /// Build profile layered over the preset: dev, release, or one from [profiles] in the config
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+
     /// Debounce delay in milliseconds
     #[arg(long, default_value = "300")]
     pub debounce: u64,
+
+    #[command(flatten)]
+    pub pool: PoolOptions,
 }
 
 #[derive(Args, Clone)]
@@ -227,9 +883,53 @@ pub struct ModelOptions {
     #[arg(long, default_value = "0.5")]
     pub lod_ratio: f32,
 
+    /// Allow LOD simplification to collapse vertices moved by morph targets
+    #[arg(long)]
+    pub no_morph_lock: bool,
+
+    /// Keep mesh border vertices in place so LODs don't tear open at boundaries
+    #[arg(long, requires = "lod")]
+    pub lock_border: bool,
+
+    /// Use sparse simplification (faster for meshes split into many chunks)
+    #[arg(long, requires = "lod")]
+    pub sparse: bool,
+
+    /// Weight of normal deviation in LOD error (e.g. 0.01-0.1, default: ignore)
+    #[arg(long, default_value = "0", requires = "lod")]
+    pub normal_weight: f32,
+
+    /// Weight of UV deviation in LOD error, protects UV seams (e.g. 0.01-0.1, default: ignore)
+    #[arg(long, default_value = "0", requires = "lod")]
+    pub uv_weight: f32,
+
+    /// Skip welding duplicate vertices before optimization
+    #[arg(long)]
+    pub no_weld: bool,
+
+    /// Weld vertices whose attributes differ by less than this (default: exact matches only)
+    #[arg(long, default_value = "0")]
+    pub weld_epsilon: f32,
+
     /// Show model information without processing
     #[arg(long)]
     pub info: bool,
+
+    /// Render a preview thumbnail (PNG) of the model
+    #[arg(long, value_name = "PATH")]
+    pub thumbnail: Option<PathBuf>,
+
+    /// Thumbnail width and height in pixels
+    #[arg(long, default_value = "128")]
+    pub thumbnail_size: u32,
+
+    /// Also write a physics mesh as <output name>_collision.glb next to the output
+    #[arg(long, value_enum)]
+    pub collision: Option<CollisionShape>,
+
+    /// Fraction of triangles kept by `--collision simplified`
+    #[arg(long, default_value = "0.05")]
+    pub collision_ratio: f32,
 }
 
 #[derive(Args, Clone)]
@@ -238,39 +938,293 @@ pub struct AudioOptions {
     #[arg(short, long)]
     pub output: Option<PathBuf>,
 
-    /// Output format (ogg, wav)
+    /// Output format (ogg, opus, mp3, flac, wav, ima-adpcm, ms-adpcm)
     #[arg(short, long, default_value = "ogg")]
     pub format: AudioOutputFormat,
 
     /// Quality for OGG encoding (1-10, default: 5)
-    #[arg(short, long, default_value = "5")]
+    #[arg(short = 'Q', long, default_value = "5")]
     pub quality: u8,
 
+    /// Target bitrate in kbps for Opus (6-510, default: chosen by the encoder) or MP3
+    /// (8-320 in LAME's steps, default: 128)
+    #[arg(long, value_name = "KBPS", value_parser = clap::value_parser!(u32).range(6..=510))]
+    pub bitrate: Option<u32>,
+
+    /// Bitrate management for Opus and MP3 encoding
+    #[arg(long, value_enum, default_value = "vbr")]
+    pub bitrate_mode: BitrateMode,
+
     /// Target sample rate (e.g., 44100, 48000)
     #[arg(long)]
     pub sample_rate: Option<u32>,
 
+    /// Output channels: 1 downmixes to mono (-3 dB pan law), 2 makes mono sources stereo or folds surround down
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..=2))]
+    pub channels: Option<u32>,
+
+    /// WAV sample bits: 8, 16, 24, or 32 for float (default: 16)
+    #[arg(long, value_name = "BITS", value_parser = clap::value_parser!(u8).range(8..=32))]
+    pub bit_depth: Option<u8>,
+
+    /// ADPCM block length in bytes (default: 256 per channel at 11 kHz, scaled with the rate)
+    #[arg(long, value_name = "BYTES")]
+    pub adpcm_block_size: Option<u32>,
+
+    /// Resampling quality (fast = linear interpolation; others use a windowed-sinc filter)
+    #[arg(long, value_name = "PRESET", default_value = "balanced")]
+    pub resample_quality: QualityPreset,
+
     /// Normalize audio volume
     #[arg(long)]
     pub normalize: bool,
 
+    /// Remove leading and trailing silence
+    #[arg(long)]
+    pub trim_silence: bool,
+
+    /// Level at or below which audio counts as silence, in dBFS
+    #[arg(long, value_name = "DB", default_value = "-50", allow_negative_numbers = true, requires = "trim_silence")]
+    pub silence_threshold: f32,
+
+    /// Silence to keep before and after the trimmed audio, in milliseconds
+    #[arg(long, value_name = "MS", default_value = "50", requires = "trim_silence")]
+    pub silence_padding: u32,
+
+    /// Fade in over this long at the start, after trimming (e.g. 50ms, 1.5s)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration_ms)]
+    pub fade_in: Option<u32>,
+
+    /// Fade out over this long at the end, after trimming (e.g. 200ms, 2s)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration_ms)]
+    pub fade_out: Option<u32>,
+
+    /// Loop start in sample frames of the source, replacing a loop read from its smpl chunk
+    #[arg(long, value_name = "SAMPLE")]
+    pub loop_start: Option<u64>,
+
+    /// Loop end (exclusive) in sample frames of the source (default: the end of the clip)
+    #[arg(long, value_name = "SAMPLE")]
+    pub loop_end: Option<u64>,
+
     /// Show audio information without processing
     #[arg(long)]
     pub info: bool,
 }
 
+#[derive(Args, Clone)]
+pub struct AudioSpriteOptions {
+    /// Output audio path; the sprite map is written next to it as JSON
+    #[arg(short, long, default_value = "sprite.ogg")]
+    pub output: PathBuf,
+
+    /// Output format (ogg, opus, mp3, flac, wav, ima-adpcm, ms-adpcm)
+    #[arg(short, long, default_value = "ogg")]
+    pub format: AudioOutputFormat,
+
+    /// Quality for OGG encoding (1-10)
+    #[arg(long, default_value = "5")]
+    pub quality: u8,
+
+    /// Target bitrate in kbps for Opus or MP3
+    #[arg(long, value_name = "KBPS", value_parser = clap::value_parser!(u32).range(6..=510))]
+    pub bitrate: Option<u32>,
+
+    /// Bitrate management for Opus and MP3 encoding
+    #[arg(long, value_enum, default_value = "vbr")]
+    pub bitrate_mode: BitrateMode,
+
+    /// Silence between sounds, in milliseconds
+    #[arg(long, value_name = "MS", default_value = "250")]
+    pub gap: u32,
+
+    /// Sample rate of the sprite (default: the highest of the sounds)
+    #[arg(long)]
+    pub sample_rate: Option<u32>,
+
+    /// Channels of the sprite (default: the most of any sound)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..=2))]
+    pub channels: Option<u32>,
+
+    /// WAV sample bits: 8, 16, 24, or 32 for float (default: 16)
+    #[arg(long, value_name = "BITS", value_parser = clap::value_parser!(u8).range(8..=32))]
+    pub bit_depth: Option<u8>,
+
+    /// ADPCM block length in bytes (default: 256 per channel at 11 kHz, scaled with the rate)
+    #[arg(long, value_name = "BYTES")]
+    pub adpcm_block_size: Option<u32>,
+
+    /// Resampling quality for sounds at other sample rates
+    #[arg(long, value_name = "PRESET", default_value = "balanced")]
+    pub resample_quality: QualityPreset,
+
+    /// Normalize the volume of every sound
+    #[arg(long)]
+    pub normalize: bool,
+}
+
 #[derive(ValueEnum, Clone, Copy, Debug, Default)]
 pub enum AudioOutputFormat {
     #[default]
     Ogg,
+    Opus,
+    Mp3,
+    Flac,
     Wav,
+    /// IMA ADPCM in WAV
+    #[value(alias = "adpcm")]
+    ImaAdpcm,
+    /// Microsoft ADPCM in WAV
+    MsAdpcm,
 }
 
 impl std::fmt::Display for AudioOutputFormat {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             AudioOutputFormat::Ogg => write!(f, "ogg"),
+            AudioOutputFormat::Opus => write!(f, "opus"),
+            AudioOutputFormat::Mp3 => write!(f, "mp3"),
+            AudioOutputFormat::Flac => write!(f, "flac"),
             AudioOutputFormat::Wav => write!(f, "wav"),
+            AudioOutputFormat::ImaAdpcm => write!(f, "ima-adpcm"),
+            AudioOutputFormat::MsAdpcm => write!(f, "ms-adpcm"),
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BitrateMode {
+    /// Variable bitrate, spending bits where the audio needs them
+    #[default]
+    Vbr,
+    /// Variable bitrate that stays close to the target over short spans
+    Cvbr,
+    /// Constant bitrate
+    Cbr,
+}
+
+impl std::fmt::Display for BitrateMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BitrateMode::Vbr => write!(f, "vbr"),
+            BitrateMode::Cvbr => write!(f, "cvbr"),
+            BitrateMode::Cbr => write!(f, "cbr"),
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FrameKeyFormat {
+    /// Path relative to the input directory, with extension (`ui/button.png`)
+    Relpath,
+    /// File name without extension (`button`)
+    #[default]
+    Stem,
+    /// Relative path without extension (`ui/button`)
+    PathNoExt,
+}
+
+/// Algorithm placing sprites on an atlas page
+/// How `atlas` splits its input into separate atlases
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AtlasGroupBy {
+    /// One atlas per top-level subfolder
+    Folder,
+}
+
+/// Schema of the atlas metadata file
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AtlasMetadataFormat {
+    /// asset-forge's own JSON, read by the runtime `AtlasMetadata`
+    #[default]
+    Native,
+    /// TexturePacker "JSON (Hash)": frames keyed by name (Phaser, PixiJS, cocos)
+    TpHash,
+    /// TexturePacker "JSON (Array)": frames as a list with a `filename` each
+    TpArray,
+    /// libGDX text `.atlas`, read by `TextureAtlas`
+    #[value(name = "libgdx")]
+    LibGdx,
+    /// A directory of Godot 4 `AtlasTexture` resources, one `.tres` per frame
+    Godot,
+    /// Sprite rects, pivots and borders per page for a Unity editor script
+    Unity,
+}
+
+impl AtlasMetadataFormat {
+    /// Default metadata file extension
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AtlasMetadataFormat::LibGdx => "atlas",
+            // A directory named after the atlas
+            AtlasMetadataFormat::Godot => "",
+            _ => "json",
+        }
+    }
+
+    /// Whether the schema can describe sprites rotated 90° clockwise
+    pub fn supports_rotation(&self) -> bool {
+        !matches!(self, AtlasMetadataFormat::LibGdx | AtlasMetadataFormat::Godot | AtlasMetadataFormat::Unity)
+    }
+}
+
+impl std::fmt::Display for AtlasMetadataFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AtlasMetadataFormat::Native => write!(f, "native"),
+            AtlasMetadataFormat::TpHash => write!(f, "tp-hash"),
+            AtlasMetadataFormat::TpArray => write!(f, "tp-array"),
+            AtlasMetadataFormat::LibGdx => write!(f, "libgdx"),
+            AtlasMetadataFormat::Godot => write!(f, "godot"),
+            AtlasMetadataFormat::Unity => write!(f, "unity"),
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AtlasPacker {
+    /// Bottom-left placement along the top edge of the packed area; fast, best with
+    /// similarly sized sprites
+    #[default]
+    Skyline,
+    /// Best-area-fit into maximal free rectangles; the tightest packing for mixed sizes
+    #[value(name = "maxrects")]
+    MaxRects,
+    /// Best-area-fit into disjoint free rectangles, each cut in two by every sprite
+    Guillotine,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PreviewFormat {
+    /// Animated lossless WebP
+    #[default]
+    Webp,
+    /// Animated GIF (256 colors, 10ms timing steps)
+    Gif,
+}
+
+impl std::fmt::Display for PreviewFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PreviewFormat::Webp => write!(f, "webp"),
+            PreviewFormat::Gif => write!(f, "gif"),
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CollisionShape {
+    /// Single convex hull around the whole model
+    Hull,
+    /// Heavily decimated copy of the render mesh
+    Simplified,
+}
+
+impl std::fmt::Display for CollisionShape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CollisionShape::Hull => write!(f, "hull"),
+            CollisionShape::Simplified => write!(f, "simplified"),
         }
     }
 }
@@ -282,6 +1236,261 @@ pub enum OutputFormat {
     Jpeg,
     Webp,
     Ktx2,
+    /// JPEG XL (lossless at ultra quality)
+    Jxl,
+    /// ASTC blocks in a `.astc` file (use ktx2 with an ASTC block size for mipmapped KTX2)
+    Astc,
+    /// DirectDraw Surface with a DX10 header: RGBA8, or BC5 for normal maps
+    Dds,
+}
+
+impl OutputFormat {
+    /// Parse a config format name (e.g. `texture_format = "ktx2"`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "png" => Some(OutputFormat::Png),
+            "jpeg" | "jpg" => Some(OutputFormat::Jpeg),
+            "webp" => Some(OutputFormat::Webp),
+            "ktx2" => Some(OutputFormat::Ktx2),
+            "jxl" => Some(OutputFormat::Jxl),
+            "astc" => Some(OutputFormat::Astc),
+            "dds" => Some(OutputFormat::Dds),
+            _ => None,
+        }
+    }
+}
+
+/// ASTC block footprint; larger blocks mean fewer bits per texel
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AstcBlockSize {
+    /// 8 bits per texel
+    #[value(name = "4x4")]
+    Block4x4,
+    /// 3.56 bits per texel
+    #[value(name = "6x6")]
+    Block6x6,
+    /// 2 bits per texel
+    #[value(name = "8x8")]
+    Block8x8,
+}
+
+impl AstcBlockSize {
+    /// Parse a config block size name (e.g. `astc_block = "6x6"`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "4x4" => Some(AstcBlockSize::Block4x4),
+            "6x6" => Some(AstcBlockSize::Block6x6),
+            "8x8" => Some(AstcBlockSize::Block8x8),
+            _ => None,
+        }
+    }
+
+    /// Default block size for a quality preset
+    pub fn for_quality(quality: QualityPreset) -> Self {
+        match quality {
+            QualityPreset::Fast => AstcBlockSize::Block8x8,
+            QualityPreset::Balanced => AstcBlockSize::Block6x6,
+            QualityPreset::High | QualityPreset::Ultra => AstcBlockSize::Block4x4,
+        }
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        match self {
+            AstcBlockSize::Block4x4 => (4, 4),
+            AstcBlockSize::Block6x6 => (6, 6),
+            AstcBlockSize::Block8x8 => (8, 8),
+        }
+    }
+
+    /// Vulkan format for KTX2 containers
+    pub fn vk_format(&self, srgb: bool) -> u32 {
+        let unorm = match self {
+            AstcBlockSize::Block4x4 => 157,
+            AstcBlockSize::Block6x6 => 165,
+            AstcBlockSize::Block8x8 => 171,
+        };
+        if srgb { unorm + 1 } else { unorm }
+    }
+}
+
+impl std::fmt::Display for AstcBlockSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (width, height) = self.dimensions();
+        write!(f, "{}x{}", width, height)
+    }
+}
+
+/// How HDR (.hdr/.exr) textures are stored
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HdrEncoding {
+    /// Tonemap to 8-bit and write the regular output format
+    #[default]
+    Tonemap,
+    /// RGB mantissas with a shared exponent in alpha, as a PNG
+    Rgbe,
+    /// Half-float RGBA KTX2
+    Half,
+    /// BC6H (unsigned float) KTX2
+    Bc6h,
+}
+
+impl HdrEncoding {
+    /// Parse a config encoding name (e.g. `hdr_encoding = "bc6h"`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "tonemap" => Some(HdrEncoding::Tonemap),
+            "rgbe" => Some(HdrEncoding::Rgbe),
+            "half" => Some(HdrEncoding::Half),
+            "bc6h" => Some(HdrEncoding::Bc6h),
+            _ => None,
+        }
+    }
+
+    /// Format the encoding always writes, or None when it tonemaps into the configured format
+    pub fn output_format(&self) -> Option<OutputFormat> {
+        match self {
+            HdrEncoding::Tonemap => None,
+            HdrEncoding::Rgbe => Some(OutputFormat::Png),
+            HdrEncoding::Half | HdrEncoding::Bc6h => Some(OutputFormat::Ktx2),
+        }
+    }
+}
+
+/// How an image's texels are interpreted
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextureType {
+    /// sRGB color
+    #[default]
+    Color,
+    /// Tangent-space normal map (linear, XYZ in RGB)
+    NormalMap,
+    /// Linear data such as packed occlusion/roughness/metallic masks
+    Mask,
+}
+
+impl TextureType {
+    /// Parse a config texture type (e.g. `texture_type = "normal-map"`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().replace('_', "-").as_str() {
+            "color" => Some(TextureType::Color),
+            "normal-map" | "normal" => Some(TextureType::NormalMap),
+            "mask" | "linear" => Some(TextureType::Mask),
+            _ => None,
+        }
+    }
+}
+
+/// Transfer function a texture's values are stored in
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureColorSpace {
+    /// Perceptual sRGB color; embedded ICC profiles are converted to it
+    Srgb,
+    /// Linear data (roughness, height, lookup tables); ICC profiles are ignored
+    Linear,
+}
+
+impl TextureColorSpace {
+    /// Parse a config colorspace (e.g. `colorspace = "linear"`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "srgb" => Some(TextureColorSpace::Srgb),
+            "linear" => Some(TextureColorSpace::Linear),
+            _ => None,
+        }
+    }
+}
+
+/// Downsampling filter for generated mip levels
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MipFilter {
+    /// 2x2 average; fastest, slightly blurry and prone to aliasing
+    Box,
+    /// Tent filter over 4x4 texels
+    Triangle,
+    /// Lanczos-3; sharp, with a little ringing on hard edges
+    Lanczos,
+    /// Kaiser-windowed sinc; sharp with little ringing
+    #[default]
+    Kaiser,
+}
+
+impl MipFilter {
+    /// Parse a config filter name (e.g. `mip_filter = "lanczos"`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "box" => Some(MipFilter::Box),
+            "triangle" | "tent" => Some(MipFilter::Triangle),
+            "lanczos" | "lanczos3" => Some(MipFilter::Lanczos),
+            "kaiser" => Some(MipFilter::Kaiser),
+            _ => None,
+        }
+    }
+}
+
+/// Threshold pattern used when dithering gradients
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DitherPattern {
+    /// Void-and-cluster noise; fine grain without a visible pattern
+    #[default]
+    BlueNoise,
+    /// 8x8 Bayer matrix; a regular crosshatch that block encoders keep well
+    Ordered,
+}
+
+impl DitherPattern {
+    /// Parse a config pattern name (e.g. `dither_pattern = "ordered"`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().replace('_', "-").as_str() {
+            "blue-noise" | "blue" => Some(DitherPattern::BlueNoise),
+            "ordered" | "bayer" => Some(DitherPattern::Ordered),
+            _ => None,
+        }
+    }
+}
+
+/// GPU block encoding of normal maps in KTX2
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NormalEncoding {
+    /// UASTC with X in RGB and Y in alpha (transcodes to BC5/EAC RG11 with separate channels)
+    #[default]
+    Uastc,
+    /// BC5 with X in red and Y in green (desktop only)
+    Bc5,
+}
+
+impl NormalEncoding {
+    /// Parse a config encoding name (e.g. `normal_encoding = "bc5"`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "uastc" => Some(NormalEncoding::Uastc),
+            "bc5" => Some(NormalEncoding::Bc5),
+            _ => None,
+        }
+    }
+}
+
+/// Curve mapping HDR values into 0-1 when tonemapping
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tonemap {
+    /// ACES filmic approximation
+    #[default]
+    Aces,
+    /// Reinhard, x / (1 + x)
+    Reinhard,
+    /// Clip values above 1
+    Clamp,
+}
+
+impl Tonemap {
+    /// Parse a config operator name (e.g. `tonemap = "reinhard"`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "aces" => Some(Tonemap::Aces),
+            "reinhard" => Some(Tonemap::Reinhard),
+            "clamp" => Some(Tonemap::Clamp),
+            _ => None,
+        }
+    }
 }
 
 #[derive(ValueEnum, Clone, Copy, Debug, Default)]
@@ -305,6 +1514,12 @@ pub enum PlatformPreset {
     Desktop,
     /// Optimized for web (WebP, smaller sizes)
     Web,
+    /// Nintendo Switch (KTX2 transcoded to ASTC)
+    Switch,
+    /// PlayStation (KTX2 transcoded to BC7)
+    Playstation,
+    /// Xbox (KTX2 transcoded to BC7)
+    Xbox,
 }
 
 impl std::fmt::Display for OutputFormat {
@@ -314,6 +1529,21 @@ impl std::fmt::Display for OutputFormat {
             OutputFormat::Jpeg => write!(f, "jpeg"),
             OutputFormat::Webp => write!(f, "webp"),
             OutputFormat::Ktx2 => write!(f, "ktx2"),
+            OutputFormat::Jxl => write!(f, "jxl"),
+            OutputFormat::Astc => write!(f, "astc"),
+            OutputFormat::Dds => write!(f, "dds"),
+        }
+    }
+}
+
+impl QualityPreset {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "fast" => Some(QualityPreset::Fast),
+            "balanced" => Some(QualityPreset::Balanced),
+            "high" => Some(QualityPreset::High),
+            "ultra" => Some(QualityPreset::Ultra),
+            _ => None,
         }
     }
 }
@@ -335,6 +1565,73 @@ impl std::fmt::Display for PlatformPreset {
             PlatformPreset::Mobile => write!(f, "mobile"),
             PlatformPreset::Desktop => write!(f, "desktop"),
             PlatformPreset::Web => write!(f, "web"),
+            PlatformPreset::Switch => write!(f, "switch"),
+            PlatformPreset::Playstation => write!(f, "playstation"),
+            PlatformPreset::Xbox => write!(f, "xbox"),
         }
     }
 }
+
+/// Parse a byte size such as `4MB`, `512KB` or `1048576`
+pub fn parse_byte_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size: {}", value))?;
+    let multiplier = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => 1.0,
+        "k" | "kb" | "kib" => 1024.0,
+        "m" | "mb" | "mib" => 1024.0 * 1024.0,
+        "g" | "gb" | "gib" => 1024.0 * 1024.0 * 1024.0,
+        _ => return Err(format!("invalid size unit: {}", unit)),
+    };
+
+    Ok((number * multiplier) as u64)
+}
+
+/// Parse a duration such as `50ms`, `1.5s` or `200` (milliseconds) into milliseconds
+pub fn parse_duration_ms(value: &str) -> Result<u32, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration: {}", value))?;
+    let multiplier = match unit.trim().to_lowercase().as_str() {
+        "" | "ms" => 1.0,
+        "s" => 1000.0,
+        _ => return Err(format!("invalid duration unit: {}", unit)),
+    };
+
+    Ok((number * multiplier).round() as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_atlas_metadata_and_global_json() {
+        let cli = Cli::try_parse_from(["asset-forge", "atlas", "sprites", "--metadata", "out/atlas.json", "--json"]).unwrap();
+        assert!(cli.json);
+        match cli.command {
+            Commands::Atlas { options, .. } => assert_eq!(options.metadata, Some(PathBuf::from("out/atlas.json"))),
+            _ => panic!("expected the atlas command"),
+        }
+    }
+
+    #[test]
+    fn test_global_json_before_subcommand() {
+        let cli = Cli::try_parse_from(["asset-forge", "--json", "build", "assets"]).unwrap();
+        assert!(cli.json);
+        assert!(matches!(cli.command, Commands::Build { .. }));
+    }
+}
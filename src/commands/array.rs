@@ -0,0 +1,53 @@
+use anyhow::Result;
+use console::style;
+use std::path::PathBuf;
+
+use crate::cli::{ArrayOptions, TextureType};
+use crate::processors::{generate_texture_array, BasisConfig};
+use crate::utils::{emit_output, format_size, say};
+
+pub fn run(input: PathBuf, options: ArrayOptions) -> Result<()> {
+    if !input.is_dir() {
+        anyhow::bail!("Input path is not a directory: {}", input.display());
+    }
+
+    say!(
+        "{} Packing texture array from: {}",
+        style("→").blue().bold(),
+        input.display()
+    );
+
+    let config = BasisConfig {
+        quality: options.quality,
+        generate_mipmaps: options.mipmaps,
+        max_size: options.max_size,
+        zstd_level: options.zstd,
+        normal_map: options.texture_type == TextureType::NormalMap,
+        linear: options.texture_type == TextureType::Mask,
+        mip_filter: options.mip_filter,
+        ..Default::default()
+    };
+    let output = options.output.with_extension("ktx2");
+    let result = generate_texture_array(&input, &output, &config)?;
+    emit_output(&input, &output, result.stats.original_size, result.stats.output_size);
+
+    say!("{} Texture array packed successfully!", style("✓").green().bold());
+    say!();
+    say!("  Texture: {}", style(output.display()).cyan());
+    say!("  Layer index: {}", style(result.index_path.display()).cyan());
+    say!(
+        "  Layers: {} at {}x{}",
+        style(result.index.layers.len()).green(),
+        result.index.width,
+        result.index.height
+    );
+    say!();
+    say!("  Original total: {}", style(format_size(result.stats.original_size)).dim());
+    say!("  Array size: {}", style(format_size(result.stats.output_size)).green());
+    say!(
+        "  Processing time: {}",
+        style(format!("{:.2}s", result.stats.processing_time_ms as f64 / 1000.0)).dim()
+    );
+
+    Ok(())
+}
@@ -1,9 +1,13 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use console::style;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::cli::AtlasOptions;
-use crate::processors::{generate_atlas, save_atlas_metadata, AtlasConfig};
+use crate::cli::{AtlasGroupBy, AtlasOptions};
+use crate::processors::{
+    atlas_image_paths, export_atlas_metadata, generate_atlas, variant_path, AtlasConfig, AtlasExportConfig,
+    AtlasMetadata,
+};
+use crate::utils::{emit_output, say};
 
 pub fn run(input: PathBuf, options: AtlasOptions) -> Result<()> {
     if !input.exists() {
@@ -14,59 +18,222 @@ pub fn run(input: PathBuf, options: AtlasOptions) -> Result<()> {
         anyhow::bail!("Input path is not a directory: {}", input.display());
     }
 
-    println!(
-        "{} Generating sprite atlas from: {}",
-        style("→").blue().bold(),
-        input.display()
-    );
+    if options.allow_rotation && !options.metadata_format.supports_rotation() {
+        anyhow::bail!(
+            "--allow-rotation can't be used with --metadata-format {}: it can't describe sprites rotated clockwise",
+            options.metadata_format
+        );
+    }
+
+    if options.update.is_some() && options.group_by.is_some() {
+        anyhow::bail!("--update can't be used with --group-by: it describes a single atlas");
+    }
+    let update = match &options.update {
+        Some(path) => Some(
+            AtlasMetadata::open(path).with_context(|| format!("Failed to read atlas metadata: {}", path.display()))?,
+        ),
+        None => None,
+    };
 
     let config = AtlasConfig {
         max_width: options.max_width,
         max_height: options.max_height,
         padding: options.padding,
+        extrude: options.extrude,
         trim: options.trim,
-        allow_rotation: false,
+        trim_threshold: options.trim_threshold,
+        allow_rotation: options.allow_rotation,
+        power_of_two: options.pot,
+        multiple_of: options.multiple_of,
+        packer: options.packer,
+        seed: options.seed,
+        format: options.format,
+        quality: options.quality,
+        generate_mipmaps: options.mipmaps,
+        ktx2_zstd_level: options.zstd,
+        astc_block: options.astc_block,
+        max_page_bytes: options.max_page_size,
+        key_format: options.key_format,
+        key_prefix: options.key_prefix.clone(),
+        key_suffix: options.key_suffix.clone(),
+        preview_dir: options.previews.clone(),
+        preview_format: options.preview_format,
+        frame_duration_ms: options.frame_duration,
+        premultiply_alpha: options.premultiply_alpha,
+        nine_slice: options.nine_slice,
+        recursive: options.recursive || options.group_by.is_some(),
+        exclude: options.exclude.clone(),
+        // Relative to the input directory in every group too
+        exclude_root: Some(input.clone()),
+        variants: options.variants.clone(),
+        variant_name: options.variant_name.clone(),
+        update,
+    };
+
+    let Some(AtlasGroupBy::Folder) = options.group_by else {
+        return write_atlas(&input, &options.output, options.metadata.as_deref(), &config, &options);
     };
 
-    let result = generate_atlas(&input, &options.output, &config)?;
+    // One atlas per subfolder, named and namespaced after it; loose images at the top level
+    // keep the plain output name
+    let mut folders: Vec<PathBuf> = std::fs::read_dir(&input)
+        .with_context(|| format!("Failed to read directory: {}", input.display()))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    folders.sort();
+
+    let mut atlases = 0;
+    let loose = AtlasConfig { recursive: false, ..config.clone() };
+    if !atlas_image_paths(&input, &loose)?.is_empty() {
+        write_atlas(&input, &options.output, options.metadata.as_deref(), &loose, &options)?;
+        atlases += 1;
+    }
+    for folder in &folders {
+        let name = folder.file_name().unwrap_or_default().to_string_lossy();
+        let group = AtlasConfig {
+            key_prefix: format!("{}{}/", options.key_prefix, name),
+            ..config.clone()
+        };
+        if atlas_image_paths(folder, &group)?.is_empty() {
+            continue;
+        }
+        let output = group_path(&options.output, &name);
+        let json = options.metadata.as_deref().map(|json| group_path(json, &name));
+        say!();
+        write_atlas(folder, &output, json.as_deref(), &group, &options)?;
+        atlases += 1;
+    }
+
+    if atlases == 0 {
+        anyhow::bail!("No image files found in directory: {}", input.display());
+    }
+    say!();
+    say!("{} {} atlases generated", style("✓").green().bold(), atlases);
+    Ok(())
+}
+
+/// `atlas.png` -> `atlas_<group>.png`
+fn group_path(path: &Path, group: &str) -> PathBuf {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("atlas");
+    let name = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}_{}.{}", stem, group, ext),
+        None => format!("{}_{}", stem, group),
+    };
+    path.with_file_name(name)
+}
+
+/// Pack one atlas from `input`, write its metadata and print the results
+fn write_atlas(input: &Path, output: &Path, json: Option<&Path>, config: &AtlasConfig, options: &AtlasOptions) -> Result<()> {
+    say!(
+        "{} Generating sprite atlas from: {}",
+        style("→").blue().bold(),
+        input.display()
+    );
+
+    // Explicit format overrides the output extension
+    let image = match options.format {
+        Some(format) => output.with_extension(format.to_string()),
+        None => output.to_path_buf(),
+    };
+
+    let result = generate_atlas(input, &image, config)?;
 
     // Save metadata JSON if requested
-    let json_path = options.json.unwrap_or_else(|| {
-        options.output.with_extension("json")
-    });
+    let json_path = json
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| output.with_extension(options.metadata_format.extension()));
 
-    save_atlas_metadata(&result.metadata, &json_path)?;
+    let export = AtlasExportConfig {
+        format: options.metadata_format,
+        pixels_per_unit: options.pixels_per_unit,
+    };
+    let metadata_paths = export_atlas_metadata(&result.metadata, &export, &json_path)?;
+    let mut variant_paths = Vec::with_capacity(result.variants.len());
+    for (scale, variant) in &result.variants {
+        let path = variant_path(&json_path, &config.variant_name, *scale);
+        variant_paths.push(export_atlas_metadata(variant, &export, &path)?);
+    }
 
     // Print results
-    println!(
+    emit_output(input, &image, result.stats.original_size, result.stats.output_size);
+    say!(
         "{} Atlas generated successfully!",
         style("✓").green().bold()
     );
-    println!();
-    println!("  Atlas image: {}", style(options.output.display()).cyan());
-    println!("  Metadata: {}", style(json_path.display()).cyan());
-    println!();
-    println!("  Dimensions: {}x{}", result.metadata.width, result.metadata.height);
-    println!("  Sprites packed: {}", style(result.metadata.frames.len()).green());
-    println!();
-    println!(
+    say!();
+    if result.metadata.pages.len() > 1 {
+        say!("  Atlas pages: {}", style(result.metadata.pages.len()).cyan());
+        for page in &result.metadata.pages {
+            say!(
+                "    {} ({}x{}, {})",
+                style(&page.image).cyan(),
+                page.width,
+                page.height,
+                format_size(page.size)
+            );
+        }
+    } else {
+        say!("  Atlas image: {}", style(image.display()).cyan());
+    }
+    for path in &metadata_paths {
+        say!("  Metadata: {}", style(path.display()).cyan());
+    }
+    for ((scale, variant), paths) in result.variants.iter().zip(&variant_paths) {
+        let pages: Vec<_> = variant
+            .pages
+            .iter()
+            .map(|page| format!("{} ({}x{}, {})", page.image, page.width, page.height, format_size(page.size)))
+            .collect();
+        say!("  Variant {}x: {}", scale, style(pages.join(", ")).cyan());
+        for path in paths {
+            say!("    Metadata: {}", style(path.display()).cyan());
+        }
+    }
+    say!();
+    say!("  Dimensions: {}x{}", result.metadata.width, result.metadata.height);
+    say!("  Sprites packed: {}", style(result.metadata.frames.len()).green());
+    say!("  Packing efficiency: {}", style(format!("{:.1}%", result.efficiency * 100.0)).green());
+    if options.update.is_some() {
+        say!("  Frames kept in place: {}", style(result.kept).green());
+    }
+    if result.duplicates > 0 {
+        say!(
+            "  Duplicate frames: {} packed once, saving {} px",
+            style(result.duplicates).green(),
+            style(result.saved_area).green()
+        );
+    }
+    if !result.metadata.animations.is_empty() {
+        say!("  Animations: {}", style(result.metadata.animations.len()).green());
+    }
+    if let Some(previews) = &options.previews {
+        say!(
+            "  Previews: {} in {}",
+            result.previews.len(),
+            style(previews.display()).cyan()
+        );
+    }
+    say!();
+    say!(
         "  Original total: {}",
         style(format_size(result.stats.original_size)).dim()
     );
-    println!(
+    say!(
         "  Atlas size: {}",
         style(format_size(result.stats.output_size)).green()
     );
 
     let reduction = result.stats.size_reduction_percent();
     if reduction > 0.0 {
-        println!(
+        say!(
             "  Size reduction: {}",
             style(format!("{:.1}%", reduction)).green()
         );
     }
 
-    println!(
+    say!(
         "  Processing time: {}",
         style(format!("{:.2}s", result.stats.processing_time_ms as f64 / 1000.0)).dim()
     );
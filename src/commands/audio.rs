@@ -3,8 +3,11 @@ use console::style;
 use std::path::PathBuf;
 use std::time::Instant;
 
-use crate::cli::{AudioOptions, AudioOutputFormat};
-use crate::processors::{process_audio, get_audio_info, AudioConfig, AudioFormat};
+use crate::cli::AudioOptions;
+use crate::processors::{
+    dbfs, process_audio, get_audio_info, AudioConfig, AudioFormat, AudioLevels, SilenceTrim, WavSampleFormat,
+};
+use crate::utils::{emit_output, say};
 
 pub fn run(input: PathBuf, options: AudioOptions) -> Result<()> {
     if !input.exists() {
@@ -17,23 +20,17 @@ pub fn run(input: PathBuf, options: AudioOptions) -> Result<()> {
     }
 
     // Determine output path
-    let output_format = match options.format {
-        AudioOutputFormat::Ogg => AudioFormat::Ogg,
-        AudioOutputFormat::Wav => AudioFormat::Wav,
-    };
+    let output_format = AudioFormat::from(options.format);
 
     let output = options.output.unwrap_or_else(|| {
         let stem = input.file_stem().unwrap_or_default();
-        let ext = match output_format {
-            AudioFormat::Ogg => "ogg",
-            AudioFormat::Wav => "wav",
-        };
+        let ext = output_format.extension();
         let default_dir = PathBuf::from(".");
         let parent = input.parent().unwrap_or(&default_dir);
         parent.join(format!("{}.{}", stem.to_string_lossy(), ext))
     });
 
-    println!(
+    say!(
         "{} Processing audio: {}",
         style("→").blue().bold(),
         input.display()
@@ -41,33 +38,96 @@ pub fn run(input: PathBuf, options: AudioOptions) -> Result<()> {
 
     // Get and display audio info
     let info = get_audio_info(&input)?;
-    println!("  Channels: {}", style(info.channels).cyan());
-    println!("  Sample rate: {} Hz", style(info.sample_rate).cyan());
-    println!("  Duration: {:.2}s", style(info.duration_secs).cyan());
-    println!("  Format: {}", style(&info.format).cyan());
+    say!("  Channels: {}", style(&info.layout).cyan());
+    say!("  Sample rate: {} Hz", style(info.sample_rate).cyan());
+    say!("  Duration: {:.2}s", style(info.duration_secs).cyan());
+    say!("  Format: {}", style(&info.format).cyan());
 
     // Build config
     let config = AudioConfig {
         output_format,
         quality: options.quality as f32 / 10.0, // Convert 1-10 to 0.1-1.0
         sample_rate: options.sample_rate,
+        max_sample_rate: None,
+        channels: options.channels,
+        resample_quality: options.resample_quality,
         normalize: options.normalize,
+        bitrate: options.bitrate,
+        bitrate_mode: options.bitrate_mode,
+        wav_format: options.bit_depth.map(WavSampleFormat::from_bits).transpose()?.unwrap_or_default(),
+        adpcm_block_size: options.adpcm_block_size,
+        trim_silence: options.trim_silence.then_some(SilenceTrim {
+            threshold_db: options.silence_threshold,
+            padding_ms: options.silence_padding,
+        }),
+        fade_in_ms: options.fade_in.unwrap_or(0),
+        fade_out_ms: options.fade_out.unwrap_or(0),
+        loop_start: options.loop_start,
+        loop_end: options.loop_end,
     };
 
     // Show processing options
-    println!();
-    println!("{} Processing options:", style("⚙").blue().bold());
-    println!("  Output format: {}", style(options.format).cyan());
+    say!();
+    say!("{} Processing options:", style("⚙").blue().bold());
+    say!("  Output format: {}", style(options.format).cyan());
     if output_format == AudioFormat::Ogg {
-        println!("  Quality: {}/10", style(options.quality).cyan());
+        say!("  Quality: {}/10", style(options.quality).cyan());
+    }
+    if output_format == AudioFormat::Wav {
+        match config.wav_format {
+            WavSampleFormat::Float32 => say!("  Bit depth: {} (float)", style(32).cyan()),
+            format => say!("  Bit depth: {}", style(format.bits()).cyan()),
+        }
+    }
+    if let AudioFormat::Adpcm(codec) = output_format {
+        match options.adpcm_block_size {
+            Some(bytes) => say!("  {} blocks: {} bytes", codec, style(bytes).cyan()),
+            None => say!("  {} blocks: auto", codec),
+        }
+    }
+    match (output_format, options.bitrate) {
+        (AudioFormat::Opus | AudioFormat::Mp3, Some(kbps)) => {
+            say!("  Bitrate: {} kbps {}", style(kbps).cyan(), options.bitrate_mode)
+        }
+        (AudioFormat::Opus, None) => say!("  Bitrate: auto {}", options.bitrate_mode),
+        (AudioFormat::Mp3, None) => say!("  Bitrate: {} kbps {}", style(128).cyan(), options.bitrate_mode),
+        _ => {}
     }
     if let Some(rate) = options.sample_rate {
-        println!("  Target sample rate: {} Hz", style(rate).cyan());
+        say!(
+            "  Target sample rate: {} Hz ({} resampling)",
+            style(rate).cyan(),
+            options.resample_quality
+        );
+    }
+    match options.channels {
+        Some(1) if info.channels > 1 => say!("  {} Downmix to mono", style("✓").green()),
+        Some(2) if info.channels > 2 => say!("  {} Downmix to stereo", style("✓").green()),
+        Some(2) if info.channels == 1 => say!("  {} Convert mono to stereo", style("✓").green()),
+        _ => {}
+    }
+    if options.trim_silence {
+        say!(
+            "  {} Trim silence below {} dB (keeping {} ms)",
+            style("✓").green(),
+            options.silence_threshold,
+            options.silence_padding
+        );
     }
     if options.normalize {
-        println!("  {} Normalize volume", style("✓").green());
+        say!("  {} Normalize volume", style("✓").green());
+    }
+    if let Some(ms) = options.fade_in {
+        say!("  {} Fade in over {} ms", style("✓").green(), ms);
+    }
+    if let Some(ms) = options.fade_out {
+        say!("  {} Fade out over {} ms", style("✓").green(), ms);
     }
-    println!();
+    if options.loop_start.is_some() || options.loop_end.is_some() {
+        let end = options.loop_end.map_or("end".to_string(), |end| end.to_string());
+        say!("  {} Loop {}..{}", style("✓").green(), options.loop_start.unwrap_or(0), end);
+    }
+    say!();
 
     // Process the audio
     let start = Instant::now();
@@ -75,15 +135,16 @@ pub fn run(input: PathBuf, options: AudioOptions) -> Result<()> {
     let elapsed = start.elapsed();
 
     // Print results
-    println!("{} Audio processed!", style("✓").green().bold());
-    println!("  Output: {}", style(output.display()).cyan());
-    println!(
+    emit_output(&input, &output, stats.original_size, stats.output_size);
+    say!("{} Audio processed!", style("✓").green().bold());
+    say!("  Output: {}", style(output.display()).cyan());
+    say!(
         "  Size: {} → {} ({})",
         format_size(stats.original_size),
         style(format_size(stats.output_size)).green(),
         format_reduction(stats.original_size, stats.output_size)
     );
-    println!("  Time: {:.2}s", elapsed.as_secs_f64());
+    say!("  Time: {:.2}s", elapsed.as_secs_f64());
 
     Ok(())
 }
@@ -92,26 +153,49 @@ fn print_audio_info(input: &PathBuf) -> Result<()> {
     let info = get_audio_info(input)?;
     let file_size = std::fs::metadata(input)?.len();
 
-    println!("{} Audio Information", style("🔊").blue().bold());
-    println!("  File: {}", style(input.display()).cyan());
-    println!("  Format: {}", style(&info.format).cyan());
-    println!();
-    println!("  {}", style("Properties:").bold());
-    println!("    Channels: {}", info.channels);
-    println!("    Sample rate: {} Hz", info.sample_rate);
-    println!("    Duration: {:.2}s", info.duration_secs);
-    println!();
-    println!("  File size: {}", format_size(file_size));
+    say!("{} Audio Information", style("🔊").blue().bold());
+    say!("  File: {}", style(input.display()).cyan());
+    say!("  Format: {}", style(&info.format).cyan());
+    say!();
+    say!("  {}", style("Properties:").bold());
+    say!("    Channels: {}", info.layout);
+    say!("    Sample rate: {} Hz", info.sample_rate);
+    say!("    Duration: {:.2}s", info.duration_secs);
+    if let Some(loop_points) = info.loop_points {
+        say!("    Loop: samples {}..{}", loop_points.start, loop_points.end);
+    }
+    say!();
+    print_levels(&info.levels);
+    say!();
+    say!("  File size: {}", format_size(file_size));
 
     // Bitrate estimate
     if info.duration_secs > 0.0 {
         let bitrate = (file_size as f64 * 8.0) / info.duration_secs / 1000.0;
-        println!("  Bitrate: ~{:.0} kbps", bitrate);
+        say!("  Bitrate: ~{:.0} kbps", bitrate);
     }
 
     Ok(())
 }
 
+/// Peak, RMS, loudness, DC offset and clipping, for QA from the command line
+pub(super) fn print_levels(levels: &AudioLevels) {
+    say!("  {}", style("Levels:").bold());
+    say!("    Peak: {:.1} dBFS", dbfs(levels.peak));
+    say!("    RMS: {:.1} dBFS", dbfs(levels.rms));
+    match levels.loudness {
+        Some(lufs) => say!("    Loudness: {:.1} LUFS (estimated)", lufs),
+        None => say!("    Loudness: {}", style("n/a (shorter than 400 ms, or silent)").dim()),
+    }
+    let (offset, channel) = levels.dc_offset;
+    say!("    DC offset: {:+.4} (channel {})", offset, channel);
+    if levels.clipped > 0 {
+        say!("    Clipped samples: {}", style(levels.clipped).red());
+    } else {
+        say!("    Clipped samples: 0");
+    }
+}
+
 fn format_size(bytes: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = 1024 * 1024;
@@ -0,0 +1,66 @@
+use anyhow::Result;
+use console::style;
+use std::path::PathBuf;
+
+use crate::cli::AudioSpriteOptions;
+use crate::processors::{generate_audio_sprite, AudioConfig, AudioFormat, AudioSpriteConfig, WavSampleFormat};
+use crate::utils::{emit_output, format_size, say};
+
+pub fn run(input: PathBuf, options: AudioSpriteOptions) -> Result<()> {
+    if !input.is_dir() {
+        anyhow::bail!("Input path is not a directory: {}", input.display());
+    }
+
+    say!(
+        "{} Generating audio sprite from: {}",
+        style("→").blue().bold(),
+        input.display()
+    );
+
+    let output_format = AudioFormat::from(options.format);
+    let config = AudioSpriteConfig {
+        audio: AudioConfig {
+            output_format,
+            quality: options.quality as f32 / 10.0,
+            sample_rate: options.sample_rate,
+            channels: options.channels,
+            resample_quality: options.resample_quality,
+            normalize: options.normalize,
+            bitrate: options.bitrate,
+            bitrate_mode: options.bitrate_mode,
+            wav_format: options.bit_depth.map(WavSampleFormat::from_bits).transpose()?.unwrap_or_default(),
+            adpcm_block_size: options.adpcm_block_size,
+            ..Default::default()
+        },
+        gap_ms: options.gap,
+    };
+    let output = options.output.with_extension(output_format.extension());
+    let result = generate_audio_sprite(&input, &output, &config)?;
+    emit_output(&input, &output, result.stats.original_size, result.stats.output_size);
+
+    say!("{} Audio sprite generated successfully!", style("✓").green().bold());
+    say!();
+    say!("  Audio: {}", style(output.display()).cyan());
+    say!("  Sprite map: {}", style(result.map_path.display()).cyan());
+    let length = result
+        .map
+        .sprites
+        .values()
+        .map(|sprite| sprite.start + sprite.duration)
+        .fold(0.0, f64::max);
+    say!(
+        "  Sounds: {} ({:.2}s with {} ms gaps)",
+        style(result.map.sprites.len()).green(),
+        length,
+        options.gap
+    );
+    say!();
+    say!("  Original total: {}", style(format_size(result.stats.original_size)).dim());
+    say!("  Sprite size: {}", style(format_size(result.stats.output_size)).green());
+    say!(
+        "  Processing time: {}",
+        style(format!("{:.2}s", result.stats.processing_time_ms as f64 / 1000.0)).dim()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,228 @@
+use anyhow::{Context, Result};
+use console::style;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::cli::{AudioAuditOptions, ColorblindAuditOptions};
+use crate::config::{find_and_load_config, load_config, Config};
+use crate::processors::{
+    analyze_audio, find_confused_colors, simulate_deficiency, AssetType, ColorDeficiency,
+};
+use crate::utils::say;
+
+/// Report textures whose touching colors become indistinguishable with color-vision deficiencies
+pub fn colorblind(input: PathBuf, options: ColorblindAuditOptions) -> Result<()> {
+    let config = load_audit_config(options.config.as_deref())?;
+    let audit = &config.audit.colorblind;
+    let folders = audit.matcher()?;
+
+    say!("{} Color-blind audit: {}", style("🎨").blue().bold(), style(input.display()).cyan());
+    if !options.all {
+        say!("  Folders: {}", audit.folders.join(", "));
+    }
+    say!("  Minimum simulated difference: {} ΔE", audit.min_distance);
+    say!();
+
+    let files: Vec<PathBuf> = WalkDir::new(&input)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.path().to_path_buf())
+        .filter(|p| AssetType::from_path(p) == AssetType::Image)
+        .filter(|p| options.all || folders.is_match(p.strip_prefix(&input).unwrap_or(p)))
+        .collect();
+
+    let mut flagged = 0;
+    for path in &files {
+        let relative = path.strip_prefix(&input).unwrap_or(path);
+        let img = match image::open(path) {
+            Ok(img) => img,
+            Err(e) => {
+                say!("  {} {} (skipped: {})", style("!").yellow(), relative.display(), e);
+                continue;
+            }
+        };
+
+        let confused = find_confused_colors(&img, audit.min_distance);
+        if confused.is_empty() {
+            continue;
+        }
+        flagged += 1;
+
+        say!("  {} {}", style("✗").red().bold(), style(relative.display()).bold());
+        let mut deficiencies: Vec<ColorDeficiency> = Vec::new();
+        for pair in &confused {
+            let simulated: Vec<String> = pair
+                .simulated
+                .iter()
+                .map(|(deficiency, distance)| format!("{} {:.1}", deficiency, distance))
+                .collect();
+            say!(
+                "      {} / {}  ΔE {:.1} → {}",
+                hex(pair.a),
+                hex(pair.b),
+                pair.distance,
+                simulated.join(", ")
+            );
+            for (deficiency, _) in &pair.simulated {
+                if !deficiencies.contains(deficiency) {
+                    deficiencies.push(*deficiency);
+                }
+            }
+        }
+
+        if let Some(previews) = &options.previews {
+            for deficiency in deficiencies {
+                let preview = previews
+                    .join(relative)
+                    .with_extension(format!("{}.png", deficiency));
+                write_preview(&img, deficiency, &preview)?;
+                say!("      {} {}", style("preview:").dim(), preview.display());
+            }
+        }
+    }
+
+    say!();
+    if flagged == 0 {
+        say!(
+            "{} {} textures checked, no confusable colors found",
+            style("✓").green().bold(),
+            files.len()
+        );
+    } else {
+        say!(
+            "{} {} of {} textures have colors that are hard to tell apart with a color-vision deficiency",
+            style("!").yellow().bold(),
+            flagged,
+            files.len()
+        );
+        say!("  Increase the lightness contrast between the listed colors, or add shape/icon cues.");
+    }
+
+    Ok(())
+}
+
+/// Report clicky loops, overlong sound effects and DC offset, grouped by folder
+pub fn audio(input: PathBuf, options: AudioAuditOptions) -> Result<()> {
+    let config = load_audit_config(options.config.as_deref())?;
+    let audit = &config.audit.audio;
+    let loops = audit.loop_matcher()?;
+    let sfx = audit.sfx_matcher()?;
+
+    say!("{} Audio audit: {}", style("🔊").blue().bold(), style(input.display()).cyan());
+    say!("  Loops: {} (max seam jump {})", audit.loops.join(", "), audit.loop_threshold);
+    say!("  Sound effects: {} (max {}s)", audit.sfx.join(", "), audit.sfx_max_secs);
+    say!("  Max DC offset: {}", audit.dc_offset_threshold);
+    say!();
+
+    let files: Vec<PathBuf> = WalkDir::new(&input)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.path().to_path_buf())
+        .filter(|p| AssetType::from_path(p) == AssetType::Audio)
+        .collect();
+
+    // Folder → (file, issue, suggested fix)
+    let mut issues: BTreeMap<PathBuf, Vec<(String, String, &str)>> = BTreeMap::new();
+    let mut skipped = 0;
+    for path in &files {
+        let relative = path.strip_prefix(&input).unwrap_or(path);
+        let name = relative
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let folder = issues
+            .entry(relative.parent().map(Path::to_path_buf).unwrap_or_default())
+            .or_default();
+
+        let analysis = match analyze_audio(path) {
+            Ok(analysis) => analysis,
+            Err(e) => {
+                say!("  {} {} (skipped: {:#})", style("!").yellow(), relative.display(), e);
+                skipped += 1;
+                continue;
+            }
+        };
+
+        if loops.is_match(relative) && analysis.loop_jump > audit.loop_threshold {
+            folder.push((
+                name.clone(),
+                format!("loop seam jumps by {:.3} (max {})", analysis.loop_jump, audit.loop_threshold),
+                "crossfade the end into the start, or cut both ends at zero crossings",
+            ));
+        }
+        if sfx.is_match(relative) && analysis.duration_secs() > audit.sfx_max_secs {
+            folder.push((
+                name.clone(),
+                format!("{:.2}s long (max {}s)", analysis.duration_secs(), audit.sfx_max_secs),
+                "trim the tail, or move it out of the sound effect folders if it is music/ambience",
+            ));
+        }
+        let (offset, channel) = analysis.dc_offset;
+        if offset.abs() > audit.dc_offset_threshold {
+            folder.push((
+                name,
+                format!("DC offset {:+.3} on channel {}", offset, channel),
+                "apply DC offset removal (or a ~20 Hz high-pass filter) in your editor",
+            ));
+        }
+    }
+    issues.retain(|_, folder| !folder.is_empty());
+
+    for (folder, folder_issues) in &issues {
+        let label = if folder.as_os_str().is_empty() {
+            ".".to_string()
+        } else {
+            folder.display().to_string()
+        };
+        say!("  {} {}/", style("✗").red().bold(), style(label).bold());
+        for (name, issue, fix) in folder_issues {
+            say!("      {}: {}", name, issue);
+            say!("        {} {}", style("fix:").dim(), fix);
+        }
+    }
+
+    let checked = files.len() - skipped;
+    let flagged: usize = issues.values().map(Vec::len).sum();
+    if !issues.is_empty() {
+        say!();
+    }
+    if flagged == 0 {
+        say!("{} {} clips checked, no issues found", style("✓").green().bold(), checked);
+    } else {
+        say!(
+            "{} {} issues in {} folders ({} clips checked)",
+            style("!").yellow().bold(),
+            flagged,
+            issues.len(),
+            checked
+        );
+    }
+
+    Ok(())
+}
+
+fn load_audit_config(path: Option<&Path>) -> Result<Config> {
+    let config = match path {
+        Some(path) => Some(load_config(path)?),
+        None => find_and_load_config()?,
+    };
+    Ok(config.unwrap_or_default())
+}
+
+fn write_preview(img: &image::DynamicImage, deficiency: ColorDeficiency, path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    simulate_deficiency(img, deficiency)
+        .save(path)
+        .with_context(|| format!("Failed to write preview: {}", path.display()))
+}
+
+fn hex(rgb: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2])
+}
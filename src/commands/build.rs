@@ -1,20 +1,70 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use console::style;
-use indicatif::{ProgressBar, ProgressStyle};
+use globset::GlobSet;
+use indicatif::ProgressStyle;
 use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use walkdir::WalkDir;
 
-use crate::cli::{BuildOptions, OutputFormat, PlatformPreset, QualityPreset};
-use crate::config::{find_and_load_config, load_config, Config, PresetConfig};
+use crate::cli::{
+    AstcBlockSize, BuildOptions, DitherPattern, HdrEncoding, MipFilter, NormalEncoding, OutputFormat, PlatformPreset, PoolOptions,
+    TextureColorSpace, TextureType, Tonemap, parse_byte_size, parse_duration_ms,
+};
+use crate::config::{
+    find_and_load_config, load_config, BundleConfig, Config, ConfigError, CustomProcessorConfig, PresetConfig, RuleConfig,
+    VirtualTextureConfig,
+};
 use crate::processors::{
-    process_image, process_audio, process_model,
-    AssetType, ImageProcessorConfig, AudioConfig, AudioFormat, ModelConfig,
-    BuildCache, hash_config,
+    process_image, process_image_variants, process_audio, process_model, process_collada, probe_audio_duration, manifest_path,
+    AssetType, DitherConfig, ImageProcessorConfig, AudioConfig, AudioFormat, WavSampleFormat, ModelConfig,
+    BuildCache, BuildManifest, ManifestEntry, MANIFEST_FILE_NAME, hash_config, markdown_report, html_report, BuildReport, find_duplicates,
+    render_model_thumbnail, load_expected_assets, output_hash, fingerprint_path, write_placeholder, generate_tiles, is_hdr_path, TileConfig,
+    write_bundle, verify_output, zstd_compress, hash_data, BundleIndex, ZSTD_COMPRESSION, DEFAULT_VARIANT_NAME, nine_slice_for, write_nine_slices,
+    gif_to_spritesheet, save_atlas_metadata, metadata_size, AtlasConfig, is_psd_path, is_tiff_path, process_psd_layers, QualityMetrics,
+    load_atlas_sprites, pack_atlas, frame_key, AtlasMetadata,
+};
+use crate::utils::{
+    advance, emit, hash_inputs, json_output, progress_bar, say, summary, Event, FileEvent, FileStatus,
 };
 
+/// Result of processing a single file
+pub(super) struct ProcessedFile {
+    pub(super) original_size: u64,
+    pub(super) output_size: u64,
+    /// Final output path (the extension may differ from the input)
+    pub(super) output: PathBuf,
+    /// Policy that overrode the preset output format, if any
+    pub(super) format_policy: Option<&'static str>,
+    /// Decoded audio length, used for encode throughput in --timings
+    pub(super) audio_secs: Option<f64>,
+    /// Texture colors were premultiplied by alpha
+    pub(super) premultiplied_alpha: bool,
+    /// Every resolution variant written (the first is `output`) with its scale, if the
+    /// rule asks for variants
+    pub(super) variants: Vec<(PathBuf, f32)>,
+    /// Every PSD layer or group exported (the first is `output`) with its path, if the rule
+    /// asks for `psd_layers`
+    pub(super) layers: Vec<(PathBuf, String)>,
+    /// PSNR and SSIM of each lossy texture output written
+    pub(super) quality: Vec<(PathBuf, QualityMetrics)>,
+    /// Bytes of source metadata left out of the output
+    pub(super) metadata_stripped: u64,
+    /// Spritesheet or atlas page images that the metadata `output` refers to
+    pub(super) pages: Vec<PathBuf>,
+}
+
+/// Per-file timing for --timings
+struct FileTiming {
+    path: PathBuf,
+    elapsed_secs: f64,
+    original_size: u64,
+    audio_secs: Option<f64>,
+}
+
 pub fn run(input: PathBuf, options: BuildOptions) -> Result<()> {
     if !input.exists() {
         anyhow::bail!("Input directory does not exist: {}", input.display());
@@ -23,6 +73,7 @@ pub fn run(input: PathBuf, options: BuildOptions) -> Result<()> {
     if !input.is_dir() {
         anyhow::bail!("Input path is not a directory: {}", input.display());
     }
+    let build_start = Instant::now();
 
     // Load configuration
     let config = if let Some(config_path) = &options.config {
@@ -39,56 +90,198 @@ pub fn run(input: PathBuf, options: BuildOptions) -> Result<()> {
         .unwrap_or_else(|| PathBuf::from("./build/assets"));
 
     // Get preset configuration
-    let preset_config = get_preset_config(&options.preset, &config);
+    let mut preset_config = get_preset_config(&options.preset, &config);
+    apply_profile(&mut preset_config, options.profile.as_deref(), &config)?;
 
-    println!(
+    say!(
         "{} Building assets from: {}",
         style("→").blue().bold(),
         input.display()
     );
-    println!("  Output directory: {}", style(output_dir.display()).cyan());
+    say!("  Output directory: {}", style(output_dir.display()).cyan());
 
     if let Some(preset) = &options.preset {
-        println!("  Platform preset: {}", style(preset).cyan());
+        say!("  Platform preset: {}", style(preset).cyan());
+    }
+    if let Some(profile) = &options.profile {
+        say!("  Profile: {}", style(profile).cyan());
     }
 
     if options.dry_run {
-        println!("  {}", style("(Dry run - no files will be processed)").yellow());
+        say!("  {}", style("(Dry run - no files will be processed)").yellow());
     }
 
-    println!();
+    say!();
 
-    // Collect all files to process
+    // Environment shared by every hook
+    let hook_env = vec![
+        ("ASSET_FORGE_INPUT_DIR", input.display().to_string()),
+        ("ASSET_FORGE_OUTPUT_DIR", output_dir.display().to_string()),
+        ("ASSET_FORGE_MANIFEST", output_dir.join(MANIFEST_FILE_NAME).display().to_string()),
+        ("ASSET_FORGE_PRESET", options.preset.map(|preset| preset.to_string()).unwrap_or_default()),
+        ("ASSET_FORGE_PROFILE", options.profile.clone().unwrap_or_default()),
+    ];
+    let hooks = config.as_ref().map(|c| c.hooks.clone()).unwrap_or_default();
+    // Before the scan, so a hook exporting sources from other tools gets them built
+    if let Some(command) = hooks.pre_build.as_deref().filter(|_| !options.dry_run) {
+        say!("{} Running pre_build hook: {}", style("→").blue().bold(), command);
+        run_hook("pre_build", command, &hook_env)?;
+        say!();
+    }
+
+    // Collect all files to process; custom processors take types asset-forge doesn't know
+    let has_custom_processor = |file: &Path| {
+        let relative = file.strip_prefix(&input).unwrap_or(file);
+        config.as_ref().is_some_and(|cfg| cfg.custom_processor(relative).is_ok_and(|p| p.is_some()))
+    };
     let files: Vec<PathBuf> = WalkDir::new(&input)
         .into_iter()
         .filter_map(|e| e.ok())
         .filter(|e| e.file_type().is_file())
         .map(|e| e.path().to_path_buf())
-        .filter(|p| AssetType::from_path(p) != AssetType::Unknown)
+        .filter(|p| AssetType::from_path(p) != AssetType::Unknown || has_custom_processor(p))
         .collect();
 
-    if files.is_empty() {
-        println!("{} No supported asset files found", style("!").yellow().bold());
+    let expected_assets = match &options.placeholders {
+        Some(path) => Some(load_expected_assets(path)?),
+        None => None,
+    };
+
+    if files.is_empty() && expected_assets.is_none() {
+        say!("{} No supported asset files found", style("!").yellow().bold());
         return Ok(());
     }
 
-    println!("Found {} asset files to process", style(files.len()).cyan());
+    say!("Found {} asset files to process", style(files.len()).cyan());
+
+    // Assets matching [priority] are built first so they land before the long tail
+    let priority = match &config {
+        Some(cfg) => cfg.priority.matcher()?,
+        None => GlobSet::empty(),
+    };
+    let (mut priority_files, mut rest_files): (Vec<&PathBuf>, Vec<&PathBuf>) = files
+        .iter()
+        .partition(|f| priority.is_match(f.strip_prefix(&input).unwrap_or(f)));
+    if !priority_files.is_empty() {
+        say!("  Priority files: {}", style(priority_files.len()).cyan());
+    }
+
+    // Images matching [virtual_texture] are sliced into tile pyramids instead
+    let virtual_texture = config
+        .as_ref()
+        .map(|cfg| cfg.virtual_texture.clone())
+        .unwrap_or_default();
+    let virtual_textures = virtual_texture.matcher()?;
+    let is_virtual_texture = |file: &Path| {
+        AssetType::from_path(file) == AssetType::Image
+            && virtual_textures.is_match(file.strip_prefix(&input).unwrap_or(file))
+    };
+    let virtual_texture_count = files.iter().filter(|f| is_virtual_texture(f)).count();
+    if virtual_texture_count > 0 {
+        say!("  Virtual textures: {}", style(virtual_texture_count).cyan());
+    }
+
+    // Images matching an `atlas = true` rule are packed into one atlas per directory instead
+    // of being built one by one
+    let mut atlas_groups: BTreeMap<PathBuf, Vec<(RuleConfig, PathBuf)>> = BTreeMap::new();
+    if let Some(cfg) = &config {
+        for file in files.iter().filter(|f| AssetType::from_path(f) == AssetType::Image && !is_virtual_texture(f)) {
+            let relative = file.strip_prefix(&input).unwrap_or(file);
+            if let Some(settings) = atlas_rule(cfg, file, relative) {
+                let dir = relative.parent().unwrap_or(Path::new("")).to_path_buf();
+                atlas_groups.entry(dir).or_default().push((settings, file.clone()));
+            }
+        }
+    }
+    let atlas_sprites: HashSet<&PathBuf> = atlas_groups.values().flatten().map(|(_, file)| file).collect();
+    if !atlas_groups.is_empty() {
+        say!(
+            "  Atlases: {} ({} sprites)",
+            style(atlas_groups.len()).cyan(),
+            atlas_sprites.len()
+        );
+        priority_files.retain(|f| !atlas_sprites.contains(f));
+        rest_files.retain(|f| !atlas_sprites.contains(f));
+    }
+
+    // Identical files under different paths; with --dedup each copy built with the same
+    // settings as an earlier one is aliased to it instead of built again
+    let duplicates = find_duplicates(&files)?;
+    let duplicate_count: usize = duplicates.iter().map(|group| group.len() - 1).sum();
+    let mut aliases: HashMap<&PathBuf, &PathBuf> = HashMap::new();
+    if options.dedup {
+        let settings_key = |file: &PathBuf| -> Option<(bool, u64)> {
+            if atlas_sprites.contains(file) {
+                return None;
+            }
+            let relative = file.strip_prefix(&input).unwrap_or(file);
+            let rule_hash = match &config {
+                Some(cfg) => hash_config(&cfg.resolve_rule(file, relative).ok()?.settings).ok()?,
+                None => 0,
+            };
+            Some((is_virtual_texture(file), rule_hash))
+        };
+        for group in &duplicates {
+            let mut kept: Vec<((bool, u64), &PathBuf)> = Vec::new();
+            for file in group {
+                let Some(key) = settings_key(file) else { continue };
+                match kept.iter().find(|(k, _)| *k == key) {
+                    Some((_, canonical)) => {
+                        aliases.insert(file, canonical);
+                    }
+                    None => kept.push((key, file)),
+                }
+            }
+        }
+        priority_files.retain(|f| !aliases.contains_key(f));
+        rest_files.retain(|f| !aliases.contains_key(f));
+    }
+    if duplicate_count > 0 {
+        say!("  Duplicate files: {}", style(duplicate_count).yellow());
+    }
 
     if options.dry_run {
-        for file in &files {
+        for file in priority_files.iter().chain(&rest_files) {
             let relative = file.strip_prefix(&input).unwrap_or(file);
-            let output_path = output_dir.join(relative);
-            println!(
+            let mut output_path = output_dir.join(relative);
+            if is_virtual_texture(file) {
+                output_path = output_path.with_extension("tiles");
+            }
+            say!(
                 "  {} → {}",
                 style(file.display()).dim(),
                 style(output_path.display()).green()
             );
         }
+        for (dir, sprites) in &atlas_groups {
+            say!(
+                "  {} ({} sprites) → {}",
+                style(input.join(dir).display()).dim(),
+                sprites.len(),
+                style(atlas_metadata_path(&output_dir, dir).display()).green()
+            );
+        }
+        for (duplicate, canonical) in &aliases {
+            say!(
+                "  {} → alias of {}",
+                style(duplicate.display()).dim(),
+                style(canonical.display()).green()
+            );
+        }
+        if let Some(expected) = &expected_assets {
+            let sources: HashSet<String> = files
+                .iter()
+                .map(|f| manifest_path(f.strip_prefix(&input).unwrap_or(f)))
+                .collect();
+            for source in expected.iter().filter(|s| !sources.contains(*s)) {
+                say!("  {} {} (missing)", style("placeholder →").yellow(), source);
+            }
+        }
         return Ok(());
     }
 
     // Create progress bar
-    let pb = ProgressBar::new(files.len() as u64);
+    let pb = progress_bar((files.len() - aliases.len() - atlas_sprites.len() + atlas_groups.len()) as u64);
     pb.set_style(
         ProgressStyle::default_bar()
             .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})")
@@ -101,12 +294,18 @@ pub fn run(input: PathBuf, options: BuildOptions) -> Result<()> {
     let total_output = Arc::new(AtomicU64::new(0));
     let processed_count = Arc::new(AtomicU64::new(0));
     let error_count = Arc::new(AtomicU64::new(0));
+    let metadata_stripped = Arc::new(AtomicU64::new(0));
 
     // Configure parallelism
-    let num_jobs = options.jobs.unwrap_or_else(num_cpus::get);
-    let pool = rayon::ThreadPoolBuilder::new()
-        .num_threads(num_jobs)
-        .build()?;
+    // Audio encoding is single-threaded per file and long tracks dominate build time,
+    // so audio runs on its own pool alongside the image/model pool
+    let (pool, audio_pool) = worker_pools(&options.pool)?;
+
+    let timings: Mutex<Vec<FileTiming>> = Mutex::new(Vec::new());
+    let structured_report = options.report_json.is_some() || options.report_html.is_some();
+    let collect_timings = options.timings || structured_report;
+    // Sources skipped because their cache entry was still valid, for the structured report
+    let cache_hits: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
 
     // Collect errors for later display
     let errors_list: Arc<Mutex<Vec<(PathBuf, String)>>> =
@@ -118,54 +317,360 @@ pub fn run(input: PathBuf, options: BuildOptions) -> Result<()> {
     let skipped_count = Arc::new(AtomicU64::new(0));
     let force_rebuild = options.force;
 
+    // Previous manifest is used to carry entries for cached files forward
+    let previous_manifest = BuildManifest::load(&output_dir).unwrap_or_default();
+    let manifest_entries: Arc<Mutex<Vec<ManifestEntry>>> = Arc::new(Mutex::new(Vec::new()));
+
     // Process files in parallel
     let errors_clone = errors_list.clone();
     let cache_clone = cache.clone();
     let skipped_clone = skipped_count.clone();
-    pool.install(|| {
-        files.par_iter().for_each(|file| {
-            let relative = file.strip_prefix(&input).unwrap_or(file);
-            let output_path = output_dir.join(relative);
+    let manifest_clone = manifest_entries.clone();
+    let gpu_format = preset_config.gpu_format.as_deref();
+    let output_zstd = preset_config.output_zstd()?;
 
-            // Check cache for incremental builds (skip if --force is used)
-            let config_hash = compute_config_hash(&preset_config);
-            let needs_rebuild = force_rebuild || cache_clone.lock().unwrap()
-                .needs_rebuild(file, config_hash)
-                .unwrap_or(true);
+    // --fail-fast and --max-errors stop starting new work once enough assets have failed
+    let max_errors = if options.fail_fast { Some(1) } else { options.max_errors };
+    let stopped = AtomicBool::new(false);
+    let count_error = || {
+        let failed = error_count.fetch_add(1, Ordering::Relaxed) + 1;
+        if max_errors.is_some_and(|max| failed >= max) {
+            stopped.store(true, Ordering::Relaxed);
+        }
+    };
 
-            if !needs_rebuild {
-                skipped_clone.fetch_add(1, Ordering::Relaxed);
-                pb.inc(1);
+    // Fingerprinting renames outputs, so switching it rebuilds everything under the new names
+    let mut preset_hash = compute_config_hash(&preset_config);
+    if options.fingerprint {
+        preset_hash = hash_inputs(&[&preset_hash.to_le_bytes(), b"fingerprint"]);
+    }
+
+    let process_one = |&file: &&PathBuf| {
+        if stopped.load(Ordering::Relaxed) {
+            return;
+        }
+        let relative = file.strip_prefix(&input).unwrap_or(file);
+        let output_path = output_dir.join(relative);
+        let source = manifest_path(relative);
+
+        // Rules, the sidecar and any custom processor matching this file
+        let resolved = config
+            .as_ref()
+            .map(|cfg| -> Result<_> { Ok((cfg.resolve_rule(file, relative)?, cfg.custom_processor(relative)?)) })
+            .transpose();
+        let (resolved, custom) = match resolved {
+            Ok(resolved) => resolved.unwrap_or_default(),
+            Err(e) => {
+                let error = e.to_string();
+                emit(&Event::File(FileEvent { error: Some(&error), ..FileEvent::new(&source, FileStatus::Failed) }));
+                errors_clone.lock().unwrap().push((file.clone(), error));
+                count_error();
+                advance(&pb, 1);
                 return;
             }
+        };
 
-            let result = process_file(file, &output_path, &preset_config);
+        // Check cache for incremental builds (skip if --force is used)
+        let tiled = is_virtual_texture(file);
+        let mut config_hash = preset_hash;
+        if !resolved.patterns.is_empty() || resolved.sidecar.is_some() {
+            let rule_hash = hash_config(&resolved.settings).unwrap_or(0);
+            config_hash = hash_inputs(&[&config_hash.to_le_bytes(), &rule_hash.to_le_bytes()]);
+        }
+        if tiled {
+            let tile_hash = hash_config(&virtual_texture).unwrap_or(0);
+            config_hash = hash_inputs(&[&config_hash.to_le_bytes(), &tile_hash.to_le_bytes()]);
+        }
+        if let Some((_, processor)) = custom {
+            let processor_hash = hash_config(processor).unwrap_or(0);
+            config_hash = hash_inputs(&[&config_hash.to_le_bytes(), &processor_hash.to_le_bytes()]);
+        }
+        let needs_rebuild = force_rebuild || cache_clone.lock().unwrap()
+            .needs_rebuild(file, config_hash)
+            .unwrap_or(true);
 
-            match result {
-                Ok(Some((orig, out))) => {
-                    total_original.fetch_add(orig, Ordering::Relaxed);
-                    total_output.fetch_add(out, Ordering::Relaxed);
-                    processed_count.fetch_add(1, Ordering::Relaxed);
-
-                    // Update cache
-                    let _ = cache_clone.lock().unwrap()
-                        .update(file, &output_path, config_hash);
+        if !needs_rebuild {
+            // Resolution variants leave several entries for one source
+            let mut entries: Vec<ManifestEntry> = previous_manifest
+                .assets
+                .iter()
+                .filter(|e| e.source == source)
+                .cloned()
+                .collect();
+            if entries.is_empty() {
+                let cache_guard = cache_clone.lock().unwrap();
+                if let Some(cached) = cache_guard.entries.get(file.as_path()) {
+                    entries.push(manifest_entry(&source, &cached.output_path, &output_dir, None, gpu_format));
+                }
+            }
+            for entry in &mut entries {
+                let output = output_dir.join(&entry.output);
+                entry.size = std::fs::metadata(&output).map(|m| m.len()).unwrap_or(entry.size);
+                entry.hash = output_hash(&output).ok().or(entry.hash.take());
+                entry.settings_hash = Some(format!("{:016x}", config_hash));
+                entry.settings = rule_settings(&resolved.settings);
+            }
+            manifest_clone.lock().unwrap().extend(entries);
+            emit(&Event::File(FileEvent::new(&source, FileStatus::Cached)));
+            cache_hits.lock().unwrap().insert(source);
+
+            skipped_clone.fetch_add(1, Ordering::Relaxed);
+            advance(&pb, 1);
+            return;
+        }
+
+        let file_start = Instant::now();
+        let result = build_asset(
+            file,
+            &output_path,
+            &preset_config,
+            &resolved.settings,
+            custom.map(|(_, processor)| processor),
+            tiled.then_some((&virtual_texture, &*cache_clone)),
+            options.timings,
+        );
+
+        match result {
+            Ok(Some(processed)) => {
+                // Before compression and bookkeeping; a failed hook fails the asset and leaves it
+                // out of the cache, so it runs again
+                if let Some(command) = &resolved.settings.post_process {
+                    let mut env = hook_env.clone();
+                    env.push(("ASSET_FORGE_SOURCE", file.display().to_string()));
+                    env.push(("ASSET_FORGE_OUTPUT", processed.output.display().to_string()));
+                    if let Err(e) = run_hook("post_process", command, &env) {
+                        let error = format!("{:#}", e);
+                        emit(&Event::File(FileEvent { error: Some(&error), ..FileEvent::new(&source, FileStatus::Failed) }));
+                        errors_clone.lock().unwrap().push((file.clone(), error));
+                        count_error();
+                        advance(&pb, 1);
+                        return;
+                    }
+                }
+
+                if collect_timings {
+                    timings.lock().unwrap().push(FileTiming {
+                        path: relative.to_path_buf(),
+                        elapsed_secs: file_start.elapsed().as_secs_f64(),
+                        original_size: processed.original_size,
+                        audio_secs: processed.audio_secs,
+                    });
+                }
+
+                let mut entry = manifest_entry(
+                    &source,
+                    &processed.output,
+                    &output_dir,
+                    processed.format_policy,
+                    gpu_format,
+                );
+                entry.premultiplied_alpha = processed.premultiplied_alpha;
+                entry.settings_hash = Some(format!("{:016x}", config_hash));
+                entry.settings = rule_settings(&resolved.settings);
+                let quality_of = |path: &Path| processed.quality.iter().find(|(p, _)| p == path).map(|(_, q)| *q);
+                entry.quality = quality_of(&processed.output);
+                let mut entries: Vec<ManifestEntry> = if !processed.layers.is_empty() {
+                    // One entry per exported layer, all for the same source
+                    processed.layers.iter().map(|(path, layer)| ManifestEntry {
+                        output: manifest_path(path.strip_prefix(&output_dir).unwrap_or(path)),
+                        size: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+                        hash: output_hash(path).ok(),
+                        layer: Some(layer.clone()),
+                        quality: quality_of(path),
+                        ..entry.clone()
+                    }).collect()
+                } else if processed.variants.is_empty() {
+                    vec![entry]
+                } else {
+                    // One entry per variant, all for the same source
+                    processed.variants.iter().map(|(path, scale)| ManifestEntry {
+                        output: manifest_path(path.strip_prefix(&output_dir).unwrap_or(path)),
+                        size: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+                        hash: output_hash(path).ok(),
+                        scale: Some(*scale),
+                        quality: quality_of(path),
+                        ..entry.clone()
+                    }).collect()
+                };
+                // Spritesheet pages after the metadata, which stays the entry `find` returns
+                let pages = page_entries(&entries[0], &processed.pages, &output_dir, gpu_format);
+                entries.extend(pages);
+
+                // The cache points at the compressed file when the main output was replaced
+                let mut cache_output = processed.output.clone();
+                if let Some((level, min_size)) = output_zstd {
+                    let main_output = manifest_path(processed.output.strip_prefix(&output_dir).unwrap_or(&processed.output));
+                    for entry in &mut entries {
+                        let original = entry.output.clone();
+                        if let Err(e) = compress_output(entry, &output_dir, level, min_size) {
+                            // Left out of the manifest and the cache, so the next build retries it
+                            let error = format!("{:#}", e);
+                            emit(&Event::File(FileEvent { error: Some(&error), ..FileEvent::new(&source, FileStatus::Failed) }));
+                            errors_clone.lock().unwrap().push((file.clone(), error));
+                            count_error();
+                            advance(&pb, 1);
+                            return;
+                        }
+                        if original == main_output {
+                            cache_output = output_dir.join(&entry.output);
+                        }
+                    }
+                }
+
+                total_original.fetch_add(processed.original_size, Ordering::Relaxed);
+                total_output.fetch_add(processed.output_size, Ordering::Relaxed);
+                metadata_stripped.fetch_add(processed.metadata_stripped, Ordering::Relaxed);
+                processed_count.fetch_add(1, Ordering::Relaxed);
+                let output = manifest_path(cache_output.strip_prefix(&output_dir).unwrap_or(&cache_output));
+                emit(&Event::File(FileEvent {
+                    output: Some(&output),
+                    input_size: Some(processed.original_size),
+                    output_size: Some(processed.output_size),
+                    ..FileEvent::new(&source, FileStatus::Built)
+                }));
+                manifest_clone.lock().unwrap().extend(entries);
+
+                // Update cache
+                let _ = cache_clone.lock().unwrap()
+                    .update(file, &cache_output, config_hash);
+            }
+            Ok(None) => {
+                // Skipped (e.g., unsupported type)
+            }
+            Err(e) => {
+                let error = e.to_string();
+                emit(&Event::File(FileEvent { error: Some(&error), ..FileEvent::new(&source, FileStatus::Failed) }));
+                errors_clone.lock().unwrap().push((file.clone(), error));
+                count_error();
+            }
+        }
+
+        advance(&pb, 1);
+    };
+
+    let process_atlas = |(dir, sprites): (&PathBuf, &Vec<(RuleConfig, PathBuf)>)| {
+        if stopped.load(Ordering::Relaxed) {
+            return;
+        }
+        let metadata_path = atlas_metadata_path(&output_dir, dir);
+        // Pages belong to the whole directory rather than one sprite
+        let group_source = if dir.as_os_str().is_empty() { ".".to_string() } else { manifest_path(dir) };
+        let sources: Vec<String> = sprites
+            .iter()
+            .map(|(_, file)| manifest_path(file.strip_prefix(&input).unwrap_or(file)))
+            .collect();
+
+        // Every sprite's hash covers the whole group, so adding, removing or changing the
+        // settings of any sprite repacks the atlas
+        let mut config_hash = preset_hash;
+        for ((settings, _), source) in sprites.iter().zip(&sources) {
+            let rule_hash = hash_config(settings).unwrap_or(0);
+            config_hash = hash_inputs(&[&config_hash.to_le_bytes(), &rule_hash.to_le_bytes(), source.as_bytes()]);
+        }
+        let needs_rebuild = force_rebuild || {
+            let cache_guard = cache_clone.lock().unwrap();
+            sprites
+                .iter()
+                .any(|(_, file)| cache_guard.needs_rebuild(file, config_hash).unwrap_or(true))
+        };
+
+        if !needs_rebuild {
+            let mut entries: Vec<ManifestEntry> = previous_manifest
+                .assets
+                .iter()
+                .filter(|e| sources.contains(&e.source) || (e.source == group_source && e.page.is_some()))
+                .cloned()
+                .collect();
+            for entry in &mut entries {
+                let output = output_dir.join(&entry.output);
+                entry.size = std::fs::metadata(&output).map(|m| m.len()).unwrap_or(entry.size);
+                entry.hash = output_hash(&output).ok().or(entry.hash.take());
+                entry.settings_hash = Some(format!("{:016x}", config_hash));
+            }
+            manifest_clone.lock().unwrap().extend(entries);
+            for source in &sources {
+                emit(&Event::File(FileEvent::new(source, FileStatus::Cached)));
+            }
+            cache_hits.lock().unwrap().extend(sources.into_iter().chain([group_source]));
+            skipped_clone.fetch_add(sprites.len() as u64, Ordering::Relaxed);
+            advance(&pb, 1);
+            return;
+        }
+
+        match process_atlas_group(&input.join(dir), sprites, &metadata_path, &preset_config) {
+            Ok((processed, keys)) => {
+                total_original.fetch_add(processed.original_size, Ordering::Relaxed);
+                total_output.fetch_add(processed.output_size, Ordering::Relaxed);
+                processed_count.fetch_add(sprites.len() as u64, Ordering::Relaxed);
+
+                // One entry per sprite, pointing at its frame in the atlas metadata, then one per page
+                let mut entries: Vec<ManifestEntry> = sprites
+                    .iter()
+                    .zip(&sources)
+                    .zip(keys)
+                    .map(|(((settings, _), source), key)| ManifestEntry {
+                        frame: Some(key),
+                        premultiplied_alpha: processed.premultiplied_alpha,
+                        settings_hash: Some(format!("{:016x}", config_hash)),
+                        settings: rule_settings(settings),
+                        ..manifest_entry(source, &processed.output, &output_dir, None, gpu_format)
+                    })
+                    .collect();
+                for entry in &entries {
+                    emit(&Event::File(FileEvent { output: Some(&entry.output), ..FileEvent::new(&entry.source, FileStatus::Built) }));
                 }
-                Ok(None) => {
-                    // Skipped (e.g., unsupported type)
+                let group = ManifestEntry {
+                    source: group_source,
+                    settings: serde_json::Map::new(),
+                    ..entries[0].clone()
+                };
+                entries.extend(page_entries(&group, &processed.pages, &output_dir, gpu_format));
+                manifest_clone.lock().unwrap().extend(entries);
+
+                let mut cache_guard = cache_clone.lock().unwrap();
+                for (_, file) in sprites {
+                    let _ = cache_guard.update(file, &processed.output, config_hash);
                 }
-                Err(e) => {
-                    errors_clone.lock().unwrap().push((file.clone(), e.to_string()));
-                    error_count.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(e) => {
+                let error = e.to_string();
+                for source in &sources {
+                    emit(&Event::File(FileEvent { error: Some(&error), ..FileEvent::new(source, FileStatus::Failed) }));
                 }
+                errors_clone.lock().unwrap().push((input.join(dir), error));
+                count_error();
             }
+        }
+        advance(&pb, 1);
+    };
 
-            pb.inc(1);
+    for phase in [priority_files, rest_files] {
+        let (audio_files, other_files) = split_audio_files(phase);
+        std::thread::scope(|scope| {
+            scope.spawn(|| audio_pool.install(|| audio_files.par_iter().for_each(process_one)));
+            pool.install(|| other_files.par_iter().for_each(process_one));
         });
-    });
+    }
+    pool.install(|| atlas_groups.par_iter().for_each(process_atlas));
 
     pb.finish_and_clear();
 
+    // A partial manifest would drop the assets that never ran, so a stopped build writes
+    // neither the manifest nor the cache
+    if stopped.load(Ordering::Relaxed) {
+        let error_list = errors_list.lock().unwrap();
+        say!();
+        say!("{} Build stopped", style("✗").red().bold());
+        say!("  Errors: {}", style(error_list.len()).red());
+        print_errors(&error_list);
+        anyhow::bail!("Build stopped after {} assets failed", error_list.len());
+    }
+
+    if options.fingerprint {
+        let mut entries = manifest_entries.lock().unwrap();
+        fingerprint_outputs(&mut entries, &output_dir, &mut cache.lock().unwrap())?;
+    }
+
     // Save cache
     {
         let mut cache_guard = cache.lock().unwrap();
@@ -173,39 +678,198 @@ pub fn run(input: PathBuf, options: BuildOptions) -> Result<()> {
         let _ = cache_guard.save(&cache_dir);
     }
 
+    // Write manifest
+    let mut manifest = BuildManifest::new();
+    manifest.preset = options.preset.map(|preset| preset.to_string());
+    manifest.assets = std::mem::take(&mut *manifest_entries.lock().unwrap());
+    let alias_entries: Vec<ManifestEntry> = aliases
+        .iter()
+        .flat_map(|(duplicate, canonical)| {
+            let source = manifest_path(duplicate.strip_prefix(&input).unwrap_or(duplicate));
+            let canonical = manifest_path(canonical.strip_prefix(&input).unwrap_or(canonical));
+            manifest
+                .assets
+                .iter()
+                .filter(|e| e.source == canonical)
+                .map(|e| ManifestEntry {
+                    source: source.clone(),
+                    alias_of: Some(canonical.clone()),
+                    ..e.clone()
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    manifest.assets.extend(alias_entries);
+    let placeholder_count = match &expected_assets {
+        Some(expected) => add_placeholders(expected, &mut manifest, &output_dir),
+        None => 0,
+    };
+    manifest.sort();
+    if let Err(e) = manifest.save(&output_dir) {
+        eprintln!("{} Failed to write manifest: {}", style("⚠").yellow(), e);
+    }
+    remove_superseded_fingerprints(&previous_manifest, &manifest, &output_dir);
+
+    // Re-open every output so broken encodes fail the build rather than the game; outputs
+    // with no check are listed rather than counted as verified
+    let mut verify_failures = Vec::new();
+    let mut unverified = Vec::new();
+    if options.verify_outputs {
+        let results: Vec<_> = pool.install(|| {
+            manifest
+                .assets
+                .par_iter()
+                .map(|entry| (entry.output.clone(), verify_output(&output_dir.join(&entry.output))))
+                .collect()
+        });
+        for (output, result) in results {
+            match result {
+                Ok(Some(_)) => {}
+                Ok(None) => unverified.push(output),
+                Err(e) => verify_failures.push((output, format!("{:#}", e))),
+            }
+        }
+    }
+
+    // Write bundle
+    let mut bundle_config = config.as_ref().map(|c| c.bundle.clone()).unwrap_or_default();
+    bundle_config.enabled |= options.bundle;
+    let bundle = if bundle_config.enabled {
+        let index = write_build_bundle(
+            &manifest,
+            &input,
+            &output_dir,
+            config.as_ref(),
+            &bundle_config,
+            preset_config.bundle_zstd_level,
+        )?;
+        Some((output_dir.join(&bundle_config.name), index))
+    } else {
+        None
+    };
+
+    // Write JSON and HTML reports
+    if structured_report {
+        let errors: Vec<(String, String)> = errors_list
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(path, error)| (manifest_path(path.strip_prefix(&input).unwrap_or(path)), error.clone()))
+            .collect();
+        let input_sizes: HashMap<String, u64> = manifest
+            .assets
+            .iter()
+            .filter(|e| !e.placeholder)
+            .map(|e| e.source.clone())
+            .chain(errors.iter().map(|(source, _)| source.clone()))
+            .map(|source| {
+                let size = std::fs::metadata(input.join(&source)).map(|m| m.len()).unwrap_or(0);
+                (source, size)
+            })
+            .collect();
+        let file_timings: HashMap<String, f64> = timings
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|t| (manifest_path(&t.path), t.elapsed_secs))
+            .collect();
+
+        let mut report = BuildReport::new(&manifest, &errors, &input_sizes, &file_timings, &cache_hits.lock().unwrap());
+        report.preset = manifest.preset.clone();
+        report.profile = options.profile.clone();
+        report.elapsed_secs = build_start.elapsed().as_secs_f64();
+
+        if let Some(report_path) = &options.report_json {
+            let json = serde_json::to_string_pretty(&report)?;
+            std::fs::write(report_path, json)
+                .with_context(|| format!("Failed to write report: {}", report_path.display()))?;
+        }
+        if let Some(report_path) = &options.report_html {
+            std::fs::write(report_path, html_report(&report))
+                .with_context(|| format!("Failed to write report: {}", report_path.display()))?;
+        }
+    }
+
+    // Write Markdown report
+    if let Some(report_path) = &options.report_markdown {
+        let baseline = match &options.report_baseline {
+            Some(path) => load_baseline_manifest(path)?,
+            None => previous_manifest,
+        };
+        let mut failed: Vec<(String, String)> = errors_list
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(path, error)| {
+                let relative = path.strip_prefix(&input).unwrap_or(path);
+                (manifest_path(relative), error.clone())
+            })
+            .collect();
+        failed.sort();
+
+        let report = markdown_report(&baseline, &manifest, &failed);
+        std::fs::write(report_path, report)
+            .with_context(|| format!("Failed to write report: {}", report_path.display()))?;
+    }
+
     // Print summary
     let processed = processed_count.load(Ordering::Relaxed);
     let errors = error_count.load(Ordering::Relaxed);
     let skipped = skipped_count.load(Ordering::Relaxed);
     let orig_size = total_original.load(Ordering::Relaxed);
     let out_size = total_output.load(Ordering::Relaxed);
+    summary("processed", processed);
+    summary("cached", skipped);
+    summary("failed", errors);
+    summary("placeholders", placeholder_count);
+    summary("input_size", orig_size);
+    summary("output_size", out_size);
+    summary("output_dir", &output_dir);
+    summary("elapsed_secs", build_start.elapsed().as_secs_f64());
 
-    println!();
-    println!("{} Build complete!", style("✓").green().bold());
-    println!("  Files processed: {}", style(processed).green());
+    say!();
+    say!("{} Build complete!", style("✓").green().bold());
+    say!("  Files processed: {}", style(processed).green());
     if skipped > 0 {
-        println!("  Files skipped (cached): {}", style(skipped).dim());
+        say!("  Files skipped (cached): {}", style(skipped).dim());
     }
-
-    if errors > 0 {
-        println!("  Errors: {}", style(errors).red());
-        let error_list = errors_list.lock().unwrap();
-        for (path, error) in error_list.iter().take(10) {
-            println!(
-                "    {} {}: {}",
-                style("✗").red(),
-                path.display(),
-                error
-            );
+    if placeholder_count > 0 {
+        say!("  Placeholders for missing assets: {}", style(placeholder_count).yellow());
+    }
+    if duplicate_count > 0 {
+        let wasted: u64 = duplicates
+            .iter()
+            .map(|group| std::fs::metadata(&group[0]).map(|m| m.len()).unwrap_or(0) * (group.len() as u64 - 1))
+            .sum();
+        say!(
+            "  Duplicates: {} files in {} groups ({})",
+            style(duplicate_count).yellow(),
+            duplicates.len(),
+            format_size(wasted)
+        );
+        if !aliases.is_empty() {
+            say!("  Aliased duplicates: {}", style(aliases.len()).green());
         }
-        if error_list.len() > 10 {
-            println!("    ... and {} more errors", error_list.len() - 10);
+        for group in duplicates.iter().take(10) {
+            let paths: Vec<String> = group
+                .iter()
+                .map(|f| manifest_path(f.strip_prefix(&input).unwrap_or(f)))
+                .collect();
+            say!("    {} {}", style("=").yellow(), paths.join(", "));
+        }
+        if duplicates.len() > 10 {
+            say!("    ... and {} more groups", duplicates.len() - 10);
         }
     }
 
+    if errors > 0 {
+        say!("  Errors: {}", style(errors).red());
+        print_errors(&errors_list.lock().unwrap());
+    }
+
     if orig_size > 0 {
         let reduction = (1.0 - out_size as f64 / orig_size as f64) * 100.0;
-        println!(
+        say!(
             "  Total size: {} → {} ({:.1}% reduction)",
             style(format_size(orig_size)).dim(),
             style(format_size(out_size)).green(),
@@ -213,16 +877,268 @@ pub fn run(input: PathBuf, options: BuildOptions) -> Result<()> {
         );
     }
 
-    println!("  Output: {}", style(output_dir.display()).cyan());
+    let stripped = metadata_stripped.load(Ordering::Relaxed);
+    if stripped > 0 {
+        say!("  Metadata stripped: {}", style(format_size(stripped)).green());
+    }
+
+    say!("  Output: {}", style(output_dir.display()).cyan());
+    let mut compressed_outputs: HashMap<&str, (u64, u64)> = HashMap::new();
+    for entry in &manifest.assets {
+        if let Some(raw_size) = entry.raw_size {
+            compressed_outputs.insert(&entry.output, (entry.size, raw_size));
+        }
+    }
+    if !compressed_outputs.is_empty() {
+        let (stored, raw) = compressed_outputs
+            .values()
+            .fold((0, 0), |(stored, raw), (size, raw_size)| (stored + size, raw + raw_size));
+        say!(
+            "  Zstandard outputs: {} files, {} → {}",
+            compressed_outputs.len(),
+            style(format_size(raw)).dim(),
+            style(format_size(stored)).green()
+        );
+    }
+    if let Some((path, index)) = &bundle {
+        summary("bundle", path);
+        let (stored, raw) = index.asset_sizes();
+        let compression = if stored < raw {
+            format!(", {} before Zstandard", format_size(raw))
+        } else {
+            String::new()
+        };
+        say!(
+            "  Bundle: {} ({} assets, {}{})",
+            style(path.display()).cyan(),
+            index.entries.len(),
+            format_size(index.size),
+            compression
+        );
+    }
+    for report_path in [&options.report_markdown, &options.report_json, &options.report_html].into_iter().flatten() {
+        say!("  Report: {}", style(report_path.display()).cyan());
+    }
+
+    if options.verify_outputs {
+        summary("verify_failures", verify_failures.iter().map(|(output, _)| output).collect::<Vec<_>>());
+        summary("verify_unchecked", &unverified);
+        if verify_failures.is_empty() {
+            say!("  Verified: {} outputs", style(manifest.assets.len() - unverified.len()).green());
+        } else {
+            say!("  Failed verification: {}", style(verify_failures.len()).red());
+            for (output, error) in &verify_failures {
+                say!("    {} {}: {}", style("✗").red(), output, error);
+            }
+        }
+        if !unverified.is_empty() {
+            say!("  Not verified (no check for the format): {}", style(unverified.len()).yellow());
+            for output in &unverified {
+                say!("    {} {}", style("-").dim(), output);
+            }
+        }
+    }
+
+    if options.timings {
+        print_timings(&mut timings.into_inner().unwrap());
+    }
 
+    if let Some(command) = &hooks.post_build {
+        say!();
+        if errors > 0 || !verify_failures.is_empty() {
+            say!("{} Skipped post_build hook: the build failed", style("!").yellow().bold());
+        } else {
+            let mut env = hook_env;
+            let bundle_path = bundle.as_ref().map(|(path, _)| path.display().to_string());
+            env.push(("ASSET_FORGE_BUNDLE", bundle_path.unwrap_or_default()));
+            env.push(("ASSET_FORGE_PROCESSED", processed.to_string()));
+            env.push(("ASSET_FORGE_CACHED", skipped.to_string()));
+            say!("{} Running post_build hook: {}", style("→").blue().bold(), command);
+            run_hook("post_build", command, &env)?;
+        }
+    }
+
+    if !verify_failures.is_empty() {
+        anyhow::bail!("{} outputs failed verification", verify_failures.len());
+    }
+    if errors > 0 {
+        anyhow::bail!("{} assets failed to build", errors);
+    }
+
+    Ok(())
+}
+
+/// Run a hook through the shell with `env` set, failing on a non-zero exit
+fn run_hook(name: &str, command: &str, env: &[(&str, String)]) -> Result<()> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = std::process::Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = std::process::Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(command).envs(env.iter().map(|(key, value)| (key, value)));
+    // stdout is reserved for events in JSON mode
+    if json_output() {
+        shell.stdout(std::io::stderr());
+    }
+
+    let status = shell
+        .status()
+        .with_context(|| format!("Failed to run {} hook: {}", name, command))?;
+    if !status.success() {
+        anyhow::bail!("{} hook failed ({}): {}", name, status, command);
+    }
     Ok(())
 }
 
+/// The first errors of a build, as listed in its summary
+fn print_errors(errors: &[(PathBuf, String)]) {
+    for (path, error) in errors.iter().take(10) {
+        say!(
+            "    {} {}: {}",
+            style("✗").red(),
+            path.display(),
+            error
+        );
+    }
+    if errors.len() > 10 {
+        say!("    ... and {} more errors", errors.len() - 10);
+    }
+}
+
+/// The image/model pool and the dedicated audio pool, sized from `--jobs` and `--audio-jobs`
+pub(crate) fn worker_pools(options: &PoolOptions) -> Result<(rayon::ThreadPool, rayon::ThreadPool)> {
+    let num_jobs = options.jobs.unwrap_or_else(num_cpus::get);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(num_jobs)
+        .build()?;
+    let audio_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(options.audio_jobs.unwrap_or(num_jobs).max(1))
+        .thread_name(|i| format!("audio-{}", i))
+        .build()?;
+    Ok((pool, audio_pool))
+}
+
+/// Split audio from other files, longest tracks first so they don't end up as the tail of the build
+fn split_audio_files(files: Vec<&PathBuf>) -> (Vec<&PathBuf>, Vec<&PathBuf>) {
+    let (mut audio_files, other_files): (Vec<&PathBuf>, Vec<&PathBuf>) = files
+        .into_iter()
+        .partition(|f| AssetType::from_path(f) == AssetType::Audio);
+    audio_files.sort_by_cached_key(|f| {
+        std::cmp::Reverse(std::fs::metadata(f).map(|m| m.len()).unwrap_or(0))
+    });
+    (audio_files, other_files)
+}
+
+/// Generate stand-ins for expected assets missing from the manifest, returning how many were added
+fn add_placeholders(expected: &[String], manifest: &mut BuildManifest, output_dir: &Path) -> usize {
+    let mut added = 0;
+    for source in expected {
+        if manifest.find(source).is_some() {
+            continue;
+        }
+        match write_placeholder(source, output_dir) {
+            Ok(Some(output)) => {
+                let mut entry = manifest_entry(source, &output, output_dir, None, None);
+                entry.placeholder = true;
+                manifest.assets.push(entry);
+                added += 1;
+            }
+            Ok(None) => {
+                eprintln!("{} No placeholder for unknown asset type: {}", style("⚠").yellow(), source);
+            }
+            Err(e) => {
+                eprintln!("{} {}", style("⚠").yellow(), e);
+            }
+        }
+    }
+    added
+}
+
+/// Pack the build output into a bundle, grouping assets by their rule tags
+fn write_build_bundle(
+    manifest: &BuildManifest,
+    input: &Path,
+    output_dir: &Path,
+    config: Option<&Config>,
+    bundle_config: &BundleConfig,
+    zstd_level: Option<i32>,
+) -> Result<BundleIndex> {
+    let mut tags = HashMap::new();
+    if let Some(config) = config {
+        for entry in &manifest.assets {
+            let relative = Path::new(&entry.source);
+            let rule = config.resolve_rule(&input.join(relative), relative)?;
+            if let Some(asset_tags) = rule.settings.tags {
+                tags.insert(entry.source.clone(), asset_tags);
+            }
+        }
+    }
+    write_bundle(manifest, output_dir, &tags, bundle_config, zstd_level).context("Failed to write bundle")
+}
+
+/// Load a baseline manifest file (e.g. from the main branch) for reports
+fn load_baseline_manifest(path: &Path) -> Result<BuildManifest> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read baseline manifest: {}", path.display()))?;
+    serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse baseline manifest: {}", path.display()))
+}
+
+/// Print per-file processing times, slowest first, with encode throughput
+fn print_timings(timings: &mut [FileTiming]) {
+    if timings.is_empty() {
+        return;
+    }
+
+    timings.sort_by(|a, b| b.elapsed_secs.total_cmp(&a.elapsed_secs));
+
+    say!();
+    say!("{} Timings:", style("⏱").blue().bold());
+    for timing in timings.iter() {
+        let secs = timing.elapsed_secs.max(1e-6);
+        let mut throughput = format!("{}/s", format_size((timing.original_size as f64 / secs) as u64));
+        if let Some(audio_secs) = timing.audio_secs {
+            throughput.push_str(&format!(", {:.1}x realtime", audio_secs / secs));
+        }
+        say!(
+            "  {:>8}  {}  {}",
+            format!("{:.2}s", timing.elapsed_secs),
+            timing.path.display(),
+            style(throughput).dim()
+        );
+    }
+}
+
+/// Build one source: with its custom processor if one matches, sliced into tiles if
+/// `virtual_texture` (the settings and the cache of tile hashes) is given, otherwise with the
+/// built-in processor for its type; shared by `build` and `watch`
+pub(super) fn build_asset(
+    input: &Path,
+    output: &Path,
+    preset: &PresetConfig,
+    rule: &RuleConfig,
+    custom: Option<&CustomProcessorConfig>,
+    virtual_texture: Option<(&VirtualTextureConfig, &Mutex<BuildCache>)>,
+    measure_audio: bool,
+) -> Result<Option<ProcessedFile>> {
+    match (custom, virtual_texture) {
+        (Some(processor), _) => run_custom_processor(processor, input, output),
+        (None, Some((virtual_texture, cache))) => process_virtual_texture(input, output, virtual_texture, preset, cache),
+        (None, None) => process_file(input, output, preset, rule, measure_audio),
+    }
+}
+
 fn process_file(
     input: &Path,
     output: &Path,
     preset: &PresetConfig,
-) -> Result<Option<(u64, u64)>> {
+    rule: &RuleConfig,
+    measure_audio: bool,
+) -> Result<Option<ProcessedFile>> {
     let asset_type = AssetType::from_path(input);
 
     match asset_type {
@@ -232,24 +1148,93 @@ fn process_file(
                 std::fs::create_dir_all(parent)?;
             }
 
-            let image_config = ImageProcessorConfig {
-                output_format: preset
-                    .texture_format
-                    .as_ref()
-                    .and_then(|f| match f.as_str() {
-                        "png" => Some(OutputFormat::Png),
-                        "jpeg" | "jpg" => Some(OutputFormat::Jpeg),
-                        "webp" => Some(OutputFormat::Webp),
-                        "ktx2" => Some(OutputFormat::Ktx2),
-                        _ => None,
-                    }),
-                quality: QualityPreset::Balanced,
-                max_size: preset.texture_max_size,
-                generate_mipmaps: preset.generate_mipmaps.unwrap_or(false),
+            // Animated GIFs become a spritesheet with frame timing instead of one still
+            if let Some(processed) = process_animated_gif(input, output, preset, rule)? {
+                return Ok(Some(processed));
+            }
+
+            let image_config = image_config(input, preset, rule)?;
+            let output_format = image_config.output_format;
+
+            // Adjust output extension based on format
+            let output = match output_format {
+                Some(format) => output.with_extension(format.to_string()),
+                None => output.to_path_buf(),
             };
 
-            let stats = process_image(input, output, &image_config)?;
-            Ok(Some((stats.original_size, stats.output_size)))
+            let premultiplied_alpha =
+                image_config.premultiply_alpha && image_config.texture_type == TextureType::Color;
+            let strips_metadata = image_config.strip_metadata
+                && output
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| matches!(e.to_lowercase().as_str(), "png" | "jpg" | "jpeg" | "webp"));
+            let metadata_stripped = if strips_metadata { metadata_size(input)? } else { 0 };
+
+            if let Some(patterns) = rule.psd_layers.as_ref().filter(|_| is_psd_path(input)) {
+                let written = process_psd_layers(input, &output, &image_config, patterns)?;
+                let Some((first, _, _)) = written.first() else {
+                    return Ok(None);
+                };
+                let quality = written.iter().filter_map(|(path, _, stats)| Some((path.clone(), stats.quality?))).collect();
+                return Ok(Some(ProcessedFile {
+                    original_size: std::fs::metadata(input)?.len(),
+                    output_size: written.iter().map(|(_, _, stats)| stats.output_size).sum(),
+                    output: first.clone(),
+                    format_policy: None,
+                    audio_secs: None,
+                    premultiplied_alpha,
+                    variants: Vec::new(),
+                    layers: written.into_iter().map(|(path, layer, _)| (path, layer)).collect(),
+                    quality,
+                    metadata_stripped,
+                    pages: Vec::new(),
+                }));
+            }
+
+            let (processed, outputs) = if let Some(scales) = &rule.variants {
+                let template = rule.variant_name.as_deref().unwrap_or(DEFAULT_VARIANT_NAME);
+                let written = process_image_variants(input, &output, &image_config, scales, template)?;
+                let Some((first, _, _)) = written.first() else {
+                    return Ok(None);
+                };
+                let outputs: Vec<(PathBuf, f32)> = written.iter().map(|(path, scale, _)| (path.clone(), *scale)).collect();
+                let processed = ProcessedFile {
+                    original_size: std::fs::metadata(input)?.len(),
+                    output_size: written.iter().map(|(_, _, stats)| stats.output_size).sum(),
+                    output: first.clone(),
+                    format_policy: None,
+                    audio_secs: None,
+                    premultiplied_alpha,
+                    variants: outputs.clone(),
+                    layers: Vec::new(),
+                    quality: written.iter().filter_map(|(path, _, stats)| Some((path.clone(), stats.quality?))).collect(),
+                    metadata_stripped,
+                    pages: Vec::new(),
+                };
+                (processed, outputs)
+            } else {
+                let stats = process_image(input, &output, &image_config)?;
+                let processed = ProcessedFile {
+                    original_size: stats.original_size,
+                    output_size: stats.output_size,
+                    output: output.clone(),
+                    format_policy: None,
+                    audio_secs: None,
+                    premultiplied_alpha,
+                    variants: Vec::new(),
+                    layers: Vec::new(),
+                    quality: stats.quality.map(|quality| (output.clone(), quality)).into_iter().collect(),
+                    metadata_stripped,
+                    pages: Vec::new(),
+                };
+                (processed, vec![(output, 1.0)])
+            };
+
+            if let Some(slice) = nine_slice_for(input, rule, None)? {
+                write_nine_slices(&slice, &outputs, image_config.max_size)?;
+            }
+            Ok(Some(processed))
         }
         AssetType::Audio => {
             // Process audio with configured format
@@ -257,36 +1242,26 @@ fn process_file(
                 std::fs::create_dir_all(parent)?;
             }
 
-            let output_format = preset
-                .audio_format
-                .as_ref()
-                .map(|f| match f.as_str() {
-                    "ogg" => AudioFormat::Ogg,
-                    "wav" => AudioFormat::Wav,
-                    _ => AudioFormat::Ogg,
-                })
-                .unwrap_or(AudioFormat::Ogg);
-
-            // Map audio quality (1-10 scale) to vorbis quality (0.0-1.0)
-            let quality = preset.audio_quality
-                .map(|q| q as f32 / 10.0)
-                .unwrap_or(0.5);
-
-            let audio_config = AudioConfig {
-                output_format,
-                quality,
-                sample_rate: None, // Keep original sample rate
-                normalize: false,
-            };
+            let (audio_config, format_policy, audio_secs) = audio_config(input, preset, rule, measure_audio)?;
+            let output_format = audio_config.output_format;
 
             // Adjust output extension based on format
-            let output = match output_format {
-                AudioFormat::Ogg => output.with_extension("ogg"),
-                AudioFormat::Wav => output.with_extension("wav"),
-            };
+            let output = output.with_extension(output_format.extension());
 
             let stats = process_audio(input, &output, &audio_config)?;
-            Ok(Some((stats.original_size, stats.output_size)))
+            Ok(Some(ProcessedFile {
+                original_size: stats.original_size,
+                output_size: stats.output_size,
+                output,
+                format_policy,
+                audio_secs,
+                premultiplied_alpha: false,
+                variants: Vec::new(),
+                layers: Vec::new(),
+                quality: Vec::new(),
+                metadata_stripped: 0,
+                pages: Vec::new(),
+            }))
         }
         AssetType::Model => {
             // Process glTF/GLB models
@@ -298,19 +1273,54 @@ fn process_file(
                 .and_then(|e| e.to_str())
                 .map(|e| e.to_lowercase());
 
-            // Only process glTF/GLB files, copy others
+            // Only process glTF/GLB (and COLLADA, converted to glTF) files, copy others
             match ext.as_deref() {
-                Some("gltf" | "glb") => {
+                Some(ext @ ("gltf" | "glb" | "dae")) => {
                     let model_config = ModelConfig::default();
                     let output = output.with_extension("glb");
-                    let stats = process_model(input, &output, &model_config)?;
-                    Ok(Some((stats.original_size, stats.output_size)))
+                    let is_collada = ext == "dae";
+                    let stats = if is_collada {
+                        process_collada(input, &output, &model_config)?
+                    } else {
+                        process_model(input, &output, &model_config)?
+                    };
+
+                    // Preview icons for asset browsers
+                    if let Some(size) = preset.model_thumbnail_size {
+                        let source = if is_collada { output.as_path() } else { input };
+                        render_model_thumbnail(source, &output.with_extension("thumb.png"), size)?;
+                    }
+                    Ok(Some(ProcessedFile {
+                        original_size: stats.original_size,
+                        output_size: stats.output_size,
+                        output,
+                        format_policy: None,
+                        audio_secs: None,
+                        premultiplied_alpha: false,
+                        variants: Vec::new(),
+                        layers: Vec::new(),
+                        quality: Vec::new(),
+                        metadata_stripped: 0,
+                        pages: Vec::new(),
+                    }))
                 }
                 _ => {
                     // Copy unsupported model formats as-is
                     std::fs::copy(input, output)?;
                     let size = std::fs::metadata(output)?.len();
-                    Ok(Some((size, size)))
+                    Ok(Some(ProcessedFile {
+                        original_size: size,
+                        output_size: size,
+                        output: output.to_path_buf(),
+                        format_policy: None,
+                        audio_secs: None,
+                        premultiplied_alpha: false,
+                        variants: Vec::new(),
+                        layers: Vec::new(),
+                        quality: Vec::new(),
+                        metadata_stripped: 0,
+                        pages: Vec::new(),
+                    }))
                 }
             }
         }
@@ -318,12 +1328,526 @@ fn process_file(
     }
 }
 
+/// Image settings for `input` from the preset and its rules
+fn image_config(input: &Path, preset: &PresetConfig, rule: &RuleConfig) -> Result<ImageProcessorConfig> {
+    let mut output_format = preset
+        .texture_format
+        .as_deref()
+        .and_then(OutputFormat::from_name);
+    let hdr_encoding = preset
+        .hdr_encoding
+        .as_deref()
+        .and_then(HdrEncoding::from_name)
+        .unwrap_or_default();
+    // HDR sources are never copied under their own extension
+    if is_hdr_path(input) {
+        output_format = hdr_encoding.output_format().or(output_format).or(Some(OutputFormat::Png));
+    }
+    // Nor are PSDs and TIFFs, which nothing can load at runtime
+    if is_psd_path(input) || is_tiff_path(input) {
+        output_format = output_format.or(Some(OutputFormat::Png));
+    }
+
+    let config = ImageProcessorConfig {
+        output_format,
+        quality: preset.encoder_quality(),
+        max_size: preset.texture_max_size,
+        generate_mipmaps: rule.generate_mipmaps.or(preset.generate_mipmaps).unwrap_or(false),
+        ktx2_zstd_level: preset.ktx2_zstd_level,
+        astc_block: preset.astc_block.as_deref().and_then(AstcBlockSize::from_name),
+        hdr_encoding,
+        tonemap: preset.tonemap.as_deref().and_then(Tonemap::from_name).unwrap_or_default(),
+        exposure: preset.exposure.unwrap_or(0.0),
+        texture_type: rule.texture_type.as_deref().and_then(TextureType::from_name).unwrap_or_default(),
+        renormalize: rule.renormalize.unwrap_or(false),
+        normal_encoding: preset
+            .normal_encoding
+            .as_deref()
+            .and_then(NormalEncoding::from_name)
+            .unwrap_or_default(),
+        quantize: rule.quantize.or(preset.png_quantize).unwrap_or(false),
+        dither: rule.dither.unwrap_or(false).then(|| DitherConfig {
+            pattern: rule.dither_pattern.as_deref().and_then(DitherPattern::from_name).unwrap_or_default(),
+            strength: rule.dither_strength.unwrap_or(1.0),
+        }),
+        premultiply_alpha: rule.premultiply_alpha.or(preset.premultiply_alpha).unwrap_or(false),
+        colorspace: rule.colorspace.as_deref().and_then(TextureColorSpace::from_name),
+        mip_filter: rule
+            .mip_filter
+            .as_ref()
+            .or(preset.mip_filter.as_ref())
+            .and_then(|name| MipFilter::from_name(name))
+            .unwrap_or_default(),
+        alpha_coverage: rule.alpha_coverage,
+        target_ssim: rule.target_ssim.or(preset.target_ssim),
+        max_output_size: rule
+            .max_output_size
+            .as_deref()
+            .map(parse_byte_size)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("max_output_size: {}", e))?,
+        strip_metadata: preset.strip_metadata.unwrap_or(false),
+        bit_depth: rule.bit_depth,
+    };
+    // Outputs keep the source's extension unless the preset picks a format
+    if let Some(target) = config.target_ssim.filter(|_| !config.searches_ssim(input)) {
+        tracing::warn!(
+            "{}: target_ssim {} is ignored, it only applies to JPEG and JPEG XL output",
+            input.display(),
+            target
+        );
+    }
+    Ok(config)
+}
+
+/// Audio settings for `input` from the preset and its rules, with the duration policy that
+/// picked the format and the clip length when it was measured
+///
+/// The clip is only measured when a duration policy applies or `measure_audio` asks for it.
+fn audio_config(
+    input: &Path,
+    preset: &PresetConfig,
+    rule: &RuleConfig,
+    measure_audio: bool,
+) -> Result<(AudioConfig, Option<&'static str>, Option<f64>)> {
+    // A rule's format wins over the preset and its duration policies
+    let rule_format = rule.format.as_deref().and_then(AudioFormat::from_name);
+    let mut output_format = rule_format
+        .or_else(|| preset.audio_format.as_deref().and_then(AudioFormat::from_name))
+        .unwrap_or(AudioFormat::Ogg);
+
+    let music = preset.music_policy()?.filter(|_| rule_format.is_none());
+    let policy = preset.short_sfx_policy().filter(|_| rule_format.is_none());
+    let audio_secs = if music.is_some() || policy.is_some() || measure_audio {
+        Some(probe_audio_duration(input)?)
+    } else {
+        None
+    };
+
+    // Long clips become streamed music and the rest sound effects; the shortest of
+    // those may be kept in a different format again
+    let mut format_policy = None;
+    if let (Some(music), Some(duration)) = (music, audio_secs) {
+        let (format, policy) = music.format_for(duration);
+        output_format = format;
+        format_policy = Some(policy);
+    }
+    if let (Some(policy), Some(duration)) = (policy, audio_secs) {
+        if policy.applies_to(duration) {
+            output_format = policy.format;
+            format_policy = Some("short_sfx");
+        }
+    }
+
+    // Map rule quality (0-100) or preset audio quality (1-10) to vorbis quality (0.0-1.0)
+    let quality = rule.quality
+        .map(|q| q as f32 / 100.0)
+        .or(preset.audio_quality.map(|q| q as f32 / 10.0))
+        .unwrap_or(0.5);
+
+    let config = AudioConfig {
+        output_format,
+        quality,
+        sample_rate: rule.sample_rate,
+        max_sample_rate: preset.audio_sample_rate_for(format_policy),
+        resample_quality: preset.encoder_quality(),
+        normalize: rule.normalize.unwrap_or(false),
+        channels: rule.channels,
+        loop_start: rule.loop_start,
+        loop_end: rule.loop_end,
+        wav_format: rule
+            .bit_depth
+            .or(preset.audio_bit_depth)
+            .map(WavSampleFormat::from_bits)
+            .transpose()?
+            .unwrap_or_default(),
+        adpcm_block_size: rule.adpcm_block_size.or(preset.audio_adpcm_block_size),
+        fade_in_ms: rule
+            .fade_in
+            .as_deref()
+            .map(parse_duration_ms)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("fade_in: {}", e))?
+            .unwrap_or(0),
+        fade_out_ms: rule
+            .fade_out
+            .as_deref()
+            .map(parse_duration_ms)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("fade_out: {}", e))?
+            .unwrap_or(0),
+        ..Default::default()
+    };
+
+    Ok((config, format_policy, audio_secs))
+}
+
+/// Spritesheet settings for animated GIFs and atlas folders (the preset's texture format, PNG
+/// by default)
+fn sheet_config(preset: &PresetConfig, rule: &RuleConfig) -> AtlasConfig {
+    let format = preset
+        .texture_format
+        .as_deref()
+        .and_then(OutputFormat::from_name)
+        .unwrap_or(OutputFormat::Png);
+    AtlasConfig {
+        format: Some(format),
+        quality: preset.encoder_quality(),
+        generate_mipmaps: rule.generate_mipmaps.or(preset.generate_mipmaps).unwrap_or(false),
+        ktx2_zstd_level: preset.ktx2_zstd_level,
+        astc_block: preset.astc_block.as_deref().and_then(AstcBlockSize::from_name),
+        trim: rule.trim.unwrap_or(false),
+        premultiply_alpha: rule.premultiply_alpha.or(preset.premultiply_alpha).unwrap_or(false),
+        ..Default::default()
+    }
+}
+
+/// Pack an animated GIF into a spritesheet (the preset's texture format, PNG by default) and
+/// `<output>.json` atlas metadata; `None` if the GIF has a single frame
+fn process_animated_gif(
+    input: &Path,
+    output: &Path,
+    preset: &PresetConfig,
+    rule: &RuleConfig,
+) -> Result<Option<ProcessedFile>> {
+    let config = sheet_config(preset, rule);
+    let format = config.format.unwrap_or(OutputFormat::Png);
+
+    let Some(result) = gif_to_spritesheet(input, &output.with_extension(format.to_string()), &config)? else {
+        return Ok(None);
+    };
+    let metadata = output.with_extension("json");
+    save_atlas_metadata(&result.metadata, &metadata)?;
+    let pages = page_paths(&result.metadata, &metadata);
+
+    Ok(Some(ProcessedFile {
+        original_size: result.stats.original_size,
+        output_size: result.stats.output_size + std::fs::metadata(&metadata)?.len(),
+        output: metadata,
+        format_policy: None,
+        audio_secs: None,
+        premultiplied_alpha: config.premultiply_alpha,
+        variants: Vec::new(),
+        layers: Vec::new(),
+        quality: Vec::new(),
+        metadata_stripped: 0,
+        pages,
+    }))
+}
+
+/// Rules of an image that an `atlas = true` rule packs into its directory's atlas, or `None`
+/// if it is built on its own; files whose rules fail to resolve report it when built that way
+pub(super) fn atlas_rule(config: &Config, file: &Path, relative: &Path) -> Option<RuleConfig> {
+    let resolved = config.resolve_rule(file, relative).ok()?;
+    (resolved.settings.atlas == Some(true)).then_some(resolved.settings)
+}
+
+/// Atlas metadata for the sprites of an input directory: `<dir>.json` beside the directory's
+/// outputs, or `atlas.json` for sprites at the top level
+pub(super) fn atlas_metadata_path(output_dir: &Path, dir: &Path) -> PathBuf {
+    match dir.file_name() {
+        Some(name) => output_dir.join(dir).with_file_name(format!("{}.json", name.to_string_lossy())),
+        None => output_dir.join("atlas.json"),
+    }
+}
+
+/// Page images of atlas metadata written to `metadata`, which names them relative to itself
+fn page_paths(atlas: &AtlasMetadata, metadata: &Path) -> Vec<PathBuf> {
+    let dir = metadata.parent().unwrap_or(Path::new(""));
+    if atlas.pages.is_empty() {
+        vec![dir.join(&atlas.image)]
+    } else {
+        atlas.pages.iter().map(|page| dir.join(&page.image)).collect()
+    }
+}
+
+/// Manifest entries of the pages that the atlas metadata `entry` refers to, built with its settings
+fn page_entries(entry: &ManifestEntry, pages: &[PathBuf], output_dir: &Path, gpu_format: Option<&str>) -> Vec<ManifestEntry> {
+    pages
+        .iter()
+        .enumerate()
+        .map(|(index, path)| ManifestEntry {
+            page: Some(index as u32),
+            premultiplied_alpha: entry.premultiplied_alpha,
+            settings_hash: entry.settings_hash.clone(),
+            settings: entry.settings.clone(),
+            ..manifest_entry(&entry.source, path, output_dir, None, gpu_format)
+        })
+        .collect()
+}
+
+/// Pack a directory's atlas sprites into pages (the preset's texture format, PNG by default)
+/// and `metadata` beside them; returns the frame key of each sprite too
+///
+/// Atlas-wide settings (`trim`, `premultiply_alpha`) come from the first sprite's rules.
+pub(super) fn process_atlas_group(
+    dir: &Path,
+    sprites: &[(RuleConfig, PathBuf)],
+    metadata: &Path,
+    preset: &PresetConfig,
+) -> Result<(ProcessedFile, Vec<String>)> {
+    let config = sheet_config(preset, &sprites[0].0);
+    let format = config.format.unwrap_or(OutputFormat::Png);
+
+    let (loaded, original_size) = load_atlas_sprites(dir, sprites, &config)?;
+    let result = pack_atlas(&loaded, &metadata.with_extension(format.to_string()), &config)?;
+    save_atlas_metadata(&result.metadata, metadata)?;
+
+    let keys = sprites.iter().map(|(_, path)| frame_key(dir, path, &config)).collect();
+    let processed = ProcessedFile {
+        original_size,
+        output_size: result.stats.output_size + std::fs::metadata(metadata)?.len(),
+        output: metadata.to_path_buf(),
+        format_policy: None,
+        audio_secs: None,
+        premultiplied_alpha: config.premultiply_alpha,
+        variants: Vec::new(),
+        layers: Vec::new(),
+        quality: Vec::new(),
+        metadata_stripped: 0,
+        pages: page_paths(&result.metadata, metadata),
+    };
+    Ok((processed, keys))
+}
+
+/// Build a file with an external command from `[processors.custom]`
+fn run_custom_processor(processor: &CustomProcessorConfig, input: &Path, output: &Path) -> Result<Option<ProcessedFile>> {
+    let output = match &processor.extension {
+        Some(extension) => output.with_extension(extension.trim_start_matches('.')),
+        None => output.to_path_buf(),
+    };
+    let output_dir = output.parent().unwrap_or(Path::new("."));
+    std::fs::create_dir_all(output_dir)?;
+
+    // Placeholders are replaced after splitting, so paths with spaces stay one argument
+    let name = input.file_stem().unwrap_or_default().to_string_lossy();
+    let args: Vec<String> = processor
+        .cmd
+        .split_whitespace()
+        .map(|arg| {
+            arg.replace("{input}", &input.to_string_lossy())
+                .replace("{output_dir}", &output_dir.to_string_lossy())
+                .replace("{output}", &output.to_string_lossy())
+                .replace("{name}", &name)
+        })
+        .collect();
+    let (program, args) = args.split_first().context("Custom processor has an empty cmd")?;
+
+    let result = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to run custom processor: {}", program))?;
+    if !result.status.success() {
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        match stderr.trim() {
+            "" => anyhow::bail!("{} failed ({})", program, result.status),
+            stderr => anyhow::bail!("{} failed ({}): {}", program, result.status, stderr),
+        }
+    }
+    let output_size = std::fs::metadata(&output)
+        .with_context(|| format!("{} did not write {}", program, output.display()))?
+        .len();
+
+    Ok(Some(ProcessedFile {
+        original_size: std::fs::metadata(input)?.len(),
+        output_size,
+        output,
+        format_policy: None,
+        audio_secs: None,
+        premultiplied_alpha: false,
+        variants: Vec::new(),
+        layers: Vec::new(),
+        quality: Vec::new(),
+        metadata_stripped: 0,
+        pages: Vec::new(),
+    }))
+}
+
+/// Slice a texture into `<output>.tiles/`, skipping tiles the cache already has
+fn process_virtual_texture(
+    input: &Path,
+    output: &Path,
+    virtual_texture: &VirtualTextureConfig,
+    preset: &PresetConfig,
+    cache: &Mutex<BuildCache>,
+) -> Result<Option<ProcessedFile>> {
+    let output_format = virtual_texture
+        .format
+        .as_deref()
+        .or(preset.texture_format.as_deref())
+        .and_then(OutputFormat::from_name)
+        .unwrap_or_default();
+
+    let config = TileConfig {
+        tile_size: virtual_texture.tile_size,
+        border: virtual_texture.border,
+        image: ImageProcessorConfig {
+            output_format: Some(output_format),
+            quality: preset.encoder_quality(),
+            ktx2_zstd_level: preset.ktx2_zstd_level,
+            astc_block: preset.astc_block.as_deref().and_then(AstcBlockSize::from_name),
+            ..Default::default()
+        },
+    };
+
+    let known = cache.lock().unwrap().tiles.clone();
+    let result = generate_tiles(input, &output.with_extension("tiles"), &config, &known)?;
+    cache.lock().unwrap().tiles.extend(result.hashes);
+
+    Ok(Some(ProcessedFile {
+        original_size: std::fs::metadata(input)?.len(),
+        output_size: result.output_size,
+        output: result.index_path,
+        format_policy: None,
+        audio_secs: None,
+        premultiplied_alpha: false,
+        variants: Vec::new(),
+        layers: Vec::new(),
+        quality: Vec::new(),
+        metadata_stripped: 0,
+        pages: Vec::new(),
+    }))
+}
+
+fn manifest_entry(
+    source: &str,
+    output: &Path,
+    output_dir: &Path,
+    format_policy: Option<&str>,
+    gpu_format: Option<&str>,
+) -> ManifestEntry {
+    let relative = output.strip_prefix(output_dir).unwrap_or(output);
+    let size = std::fs::metadata(output).map(|m| m.len()).unwrap_or(0);
+    let format = output
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    // The transcode target only applies to KTX2 textures
+    let gpu_format = gpu_format.filter(|_| format == "ktx2").map(str::to_string);
+
+    let asset_type = [Path::new(source), output]
+        .into_iter()
+        .find_map(|path| AssetType::from_path(path).manifest_name())
+        .map(str::to_string);
+
+    ManifestEntry {
+        source: source.to_string(),
+        output: manifest_path(relative),
+        unhashed_output: None,
+        format,
+        asset_type,
+        size,
+        compression: None,
+        raw_size: None,
+        hash: output_hash(output).ok(),
+        format_policy: format_policy.map(str::to_string),
+        streaming: format_policy == Some("music"),
+        gpu_format,
+        premultiplied_alpha: false,
+        placeholder: false,
+        scale: None,
+        layer: None,
+        frame: None,
+        page: None,
+        quality: None,
+        alias_of: None,
+        settings_hash: None,
+        settings: serde_json::Map::new(),
+    }
+}
+
+/// Replace an output of at least `min_size` bytes with `<output>.zst` when that saves enough
+///
+/// The entry keeps its `format`; `size` and `hash` become the compressed file's.
+fn compress_output(entry: &mut ManifestEntry, output_dir: &Path, level: i32, min_size: u64) -> Result<()> {
+    // Pages keep the name their metadata refers to
+    if entry.compression.is_some() || entry.page.is_some() || entry.size < min_size {
+        return Ok(());
+    }
+    let path = output_dir.join(&entry.output);
+    let raw = std::fs::read(&path).with_context(|| format!("Failed to read output: {}", path.display()))?;
+    let Some(compressed) = zstd_compress(&raw, level)? else {
+        return Ok(());
+    };
+
+    let output = format!("{}.zst", entry.output);
+    let compressed_path = output_dir.join(&output);
+    std::fs::write(&compressed_path, &compressed)
+        .with_context(|| format!("Failed to write {}", compressed_path.display()))?;
+    std::fs::remove_file(&path)?;
+
+    entry.output = output;
+    entry.compression = Some(ZSTD_COMPRESSION.to_string());
+    entry.raw_size = Some(raw.len() as u64);
+    entry.size = compressed.len() as u64;
+    entry.hash = Some(format!("{:016x}", hash_data(&compressed)));
+    Ok(())
+}
+
+/// Rename outputs to include their content hash and point the cache at the renamed files
+///
+/// Entries carried over from a fingerprinted build already have their hashed name and are
+/// left alone. Entries sharing one output (atlas sprites) rename it once, and pages keep the
+/// name their metadata refers to.
+fn fingerprint_outputs(entries: &mut [ManifestEntry], output_dir: &Path, cache: &mut BuildCache) -> Result<()> {
+    let mut renamed: HashMap<String, String> = HashMap::new();
+    for entry in entries.iter_mut().filter(|entry| entry.page.is_none()) {
+        let Some(hash) = entry.hash.as_deref() else { continue };
+        let unhashed = entry.unhashed_output.clone().unwrap_or_else(|| entry.output.clone());
+        let fingerprinted = fingerprint_path(&unhashed, hash);
+        if entry.output != fingerprinted && !renamed.contains_key(&entry.output) {
+            std::fs::rename(output_dir.join(&entry.output), output_dir.join(&fingerprinted))
+                .with_context(|| format!("Failed to fingerprint output: {}", entry.output))?;
+            renamed.insert(entry.output.clone(), fingerprinted.clone());
+        }
+        entry.output = fingerprinted;
+        entry.unhashed_output = Some(unhashed);
+    }
+
+    for cached in cache.entries.values_mut() {
+        let relative = cached.output_path.strip_prefix(output_dir).unwrap_or(&cached.output_path);
+        if let Some(fingerprinted) = renamed.get(&manifest_path(relative)) {
+            cached.output_path = output_dir.join(fingerprinted);
+        }
+    }
+    Ok(())
+}
+
+/// Delete fingerprinted outputs of the previous build that a rebuilt source replaced
+///
+/// Outputs of sources that were removed or failed this time are kept, like unhashed ones.
+fn remove_superseded_fingerprints(previous: &BuildManifest, manifest: &BuildManifest, output_dir: &Path) {
+    let sources: HashSet<&str> = manifest.assets.iter().map(|e| e.source.as_str()).collect();
+    let outputs: HashSet<&str> = manifest.assets.iter().map(|e| e.output.as_str()).collect();
+    for entry in &previous.assets {
+        if entry.unhashed_output.is_some()
+            && sources.contains(entry.source.as_str())
+            && !outputs.contains(entry.output.as_str())
+        {
+            let _ = std::fs::remove_file(output_dir.join(&entry.output));
+        }
+    }
+}
+
+/// Rule and sidecar fields that were set, for the manifest `settings` record
+fn rule_settings(settings: &RuleConfig) -> serde_json::Map<String, serde_json::Value> {
+    match serde_json::to_value(settings) {
+        Ok(serde_json::Value::Object(mut fields)) => {
+            fields.retain(|_, value| !value.is_null());
+            fields
+        }
+        _ => serde_json::Map::new(),
+    }
+}
+
 /// Compute a hash of the preset configuration for cache invalidation
 fn compute_config_hash(preset: &PresetConfig) -> u64 {
     hash_config(preset).unwrap_or(0)
 }
 
-fn get_preset_config(preset: &Option<PlatformPreset>, config: &Option<Config>) -> PresetConfig {
+pub(super) fn get_preset_config(preset: &Option<PlatformPreset>, config: &Option<Config>) -> PresetConfig {
     if let Some(preset_name) = preset {
         if let Some(cfg) = config {
             let name = preset_name.to_string();
@@ -332,41 +1856,28 @@ fn get_preset_config(preset: &Option<PlatformPreset>, config: &Option<Config>) -
             }
         }
 
-        // Default presets
-        match preset_name {
-            PlatformPreset::Mobile => PresetConfig {
-                texture_max_size: Some(1024),
-                texture_format: Some("png".to_string()), // Use PNG for now, KTX2 in Phase 2
-                texture_quality: Some(75),
-                audio_format: Some("ogg".to_string()),
-                audio_quality: Some(6),
-                compress_textures: Some(true),
-                generate_mipmaps: Some(true),
-            },
-            PlatformPreset::Desktop => PresetConfig {
-                texture_max_size: Some(4096),
-                texture_format: Some("png".to_string()),
-                texture_quality: Some(90),
-                audio_format: Some("wav".to_string()),
-                audio_quality: Some(10),
-                compress_textures: Some(false),
-                generate_mipmaps: Some(true),
-            },
-            PlatformPreset::Web => PresetConfig {
-                texture_max_size: Some(2048),
-                texture_format: Some("webp".to_string()),
-                texture_quality: Some(80),
-                audio_format: Some("ogg".to_string()),
-                audio_quality: Some(7),
-                compress_textures: Some(true),
-                generate_mipmaps: Some(false),
-            },
-        }
+        PresetConfig::builtin(*preset_name)
     } else {
         PresetConfig::default()
     }
 }
 
+/// Layer the `--profile` named `name` over the preset; config profiles replace built-in ones
+pub(super) fn apply_profile(preset: &mut PresetConfig, name: Option<&str>, config: &Option<Config>) -> Result<()> {
+    if let Some(name) = name {
+        let profile = config
+            .as_ref()
+            .and_then(|cfg| cfg.profiles.get(name).cloned())
+            .or_else(|| PresetConfig::builtin_profile(name))
+            .with_context(|| {
+                ConfigError::Invalid(format!("Unknown build profile: {} (define it under [profiles.{}])", name, name))
+            })?;
+        preset.merge(&profile);
+    }
+    preset.apply_skip_basis();
+    Ok(())
+}
+
 fn format_size(bytes: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = 1024 * 1024;
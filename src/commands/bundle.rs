@@ -0,0 +1,128 @@
+use anyhow::{Context, Result};
+use console::style;
+use std::path::{Path, PathBuf};
+
+use crate::processors::{hash_data, Bundle, BundleEntry};
+use crate::utils::{format_size, say};
+
+pub fn ls(bundle: PathBuf) -> Result<()> {
+    let bundle = open(&bundle)?;
+    let index = &bundle.index;
+
+    say!(
+        "{} {} ({} assets, {}, {}-byte alignment)",
+        style("→").blue().bold(),
+        index.bundle,
+        index.entries.len(),
+        format_size(index.size),
+        index.alignment
+    );
+
+    if !index.groups.is_empty() {
+        say!();
+        say!("  Prefetch groups:");
+        for group in &index.groups {
+            let count = index
+                .entries
+                .iter()
+                .filter(|e| e.group.as_deref() == Some(group.name.as_str()))
+                .count();
+            say!(
+                "    {} {:>10} +{:<10} {} assets, {}",
+                style(&group.name).cyan(),
+                group.offset,
+                group.length,
+                count,
+                format_size(group.length)
+            );
+        }
+    }
+
+    say!();
+    say!("  {:>10} {:>10}  {:<10} Asset", "Offset", "Length", "Group");
+    for entry in &index.entries {
+        let raw = match (&entry.compression, entry.raw_length) {
+            (Some(compression), Some(raw_length)) => format!(" ({}, {} raw)", compression, format_size(raw_length)),
+            _ => String::new(),
+        };
+        say!(
+            "  {:>10} {:>10}  {:<10} {}{}",
+            entry.offset,
+            entry.length,
+            entry.group.as_deref().unwrap_or("-"),
+            entry.output,
+            style(raw).dim()
+        );
+    }
+
+    Ok(())
+}
+
+pub fn extract(bundle_path: PathBuf, assets: Vec<String>, output: PathBuf) -> Result<()> {
+    let mut bundle = open(&bundle_path)?;
+
+    let entries: Vec<BundleEntry> = if assets.is_empty() {
+        bundle.index.entries.clone()
+    } else {
+        assets
+            .iter()
+            .map(|asset| {
+                bundle
+                    .index
+                    .find(asset)
+                    .cloned()
+                    .with_context(|| format!("Asset not in bundle: {}", asset))
+            })
+            .collect::<Result<_>>()?
+    };
+
+    say!(
+        "{} Extracting {} assets from: {}",
+        style("→").blue().bold(),
+        style(entries.len()).cyan(),
+        bundle_path.display()
+    );
+
+    let mut total = 0;
+    for entry in &entries {
+        let data = bundle
+            .read(&entry.output)?
+            .with_context(|| format!("Asset not in bundle: {}", entry.output))?;
+
+        // Catches a bundle rewritten or truncated after its index
+        if let Some(expected) = &entry.hash {
+            let actual = format!("{:016x}", hash_data(&data));
+            if &actual != expected {
+                anyhow::bail!(
+                    "Hash mismatch for {} (expected {}, got {})",
+                    entry.output,
+                    expected,
+                    actual
+                );
+            }
+        }
+
+        let path = output.join(&entry.output);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, &data)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        total += data.len() as u64;
+        say!("  {} {}", style("✓").green(), entry.output);
+    }
+
+    say!();
+    say!(
+        "{} Extracted {} to {}",
+        style("✓").green().bold(),
+        format_size(total),
+        style(output.display()).cyan()
+    );
+
+    Ok(())
+}
+
+fn open(path: &Path) -> Result<Bundle> {
+    Bundle::open(path).with_context(|| format!("Failed to open bundle: {}", path.display()))
+}
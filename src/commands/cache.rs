@@ -0,0 +1,165 @@
+use anyhow::Result;
+use console::style;
+use std::path::{Path, PathBuf};
+
+use crate::cli::CacheOptions;
+use crate::config::{find_and_load_config, load_config};
+use crate::processors::{BuildCache, CacheIssue, CacheUpgrade};
+use crate::utils::{format_size, say};
+
+pub fn stats(options: CacheOptions) -> Result<()> {
+    let cache_dir = cache_dir(&options)?;
+    let Some(cache) = open(&cache_dir)? else {
+        return Ok(());
+    };
+    let stats = cache.stats();
+    let file_size = std::fs::metadata(cache_dir.join("cache.json")).map(|m| m.len()).unwrap_or(0);
+
+    say!(
+        "{} Build cache: {} (version {}, {})",
+        style("→").blue().bold(),
+        cache_dir.display(),
+        cache.version,
+        format_size(file_size)
+    );
+    say!();
+    say!("  Entries: {}", style(stats.total_entries).cyan());
+    say!("    With output: {}", stats.valid_entries);
+    if stats.stale_entries > 0 {
+        say!("    Output missing: {}", style(stats.stale_entries).yellow());
+    }
+    if stats.missing_inputs > 0 {
+        say!("    Input missing: {}", style(stats.missing_inputs).yellow());
+    }
+    if stats.tiles > 0 {
+        say!("  Virtual texture tiles: {}", stats.tiles);
+    }
+    if let (Some(oldest), Some(newest)) = (stats.oldest, stats.newest) {
+        say!("  Oldest entry: {}", format_age(oldest));
+        say!("  Newest entry: {}", format_age(newest));
+    }
+    if stats.stale_entries > 0 || stats.missing_inputs > 0 {
+        say!();
+        say!("  Run {} to drop stale entries", style("asset-forge cache prune").cyan());
+    }
+
+    Ok(())
+}
+
+pub fn verify(options: CacheOptions) -> Result<()> {
+    let cache_dir = cache_dir(&options)?;
+    let Some(cache) = open(&cache_dir)? else {
+        return Ok(());
+    };
+
+    say!(
+        "{} Verifying {} cache entries in {}",
+        style("→").blue().bold(),
+        style(cache.entries.len()).cyan(),
+        cache_dir.display()
+    );
+
+    let issues = cache.verify();
+    for (path, issue) in &issues {
+        let marker = match issue {
+            CacheIssue::InputChanged => style("~").dim(),
+            CacheIssue::MissingInput | CacheIssue::MissingOutput | CacheIssue::OutputModified => style("✗").red(),
+        };
+        say!("  {} {}: {}", marker, path.display(), issue);
+    }
+
+    let stale = issues.iter().filter(|(_, issue)| *issue != CacheIssue::InputChanged).count();
+    let changed = issues.len() - stale;
+    say!();
+    if changed > 0 {
+        say!("  Rebuilt on the next build: {}", changed);
+    }
+    if stale > 0 {
+        anyhow::bail!("{} stale cache entries (run `asset-forge cache prune`)", stale);
+    }
+    say!("{} Cache is consistent", style("✓").green().bold());
+
+    Ok(())
+}
+
+pub fn prune(options: CacheOptions, dry_run: bool) -> Result<()> {
+    let cache_dir = cache_dir(&options)?;
+    let Some(mut cache) = open(&cache_dir)? else {
+        return Ok(());
+    };
+
+    if dry_run {
+        for (path, issue) in cache.verify() {
+            if issue != CacheIssue::InputChanged {
+                say!("  {} {}: {}", style("-").dim(), path.display(), issue);
+            }
+        }
+    }
+
+    let (entries, tiles) = cache.prune();
+    if dry_run {
+        say!(
+            "{} Would remove {} entries and {} tiles (dry run)",
+            style("→").blue().bold(),
+            entries,
+            tiles
+        );
+        return Ok(());
+    }
+
+    cache.save(&cache_dir)?;
+    say!(
+        "{} Removed {} entries and {} tiles; {} entries kept",
+        style("✓").green().bold(),
+        entries,
+        tiles,
+        cache.entries.len()
+    );
+
+    Ok(())
+}
+
+/// `.cache` in the output directory, found the same way as `build` does
+fn cache_dir(options: &CacheOptions) -> Result<PathBuf> {
+    let config = match &options.config {
+        Some(path) => Some(load_config(path)?),
+        None => find_and_load_config()?,
+    };
+    let output_dir = options
+        .output
+        .clone()
+        .or_else(|| config.as_ref().map(|c| c.project.output.clone()))
+        .unwrap_or_else(|| PathBuf::from("./build/assets"));
+    Ok(output_dir.join(".cache"))
+}
+
+/// Load the cache, or `None` with a note when there is none yet
+///
+/// Unlike `build`, which quietly starts over, a cache from an unsupported version is an error.
+fn open(cache_dir: &Path) -> Result<Option<BuildCache>> {
+    match BuildCache::check_upgrade(cache_dir)? {
+        CacheUpgrade::Missing => {
+            say!("{} No build cache in {}", style("-").dim(), cache_dir.display());
+            Ok(None)
+        }
+        CacheUpgrade::Unsupported(version) => anyhow::bail!(
+            "Cache version {} is not supported; the next build starts a new cache",
+            version
+        ),
+        CacheUpgrade::Upgradable(cache, _) => Ok(Some(cache)),
+    }
+}
+
+fn format_age(timestamp: u64) -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let secs = now.saturating_sub(timestamp);
+    match secs {
+        0..=59 => format!("{}s ago", secs),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
@@ -3,6 +3,7 @@ use console::style;
 use std::path::PathBuf;
 
 use crate::config::find_and_load_config;
+use crate::utils::say;
 
 pub fn run(cache_dir: Option<PathBuf>, all: bool) -> Result<()> {
     // Try to load config to find default directories
@@ -21,20 +22,20 @@ pub fn run(cache_dir: Option<PathBuf>, all: bool) -> Result<()> {
         .map(|c| c.project.output.clone())
         .unwrap_or_else(|| PathBuf::from("./build"));
 
-    println!("{} Cleaning build artifacts", style("🧹").blue().bold());
+    say!("{} Cleaning build artifacts", style("🧹").blue().bold());
 
     // Clean cache directory
     if cache_path.exists() {
         let cache_size = dir_size(&cache_path).unwrap_or(0);
         std::fs::remove_dir_all(&cache_path)?;
-        println!(
+        say!(
             "  {} Removed cache: {} ({})",
             style("✓").green(),
             cache_path.display(),
             format_size(cache_size)
         );
     } else {
-        println!(
+        say!(
             "  {} Cache not found: {}",
             style("-").dim(),
             cache_path.display()
@@ -46,14 +47,14 @@ pub fn run(cache_dir: Option<PathBuf>, all: bool) -> Result<()> {
         if output_path.exists() {
             let output_size = dir_size(&output_path).unwrap_or(0);
             std::fs::remove_dir_all(&output_path)?;
-            println!(
+            say!(
                 "  {} Removed output: {} ({})",
                 style("✓").green(),
                 output_path.display(),
                 format_size(output_size)
             );
         } else {
-            println!(
+            say!(
                 "  {} Output not found: {}",
                 style("-").dim(),
                 output_path.display()
@@ -71,7 +72,7 @@ pub fn run(cache_dir: Option<PathBuf>, all: bool) -> Result<()> {
         if path.exists() && path != &cache_path {
             let size = dir_size(path).unwrap_or(0);
             std::fs::remove_dir_all(path)?;
-            println!(
+            say!(
                 "  {} Removed: {} ({})",
                 style("✓").green(),
                 path.display(),
@@ -80,8 +81,8 @@ pub fn run(cache_dir: Option<PathBuf>, all: bool) -> Result<()> {
         }
     }
 
-    println!();
-    println!("{} Clean complete!", style("✓").green().bold());
+    say!();
+    say!("{} Clean complete!", style("✓").green().bold());
 
     Ok(())
 }
@@ -3,7 +3,12 @@ use console::style;
 use image::GenericImageView;
 use std::path::PathBuf;
 
-use crate::processors::{AssetType, get_model_info, get_audio_info, detect_model_format};
+use super::audio::print_levels;
+use crate::processors::{
+    AssetType, get_model_info, get_audio_info, detect_model_format, icc_color_space, icc_description,
+    image_icc_profile, open_image,
+};
+use crate::utils::say;
 
 pub fn run(input: PathBuf) -> Result<()> {
     if !input.exists() {
@@ -13,18 +18,18 @@ pub fn run(input: PathBuf) -> Result<()> {
     let asset_type = AssetType::from_path(&input);
     let file_size = std::fs::metadata(&input)?.len();
 
-    println!("{} Asset Information", style("📋").blue().bold());
-    println!("  File: {}", style(input.display()).cyan());
-    println!("  Size: {}", format_size(file_size));
-    println!("  Type: {}", style(format!("{:?}", asset_type)).cyan());
-    println!();
+    say!("{} Asset Information", style("📋").blue().bold());
+    say!("  File: {}", style(input.display()).cyan());
+    say!("  Size: {}", format_size(file_size));
+    say!("  Type: {}", style(format!("{:?}", asset_type)).cyan());
+    say!();
 
     match asset_type {
         AssetType::Image => print_image_info(&input)?,
         AssetType::Model => print_model_info(&input)?,
         AssetType::Audio => print_audio_info(&input)?,
         AssetType::Unknown => {
-            println!("  {}", style("Unknown or unsupported file type").yellow());
+            say!("  {}", style("Unknown or unsupported file type").yellow());
         }
     }
 
@@ -32,14 +37,23 @@ pub fn run(input: PathBuf) -> Result<()> {
 }
 
 fn print_image_info(input: &PathBuf) -> Result<()> {
-    let img = image::open(input)?;
+    let img = open_image(input)?;
     let (width, height) = img.dimensions();
     let color_type = img.color();
 
-    println!("  {}", style("Image Properties:").bold());
-    println!("    Dimensions: {}x{}", width, height);
-    println!("    Color type: {:?}", color_type);
-    println!("    Pixels: {}", width * height);
+    say!("  {}", style("Image Properties:").bold());
+    say!("    Dimensions: {}x{}", width, height);
+    say!("    Color type: {:?}", color_type);
+    match image_icc_profile(input)? {
+        Some(icc) => say!(
+            "    ICC profile: {} ({}, {} bytes)",
+            icc_description(&icc).unwrap_or_else(|| "unnamed".to_string()),
+            icc_color_space(&icc).map(|s| String::from_utf8_lossy(s).trim().to_string()).unwrap_or_default(),
+            icc.len()
+        ),
+        None => say!("    ICC profile: none (treated as sRGB)"),
+    }
+    say!("    Pixels: {}", width * height);
 
     // Estimate uncompressed size
     let bytes_per_pixel = match color_type {
@@ -56,20 +70,20 @@ fn print_image_info(input: &PathBuf) -> Result<()> {
         _ => 4,
     };
     let uncompressed = width as u64 * height as u64 * bytes_per_pixel;
-    println!("    Uncompressed: {}", format_size(uncompressed));
+    say!("    Uncompressed: {}", format_size(uncompressed));
 
     // Format detection
     let ext = input.extension()
         .and_then(|e| e.to_str())
         .map(|e| e.to_uppercase())
         .unwrap_or_else(|| "Unknown".to_string());
-    println!("    Format: {}", ext);
+    say!("    Format: {}", ext);
 
     // Compression ratio
     let file_size = std::fs::metadata(input)?.len();
     if uncompressed > 0 {
         let ratio = file_size as f64 / uncompressed as f64;
-        println!("    Compression: {:.1}%", ratio * 100.0);
+        say!("    Compression: {:.1}%", ratio * 100.0);
     }
 
     Ok(())
@@ -78,9 +92,9 @@ fn print_image_info(input: &PathBuf) -> Result<()> {
 fn print_model_info(input: &PathBuf) -> Result<()> {
     let format = detect_model_format(input);
 
-    println!("  {}", style("Model Properties:").bold());
+    say!("  {}", style("Model Properties:").bold());
     if let Some(fmt) = format {
-        println!("    Format: {}", fmt);
+        say!("    Format: {}", fmt);
     }
 
     // Only process glTF/GLB
@@ -91,17 +105,17 @@ fn print_model_info(input: &PathBuf) -> Result<()> {
     match ext.as_deref() {
         Some("gltf" | "glb") => {
             let info = get_model_info(input)?;
-            println!("    Meshes: {}", info.meshes);
-            println!("    Vertices: {}", info.total_vertices);
-            println!("    Indices: {}", info.total_indices);
-            println!("    Triangles: ~{}", info.total_indices / 3);
-            println!("    Materials: {}", info.materials);
-            println!("    Textures: {}", info.textures);
-            println!("    Animations: {}", info.animations);
-            println!("    Nodes: {}", info.nodes);
+            say!("    Meshes: {}", info.meshes);
+            say!("    Vertices: {}", info.total_vertices);
+            say!("    Indices: {}", info.total_indices);
+            say!("    Triangles: ~{}", info.total_indices / 3);
+            say!("    Materials: {}", info.materials);
+            say!("    Textures: {}", info.textures);
+            say!("    Animations: {}", info.animations);
+            say!("    Nodes: {}", info.nodes);
         }
         _ => {
-            println!("    {}", style("Detailed info not available for this format").dim());
+            say!("    {}", style("Detailed info not available for this format").dim());
         }
     }
 
@@ -111,19 +125,22 @@ fn print_model_info(input: &PathBuf) -> Result<()> {
 fn print_audio_info(input: &PathBuf) -> Result<()> {
     let info = get_audio_info(input)?;
 
-    println!("  {}", style("Audio Properties:").bold());
-    println!("    Format: {}", info.format);
-    println!("    Channels: {}", info.channels);
-    println!("    Sample rate: {} Hz", info.sample_rate);
-    println!("    Duration: {:.2}s", info.duration_secs);
+    say!("  {}", style("Audio Properties:").bold());
+    say!("    Format: {}", info.format);
+    say!("    Channels: {}", info.layout);
+    say!("    Sample rate: {} Hz", info.sample_rate);
+    say!("    Duration: {:.2}s", info.duration_secs);
 
     // Bitrate estimate
     let file_size = std::fs::metadata(input)?.len();
     if info.duration_secs > 0.0 {
         let bitrate = (file_size as f64 * 8.0) / info.duration_secs / 1000.0;
-        println!("    Bitrate: ~{:.0} kbps", bitrate);
+        say!("    Bitrate: ~{:.0} kbps", bitrate);
     }
 
+    say!();
+    print_levels(&info.levels);
+
     Ok(())
 }
 
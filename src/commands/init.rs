@@ -3,6 +3,7 @@ use console::style;
 use std::path::Path;
 
 use crate::config::Config;
+use crate::utils::say;
 
 const CONFIG_FILE_NAME: &str = "asset-forge.toml";
 
@@ -10,12 +11,12 @@ pub fn run(force: bool) -> Result<()> {
     let config_path = Path::new(CONFIG_FILE_NAME);
 
     if config_path.exists() && !force {
-        println!(
+        say!(
             "{} Configuration file already exists: {}",
             style("!").yellow().bold(),
             config_path.display()
         );
-        println!("  Use {} to overwrite.", style("--force").cyan());
+        say!("  Use {} to overwrite.", style("--force").cyan());
         return Ok(());
     }
 
@@ -25,23 +26,23 @@ pub fn run(force: bool) -> Result<()> {
     std::fs::write(config_path, &content)
         .with_context(|| format!("Failed to write config file: {}", config_path.display()))?;
 
-    println!(
+    say!(
         "{} Created configuration file: {}",
         style("✓").green().bold(),
         style(config_path.display()).cyan()
     );
 
-    println!();
-    println!("Next steps:");
-    println!(
+    say!();
+    say!("Next steps:");
+    say!(
         "  1. Edit {} to configure your project",
         style(CONFIG_FILE_NAME).cyan()
     );
-    println!(
+    say!(
         "  2. Run {} to process your assets",
         style("asset-forge build ./assets").cyan()
     );
-    println!(
+    say!(
         "  3. Run {} for help",
         style("asset-forge --help").cyan()
     );
@@ -0,0 +1,173 @@
+use anyhow::{Context, Result};
+use console::style;
+use std::path::{Path, PathBuf};
+
+use crate::cli::MigrateOptions;
+use crate::config::{find_config_path, migrate_config, Config};
+use crate::processors::{BuildCache, BuildManifest, CacheUpgrade, MANIFEST_FILE_NAME, MANIFEST_VERSION};
+use crate::utils::say;
+
+pub fn run(options: MigrateOptions) -> Result<()> {
+    let config_path = match &options.config {
+        Some(path) => Some(path.clone()),
+        None => find_config_path()?,
+    };
+
+    if options.dry_run {
+        say!("{} Migration preview (dry run)", style("→").blue().bold());
+    } else {
+        say!("{} Migrating project files", style("→").blue().bold());
+    }
+    say!();
+
+    let mut pending = 0;
+    let mut config = None;
+    match &config_path {
+        Some(path) => {
+            let (changes, migrated) = migrate_config_file(path, options.dry_run)?;
+            pending += changes;
+            config = Some(migrated);
+        }
+        None => say!("  {} Config: no asset-forge.toml found", style("-").dim()),
+    }
+
+    let output_dir = options
+        .output
+        .clone()
+        .or_else(|| config.as_ref().map(|c| c.project.output.clone()))
+        .unwrap_or_else(|| PathBuf::from("./build/assets"));
+    pending += migrate_manifest(&output_dir, options.dry_run)?;
+    pending += migrate_cache(&output_dir.join(".cache"), options.dry_run)?;
+
+    say!();
+    if pending == 0 {
+        say!("{} Everything is up to date", style("✓").green().bold());
+    } else if options.dry_run {
+        say!(
+            "{} {} changes pending; run without --dry-run to apply them",
+            style("!").yellow().bold(),
+            pending
+        );
+    } else {
+        say!("{} Migration complete! ({} changes)", style("✓").green().bold(), pending);
+    }
+
+    Ok(())
+}
+
+/// Rewrite deprecated config keys, keeping a `.bak` copy of the original
+fn migrate_config_file(path: &Path, dry_run: bool) -> Result<(usize, Config)> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    let (migrated, changes) = migrate_config(&content)
+        .with_context(|| format!("Failed to migrate config file: {}", path.display()))?;
+    let config: Config = toml::from_str(&migrated)
+        .with_context(|| format!("Migrated config does not parse: {}", path.display()))?;
+
+    if changes.is_empty() {
+        say!("  {} Config: {} is up to date", style("✓").green(), path.display());
+        return Ok((0, config));
+    }
+
+    say!("  {} Config: {}", style("~").yellow(), path.display());
+    for change in &changes {
+        if change.dropped {
+            say!(
+                "      {} {} (already set as {}; {})",
+                style("remove").red(),
+                change.from,
+                change.to,
+                change.reason
+            );
+        } else {
+            say!("      {} → {} ({})", change.from, style(&change.to).green(), change.reason);
+        }
+    }
+
+    if !dry_run {
+        let backup = path.with_extension("toml.bak");
+        std::fs::write(&backup, &content)
+            .with_context(|| format!("Failed to write backup: {}", backup.display()))?;
+        std::fs::write(path, &migrated)
+            .with_context(|| format!("Failed to write config file: {}", path.display()))?;
+        say!("      {} {}", style("backup:").dim(), backup.display());
+    }
+
+    Ok((changes.len(), config))
+}
+
+/// Fill in the sizes and hashes that manifests from older builds lack
+fn migrate_manifest(output_dir: &Path, dry_run: bool) -> Result<usize> {
+    let path = output_dir.join(MANIFEST_FILE_NAME);
+    if !path.exists() {
+        say!("  {} Manifest: none in {}", style("-").dim(), output_dir.display());
+        return Ok(0);
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read manifest: {}", path.display()))?;
+    let mut manifest: BuildManifest = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse manifest: {}", path.display()))?;
+    if manifest.version != MANIFEST_VERSION {
+        say!(
+            "  {} Manifest: version {} can't be upgraded; the next build writes a new one",
+            style("!").yellow(),
+            manifest.version
+        );
+        return Ok(0);
+    }
+
+    let filled = manifest.fill_missing_hashes(output_dir)?;
+    if filled.is_empty() {
+        say!("  {} Manifest: {} is up to date", style("✓").green(), path.display());
+        return Ok(0);
+    }
+
+    say!(
+        "  {} Manifest: record size and hash of {} outputs",
+        style("~").yellow(),
+        filled.len()
+    );
+    for output in filled.iter().take(10) {
+        say!("      {}", output);
+    }
+    if filled.len() > 10 {
+        say!("      ... and {} more", filled.len() - 10);
+    }
+
+    if !dry_run {
+        manifest.save(output_dir)?;
+    }
+    Ok(filled.len())
+}
+
+fn migrate_cache(cache_dir: &Path, dry_run: bool) -> Result<usize> {
+    match BuildCache::check_upgrade(cache_dir)? {
+        CacheUpgrade::Missing => {
+            say!("  {} Cache: none in {}", style("-").dim(), cache_dir.display());
+            Ok(0)
+        }
+        CacheUpgrade::Unsupported(version) => {
+            say!(
+                "  {} Cache: version {} can't be upgraded; the next build starts a new cache",
+                style("!").yellow(),
+                version
+            );
+            Ok(0)
+        }
+        CacheUpgrade::Upgradable(_, changes) if changes.is_empty() => {
+            say!("  {} Cache: {} is up to date", style("✓").green(), cache_dir.display());
+            Ok(0)
+        }
+        CacheUpgrade::Upgradable(cache, changes) => {
+            say!("  {} Cache: {}", style("~").yellow(), cache_dir.display());
+            for change in &changes {
+                say!("      {}", change);
+            }
+            if !dry_run {
+                cache.save(cache_dir)?;
+            }
+            Ok(changes.len())
+        }
+    }
+}
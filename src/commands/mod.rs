@@ -2,8 +2,19 @@ pub mod init;
 pub mod optimize;
 pub mod build;
 pub mod atlas;
+pub mod tiles;
+pub mod array;
 pub mod watch;
 pub mod model;
 pub mod audio;
+pub mod audiosprite;
 pub mod info;
+pub mod rules;
+pub mod pack;
+pub mod audit;
+pub mod restore;
+pub mod bundle;
+pub mod migrate;
+pub mod mv;
+pub mod cache;
 pub mod clean;
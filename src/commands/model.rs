@@ -1,13 +1,15 @@
 use anyhow::Result;
 use console::style;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 
 use crate::cli::ModelOptions;
 use crate::processors::{
     get_model_info, process_model, estimate_lod_levels,
-    ModelConfig, detect_model_format,
+    ModelConfig, ModelFormat, PrimitiveInfo, detect_model_format, generate_collision_mesh, load_collada,
+    render_model_thumbnail,
 };
+use crate::utils::{emit_output, say};
 
 pub fn run(input: PathBuf, options: ModelOptions) -> Result<()> {
     if !input.exists() {
@@ -20,38 +22,60 @@ pub fn run(input: PathBuf, options: ModelOptions) -> Result<()> {
 
     // Check if it's a supported format
     match format {
-        crate::processors::ModelFormat::GlTF | crate::processors::ModelFormat::GLB => {}
+        ModelFormat::GlTF | ModelFormat::GLB => run_gltf(&input, &input, format, options),
+        ModelFormat::Collada => {
+            // Convert to glTF first and work from the converted file
+            let converted = std::env::temp_dir()
+                .join(format!("asset-forge-{}.glb", std::process::id()));
+            load_collada(&input)?.write(&converted)?;
+            let result = run_gltf(&input, &converted, format, options);
+            let _ = std::fs::remove_file(&converted);
+            result
+        }
         _ => {
             anyhow::bail!(
-                "Only glTF/GLB formats are supported for optimization. Found: {}",
+                "Only glTF/GLB and COLLADA formats are supported for optimization. Found: {}",
                 format
             );
         }
     }
+}
+
+/// Process a glTF model; `source` is the file the user passed, `input` the glTF to read
+fn run_gltf(source: &Path, input: &Path, format: ModelFormat, options: ModelOptions) -> Result<()> {
+    // Preview thumbnail
+    if let Some(thumbnail) = &options.thumbnail {
+        render_model_thumbnail(input, thumbnail, options.thumbnail_size)?;
+        say!(
+            "{} Thumbnail: {}",
+            style("✓").green().bold(),
+            style(thumbnail.display()).cyan()
+        );
+    }
 
     // Info-only mode
     if options.info {
-        return print_model_info(&input);
+        return print_model_info(source, input, format);
     }
 
     // Determine output path
     let output = options.output.unwrap_or_else(|| {
-        let stem = input.file_stem().unwrap_or_default();
+        let stem = source.file_stem().unwrap_or_default();
         let default_dir = PathBuf::from(".");
-        let parent = input.parent().unwrap_or(&default_dir);
+        let parent = source.parent().unwrap_or(&default_dir);
         parent.join(format!("{}_optimized.glb", stem.to_string_lossy()))
     });
 
-    println!(
+    say!(
         "{} Processing model: {}",
         style("→").blue().bold(),
-        input.display()
+        source.display()
     );
-    println!("  Format: {}", style(format).cyan());
+    say!("  Format: {}", style(format).cyan());
 
     // Get and display model info
-    let info = get_model_info(&input)?;
-    println!(
+    let info = get_model_info(input)?;
+    say!(
         "  Meshes: {}, Vertices: {}, Indices: {}",
         style(info.meshes).cyan(),
         style(info.total_vertices).cyan(),
@@ -59,13 +83,13 @@ pub fn run(input: PathBuf, options: ModelOptions) -> Result<()> {
     );
 
     if info.materials > 0 {
-        println!("  Materials: {}", style(info.materials).cyan());
+        say!("  Materials: {}", style(info.materials).cyan());
     }
     if info.textures > 0 {
-        println!("  Textures: {}", style(info.textures).cyan());
+        say!("  Textures: {}", style(info.textures).cyan());
     }
     if info.animations > 0 {
-        println!("  Animations: {}", style(info.animations).cyan());
+        say!("  Animations: {}", style(info.animations).cyan());
     }
 
     // Build config
@@ -76,31 +100,55 @@ pub fn run(input: PathBuf, options: ModelOptions) -> Result<()> {
         lod_count: options.lod_count.clamp(1, 4),
         lod_ratio: options.lod_ratio.clamp(0.1, 0.9),
         output_glb: true,
+        lock_morph_vertices: !options.no_morph_lock,
+        lock_border: options.lock_border,
+        sparse: options.sparse,
+        normal_weight: options.normal_weight.max(0.0),
+        uv_weight: options.uv_weight.max(0.0),
+        weld_vertices: !options.no_weld,
+        weld_epsilon: options.weld_epsilon.max(0.0),
     };
 
     // Show what optimizations will be applied
-    println!();
-    println!("{} Optimizations:", style("⚙").blue().bold());
+    say!();
+    say!("{} Optimizations:", style("⚙").blue().bold());
+    if config.weld_vertices {
+        say!("  {} Vertex welding", style("✓").green());
+    }
     if config.optimize_meshes {
-        println!("  {} Vertex cache optimization", style("✓").green());
-        println!("  {} Overdraw optimization", style("✓").green());
-        println!("  {} Vertex fetch optimization", style("✓").green());
+        say!("  {} Vertex cache optimization", style("✓").green());
+        say!("  {} Overdraw optimization", style("✓").green());
+        say!("  {} Vertex fetch optimization", style("✓").green());
     }
     if config.encode_buffers {
-        println!("  {} Meshopt buffer compression", style("✓").green());
+        say!("  {} Meshopt buffer compression", style("✓").green());
     }
     if config.generate_lods {
-        println!(
+        say!(
             "  {} LOD generation ({} levels, {}% ratio)",
             style("✓").green(),
             config.lod_count,
             (config.lod_ratio * 100.0) as u32
         );
+        if config.lock_border {
+            say!("    {} Border locking", style("✓").green());
+        }
+        if config.sparse {
+            say!("    {} Sparse simplification", style("✓").green());
+        }
+        if config.normal_weight > 0.0 || config.uv_weight > 0.0 {
+            say!(
+                "    {} Attribute-aware error (normals {}, UVs {})",
+                style("✓").green(),
+                config.normal_weight,
+                config.uv_weight
+            );
+        }
 
         // Show estimated LOD levels
         let lod_estimates = estimate_lod_levels(&info);
         for est in &lod_estimates {
-            println!(
+            say!(
                 "    LOD {}: ~{} triangles (distance: {})",
                 est.level,
                 est.estimated_triangles,
@@ -109,17 +157,19 @@ pub fn run(input: PathBuf, options: ModelOptions) -> Result<()> {
         }
     }
 
-    println!();
+    say!();
 
     // Process the model
     let start = Instant::now();
-    let stats = process_model(&input, &output, &config)?;
+    let mut stats = process_model(input, &output, &config)?;
+    stats.original_size = std::fs::metadata(source)?.len();
     let elapsed = start.elapsed();
 
     // Print results
-    println!("{} Model processed!", style("✓").green().bold());
-    println!("  Output: {}", style(output.display()).cyan());
-    println!(
+    emit_output(source, &output, stats.original_size, stats.output_size);
+    say!("{} Model processed!", style("✓").green().bold());
+    say!("  Output: {}", style(output.display()).cyan());
+    say!(
         "  Size: {} → {} ({:.1}%)",
         style(format_size(stats.original_size)).dim(),
         style(format_size(stats.output_size)).green(),
@@ -129,40 +179,70 @@ pub fn run(input: PathBuf, options: ModelOptions) -> Result<()> {
             0.0
         }
     );
-    println!("  Time: {:.2}s", elapsed.as_secs_f64());
+    say!("  Time: {:.2}s", elapsed.as_secs_f64());
+
+    // Physics shape from the same source geometry, named after the output it belongs to
+    if let Some(shape) = options.collision {
+        let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+        let collision_output = output.with_file_name(format!("{}_collision.glb", stem));
+        let collision = generate_collision_mesh(
+            input,
+            &collision_output,
+            shape,
+            options.collision_ratio.clamp(0.001, 1.0),
+        )?;
+        say!();
+        say!(
+            "{} Collision mesh ({}): {}",
+            style("✓").green().bold(),
+            shape,
+            style(collision_output.display()).cyan()
+        );
+        say!(
+            "  Triangles: {} → {}, Vertices: {}, Size: {}",
+            collision.source_triangles,
+            style(collision.triangles).green(),
+            collision.vertices,
+            format_size(collision.output_size)
+        );
+    }
 
     Ok(())
 }
 
-fn print_model_info(input: &PathBuf) -> Result<()> {
-    let format = detect_model_format(input)
-        .ok_or_else(|| anyhow::anyhow!("Unsupported model format"))?;
-
+fn print_model_info(source: &Path, input: &Path, format: ModelFormat) -> Result<()> {
     let info = get_model_info(input)?;
 
-    println!("{} Model Information", style("📊").blue().bold());
-    println!("  File: {}", style(input.display()).cyan());
-    println!("  Format: {}", style(format).cyan());
-    println!();
-    println!("  {}", style("Geometry:").bold());
-    println!("    Meshes: {}", info.meshes);
-    println!("    Vertices: {}", info.total_vertices);
-    println!("    Indices: {}", info.total_indices);
-    println!("    Triangles: ~{}", info.total_indices / 3);
-    println!();
-    println!("  {}", style("Resources:").bold());
-    println!("    Materials: {}", info.materials);
-    println!("    Textures: {}", info.textures);
-    println!("    Animations: {}", info.animations);
-    println!("    Nodes: {}", info.nodes);
+    say!("{} Model Information", style("📊").blue().bold());
+    say!("  File: {}", style(source.display()).cyan());
+    say!("  Format: {}", style(format).cyan());
+    say!();
+    say!("  {}", style("Geometry:").bold());
+    say!("    Meshes: {}", info.meshes);
+    say!("    Vertices: {}", info.total_vertices);
+    say!("    Indices: {}", info.total_indices);
+    say!("    Triangles: ~{}", info.total_indices / 3);
+
+    if !info.primitives.is_empty() {
+        say!();
+        say!("  {}", style("Primitives:").bold());
+        print_primitive_table(&info.primitives);
+    }
+
+    say!();
+    say!("  {}", style("Resources:").bold());
+    say!("    Materials: {}", info.materials);
+    say!("    Textures: {}", info.textures);
+    say!("    Animations: {}", info.animations);
+    say!("    Nodes: {}", info.nodes);
 
     // Show LOD recommendations
     let lod_estimates = estimate_lod_levels(&info);
     if lod_estimates.len() > 1 {
-        println!();
-        println!("  {}", style("Recommended LOD Levels:").bold());
+        say!();
+        say!("  {}", style("Recommended LOD Levels:").bold());
         for est in &lod_estimates {
-            println!(
+            say!(
                 "    LOD {}: {:.0}% vertices (~{} triangles) at distance {}",
                 est.level,
                 est.vertex_ratio * 100.0,
@@ -173,13 +253,81 @@ fn print_model_info(input: &PathBuf) -> Result<()> {
     }
 
     // File size
-    let file_size = std::fs::metadata(input)?.len();
-    println!();
-    println!("  File size: {}", format_size(file_size));
+    let file_size = std::fs::metadata(source)?.len();
+    say!();
+    say!("  File size: {}", format_size(file_size));
 
     Ok(())
 }
 
+/// Print one row per primitive, with per-mesh subtotals for multi-primitive meshes
+fn print_primitive_table(primitives: &[PrimitiveInfo]) {
+    let mesh_label = |p: &PrimitiveInfo| match &p.mesh_name {
+        Some(name) => format!("{} {}", p.mesh_index, name),
+        None => p.mesh_index.to_string(),
+    };
+    let mesh_width = primitives
+        .iter()
+        .map(|p| mesh_label(p).chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    let material_width = primitives
+        .iter()
+        .map(|p| p.material.as_deref().unwrap_or("-").chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(8);
+
+    say!(
+        "    {:<mesh_width$}  {:>4}  {:>9}  {:>9}  {:<material_width$}  {:<5}  Attributes",
+        "Mesh", "Prim", "Vertices", "Triangles", "Material", "Index",
+    );
+
+    let mut i = 0;
+    while i < primitives.len() {
+        let mesh_index = primitives[i].mesh_index;
+        let end = primitives[i..]
+            .iter()
+            .position(|p| p.mesh_index != mesh_index)
+            .map(|n| i + n)
+            .unwrap_or(primitives.len());
+        let group = &primitives[i..end];
+
+        for (n, p) in group.iter().enumerate() {
+            let label = if n == 0 { mesh_label(p) } else { String::new() };
+            let mut attributes = p.attributes.join(", ");
+            if p.morph_targets > 0 {
+                attributes.push_str(&format!(" (+{} morph targets)", p.morph_targets));
+            }
+            say!(
+                "    {:<mesh_width$}  {:>4}  {:>9}  {:>9}  {:<material_width$}  {:<5}  {}",
+                label,
+                p.primitive_index,
+                p.vertices,
+                p.triangles,
+                p.material.as_deref().unwrap_or("-"),
+                p.index_type.unwrap_or("none"),
+                style(attributes).dim(),
+            );
+        }
+
+        if group.len() > 1 {
+            let vertices: usize = group.iter().map(|p| p.vertices).sum();
+            let triangles: usize = group.iter().map(|p| p.triangles).sum();
+            say!(
+                "    {:<mesh_width$}  {:>4}  {:>9}  {:>9}",
+                "",
+                style("sum").dim(),
+                style(vertices).bold(),
+                style(triangles).bold(),
+            );
+        }
+
+        i = end;
+    }
+}
+
 fn format_size(bytes: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = 1024 * 1024;
@@ -0,0 +1,602 @@
+use anyhow::{Context, Result};
+use console::style;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::cli::MoveOptions;
+use crate::config::{find_and_load_config, load_config, sidecar_path, Config};
+use crate::processors::{
+    frame_hash, hash_config, manifest_path, open_image, save_atlas_metadata, AssetType,
+    AtlasMetadata, BuildCache, BuildManifest, MANIFEST_FILE_NAME,
+};
+use crate::utils::say;
+
+/// One source asset being moved, as paths relative to the source directory
+struct Move {
+    from: PathBuf,
+    to: PathBuf,
+    /// Rules resolve the same at both paths, so the built outputs can move along
+    outputs_valid: bool,
+    /// Pixel hash of image sources, to recognise their atlas frames
+    frame_hash: Option<String>,
+}
+
+/// A built output (file, or tile directory) that follows its source
+struct OutputMove {
+    from: String,
+    /// None when the new path builds differently and the output is removed
+    to: Option<String>,
+}
+
+pub fn run(from: PathBuf, to: PathBuf, options: MoveOptions) -> Result<()> {
+    let config = match &options.config {
+        Some(path) => Some(load_config(path)?),
+        None => find_and_load_config()?,
+    };
+    let source_dir = options
+        .source
+        .clone()
+        .or_else(|| config.as_ref().map(|c| c.project.source.clone()))
+        .unwrap_or_else(|| PathBuf::from("./assets"));
+    let output_dir = options
+        .output
+        .clone()
+        .or_else(|| config.as_ref().map(|c| c.project.output.clone()))
+        .unwrap_or_else(|| PathBuf::from("./build/assets"));
+
+    let source_root = source_dir
+        .canonicalize()
+        .with_context(|| format!("Source directory does not exist: {}", source_dir.display()))?;
+    let from_path = from
+        .canonicalize()
+        .with_context(|| format!("Path does not exist: {}", from.display()))?;
+    let from_rel = from_path
+        .strip_prefix(&source_root)
+        .with_context(|| {
+            format!(
+                "{} is not inside the source directory {}",
+                from.display(),
+                source_dir.display()
+            )
+        })?
+        .to_path_buf();
+    if from_rel.as_os_str().is_empty() {
+        anyhow::bail!("Cannot move the source directory itself");
+    }
+
+    // Moving onto a directory moves inside it, like mv
+    let to = match from_path.file_name() {
+        Some(name) if to.is_dir() => to.join(name),
+        _ => to,
+    };
+    if to.exists() {
+        anyhow::bail!("Destination already exists: {}", to.display());
+    }
+    let to_path = absolute(&to)?;
+    let to_rel = to_path
+        .strip_prefix(&source_root)
+        .with_context(|| {
+            format!(
+                "{} is not inside the source directory {}",
+                to.display(),
+                source_dir.display()
+            )
+        })?
+        .to_path_buf();
+    if to_rel.starts_with(&from_rel) {
+        anyhow::bail!("Cannot move {} into itself", from.display());
+    }
+
+    let atlases = find_atlases(&output_dir);
+    let moves = plan_moves(
+        &source_root,
+        &from_rel,
+        &to_rel,
+        config.as_ref(),
+        !atlases.is_empty(),
+    )?;
+
+    if options.dry_run {
+        say!("{} Move preview (dry run)", style("→").blue().bold());
+    } else {
+        say!("{} Moving assets", style("→").blue().bold());
+    }
+    say!();
+    say!(
+        "  {} Source: {} → {} ({} assets)",
+        style("~").yellow(),
+        manifest_path(&from_rel),
+        style(manifest_path(&to_rel)).green(),
+        moves.len()
+    );
+
+    let sidecar = from_path
+        .is_file()
+        .then(|| sidecar_path(&from_path))
+        .filter(|p| p.is_file());
+    if sidecar.is_some() {
+        say!(
+            "  {} Sidecar: {} → {}",
+            style("~").yellow(),
+            sidecar_path(&from).display(),
+            sidecar_path(&to).display()
+        );
+    }
+
+    if !options.dry_run {
+        if let Some(parent) = to.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::rename(&from_path, &to)
+            .with_context(|| format!("Failed to move {} to {}", from.display(), to.display()))?;
+        if let Some(sidecar) = &sidecar {
+            std::fs::rename(sidecar, sidecar_path(&to))
+                .with_context(|| format!("Failed to move sidecar: {}", sidecar.display()))?;
+        }
+    }
+
+    let outputs = move_manifest(&output_dir, &moves, options.dry_run)?;
+    move_cache(&output_dir, &source_root, &moves, &outputs, options.dry_run)?;
+    if !options.dry_run {
+        apply_output_moves(&output_dir, &outputs)?;
+    }
+    let stale_frames = move_atlas_frames(&atlases, &moves, &from_rel, options.dry_run)?;
+
+    let rebuilds = outputs.iter().filter(|o| o.to.is_none()).count();
+    say!();
+    if options.dry_run {
+        say!(
+            "{} Nothing was changed; run without --dry-run to move",
+            style("!").yellow().bold()
+        );
+    } else {
+        say!("{} Move complete!", style("✓").green().bold());
+    }
+    if rebuilds > 0 {
+        say!(
+            "  {} assets match different rules at their new path; the next build rebuilds them",
+            style(rebuilds).cyan()
+        );
+    }
+    if stale_frames > 0 {
+        say!(
+            "  {} atlas frames left their atlas directory; regenerate the atlas to drop them",
+            style(stale_frames).cyan()
+        );
+    }
+
+    Ok(())
+}
+
+/// Every asset under the moved path, with whether its outputs stay valid
+fn plan_moves(
+    source_root: &Path,
+    from: &Path,
+    to: &Path,
+    config: Option<&Config>,
+    hash_frames: bool,
+) -> Result<Vec<Move>> {
+    let from_path = source_root.join(from);
+    let relative: Vec<PathBuf> = if from_path.is_dir() {
+        WalkDir::new(&from_path)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter(|e| AssetType::from_path(e.path()) != AssetType::Unknown)
+            .filter_map(|e| {
+                e.path()
+                    .strip_prefix(&from_path)
+                    .ok()
+                    .map(Path::to_path_buf)
+            })
+            .collect()
+    } else {
+        vec![PathBuf::new()]
+    };
+
+    let virtual_textures = match config {
+        Some(cfg) => Some(cfg.virtual_texture.matcher()?),
+        None => None,
+    };
+    relative
+        .into_iter()
+        .map(|rel| {
+            let (old, new) = if rel.as_os_str().is_empty() {
+                (from.to_path_buf(), to.to_path_buf())
+            } else {
+                (from.join(&rel), to.join(&rel))
+            };
+            let asset = source_root.join(&old);
+
+            // The sidecar moves too, so only the rule patterns can differ
+            let outputs_valid = match config {
+                Some(cfg) => {
+                    let before = cfg.resolve_rule(&asset, &old)?;
+                    let after = cfg.resolve_rule(&asset, &new)?;
+                    let tiled =
+                        |path: &Path| virtual_textures.as_ref().is_some_and(|m| m.is_match(path));
+                    hash_config(&before.settings)? == hash_config(&after.settings)?
+                        && before.patterns.is_empty() == after.patterns.is_empty()
+                        && tiled(&old) == tiled(&new)
+                }
+                None => true,
+            };
+            let frame_hash = (hash_frames && AssetType::from_path(&asset) == AssetType::Image)
+                .then(|| open_image(&asset).ok().map(|img| frame_hash(&img)))
+                .flatten();
+
+            Ok(Move {
+                from: old,
+                to: new,
+                outputs_valid,
+                frame_hash,
+            })
+        })
+        .collect()
+}
+
+/// Point manifest entries at the new sources and plan where their outputs go
+fn move_manifest(output_dir: &Path, moves: &[Move], dry_run: bool) -> Result<Vec<OutputMove>> {
+    let path = output_dir.join(MANIFEST_FILE_NAME);
+    if !path.exists() {
+        say!(
+            "  {} Manifest: none in {}",
+            style("-").dim(),
+            output_dir.display()
+        );
+        return Ok(Vec::new());
+    }
+    let mut manifest = BuildManifest::load(output_dir)?;
+
+    let by_source: HashMap<String, &Move> =
+        moves.iter().map(|m| (manifest_path(&m.from), m)).collect();
+    let mut outputs = Vec::new();
+    manifest.assets.retain_mut(|entry| {
+        let Some(asset) = by_source.get(&entry.source) else {
+            return true;
+        };
+        let Some((unit, rest)) = output_unit(&entry.output, &asset.from) else {
+            return true;
+        };
+        let extension = &unit[manifest_path(&asset.from.with_extension("")).len()..];
+        let new_unit = format!(
+            "{}{}",
+            manifest_path(&asset.to.with_extension("")),
+            extension
+        );
+
+        if !asset.outputs_valid {
+            say!(
+                "  {} Output: {} (rebuilt by the next build)",
+                style("remove").red(),
+                entry.output
+            );
+            outputs.push(OutputMove {
+                from: unit,
+                to: None,
+            });
+            return false;
+        }
+        let output = format!("{}{}", new_unit, rest);
+        say!(
+            "  {} Output: {} → {}",
+            style("~").yellow(),
+            entry.output,
+            style(&output).green()
+        );
+        entry.source = manifest_path(&asset.to);
+        entry.output = output;
+        outputs.push(OutputMove {
+            from: unit,
+            to: Some(new_unit),
+        });
+        true
+    });
+
+    if outputs.is_empty() {
+        say!(
+            "  {} Manifest: no entries for the moved assets",
+            style("-").dim()
+        );
+    } else if !dry_run {
+        manifest.sort();
+        manifest.save(output_dir)?;
+    }
+    Ok(outputs)
+}
+
+/// Files written next to an output that aren't in the manifest: model thumbnails and
+/// nine-slice borders
+const OUTPUT_SIDECARS: [&str; 2] = ["thumb.png", "slice.json"];
+
+/// Move outputs to their new paths, or remove the ones the next build replaces
+fn apply_output_moves(output_dir: &Path, outputs: &[OutputMove]) -> Result<()> {
+    for output in outputs {
+        let old = output_dir.join(&output.from);
+        match &output.to {
+            Some(to) => {
+                let new = output_dir.join(to);
+                if let Some(parent) = new.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                if old.exists() {
+                    std::fs::rename(&old, &new)
+                        .with_context(|| format!("Failed to move output: {}", old.display()))?;
+                }
+                for extension in OUTPUT_SIDECARS {
+                    if old.with_extension(extension).is_file() {
+                        std::fs::rename(
+                            old.with_extension(extension),
+                            new.with_extension(extension),
+                        )?;
+                    }
+                }
+            }
+            None => {
+                if old.is_dir() {
+                    std::fs::remove_dir_all(&old)?;
+                } else if old.exists() {
+                    std::fs::remove_file(&old)?;
+                }
+                for extension in OUTPUT_SIDECARS {
+                    if old.with_extension(extension).is_file() {
+                        std::fs::remove_file(old.with_extension(extension))?;
+                    }
+                }
+            }
+        }
+
+        // Directories emptied by the move, up to the output directory
+        let mut parent = old.parent();
+        while let Some(dir) = parent.filter(|dir| *dir != output_dir) {
+            if std::fs::remove_dir(dir).is_err() {
+                break;
+            }
+            parent = dir.parent();
+        }
+    }
+    Ok(())
+}
+
+/// Re-key cache entries to the new sources so unchanged assets stay cached
+fn move_cache(
+    output_dir: &Path,
+    source_root: &Path,
+    moves: &[Move],
+    outputs: &[OutputMove],
+    dry_run: bool,
+) -> Result<()> {
+    let cache_dir = output_dir.join(".cache");
+    let (Ok(output_root), true) = (output_dir.canonicalize(), cache_dir.exists()) else {
+        say!(
+            "  {} Cache: none in {}",
+            style("-").dim(),
+            cache_dir.display()
+        );
+        return Ok(());
+    };
+    let mut cache = BuildCache::load(&cache_dir)?;
+
+    let mut migrated = 0;
+    let mut dropped = 0;
+    for key in cache.entries.keys().cloned().collect::<Vec<_>>() {
+        let Some((asset, new_key)) = moves
+            .iter()
+            .find_map(|m| Some((m, rebase(&key, source_root, &m.from, &m.to)?)))
+        else {
+            continue;
+        };
+        let mut entry = cache.entries.remove(&key).unwrap();
+        let output = outputs.iter().find_map(|o| {
+            let to = o.to.as_ref()?;
+            rebase(
+                &entry.output_path,
+                &output_root,
+                Path::new(&o.from),
+                Path::new(to),
+            )
+        });
+        match output {
+            Some(output) if asset.outputs_valid => {
+                entry.output_path = output;
+                cache.entries.insert(new_key, entry);
+                migrated += 1;
+            }
+            _ => dropped += 1,
+        }
+    }
+
+    // Virtual texture tile hashes follow their tile directory
+    for key in cache.tiles.keys().cloned().collect::<Vec<_>>() {
+        for output in outputs {
+            let Some(new_key) = output
+                .to
+                .as_ref()
+                .and_then(|to| rebase(&key, &output_root, Path::new(&output.from), Path::new(to)))
+            else {
+                continue;
+            };
+            let hash = cache.tiles.remove(&key).unwrap();
+            cache.tiles.insert(new_key, hash);
+            break;
+        }
+    }
+
+    if migrated + dropped == 0 {
+        say!(
+            "  {} Cache: no entries for the moved assets",
+            style("-").dim()
+        );
+        return Ok(());
+    }
+    say!(
+        "  {} Cache: {} entries migrated, {} dropped",
+        style("~").yellow(),
+        migrated,
+        dropped
+    );
+    if !dry_run {
+        cache.save(&cache_dir)?;
+    }
+    Ok(())
+}
+
+/// Rename the frames of moved sprites in atlas metadata under the output directory
+///
+/// Frame keys are relative to the atlas's own input directory, so a frame is matched by
+/// the trailing part of the old path (with or without extension) and, where the atlas
+/// records one, by its pixel hash. `moved` is the path given to `mv`. Returns the frames
+/// whose sprite left the atlas directory.
+fn move_atlas_frames(
+    atlases: &[(PathBuf, AtlasMetadata)],
+    moves: &[Move],
+    moved: &Path,
+    dry_run: bool,
+) -> Result<usize> {
+    let mut stale = 0;
+    for (path, metadata) in atlases {
+        let mut metadata = metadata.clone();
+        let mut renames: Vec<(String, String)> = Vec::new();
+        for (key, frame) in &metadata.frames {
+            let found = moves.iter().find_map(|m| {
+                frame_forms(&m.from)
+                    .into_iter()
+                    .find_map(|(root, form, with_ext)| {
+                        let matches = match (&frame.hash, &m.frame_hash) {
+                            (Some(hash), Some(sprite)) => hash == sprite && key.contains(&form),
+                            _ => key == &form,
+                        };
+                        matches.then_some((m, root, form, with_ext))
+                    })
+            });
+            let Some((asset, root, form, with_ext)) = found else {
+                continue;
+            };
+            // The whole atlas directory moved, so its keys still hold
+            if root.starts_with(moved) {
+                continue;
+            }
+            match asset.to.strip_prefix(&root) {
+                Ok(tail) => {
+                    let tail = if with_ext {
+                        tail.to_path_buf()
+                    } else {
+                        tail.with_extension("")
+                    };
+                    renames.push((key.clone(), key.replacen(&form, &manifest_path(&tail), 1)));
+                }
+                Err(_) => {
+                    say!(
+                        "  {} Atlas {}: {} left the atlas directory",
+                        style("!").yellow(),
+                        path.display(),
+                        key
+                    );
+                    stale += 1;
+                }
+            }
+        }
+        if renames.is_empty() {
+            continue;
+        }
+
+        for (old, new) in &renames {
+            say!(
+                "  {} Atlas {}: {} → {}",
+                style("~").yellow(),
+                path.display(),
+                old,
+                style(new).green()
+            );
+            let frame = metadata.frames.remove(old).unwrap();
+            metadata.frames.insert(new.clone(), frame);
+            for animation in metadata.animations.values_mut() {
+                for frame in animation.frames.iter_mut().filter(|f| *f == old) {
+                    *frame = new.clone();
+                }
+            }
+        }
+        if !dry_run {
+            save_atlas_metadata(&metadata, path)?;
+        }
+    }
+    Ok(stale)
+}
+
+/// Atlas metadata files in the output directory
+fn find_atlases(output_dir: &Path) -> Vec<(PathBuf, AtlasMetadata)> {
+    WalkDir::new(output_dir)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".cache")
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            e.file_type().is_file() && e.path().extension().is_some_and(|ext| ext == "json")
+        })
+        .filter_map(|e| {
+            let metadata = AtlasMetadata::open(e.path()).ok()?;
+            Some((e.path().to_path_buf(), metadata))
+        })
+        .collect()
+}
+
+/// Keys a sprite could have in an atlas built from one of its parent directories, longest first
+///
+/// Each is the atlas root it implies, the key and whether the key keeps the extension.
+fn frame_forms(path: &Path) -> Vec<(PathBuf, String, bool)> {
+    let components: Vec<_> = path.components().collect();
+    let mut forms = Vec::new();
+    for start in 0..components.len() {
+        let root: PathBuf = components[..start].iter().collect();
+        let tail: PathBuf = components[start..].iter().collect();
+        forms.push((root.clone(), manifest_path(&tail), true));
+        forms.push((root, manifest_path(&tail.with_extension("")), false));
+    }
+    forms
+}
+
+/// Split a manifest output into the part that moves on disk (the output file, or the tile
+/// directory of a virtual texture) and the rest, if it was built from `source`
+fn output_unit(output: &str, source: &Path) -> Option<(String, String)> {
+    let after = output.strip_prefix(&manifest_path(&source.with_extension("")))?;
+    if !after.starts_with('.') {
+        return None;
+    }
+    let end = output.len() - after.len() + after.find('/').unwrap_or(after.len());
+    Some((output[..end].to_string(), output[end..].to_string()))
+}
+
+/// `path` with `from` swapped for `to`, if it is `from` (or inside it) under a directory that is `root`
+fn rebase(path: &Path, root: &Path, from: &Path, to: &Path) -> Option<PathBuf> {
+    path.ancestors().skip(1).find_map(|dir| {
+        let rest = path.strip_prefix(dir).ok()?.strip_prefix(from).ok()?;
+        let base = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        if base.canonicalize().ok()? != root {
+            return None;
+        }
+        let moved = dir.join(to);
+        Some(if rest.as_os_str().is_empty() {
+            moved
+        } else {
+            moved.join(rest)
+        })
+    })
+}
+
+/// Absolute form of a path that may not exist yet, resolving its nearest existing ancestor
+fn absolute(path: &Path) -> Result<PathBuf> {
+    let path = std::env::current_dir()?.join(path);
+    let mut existing = path.as_path();
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        missing.push(existing.file_name().context("Invalid destination path")?);
+        existing = existing.parent().context("Invalid destination path")?;
+    }
+    let mut absolute = existing.canonicalize()?;
+    absolute.extend(missing.into_iter().rev());
+    Ok(absolute)
+}
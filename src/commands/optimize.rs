@@ -2,8 +2,12 @@ use anyhow::{Context, Result};
 use console::style;
 use std::path::PathBuf;
 
-use crate::cli::OptimizeOptions;
-use crate::processors::{process_image, AssetType, ImageProcessorConfig};
+use crate::cli::{OptimizeOptions, OutputFormat};
+use crate::processors::{
+    is_hdr_path, is_psd_path, is_tiff_path, metadata_size, process_image, process_image_variants, trim_image, write_trim, AssetType, DitherConfig,
+    ImageProcessorConfig, ProcessingStats, QualityMetrics, TrimSidecar, LOW_SSIM,
+};
+use crate::utils::{emit_output, say};
 
 pub fn run(input: PathBuf, options: OptimizeOptions) -> Result<()> {
     if !input.exists() {
@@ -15,14 +19,14 @@ pub fn run(input: PathBuf, options: OptimizeOptions) -> Result<()> {
     match asset_type {
         AssetType::Image => optimize_image(&input, &options),
         AssetType::Model => {
-            println!(
+            say!(
                 "{} 3D model optimization is coming in Phase 2",
                 style("!").yellow().bold()
             );
             Ok(())
         }
         AssetType::Audio => {
-            println!(
+            say!(
                 "{} Audio optimization is coming in Phase 2",
                 style("!").yellow().bold()
             );
@@ -38,32 +42,119 @@ pub fn run(input: PathBuf, options: OptimizeOptions) -> Result<()> {
 }
 
 fn optimize_image(input: &PathBuf, options: &OptimizeOptions) -> Result<()> {
+    // HDR inputs, PSDs and TIFFs can't be overwritten in place, so they always get a new format
+    // (PSDs and TIFFs take the one the output path names, if given)
+    let format = if is_hdr_path(input) {
+        options.hdr_encoding.output_format().or(options.format).or(Some(OutputFormat::Png))
+    } else if is_psd_path(input) || is_tiff_path(input) {
+        options.format.or_else(|| options.output.is_none().then_some(OutputFormat::Png))
+    } else {
+        options.format
+    };
+
     let output = options.output.clone().unwrap_or_else(|| {
-        if let Some(format) = &options.format {
+        if let Some(format) = &format {
             input.with_extension(format.to_string())
         } else {
             input.clone()
         }
     });
 
-    println!(
+    say!(
         "{} Optimizing image: {}",
         style("→").blue().bold(),
         input.display()
     );
 
     let config = ImageProcessorConfig {
-        output_format: options.format,
+        output_format: format,
         quality: options.quality,
         max_size: None,
         generate_mipmaps: options.mipmap,
+        ktx2_zstd_level: options.zstd,
+        astc_block: options.astc_block,
+        hdr_encoding: options.hdr_encoding,
+        tonemap: options.tonemap,
+        exposure: options.exposure,
+        texture_type: options.texture_type,
+        renormalize: options.renormalize,
+        normal_encoding: options.normal_encoding,
+        quantize: options.quantize,
+        dither: options.dither.then_some(DitherConfig {
+            pattern: options.dither_pattern,
+            strength: options.dither_strength,
+        }),
+        premultiply_alpha: options.premultiply_alpha,
+        colorspace: options.colorspace,
+        mip_filter: options.mip_filter,
+        alpha_coverage: options.alpha_coverage,
+        target_ssim: options.target_ssim,
+        max_output_size: None,
+        strip_metadata: options.strip_metadata,
+        bit_depth: options.bit_depth,
+    };
+    if config.target_ssim.is_some() && !config.searches_ssim(&output) {
+        say!(
+            "  {} --target-ssim only applies to JPEG and JPEG XL output",
+            style("!").yellow().bold()
+        );
+    }
+
+    // Trimmed images are processed from a cropped copy of the source
+    let trimmed = match options.trim {
+        true => Some(trim_image(input, &output, config.is_linear())?),
+        false => None,
     };
+    let source = trimmed.as_ref().map_or(input.as_path(), |(temp, _)| temp.as_path());
+    let original_size = std::fs::metadata(input)?.len();
+    let strips_metadata = options.strip_metadata
+        && output
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| matches!(e.to_lowercase().as_str(), "png" | "jpg" | "jpeg" | "webp"));
+    let metadata_stripped = if strips_metadata { metadata_size(input)? } else { 0 };
+
+    if !options.variants.is_empty() {
+        let variants = process_image_variants(source, &output, &config, &options.variants, &options.variant_name);
+        if let Some((temp, _)) = &trimmed {
+            let _ = std::fs::remove_file(temp);
+        }
+        let variants = variants.with_context(|| format!("Failed to optimize image: {}", input.display()))?;
+        for (path, scale, stats) in &variants {
+            emit_output(input, path, original_size, stats.output_size);
+            say!(
+                "{} {}x: {} → {}  {}",
+                style("✓").green().bold(),
+                scale,
+                style(format_size(original_size)).dim(),
+                style(format_size(stats.output_size)).green(),
+                style(path.display()).cyan()
+            );
+            if let Some(quality) = &stats.quality {
+                print_quality(quality);
+            }
+        }
+        print_metadata_stripped(metadata_stripped);
+        if let Some((_, trim)) = &trimmed {
+            let outputs: Vec<(PathBuf, f32)> = variants.iter().map(|(path, scale, _)| (path.clone(), *scale)).collect();
+            write_trim(trim, &outputs)?;
+            print_trim(trim);
+        }
+        return Ok(());
+    }
 
-    let stats = process_image(input, &output, &config)
-        .with_context(|| format!("Failed to optimize image: {}", input.display()))?;
+    let stats = process_image(source, &output, &config);
+    if let Some((temp, _)) = &trimmed {
+        let _ = std::fs::remove_file(temp);
+    }
+    let stats = ProcessingStats {
+        original_size,
+        ..stats.with_context(|| format!("Failed to optimize image: {}", input.display()))?
+    };
 
     // Print results
-    println!(
+    emit_output(input, &output, stats.original_size, stats.output_size);
+    say!(
         "{} Optimized: {} → {}",
         style("✓").green().bold(),
         style(format_size(stats.original_size)).dim(),
@@ -72,31 +163,68 @@ fn optimize_image(input: &PathBuf, options: &OptimizeOptions) -> Result<()> {
 
     let reduction = stats.size_reduction_percent();
     if reduction > 0.0 {
-        println!(
+        say!(
             "  {} size reduction ({} saved)",
             style(format!("{:.1}%", reduction)).green(),
             style(format_size(stats.original_size - stats.output_size)).green()
         );
     } else if reduction < 0.0 {
-        println!(
+        say!(
             "  {} File size increased by {:.1}%",
             style("!").yellow().bold(),
             -reduction
         );
     }
 
-    println!(
+    if let Some(quality) = &stats.quality {
+        print_quality(quality);
+    }
+    print_metadata_stripped(metadata_stripped);
+
+    say!(
         "  Processed in {}",
         style(format!("{:.2}s", stats.processing_time_ms as f64 / 1000.0)).dim()
     );
 
     if output != *input {
-        println!("  Output: {}", style(output.display()).cyan());
+        say!("  Output: {}", style(output.display()).cyan());
+    }
+
+    if let Some((_, trim)) = &trimmed {
+        write_trim(trim, &[(output.clone(), 1.0)])?;
+        print_trim(trim);
     }
 
     Ok(())
 }
 
+fn print_quality(quality: &QualityMetrics) {
+    let ssim = format!("{:.4}", quality.ssim);
+    if quality.ssim < LOW_SSIM {
+        say!(
+            "  {} Quality: PSNR {:.1} dB, SSIM {} (likely over-compressed)",
+            style("!").yellow().bold(),
+            quality.psnr,
+            style(ssim).yellow()
+        );
+    } else {
+        say!("  Quality: PSNR {:.1} dB, SSIM {}", quality.psnr, ssim);
+    }
+}
+
+fn print_metadata_stripped(bytes: u64) {
+    if bytes > 0 {
+        say!("  Metadata stripped: {}", style(format_size(bytes)).green());
+    }
+}
+
+fn print_trim(trim: &TrimSidecar) {
+    say!(
+        "  Trimmed: {}x{} → {}x{} at ({}, {}), original size and offset in .trim.json",
+        trim.source_width, trim.source_height, trim.width, trim.height, trim.trim_x, trim.trim_y
+    );
+}
+
 fn format_size(bytes: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = 1024 * 1024;
@@ -0,0 +1,74 @@
+use anyhow::Result;
+use console::style;
+
+use crate::cli::{OutputFormat, PackOptions, TextureType};
+use crate::processors::{write_packed_texture, ChannelPackConfig, ChannelSource, ImageProcessorConfig};
+use crate::utils::{format_size, say, summary};
+
+pub fn run(options: PackOptions) -> Result<()> {
+    let sources = [&options.red, &options.green, &options.blue, &options.alpha];
+    let mut channels: [Option<ChannelSource>; 4] = Default::default();
+    for (channel, spec) in channels.iter_mut().zip(sources) {
+        *channel = spec.as_deref().map(ChannelSource::parse).transpose()?;
+    }
+
+    let mut invert = [false; 4];
+    for c in options.invert.as_deref().unwrap_or("").chars() {
+        match "rgba".find(c.to_ascii_lowercase()) {
+            Some(index) => invert[index] = true,
+            None => anyhow::bail!("Unknown channel '{}' in --invert (use r, g, b or a)", c),
+        }
+    }
+
+    let format = options
+        .format
+        .or_else(|| {
+            options
+                .output
+                .extension()
+                .and_then(|e| e.to_str())
+                .and_then(OutputFormat::from_name)
+        })
+        .unwrap_or(OutputFormat::Png);
+
+    say!(
+        "{} Packing channels into: {}",
+        style("→").blue().bold(),
+        options.output.display()
+    );
+    for (name, spec) in ["R", "G", "B", "A"].iter().zip(sources) {
+        if let Some(spec) = spec {
+            say!("  {}: {}", name, spec);
+        }
+    }
+
+    let config = ChannelPackConfig {
+        channels,
+        invert,
+        image: ImageProcessorConfig {
+            output_format: Some(format),
+            quality: options.quality,
+            max_size: options.max_size,
+            generate_mipmaps: options.mipmaps,
+            ktx2_zstd_level: options.zstd,
+            // Packed masks are data, so GPU formats must not treat them as sRGB
+            texture_type: TextureType::Mask,
+            ..Default::default()
+        },
+    };
+    let stats = write_packed_texture(&options.output, &config)?;
+    summary("output", &options.output);
+    summary("input_size", stats.original_size);
+    summary("output_size", stats.output_size);
+
+    say!("{} Channels packed successfully!", style("✓").green().bold());
+    say!();
+    say!("  Inputs: {}", format_size(stats.original_size));
+    say!("  Packed: {} ({})", style(format_size(stats.output_size)).green(), format);
+    say!(
+        "  Processing time: {}",
+        style(format!("{}ms", stats.processing_time_ms)).dim()
+    );
+
+    Ok(())
+}
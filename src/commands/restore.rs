@@ -0,0 +1,204 @@
+use anyhow::{Context, Result};
+use console::style;
+use indicatif::ProgressStyle;
+use rayon::prelude::*;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::cli::RestoreOptions;
+use crate::config::{find_and_load_config, load_config};
+use crate::processors::{hash_data, output_hash, BuildManifest, ManifestEntry, MANIFEST_FILE_NAME};
+use crate::utils::{advance, emit, format_size, progress_bar, say, summary, Event, FileEvent, FileStatus};
+
+/// Where restored outputs come from
+enum RestoreSource {
+    Dir(PathBuf),
+    Url(String),
+}
+
+impl RestoreSource {
+    fn parse(from: &str) -> Self {
+        if from.starts_with("http://") || from.starts_with("https://") {
+            RestoreSource::Url(from.trim_end_matches('/').to_string())
+        } else {
+            RestoreSource::Dir(PathBuf::from(from))
+        }
+    }
+
+    /// Read an output by its manifest path
+    fn fetch(&self, output: &str) -> Result<Vec<u8>> {
+        match self {
+            RestoreSource::Dir(dir) => {
+                let path = dir.join(output);
+                std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))
+            }
+            RestoreSource::Url(base) => {
+                let url = format!("{}/{}", base, encode_url_path(output));
+                let response = ureq::get(&url)
+                    .call()
+                    .with_context(|| format!("Failed to download {}", url))?;
+                let mut data = Vec::new();
+                response
+                    .into_reader()
+                    .read_to_end(&mut data)
+                    .with_context(|| format!("Failed to download {}", url))?;
+                Ok(data)
+            }
+        }
+    }
+}
+
+pub fn run(options: RestoreOptions) -> Result<()> {
+    let content = std::fs::read_to_string(&options.manifest)
+        .with_context(|| format!("Failed to read manifest: {}", options.manifest.display()))?;
+    let manifest = BuildManifest::from_json(&content)
+        .with_context(|| format!("Failed to parse manifest: {}", options.manifest.display()))?;
+
+    let config = match &options.config {
+        Some(path) => Some(load_config(path)?),
+        None => find_and_load_config()?,
+    };
+    let output_dir = options
+        .output
+        .clone()
+        .or_else(|| config.as_ref().map(|c| c.project.output.clone()))
+        .unwrap_or_else(|| PathBuf::from("./build/assets"));
+    let source = RestoreSource::parse(&options.from);
+
+    say!(
+        "{} Restoring {} assets from: {}",
+        style("→").blue().bold(),
+        style(manifest.assets.len()).cyan(),
+        options.from
+    );
+    say!("  Output directory: {}", style(output_dir.display()).cyan());
+    say!();
+
+    let pb = progress_bar(manifest.assets.len() as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})")
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+
+    let restored = AtomicU64::new(0);
+    let restored_bytes = AtomicU64::new(0);
+    let up_to_date = AtomicU64::new(0);
+    let errors: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(options.jobs.unwrap_or_else(num_cpus::get))
+        .build()?;
+    pool.install(|| {
+        manifest.assets.par_iter().for_each(|entry| {
+            match restore_entry(entry, &source, &output_dir, options.force) {
+                Ok(Some(size)) => {
+                    emit(&Event::File(FileEvent {
+                        output_size: Some(size),
+                        ..FileEvent::new(&entry.output, FileStatus::Restored)
+                    }));
+                    restored.fetch_add(1, Ordering::Relaxed);
+                    restored_bytes.fetch_add(size, Ordering::Relaxed);
+                }
+                Ok(None) => {
+                    emit(&Event::File(FileEvent::new(&entry.output, FileStatus::Cached)));
+                    up_to_date.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(e) => {
+                    let error = format!("{:#}", e);
+                    emit(&Event::File(FileEvent { error: Some(&error), ..FileEvent::new(&entry.output, FileStatus::Failed) }));
+                    errors.lock().unwrap().push((entry.output.clone(), error));
+                }
+            }
+            advance(&pb, 1);
+        });
+    });
+    pb.finish_and_clear();
+
+    let mut errors = errors.into_inner().unwrap();
+    if errors.is_empty() {
+        // A complete restore looks like a local build, so later builds can start from it
+        std::fs::create_dir_all(&output_dir)?;
+        std::fs::write(output_dir.join(MANIFEST_FILE_NAME), &content)
+            .with_context(|| format!("Failed to write manifest to {}", output_dir.display()))?;
+    }
+
+    say!("{} Restore complete!", style("✓").green().bold());
+    say!(
+        "  Files restored: {} ({})",
+        style(restored.load(Ordering::Relaxed)).green(),
+        format_size(restored_bytes.load(Ordering::Relaxed))
+    );
+    let up_to_date = up_to_date.load(Ordering::Relaxed);
+    summary("restored", restored.load(Ordering::Relaxed));
+    summary("restored_size", restored_bytes.load(Ordering::Relaxed));
+    summary("up_to_date", up_to_date);
+    summary("failed", errors.len());
+    if up_to_date > 0 {
+        say!("  Files already up to date: {}", style(up_to_date).dim());
+    }
+
+    if !errors.is_empty() {
+        errors.sort();
+        say!("  Errors: {}", style(errors.len()).red());
+        for (output, error) in &errors {
+            say!("    {} {}: {}", style("✗").red(), output, error);
+        }
+        anyhow::bail!("{} outputs could not be restored", errors.len());
+    }
+
+    Ok(())
+}
+
+/// Fetch one output unless the local copy already matches, returning the bytes written
+fn restore_entry(
+    entry: &ManifestEntry,
+    source: &RestoreSource,
+    output_dir: &Path,
+    force: bool,
+) -> Result<Option<u64>> {
+    let expected = entry
+        .hash
+        .as_deref()
+        .context("No hash in manifest (rebuild it with a newer asset-forge)")?;
+
+    let target = output_dir.join(&entry.output);
+    if !force && target.exists() && output_hash(&target)? == expected {
+        return Ok(None);
+    }
+
+    let data = source.fetch(&entry.output)?;
+    let actual = format!("{:016x}", hash_data(&data));
+    if actual != expected {
+        anyhow::bail!("Hash mismatch (expected {}, got {})", expected, actual);
+    }
+
+    // Write next to the target and rename, so an interrupted restore never leaves a partial file
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let temp = target.with_extension("restore.tmp");
+    std::fs::write(&temp, &data)
+        .with_context(|| format!("Failed to write {}", temp.display()))?;
+    std::fs::rename(&temp, &target)
+        .with_context(|| format!("Failed to write {}", target.display()))?;
+
+    Ok(Some(data.len() as u64))
+}
+
+/// Percent-encode a manifest path for use in a URL, keeping the `/` separators
+fn encode_url_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
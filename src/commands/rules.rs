@@ -0,0 +1,113 @@
+use anyhow::Result;
+use console::style;
+use serde::Serialize;
+use std::path::{Component, Path, PathBuf};
+
+use crate::cli::RulesTestOptions;
+use crate::config::{find_and_load_config, load_config, load_sidecar, PresetConfig};
+use crate::processors::AssetType;
+use crate::utils::say;
+
+/// Print what would be applied to a single asset, without building anything
+pub fn test(path: PathBuf, options: RulesTestOptions) -> Result<()> {
+    let config = if let Some(config_path) = &options.config {
+        Some(load_config(config_path)?)
+    } else {
+        find_and_load_config()?
+    };
+    let config = config.unwrap_or_default();
+
+    let relative = relative_to(&path, &config.project.source);
+
+    say!("{} Rules for: {}", style("🔍").blue().bold(), style(path.display()).cyan());
+    say!("  Relative path: {}", relative.display());
+    say!("  Asset type: {}", AssetType::from_path(&path).description());
+    if !path.exists() {
+        say!("  {}", style("(file does not exist, sidecar not checked)").yellow());
+    }
+
+    // Preset
+    say!();
+    match options.preset {
+        Some(preset) => {
+            let name = preset.to_string();
+            let (preset_config, origin) = match config.presets.get(&name) {
+                Some(preset_config) => (preset_config.clone(), "config"),
+                None => (PresetConfig::builtin(preset), "built-in"),
+            };
+            say!("  {} {} ({})", style("Preset:").bold(), style(&name).cyan(), origin);
+            print_settings(&preset_config);
+        }
+        None => say!("  {} none", style("Preset:").bold()),
+    }
+
+    // Rules in precedence order
+    say!();
+    let resolved = config.resolve_rule(&path, &relative)?;
+    let matches = config.matching_rules(&relative)?;
+    if matches.is_empty() {
+        say!("  {} none match", style("Rules:").bold());
+    } else {
+        say!("  {} (lowest to highest precedence)", style("Rules:").bold());
+        for (i, (pattern, rule)) in matches.iter().enumerate() {
+            say!("    {}. {}", i + 1, style(format!("\"{}\"", pattern)).cyan());
+            print_settings(*rule);
+        }
+    }
+
+    // Sidecar overrides everything else
+    say!();
+    match &resolved.sidecar {
+        Some(sidecar) => {
+            say!("  {} {}", style("Sidecar:").bold(), style(sidecar.display()).cyan());
+            if let Some(rule) = load_sidecar(&path)? {
+                print_settings(&rule);
+            }
+        }
+        None => say!("  {} none", style("Sidecar:").bold()),
+    }
+
+    if config.priority.matcher()?.is_match(&relative) {
+        say!();
+        say!("  {} yes (matches [priority])", style("Priority:").bold());
+    }
+
+    say!();
+    say!("{} Resolved rule settings:", style("→").blue().bold());
+    print_settings(&resolved.settings);
+
+    Ok(())
+}
+
+/// Path relative to the source directory, if it lies inside it
+fn relative_to(path: &Path, source: &Path) -> PathBuf {
+    if let (Ok(path), Ok(source)) = (path.canonicalize(), source.canonicalize()) {
+        if let Ok(relative) = path.strip_prefix(&source) {
+            return relative.to_path_buf();
+        }
+    }
+
+    // Fall back to a lexical comparison for files that don't exist yet
+    let normalize = |p: &Path| -> PathBuf {
+        p.components().filter(|c| !matches!(c, Component::CurDir)).collect()
+    };
+    let path = normalize(path);
+    path.strip_prefix(normalize(source))
+        .map(Path::to_path_buf)
+        .unwrap_or(path)
+}
+
+/// Print the settings that are set, one `key = value` per line
+fn print_settings<T: Serialize>(settings: &T) {
+    let table = match toml::Value::try_from(settings) {
+        Ok(toml::Value::Table(table)) => table,
+        _ => return,
+    };
+
+    if table.is_empty() {
+        say!("       {}", style("(no settings)").dim());
+    }
+    for (key, value) in &table {
+        say!("       {} = {}", key, value);
+    }
+}
@@ -0,0 +1,81 @@
+use anyhow::Result;
+use console::style;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use crate::cli::TileOptions;
+use crate::processors::{generate_tiles, BuildCache, ImageProcessorConfig, TileConfig};
+use crate::utils::{emit_output, format_size, say};
+
+pub fn run(input: PathBuf, options: TileOptions) -> Result<()> {
+    if !input.is_file() {
+        anyhow::bail!("Input file does not exist: {}", input.display());
+    }
+
+    let output_dir = options
+        .output
+        .clone()
+        .unwrap_or_else(|| input.with_extension("tiles"));
+
+    say!(
+        "{} Generating virtual texture tiles from: {}",
+        style("→").blue().bold(),
+        input.display()
+    );
+
+    let config = TileConfig {
+        tile_size: options.tile_size,
+        border: options.border,
+        image: ImageProcessorConfig {
+            output_format: Some(options.format),
+            quality: options.quality,
+            ktx2_zstd_level: options.zstd,
+            astc_block: options.astc_block,
+            ..Default::default()
+        },
+    };
+
+    // Tile hashes live in the output directory's build cache, like `build`
+    let cache_dir = output_dir.join(".cache");
+    let mut cache = BuildCache::load(&cache_dir).unwrap_or_default();
+    let known = if options.force { HashMap::new() } else { cache.tiles.clone() };
+
+    let start = Instant::now();
+    let result = generate_tiles(&input, &output_dir, &config, &known)?;
+    emit_output(&input, &result.index_path, std::fs::metadata(&input)?.len(), result.output_size);
+    cache.tiles.extend(result.hashes);
+    cache.cleanup();
+    cache.save(&cache_dir)?;
+
+    say!("{} Tiles generated successfully!", style("✓").green().bold());
+    say!();
+    say!("  Index: {}", style(result.index_path.display()).cyan());
+    say!(
+        "  Texture: {}x{}, {} levels",
+        result.index.width,
+        result.index.height,
+        result.index.levels.len()
+    );
+    say!(
+        "  Tile size: {} + {} border ({})",
+        result.index.tile_size, result.index.border, result.index.format
+    );
+    say!(
+        "  Tiles: {} written, {} unchanged",
+        style(result.tiles_written).green(),
+        style(result.tiles_reused).dim()
+    );
+    let total: u32 = result.index.levels.iter().map(|l| l.columns * l.rows).sum();
+    let empty = total as usize - result.index.tiles.len();
+    if empty > 0 {
+        say!("  Empty tiles skipped: {}", style(empty).dim());
+    }
+    say!("  Total size: {}", style(format_size(result.output_size)).green());
+    say!(
+        "  Processing time: {}",
+        style(format!("{:.2}s", start.elapsed().as_secs_f64())).dim()
+    );
+
+    Ok(())
+}
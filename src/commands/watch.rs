@@ -1,17 +1,24 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use console::style;
+use globset::GlobSet;
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher, Event, EventKind};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::sync::mpsc::channel;
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
+use walkdir::WalkDir;
 
-use crate::cli::{WatchOptions, PlatformPreset};
-use crate::config::{find_and_load_config, load_config, PresetConfig};
-use crate::processors::{
-    process_image, process_audio, process_model,
-    AssetType, ImageProcessorConfig, AudioConfig, AudioFormat, ModelConfig,
+use crate::cli::WatchOptions;
+use super::build::{
+    apply_profile, atlas_metadata_path, atlas_rule, build_asset, get_preset_config, process_atlas_group, worker_pools,
+    ProcessedFile,
 };
+use crate::config::{
+    find_and_load_config, load_config, Config as ProjectConfig, PresetConfig, RuleConfig, VirtualTextureConfig,
+};
+use crate::processors::{AssetType, BuildCache};
+use crate::utils::say;
 
 /// Watch statistics
 struct WatchStats {
@@ -33,18 +40,18 @@ impl WatchStats {
 
     fn print_summary(&self) {
         let elapsed = self.start_time.elapsed();
-        println!();
-        println!(
+        say!();
+        say!(
             "{} Watch session summary:",
             style("📊").blue().bold()
         );
-        println!("  Duration: {:.1}s", elapsed.as_secs_f64());
-        println!("  Processed: {}", style(self.processed).green());
+        say!("  Duration: {:.1}s", elapsed.as_secs_f64());
+        say!("  Processed: {}", style(self.processed).green());
         if self.errors > 0 {
-            println!("  Errors: {}", style(self.errors).red());
+            say!("  Errors: {}", style(self.errors).red());
         }
         if self.skipped > 0 {
-            println!("  Skipped: {}", style(self.skipped).dim());
+            say!("  Skipped: {}", style(self.skipped).dim());
         }
     }
 }
@@ -94,6 +101,9 @@ pub fn run(input: PathBuf, options: WatchOptions) -> Result<()> {
         anyhow::bail!("Watch path is not a directory: {}", input.display());
     }
 
+    // Events carry absolute paths, which only strip to output-relative paths against an absolute input
+    let input = input.canonicalize()?;
+
     // Load configuration
     let config = if let Some(config_path) = &options.config {
         Some(load_config(config_path)?)
@@ -108,27 +118,46 @@ pub fn run(input: PathBuf, options: WatchOptions) -> Result<()> {
         .or_else(|| config.as_ref().map(|c| c.project.output.clone()))
         .unwrap_or_else(|| PathBuf::from("./build/assets"));
 
-    // Get preset configuration
-    let preset_config = get_preset_config(&options.preset);
+    // Presets and profiles resolve as in build
+    let mut preset_config = get_preset_config(&options.preset, &config);
+    apply_profile(&mut preset_config, options.profile.as_deref(), &config)?;
+
+    let virtual_texture = config
+        .as_ref()
+        .map(|cfg| cfg.virtual_texture.clone())
+        .unwrap_or_default();
+    let virtual_textures = virtual_texture.matcher()?;
+
+    // Assets matching [priority] jump the queue
+    let priority = match &config {
+        Some(cfg) => cfg.priority.matcher()?,
+        None => GlobSet::empty(),
+    };
 
     // Create output directory
     std::fs::create_dir_all(&output_dir)?;
 
-    println!(
+    say!(
         "{} Watch mode started",
         style("👁").blue().bold()
     );
-    println!("  Watching: {}", style(input.display()).cyan());
-    println!("  Output: {}", style(output_dir.display()).cyan());
+    say!("  Watching: {}", style(input.display()).cyan());
+    say!("  Output: {}", style(output_dir.display()).cyan());
     if let Some(preset) = &options.preset {
-        println!("  Preset: {}", style(preset).cyan());
+        say!("  Preset: {}", style(preset).cyan());
+    }
+    if let Some(profile) = &options.profile {
+        say!("  Profile: {}", style(profile).cyan());
     }
-    println!("  Debounce: {}ms", options.debounce);
-    println!();
-    println!("  Press {} to stop", style("Ctrl+C").yellow());
-    println!();
-    println!("{}", style("─".repeat(50)).dim());
-    println!();
+    say!("  Debounce: {}ms", options.debounce);
+    if !priority.is_empty() {
+        say!("  Priority patterns: {}", style(priority.len()).cyan());
+    }
+    say!();
+    say!("  Press {} to stop", style("Ctrl+C").yellow());
+    say!();
+    say!("{}", style("─".repeat(50)).dim());
+    say!();
 
     // Create a channel to receive the events
     let (tx, rx) = channel();
@@ -145,7 +174,7 @@ pub fn run(input: PathBuf, options: WatchOptions) -> Result<()> {
     // Initialize debouncer and stats
     let mut debouncer = Debouncer::new(options.debounce);
     let mut stats = WatchStats::new();
-    let mut cleanup_counter = 0u32;
+    let mut last_cleanup = Instant::now();
 
     // Set up Ctrl+C handler
     let running = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(true));
@@ -155,12 +184,31 @@ pub fn run(input: PathBuf, options: WatchOptions) -> Result<()> {
         r.store(false, std::sync::atomic::Ordering::SeqCst);
     }).ok(); // Ignore if already set
 
+    // Paths waiting to be processed; priority assets jump the queue
+    let mut queue: Vec<PathBuf> = Vec::new();
+
+    // Processing runs on the same pools as build, so a large model no longer holds up
+    // the image saves behind it
+    let (pool, audio_pool) = worker_pools(&options.pool)?;
+    let context = Arc::new(WatchContext {
+        input: input.clone(),
+        output_dir: output_dir.clone(),
+        preset: preset_config,
+        config,
+        virtual_texture,
+        virtual_textures,
+        tiles: Mutex::new(BuildCache::default()),
+    });
+    let (done_tx, done_rx) = channel::<JobResult>();
+    let mut workers = Workers::new(pool.current_num_threads(), audio_pool.current_num_threads());
+
     // Process events
     while running.load(std::sync::atomic::Ordering::SeqCst) {
-        // Use recv_timeout to allow checking the running flag
-        match rx.recv_timeout(Duration::from_millis(500)) {
+        // Poll quickly while jobs are running so their results are reported promptly
+        let timeout = if workers.is_idle() { 500 } else { 50 };
+        match rx.recv_timeout(Duration::from_millis(timeout)) {
             Ok(Ok(event)) => {
-                process_event(&event, &input, &output_dir, &preset_config, &mut debouncer, &mut stats);
+                queue_event(&event, &context, &mut queue, &mut debouncer, &mut stats);
             }
             Ok(Err(e)) => {
                 eprintln!(
@@ -172,10 +220,9 @@ pub fn run(input: PathBuf, options: WatchOptions) -> Result<()> {
             }
             Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
                 // Periodic cleanup
-                cleanup_counter += 1;
-                if cleanup_counter >= 120 { // Every ~60 seconds
+                if last_cleanup.elapsed() >= Duration::from_secs(60) {
                     debouncer.cleanup();
-                    cleanup_counter = 0;
+                    last_cleanup = Instant::now();
                 }
             }
             Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
@@ -183,7 +230,42 @@ pub fn run(input: PathBuf, options: WatchOptions) -> Result<()> {
                 break;
             }
         }
+
+        // Pick up everything else that changed in the meantime
+        while let Ok(Ok(event)) = rx.try_recv() {
+            queue_event(&event, &context, &mut queue, &mut debouncer, &mut stats);
+        }
+
+        while let Ok(done) = done_rx.try_recv() {
+            workers.finish(done, &mut stats);
+        }
+
+        // Fill free worker slots; a file already being processed waits for its previous run
+        while running.load(std::sync::atomic::Ordering::SeqCst) {
+            let available = |path: &PathBuf| workers.can_start(path);
+            let next = queue
+                .iter()
+                .position(|path| available(path) && priority.is_match(path.strip_prefix(&input).unwrap_or(path)))
+                .or_else(|| queue.iter().position(available));
+            let Some(next) = next else {
+                break;
+            };
+            let path = queue.remove(next);
+            let pool = if is_audio(&path) { &audio_pool } else { &pool };
+            workers.start(path, &context, pool, &done_tx);
+        }
+
+        workers.print_batch_summary();
+    }
+
+    // Let running jobs finish so their outputs aren't left half-written
+    while !workers.is_idle() {
+        match done_rx.recv() {
+            Ok(done) => workers.finish(done, &mut stats),
+            Err(_) => break,
+        }
     }
+    workers.print_batch_summary();
 
     // Print summary on exit
     stats.print_summary();
@@ -191,191 +273,273 @@ pub fn run(input: PathBuf, options: WatchOptions) -> Result<()> {
     Ok(())
 }
 
-fn process_event(
-    event: &Event,
-    input_dir: &Path,
-    output_dir: &Path,
-    preset: &PresetConfig,
-    debouncer: &mut Debouncer,
-    stats: &mut WatchStats,
-) {
-    // Only process create and modify events
-    match event.kind {
-        EventKind::Create(_) | EventKind::Modify(_) => {}
-        _ => return,
+/// Settings every job reads
+struct WatchContext {
+    input: PathBuf,
+    output_dir: PathBuf,
+    preset: PresetConfig,
+    config: Option<ProjectConfig>,
+    virtual_texture: VirtualTextureConfig,
+    virtual_textures: GlobSet,
+    /// Tile hashes of the virtual textures built this session, so a repaint only re-encodes
+    /// the tiles it touched
+    tiles: Mutex<BuildCache>,
+}
+
+impl WatchContext {
+    fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(&self.input).unwrap_or(path)
     }
 
-    for path in &event.paths {
-        // Skip directories
-        if !path.is_file() {
-            continue;
-        }
+    fn is_virtual_texture(&self, path: &Path) -> bool {
+        AssetType::from_path(path) == AssetType::Image && self.virtual_textures.is_match(self.relative(path))
+    }
 
-        // Check asset type
+    /// What a change to `path` rebuilds, as `build` would: the file itself, the directory
+    /// whose atlas packs it, or nothing if neither a processor nor a custom one takes it
+    fn job_for(&self, path: &Path) -> Option<PathBuf> {
         let asset_type = AssetType::from_path(path);
-        if asset_type == AssetType::Unknown {
-            continue;
+        let Some(cfg) = &self.config else {
+            return (asset_type != AssetType::Unknown).then(|| path.to_path_buf());
+        };
+        let relative = self.relative(path);
+        match asset_type {
+            AssetType::Unknown => cfg
+                .custom_processor(relative)
+                .is_ok_and(|p| p.is_some())
+                .then(|| path.to_path_buf()),
+            AssetType::Image if !self.is_virtual_texture(path) && atlas_rule(cfg, path, relative).is_some() => {
+                path.parent().map(Path::to_path_buf)
+            }
+            _ => Some(path.to_path_buf()),
         }
+    }
 
-        // Debounce check
-        if !debouncer.should_process(path) {
-            stats.skipped += 1;
-            continue;
+    /// Build one file with its rules, custom processor or virtual texture settings, as `build`
+    /// does; returns the output written and its size change
+    fn process_asset(&self, path: &Path) -> Result<(PathBuf, String)> {
+        let relative = self.relative(path);
+        let (resolved, custom) = self
+            .config
+            .as_ref()
+            .map(|cfg| -> Result<_> { Ok((cfg.resolve_rule(path, relative)?, cfg.custom_processor(relative)?)) })
+            .transpose()?
+            .unwrap_or_default();
+        let processed = build_asset(
+            path,
+            &self.output_dir.join(relative),
+            &self.preset,
+            &resolved.settings,
+            custom.map(|(_, processor)| processor),
+            self.is_virtual_texture(path).then_some((&self.virtual_texture, &self.tiles)),
+            false,
+        )?
+        .context("Nothing to build")?;
+        Ok((processed.output.clone(), describe(&processed)))
+    }
+
+    /// Repack the atlas of a directory after one of its sprites changed
+    fn process_atlas(&self, dir: &Path) -> Result<(PathBuf, String)> {
+        let cfg = self.config.as_ref().context("Atlas sprites need a config")?;
+        let sprites: Vec<(RuleConfig, PathBuf)> = WalkDir::new(dir)
+            .max_depth(1)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| e.into_path())
+            .filter(|p| AssetType::from_path(p) == AssetType::Image && !self.is_virtual_texture(p))
+            .filter_map(|p| Some((atlas_rule(cfg, &p, self.relative(&p))?, p)))
+            .collect();
+        anyhow::ensure!(!sprites.is_empty(), "No atlas sprites left in {}", dir.display());
+
+        let metadata = atlas_metadata_path(&self.output_dir, self.relative(dir));
+        let (processed, _) = process_atlas_group(dir, &sprites, &metadata, &self.preset)?;
+        Ok((
+            processed.output.clone(),
+            format!("{} ({} sprites)", describe(&processed), sprites.len()),
+        ))
+    }
+}
+
+/// Outcome of one asset processed on a worker
+struct JobResult {
+    /// Position in the current batch
+    id: usize,
+    path: PathBuf,
+    /// Output written and its size change
+    result: Result<(PathBuf, String)>,
+    elapsed: Duration,
+}
+
+/// Bounded dispatch to the worker pools, and the batch of jobs since the last idle moment
+struct Workers {
+    limit: usize,
+    audio_limit: usize,
+    running: Vec<PathBuf>,
+    /// Per job of the batch, in start order: file name and whether it succeeded (None = running)
+    batch: Vec<(String, Option<bool>)>,
+    batch_start: Instant,
+}
+
+impl Workers {
+    fn new(limit: usize, audio_limit: usize) -> Self {
+        Self {
+            limit: limit.max(1),
+            audio_limit: audio_limit.max(1),
+            running: Vec::new(),
+            batch: Vec::new(),
+            batch_start: Instant::now(),
         }
+    }
 
-        // Calculate output path
-        let relative = path.strip_prefix(input_dir).unwrap_or(path);
-        let output_path = output_dir.join(relative);
+    fn is_idle(&self) -> bool {
+        self.running.is_empty()
+    }
 
-        // Print processing message
-        let now = chrono_lite_time();
-        println!(
+    /// Whether a path can start now: its pool has a free slot and it isn't already running
+    fn can_start(&self, path: &Path) -> bool {
+        let audio = is_audio(path);
+        let busy = self.running.iter().filter(|p| is_audio(p) == audio).count();
+        let limit = if audio { self.audio_limit } else { self.limit };
+        busy < limit && !self.running.iter().any(|p| p == path)
+    }
+
+    fn start(&mut self, path: PathBuf, context: &Arc<WatchContext>, pool: &rayon::ThreadPool, done: &Sender<JobResult>) {
+        if self.batch.is_empty() {
+            self.batch_start = Instant::now();
+        }
+        let id = self.batch.len();
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+
+        say!(
             "{} [{}] {}",
             style("→").blue(),
-            style(&now).dim(),
-            path.file_name().unwrap_or_default().to_string_lossy()
+            style(chrono_lite_time()).dim(),
+            name
         );
 
-        // Process the asset
-        let start = Instant::now();
-        match process_asset(path, &output_path, preset) {
-            Ok(size_info) => {
-                let elapsed = start.elapsed();
+        self.batch.push((name, None));
+        self.running.push(path.clone());
+
+        let context = context.clone();
+        let done = done.clone();
+        pool.spawn(move || {
+            let start = Instant::now();
+            let result = if path.is_dir() {
+                context.process_atlas(&path)
+            } else {
+                context.process_asset(&path)
+            };
+            let _ = done.send(JobResult { id, path, result, elapsed: start.elapsed() });
+        });
+    }
+
+    fn finish(&mut self, done: JobResult, stats: &mut WatchStats) {
+        self.running.retain(|p| p != &done.path);
+        let succeeded = done.result.is_ok();
+        if let Some((_, outcome)) = self.batch.get_mut(done.id) {
+            *outcome = Some(succeeded);
+        }
+
+        match done.result {
+            Ok((output, size_info)) => {
                 stats.processed += 1;
-                println!(
+                say!(
                     "  {} {} ({}, {:.0}ms)",
                     style("✓").green(),
-                    output_path.file_name().unwrap_or_default().to_string_lossy(),
+                    output.file_name().unwrap_or_default().to_string_lossy(),
                     size_info,
-                    elapsed.as_secs_f64() * 1000.0
+                    done.elapsed.as_secs_f64() * 1000.0
                 );
             }
             Err(e) => {
                 stats.errors += 1;
                 eprintln!(
-                    "  {} Error: {}",
+                    "  {} {}: {}",
                     style("✗").red(),
+                    done.path.file_name().unwrap_or_default().to_string_lossy(),
                     e
                 );
             }
         }
     }
+
+    /// Once the workers are idle, list a batch of several jobs in the order they started
+    fn print_batch_summary(&mut self) {
+        if !self.is_idle() || self.batch.is_empty() {
+            return;
+        }
+        let batch = std::mem::take(&mut self.batch);
+        if batch.len() < 2 {
+            return;
+        }
+
+        let failed = batch.iter().filter(|(_, outcome)| *outcome != Some(true)).count();
+        say!(
+            "{} Batch: {} assets in {:.1}s{}",
+            style("■").blue(),
+            batch.len(),
+            self.batch_start.elapsed().as_secs_f64(),
+            if failed > 0 { format!(", {} failed", style(failed).red()) } else { String::new() }
+        );
+        for (name, outcome) in &batch {
+            let mark = match outcome {
+                Some(true) => style("✓").green(),
+                _ => style("✗").red(),
+            };
+            say!("  {} {}", mark, name);
+        }
+        say!();
+    }
 }
 
-fn process_asset(input: &Path, output: &Path, preset: &PresetConfig) -> Result<String> {
-    let asset_type = AssetType::from_path(input);
+fn is_audio(path: &Path) -> bool {
+    AssetType::from_path(path) == AssetType::Audio
+}
 
-    // Create output directory
-    if let Some(parent) = output.parent() {
-        std::fs::create_dir_all(parent)?;
+/// Queue the jobs of changed asset files, skipping duplicates and debounced events
+fn queue_event(
+    event: &Event,
+    context: &WatchContext,
+    queue: &mut Vec<PathBuf>,
+    debouncer: &mut Debouncer,
+    stats: &mut WatchStats,
+) {
+    // Only process create and modify events
+    match event.kind {
+        EventKind::Create(_) | EventKind::Modify(_) => {}
+        _ => return,
     }
 
-    let original_size = std::fs::metadata(input)?.len();
-
-    match asset_type {
-        AssetType::Image => {
-            let config = ImageProcessorConfig {
-                output_format: preset.texture_format.as_ref().and_then(|f| {
-                    match f.as_str() {
-                        "png" => Some(crate::cli::OutputFormat::Png),
-                        "jpeg" | "jpg" => Some(crate::cli::OutputFormat::Jpeg),
-                        "webp" => Some(crate::cli::OutputFormat::Webp),
-                        "ktx2" => Some(crate::cli::OutputFormat::Ktx2),
-                        _ => None,
-                    }
-                }),
-                quality: crate::cli::QualityPreset::Balanced,
-                max_size: preset.texture_max_size,
-                generate_mipmaps: preset.generate_mipmaps.unwrap_or(false),
-            };
-            let stats = process_image(input, output, &config)?;
-            Ok(format_size_change(stats.original_size, stats.output_size))
+    for path in &event.paths {
+        // Skip directories
+        if !path.is_file() {
+            continue;
         }
-        AssetType::Audio => {
-            let output_format = preset.audio_format.as_ref()
-                .map(|f| match f.as_str() {
-                    "ogg" => AudioFormat::Ogg,
-                    "wav" => AudioFormat::Wav,
-                    _ => AudioFormat::Ogg,
-                })
-                .unwrap_or(AudioFormat::Ogg);
-
-            let quality = preset.audio_quality
-                .map(|q| q as f32 / 10.0)
-                .unwrap_or(0.5);
-
-            let audio_config = AudioConfig {
-                output_format,
-                quality,
-                sample_rate: None,
-                normalize: false,
-            };
 
-            // Adjust output extension
-            let output = match output_format {
-                AudioFormat::Ogg => output.with_extension("ogg"),
-                AudioFormat::Wav => output.with_extension("wav"),
-            };
+        let Some(job) = context.job_for(path) else {
+            continue;
+        };
 
-            let stats = process_audio(input, &output, &audio_config)?;
-            Ok(format_size_change(stats.original_size, stats.output_size))
-        }
-        AssetType::Model => {
-            let ext = input.extension()
-                .and_then(|e| e.to_str())
-                .map(|e| e.to_lowercase());
-
-            match ext.as_deref() {
-                Some("gltf" | "glb") => {
-                    let model_config = ModelConfig::default();
-                    let output = output.with_extension("glb");
-                    let stats = process_model(input, &output, &model_config)?;
-                    Ok(format_size_change(stats.original_size, stats.output_size))
-                }
-                _ => {
-                    // Copy unsupported model formats
-                    std::fs::copy(input, output)?;
-                    let output_size = std::fs::metadata(output)?.len();
-                    Ok(format_size_change(original_size, output_size))
-                }
-            }
-        }
-        AssetType::Unknown => {
-            anyhow::bail!("Unknown asset type");
+        // Debounce check; several sprites saved together repack their atlas once
+        if !debouncer.should_process(path) || queue.contains(&job) {
+            stats.skipped += 1;
+            continue;
         }
+
+        queue.push(job);
     }
 }
 
-fn get_preset_config(preset: &Option<PlatformPreset>) -> PresetConfig {
-    match preset {
-        Some(PlatformPreset::Mobile) => PresetConfig {
-            texture_max_size: Some(1024),
-            texture_format: Some("png".to_string()),
-            texture_quality: Some(75),
-            audio_format: Some("ogg".to_string()),
-            audio_quality: Some(6),
-            compress_textures: Some(true),
-            generate_mipmaps: Some(true),
-        },
-        Some(PlatformPreset::Desktop) => PresetConfig {
-            texture_max_size: Some(4096),
-            texture_format: Some("png".to_string()),
-            texture_quality: Some(90),
-            audio_format: Some("wav".to_string()),
-            audio_quality: Some(10),
-            compress_textures: Some(false),
-            generate_mipmaps: Some(true),
-        },
-        Some(PlatformPreset::Web) => PresetConfig {
-            texture_max_size: Some(2048),
-            texture_format: Some("webp".to_string()),
-            texture_quality: Some(80),
-            audio_format: Some("ogg".to_string()),
-            audio_quality: Some(7),
-            compress_textures: Some(true),
-            generate_mipmaps: Some(false),
-        },
-        None => PresetConfig::default(),
+/// Size change of a build, and how many files it wrote when a rule asks for several
+fn describe(processed: &ProcessedFile) -> String {
+    let size = format_size_change(processed.original_size, processed.output_size);
+    if !processed.variants.is_empty() {
+        format!("{} ({} variants)", size, processed.variants.len())
+    } else if !processed.layers.is_empty() {
+        format!("{} ({} layers)", size, processed.layers.len())
+    } else {
+        size
     }
 }
 
@@ -399,7 +563,7 @@ fn format_size_change(original: u64, output: u64) -> String {
             -reduction
         )
     } else {
-        format!("{}", format_size(output))
+        format_size(output)
     }
 }
 
@@ -0,0 +1,15 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Problems with the configuration rather than the assets, reported with their own exit code
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to read config file: {}", .0.display())]
+    Read(PathBuf),
+
+    #[error("Failed to parse config file: {}", .0.display())]
+    Parse(PathBuf),
+
+    #[error("{0}")]
+    Invalid(String),
+}
@@ -1,15 +1,28 @@
 use anyhow::{Context, Result};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use super::Config;
+use super::{migrate_config, Config, ConfigError};
 
 /// Load configuration from a TOML file
 pub fn load_config(path: &Path) -> Result<Config> {
     let content = std::fs::read_to_string(path)
-        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+        .with_context(|| ConfigError::Read(path.to_path_buf()))?;
 
     let config: Config = toml::from_str(&content)
-        .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+        .with_context(|| ConfigError::Parse(path.to_path_buf()))?;
+    config.check_patterns()?;
+
+    // Deprecated keys still load; point at `migrate` so they don't linger
+    if let Ok((_, changes)) = migrate_config(&content) {
+        for change in changes {
+            tracing::warn!(
+                "{}: `{}` is deprecated, use `{}` (run `asset-forge migrate`)",
+                path.display(),
+                change.from,
+                change.to
+            );
+        }
+    }
 
     Ok(config)
 }
@@ -17,6 +30,14 @@ pub fn load_config(path: &Path) -> Result<Config> {
 /// Find and load configuration file
 /// Searches in current directory and parent directories for asset-forge.toml
 pub fn find_and_load_config() -> Result<Option<Config>> {
+    match find_config_path()? {
+        Some(path) => Ok(Some(load_config(&path)?)),
+        None => Ok(None),
+    }
+}
+
+/// Path of the nearest asset-forge.toml in the current or a parent directory
+pub fn find_config_path() -> Result<Option<PathBuf>> {
     let config_names = ["asset-forge.toml", ".asset-forge.toml"];
 
     let mut current_dir = std::env::current_dir()?;
@@ -25,8 +46,7 @@ pub fn find_and_load_config() -> Result<Option<Config>> {
         for name in &config_names {
             let config_path = current_dir.join(name);
             if config_path.exists() {
-                let config = load_config(&config_path)?;
-                return Ok(Some(config));
+                return Ok(Some(config_path));
             }
         }
 
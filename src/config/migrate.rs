@@ -0,0 +1,150 @@
+use anyhow::{Context, Result};
+use toml_edit::{DocumentMut, Item, Table, TableLike};
+
+/// A config key that was renamed or moved to another section
+///
+/// Paths are dotted keys; a `*` segment matches any key at that level and is carried over
+/// to the same position in `to` (e.g. every rule pattern).
+pub struct KeyMigration {
+    pub from: &'static str,
+    pub to: &'static str,
+    pub reason: &'static str,
+}
+
+/// Every deprecated key, oldest first
+pub const CONFIG_MIGRATIONS: &[KeyMigration] = &[KeyMigration {
+    from: "rules.*.mipmap",
+    to: "rules.*.generate_mipmaps",
+    reason: "renamed to match the preset key",
+}];
+
+/// One key rewritten by a migration
+#[derive(Debug, Clone)]
+pub struct ConfigChange {
+    pub from: String,
+    pub to: String,
+    pub reason: &'static str,
+    /// The new key was already set, so the deprecated one was dropped
+    pub dropped: bool,
+}
+
+/// Rewrite deprecated keys in an asset-forge.toml, keeping comments and formatting
+///
+/// Returns the migrated document and what changed; the text is unchanged if nothing did.
+pub fn migrate_config(content: &str) -> Result<(String, Vec<ConfigChange>)> {
+    let mut doc: DocumentMut = content.parse().context("Failed to parse config")?;
+    let mut changes = Vec::new();
+
+    for migration in CONFIG_MIGRATIONS {
+        let from: Vec<&str> = migration.from.split('.').collect();
+        let to: Vec<&str> = migration.to.split('.').collect();
+
+        let mut matches = Vec::new();
+        find_keys(doc.as_table(), &from, Vec::new(), &mut matches);
+        for path in matches {
+            // Wildcards in `to` take the keys they matched in `from`
+            let mut captured = path.iter().zip(&from).filter(|(_, s)| **s == "*").map(|(k, _)| k);
+            let target: Vec<String> = to
+                .iter()
+                .map(|s| if *s == "*" { captured.next().cloned().unwrap_or_default() } else { s.to_string() })
+                .collect();
+
+            let (key, parent) = path.split_last().context("Empty migration path")?;
+            let item = table_mut(doc.as_table_mut(), parent, false)
+                .and_then(|table| table.remove(key))
+                .context("Migrated key disappeared")?;
+
+            let (new_key, new_parent) = target.split_last().context("Empty migration path")?;
+            let table = table_mut(doc.as_table_mut(), new_parent, true)
+                .with_context(|| format!("Cannot move {} into {}", migration.from, migration.to))?;
+            let dropped = table.contains_key(new_key);
+            if !dropped {
+                table.insert(new_key, item);
+            }
+
+            changes.push(ConfigChange {
+                from: dotted(&path),
+                to: dotted(&target),
+                reason: migration.reason,
+                dropped,
+            });
+        }
+    }
+
+    if changes.is_empty() {
+        return Ok((content.to_string(), changes));
+    }
+    Ok((doc.to_string(), changes))
+}
+
+/// Concrete paths matching `segments` below `table`
+fn find_keys(table: &dyn TableLike, segments: &[&str], prefix: Vec<String>, out: &mut Vec<Vec<String>>) {
+    let Some((segment, rest)) = segments.split_first() else {
+        return;
+    };
+    for (key, item) in table.iter() {
+        if *segment != "*" && key != *segment {
+            continue;
+        }
+        let mut path = prefix.clone();
+        path.push(key.to_string());
+        if rest.is_empty() {
+            out.push(path);
+        } else if let Some(child) = item.as_table_like() {
+            find_keys(child, rest, path, out);
+        }
+    }
+}
+
+/// The table at `path`, creating missing tables when `create` is set
+fn table_mut<'a>(table: &'a mut dyn TableLike, path: &[String], create: bool) -> Option<&'a mut dyn TableLike> {
+    let Some((key, rest)) = path.split_first() else {
+        return Some(table);
+    };
+    if create && !table.contains_key(key) {
+        // Becomes an inline table when the parent is one
+        table.insert(key, Item::Table(Table::new()));
+    }
+    table_mut(table.get_mut(key)?.as_table_like_mut()?, rest, create)
+}
+
+fn dotted(path: &[String]) -> String {
+    path.iter()
+        .map(|key| {
+            if key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                key.clone()
+            } else {
+                format!("{:?}", key)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_mipmap_renamed_keeping_comments() {
+        let config = r#"[project]
+name = "game"
+
+[rules]
+# Terrain textures
+"textures/*.png" = { format = "ktx2", mipmap = true }
+"ui/*.png" = { mipmap = false, generate_mipmaps = true }
+"#;
+        let (migrated, changes) = migrate_config(config).unwrap();
+
+        assert_eq!(changes.len(), 2);
+        assert!(migrated.contains("# Terrain textures"));
+        assert!(migrated.contains(r#""textures/*.png" = { format = "ktx2", generate_mipmaps = true }"#));
+        assert!(migrated.contains(r#""ui/*.png" = { generate_mipmaps = true }"#));
+        assert!(changes.iter().any(|c| c.dropped && c.from == r#"rules."ui/*.png".mipmap"#));
+
+        let (again, changes) = migrate_config(&migrated).unwrap();
+        assert!(changes.is_empty());
+        assert_eq!(again, migrated);
+    }
+}
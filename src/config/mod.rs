@@ -1,5 +1,11 @@
 mod schema;
 mod loader;
+mod rules;
+mod migrate;
+mod error;
 
 pub use schema::*;
 pub use loader::*;
+pub use rules::*;
+pub use migrate::*;
+pub use error::*;
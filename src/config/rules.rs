@@ -0,0 +1,127 @@
+use anyhow::{Context, Result};
+use globset::Glob;
+use std::path::{Path, PathBuf};
+
+use super::{Config, ConfigError, CustomProcessorConfig, RuleConfig};
+
+/// Suffix of per-asset sidecar files (`hero.png` → `hero.png.meta.toml`)
+pub const SIDECAR_SUFFIX: &str = ".meta.toml";
+
+/// Rules, sidecar and merged settings that apply to one asset
+#[derive(Debug, Clone, Default)]
+pub struct ResolvedRule {
+    /// Matching rule patterns, lowest precedence first
+    pub patterns: Vec<String>,
+    /// Sidecar file, if the asset has one
+    pub sidecar: Option<PathBuf>,
+    /// Settings after applying every matching rule and then the sidecar
+    pub settings: RuleConfig,
+}
+
+impl RuleConfig {
+    /// Override fields with those set in `other`
+    pub fn merge(&mut self, other: &RuleConfig) {
+        macro_rules! merge_fields {
+            ($($field:ident),*) => {
+                $(if other.$field.is_some() {
+                    self.$field = other.$field.clone();
+                })*
+            };
+        }
+        merge_fields!(
+            format, atlas, trim, generate_mipmaps, mip_filter, alpha_coverage, draco, meshopt, normalize, sample_rate, quality, max_size, output,
+            frame_duration, texture_type, renormalize, quantize, dither,
+            dither_strength, dither_pattern, premultiply_alpha, colorspace, tags, variants, variant_name,
+            nine_slice, slice_borders, pivot, psd_layers, target_ssim, max_output_size, bit_depth,
+            channels, loop_start, loop_end, fade_in, fade_out, adpcm_block_size, post_process
+        );
+    }
+}
+
+impl Config {
+    /// Fail with a [`ConfigError`] if any rule or processor pattern is not a valid glob
+    pub fn check_patterns(&self) -> Result<()> {
+        self.matching_rules(Path::new(""))?;
+        self.custom_processor(Path::new(""))?;
+        Ok(())
+    }
+
+    /// Rules whose pattern matches `relative` (a path relative to the source directory),
+    /// ordered from lowest to highest precedence
+    ///
+    /// More specific patterns (more literal characters) take precedence; ties are broken
+    /// by pattern text so the order never depends on table iteration.
+    pub fn matching_rules(&self, relative: &Path) -> Result<Vec<(&str, &RuleConfig)>> {
+        let mut matches = Vec::new();
+        for (pattern, rule) in &self.rules {
+            let glob = Glob::new(pattern)
+                .with_context(|| ConfigError::Invalid(format!("Invalid rule pattern: {}", pattern)))?
+                .compile_matcher();
+            if glob.is_match(relative) {
+                matches.push((pattern.as_str(), rule));
+            }
+        }
+        matches.sort_by_key(|(pattern, _)| (specificity(pattern), *pattern));
+        Ok(matches)
+    }
+
+    /// The `[processors.custom]` entry for `relative`, the most specific pattern winning as
+    /// for rules
+    pub fn custom_processor(&self, relative: &Path) -> Result<Option<(&str, &CustomProcessorConfig)>> {
+        let mut matches = Vec::new();
+        for (pattern, processor) in &self.processors.custom {
+            let glob = Glob::new(pattern)
+                .with_context(|| ConfigError::Invalid(format!("Invalid processor pattern: {}", pattern)))?
+                .compile_matcher();
+            if glob.is_match(relative) {
+                matches.push((pattern.as_str(), processor));
+            }
+        }
+        Ok(matches.into_iter().max_by_key(|(pattern, _)| (specificity(pattern), *pattern)))
+    }
+
+    /// Resolve the rule settings for an asset; `asset` is the file on disk, used to find its sidecar
+    pub fn resolve_rule(&self, asset: &Path, relative: &Path) -> Result<ResolvedRule> {
+        let mut resolved = ResolvedRule::default();
+        for (pattern, rule) in self.matching_rules(relative)? {
+            resolved.patterns.push(pattern.to_string());
+            resolved.settings.merge(rule);
+        }
+
+        if let Some(sidecar) = load_sidecar(asset)? {
+            resolved.settings.merge(&sidecar);
+            resolved.sidecar = Some(sidecar_path(asset));
+        }
+
+        Ok(resolved)
+    }
+}
+
+/// Sidecar path for an asset
+pub fn sidecar_path(asset: &Path) -> PathBuf {
+    let mut path = asset.as_os_str().to_owned();
+    path.push(SIDECAR_SUFFIX);
+    PathBuf::from(path)
+}
+
+/// Load the sidecar settings for an asset, if it has one
+pub fn load_sidecar(asset: &Path) -> Result<Option<RuleConfig>> {
+    let path = sidecar_path(asset);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read sidecar: {}", path.display()))?;
+    let rule = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse sidecar: {}", path.display()))?;
+    Ok(Some(rule))
+}
+
+/// Number of literal (non-wildcard) characters in a glob pattern
+fn specificity(pattern: &str) -> usize {
+    pattern
+        .chars()
+        .filter(|c| !matches!(c, '*' | '?' | '[' | ']' | '{' | '}' | ','))
+        .count()
+}
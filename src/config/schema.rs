@@ -1,7 +1,14 @@
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 
+use crate::cli::{parse_byte_size, parse_duration_ms, OutputFormat, PlatformPreset, QualityPreset};
+use crate::processors::{AudioFormat, MusicPolicy, ShortSfxPolicy};
+
+use super::ConfigError;
+
 /// Root configuration structure for asset-forge.toml
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Config {
@@ -20,6 +27,34 @@ pub struct Config {
     /// Cache configuration
     #[serde(default)]
     pub cache: CacheConfig,
+
+    /// Assets processed ahead of everything else
+    #[serde(default)]
+    pub priority: PriorityConfig,
+
+    /// Asset audits
+    #[serde(default)]
+    pub audit: AuditConfig,
+
+    /// Textures built as virtual texture tile pyramids
+    #[serde(default)]
+    pub virtual_texture: VirtualTextureConfig,
+
+    /// Single-file bundle of the build output
+    #[serde(default)]
+    pub bundle: BundleConfig,
+
+    /// Build profiles (`build --profile dev`): preset fields layered over the selected preset
+    #[serde(default)]
+    pub profiles: HashMap<String, PresetConfig>,
+
+    /// Commands run before and after `build`
+    #[serde(default)]
+    pub hooks: HooksConfig,
+
+    /// External commands that build matching files instead of the built-in processors
+    #[serde(default)]
+    pub processors: ProcessorsConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -69,10 +104,54 @@ pub struct PresetConfig {
     #[serde(default)]
     pub texture_format: Option<String>,
 
+    /// GPU block format KTX2 textures are transcoded to on the target (astc, bc7, etc2)
+    #[serde(default)]
+    pub gpu_format: Option<String>,
+
+    /// Zstandard supercompression level for KTX2 textures (1-22, unset = none)
+    #[serde(default)]
+    pub ktx2_zstd_level: Option<i32>,
+
+    /// ASTC block size (4x4, 6x6, 8x8) for astc textures; with ktx2 it stores native ASTC blocks
+    #[serde(default)]
+    pub astc_block: Option<String>,
+
+    /// How HDR (.hdr/.exr) textures are stored: tonemap (default), rgbe, half or bc6h
+    #[serde(default)]
+    pub hdr_encoding: Option<String>,
+
+    /// Tonemapping curve for HDR textures written to 8-bit formats: aces (default), reinhard, clamp
+    #[serde(default)]
+    pub tonemap: Option<String>,
+
+    /// Exposure adjustment in stops applied before tonemapping
+    #[serde(default)]
+    pub exposure: Option<f32>,
+
+    /// KTX2 encoding of normal maps: uastc (default, X in RGB and Y in alpha) or bc5
+    #[serde(default)]
+    pub normal_encoding: Option<String>,
+
     /// Texture quality (0-100)
     #[serde(default)]
     pub texture_quality: Option<u8>,
 
+    /// Reduce PNG textures to a 256-color palette before oxipng (lossy, pngquant-style)
+    #[serde(default)]
+    pub png_quantize: Option<bool>,
+
+    /// SSIM that JPEG and JPEG XL textures are encoded to instead of the quality preset
+    #[serde(default)]
+    pub target_ssim: Option<f64>,
+
+    /// Multiply texture color by alpha before encoding
+    #[serde(default)]
+    pub premultiply_alpha: Option<bool>,
+
+    /// Strip EXIF, XMP and text metadata from PNG, JPEG and WebP textures (on for built-in presets)
+    #[serde(default)]
+    pub strip_metadata: Option<bool>,
+
     /// Audio output format
     #[serde(default)]
     pub audio_format: Option<String>,
@@ -81,6 +160,47 @@ pub struct PresetConfig {
     #[serde(default)]
     pub audio_quality: Option<u8>,
 
+    /// WAV sample bits: 8, 16 (default), 24, or 32 for float
+    #[serde(default)]
+    pub audio_bit_depth: Option<u8>,
+
+    /// Highest audio sample rate; sources above it are resampled down (e.g. 22050 for mobile)
+    #[serde(default)]
+    pub audio_sample_rate: Option<u32>,
+
+    /// ADPCM block length in bytes (default: 256 per channel at 11 kHz, scaled with the rate)
+    #[serde(default)]
+    pub audio_adpcm_block_size: Option<u32>,
+
+    /// Clips at or below this duration (seconds) use `short_sfx_format`
+    #[serde(default)]
+    pub short_sfx_max_secs: Option<f64>,
+
+    /// Audio output format for short clips (default: wav)
+    #[serde(default)]
+    pub short_sfx_format: Option<String>,
+
+    /// Clips longer than this (e.g. `"15s"`) are streamed music in `music_format`, shorter
+    /// ones in-memory sound effects in `sfx_format`
+    #[serde(default)]
+    pub music_threshold: Option<String>,
+
+    /// Audio output format for music under `music_threshold` (default: ogg)
+    #[serde(default)]
+    pub music_format: Option<String>,
+
+    /// Audio output format for sound effects under `music_threshold` (default: wav)
+    #[serde(default)]
+    pub sfx_format: Option<String>,
+
+    /// `audio_sample_rate` for clips over `music_threshold`
+    #[serde(default)]
+    pub music_sample_rate: Option<u32>,
+
+    /// `audio_sample_rate` for sound effects: clips under `music_threshold` or `short_sfx_max_secs`
+    #[serde(default)]
+    pub sfx_sample_rate: Option<u32>,
+
     /// Enable texture compression
     #[serde(default)]
     pub compress_textures: Option<bool>,
@@ -88,8 +208,207 @@ pub struct PresetConfig {
     /// Generate mipmaps
     #[serde(default)]
     pub generate_mipmaps: Option<bool>,
+
+    /// Mip downsampling filter: kaiser (default), lanczos, triangle or box
+    #[serde(default)]
+    pub mip_filter: Option<String>,
+
+    /// Render `<model>.thumb.png` previews of this size next to processed models
+    #[serde(default)]
+    pub model_thumbnail_size: Option<u32>,
+
+    /// Zstandard level for bundle entries (1-22, unset = stored as-is)
+    #[serde(default)]
+    pub bundle_zstd_level: Option<i32>,
+
+    /// Zstandard level for large loose outputs, stored as `<output>.zst` (unset = none)
+    #[serde(default)]
+    pub output_zstd_level: Option<i32>,
+
+    /// Smallest output `output_zstd_level` compresses, e.g. `"256KB"` (default: 1MB)
+    #[serde(default)]
+    pub output_zstd_min_size: Option<String>,
+
+    /// Encoder effort for textures and audio resampling: fast, balanced (default), high or ultra
+    #[serde(default)]
+    pub encoder_quality: Option<String>,
+
+    /// Write PNG where `texture_format` is KTX2, skipping the slow Basis encode
+    #[serde(default)]
+    pub skip_basis: Option<bool>,
+}
+
+impl PresetConfig {
+    /// Built-in defaults for a platform preset
+    ///
+    /// Console presets only name the target formats; nothing here depends on a platform SDK.
+    pub fn builtin(preset: PlatformPreset) -> Self {
+        match preset {
+            PlatformPreset::Mobile => PresetConfig {
+                texture_max_size: Some(1024),
+                texture_format: Some("png".to_string()), // Use PNG for now, KTX2 in Phase 2
+                texture_quality: Some(75),
+                audio_format: Some("ogg".to_string()),
+                audio_quality: Some(6),
+                compress_textures: Some(true),
+                generate_mipmaps: Some(true),
+                strip_metadata: Some(true),
+                ..Default::default()
+            },
+            PlatformPreset::Desktop => PresetConfig {
+                texture_max_size: Some(4096),
+                texture_format: Some("png".to_string()),
+                texture_quality: Some(90),
+                audio_format: Some("flac".to_string()),
+                audio_quality: Some(10),
+                compress_textures: Some(false),
+                generate_mipmaps: Some(true),
+                strip_metadata: Some(true),
+                ..Default::default()
+            },
+            PlatformPreset::Web => PresetConfig {
+                texture_max_size: Some(2048),
+                texture_format: Some("webp".to_string()),
+                texture_quality: Some(80),
+                audio_format: Some("ogg".to_string()),
+                audio_quality: Some(7),
+                compress_textures: Some(true),
+                generate_mipmaps: Some(false),
+                strip_metadata: Some(true),
+                ..Default::default()
+            },
+            PlatformPreset::Switch => PresetConfig {
+                texture_max_size: Some(2048),
+                texture_format: Some("ktx2".to_string()),
+                gpu_format: Some("astc".to_string()),
+                texture_quality: Some(80),
+                audio_format: Some("ogg".to_string()),
+                audio_quality: Some(6),
+                compress_textures: Some(true),
+                generate_mipmaps: Some(true),
+                strip_metadata: Some(true),
+                ..Default::default()
+            },
+            PlatformPreset::Playstation | PlatformPreset::Xbox => PresetConfig {
+                texture_max_size: Some(4096),
+                texture_format: Some("ktx2".to_string()),
+                gpu_format: Some("bc7".to_string()),
+                texture_quality: Some(90),
+                audio_format: Some("ogg".to_string()),
+                audio_quality: Some(8),
+                compress_textures: Some(true),
+                generate_mipmaps: Some(true),
+                strip_metadata: Some(true),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Built-in build profiles; a `[profiles.<name>]` table of the same name replaces one
+    ///
+    /// `release` changes nothing, so the preset alone is what ships.
+    pub fn builtin_profile(name: &str) -> Option<Self> {
+        match name {
+            "dev" => Some(PresetConfig {
+                encoder_quality: Some("fast".to_string()),
+                skip_basis: Some(true),
+                ..Default::default()
+            }),
+            "release" => Some(PresetConfig::default()),
+            _ => None,
+        }
+    }
+
+    /// Override fields with those set in `other`
+    pub fn merge(&mut self, other: &PresetConfig) {
+        macro_rules! merge_fields {
+            ($($field:ident),*) => {
+                $(if other.$field.is_some() {
+                    self.$field = other.$field.clone();
+                })*
+            };
+        }
+        merge_fields!(
+            texture_max_size, texture_format, gpu_format, ktx2_zstd_level, astc_block, hdr_encoding, tonemap,
+            exposure, normal_encoding, texture_quality, png_quantize, target_ssim, premultiply_alpha,
+            strip_metadata, audio_format, audio_quality, audio_bit_depth, audio_sample_rate,
+            audio_adpcm_block_size, short_sfx_max_secs, short_sfx_format, music_threshold, music_format,
+            sfx_format, music_sample_rate, sfx_sample_rate, compress_textures, generate_mipmaps, mip_filter,
+            model_thumbnail_size, bundle_zstd_level, output_zstd_level, output_zstd_min_size,
+            encoder_quality, skip_basis
+        );
+    }
+
+    /// Swap a KTX2 `texture_format` for PNG when `skip_basis` is set
+    pub fn apply_skip_basis(&mut self) {
+        let ktx2 = matches!(self.texture_format.as_deref().and_then(OutputFormat::from_name), Some(OutputFormat::Ktx2));
+        if ktx2 && self.skip_basis == Some(true) {
+            self.texture_format = Some("png".to_string());
+        }
+    }
+
+    /// Encoder effort, `Balanced` unless `encoder_quality` names another
+    pub fn encoder_quality(&self) -> QualityPreset {
+        self.encoder_quality
+            .as_deref()
+            .and_then(QualityPreset::from_name)
+            .unwrap_or_default()
+    }
+
+    /// Duration-based audio format override, if the preset defines one
+    pub fn short_sfx_policy(&self) -> Option<ShortSfxPolicy> {
+        let max_secs = self.short_sfx_max_secs?;
+        let format = self
+            .short_sfx_format
+            .as_deref()
+            .and_then(AudioFormat::from_name)
+            .unwrap_or(AudioFormat::Wav);
+
+        Some(ShortSfxPolicy { max_secs, format })
+    }
+
+    /// Highest audio sample rate for a clip the duration policies sorted into `format_policy`
+    pub fn audio_sample_rate_for(&self, format_policy: Option<&str>) -> Option<u32> {
+        let rate = match format_policy {
+            Some("music") => self.music_sample_rate,
+            Some("sfx" | "short_sfx") => self.sfx_sample_rate,
+            _ => None,
+        };
+        rate.or(self.audio_sample_rate)
+    }
+
+    /// Music/sound effect split by duration, if the preset sets `music_threshold`
+    pub fn music_policy(&self) -> Result<Option<MusicPolicy>> {
+        let Some(threshold) = self.music_threshold.as_deref() else {
+            return Ok(None);
+        };
+        let threshold_ms = parse_duration_ms(threshold).map_err(|e| anyhow::anyhow!("music_threshold: {}", e))?;
+        let format = |name: &Option<String>, default| name.as_deref().and_then(AudioFormat::from_name).unwrap_or(default);
+
+        Ok(Some(MusicPolicy {
+            threshold_secs: threshold_ms as f64 / 1000.0,
+            music_format: format(&self.music_format, AudioFormat::Ogg),
+            sfx_format: format(&self.sfx_format, AudioFormat::Wav),
+        }))
+    }
+
+    /// Level and minimum size for compressing loose outputs, if the preset sets `output_zstd_level`
+    pub fn output_zstd(&self) -> Result<Option<(i32, u64)>> {
+        let Some(level) = self.output_zstd_level else {
+            return Ok(None);
+        };
+        let min_size = match self.output_zstd_min_size.as_deref() {
+            Some(size) => parse_byte_size(size)
+                .map_err(|e| ConfigError::Invalid(format!("output_zstd_min_size: {}", e)))?,
+            None => DEFAULT_ZSTD_MIN_SIZE,
+        };
+        Ok(Some((level, min_size)))
+    }
 }
 
+/// Outputs smaller than this aren't worth a decompression step when loading
+const DEFAULT_ZSTD_MIN_SIZE: u64 = 1024 * 1024;
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct RuleConfig {
     /// Output format
@@ -104,9 +423,17 @@ pub struct RuleConfig {
     #[serde(default)]
     pub trim: Option<bool>,
 
-    /// Generate mipmaps
+    /// Generate mipmaps (overrides the preset)
+    #[serde(default, alias = "mipmap")]
+    pub generate_mipmaps: Option<bool>,
+
+    /// Mip downsampling filter (overrides the preset)
     #[serde(default)]
-    pub mipmap: Option<bool>,
+    pub mip_filter: Option<String>,
+
+    /// Alpha-test threshold (0-1) whose coverage mip levels keep, for foliage and fences
+    #[serde(default)]
+    pub alpha_coverage: Option<f32>,
 
     /// Apply Draco compression (for 3D models)
     #[serde(default)]
@@ -120,6 +447,10 @@ pub struct RuleConfig {
     #[serde(default)]
     pub normalize: Option<bool>,
 
+    /// Audio sample rate to resample to (default: keep the source's)
+    #[serde(default)]
+    pub sample_rate: Option<u32>,
+
     /// Quality setting (0-100)
     #[serde(default)]
     pub quality: Option<u8>,
@@ -131,6 +462,117 @@ pub struct RuleConfig {
     /// Custom output path pattern
     #[serde(default)]
     pub output: Option<String>,
+
+    /// Display time of an animation frame in milliseconds (atlas sprites)
+    #[serde(default)]
+    pub frame_duration: Option<u32>,
+
+    /// What a texture holds: color (default), normal-map (linear, XY-channel GPU encoding)
+    /// or mask (linear data)
+    #[serde(default)]
+    pub texture_type: Option<String>,
+
+    /// Rescale normal map vectors to unit length after resizing and in every mip level
+    #[serde(default)]
+    pub renormalize: Option<bool>,
+
+    /// Reduce PNG output to a palette (overrides the preset's `png_quantize`)
+    #[serde(default)]
+    pub quantize: Option<bool>,
+
+    /// Dither gradients before lossy encoding to hide banding
+    #[serde(default)]
+    pub dither: Option<bool>,
+
+    /// Dither amplitude as a fraction of the output format's quantization step (default 1.0)
+    #[serde(default)]
+    pub dither_strength: Option<f32>,
+
+    /// Dither threshold pattern: blue-noise (default) or ordered
+    #[serde(default)]
+    pub dither_pattern: Option<String>,
+
+    /// Multiply texture color by alpha before encoding (overrides the preset)
+    #[serde(default)]
+    pub premultiply_alpha: Option<bool>,
+
+    /// Transfer function of textures: srgb or linear (default follows `texture_type`)
+    #[serde(default)]
+    pub colorspace: Option<String>,
+
+    /// Labels used to group assets, e.g. for bundle prefetch order
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+
+    /// Scale factors of resolution variants to write, e.g. `[1.0, 0.5, 0.25]`
+    #[serde(default)]
+    pub variants: Option<Vec<f32>>,
+
+    /// File name template of variants, with `{name}` and `{scale}` (default `{name}@{scale}x`)
+    #[serde(default)]
+    pub variant_name: Option<String>,
+
+    /// Detect nine-slice borders and write them to a `.slice.json` sidecar (UI images)
+    #[serde(default)]
+    pub nine_slice: Option<bool>,
+
+    /// Nine-slice borders in source pixels as `[left, top, right, bottom]`, instead of detecting them
+    #[serde(default)]
+    pub slice_borders: Option<Vec<u32>>,
+
+    /// Anchor of an atlas sprite as `[x, y]`, normalized in the untrimmed sprite from its top
+    /// left (`[0.5, 1.0]` is the bottom center); copied into the frame
+    #[serde(default)]
+    pub pivot: Option<[f32; 2]>,
+
+    /// PSD layers or groups to export as separate textures instead of the flattened image, as
+    /// globs over `Group/Layer` paths
+    #[serde(default)]
+    pub psd_layers: Option<Vec<String>>,
+
+    /// SSIM to encode to (overrides the preset's `target_ssim`)
+    #[serde(default)]
+    pub target_ssim: Option<f64>,
+
+    /// Largest output size for images (e.g. `"256KB"`); quality and then resolution are
+    /// lowered until it fits, and the build fails if nothing does
+    #[serde(default)]
+    pub max_output_size: Option<String>,
+
+    /// Bits per channel of PNG and KTX2 output: 16 keeps 16-bit sources such as heightmaps
+    /// (16-bit PNG, or uncompressed R16/RGBA16 KTX2 for linear textures), 8 reduces them.
+    /// For WAV output, the sample bits: 8, 16, 24, or 32 for float
+    #[serde(default)]
+    pub bit_depth: Option<u8>,
+
+    /// Audio output channels: 1 downmixes to mono (e.g. positional SFX), 2 makes mono stereo
+    #[serde(default)]
+    pub channels: Option<u32>,
+
+    /// Loop start in sample frames of the source audio, overriding its `smpl` chunk (music)
+    #[serde(default)]
+    pub loop_start: Option<u64>,
+
+    /// Loop end (exclusive) in sample frames of the source audio (default: the end of the clip)
+    #[serde(default)]
+    pub loop_end: Option<u64>,
+
+    /// Audio fade-in length after trimming (e.g. `"50ms"`, `"1.5s"`)
+    #[serde(default)]
+    pub fade_in: Option<String>,
+
+    /// Audio fade-out length after trimming (e.g. `"200ms"`)
+    #[serde(default)]
+    pub fade_out: Option<String>,
+
+    /// Block length in bytes of ADPCM audio output; smaller blocks seek finer but compress less
+    #[serde(default)]
+    pub adpcm_block_size: Option<u32>,
+
+    /// Shell command run after the asset is built (not when cached), with
+    /// `ASSET_FORGE_SOURCE` and `ASSET_FORGE_OUTPUT` set; a failure fails the asset
+    #[serde(default)]
+    pub post_process: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -161,52 +603,270 @@ fn default_cache_dir() -> PathBuf {
     PathBuf::from(".asset-forge-cache")
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PriorityConfig {
+    /// Globs, relative to the source directory, for assets under active iteration
+    #[serde(default)]
+    pub patterns: Vec<String>,
+}
+
+impl PriorityConfig {
+    /// Compile the patterns into a matcher
+    pub fn matcher(&self) -> Result<GlobSet> {
+        glob_set(&self.patterns, "priority")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct AuditConfig {
+    /// Color-blind accessibility audit
+    #[serde(default)]
+    pub colorblind: ColorblindAuditConfig,
+
+    /// Loop seam, SFX length and DC offset audit
+    #[serde(default)]
+    pub audio: AudioAuditConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColorblindAuditConfig {
+    /// Globs, relative to the audited directory, of textures that must stay readable (UI, HUD)
+    #[serde(default = "default_colorblind_folders")]
+    pub folders: Vec<String>,
+
+    /// Smallest CIE76 difference touching colors may have under simulation
+    #[serde(default = "default_colorblind_min_distance")]
+    pub min_distance: f32,
+}
+
+impl Default for ColorblindAuditConfig {
+    fn default() -> Self {
+        Self {
+            folders: default_colorblind_folders(),
+            min_distance: default_colorblind_min_distance(),
+        }
+    }
+}
+
+impl ColorblindAuditConfig {
+    /// Compile the folder globs into a matcher
+    pub fn matcher(&self) -> Result<GlobSet> {
+        glob_set(&self.folders, "audit folder")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioAuditConfig {
+    /// Globs, relative to the audited directory, of clips that play as seamless loops
+    #[serde(default = "default_audio_audit_loops")]
+    pub loops: Vec<String>,
+
+    /// Largest jump between a loop's last and first sample (full scale 1.0) before it clicks
+    #[serde(default = "default_audio_audit_loop_threshold")]
+    pub loop_threshold: f32,
+
+    /// Globs, relative to the audited directory, of sound effects
+    #[serde(default = "default_audio_audit_sfx")]
+    pub sfx: Vec<String>,
+
+    /// Longest a sound effect may be, in seconds
+    #[serde(default = "default_audio_audit_sfx_max_secs")]
+    pub sfx_max_secs: f64,
+
+    /// Largest per-channel mean (full scale 1.0) any clip may have
+    #[serde(default = "default_audio_audit_dc_threshold")]
+    pub dc_offset_threshold: f32,
+}
+
+impl Default for AudioAuditConfig {
+    fn default() -> Self {
+        Self {
+            loops: default_audio_audit_loops(),
+            loop_threshold: default_audio_audit_loop_threshold(),
+            sfx: default_audio_audit_sfx(),
+            sfx_max_secs: default_audio_audit_sfx_max_secs(),
+            dc_offset_threshold: default_audio_audit_dc_threshold(),
+        }
+    }
+}
+
+impl AudioAuditConfig {
+    /// Compile the loop globs into a matcher
+    pub fn loop_matcher(&self) -> Result<GlobSet> {
+        glob_set(&self.loops, "audit loop")
+    }
+
+    /// Compile the sound effect globs into a matcher
+    pub fn sfx_matcher(&self) -> Result<GlobSet> {
+        glob_set(&self.sfx, "audit sfx")
+    }
+}
+
+fn default_audio_audit_loops() -> Vec<String> {
+    vec!["**/*_loop.*".to_string(), "music/**".to_string()]
+}
+
+fn default_audio_audit_loop_threshold() -> f32 {
+    0.05
+}
+
+fn default_audio_audit_sfx() -> Vec<String> {
+    vec!["sfx/**".to_string()]
+}
+
+fn default_audio_audit_sfx_max_secs() -> f64 {
+    5.0
+}
+
+fn default_audio_audit_dc_threshold() -> f32 {
+    0.01
+}
+
+fn default_colorblind_folders() -> Vec<String> {
+    vec!["ui/**".to_string()]
+}
+
+fn default_colorblind_min_distance() -> f32 {
+    10.0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VirtualTextureConfig {
+    /// Globs, relative to the input directory, of textures sliced into tiles (e.g. terrain)
+    #[serde(default)]
+    pub folders: Vec<String>,
+
+    /// Texels per tile side, excluding the border
+    #[serde(default = "default_tile_size")]
+    pub tile_size: u32,
+
+    /// Texels repeated from neighbouring tiles on each side
+    #[serde(default = "default_tile_border")]
+    pub border: u32,
+
+    /// Tile format (default: the preset's texture format, or png)
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+impl Default for VirtualTextureConfig {
+    fn default() -> Self {
+        Self {
+            folders: Vec::new(),
+            tile_size: default_tile_size(),
+            border: default_tile_border(),
+            format: None,
+        }
+    }
+}
+
+impl VirtualTextureConfig {
+    /// Compile the folder globs into a matcher
+    pub fn matcher(&self) -> Result<GlobSet> {
+        glob_set(&self.folders, "virtual texture folder")
+    }
+}
+
+fn default_tile_size() -> u32 {
+    256
+}
+
+fn default_tile_border() -> u32 {
+    4
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleConfig {
+    /// Pack every output into one bundle file after each build
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Bundle file name inside the output directory; the index is `<name>.json`
+    #[serde(default = "default_bundle_name")]
+    pub name: String,
+
+    /// Rule tags laid out first, in this order, so each can be read in one request
+    #[serde(default)]
+    pub prefetch: Vec<String>,
+
+    /// Byte alignment of every asset in the bundle
+    #[serde(default = "default_bundle_alignment")]
+    pub alignment: u64,
+}
+
+impl Default for BundleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            name: default_bundle_name(),
+            prefetch: Vec::new(),
+            alignment: default_bundle_alignment(),
+        }
+    }
+}
+
+fn default_bundle_name() -> String {
+    crate::runtime::BUNDLE_FILE_NAME.to_string()
+}
+
+fn default_bundle_alignment() -> u64 {
+    16
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct HooksConfig {
+    /// Shell command run before the build scans its input; a failure stops the build
+    #[serde(default)]
+    pub pre_build: Option<String>,
+
+    /// Shell command run after a build in which no asset failed, e.g. an engine import or upload
+    #[serde(default)]
+    pub post_build: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ProcessorsConfig {
+    /// Command processors by glob, relative to the source directory (`"**/*.tga"`)
+    #[serde(default)]
+    pub custom: HashMap<String, CustomProcessorConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomProcessorConfig {
+    /// Command template, split on whitespace and run without a shell; `{input}`, `{output}`,
+    /// `{output_dir}` and `{name}` (the source file stem) are replaced in each argument
+    pub cmd: String,
+
+    /// Output extension (default: the source's)
+    #[serde(default)]
+    pub extension: Option<String>,
+}
+
+fn glob_set(patterns: &[String], what: &str) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern)
+            .with_context(|| format!("Invalid {} pattern: {}", what, pattern))?;
+        builder.add(glob);
+    }
+    Ok(builder.build()?)
+}
+
 impl Config {
     /// Create a default configuration with sensible presets
     pub fn with_defaults() -> Self {
         let mut config = Config::default();
 
-        // Mobile preset
-        config.presets.insert(
-            "mobile".to_string(),
-            PresetConfig {
-                texture_max_size: Some(1024),
-                texture_format: Some("ktx2".to_string()),
-                texture_quality: Some(75),
-                audio_format: Some("ogg".to_string()),
-                audio_quality: Some(6),
-                compress_textures: Some(true),
-                generate_mipmaps: Some(true),
-            },
-        );
-
-        // Desktop preset
-        config.presets.insert(
-            "desktop".to_string(),
-            PresetConfig {
-                texture_max_size: Some(4096),
-                texture_format: Some("png".to_string()),
-                texture_quality: Some(90),
-                audio_format: Some("wav".to_string()),
-                audio_quality: Some(10),
-                compress_textures: Some(false),
-                generate_mipmaps: Some(true),
-            },
-        );
-
-        // Web preset
-        config.presets.insert(
-            "web".to_string(),
-            PresetConfig {
-                texture_max_size: Some(2048),
-                texture_format: Some("webp".to_string()),
-                texture_quality: Some(80),
-                audio_format: Some("ogg".to_string()),
-                audio_quality: Some(7),
-                compress_textures: Some(true),
-                generate_mipmaps: Some(false),
-            },
-        );
+        for preset in [
+            PlatformPreset::Mobile,
+            PlatformPreset::Desktop,
+            PlatformPreset::Web,
+            PlatformPreset::Switch,
+            PlatformPreset::Playstation,
+            PlatformPreset::Xbox,
+        ] {
+            config.presets.insert(preset.to_string(), PresetConfig::builtin(preset));
+        }
 
         config
     }
@@ -226,12 +886,17 @@ audio_format = "ogg"
 audio_quality = 6
 compress_textures = true
 generate_mipmaps = true
+# Keep short SFX uncompressed for low-latency playback
+# short_sfx_max_secs = 2.0
+# short_sfx_format = "wav"
+# Stream long clips as OGG and keep the rest as in-memory WAV
+# music_threshold = "15s"
 
 [presets.desktop]
 texture_max_size = 4096
 texture_format = "png"
 texture_quality = 90
-audio_format = "wav"
+audio_format = "flac"
 audio_quality = 10
 compress_textures = false
 generate_mipmaps = true
@@ -245,12 +910,47 @@ audio_quality = 7
 compress_textures = true
 generate_mipmaps = false
 
+# Console presets name target formats only; KTX2 is transcoded to gpu_format at load time
+[presets.switch]
+texture_max_size = 2048
+texture_format = "ktx2"
+gpu_format = "astc"
+# ktx2_zstd_level = 18
+# bundle_zstd_level = 19
+texture_quality = 80
+audio_format = "ogg"
+audio_quality = 6
+compress_textures = true
+generate_mipmaps = true
+
+[presets.playstation]
+texture_max_size = 4096
+texture_format = "ktx2"
+gpu_format = "bc7"
+texture_quality = 90
+audio_format = "ogg"
+audio_quality = 8
+compress_textures = true
+generate_mipmaps = true
+
+[presets.xbox]
+texture_max_size = 4096
+texture_format = "ktx2"
+gpu_format = "bc7"
+texture_quality = 90
+audio_format = "ogg"
+audio_quality = 8
+compress_textures = true
+generate_mipmaps = true
+
 [rules]
 # Sprite atlas rules
 # "sprites/*.png" = { atlas = true, trim = true }
 
 # Texture rules
-# "textures/*.png" = { format = "ktx2", mipmap = true }
+# "textures/*.png" = { format = "ktx2", generate_mipmaps = true }
+# "textures/**/*_normal.png" = { texture_type = "normal-map", renormalize = true }
+# "ui/backgrounds/*.png" = { quantize = true, dither = true }
 
 # Model rules
 # "models/*.gltf" = { draco = true, meshopt = true }
@@ -258,9 +958,65 @@ generate_mipmaps = false
 # Audio rules
 # "audio/*.wav" = { format = "ogg", normalize = true }
 
+# Tags group assets for the bundle's prefetch order
+# "ui/boot/**" = { tags = ["boot"] }
+
+# Run a command on each freshly built output
+# "textures/ui/*.png" = { post_process = "my-tool check \"$ASSET_FORGE_OUTPUT\"" }
+
+# Assets you are iterating on are built first and jump the watch queue
+[priority]
+patterns = [
+    # "characters/hero/**",
+    # "ui/hud/*.png",
+]
+
 [cache]
 enabled = true
 directory = ".asset-forge-cache"
+
+# `build --profile dev` layers a profile over the preset: any preset field,
+# plus encoder_quality and skip_basis (KTX2 textures are written as PNG)
+[profiles.dev]
+encoder_quality = "fast"
+skip_basis = true
+
+# [profiles.release]
+# encoder_quality = "high"
+
+# Shell commands run by `build`, with ASSET_FORGE_OUTPUT_DIR, ASSET_FORGE_MANIFEST
+# and friends in the environment
+[hooks]
+# pre_build = "./tools/export-sources.sh"
+# post_build = "godot --headless --import"
+
+# External commands that build matching files instead of asset-forge,
+# with caching, stats and the manifest handled as for built-in assets
+[processors.custom]
+# "textures/**/*.png" = { cmd = "toktx --t2 {output} {input}", extension = "ktx2" }
+
+# Pack the build output into one file with a random-access index
+# (`<name>.json`); assets tagged by rules are laid out first, in this order
+[bundle]
+enabled = false
+name = "assets.bundle"
+prefetch = ["boot", "menu"]
+alignment = 16
+
+# `asset-forge audit colorblind` checks these textures for colors that
+# color-blind players can't tell apart
+[audit.colorblind]
+folders = ["ui/**"]
+min_distance = 10.0
+
+# `asset-forge audit audio` checks loops for clicks at the seam, sound
+# effects for length, and every clip for DC offset
+[audit.audio]
+loops = ["**/*_loop.*", "music/**"]
+loop_threshold = 0.05
+sfx = ["sfx/**"]
+sfx_max_secs = 5.0
+dc_offset_threshold = 0.01
 "#
         .to_string()
     }
@@ -0,0 +1,7 @@
+//! asset-forge library API
+//!
+//! The CLI lives in the `asset-forge` binary; the library only exposes the
+//! optional [`runtime`] module for reading build output from games.
+
+#[cfg(feature = "runtime")]
+pub mod runtime;
@@ -2,34 +2,99 @@ mod cli;
 mod commands;
 mod config;
 mod processors;
+#[allow(dead_code)]
+mod runtime;
 mod utils;
 
 use anyhow::Result;
-use clap::Parser;
+use std::process::ExitCode;
+use clap::{ArgMatches, CommandFactory, FromArgMatches};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use cli::{Cli, Commands};
+use config::ConfigError;
 
-fn main() -> Result<()> {
-    // Initialize tracing
+/// Exit code of a failed command, including builds where any asset failed
+const EXIT_FAILURE: u8 = 1;
+/// Exit code for configuration errors, the same as clap uses for invalid arguments
+const EXIT_CONFIG_ERROR: u8 = 2;
+
+fn main() -> ExitCode {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    utils::set_json_output(cli.json);
+
+    // Initialize tracing; logs stay off stdout when it carries JSON events
+    let writer = if cli.json { BoxMakeWriter::new(std::io::stderr) } else { BoxMakeWriter::new(std::io::stdout) };
     tracing_subscriber::registry()
         .with(tracing_subscriber::EnvFilter::new(
             std::env::var("RUST_LOG").unwrap_or_else(|_| "asset_forge=info".to_string()),
         ))
-        .with(tracing_subscriber::fmt::layer().without_time())
+        .with(tracing_subscriber::fmt::layer().without_time().with_writer(writer))
         .init();
 
-    let cli = Cli::parse();
+    let result = run(cli.command);
+    utils::emit_summary(&command_name(&matches), &result);
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            if e.downcast_ref::<ConfigError>().is_some() {
+                ExitCode::from(EXIT_CONFIG_ERROR)
+            } else {
+                ExitCode::from(EXIT_FAILURE)
+            }
+        }
+    }
+}
+
+/// The subcommand path, e.g. `build` or `cache stats`
+fn command_name(matches: &ArgMatches) -> String {
+    let mut names = Vec::new();
+    let mut current = matches;
+    while let Some((name, sub)) = current.subcommand() {
+        names.push(name);
+        current = sub;
+    }
+    names.join(" ")
+}
 
-    match cli.command {
+fn run(command: Commands) -> Result<()> {
+    match command {
         Commands::Init { force } => commands::init::run(force),
         Commands::Optimize { input, options } => commands::optimize::run(input, options),
         Commands::Build { input, options } => commands::build::run(input, options),
         Commands::Atlas { input, options } => commands::atlas::run(input, options),
+        Commands::Tiles { input, options } => commands::tiles::run(input, options),
+        Commands::Array { input, options } => commands::array::run(input, options),
         Commands::Watch { input, options } => commands::watch::run(input, options),
         Commands::Model { input, options } => commands::model::run(input, options),
         Commands::Audio { input, options } => commands::audio::run(input, options),
+        Commands::Audiosprite { input, options } => commands::audiosprite::run(input, options),
         Commands::Info { input } => commands::info::run(input),
+        Commands::Rules { command } => match command {
+            cli::RulesCommand::Test { path, options } => commands::rules::test(path, options),
+        },
+        Commands::Pack { options } => commands::pack::run(options),
+        Commands::Audit { command } => match command {
+            cli::AuditCommand::Colorblind { input, options } => commands::audit::colorblind(input, options),
+            cli::AuditCommand::Audio { input, options } => commands::audit::audio(input, options),
+        },
+        Commands::Restore { options } => commands::restore::run(options),
+        Commands::Bundle { command } => match command {
+            cli::BundleCommand::Ls { bundle } => commands::bundle::ls(bundle),
+            cli::BundleCommand::Extract { bundle, assets, output } => {
+                commands::bundle::extract(bundle, assets, output)
+            }
+        },
+        Commands::Migrate { options } => commands::migrate::run(options),
+        Commands::Mv { from, to, options } => commands::mv::run(from, to, options),
+        Commands::Cache { command } => match command {
+            cli::CacheCommand::Stats { options } => commands::cache::stats(options),
+            cli::CacheCommand::Verify { options } => commands::cache::verify(options),
+            cli::CacheCommand::Prune { options, dry_run } => commands::cache::prune(options, dry_run),
+        },
         Commands::Clean { cache_dir, all } => commands::clean::run(cache_dir, all),
     }
 }
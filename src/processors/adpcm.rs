@@ -0,0 +1,451 @@
+//! 4-bit ADPCM WAV encoding (IMA and Microsoft variants)
+//!
+//! Both codecs store audio in fixed-size blocks. Each block starts with a header holding the
+//! decoder state, so a player can start decoding at any block without the ones before it.
+
+use anyhow::{bail, Result};
+use std::io::{Seek, SeekFrom, Write};
+
+/// ADPCM flavour written into the WAV `fmt` chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdpcmCodec {
+    /// IMA/DVI ADPCM (`WAVE_FORMAT_IMA_ADPCM`), decoded by nearly every engine and handheld SDK
+    Ima,
+    /// Microsoft ADPCM (`WAVE_FORMAT_ADPCM`), slightly better quality at the same size
+    Ms,
+}
+
+impl AdpcmCodec {
+    fn format_tag(self) -> u16 {
+        match self {
+            Self::Ima => 0x0011,
+            Self::Ms => 0x0002,
+        }
+    }
+
+    /// Bytes of per-channel state at the start of each block
+    fn header_bytes(self) -> usize {
+        match self {
+            Self::Ima => 4,
+            Self::Ms => 7,
+        }
+    }
+
+    /// Sample frames in a block of `block_size` bytes
+    fn frames_per_block(self, block_size: usize, channels: usize) -> usize {
+        let data = block_size - self.header_bytes() * channels;
+        match self {
+            Self::Ima => data * 2 / channels + 1,
+            Self::Ms => data * 2 / channels + 2,
+        }
+    }
+
+    /// The block size Windows' ACM codecs pick: 256 bytes per channel at 11 kHz, growing with
+    /// the sample rate
+    pub fn default_block_size(channels: u32, sample_rate: u32) -> u32 {
+        256 * channels * (sample_rate / 11025).max(1)
+    }
+}
+
+impl std::fmt::Display for AdpcmCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ima => write!(f, "IMA ADPCM"),
+            Self::Ms => write!(f, "MS ADPCM"),
+        }
+    }
+}
+
+#[rustfmt::skip]
+const IMA_INDEX_TABLE: [i32; 16] = [
+    -1, -1, -1, -1, 2, 4, 6, 8,
+    -1, -1, -1, -1, 2, 4, 6, 8,
+];
+
+#[rustfmt::skip]
+const IMA_STEP_TABLE: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17,
+    19, 21, 23, 25, 28, 31, 34, 37, 41, 45,
+    50, 55, 60, 66, 73, 80, 88, 97, 107, 118,
+    130, 143, 157, 173, 190, 209, 230, 253, 279, 307,
+    337, 371, 408, 449, 494, 544, 598, 658, 724, 796,
+    876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066,
+    2272, 2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358,
+    5894, 6484, 7132, 7845, 8630, 9493, 10442, 11487, 12635, 13899,
+    15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
+];
+
+#[rustfmt::skip]
+const MS_ADAPTATION_TABLE: [i32; 16] = [
+    230, 230, 230, 230, 307, 409, 512, 614,
+    768, 614, 512, 409, 307, 230, 230, 230,
+];
+
+/// The standard predictor coefficient pairs, stored in the `fmt` chunk
+const MS_COEFFICIENTS: [(i32, i32); 7] = [(256, 0), (512, -256), (0, 0), (192, 64), (240, 0), (460, -208), (392, -232)];
+
+const MS_DELTA_MIN: i32 = 16;
+
+/// Largest `fmt` chunk, for Microsoft ADPCM with its coefficient table
+const FMT_MAX: usize = 50;
+
+/// ADPCM WAV encoder for interleaved mono or stereo audio, taken in chunks and written out
+/// block by block
+///
+/// `block_size` is the block length in bytes (the WAV block align). Smaller blocks recover
+/// from prediction errors sooner and let players seek more finely, at the cost of more header
+/// bytes. The last block is padded with silence; the `fact` chunk records the real length.
+pub struct AdpcmWriter<W: Write + Seek> {
+    writer: W,
+    codec: AdpcmCodec,
+    channels: usize,
+    block_size: usize,
+    frames_per_block: usize,
+    /// IMA step index of each channel, carried from block to block
+    step_index: Vec<i32>,
+    /// Samples not yet making up a whole block
+    pending: Vec<i16>,
+    frames: u64,
+    data_bytes: u64,
+    /// Offset of the `fact` chunk's frame count and the `data` chunk's size
+    fact_offset: u64,
+}
+
+impl<W: Write + Seek> AdpcmWriter<W> {
+    pub fn new(mut writer: W, codec: AdpcmCodec, channels: u32, sample_rate: u32, block_size: u32) -> Result<Self> {
+        if !(1..=2).contains(&channels) {
+            bail!("{} output supports mono and stereo only, got {} channels (use --channels)", codec, channels);
+        }
+        let channels = channels as usize;
+        let block_size = block_size as usize;
+        let header = codec.header_bytes() * channels;
+        // IMA stores each channel's samples in 4-byte words
+        let granule = match codec {
+            AdpcmCodec::Ima => 4 * channels,
+            AdpcmCodec::Ms => channels,
+        };
+        if block_size <= header || block_size > u16::MAX as usize || !(block_size - header).is_multiple_of(granule) {
+            bail!(
+                "Invalid {} block size {} for {} channel(s): use {} plus a multiple of {} bytes, up to {}",
+                codec,
+                block_size,
+                channels,
+                header,
+                granule,
+                u16::MAX
+            );
+        }
+        let frames_per_block = codec.frames_per_block(block_size, channels);
+        if frames_per_block > u16::MAX as usize {
+            bail!("{} block size {} holds too many samples; use a smaller block", codec, block_size);
+        }
+
+        let mut fmt = Vec::with_capacity(FMT_MAX);
+        fmt.extend_from_slice(&codec.format_tag().to_le_bytes());
+        fmt.extend_from_slice(&(channels as u16).to_le_bytes());
+        fmt.extend_from_slice(&sample_rate.to_le_bytes());
+        let bytes_per_sec = (sample_rate as u64 * block_size as u64 / frames_per_block as u64) as u32;
+        fmt.extend_from_slice(&bytes_per_sec.to_le_bytes());
+        fmt.extend_from_slice(&(block_size as u16).to_le_bytes());
+        fmt.extend_from_slice(&4u16.to_le_bytes());
+        match codec {
+            AdpcmCodec::Ima => {
+                fmt.extend_from_slice(&2u16.to_le_bytes());
+                fmt.extend_from_slice(&(frames_per_block as u16).to_le_bytes());
+            }
+            AdpcmCodec::Ms => {
+                fmt.extend_from_slice(&(4 + 4 * MS_COEFFICIENTS.len() as u16).to_le_bytes());
+                fmt.extend_from_slice(&(frames_per_block as u16).to_le_bytes());
+                fmt.extend_from_slice(&(MS_COEFFICIENTS.len() as u16).to_le_bytes());
+                for (coeff1, coeff2) in MS_COEFFICIENTS {
+                    fmt.extend_from_slice(&(coeff1 as i16).to_le_bytes());
+                    fmt.extend_from_slice(&(coeff2 as i16).to_le_bytes());
+                }
+            }
+        }
+
+        // RIFF and data sizes are filled in by `finish`
+        writer.write_all(b"RIFF\0\0\0\0WAVEfmt ")?;
+        writer.write_all(&(fmt.len() as u32).to_le_bytes())?;
+        writer.write_all(&fmt)?;
+        let fact_offset = 12 + 8 + fmt.len() as u64 + 8;
+        writer.write_all(b"fact")?;
+        writer.write_all(&4u32.to_le_bytes())?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(b"data\0\0\0\0")?;
+
+        Ok(Self {
+            writer,
+            codec,
+            channels,
+            block_size,
+            frames_per_block,
+            step_index: vec![0; channels],
+            pending: Vec::with_capacity(frames_per_block * channels),
+            frames: 0,
+            data_bytes: 0,
+            fact_offset,
+        })
+    }
+
+    /// Encode interleaved samples
+    pub fn write(&mut self, samples: &[f32]) -> Result<()> {
+        self.frames += (samples.len() / self.channels) as u64;
+        let block_samples = self.frames_per_block * self.channels;
+        for &sample in samples {
+            self.pending.push((sample * 32767.0).clamp(-32768.0, 32767.0) as i16);
+            if self.pending.len() == block_samples {
+                self.encode_block()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Pad and encode the last block and fill in the chunk sizes
+    pub fn finish(mut self) -> Result<W> {
+        if !self.pending.is_empty() {
+            self.pending.resize(self.frames_per_block * self.channels, 0);
+            self.encode_block()?;
+        }
+        // Chunks are padded to an even length
+        if self.data_bytes % 2 == 1 {
+            self.writer.write_all(&[0])?;
+        }
+        let end = self.writer.stream_position()?;
+        self.writer.seek(SeekFrom::Start(4))?;
+        self.writer.write_all(&((end - 8) as u32).to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(self.fact_offset))?;
+        self.writer.write_all(&(self.frames as u32).to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(self.fact_offset + 8))?;
+        self.writer.write_all(&(self.data_bytes as u32).to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        Ok(self.writer)
+    }
+
+    fn encode_block(&mut self) -> Result<()> {
+        let block = match self.codec {
+            AdpcmCodec::Ima => self.encode_ima_block(),
+            AdpcmCodec::Ms => self.encode_ms_block(),
+        };
+        debug_assert_eq!(block.len(), self.block_size);
+        self.writer.write_all(&block)?;
+        self.data_bytes += block.len() as u64;
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Per channel: the first sample and step index, then 4-byte words of eight samples
+    /// each, alternating between channels, low nibble first
+    fn encode_ima_block(&mut self) -> Vec<u8> {
+        let mut block = Vec::with_capacity(self.block_size);
+        let mut encoded = Vec::with_capacity(self.channels);
+        for channel in 0..self.channels {
+            let samples: Vec<i16> = self.pending.iter().skip(channel).step_by(self.channels).copied().collect();
+            block.extend_from_slice(&samples[0].to_le_bytes());
+            block.push(self.step_index[channel] as u8);
+            block.push(0);
+
+            let mut predictor = samples[0] as i32;
+            let mut index = self.step_index[channel];
+            let nibbles: Vec<u8> = samples[1..]
+                .iter()
+                .map(|&sample| {
+                    let step = IMA_STEP_TABLE[index as usize];
+                    let diff = sample as i32 - predictor;
+                    let magnitude = (diff.abs() * 4 / step).min(7);
+                    let nibble = magnitude as u8 | if diff < 0 { 8 } else { 0 };
+                    // Track the decoder's reconstruction exactly
+                    let delta = ((2 * magnitude + 1) * step) >> 3;
+                    predictor = (if diff < 0 { predictor - delta } else { predictor + delta }).clamp(-32768, 32767);
+                    index = (index + IMA_INDEX_TABLE[nibble as usize]).clamp(0, 88);
+                    nibble
+                })
+                .collect();
+            self.step_index[channel] = index;
+            encoded.push(nibbles);
+        }
+
+        for word in 0..(self.frames_per_block - 1) / 8 {
+            for nibbles in &encoded {
+                for pair in nibbles[word * 8..word * 8 + 8].chunks_exact(2) {
+                    block.push(pair[0] | pair[1] << 4);
+                }
+            }
+        }
+        block
+    }
+
+    /// Per channel: predictor, delta, second and first sample, then one nibble per sample
+    /// interleaved across channels, high nibble first; each channel uses whichever predictor
+    /// fits this block best
+    fn encode_ms_block(&mut self) -> Vec<u8> {
+        let mut states = Vec::with_capacity(self.channels);
+        let mut encoded = Vec::with_capacity(self.channels);
+        for channel in 0..self.channels {
+            let samples: Vec<i16> = self.pending.iter().skip(channel).step_by(self.channels).copied().collect();
+            let delta = initial_ms_delta(&samples);
+            let (predictor, nibbles, _) = (0..MS_COEFFICIENTS.len())
+                .map(|predictor| {
+                    let (nibbles, error) = encode_ms_channel(&samples, MS_COEFFICIENTS[predictor], delta);
+                    (predictor, nibbles, error)
+                })
+                .min_by_key(|&(_, _, error)| error)
+                .unwrap();
+            // The decoder outputs the second sample first
+            states.push((predictor as u8, [delta as i16, samples[1], samples[0]]));
+            encoded.push(nibbles);
+        }
+
+        let mut block = Vec::with_capacity(self.block_size);
+        block.extend(states.iter().map(|(predictor, _)| predictor));
+        for field in 0..3 {
+            for (_, values) in &states {
+                block.extend_from_slice(&values[field].to_le_bytes());
+            }
+        }
+        let nibbles: Vec<u8> = (0..self.frames_per_block - 2)
+            .flat_map(|frame| encoded.iter().map(move |channel| channel[frame]))
+            .collect();
+        block.extend(nibbles.chunks_exact(2).map(|pair| pair[0] << 4 | pair[1]));
+        block
+    }
+}
+
+/// Starting step size for a Microsoft ADPCM block, from how fast its first samples change
+fn initial_ms_delta(samples: &[i16]) -> i32 {
+    let steps = samples.windows(2).take(4);
+    let count = steps.len().max(1) as i32;
+    let change: i32 = steps.map(|pair| (pair[1] as i32 - pair[0] as i32).abs()).sum();
+    (change / count / 4).clamp(MS_DELTA_MIN, i16::MAX as i32)
+}
+
+/// Nibbles of one channel's block after its two header samples, and their squared error
+fn encode_ms_channel(samples: &[i16], (coeff1, coeff2): (i32, i32), mut delta: i32) -> (Vec<u8>, u64) {
+    let (mut sample1, mut sample2) = (samples[1] as i32, samples[0] as i32);
+    let mut error = 0u64;
+    let nibbles = samples[2..]
+        .iter()
+        .map(|&sample| {
+            let predicted = (sample1 * coeff1 + sample2 * coeff2) / 256;
+            let diff = sample as i32 - predicted;
+            let rounded = if diff < 0 { diff - delta / 2 } else { diff + delta / 2 };
+            let code = (rounded / delta).clamp(-8, 7);
+            let decoded = (predicted + code * delta).clamp(-32768, 32767);
+            error += ((sample as i32 - decoded) as i64).pow(2) as u64;
+            sample2 = sample1;
+            sample1 = decoded;
+            let nibble = (code & 0x0f) as u8;
+            delta = (MS_ADAPTATION_TABLE[nibble as usize] * delta / 256).max(MS_DELTA_MIN);
+            nibble
+        })
+        .collect();
+    (nibbles, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processors::audio_stream::AudioDecoder;
+    use std::io::Cursor;
+
+    const RATE: u32 = 22_050;
+
+    /// Interleaved stereo tones, 330 Hz left and 880 Hz right
+    fn signal(frames: usize) -> Vec<f32> {
+        (0..frames)
+            .flat_map(|i| {
+                let t = i as f32 / RATE as f32;
+                [0.5 * (std::f32::consts::TAU * 330.0 * t).sin(), 0.3 * (std::f32::consts::TAU * 880.0 * t).sin()]
+            })
+            .collect()
+    }
+
+    fn encode(codec: AdpcmCodec, samples: &[f32], block_size: u32) -> Vec<u8> {
+        let mut writer = AdpcmWriter::new(Cursor::new(Vec::new()), codec, 2, RATE, block_size).unwrap();
+        for chunk in samples.chunks(1234) {
+            writer.write(chunk).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn u16_at(data: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes([data[at], data[at + 1]])
+    }
+
+    fn u32_at(data: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+    }
+
+    /// Decode with symphonia and return the error against `input` relative to full scale
+    fn decoded_rms_error(data: &[u8], input: &[f32]) -> f32 {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.wav");
+        std::fs::write(&path, data).unwrap();
+        let mut decoder = AudioDecoder::open(&path).unwrap();
+        assert_eq!(decoder.channels, 2);
+        assert_eq!(decoder.sample_rate, RATE);
+        let mut decoded = Vec::new();
+        while let Some(chunk) = decoder.next_chunk().unwrap() {
+            decoded.extend(chunk);
+        }
+        assert!(decoded.len() >= input.len());
+        let squared: f32 = input.iter().zip(&decoded).map(|(a, b)| (a - b).powi(2)).sum();
+        (squared / input.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_ima_header_fields() {
+        let frames = 5000;
+        let block_size = AdpcmCodec::default_block_size(2, RATE);
+        let data = encode(AdpcmCodec::Ima, &signal(frames), block_size);
+
+        assert_eq!(&data[0..4], b"RIFF");
+        assert_eq!(u32_at(&data, 4) as usize, data.len() - 8);
+        assert_eq!(&data[8..16], b"WAVEfmt ");
+        assert_eq!(u32_at(&data, 16), 20);
+        assert_eq!(u16_at(&data, 20), 0x0011);
+        assert_eq!(u16_at(&data, 22), 2);
+        assert_eq!(u32_at(&data, 24), RATE);
+        assert_eq!(u16_at(&data, 32) as u32, block_size);
+        assert_eq!(u16_at(&data, 34), 4);
+        assert_eq!(u16_at(&data, 36), 2);
+        // 8 header bytes, then two nibbles per sample per channel, plus the header sample
+        let frames_per_block = (block_size as usize - 8) + 1;
+        assert_eq!(u16_at(&data, 38) as usize, frames_per_block);
+        assert_eq!(&data[40..44], b"fact");
+        assert_eq!(u32_at(&data, 48) as usize, frames);
+        assert_eq!(&data[52..56], b"data");
+        let blocks = frames.div_ceil(frames_per_block);
+        assert_eq!(u32_at(&data, 56) as usize, blocks * block_size as usize);
+
+        // Each channel's block header holds its first sample and a valid step index
+        let first = &data[60..68];
+        assert_eq!(i16::from_le_bytes([first[0], first[1]]), 0);
+        assert!(first[2] <= 88 && first[3] == 0);
+    }
+
+    #[test]
+    fn test_ima_round_trip() {
+        let input = signal(RATE as usize);
+        let data = encode(AdpcmCodec::Ima, &input, AdpcmCodec::default_block_size(2, RATE));
+        let error = decoded_rms_error(&data, &input);
+        assert!(error < 0.008, "RMS error {}", error);
+    }
+
+    #[test]
+    fn test_ms_round_trip() {
+        let input = signal(RATE as usize);
+        let data = encode(AdpcmCodec::Ms, &input, AdpcmCodec::default_block_size(2, RATE));
+        assert_eq!(u16_at(&data, 20), 0x0002);
+        let error = decoded_rms_error(&data, &input);
+        assert!(error < 0.003, "RMS error {}", error);
+    }
+
+    #[test]
+    fn test_rejects_invalid_block_size() {
+        // IMA stereo data comes in 8-byte groups after the 8 header bytes
+        assert!(AdpcmWriter::new(Cursor::new(Vec::new()), AdpcmCodec::Ima, 2, RATE, 8 + 12).is_err());
+        assert!(AdpcmWriter::new(Cursor::new(Vec::new()), AdpcmCodec::Ima, 3, RATE, 1024).is_err());
+        assert!(AdpcmWriter::new(Cursor::new(Vec::new()), AdpcmCodec::Ima, 2, RATE, 8 + 16).is_ok());
+    }
+}
@@ -0,0 +1,154 @@
+use anyhow::{Context, Result};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::codecs::webp::WebPEncoder;
+use image::{Delay, DynamicImage, ExtendedColorType, Frame, GenericImage, RgbaImage};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::cli::PreviewFormat;
+
+/// Group frame keys that end in a frame number (`walk_01`, `walk-2`, `walk/3`) into animations
+///
+/// Returns animation name → frame keys ordered by frame number. `suffix` is the configured
+/// key suffix and any file extension is ignored when looking for the number. Single frames
+/// are not animations.
+pub fn group_animations<'a>(
+    keys: impl IntoIterator<Item = &'a str>,
+    suffix: &str,
+) -> BTreeMap<String, Vec<String>> {
+    let mut groups: BTreeMap<String, Vec<(u32, String)>> = BTreeMap::new();
+    for key in keys {
+        let base = key.strip_suffix(suffix).unwrap_or(key);
+        let base = match base.rfind('.') {
+            Some(dot) if !base[dot..].contains('/') => &base[..dot],
+            _ => base,
+        };
+        let digits = base.len() - base.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 {
+            continue;
+        }
+        let (name, number) = base.split_at(base.len() - digits);
+        let name = name.strip_suffix(['_', '-', '/']).unwrap_or(name);
+        if name.is_empty() || name.ends_with('/') {
+            continue;
+        }
+        let Ok(number) = number.parse() else { continue };
+        groups
+            .entry(format!("{}{}", name, suffix))
+            .or_default()
+            .push((number, key.to_string()));
+    }
+
+    groups
+        .into_iter()
+        .filter(|(_, frames)| frames.len() > 1)
+        .map(|(name, mut frames)| {
+            frames.sort();
+            (name, frames.into_iter().map(|(_, key)| key).collect())
+        })
+        .collect()
+}
+
+/// Write an animated preview of untrimmed frames, each shown for its duration in milliseconds
+///
+/// Frames are drawn at the top-left of a canvas sized to the largest frame.
+pub fn write_animation_preview(
+    frames: &[(&DynamicImage, u32)],
+    path: &Path,
+    format: PreviewFormat,
+) -> Result<()> {
+    let width = frames.iter().map(|(img, _)| img.width()).max().unwrap_or(1);
+    let height = frames.iter().map(|(img, _)| img.height()).max().unwrap_or(1);
+    let canvases: Vec<(RgbaImage, u32)> = frames
+        .iter()
+        .map(|(img, duration)| {
+            let mut canvas = RgbaImage::new(width, height);
+            canvas.copy_from(&img.to_rgba8(), 0, 0)?;
+            Ok((canvas, *duration))
+        })
+        .collect::<Result<_>>()?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let data = match format {
+        PreviewFormat::Webp => animated_webp(&canvases, width, height)?,
+        PreviewFormat::Gif => {
+            let mut data = Vec::new();
+            {
+                let mut encoder = GifEncoder::new(&mut data);
+                encoder.set_repeat(Repeat::Infinite)?;
+                encoder.encode_frames(canvases.into_iter().map(|(canvas, duration)| {
+                    Frame::from_parts(canvas, 0, 0, Delay::from_numer_denom_ms(duration, 1))
+                }))?;
+            }
+            data
+        }
+    };
+
+    std::fs::write(path, data)
+        .with_context(|| format!("Failed to write animation preview: {}", path.display()))
+}
+
+/// Build an animated WebP (VP8X + ANIM + one ANMF per frame) from lossless frames
+fn animated_webp(frames: &[(RgbaImage, u32)], width: u32, height: u32) -> Result<Vec<u8>> {
+    const FLAG_ANIMATION: u8 = 0x02;
+    const FLAG_ALPHA: u8 = 0x10;
+    const ANMF_NO_BLEND: u8 = 0x02;
+
+    let mut vp8x = vec![FLAG_ANIMATION | FLAG_ALPHA, 0, 0, 0];
+    vp8x.extend_from_slice(&u24(width - 1));
+    vp8x.extend_from_slice(&u24(height - 1));
+
+    // Transparent background, loop forever
+    let anim = [0u8, 0, 0, 0, 0, 0];
+
+    let mut body = Vec::new();
+    body.extend_from_slice(b"WEBP");
+    push_chunk(&mut body, b"VP8X", &vp8x);
+    push_chunk(&mut body, b"ANIM", &anim);
+
+    for (canvas, duration) in frames {
+        let mut still = Vec::new();
+        WebPEncoder::new_lossless(&mut still).encode(
+            canvas.as_raw(),
+            canvas.width(),
+            canvas.height(),
+            ExtendedColorType::Rgba8,
+        )?;
+
+        // The encoder writes a simple RIFF/WEBP file; its chunks become the frame data
+        let frame_data = still.get(12..).context("Invalid WebP frame")?;
+
+        let mut anmf = Vec::with_capacity(16 + frame_data.len());
+        anmf.extend_from_slice(&u24(0)); // x / 2
+        anmf.extend_from_slice(&u24(0)); // y / 2
+        anmf.extend_from_slice(&u24(canvas.width() - 1));
+        anmf.extend_from_slice(&u24(canvas.height() - 1));
+        anmf.extend_from_slice(&u24((*duration).min(0xFF_FFFF)));
+        anmf.push(ANMF_NO_BLEND);
+        anmf.extend_from_slice(frame_data);
+        push_chunk(&mut body, b"ANMF", &anmf);
+    }
+
+    let mut webp = Vec::with_capacity(8 + body.len());
+    webp.extend_from_slice(b"RIFF");
+    webp.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    webp.extend_from_slice(&body);
+    Ok(webp)
+}
+
+fn push_chunk(out: &mut Vec<u8>, fourcc: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(fourcc);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    if !data.len().is_multiple_of(2) {
+        out.push(0);
+    }
+}
+
+fn u24(value: u32) -> [u8; 3] {
+    let bytes = value.to_le_bytes();
+    [bytes[0], bytes[1], bytes[2]]
+}
@@ -0,0 +1,910 @@
+use anyhow::{Context, Result};
+use image::{imageops::FilterType, DynamicImage, RgbaImage};
+use rayon::prelude::*;
+use std::path::Path;
+use std::time::Instant;
+
+use super::{
+    astc_to_ktx2, mip_chain, normal_mip_chain, open_image, open_image_linear,
+    preserve_alpha_coverage, renormalize_normals, ProcessingStats,
+};
+use crate::cli::{AstcBlockSize, MipFilter, QualityPreset};
+
+const ASTC_MAGIC: [u8; 4] = [0x13, 0xAB, 0xA1, 0x5C];
+const BLOCK_BYTES: usize = 16;
+
+/// Color endpoint modes used by the encoder
+const CEM_RGB_DIRECT: u32 = 8;
+const CEM_RGBA_DIRECT: u32 = 12;
+
+/// Quantization levels in ISE order (the index is the ASTC quantization mode)
+const QUANT_LEVELS: [u32; 21] = [
+    2, 3, 4, 5, 6, 8, 10, 12, 16, 20, 24, 32, 40, 48, 64, 80, 96, 128, 160, 192, 256,
+];
+
+/// Configuration for ASTC compression
+#[derive(Debug, Clone)]
+pub struct AstcConfig {
+    /// Block footprint (None = chosen from the quality preset)
+    pub block: Option<AstcBlockSize>,
+    pub quality: QualityPreset,
+    pub generate_mipmaps: bool,
+    pub max_size: Option<u32>,
+    /// Zstandard level for KTX2 supercompression (None = uncompressed)
+    pub zstd_level: Option<i32>,
+    /// Whether texels are sRGB color (false for normal maps and other linear data)
+    pub srgb: bool,
+    /// Rescale normal map vectors to unit length after resizing and in every mip level
+    pub renormalize: bool,
+    pub mip_filter: MipFilter,
+    /// Alpha-test threshold (0-1) whose coverage every mip level keeps, for foliage and fences
+    pub alpha_coverage: Option<f32>,
+}
+
+impl AstcConfig {
+    pub fn block_size(&self) -> AstcBlockSize {
+        self.block
+            .unwrap_or_else(|| AstcBlockSize::for_quality(self.quality))
+    }
+}
+
+/// Compress an image to a `.astc` file (base level only)
+pub fn compress_to_astc(
+    input: &Path,
+    output: &Path,
+    config: &AstcConfig,
+) -> Result<ProcessingStats> {
+    write_compressed(input, output, config, |img| {
+        let block = config.block_size();
+        let (bw, bh) = block.dimensions();
+        let mut data = Vec::with_capacity(16);
+        data.extend_from_slice(&ASTC_MAGIC);
+        data.extend_from_slice(&[bw as u8, bh as u8, 1]);
+        for size in [img.width(), img.height(), 1] {
+            data.extend_from_slice(&size.to_le_bytes()[..3]);
+        }
+        data.extend_from_slice(&encode_astc(&img.to_rgba8(), block, config.quality));
+        Ok(data)
+    })
+}
+
+/// Compress an image to a KTX2 container with native ASTC blocks
+pub fn compress_to_astc_ktx2(
+    input: &Path,
+    output: &Path,
+    config: &AstcConfig,
+) -> Result<ProcessingStats> {
+    write_compressed(input, output, config, |img| {
+        let block = config.block_size();
+        let base = img.to_rgba8();
+        let chain = match (config.generate_mipmaps, config.renormalize) {
+            (false, _) => vec![base],
+            (true, true) => normal_mip_chain(base, true, config.mip_filter),
+            (true, false) => {
+                let mut chain = mip_chain(&base, config.mip_filter, config.srgb);
+                if let Some(threshold) = config.alpha_coverage {
+                    preserve_alpha_coverage(&mut chain, threshold);
+                }
+                chain
+            }
+        };
+        let levels: Vec<Vec<u8>> = chain
+            .iter()
+            .map(|level| encode_astc(level, block, config.quality))
+            .collect();
+        astc_to_ktx2(
+            &levels,
+            img.width(),
+            img.height(),
+            block,
+            config.srgb,
+            config.zstd_level,
+        )
+    })
+}
+
+/// Check that a `.astc` file's header matches its block data; returns the width and height
+pub fn validate_astc(data: &[u8]) -> Result<(u32, u32)> {
+    if data.len() < 16 || data[..4] != ASTC_MAGIC {
+        anyhow::bail!("Not an ASTC file");
+    }
+    let (bw, bh, bd) = (data[4] as u32, data[5] as u32, data[6] as u32);
+    let size = |at: usize| u32::from_le_bytes([data[at], data[at + 1], data[at + 2], 0]);
+    let (width, height, depth) = (size(7), size(10), size(13));
+    if bw == 0 || bh == 0 || bd == 0 || width == 0 || height == 0 || depth == 0 {
+        anyhow::bail!("ASTC header has a zero block or image size");
+    }
+
+    let blocks =
+        width.div_ceil(bw) as usize * height.div_ceil(bh) as usize * depth.div_ceil(bd) as usize;
+    if data.len() - 16 != blocks * BLOCK_BYTES {
+        anyhow::bail!(
+            "ASTC data is {} bytes, expected {}",
+            data.len() - 16,
+            blocks * BLOCK_BYTES
+        );
+    }
+    Ok((width, height))
+}
+
+fn write_compressed(
+    input: &Path,
+    output: &Path,
+    config: &AstcConfig,
+    container: impl FnOnce(&DynamicImage) -> Result<Vec<u8>>,
+) -> Result<ProcessingStats> {
+    let start = Instant::now();
+    let original_size = std::fs::metadata(input)
+        .with_context(|| format!("Failed to read input file: {}", input.display()))?
+        .len();
+
+    let mut img = if config.srgb {
+        open_image(input)?
+    } else {
+        open_image_linear(input)?
+    };
+    if let Some(max) = config.max_size {
+        if img.width() > max || img.height() > max {
+            img = img.resize(max, max, FilterType::Lanczos3);
+            if config.renormalize {
+                let mut resized = img.to_rgba8();
+                renormalize_normals(&mut resized);
+                img = DynamicImage::ImageRgba8(resized);
+            }
+        }
+    }
+
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let data = container(&img)?;
+    std::fs::write(output, &data)
+        .with_context(|| format!("Failed to write texture: {}", output.display()))?;
+
+    Ok(ProcessingStats {
+        original_size,
+        output_size: data.len() as u64,
+        processing_time_ms: start.elapsed().as_millis() as u64,
+        quality: None,
+    })
+}
+
+/// Encode an image as ASTC LDR blocks, row by row
+///
+/// Every block uses a single partition with direct RGB (opaque blocks) or RGBA endpoints.
+/// Higher quality presets try more weight grids and refine endpoints more often.
+pub fn encode_astc(img: &RgbaImage, block: AstcBlockSize, quality: QualityPreset) -> Vec<u8> {
+    let (bw, bh) = block.dimensions();
+    let (width, height) = img.dimensions();
+    let blocks_x = width.div_ceil(bw);
+    let blocks_y = height.div_ceil(bh);
+
+    let (grid_count, iterations) = match quality {
+        QualityPreset::Fast => (1, 0),
+        QualityPreset::Balanced => (1, 1),
+        QualityPreset::High => (2, 2),
+        QualityPreset::Ultra => (3, 4),
+    };
+    let modes: Vec<BlockMode> = weight_grids(bw, bh)
+        .into_iter()
+        .take(grid_count)
+        .map(|(gw, gh, quant)| BlockMode::new(bw, bh, gw, gh, quant))
+        .collect();
+
+    let mut out = vec![0u8; (blocks_x * blocks_y) as usize * BLOCK_BYTES];
+    out.par_chunks_mut(blocks_x as usize * BLOCK_BYTES)
+        .enumerate()
+        .for_each(|(by, row)| {
+            for bx in 0..blocks_x {
+                // Edge blocks repeat the last row/column
+                let texels: Vec<[f32; 4]> = (0..bh)
+                    .flat_map(|y| (0..bw).map(move |x| (x, y)))
+                    .map(|(x, y)| {
+                        let px = (bx * bw + x).min(width - 1);
+                        let py = (by as u32 * bh + y).min(height - 1);
+                        img.get_pixel(px, py).0.map(|c| c as f32)
+                    })
+                    .collect();
+
+                let encoded = modes
+                    .iter()
+                    .map(|mode| mode.encode(&texels, iterations))
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(bits, _)| bits)
+                    .unwrap_or_default();
+                let at = bx as usize * BLOCK_BYTES;
+                row[at..at + BLOCK_BYTES].copy_from_slice(&encoded.to_le_bytes());
+            }
+        });
+    out
+}
+
+/// Weight grids (width, height, weight quantization mode) worth trying, best first
+fn weight_grids(bw: u32, bh: u32) -> Vec<(u32, u32, usize)> {
+    const QUANT_4: usize = 2;
+    const QUANT_8: usize = 5;
+    const QUANT_16: usize = 8;
+    let candidates: &[(u32, u32, usize)] = match (bw, bh) {
+        (4, 4) => &[(4, 4, QUANT_8), (4, 4, QUANT_16), (4, 4, QUANT_4)],
+        (6, 6) => &[(5, 5, QUANT_4), (4, 4, QUANT_8), (4, 4, QUANT_16)],
+        _ => &[(4, 4, QUANT_8), (5, 5, QUANT_4), (4, 4, QUANT_16)],
+    };
+    candidates.to_vec()
+}
+
+/// A block layout: weight grid, weight and endpoint quantization, infill table
+struct BlockMode {
+    grid_width: u32,
+    grid_height: u32,
+    weight_quant: usize,
+    weight_bits: usize,
+    /// Per texel, the grid weights it interpolates and their factors (out of 16)
+    infill: Vec<[(usize, u32); 4]>,
+    /// Unquantized weight (0-64) of each weight symbol
+    weight_values: Vec<u32>,
+}
+
+/// Endpoint quantization for one color endpoint mode within a block mode
+struct EndpointQuant {
+    quant: usize,
+    /// Unquantized value (0-255) of each endpoint symbol
+    values: Vec<u8>,
+}
+
+impl BlockMode {
+    fn new(bw: u32, bh: u32, grid_width: u32, grid_height: u32, weight_quant: usize) -> Self {
+        let mut infill = Vec::with_capacity((bw * bh) as usize);
+        let ds = (1024 + bw / 2) / (bw - 1);
+        let dt = (1024 + bh / 2) / (bh - 1);
+        for y in 0..bh {
+            for x in 0..bw {
+                let gs = (ds * x * (grid_width - 1) + 32) >> 6;
+                let gt = (dt * y * (grid_height - 1) + 32) >> 6;
+                let (fs, ft) = (gs & 0xF, gt & 0xF);
+                let base = ((gs >> 4) + (gt >> 4) * grid_width) as usize;
+                let w11 = (fs * ft + 8) >> 4;
+                let gw = grid_width as usize;
+                infill.push([
+                    (base, 16 + w11 - fs - ft),
+                    (base + 1, fs - w11),
+                    (base + gw, ft - w11),
+                    (base + gw + 1, w11),
+                ]);
+            }
+        }
+
+        let bits = bit_count(QUANT_LEVELS[weight_quant]);
+        let weight_values = (0..QUANT_LEVELS[weight_quant])
+            .map(|q| {
+                let w = replicate(q, bits, 6);
+                if w > 32 {
+                    w + 1
+                } else {
+                    w
+                }
+            })
+            .collect();
+
+        Self {
+            grid_width,
+            grid_height,
+            weight_quant,
+            weight_bits: ise_bits(weight_quant, (grid_width * grid_height) as usize),
+            infill,
+            weight_values,
+        }
+    }
+
+    /// 11-bit block mode for a single-plane grid of 4-7 x 2-5 weights
+    fn mode_bits(&self) -> u128 {
+        let r = (self.weight_quant % 6 + 2) as u128;
+        let h = (self.weight_quant / 6) as u128;
+        let a = (self.grid_height - 2) as u128;
+        let b = (self.grid_width - 4) as u128;
+        (r >> 1) | ((r & 1) << 4) | (a << 5) | (b << 7) | (h << 9)
+    }
+
+    fn endpoint_quant(&self, values: usize) -> EndpointQuant {
+        let available = 128 - 17 - self.weight_bits;
+        let quant = (0..QUANT_LEVELS.len())
+            .rev()
+            .find(|&q| ise_bits(q, values) <= available)
+            .unwrap_or(0);
+        let values = (0..QUANT_LEVELS[quant])
+            .map(|s| unquantize_color(quant, s))
+            .collect();
+        EndpointQuant { quant, values }
+    }
+
+    /// Encode one block, returning its bits and squared error
+    fn encode(&self, texels: &[[f32; 4]], iterations: usize) -> (u128, f32) {
+        let opaque = texels.iter().all(|t| t[3] >= 255.0);
+        let channels = if opaque { 3 } else { 4 };
+        let cem = if opaque {
+            CEM_RGB_DIRECT
+        } else {
+            CEM_RGBA_DIRECT
+        };
+        let endpoints_quant = self.endpoint_quant(channels * 2);
+
+        let (mut e0, mut e1) = principal_endpoints(texels, channels);
+        let mut weights = self.fit_weights(texels, &e0, &e1, channels);
+        let mut symbols = quantize_endpoints(&e0, &e1, channels, &endpoints_quant);
+
+        for _ in 0..iterations {
+            let decoded = self.texel_weights(&weights);
+            if let Some((r0, r1)) = refine_endpoints(texels, &decoded, channels) {
+                e0 = r0;
+                e1 = r1;
+            }
+            symbols = quantize_endpoints(&e0, &e1, channels, &endpoints_quant);
+            let (q0, q1) = unquantized(&symbols, channels, &endpoints_quant);
+            weights = self.fit_weights(texels, &q0, &q1, channels);
+        }
+
+        // The decoder swaps endpoints with a smaller RGB sum into blue contraction; avoid it
+        let (q0, q1) = unquantized(&symbols, channels, &endpoints_quant);
+        if q1[..3].iter().sum::<f32>() < q0[..3].iter().sum::<f32>() {
+            for c in 0..channels {
+                symbols.swap(2 * c, 2 * c + 1);
+            }
+            let max = QUANT_LEVELS[self.weight_quant] - 1;
+            for w in &mut weights {
+                *w = max - *w;
+            }
+        }
+
+        let (q0, q1) = unquantized(&symbols, channels, &endpoints_quant);
+        let decoded = self.texel_weights(&weights);
+        let error: f32 = texels
+            .iter()
+            .zip(&decoded)
+            .map(|(t, &w)| {
+                (0..4)
+                    .map(|c| {
+                        let (lo, hi) = if c < channels {
+                            (q0[c], q1[c])
+                        } else {
+                            (255.0, 255.0)
+                        };
+                        let value = ((lo * (64 - w) as f32 + hi * w as f32) / 64.0).round();
+                        (value - t[c]).powi(2)
+                    })
+                    .sum::<f32>()
+            })
+            .sum();
+
+        let mut bits = BitWriter::default();
+        bits.write(self.mode_bits(), 11);
+        bits.write(0, 2); // single partition
+        bits.write(cem as u128, 4);
+        write_ise(&mut bits, endpoints_quant.quant, &symbols);
+
+        let mut weight_stream = BitWriter::default();
+        write_ise(&mut weight_stream, self.weight_quant, &weights);
+        let block = bits.value | weight_stream.value.reverse_bits();
+        (block, error)
+    }
+
+    /// Quantized grid weights that best interpolate between two endpoints
+    fn fit_weights(
+        &self,
+        texels: &[[f32; 4]],
+        e0: &[f32; 4],
+        e1: &[f32; 4],
+        channels: usize,
+    ) -> Vec<u32> {
+        let axis: Vec<f32> = (0..channels).map(|c| e1[c] - e0[c]).collect();
+        let length: f32 = axis.iter().map(|a| a * a).sum();
+        let ideal: Vec<f32> = texels
+            .iter()
+            .map(|t| {
+                if length <= f32::EPSILON {
+                    return 0.0;
+                }
+                let dot: f32 = (0..channels).map(|c| (t[c] - e0[c]) * axis[c]).sum();
+                (dot / length).clamp(0.0, 1.0)
+            })
+            .collect();
+
+        // Each grid weight is the infill-weighted average of the texel weights it covers
+        let count = (self.grid_width * self.grid_height) as usize;
+        let mut sums = vec![0.0f32; count];
+        let mut totals = vec![0.0f32; count];
+        for (texel, taps) in self.infill.iter().enumerate() {
+            for &(index, factor) in taps.iter().filter(|(_, f)| *f > 0) {
+                sums[index] += ideal[texel] * factor as f32;
+                totals[index] += factor as f32;
+            }
+        }
+
+        sums.iter()
+            .zip(&totals)
+            .map(|(&sum, &total)| {
+                let target = if total > 0.0 { sum / total * 64.0 } else { 0.0 };
+                (0..self.weight_values.len() as u32)
+                    .min_by(|&a, &b| {
+                        let da = (self.weight_values[a as usize] as f32 - target).abs();
+                        let db = (self.weight_values[b as usize] as f32 - target).abs();
+                        da.total_cmp(&db)
+                    })
+                    .unwrap_or(0)
+            })
+            .collect()
+    }
+
+    /// Per-texel weights (0-64) the decoder interpolates from the grid
+    fn texel_weights(&self, weights: &[u32]) -> Vec<u32> {
+        self.infill
+            .iter()
+            .map(|taps| {
+                let sum: u32 = taps
+                    .iter()
+                    .filter(|(_, factor)| *factor > 0)
+                    .map(|&(index, factor)| self.weight_values[weights[index] as usize] * factor)
+                    .sum();
+                (sum + 8) >> 4
+            })
+            .collect()
+    }
+}
+
+/// Endpoints along the principal axis of the block's colors
+fn principal_endpoints(texels: &[[f32; 4]], channels: usize) -> ([f32; 4], [f32; 4]) {
+    let n = texels.len() as f32;
+    let mut mean = [0.0f32; 4];
+    for t in texels {
+        for c in 0..channels {
+            mean[c] += t[c] / n;
+        }
+    }
+
+    let mut covariance = [[0.0f32; 4]; 4];
+    for t in texels {
+        for i in 0..channels {
+            for j in 0..channels {
+                covariance[i][j] += (t[i] - mean[i]) * (t[j] - mean[j]);
+            }
+        }
+    }
+
+    // Power iteration, starting from the widest channel
+    let mut axis = [0.0f32; 4];
+    let widest = (0..channels)
+        .max_by(|&a, &b| covariance[a][a].total_cmp(&covariance[b][b]))
+        .unwrap_or(0);
+    axis[widest] = 1.0;
+    for _ in 0..8 {
+        let mut next = [0.0f32; 4];
+        for i in 0..channels {
+            next[i] = (0..channels).map(|j| covariance[i][j] * axis[j]).sum();
+        }
+        let length = next.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if length <= f32::EPSILON {
+            break;
+        }
+        axis = next.map(|v| v / length);
+    }
+
+    let projections = texels.iter().map(|t| {
+        (0..channels)
+            .map(|c| (t[c] - mean[c]) * axis[c])
+            .sum::<f32>()
+    });
+    let (lo, hi) = projections.fold((0.0f32, 0.0f32), |(lo, hi), p| (lo.min(p), hi.max(p)));
+
+    let mut e0 = [255.0f32; 4];
+    let mut e1 = [255.0f32; 4];
+    for c in 0..channels {
+        e0[c] = (mean[c] + axis[c] * lo).clamp(0.0, 255.0);
+        e1[c] = (mean[c] + axis[c] * hi).clamp(0.0, 255.0);
+    }
+    (e0, e1)
+}
+
+/// Least-squares endpoints for fixed texel weights
+fn refine_endpoints(
+    texels: &[[f32; 4]],
+    weights: &[u32],
+    channels: usize,
+) -> Option<([f32; 4], [f32; 4])> {
+    let (mut aa, mut ab, mut bb) = (0.0f32, 0.0f32, 0.0f32);
+    let mut ax = [0.0f32; 4];
+    let mut bx = [0.0f32; 4];
+    for (t, &w) in texels.iter().zip(weights) {
+        let b = w as f32 / 64.0;
+        let a = 1.0 - b;
+        aa += a * a;
+        ab += a * b;
+        bb += b * b;
+        for c in 0..channels {
+            ax[c] += a * t[c];
+            bx[c] += b * t[c];
+        }
+    }
+
+    let det = aa * bb - ab * ab;
+    if det.abs() <= 1e-6 {
+        return None;
+    }
+    let mut e0 = [255.0f32; 4];
+    let mut e1 = [255.0f32; 4];
+    for c in 0..channels {
+        e0[c] = ((bb * ax[c] - ab * bx[c]) / det).clamp(0.0, 255.0);
+        e1[c] = ((aa * bx[c] - ab * ax[c]) / det).clamp(0.0, 255.0);
+    }
+    Some((e0, e1))
+}
+
+/// Endpoint symbols in ASTC order (r0 r1 g0 g1 b0 b1 [a0 a1])
+fn quantize_endpoints(
+    e0: &[f32; 4],
+    e1: &[f32; 4],
+    channels: usize,
+    quant: &EndpointQuant,
+) -> Vec<u32> {
+    let nearest = |value: f32| -> u32 {
+        (0..quant.values.len() as u32)
+            .min_by(|&a, &b| {
+                let da = (quant.values[a as usize] as f32 - value).abs();
+                let db = (quant.values[b as usize] as f32 - value).abs();
+                da.total_cmp(&db)
+            })
+            .unwrap_or(0)
+    };
+    (0..channels)
+        .flat_map(|c| [nearest(e0[c]), nearest(e1[c])])
+        .collect()
+}
+
+fn unquantized(symbols: &[u32], channels: usize, quant: &EndpointQuant) -> ([f32; 4], [f32; 4]) {
+    let mut e0 = [255.0f32; 4];
+    let mut e1 = [255.0f32; 4];
+    for c in 0..channels {
+        e0[c] = quant.values[symbols[2 * c] as usize] as f32;
+        e1[c] = quant.values[symbols[2 * c + 1] as usize] as f32;
+    }
+    (e0, e1)
+}
+
+/// Trits, quints and plain bits of a quantization mode
+fn ise_shape(quant: usize) -> (bool, bool, u32) {
+    let levels = QUANT_LEVELS[quant];
+    if levels.is_power_of_two() {
+        (false, false, bit_count(levels))
+    } else if levels.is_multiple_of(3) {
+        (true, false, bit_count(levels / 3))
+    } else {
+        (false, true, bit_count(levels / 5))
+    }
+}
+
+fn bit_count(levels: u32) -> u32 {
+    levels.trailing_zeros()
+}
+
+/// Bits needed to store `count` values of a quantization mode
+fn ise_bits(quant: usize, count: usize) -> usize {
+    let (trits, quints, bits) = ise_shape(quant);
+    let mut total = count * bits as usize;
+    if trits {
+        total += (count * 8).div_ceil(5);
+    }
+    if quints {
+        total += (count * 7).div_ceil(3);
+    }
+    total
+}
+
+/// Integer sequence encoding: values packed in groups of 5 (trits) or 3 (quints)
+fn write_ise(out: &mut BitWriter, quant: usize, values: &[u32]) {
+    let (trits, quints, bits) = ise_shape(quant);
+    let start = out.len;
+    let mask = (1u32 << bits) - 1;
+
+    if trits {
+        for group in values.chunks(5) {
+            let mut t = [0u32; 5];
+            let mut m = [0u32; 5];
+            for (i, &v) in group.iter().enumerate() {
+                t[i] = v >> bits;
+                m[i] = v & mask;
+            }
+            let packed = trit_block(t);
+            let layout = [(0, 2), (2, 2), (4, 1), (5, 2), (7, 1)];
+            for (i, (shift, len)) in layout.into_iter().enumerate() {
+                out.write(m[i] as u128, bits);
+                out.write(((packed >> shift) & ((1 << len) - 1)) as u128, len);
+            }
+        }
+    } else if quints {
+        for group in values.chunks(3) {
+            let mut q = [0u32; 3];
+            let mut m = [0u32; 3];
+            for (i, &v) in group.iter().enumerate() {
+                q[i] = v >> bits;
+                m[i] = v & mask;
+            }
+            let packed = quint_block(q);
+            let layout = [(0, 3), (3, 2), (5, 2)];
+            for (i, (shift, len)) in layout.into_iter().enumerate() {
+                out.write(m[i] as u128, bits);
+                out.write(((packed >> shift) & ((1 << len) - 1)) as u128, len);
+            }
+        }
+    } else {
+        for &v in values {
+            out.write(v as u128, bits);
+        }
+    }
+
+    // A partial last group only stores the bits of the values it has
+    out.truncate(start + ise_bits(quant, values.len()));
+}
+
+/// Smallest 8-bit code that decodes to five trits
+fn trit_block(trits: [u32; 5]) -> u32 {
+    (0..256)
+        .find(|&code| decode_trits(code) == trits)
+        .unwrap_or(0)
+}
+
+fn decode_trits(code: u32) -> [u32; 5] {
+    let bit = |i: u32| (code >> i) & 1;
+    let (c, t4, t3);
+    if (code >> 2) & 7 == 7 {
+        c = ((code >> 5) & 7) << 2 | (code & 3);
+        t4 = 2;
+        t3 = 2;
+    } else {
+        c = code & 0x1F;
+        if (code >> 5) & 3 == 3 {
+            t4 = 2;
+            t3 = bit(7);
+        } else {
+            t4 = bit(7);
+            t3 = (code >> 5) & 3;
+        }
+    }
+    let cb = |i: u32| (c >> i) & 1;
+    let (t2, t1, t0);
+    if c & 3 == 3 {
+        t2 = 2;
+        t1 = cb(4);
+        t0 = (cb(3) << 1) | (cb(2) & !cb(3) & 1);
+    } else if (c >> 2) & 3 == 3 {
+        t2 = 2;
+        t1 = 2;
+        t0 = c & 3;
+    } else {
+        t2 = cb(4);
+        t1 = (c >> 2) & 3;
+        t0 = (cb(1) << 1) | (cb(0) & !cb(1) & 1);
+    }
+    [t0, t1, t2, t3, t4]
+}
+
+/// Smallest 7-bit code that decodes to three quints
+fn quint_block(quints: [u32; 3]) -> u32 {
+    (0..128)
+        .find(|&code| decode_quints(code) == quints)
+        .unwrap_or(0)
+}
+
+fn decode_quints(code: u32) -> [u32; 3] {
+    let bit = |i: u32| (code >> i) & 1;
+    if (code >> 1) & 3 == 3 && (code >> 5) & 3 == 0 {
+        let q2 = (bit(0) << 2) | ((bit(4) & !bit(0) & 1) << 1) | (bit(3) & !bit(0) & 1);
+        return [4, 4, q2];
+    }
+    let (q2, c) = if (code >> 1) & 3 == 3 {
+        (4, ((code >> 3) & 3) << 3 | ((!code >> 5) & 3) << 1 | bit(0))
+    } else {
+        ((code >> 5) & 3, code & 0x1F)
+    };
+    let (q1, q0) = if c & 7 == 5 {
+        (4, (c >> 3) & 3)
+    } else {
+        ((c >> 3) & 3, c & 7)
+    };
+    [q0, q1, q2]
+}
+
+/// Unquantized 8-bit value of an endpoint symbol
+fn unquantize_color(quant: usize, symbol: u32) -> u8 {
+    let (trits, quints, bits) = ise_shape(quant);
+    if !trits && !quints {
+        return replicate(symbol, bits, 8) as u8;
+    }
+
+    let d = symbol >> bits;
+    let m = symbol & ((1 << bits) - 1);
+    // Bit patterns (MSB first) and multipliers from the ASTC color unquantization table
+    let (pattern, c) = match (trits, bits) {
+        (true, 1) => ("000000000", 204),
+        (true, 2) => ("b000b0bb0", 93),
+        (true, 3) => ("cb000cbcb", 44),
+        (true, 4) => ("dcb000dcb", 22),
+        (true, 5) => ("edcb000ed", 11),
+        (true, _) => ("fedcb000f", 5),
+        (false, 1) => ("000000000", 113),
+        (false, 2) => ("b0000bb00", 54),
+        (false, 3) => ("cb0000cbc", 26),
+        (false, 4) => ("dcb0000dc", 13),
+        (false, _) => ("edcb0000e", 6),
+    };
+    let b = pattern.bytes().fold(0u32, |acc, ch| {
+        let value = if ch == b'0' {
+            0
+        } else {
+            (m >> (ch - b'a')) & 1
+        };
+        (acc << 1) | value
+    });
+    let a = if m & 1 == 1 { 0x1FF } else { 0 };
+    let t = (d * c + b) ^ a;
+    ((a & 0x80) | (t >> 2)) as u8
+}
+
+/// Scale a `bits`-bit value to `to` bits by repeating its bit pattern
+fn replicate(value: u32, bits: u32, to: u32) -> u32 {
+    let mut out = 0;
+    let mut filled = 0;
+    while filled < to {
+        out = (out << bits) | value;
+        filled += bits;
+    }
+    out >> (filled - to)
+}
+
+#[derive(Default)]
+struct BitWriter {
+    value: u128,
+    len: usize,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u128, bits: u32) {
+        if bits > 0 {
+            self.value |= (value & ((1u128 << bits) - 1)) << self.len;
+            self.len += bits as usize;
+        }
+    }
+
+    fn truncate(&mut self, len: usize) {
+        if len < 128 {
+            self.value &= (1u128 << len) - 1;
+        }
+        self.len = len;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lowest PSNR (dB) accepted for a smooth gradient at each block size
+    ///
+    /// Single-partition blocks fit one color line per block, so two-axis gradients land a few
+    /// dB under what dual-plane and partitioned encodes reach.
+    const PSNR_FLOORS: [(AstcBlockSize, f64); 3] = [
+        (AstcBlockSize::Block4x4, 39.0),
+        (AstcBlockSize::Block6x6, 35.0),
+        (AstcBlockSize::Block8x8, 32.0),
+    ];
+
+    /// Smooth two-axis color gradient with an alpha ramp
+    fn gradient(size: u32) -> RgbaImage {
+        RgbaImage::from_fn(size, size, |x, y| {
+            let (u, v) = (x * 255 / (size - 1), y * 255 / (size - 1));
+            image::Rgba([u as u8, v as u8, ((u + v) / 2) as u8, (255 - v / 2) as u8])
+        })
+    }
+
+    /// The reference decoder's reading of `encoded`
+    fn decode(encoded: &[u8], width: u32, height: u32, block: AstcBlockSize) -> RgbaImage {
+        let (bw, bh) = block.dimensions();
+        let mut decoded = RgbaImage::new(width, height);
+        astc_decode::astc_decode(
+            encoded,
+            width,
+            height,
+            astc_decode::Footprint::new(bw, bh),
+            |x, y, texel| {
+                decoded.put_pixel(x, y, image::Rgba(texel));
+            },
+        )
+        .unwrap();
+        decoded
+    }
+
+    fn psnr(a: &RgbaImage, b: &RgbaImage) -> f64 {
+        let squared: f64 = a
+            .as_raw()
+            .iter()
+            .zip(b.as_raw())
+            .map(|(&a, &b)| (a as f64 - b as f64).powi(2))
+            .sum();
+        let mse = squared / a.as_raw().len() as f64;
+        10.0 * (255.0 * 255.0 / mse.max(f64::EPSILON)).log10()
+    }
+
+    #[test]
+    fn test_round_trip_psnr_per_block_size() {
+        let img = gradient(64);
+        for (block, floor) in PSNR_FLOORS {
+            for quality in [
+                QualityPreset::Fast,
+                QualityPreset::Balanced,
+                QualityPreset::Ultra,
+            ] {
+                let encoded = encode_astc(&img, block, quality);
+                let (bw, bh) = block.dimensions();
+                assert_eq!(
+                    encoded.len(),
+                    (64u32.div_ceil(bw) * 64u32.div_ceil(bh)) as usize * BLOCK_BYTES
+                );
+                let psnr = psnr(&img, &decode(&encoded, 64, 64, block));
+                assert!(
+                    psnr >= floor,
+                    "{:?} {:?}: {:.1} dB, floor {} dB",
+                    block,
+                    quality,
+                    psnr,
+                    floor
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip_partial_edge_blocks() {
+        // No block size divides 61, so edge blocks cover texels outside the image
+        let img = image::imageops::crop_imm(&gradient(64), 0, 0, 61, 61).to_image();
+        for (block, floor) in PSNR_FLOORS {
+            let encoded = encode_astc(&img, block, QualityPreset::Balanced);
+            let psnr = psnr(&img, &decode(&encoded, 61, 61, block));
+            assert!(
+                psnr >= floor,
+                "{:?}: {:.1} dB, floor {} dB",
+                block,
+                psnr,
+                floor
+            );
+        }
+    }
+
+    #[test]
+    fn test_opaque_blocks_decode_opaque() {
+        let img = RgbaImage::from_fn(16, 16, |x, y| {
+            image::Rgba([(x * 16) as u8, (y * 16) as u8, 128, 255])
+        });
+        let encoded = encode_astc(&img, AstcBlockSize::Block4x4, QualityPreset::Balanced);
+        let decoded = decode(&encoded, 16, 16, AstcBlockSize::Block4x4);
+        assert!(decoded.pixels().all(|p| p[3] == 255));
+    }
+
+    #[test]
+    fn test_astc_file_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in.png");
+        let output = dir.path().join("out.astc");
+        gradient(20).save(&input).unwrap();
+        let config = AstcConfig {
+            block: Some(AstcBlockSize::Block6x6),
+            quality: QualityPreset::Balanced,
+            generate_mipmaps: false,
+            max_size: None,
+            zstd_level: None,
+            srgb: true,
+            renormalize: false,
+            mip_filter: MipFilter::default(),
+            alpha_coverage: None,
+        };
+        compress_to_astc(&input, &output, &config).unwrap();
+        let data = std::fs::read(&output).unwrap();
+        assert_eq!(validate_astc(&data).unwrap(), (20, 20));
+        assert_eq!(&data[4..7], &[6, 6, 1]);
+        assert_eq!(data.len(), 16 + 4 * 4 * BLOCK_BYTES);
+    }
+}
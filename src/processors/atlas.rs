@@ -1,14 +1,21 @@
 use anyhow::{Context, Result};
-use image::{GenericImageView, RgbaImage};
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::Path;
+use globset::{Glob, GlobSetBuilder};
+use image::{DynamicImage, ImageFormat, RgbaImage};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::time::Instant;
-use texture_packer::{TexturePacker, TexturePackerConfig};
-use texture_packer::exporter::ImageExporter;
-use texture_packer::importer::ImageImporter;
+use walkdir::WalkDir;
 
-use super::ProcessingStats;
+use crate::cli::{AstcBlockSize, AtlasPacker, FrameKeyFormat, OutputFormat, PreviewFormat, QualityPreset};
+use crate::config::{load_sidecar, RuleConfig};
+use crate::utils::hash_inputs;
+
+use super::{
+    animated_gif_frames, GifFrame, group_animations, manifest_path, nine_slice_for, opaque_bounds, open_image, premultiply_alpha,
+    process_image, variant_path, write_animation_preview, ImageProcessorConfig, DEFAULT_VARIANT_NAME, NineSlice, Placement, ProcessingStats, RectPacker,
+};
+
+pub use crate::runtime::{AtlasMetadata, AtlasPage, SpriteAnimation, SpriteFrame};
 
 /// Configuration for atlas generation
 #[derive(Debug, Clone)]
@@ -16,8 +23,59 @@ pub struct AtlasConfig {
     pub max_width: u32,
     pub max_height: u32,
     pub padding: u32,
+    /// Pixels of each sprite's edge repeated around it, against filtering seams
+    pub extrude: u32,
     pub trim: bool,
+    /// Alpha values up to this count as transparent when trimming
+    pub trim_threshold: u8,
     pub allow_rotation: bool,
+    /// Round page dimensions up to a power of two
+    pub power_of_two: bool,
+    /// Round page dimensions up to a multiple of this (after `power_of_two`)
+    pub multiple_of: Option<u32>,
+    /// Algorithm placing sprites on a page
+    pub packer: AtlasPacker,
+    /// Orders sprites the packer would otherwise rank equally
+    pub seed: u64,
+    /// Output format for atlas pages (inferred from the output extension if unset)
+    pub format: Option<OutputFormat>,
+    /// Quality preset pages are encoded with when `format` is set
+    pub quality: QualityPreset,
+    pub generate_mipmaps: bool,
+    /// Zstandard level for KTX2 supercompression (None = uncompressed)
+    pub ktx2_zstd_level: Option<i32>,
+    /// ASTC block size for `.astc` pages; for KTX2 it switches the payload from UASTC to ASTC
+    pub astc_block: Option<AstcBlockSize>,
+    /// Maximum encoded size of a single page in bytes; pages over budget are split
+    pub max_page_bytes: Option<u64>,
+    /// How frame keys are derived from sprite paths
+    pub key_format: FrameKeyFormat,
+    /// Prepended to every frame key
+    pub key_prefix: String,
+    /// Appended to every frame key
+    pub key_suffix: String,
+    /// Write an animated preview of each animation into this directory
+    pub preview_dir: Option<PathBuf>,
+    pub preview_format: PreviewFormat,
+    /// Frame duration in milliseconds for frames without a `frame_duration` sidecar
+    pub frame_duration_ms: u32,
+    /// Multiply page colors by alpha
+    pub premultiply_alpha: bool,
+    /// Detect nine-slice borders of sprites whose sidecar doesn't set `nine_slice`
+    pub nine_slice: bool,
+    /// Include images in subdirectories of the input directory
+    pub recursive: bool,
+    /// Globs of images to leave out, matched against paths relative to `exclude_root`
+    pub exclude: Vec<String>,
+    /// Directory `exclude` is relative to (default: the input directory)
+    pub exclude_root: Option<PathBuf>,
+    /// Scales of downscaled copies of the pages to write as well, e.g. 0.5
+    pub variants: Vec<f32>,
+    /// File name template of the copies, with `{name}` and `{scale}`
+    pub variant_name: String,
+    /// Earlier metadata of this atlas: its frames stay where they are when they still fit their
+    /// region, and its pages keep their sizes
+    pub update: Option<AtlasMetadata>,
 }
 
 impl Default for AtlasConfig {
@@ -26,188 +84,892 @@ impl Default for AtlasConfig {
             max_width: 2048,
             max_height: 2048,
             padding: 2,
+            extrude: 0,
             trim: false,
+            trim_threshold: 0,
             allow_rotation: false,
+            power_of_two: false,
+            multiple_of: None,
+            packer: AtlasPacker::default(),
+            seed: 0,
+            format: None,
+            quality: QualityPreset::Balanced,
+            generate_mipmaps: false,
+            ktx2_zstd_level: None,
+            astc_block: None,
+            max_page_bytes: None,
+            key_format: FrameKeyFormat::default(),
+            key_prefix: String::new(),
+            key_suffix: String::new(),
+            preview_dir: None,
+            preview_format: PreviewFormat::default(),
+            frame_duration_ms: 100,
+            premultiply_alpha: false,
+            nine_slice: false,
+            recursive: false,
+            exclude: Vec::new(),
+            exclude_root: None,
+            variants: Vec::new(),
+            variant_name: DEFAULT_VARIANT_NAME.to_string(),
+            update: None,
         }
     }
 }
 
-/// Metadata for a sprite in the atlas
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SpriteFrame {
-    pub x: u32,
-    pub y: u32,
-    pub width: u32,
-    pub height: u32,
-    pub rotated: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub source_width: Option<u32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub source_height: Option<u32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub trim_x: Option<u32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub trim_y: Option<u32>,
-}
-
-/// Atlas metadata (JSON output)
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AtlasMetadata {
-    pub image: String,
-    pub width: u32,
-    pub height: u32,
-    pub frames: HashMap<String, SpriteFrame>,
-}
-
 /// Result of atlas generation
 pub struct AtlasResult {
+    /// First page image
     pub image: RgbaImage,
     pub metadata: AtlasMetadata,
+    /// Metadata of the scaled copies written for `variants`, with their scale
+    pub variants: Vec<(f32, AtlasMetadata)>,
+    /// Animation previews written to `preview_dir`
+    pub previews: Vec<PathBuf>,
+    /// Share of the page area covered by sprites (0-1)
+    pub efficiency: f64,
+    /// Frames whose packed pixels are identical to an earlier frame's, and share its region
+    pub duplicates: usize,
+    /// Page pixels the duplicate frames would have taken
+    pub saved_area: u64,
+    /// Frames left in place from `update`
+    pub kept: usize,
     pub stats: ProcessingStats,
 }
 
+/// A decoded sprite ready to be packed
+pub struct AtlasSprite {
+    /// Frame key in the metadata
+    pub key: String,
+    /// Upright pixels
+    pub image: DynamicImage,
+    /// Display time in milliseconds as an animation frame (default: `frame_duration_ms`)
+    pub duration_ms: Option<u32>,
+    pub nine_slice: Option<NineSlice>,
+    /// Anchor normalized in the untrimmed sprite
+    pub pivot: Option<[f32; 2]>,
+}
+
 /// Generate a sprite atlas from a directory of images
+///
+/// Animated GIFs add one sprite per frame, keyed like numbered sprites (`<key>_000`,
+/// `<key>_001`, ...) so they form an animation timed by the GIF's frame delays.
 pub fn generate_atlas(
     input_dir: &Path,
     output_image: &Path,
     config: &AtlasConfig,
 ) -> Result<AtlasResult> {
     let start = Instant::now();
+    let image_paths = atlas_image_paths(input_dir, config)?;
+    if image_paths.is_empty() {
+        anyhow::bail!("No image files found in directory: {}", input_dir.display());
+    }
+
+    // Each sprite's own settings come from its sidecar
+    let sources = image_paths
+        .into_iter()
+        .map(|path| Ok((load_sidecar(&path)?.unwrap_or_default(), path)))
+        .collect::<Result<Vec<_>>>()?;
+    let (sprites, total_input_size) = load_atlas_sprites(input_dir, &sources, config)?;
+
+    let result = pack_atlas(&sprites, output_image, config)?;
+    Ok(AtlasResult {
+        stats: ProcessingStats {
+            original_size: total_input_size,
+            processing_time_ms: start.elapsed().as_millis() as u64,
+            ..result.stats
+        },
+        ..result
+    })
+}
+
+/// Load sprites from image files with their settings, keyed relative to `base_dir`
+///
+/// Returns the sprites and the total size of the files read.
+pub fn load_atlas_sprites(
+    base_dir: &Path,
+    sources: &[(RuleConfig, PathBuf)],
+    config: &AtlasConfig,
+) -> Result<(Vec<AtlasSprite>, u64)> {
     let mut total_input_size: u64 = 0;
+    let mut sprites = Vec::with_capacity(sources.len());
+    let mut keys: HashMap<String, &Path> = HashMap::new();
+    for (settings, path) in sources {
+        let name = frame_key(base_dir, path, config);
+        let metadata = std::fs::metadata(path)?;
+        total_input_size += metadata.len();
 
-    // Configure texture packer
-    let packer_config = TexturePackerConfig {
-        max_width: config.max_width,
-        max_height: config.max_height,
-        allow_rotation: config.allow_rotation,
-        border_padding: config.padding,
-        texture_padding: config.padding,
-        trim: config.trim,
-        ..Default::default()
-    };
+        // Borders come from the sprite's settings, or are detected for every sprite with --nine-slice
+        let mut settings = settings.clone();
+        if config.nine_slice && settings.nine_slice.is_none() {
+            settings.nine_slice = Some(true);
+        }
 
-    let mut packer = TexturePacker::new_skyline(packer_config);
+        // Orientation is normalized here so frames never pack flipped or rotated
+        let mut loaded = match animated_gif_frames(path)? {
+            Some(frames) => gif_sprites(&name, frames, config),
+            None => vec![AtlasSprite {
+                key: name,
+                image: open_image(path)?,
+                duration_ms: None,
+                nine_slice: None,
+                pivot: None,
+            }],
+        };
+        for sprite in &mut loaded {
+            if let Some(existing) = keys.insert(sprite.key.clone(), path) {
+                anyhow::bail!(
+                    "Duplicate frame key '{}' for '{}' and '{}'. Use --key-format relpath or rename one of the sprites.",
+                    sprite.key,
+                    existing.display(),
+                    path.display()
+                );
+            }
+            sprite.duration_ms = settings.frame_duration.or(sprite.duration_ms);
+            sprite.nine_slice = nine_slice_for(path, &settings, Some(&sprite.image))?.map(|slice| slice.borders);
+            sprite.pivot = settings.pivot;
+        }
+        sprites.extend(loaded);
+    }
+    Ok((sprites, total_input_size))
+}
 
-    // Find all image files in the directory
+/// Sprite images in a directory (and its subdirectories when `recursive`) that aren't
+/// excluded, sorted by path
+pub fn atlas_image_paths(dir: &Path, config: &AtlasConfig) -> Result<Vec<PathBuf>> {
     let image_extensions = ["png", "jpg", "jpeg", "bmp", "gif", "tga"];
-    let mut image_paths: Vec<_> = std::fs::read_dir(input_dir)
-        .with_context(|| format!("Failed to read directory: {}", input_dir.display()))?
-        .filter_map(|entry| entry.ok())
-        .map(|entry| entry.path())
-        .filter(|path| {
-            path.extension()
-                .and_then(|e| e.to_str())
-                .map(|e| image_extensions.contains(&e.to_lowercase().as_str()))
-                .unwrap_or(false)
-        })
-        .collect();
+    let root = config.exclude_root.as_deref().unwrap_or(dir);
+    let mut builder = GlobSetBuilder::new();
+    for pattern in &config.exclude {
+        let glob = Glob::new(pattern).with_context(|| format!("Invalid exclude pattern: {}", pattern))?;
+        builder.add(glob);
+    }
+    let excluded = builder.build()?;
+
+    let max_depth = if config.recursive { usize::MAX } else { 1 };
+    let mut image_paths = Vec::new();
+    for entry in WalkDir::new(dir).max_depth(max_depth) {
+        let entry = entry.with_context(|| format!("Failed to read directory: {}", dir.display()))?;
+        let path = entry.path();
+        let is_image = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| image_extensions.contains(&e.to_lowercase().as_str()));
+        let relative = manifest_path(path.strip_prefix(root).unwrap_or(path));
+        if entry.file_type().is_file() && is_image && !excluded.is_match(&relative) {
+            image_paths.push(path.to_path_buf());
+        }
+    }
 
     // Sort for deterministic output
     image_paths.sort();
+    Ok(image_paths)
+}
 
-    if image_paths.is_empty() {
-        anyhow::bail!("No image files found in directory: {}", input_dir.display());
+/// Sprites for the frames of an animated GIF whose frame key is `name`
+pub fn gif_sprites(name: &str, frames: Vec<GifFrame>, config: &AtlasConfig) -> Vec<AtlasSprite> {
+    let base = name.strip_suffix(config.key_suffix.as_str()).unwrap_or(name);
+    frames
+        .into_iter()
+        .enumerate()
+        .map(|(index, (image, delay))| AtlasSprite {
+            key: format!("{}_{:03}{}", base, index, config.key_suffix),
+            image: DynamicImage::ImageRgba8(image),
+            duration_ms: delay,
+            nine_slice: None,
+            pivot: None,
+        })
+        .collect()
+}
+
+/// Pack decoded sprites into atlas pages at `output_image` (numbered when split)
+///
+/// `stats.original_size` is left at zero for the caller, who knows what the sprites were
+/// loaded from.
+pub fn pack_atlas(sprites: &[AtlasSprite], output_image: &Path, config: &AtlasConfig) -> Result<AtlasResult> {
+    let start = Instant::now();
+    if sprites.is_empty() {
+        anyhow::bail!("No sprites to pack into {}", output_image.display());
+    }
+    if let Some(scale) = config.variants.iter().find(|s| !s.is_finite() || **s <= 0.0 || **s > 1.0) {
+        anyhow::bail!("Invalid atlas variant scale {}: variants are downscaled copies (0-1)", scale);
+    }
+    if !config.variants.is_empty() && !config.variant_name.contains("{scale}") {
+        anyhow::bail!("Variant name template \"{}\" must contain {{scale}}", config.variant_name);
     }
 
-    // Pack each image
-    for path in &image_paths {
-        let metadata = std::fs::metadata(path)?;
-        total_input_size += metadata.len();
+    // Sprites are packed as their opaque bounds when trimming
+    let pixels: Vec<RgbaImage> = sprites.iter().map(|sprite| sprite.image.to_rgba8()).collect();
+    let bounds: Vec<(u32, u32, u32, u32)> = sprites
+        .iter()
+        .zip(&pixels)
+        .map(|(sprite, rgba)| match config.trim {
+            true => opaque_bounds(rgba, config.trim_threshold).with_context(|| match config.trim_threshold {
+                0 => format!("Failed to pack '{}': the sprite is fully transparent", sprite.key),
+                threshold => format!("Failed to pack '{}': no pixel has alpha above {}", sprite.key, threshold),
+            }),
+            false => Ok((0, 0, rgba.width(), rgba.height())),
+        })
+        .collect::<Result<_>>()?;
 
-        let texture = ImageImporter::import_from_file(path)
-            .map_err(|e| anyhow::anyhow!("Failed to import image '{}': {}", path.display(), e))?;
+    // Sprites whose packed pixels match an earlier one's are packed once and share its region
+    let originals = duplicate_regions(&pixels, &bounds);
 
-        let name = path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("unknown")
-            .to_string();
+    // The free-rectangle packers place large sprites best when they come first. Ties are
+    // broken by the seeded hash of the frame key rather than input order, so the order only
+    // depends on the sprites themselves
+    let mut order: Vec<usize> = (0..sprites.len()).filter(|&i| originals[i] == i).collect();
+    if config.packer != AtlasPacker::Skyline {
+        order.sort_by_cached_key(|&i| {
+            let (_, _, width, height) = bounds[i];
+            let tie = hash_inputs(&[&config.seed.to_le_bytes(), sprites[i].key.as_bytes()]);
+            (std::cmp::Reverse(width as u64 * height as u64), std::cmp::Reverse(width.max(height)), tie, &sprites[i].key)
+        });
+    }
 
-        packer.pack_own(name, texture).map_err(|e| {
-            anyhow::anyhow!(
-                "Failed to pack '{}': {:?}. Try increasing atlas size or reducing sprite count.",
-                path.display(),
-                e
-            )
-        })?;
+    // Pages may only grow to the largest size the constraints allow, so rounding them up
+    // never passes the maximum
+    let max_size = (constrained_max(config.max_width, config), constrained_max(config.max_height, config));
+    if max_size.0 == 0 || max_size.1 == 0 {
+        anyhow::bail!(
+            "No atlas size up to {}x{} meets the power-of-two/multiple-of constraints",
+            config.max_width,
+            config.max_height
+        );
     }
 
-    // Export the atlas image
-    let exporter = ImageExporter::export(&packer, None)
-        .map_err(|e| anyhow::anyhow!("Failed to export atlas image: {}", e))?;
+    // Each sprite takes its padding on the right and bottom and its extrusion on every side;
+    // the page border adds the rest
+    let cell = config.padding + 2 * config.extrude;
+    let area = (max_size.0.saturating_sub(config.padding), max_size.1.saturating_sub(config.padding));
+
+    let hashes: HashMap<&str, String> = sprites
+        .iter()
+        .map(|sprite| (sprite.key.as_str(), frame_hash(&sprite.image)))
+        .collect();
 
     // Create output directory if needed
     if let Some(parent) = output_image.parent() {
         std::fs::create_dir_all(parent)?;
     }
 
-    // Save the atlas image
-    exporter.save(output_image)
-        .with_context(|| format!("Failed to save atlas image: {}", output_image.display()))?;
+    // A sprite too large for an empty page can't go on any page
+    for (sprite, &(_, _, width, height)) in sprites.iter().zip(&bounds) {
+        let (width, height) = (width + cell, height + cell);
+        let fits = |w: u32, h: u32| w <= area.0 && h <= area.1;
+        let rotated_fits = config.allow_rotation && fits(height, width);
+        if !fits(width, height) && !rotated_fits {
+            anyhow::bail!(
+                "Failed to pack '{}': it doesn't fit in {}x{}. Try increasing atlas size.",
+                sprite.key,
+                max_size.0,
+                max_size.1
+            );
+        }
+    }
+
+    // Sprites of an updated atlas stay in their old region if they still pack to its size
+    let kept = match &config.update {
+        Some(previous) => kept_placements(sprites, &order, &bounds, previous, config),
+        None => Vec::new(),
+    };
+    let kept_count = kept.iter().map(Vec::len).sum();
+
+    // Pack pages: pack -> encode -> measure, shrinking the page until it fits the byte budget.
+    // Sprites that don't fit on a page move on to the next one.
+    let mut pages: Vec<PackedPage> = Vec::new();
+    let mut remaining = order;
+    remaining.retain(|index| !kept.iter().flatten().any(|(kept, _)| kept == index));
+
+    while !remaining.is_empty() || pages.len() < kept.len() {
+        let page_index = pages.len();
+        let page_path = page_file_path(output_image, page_index);
+        let reserved = kept.get(page_index).map(Vec::as_slice).unwrap_or_default();
+        // Earlier pages keep their size, so normalized coordinates of kept frames don't change
+        let fixed_size = config
+            .update
+            .as_ref()
+            .and_then(|previous| previous.pages.get(page_index))
+            .map(|page| (page.width, page.height));
+        let page_area = match fixed_size {
+            Some((width, height)) => (width.saturating_sub(config.padding), height.saturating_sub(config.padding)),
+            None => area,
+        };
+        let mut limit = reserved.len() + remaining.len();
+        // Kept sprites are never moved off their page to meet the budget
+        let floor = reserved.len().max(1);
+
+        let page = loop {
+            let mut placed = place_sprites(&remaining, &bounds, page_area, limit, reserved, config);
+            if config.power_of_two && fixed_size.is_none() {
+                placed = shrink_power_of_two(placed, &bounds, max_size, config);
+            }
+
+            let size = fixed_size.unwrap_or_else(|| {
+                let natural = page_size(&placed, config);
+                (round_page_size(natural.0, config), round_page_size(natural.1, config))
+            });
+            let image = compose_page(&pixels, &bounds, &placed, size, config.extrude);
+            let size = save_page(&image, &page_path, config)?;
+            let count = placed.len();
+
+            let over_budget = config.max_page_bytes.filter(|&budget| size > budget);
+            match over_budget {
+                Some(budget) if count > floor => {
+                    // Estimate how many sprites fit, always dropping at least one
+                    let estimate = (count as f64 * budget as f64 / size as f64 * 0.95) as usize;
+                    limit = estimate.clamp(floor, count - 1);
+                    tracing::debug!(
+                        "Atlas page {} is {} bytes (budget {}), retrying with {} sprites",
+                        page_index, size, budget, limit
+                    );
+                }
+                Some(budget) if !reserved.is_empty() => {
+                    tracing::warn!(
+                        "Atlas page {} exceeds the page budget with only its kept sprites ({} > {} bytes)",
+                        page_index, size, budget
+                    );
+                    break PackedPage { placed, image, size };
+                }
+                Some(budget) => {
+                    tracing::warn!(
+                        "Sprite '{}' alone exceeds the page budget ({} > {} bytes)",
+                        sprites[placed[0].0].key, size, budget
+                    );
+                    break PackedPage { placed, image, size };
+                }
+                None => break PackedPage { placed, image, size },
+            }
+        };
+
+        remaining.retain(|index| !page.placed.iter().any(|(placed, _)| placed == index));
+        pages.push(page);
+    }
+
+    // A single page keeps the requested output name
+    let page_paths: Vec<PathBuf> = if pages.len() == 1 {
+        let page_path = page_file_path(output_image, 0);
+        std::fs::rename(&page_path, output_image)
+            .with_context(|| format!("Failed to save atlas image: {}", output_image.display()))?;
+        vec![output_image.to_path_buf()]
+    } else {
+        (0..pages.len()).map(|i| page_file_path(output_image, i)).collect()
+    };
 
     // Build metadata
-    let mut frames = HashMap::new();
-    for (name, frame) in packer.get_frames() {
+    let sources: HashMap<&str, &AtlasSprite> = sprites.iter().map(|sprite| (sprite.key.as_str(), sprite)).collect();
+    let mut frames = BTreeMap::new();
+    let mut located = HashMap::new();
+    let mut page_meta = Vec::with_capacity(pages.len());
+
+    for (index, (page, path)) in pages.iter().zip(&page_paths).enumerate() {
+        page_meta.push(AtlasPage {
+            image: file_name(path),
+            width: page.image.width(),
+            height: page.image.height(),
+            size: page.size,
+        });
+
+        for &(sprite_index, placement) in &page.placed {
+            located.insert(sprite_index, (index, placement));
+        }
+    }
+
+    // Duplicates take their original's region and keep their own trim, hash and settings
+    for (sprite_index, sprite) in sprites.iter().enumerate() {
+        let (page, placement) = located[&originals[sprite_index]];
+        let (trim_x, trim_y, _, _) = bounds[sprite_index];
         frames.insert(
-            name.clone(),
+            sprite.key.clone(),
             SpriteFrame {
-                x: frame.frame.x,
-                y: frame.frame.y,
-                width: frame.frame.w,
-                height: frame.frame.h,
-                rotated: frame.rotated,
-                source_width: if frame.trimmed {
-                    Some(frame.source.w)
-                } else {
-                    None
-                },
-                source_height: if frame.trimmed {
-                    Some(frame.source.h)
-                } else {
-                    None
-                },
-                trim_x: if frame.trimmed {
-                    Some(frame.source.x)
-                } else {
-                    None
-                },
-                trim_y: if frame.trimmed {
-                    Some(frame.source.y)
-                } else {
-                    None
-                },
+                x: placement.x,
+                y: placement.y,
+                width: placement.width,
+                height: placement.height,
+                rotated: placement.rotated,
+                page,
+                source_width: config.trim.then(|| sprite.image.width()),
+                source_height: config.trim.then(|| sprite.image.height()),
+                trim_x: config.trim.then_some(trim_x),
+                trim_y: config.trim.then_some(trim_y),
+                hash: hashes.get(sprite.key.as_str()).cloned(),
+                nine_slice: sprite.nine_slice,
+                pivot: sprite.pivot,
             },
         );
     }
 
-    let output_size = std::fs::metadata(output_image)?.len();
-    let processing_time_ms = start.elapsed().as_millis() as u64;
+    // Group numbered frames into animations, previewing them from the untrimmed sources
+    let mut animations = BTreeMap::new();
+    let mut previews = Vec::new();
+    for (name, keys) in group_animations(frames.keys().map(String::as_str), &config.key_suffix) {
+        let durations: Vec<u32> = keys
+            .iter()
+            .map(|key| sources[key.as_str()].duration_ms.unwrap_or(config.frame_duration_ms).max(1))
+            .collect();
+
+        if let Some(dir) = &config.preview_dir {
+            let preview_frames: Vec<(&DynamicImage, u32)> = keys
+                .iter()
+                .zip(&durations)
+                .map(|(key, &duration)| (&sources[key.as_str()].image, duration))
+                .collect();
+            let preview = dir.join(format!("{}.{}", name, config.preview_format));
+            write_animation_preview(&preview_frames, &preview, config.preview_format)?;
+            previews.push(preview);
+        }
+
+        animations.insert(name, SpriteAnimation { frames: keys, durations });
+    }
+
+    let output_size = pages.iter().map(|p| p.size).sum();
+    let sprite_area: u64 = pages
+        .iter()
+        .flat_map(|page| &page.placed)
+        .map(|(_, p)| p.width as u64 * p.height as u64)
+        .sum();
+    let page_area: u64 = pages.iter().map(|p| p.image.width() as u64 * p.image.height() as u64).sum();
+    let duplicates: Vec<usize> = (0..sprites.len()).filter(|&i| originals[i] != i).collect();
+    let saved_area = duplicates
+        .iter()
+        .map(|&i| bounds[i].2 as u64 * bounds[i].3 as u64)
+        .sum();
 
     let metadata = AtlasMetadata {
-        image: output_image
-            .file_name()
-            .and_then(|s| s.to_str())
-            .unwrap_or("atlas.png")
-            .to_string(),
-        width: exporter.width(),
-        height: exporter.height(),
+        image: page_meta[0].image.clone(),
+        width: page_meta[0].width,
+        height: page_meta[0].height,
+        pages: page_meta,
         frames,
+        animations,
+        premultiplied_alpha: config.premultiply_alpha,
     };
 
-    // Load the saved image for return
-    let atlas_image = image::open(output_image)?.to_rgba8();
+    let variants = config
+        .variants
+        .iter()
+        .filter(|&&scale| scale != 1.0)
+        .map(|&scale| {
+            let variant = scaled_variant(&metadata, &pages, &page_paths, &pixels, &bounds, scale, config)?;
+            Ok((scale, variant))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let processing_time_ms = start.elapsed().as_millis() as u64;
 
     Ok(AtlasResult {
-        image: atlas_image,
+        image: pages[0].image.to_rgba8(),
         metadata,
+        variants,
+        previews,
+        efficiency: sprite_area as f64 / page_area.max(1) as f64,
+        duplicates: duplicates.len(),
+        saved_area,
+        kept: kept_count,
         stats: ProcessingStats {
-            original_size: total_input_size,
+            original_size: 0,
             output_size,
             processing_time_ms,
+            quality: None,
         },
     })
 }
 
+/// Frame key for a sprite, e.g. `ui/button.png` (relpath), `button` (stem) or `ui/button` (path-no-ext)
+pub fn frame_key(input_dir: &Path, path: &Path, config: &AtlasConfig) -> String {
+    let relative = path.strip_prefix(input_dir).unwrap_or(path);
+    let key = match config.key_format {
+        FrameKeyFormat::Stem => relative
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("unknown")
+            .to_string(),
+        FrameKeyFormat::Relpath => manifest_path(relative),
+        FrameKeyFormat::PathNoExt => manifest_path(&relative.with_extension("")),
+    };
+    format!("{}{}{}", config.key_prefix, key, config.key_suffix)
+}
+
+/// Content hash of a sprite's pixels, independent of the source file encoding
+///
+/// Hashes the untrimmed RGBA8 pixels and dimensions, so re-saving a PNG with different
+/// compression keeps the hash while any pixel change alters it.
+pub fn frame_hash(image: &DynamicImage) -> String {
+    let rgba = image.to_rgba8();
+    let hash = hash_inputs(&[
+        &rgba.width().to_le_bytes(),
+        &rgba.height().to_le_bytes(),
+        rgba.as_raw(),
+    ]);
+    format!("{:016x}", hash)
+}
+
+/// For each sprite, the first sprite whose packed region has the same pixels (itself if none)
+fn duplicate_regions(pixels: &[RgbaImage], bounds: &[(u32, u32, u32, u32)]) -> Vec<usize> {
+    let region = |i: usize| {
+        let (x, y, width, height) = bounds[i];
+        image::imageops::crop_imm(&pixels[i], x, y, width, height).to_image()
+    };
+
+    let mut seen: HashMap<u64, Vec<usize>> = HashMap::new();
+    let mut originals = Vec::with_capacity(pixels.len());
+    for i in 0..pixels.len() {
+        let pixels = region(i);
+        let hash = hash_inputs(&[&pixels.width().to_le_bytes(), &pixels.height().to_le_bytes(), pixels.as_raw()]);
+        let candidates = seen.entry(hash).or_default();
+        // Hashes only narrow down the candidates; the pixels decide
+        match candidates.iter().find(|&&j| region(j) == pixels) {
+            Some(&j) => originals.push(j),
+            None => {
+                candidates.push(i);
+                originals.push(i);
+            }
+        }
+    }
+    originals
+}
+
+/// Sprites (of `order`) that keep their region from `previous`, per page
+///
+/// A sprite keeps its region when it packs to the same size and the region is still on the
+/// page; a region is given to the first sprite claiming it, in case two frames shared it.
+fn kept_placements(
+    sprites: &[AtlasSprite],
+    order: &[usize],
+    bounds: &[(u32, u32, u32, u32)],
+    previous: &AtlasMetadata,
+    config: &AtlasConfig,
+) -> Vec<Vec<(usize, Placement)>> {
+    let mut kept = vec![Vec::new(); previous.pages.len()];
+    let mut claimed = HashSet::new();
+    for &index in order {
+        let Some(frame) = previous.frames.get(&sprites[index].key) else {
+            continue;
+        };
+        let (_, _, width, height) = bounds[index];
+        let size = if frame.rotated { (height, width) } else { (width, height) };
+        let Some(page) = previous.pages.get(frame.page) else {
+            continue;
+        };
+        let on_page = frame.x + frame.width <= page.width && frame.y + frame.height <= page.height;
+        if (frame.width, frame.height) != size
+            || !on_page
+            || (frame.rotated && !config.allow_rotation)
+            || !claimed.insert((frame.page, frame.x, frame.y))
+        {
+            continue;
+        }
+        kept[frame.page].push((
+            index,
+            Placement { x: frame.x, y: frame.y, width: frame.width, height: frame.height, rotated: frame.rotated },
+        ));
+    }
+    kept
+}
+
+/// A packed and encoded atlas page
+struct PackedPage {
+    /// Sprite indices and where they were placed
+    placed: Vec<(usize, Placement)>,
+    image: DynamicImage,
+    size: u64,
+}
+
+/// Place sprites (`candidates` into `bounds`) on one page of `area`, up to `limit` of them
+/// including the `reserved` ones already on it
+///
+/// Sprites that don't fit are skipped, leaving them for another page.
+fn place_sprites(
+    candidates: &[usize],
+    bounds: &[(u32, u32, u32, u32)],
+    area: (u32, u32),
+    limit: usize,
+    reserved: &[(usize, Placement)],
+    config: &AtlasConfig,
+) -> Vec<(usize, Placement)> {
+    let cell = config.padding + 2 * config.extrude;
+    // Free space around reserved sprites is only tracked by MaxRects
+    let packer_kind = if reserved.is_empty() { config.packer } else { AtlasPacker::MaxRects };
+    let mut packer = RectPacker::new(packer_kind, area.0, area.1, config.allow_rotation);
+    let mut placed = reserved.to_vec();
+    for (_, placement) in reserved {
+        let margin = config.padding + config.extrude;
+        packer.reserve(
+            placement.x.saturating_sub(margin),
+            placement.y.saturating_sub(margin),
+            placement.width + cell,
+            placement.height + cell,
+        );
+    }
+
+    for &index in candidates {
+        if placed.len() == limit {
+            break;
+        }
+        let (_, _, width, height) = bounds[index];
+        let Some(placement) = packer.insert(width + cell, height + cell) else {
+            continue;
+        };
+        placed.push((
+            index,
+            Placement {
+                x: placement.x + config.padding + config.extrude,
+                y: placement.y + config.padding + config.extrude,
+                width: placement.width - cell,
+                height: placement.height - cell,
+                rotated: placement.rotated,
+            },
+        ));
+    }
+    placed
+}
+
+/// Repack a page's sprites into the smallest power-of-two page that holds them all
+///
+/// Rounding the packed size up can nearly double each side; a squarer, smaller page often fits
+/// the same sprites.
+fn shrink_power_of_two(
+    placed: Vec<(usize, Placement)>,
+    bounds: &[(u32, u32, u32, u32)],
+    max_size: (u32, u32),
+    config: &AtlasConfig,
+) -> Vec<(usize, Placement)> {
+    let natural = page_size(&placed, config);
+    let current = round_page_size(natural.0, config) as u64 * round_page_size(natural.1, config) as u64;
+
+    let sides = |max: u32| {
+        let mut sides = Vec::new();
+        let mut side = 1;
+        while side <= max {
+            if round_page_size(side, config) == side {
+                sides.push(side);
+            }
+            side *= 2;
+        }
+        sides
+    };
+    let mut candidates: Vec<(u32, u32)> = sides(max_size.0)
+        .into_iter()
+        .flat_map(|width| sides(max_size.1).into_iter().map(move |height| (width, height)))
+        .filter(|&(width, height)| (width as u64 * height as u64) < current)
+        .collect();
+    candidates.sort_by_key(|&(width, height)| (width as u64 * height as u64, width.max(height)));
+
+    let indices: Vec<usize> = placed.iter().map(|&(index, _)| index).collect();
+    for (width, height) in candidates {
+        let area = (width.saturating_sub(config.padding), height.saturating_sub(config.padding));
+        let repacked = place_sprites(&indices, bounds, area, usize::MAX, &[], config);
+        if repacked.len() == indices.len() {
+            return repacked;
+        }
+    }
+    placed
+}
+
+/// Size of a page holding the placed sprites, with a `padding` border on the right and bottom
+fn page_size(placed: &[(usize, Placement)], config: &AtlasConfig) -> (u32, u32) {
+    let border = config.extrude + config.padding;
+    let width = placed.iter().map(|(_, p)| p.x + p.width).max().unwrap_or(0) + border;
+    let height = placed.iter().map(|(_, p)| p.y + p.height).max().unwrap_or(0) + border;
+    (width, height)
+}
+
+/// A page side rounded up to a power of two and/or a multiple, as configured
+fn round_page_size(side: u32, config: &AtlasConfig) -> u32 {
+    let side = match config.power_of_two {
+        true => side.next_power_of_two(),
+        false => side,
+    };
+    match config.multiple_of {
+        Some(multiple) if multiple > 1 => side.div_ceil(multiple) * multiple,
+        _ => side,
+    }
+}
+
+/// The largest page side up to `max` that rounding leaves unchanged (0 if there is none)
+fn constrained_max(max: u32, config: &AtlasConfig) -> u32 {
+    (1..=max).rev().find(|&side| round_page_size(side, config) == side).unwrap_or(0)
+}
+
+/// Write the pages again at `scale`, with the same layout, and return their metadata
+///
+/// Regions start at the scaled position of the original and end at its scaled end, both
+/// rounded down, so gaps never close and regions never overlap. Each sprite is resized into its
+/// region separately rather than the page as a whole, so neighbors don't bleed into each other.
+fn scaled_variant(
+    metadata: &AtlasMetadata,
+    pages: &[PackedPage],
+    page_paths: &[PathBuf],
+    pixels: &[RgbaImage],
+    bounds: &[(u32, u32, u32, u32)],
+    scale: f32,
+    config: &AtlasConfig,
+) -> Result<AtlasMetadata> {
+    let floor = |value: u32| (value as f64 * scale as f64).floor() as u32;
+    let round = |value: u32| ((value as f64 * scale as f64).round() as u32).max(1);
+    let region = |p: &Placement| {
+        let (x, y) = (floor(p.x), floor(p.y));
+        Placement {
+            x,
+            y,
+            width: (floor(p.x + p.width) - x).max(1),
+            height: (floor(p.y + p.height) - y).max(1),
+            rotated: p.rotated,
+        }
+    };
+
+    let mut page_meta = Vec::with_capacity(pages.len());
+    for (page, path) in pages.iter().zip(page_paths) {
+        let mut resized = Vec::with_capacity(page.placed.len());
+        let mut placed = Vec::with_capacity(page.placed.len());
+        for &(index, placement) in &page.placed {
+            let scaled = region(&placement);
+            let (width, height) = match scaled.rotated {
+                true => (scaled.height, scaled.width),
+                false => (scaled.width, scaled.height),
+            };
+            let (x, y, source_width, source_height) = bounds[index];
+            let sprite = image::imageops::crop_imm(&pixels[index], x, y, source_width, source_height).to_image();
+            resized.push(image::imageops::resize(&sprite, width, height, image::imageops::FilterType::Lanczos3));
+            placed.push((placed.len(), scaled));
+        }
+        let resized_bounds: Vec<_> = resized.iter().map(|sprite| (0, 0, sprite.width(), sprite.height())).collect();
+
+        let size = (
+            round_page_size(round(page.image.width()), config),
+            round_page_size(round(page.image.height()), config),
+        );
+        let image = compose_page(&resized, &resized_bounds, &placed, size, floor(config.extrude));
+        let path = variant_path(path, &config.variant_name, scale);
+        let bytes = save_page(&image, &path, config)?;
+        page_meta.push(AtlasPage { image: file_name(&path), width: size.0, height: size.1, size: bytes });
+    }
+
+    let mut variant = metadata.clone();
+    for frame in variant.frames.values_mut() {
+        let placement = Placement {
+            x: frame.x,
+            y: frame.y,
+            width: frame.width,
+            height: frame.height,
+            rotated: frame.rotated,
+        };
+        let (width, height) = frame.size();
+        let scaled = region(&placement);
+        (frame.x, frame.y, frame.width, frame.height) = (scaled.x, scaled.y, scaled.width, scaled.height);
+        let (scaled_width, scaled_height) = frame.size();
+
+        // Sources still hold the trimmed sprite, and untrimmed sides stay untrimmed
+        frame.trim_x = frame.trim_x.map(floor);
+        frame.trim_y = frame.trim_y.map(floor);
+        let source = |source: u32, size: u32, trim: Option<u32>, scaled_size: u32| match source == size {
+            true => scaled_size,
+            false => round(source).max(trim.unwrap_or(0) + scaled_size),
+        };
+        frame.source_width = frame.source_width.map(|value| source(value, width, frame.trim_x, scaled_width));
+        frame.source_height = frame.source_height.map(|value| source(value, height, frame.trim_y, scaled_height));
+        if let Some(borders) = &mut frame.nine_slice {
+            let scale = |value: u32| (value as f64 * scale as f64).round() as u32;
+            *borders = NineSlice {
+                left: scale(borders.left),
+                top: scale(borders.top),
+                right: scale(borders.right),
+                bottom: scale(borders.bottom),
+            };
+        }
+    }
+    variant.image = page_meta[0].image.clone();
+    (variant.width, variant.height) = (page_meta[0].width, page_meta[0].height);
+    variant.pages = page_meta;
+    Ok(variant)
+}
+
+/// Draw the placed sprites (their `bounds` within `pixels`) onto a `size` page
+///
+/// Rotated sprites are turned 90° clockwise. Each sprite's edge pixels are repeated `extrude`
+/// pixels outward, so filtering at the frame's edge samples the sprite rather than its neighbors.
+fn compose_page(
+    pixels: &[RgbaImage],
+    bounds: &[(u32, u32, u32, u32)],
+    placed: &[(usize, Placement)],
+    (width, height): (u32, u32),
+    extrude: u32,
+) -> DynamicImage {
+    let mut page = RgbaImage::new(width, height);
+    for &(index, placement) in placed {
+        let (source_x, source_y, _, source_height) = bounds[index];
+        for y in 0..placement.height + 2 * extrude {
+            for x in 0..placement.width + 2 * extrude {
+                // Position within the frame, clamped to its edge in the extruded margin
+                let fx = x.saturating_sub(extrude).min(placement.width - 1);
+                let fy = y.saturating_sub(extrude).min(placement.height - 1);
+                let (sx, sy) = match placement.rotated {
+                    true => (source_x + fy, source_y + source_height - 1 - fx),
+                    false => (source_x + fx, source_y + fy),
+                };
+                page.put_pixel(
+                    placement.x + x - extrude,
+                    placement.y + y - extrude,
+                    *pixels[index].get_pixel(sx, sy),
+                );
+            }
+        }
+    }
+    DynamicImage::ImageRgba8(page)
+}
+
+/// Path of a numbered atlas page (`atlas.png` -> `atlas_0.png`)
+pub(crate) fn page_file_path(output: &Path, index: usize) -> PathBuf {
+    let stem = output.file_stem().and_then(|s| s.to_str()).unwrap_or("atlas");
+    let name = match output.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}_{}.{}", stem, index, ext),
+        None => format!("{}_{}", stem, index),
+    };
+    output.with_file_name(name)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("atlas.png")
+        .to_string()
+}
+
+/// Encode a page to disk and return its size in bytes
+fn save_page(image: &DynamicImage, path: &Path, config: &AtlasConfig) -> Result<u64> {
+    match config.format {
+        Some(format) => {
+            // Run the page through the regular image pipeline (oxipng, WebP, KTX2, ...)
+            let temp = path.with_extension("page.tmp.png");
+            image.save_with_format(&temp, ImageFormat::Png)
+                .with_context(|| format!("Failed to save atlas image: {}", temp.display()))?;
+
+            let image_config = ImageProcessorConfig {
+                output_format: Some(format),
+                quality: config.quality,
+                generate_mipmaps: config.generate_mipmaps,
+                ktx2_zstd_level: config.ktx2_zstd_level,
+                astc_block: config.astc_block,
+                premultiply_alpha: config.premultiply_alpha,
+                ..Default::default()
+            };
+            let result = process_image(&temp, path, &image_config);
+            let _ = std::fs::remove_file(&temp);
+            Ok(result?.output_size)
+        }
+        None if config.premultiply_alpha => {
+            let mut image = image.to_rgba8();
+            premultiply_alpha(&mut image);
+            image.save(path)
+                .with_context(|| format!("Failed to save atlas image: {}", path.display()))?;
+            Ok(std::fs::metadata(path)?.len())
+        }
+        None => {
+            image.save(path)
+                .with_context(|| format!("Failed to save atlas image: {}", path.display()))?;
+            Ok(std::fs::metadata(path)?.len())
+        }
+    }
+}
+
 /// Save atlas metadata to JSON file
 pub fn save_atlas_metadata(metadata: &AtlasMetadata, path: &Path) -> Result<()> {
     let json = serde_json::to_string_pretty(metadata)?;
@@ -215,3 +977,210 @@ pub fn save_atlas_metadata(metadata: &AtlasMetadata, path: &Path) -> Result<()>
         .with_context(|| format!("Failed to write metadata: {}", path.display()))?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Uncompressed 32-bit TGA from rows listed top to bottom, stored in the given origin
+    fn write_tga(path: &Path, rows: &[&[[u8; 4]]], image_desc: u8) {
+        let (width, height) = (rows[0].len() as u16, rows.len() as u16);
+        let mut data = vec![0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        data.extend_from_slice(&width.to_le_bytes());
+        data.extend_from_slice(&height.to_le_bytes());
+        data.extend_from_slice(&[32, image_desc]);
+
+        let bottom_up = image_desc & 0x20 == 0;
+        let right_left = image_desc & 0x10 != 0;
+        let mut stored: Vec<&[[u8; 4]]> = rows.to_vec();
+        if bottom_up {
+            stored.reverse();
+        }
+        for row in stored {
+            let mut row = row.to_vec();
+            if right_left {
+                row.reverse();
+            }
+            for [r, g, b, a] in row {
+                data.extend_from_slice(&[b, g, r, a]);
+            }
+        }
+        std::fs::write(path, data).unwrap();
+    }
+
+    #[test]
+    fn test_tga_origins_pack_upright() {
+        const RED: [u8; 4] = [255, 0, 0, 255];
+        const GREEN: [u8; 4] = [0, 255, 0, 255];
+        const BLUE: [u8; 4] = [0, 0, 255, 255];
+        const WHITE: [u8; 4] = [255, 255, 255, 255];
+        let rows: &[&[[u8; 4]]] = &[&[RED, GREEN], &[BLUE, WHITE]];
+
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path().join("sprites");
+        std::fs::create_dir_all(&input).unwrap();
+        // 8 alpha bits, plus bottom-left (0x00), bottom-right (0x10), top-left (0x20) origins
+        write_tga(&input.join("bottom_left.tga"), rows, 0x08);
+        write_tga(&input.join("bottom_right.tga"), rows, 0x18);
+        write_tga(&input.join("top_left.tga"), rows, 0x28);
+
+        let config = AtlasConfig {
+            padding: 0,
+            ..Default::default()
+        };
+        let result = generate_atlas(&input, &temp_dir.path().join("atlas.png"), &config).unwrap();
+
+        for name in ["bottom_left", "bottom_right", "top_left"] {
+            let frame = result.metadata.frame(name).unwrap();
+            assert!(!frame.rotated);
+            let pixel = |x, y| result.image.get_pixel(frame.x + x, frame.y + y).0;
+            assert_eq!(pixel(0, 0), RED, "{}", name);
+            assert_eq!(pixel(1, 0), GREEN, "{}", name);
+            assert_eq!(pixel(0, 1), BLUE, "{}", name);
+            assert_eq!(pixel(1, 1), WHITE, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_rotated_frames_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path().join("sprites");
+        std::fs::create_dir_all(&input).unwrap();
+        let pixels = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255]];
+        RgbaImage::from_raw(3, 1, pixels.concat()).unwrap().save(input.join("wide.png")).unwrap();
+
+        // Only fits standing up
+        let config = AtlasConfig {
+            max_width: 1,
+            max_height: 3,
+            padding: 0,
+            allow_rotation: true,
+            ..Default::default()
+        };
+        let output = temp_dir.path().join("atlas.png");
+        let result = generate_atlas(&input, &output, &config).unwrap();
+        let json = temp_dir.path().join("atlas.json");
+        save_atlas_metadata(&result.metadata, &json).unwrap();
+
+        let metadata = AtlasMetadata::open(&json).unwrap();
+        let frame = metadata.frame("wide").unwrap();
+        assert!(frame.rotated);
+        assert_eq!((frame.width, frame.height), (1, 3));
+        assert_eq!(frame.size(), (3, 1));
+        let page = image::open(&output).unwrap().to_rgba8();
+        for (x, expected) in pixels.iter().enumerate() {
+            let (px, py) = frame.page_position(x as u32, 0);
+            assert_eq!(&page.get_pixel(px, py).0, expected);
+        }
+    }
+
+    #[test]
+    fn test_packing_is_deterministic() {
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path().join("sprites");
+        std::fs::create_dir_all(&input).unwrap();
+        // Equal sizes, so only the tie-break decides the order
+        for i in 0..6u8 {
+            RgbaImage::from_pixel(4, 3 + (i % 2) as u32, image::Rgba([i * 40, 0, 0, 255]))
+                .save(input.join(format!("sprite_{}.png", i)))
+                .unwrap();
+        }
+
+        let config = AtlasConfig { packer: AtlasPacker::MaxRects, ..Default::default() };
+        let build = |name: &str| {
+            let output = temp_dir.path().join(name).join("atlas.png");
+            let result = generate_atlas(&input, &output, &config).unwrap();
+            (serde_json::to_string(&result.metadata).unwrap(), std::fs::read(&output).unwrap())
+        };
+        assert_eq!(build("first"), build("second"));
+    }
+
+    #[test]
+    fn test_identical_frames_share_region() {
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path().join("sprites");
+        std::fs::create_dir_all(&input).unwrap();
+        // The same opaque 2x2 block at different offsets trims to the same pixels
+        for (name, offset) in [("hold_0", 0), ("hold_1", 1), ("other", 0)] {
+            let mut image = RgbaImage::new(4, 4);
+            let color = if name == "other" { [0, 255, 0, 255] } else { [255, 0, 0, 255] };
+            for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                image.put_pixel(x + offset, y + offset, image::Rgba(color));
+            }
+            image.save(input.join(format!("{}.png", name))).unwrap();
+        }
+
+        let config = AtlasConfig { trim: true, ..Default::default() };
+        let result = generate_atlas(&input, &temp_dir.path().join("atlas.png"), &config).unwrap();
+        let frames = &result.metadata.frames;
+        let (first, second) = (&frames["hold_0"], &frames["hold_1"]);
+        assert_eq!((first.x, first.y, first.page), (second.x, second.y, second.page));
+        assert_eq!((first.trim_x, second.trim_x), (Some(0), Some(1)));
+        assert_ne!((frames["other"].x, frames["other"].y), (first.x, first.y));
+        assert_eq!((result.duplicates, result.saved_area), (1, 4));
+    }
+
+    #[test]
+    fn test_update_keeps_frames_in_place() {
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path().join("sprites");
+        std::fs::create_dir_all(&input).unwrap();
+        let add = |name: &str, width: u32, height: u32, red: u8| {
+            RgbaImage::from_pixel(width, height, image::Rgba([red, 0, 0, 255]))
+                .save(input.join(format!("{}.png", name)))
+                .unwrap();
+        };
+        add("large", 20, 20, 10);
+        add("small", 6, 4, 20);
+
+        let output = temp_dir.path().join("atlas.png");
+        let first = generate_atlas(&input, &output, &AtlasConfig::default()).unwrap().metadata;
+
+        // A new sprite larger than the others would be packed first from scratch
+        add("huge", 30, 30, 30);
+        let config = AtlasConfig { update: Some(first.clone()), ..Default::default() };
+        let result = generate_atlas(&input, &output, &config).unwrap();
+        assert_eq!(result.kept, 2);
+        for key in ["large", "small"] {
+            let (before, after) = (&first.frames[key], &result.metadata.frames[key]);
+            assert_eq!((before.x, before.y, before.page), (after.x, after.y, after.page));
+        }
+        assert_eq!((result.metadata.pages[0].width, result.metadata.pages[0].height), (first.width, first.height));
+        assert_eq!(result.metadata.frames["huge"].page, 1);
+    }
+
+    #[test]
+    fn test_exif_orientation_applied() {
+        use image::codecs::png::PngEncoder;
+        use image::ImageEncoder;
+
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path().join("sprites");
+        std::fs::create_dir_all(&input).unwrap();
+
+        // Little-endian TIFF header with a single Orientation (0x0112) entry: 6 = rotate 90° CW
+        let mut exif = b"II*\0\x08\0\0\0\x01\0\x12\x01\x03\0\x01\0\0\0\x06\0\0\0".to_vec();
+        exif.extend_from_slice(&[0; 4]);
+        let file = std::fs::File::create(input.join("photo.png")).unwrap();
+        let mut encoder = PngEncoder::new(file);
+        encoder.set_exif_metadata(exif).unwrap();
+        let pixels = [255, 0, 0, 255, 0, 255, 0, 255];
+        encoder
+            .write_image(&pixels, 2, 1, image::ExtendedColorType::Rgba8)
+            .unwrap();
+
+        let upright = open_image(&input.join("photo.png")).unwrap().to_rgba8();
+        assert_eq!(upright.dimensions(), (1, 2));
+        assert_eq!(upright.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_eq!(upright.get_pixel(0, 1).0, [0, 255, 0, 255]);
+
+        let config = AtlasConfig {
+            padding: 0,
+            ..Default::default()
+        };
+        let result = generate_atlas(&input, &temp_dir.path().join("atlas.png"), &config).unwrap();
+        let frame = result.metadata.frame("photo").unwrap();
+        assert_eq!((frame.width, frame.height), (1, 2));
+    }
+}
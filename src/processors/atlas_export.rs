@@ -0,0 +1,428 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use crate::cli::AtlasMetadataFormat;
+use crate::runtime::{AtlasMetadata, NineSlice, SpriteFrame};
+
+use super::{page_file_path, save_atlas_metadata};
+
+/// Atlas metadata export configuration
+#[derive(Debug, Clone)]
+pub struct AtlasExportConfig {
+    pub format: AtlasMetadataFormat,
+    /// Sprite pixels per world unit (Unity)
+    pub pixels_per_unit: f32,
+}
+
+impl Default for AtlasExportConfig {
+    fn default() -> Self {
+        Self {
+            format: AtlasMetadataFormat::Native,
+            pixels_per_unit: 100.0,
+        }
+    }
+}
+
+/// Write atlas metadata in the given schema and return the files written
+///
+/// Godot resources go into the directory `path`, which is returned instead of every file.
+/// libGDX and Unity metadata list every page in one file. TexturePacker formats hold a single
+/// page, so a multi-page atlas gets one file per page (`atlas.json` -> `atlas_0.json`,
+/// `atlas_1.json`, ...), each listing the others under `meta.related_multi_packs`.
+pub fn export_atlas_metadata(
+    metadata: &AtlasMetadata,
+    config: &AtlasExportConfig,
+    path: &Path,
+) -> Result<Vec<PathBuf>> {
+    let array = match config.format {
+        AtlasMetadataFormat::Native => {
+            save_atlas_metadata(metadata, path)?;
+            return Ok(vec![path.to_path_buf()]);
+        }
+        AtlasMetadataFormat::LibGdx => {
+            let atlas = libgdx_atlas(metadata)?;
+            std::fs::write(path, atlas)
+                .with_context(|| format!("Failed to write metadata: {}", path.display()))?;
+            return Ok(vec![path.to_path_buf()]);
+        }
+        AtlasMetadataFormat::Unity => {
+            let json =
+                serde_json::to_string_pretty(&unity_json(metadata, config.pixels_per_unit)?)?;
+            std::fs::write(path, json)
+                .with_context(|| format!("Failed to write metadata: {}", path.display()))?;
+            return Ok(vec![path.to_path_buf()]);
+        }
+        AtlasMetadataFormat::Godot => {
+            write_godot_resources(metadata, path)?;
+            return Ok(vec![path.to_path_buf()]);
+        }
+        AtlasMetadataFormat::TpHash => false,
+        AtlasMetadataFormat::TpArray => true,
+    };
+
+    let page_count = metadata.pages.len().max(1);
+    let paths: Vec<PathBuf> = match page_count {
+        1 => vec![path.to_path_buf()],
+        _ => (0..page_count).map(|i| page_file_path(path, i)).collect(),
+    };
+
+    for (page, page_path) in paths.iter().enumerate() {
+        let related: Vec<String> = paths
+            .iter()
+            .filter(|other| *other != page_path)
+            .map(|other| {
+                other
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        let document = texture_packer_json(metadata, page, array, &related);
+        let json = serde_json::to_string_pretty(&document)?;
+        std::fs::write(page_path, json)
+            .with_context(|| format!("Failed to write metadata: {}", page_path.display()))?;
+    }
+    Ok(paths)
+}
+
+/// TexturePacker JSON for one page of an atlas
+fn texture_packer_json(
+    metadata: &AtlasMetadata,
+    page: usize,
+    array: bool,
+    related: &[String],
+) -> Value {
+    let keys: Vec<&String> = metadata
+        .frames
+        .keys()
+        .filter(|key| metadata.frames[*key].page == page)
+        .collect();
+
+    let frames = match array {
+        true => Value::Array(
+            keys.iter()
+                .map(|key| {
+                    let mut frame = texture_packer_frame(&metadata.frames[*key]);
+                    frame["filename"] = json!(key);
+                    frame
+                })
+                .collect(),
+        ),
+        false => Value::Object(
+            keys.iter()
+                .map(|key| {
+                    (
+                        key.to_string(),
+                        texture_packer_frame(&metadata.frames[*key]),
+                    )
+                })
+                .collect(),
+        ),
+    };
+
+    let (image, width, height) = match metadata.pages.get(page) {
+        Some(p) => (p.image.as_str(), p.width, p.height),
+        None => (metadata.image.as_str(), metadata.width, metadata.height),
+    };
+    let mut meta = json!({
+        "app": "https://github.com/esengine/asset-forge",
+        "version": "1.0",
+        "image": image,
+        "format": "RGBA8888",
+        "size": { "w": width, "h": height },
+        "scale": "1",
+    });
+    if metadata.premultiplied_alpha {
+        meta["premultipliedAlpha"] = json!(true);
+    }
+    if !related.is_empty() {
+        meta["related_multi_packs"] = json!(related);
+    }
+
+    let mut document = json!({ "frames": frames, "meta": meta });
+
+    // Animations whose frames all live on this page
+    let animations: Vec<(&String, &Vec<String>)> = metadata
+        .animations
+        .iter()
+        .map(|(name, animation)| (name, &animation.frames))
+        .filter(|(_, frames)| {
+            frames
+                .iter()
+                .all(|f| metadata.frames.get(f).is_some_and(|f| f.page == page))
+        })
+        .collect();
+    if !animations.is_empty() {
+        document["animations"] = Value::Object(
+            animations
+                .into_iter()
+                .map(|(name, frames)| (name.clone(), json!(frames)))
+                .collect(),
+        );
+    }
+    document
+}
+
+/// A frame in TexturePacker's schema, where `frame` has the unrotated size of the sprite
+fn texture_packer_frame(frame: &SpriteFrame) -> Value {
+    let (width, height) = frame.size();
+    let source = (
+        frame.source_width.unwrap_or(width),
+        frame.source_height.unwrap_or(height),
+    );
+    let mut value = json!({
+        "frame": { "x": frame.x, "y": frame.y, "w": width, "h": height },
+        "rotated": frame.rotated,
+        "trimmed": source != (width, height),
+        "spriteSourceSize": {
+            "x": frame.trim_x.unwrap_or(0),
+            "y": frame.trim_y.unwrap_or(0),
+            "w": width,
+            "h": height,
+        },
+        "sourceSize": { "w": source.0, "h": source.1 },
+    });
+    if let Some([x, y]) = frame.pivot {
+        value["pivot"] = json!({ "x": widen(x), "y": widen(y) });
+    }
+    value
+}
+
+/// libGDX's text atlas: per page a header, then per region its placement
+///
+/// Animation frames become regions named after the animation with their position as `index`,
+/// so `TextureAtlas.findRegions("walk")` returns them in order. libGDX measures `offset` from the
+/// bottom left and only knows counterclockwise rotation, so rotated frames are an error.
+fn libgdx_atlas(metadata: &AtlasMetadata) -> Result<String> {
+    let mut indices: HashMap<&str, (&str, usize)> = HashMap::new();
+    for (name, animation) in &metadata.animations {
+        for (index, frame) in animation.frames.iter().enumerate() {
+            indices.insert(frame.as_str(), (name.as_str(), index));
+        }
+    }
+
+    let pages: Vec<(&str, u32, u32)> = match metadata.pages.is_empty() {
+        true => vec![(metadata.image.as_str(), metadata.width, metadata.height)],
+        false => metadata
+            .pages
+            .iter()
+            .map(|p| (p.image.as_str(), p.width, p.height))
+            .collect(),
+    };
+
+    let mut out = String::new();
+    for (page, (image, width, height)) in pages.into_iter().enumerate() {
+        writeln!(out)?;
+        writeln!(out, "{}", image)?;
+        writeln!(out, "size: {},{}", width, height)?;
+        writeln!(out, "format: RGBA8888")?;
+        writeln!(out, "filter: Linear,Linear")?;
+        writeln!(out, "repeat: none")?;
+
+        let keys: Vec<&String> = metadata
+            .frames
+            .keys()
+            .filter(|key| metadata.frames[*key].page == page)
+            .collect();
+        for key in keys {
+            let frame = &metadata.frames[key];
+            if frame.rotated {
+                anyhow::bail!(
+                    "Frame '{}' is rotated, which libGDX atlases can't describe",
+                    key
+                );
+            }
+            let (name, index) = match indices.get(key.as_str()) {
+                Some(&(name, index)) => (name, index as i64),
+                None => (key.as_str(), -1),
+            };
+            let source = (
+                frame.source_width.unwrap_or(frame.width),
+                frame.source_height.unwrap_or(frame.height),
+            );
+            let (trim_x, trim_y) = (frame.trim_x.unwrap_or(0), frame.trim_y.unwrap_or(0));
+
+            writeln!(out, "{}", name)?;
+            writeln!(out, "  rotate: false")?;
+            writeln!(out, "  xy: {}, {}", frame.x, frame.y)?;
+            writeln!(out, "  size: {}, {}", frame.width, frame.height)?;
+            if let Some(slice) = trimmed_nine_slice(frame) {
+                writeln!(
+                    out,
+                    "  split: {}, {}, {}, {}",
+                    slice.left, slice.right, slice.top, slice.bottom
+                )?;
+            }
+            writeln!(out, "  orig: {}, {}", source.0, source.1)?;
+            writeln!(
+                out,
+                "  offset: {}, {}",
+                trim_x,
+                source.1 - trim_y - frame.height
+            )?;
+            writeln!(out, "  index: {}", index)?;
+        }
+    }
+    Ok(out)
+}
+
+/// One Godot 4 `AtlasTexture` resource per frame, at `<dir>/<frame key>.tres`
+///
+/// Each resource refers to its page by a path relative to itself, so the directory has to sit
+/// next to the pages. Trimmed frames get a `margin` restoring their source size.
+fn write_godot_resources(metadata: &AtlasMetadata, dir: &Path) -> Result<()> {
+    for (key, frame) in &metadata.frames {
+        if frame.rotated {
+            anyhow::bail!(
+                "Frame '{}' is rotated, which Godot atlas textures can't describe",
+                key
+            );
+        }
+        let path = dir.join(format!("{}.tres", key));
+        // Up from the resource's directory to the one holding the directory and the pages
+        let depth = key.matches('/').count() + 1;
+        let image = format!("{}{}", "../".repeat(depth), metadata.page_image(frame));
+
+        let mut tres = String::new();
+        writeln!(
+            tres,
+            "[gd_resource type=\"AtlasTexture\" load_steps=2 format=3]"
+        )?;
+        writeln!(tres)?;
+        writeln!(
+            tres,
+            "[ext_resource type=\"Texture2D\" path=\"{}\" id=\"1_page\"]",
+            image
+        )?;
+        writeln!(tres)?;
+        writeln!(tres, "[resource]")?;
+        writeln!(tres, "atlas = ExtResource(\"1_page\")")?;
+        writeln!(
+            tres,
+            "region = Rect2({}, {}, {}, {})",
+            frame.x, frame.y, frame.width, frame.height
+        )?;
+        let source = (
+            frame.source_width.unwrap_or(frame.width),
+            frame.source_height.unwrap_or(frame.height),
+        );
+        if source != (frame.width, frame.height) {
+            writeln!(
+                tres,
+                "margin = Rect2({}, {}, {}, {})",
+                frame.trim_x.unwrap_or(0),
+                frame.trim_y.unwrap_or(0),
+                source.0 - frame.width,
+                source.1 - frame.height
+            )?;
+        }
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, tres)
+            .with_context(|| format!("Failed to write metadata: {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Sprite rects, pivots and borders for a Unity editor script to slice the pages with
+///
+/// Unity measures rects from the bottom left of the texture. The pivot (normalized in the
+/// rect) is the frame's `pivot`, or the center, of the untrimmed sprite, so trimmed sprites
+/// stay where they were drawn, and `border` is Unity's (left, bottom, right, top) order.
+fn unity_json(metadata: &AtlasMetadata, pixels_per_unit: f32) -> Result<Value> {
+    let pages: Vec<(&str, u32, u32)> = match metadata.pages.is_empty() {
+        true => vec![(metadata.image.as_str(), metadata.width, metadata.height)],
+        false => metadata
+            .pages
+            .iter()
+            .map(|p| (p.image.as_str(), p.width, p.height))
+            .collect(),
+    };
+
+    let mut textures = Vec::with_capacity(pages.len());
+    for (page, (image, width, height)) in pages.into_iter().enumerate() {
+        let keys: Vec<&String> = metadata
+            .frames
+            .keys()
+            .filter(|key| metadata.frames[*key].page == page)
+            .collect();
+
+        let mut sprites = Vec::with_capacity(keys.len());
+        for key in keys {
+            let frame = &metadata.frames[key];
+            if frame.rotated {
+                anyhow::bail!(
+                    "Frame '{}' is rotated, which Unity sprites can't describe",
+                    key
+                );
+            }
+            let source = (
+                frame.source_width.unwrap_or(frame.width),
+                frame.source_height.unwrap_or(frame.height),
+            );
+            let trim_x = frame.trim_x.unwrap_or(0);
+            let trim_bottom = source.1 - frame.trim_y.unwrap_or(0) - frame.height;
+            let [pivot_x, pivot_y] = frame.pivot.unwrap_or([0.5, 0.5]);
+            let pivot = (
+                widen(pivot_x) * source.0 as f64,
+                (1.0 - widen(pivot_y)) * source.1 as f64,
+            );
+
+            let mut sprite = json!({
+                "name": key,
+                "rect": {
+                    "x": frame.x,
+                    "y": height - frame.y - frame.height,
+                    "width": frame.width,
+                    "height": frame.height,
+                },
+                "pivot": {
+                    "x": (pivot.0 - trim_x as f64) / frame.width as f64,
+                    "y": (pivot.1 - trim_bottom as f64) / frame.height as f64,
+                },
+            });
+            if let Some(slice) = trimmed_nine_slice(frame) {
+                sprite["border"] =
+                    json!({ "x": slice.left, "y": slice.bottom, "z": slice.right, "w": slice.top });
+            }
+            sprites.push(sprite);
+        }
+        textures
+            .push(json!({ "image": image, "width": width, "height": height, "sprites": sprites }));
+    }
+
+    Ok(json!({
+        "pixelsPerUnit": widen(pixels_per_unit),
+        "premultipliedAlpha": metadata.premultiplied_alpha,
+        "textures": textures,
+    }))
+}
+
+/// A frame's nine-slice borders in pixels of its packed (trimmed) region
+fn trimmed_nine_slice(frame: &SpriteFrame) -> Option<NineSlice> {
+    let slice = frame.nine_slice?;
+    let source = (
+        frame.source_width.unwrap_or(frame.width),
+        frame.source_height.unwrap_or(frame.height),
+    );
+    let (trim_x, trim_y) = (frame.trim_x.unwrap_or(0), frame.trim_y.unwrap_or(0));
+    Some(NineSlice {
+        left: slice.left.saturating_sub(trim_x),
+        top: slice.top.saturating_sub(trim_y),
+        right: slice.right.saturating_sub(source.0 - trim_x - frame.width),
+        bottom: slice
+            .bottom
+            .saturating_sub(source.1 - trim_y - frame.height),
+    })
+}
+
+/// An `f32` as the `f64` it was written as, so `0.3` isn't exported as `0.30000001192092896`
+fn widen(value: f32) -> f64 {
+    value.to_string().parse().unwrap_or(value as f64)
+}
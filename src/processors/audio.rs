@@ -1,31 +1,163 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use flacenc::component::{BitRepr, MetadataBlockData, StreamInfo};
+use flacenc::error::Verify;
+use flacenc::source::{Fill, FrameBuf};
 use hound::{WavSpec, WavWriter};
 use std::fs::File;
-use std::path::Path;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
 use std::time::Instant;
-use symphonia::core::audio::{AudioBufferRef, Signal};
-use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
-use symphonia::core::formats::FormatOptions;
-use symphonia::core::io::MediaSourceStream;
-use symphonia::core::meta::MetadataOptions;
-use symphonia::core::probe::Hint;
+#[cfg(feature = "native-audio")]
+use mp3lame_encoder::{Bitrate, FlushNoGap, InterleavedPcm, MonoPcm, VbrMode};
+#[cfg(feature = "native-audio")]
 use vorbis_rs::{VorbisBitrateManagementStrategy, VorbisEncoderBuilder};
 
-use super::ProcessingStats;
+#[cfg(not(feature = "native-audio"))]
+use super::VorbisWriter;
+use super::audio_channels::ChannelMix;
+use super::audio_levels::LevelMeter;
+use super::audio_stream::{Analysis, AudioDecoder, AudioPipeline, Fade, Resampler};
+use super::ogg::vorbis_comment;
+use super::{read_wav_loop, write_wav_loop, AdpcmCodec, AdpcmWriter, AudioLevels, ChannelLayout, LoopPoints, OpusWriter, ProcessingStats};
+use crate::cli::{AudioOutputFormat, BitrateMode, QualityPreset};
 
 /// Audio output format
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum AudioFormat {
     Wav,
+    #[default]
     Ogg,
+    Opus,
+    Mp3,
+    Flac,
+    /// 4-bit ADPCM in a WAV file, for platforms that can't afford a Vorbis decode
+    Adpcm(AdpcmCodec),
 }
 
-impl Default for AudioFormat {
-    fn default() -> Self {
-        Self::Ogg
+impl AudioFormat {
+    /// Parse a format name as used in presets and rules
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "ogg" | "vorbis" => Some(Self::Ogg),
+            "wav" => Some(Self::Wav),
+            "opus" => Some(Self::Opus),
+            "mp3" => Some(Self::Mp3),
+            "flac" => Some(Self::Flac),
+            "adpcm" | "ima-adpcm" | "ima_adpcm" => Some(Self::Adpcm(AdpcmCodec::Ima)),
+            "ms-adpcm" | "ms_adpcm" => Some(Self::Adpcm(AdpcmCodec::Ms)),
+            _ => None,
+        }
+    }
+
+    /// File extension for this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Wav | Self::Adpcm(_) => "wav",
+            Self::Ogg => "ogg",
+            Self::Opus => "opus",
+            Self::Mp3 => "mp3",
+            Self::Flac => "flac",
+        }
+    }
+}
+
+impl From<AudioOutputFormat> for AudioFormat {
+    fn from(format: AudioOutputFormat) -> Self {
+        match format {
+            AudioOutputFormat::Ogg => Self::Ogg,
+            AudioOutputFormat::Opus => Self::Opus,
+            AudioOutputFormat::Mp3 => Self::Mp3,
+            AudioOutputFormat::Flac => Self::Flac,
+            AudioOutputFormat::Wav => Self::Wav,
+            AudioOutputFormat::ImaAdpcm => Self::Adpcm(AdpcmCodec::Ima),
+            AudioOutputFormat::MsAdpcm => Self::Adpcm(AdpcmCodec::Ms),
+        }
+    }
+}
+
+/// Sample encoding of WAV output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WavSampleFormat {
+    /// 8-bit unsigned, for tiny UI blips where size matters more than noise floor
+    Int8,
+    #[default]
+    Int16,
+    Int24,
+    /// 32-bit float, keeping peaks above full scale
+    Float32,
+}
+
+impl WavSampleFormat {
+    /// Format for a bit depth as used in presets and rules; 32 is float
+    pub fn from_bits(bits: u8) -> Result<Self> {
+        match bits {
+            8 => Ok(Self::Int8),
+            16 => Ok(Self::Int16),
+            24 => Ok(Self::Int24),
+            32 => Ok(Self::Float32),
+            _ => bail!("Unsupported WAV bit depth {} (use 8, 16, 24 or 32 for float)", bits),
+        }
+    }
+
+    pub fn bits(self) -> u16 {
+        match self {
+            Self::Int8 => 8,
+            Self::Int16 => 16,
+            Self::Int24 => 24,
+            Self::Float32 => 32,
+        }
+    }
+}
+
+/// Duration-based format override for short sound effects.
+///
+/// Short clips are usually triggered with tight latency requirements, so they
+/// are better kept uncompressed while longer clips get the preset format.
+#[derive(Debug, Clone, Copy)]
+pub struct ShortSfxPolicy {
+    /// Clips at or below this length (in seconds) use `format`
+    pub max_secs: f64,
+    pub format: AudioFormat,
+}
+
+impl ShortSfxPolicy {
+    pub fn applies_to(&self, duration_secs: f64) -> bool {
+        duration_secs <= self.max_secs
+    }
+}
+
+/// Duration-based split between streamed music and in-memory sound effects.
+///
+/// Engines stream long clips from disk and decode short ones into memory up front, so each
+/// side gets the format that suits how it is played.
+#[derive(Debug, Clone, Copy)]
+pub struct MusicPolicy {
+    /// Clips longer than this (in seconds) are music
+    pub threshold_secs: f64,
+    pub music_format: AudioFormat,
+    pub sfx_format: AudioFormat,
+}
+
+impl MusicPolicy {
+    /// Output format for a clip, with the policy name recorded in the manifest
+    pub fn format_for(&self, duration_secs: f64) -> (AudioFormat, &'static str) {
+        if duration_secs > self.threshold_secs {
+            (self.music_format, "music")
+        } else {
+            (self.sfx_format, "sfx")
+        }
     }
 }
 
+/// Leading and trailing silence removal
+#[derive(Debug, Clone, Copy)]
+pub struct SilenceTrim {
+    /// Samples at or below this level (dBFS) count as silence
+    pub threshold_db: f32,
+    /// Silence kept before the first and after the last audible sample, in milliseconds
+    pub padding_ms: u32,
+}
+
 /// Configuration for audio processing
 #[derive(Debug, Clone)]
 pub struct AudioConfig {
@@ -34,8 +166,31 @@ pub struct AudioConfig {
     pub quality: f32,
     /// Target sample rate (None = keep original)
     pub sample_rate: Option<u32>,
+    /// Highest sample rate when `sample_rate` is unset; sources above it are resampled down
+    /// and the rest kept as they are
+    pub max_sample_rate: Option<u32>,
+    /// Target channel count: 1 downmixes to mono, 2 turns mono into stereo (None = keep original)
+    pub channels: Option<u32>,
+    /// Resampler quality: linear interpolation for Fast, windowed sinc otherwise
+    pub resample_quality: QualityPreset,
     /// Normalize audio volume
     pub normalize: bool,
+    /// Target bitrate for Opus and MP3 encoding in kbps (None = libopus default, 128 for MP3)
+    pub bitrate: Option<u32>,
+    pub bitrate_mode: BitrateMode,
+    /// Sample encoding of WAV output
+    pub wav_format: WavSampleFormat,
+    /// ADPCM block length in bytes (None = 256 per channel at 11 kHz, scaled with the rate)
+    pub adpcm_block_size: Option<u32>,
+    /// Remove leading and trailing silence
+    pub trim_silence: Option<SilenceTrim>,
+    /// Linear fade-in and fade-out lengths in milliseconds, after trimming (0 = none)
+    pub fade_in_ms: u32,
+    pub fade_out_ms: u32,
+    /// Loop region in source sample frames, replacing the source's own; a missing end is
+    /// the end of the clip
+    pub loop_start: Option<u64>,
+    pub loop_end: Option<u64>,
 }
 
 impl Default for AudioConfig {
@@ -44,12 +199,28 @@ impl Default for AudioConfig {
             output_format: AudioFormat::Ogg,
             quality: 0.5,
             sample_rate: None,
+            max_sample_rate: None,
+            channels: None,
+            resample_quality: QualityPreset::Balanced,
             normalize: false,
+            bitrate: None,
+            bitrate_mode: BitrateMode::Vbr,
+            wav_format: WavSampleFormat::Int16,
+            adpcm_block_size: None,
+            trim_silence: None,
+            fade_in_ms: 0,
+            fade_out_ms: 0,
+            loop_start: None,
+            loop_end: None,
         }
     }
 }
 
 /// Process an audio file
+///
+/// Audio streams from the decoder through the pipeline to the encoder a packet at a time.
+/// Normalizing and trimming silence need the peak level and audible range before the first
+/// sample goes out, so those read the file twice instead.
 pub fn process_audio(
     input: &Path,
     output: &Path,
@@ -65,32 +236,13 @@ pub fn process_audio(
         std::fs::create_dir_all(parent)?;
     }
 
-    // Decode input audio
-    let audio_data = decode_audio(input)?;
-
-    // Apply normalization if requested
-    let audio_data = if config.normalize {
-        normalize_audio(audio_data)
-    } else {
-        audio_data
-    };
-
-    // Resample if needed
-    let audio_data = if let Some(target_rate) = config.sample_rate {
-        if audio_data.sample_rate != target_rate {
-            resample_audio(audio_data, target_rate)?
-        } else {
-            audio_data
-        }
-    } else {
-        audio_data
-    };
-
-    // Encode to output format
-    match config.output_format {
-        AudioFormat::Wav => encode_wav(&audio_data, output)?,
-        AudioFormat::Ogg => encode_ogg(&audio_data, output, config.quality)?,
+    let (mut decoder, mut pipeline, format) = open_pipeline(input, config)?;
+    let mut sink = create_sink(output, config, format)?;
+    while let Some(chunk) = decoder.next_chunk()? {
+        sink.write(&pipeline.process(chunk)?)?;
     }
+    sink.write(&pipeline.finish()?)?;
+    sink.finish()?;
 
     let output_size = std::fs::metadata(output)
         .with_context(|| format!("Failed to read output file: {}", output.display()))?
@@ -102,276 +254,638 @@ pub fn process_audio(
         original_size,
         output_size,
         processing_time_ms,
+        quality: None,
     })
 }
 
-/// Decoded audio data
-#[derive(Debug, Clone)]
-pub struct AudioData {
-    pub samples: Vec<f32>,
+/// Open `input` with the pipeline `config` describes (channel mixing, silence trimming,
+/// normalization, fades and resampling, in that order) and the format that comes out of it
+///
+/// When the pipeline needs the peak or audible range first, the file is read once for that
+/// and the returned decoder starts over.
+pub(super) fn open_pipeline(input: &Path, config: &AudioConfig) -> Result<(AudioDecoder, AudioPipeline, StreamFormat)> {
+    let loop_points = read_loop(input)?;
+    let mut decoder = AudioDecoder::open(input)?;
+    let mix = ChannelMix::new(&decoder.layout, config.channels.unwrap_or(decoder.channels))?;
+    let analysis = if needs_analysis(config, decoder.frames) {
+        let chunks = std::iter::from_fn(|| decoder.next_chunk().transpose());
+        let analysis = analyze(chunks, &mix, config)?;
+        decoder = AudioDecoder::open(input)?;
+        Some(analysis)
+    } else {
+        None
+    };
+
+    let source = StreamFormat { channels: decoder.channels, sample_rate: decoder.sample_rate, loop_points };
+    let (pipeline, format) = plan_audio(source, mix, decoder.frames, analysis, config)?;
+    Ok((decoder, pipeline, format))
+}
+
+/// Channel count, sample rate and loop region of a stream of interleaved samples
+#[derive(Debug, Clone, Copy)]
+pub(super) struct StreamFormat {
     pub channels: u32,
     pub sample_rate: u32,
+    pub loop_points: Option<LoopPoints>,
 }
 
-impl AudioData {
-    pub fn duration_secs(&self) -> f64 {
-        if self.sample_rate == 0 || self.channels == 0 {
-            return 0.0;
-        }
-        self.samples.len() as f64 / (self.sample_rate as f64 * self.channels as f64)
-    }
+/// Whether the pipeline needs a first pass over the audio before it can start
+///
+/// Normalizing needs the peak and trimming the audible range; a loop override needs the clip
+/// length to check against, and a fade-out where to start, when the container doesn't record it.
+fn needs_analysis(config: &AudioConfig, frames: Option<u64>) -> bool {
+    config.normalize
+        || config.trim_silence.is_some()
+        || (frames.is_none() && (config.loop_start.is_some() || config.loop_end.is_some() || config.fade_out_ms > 0))
 }
 
-/// Decode an audio file using Symphonia
-fn decode_audio(path: &Path) -> Result<AudioData> {
-    let file = File::open(path)
-        .with_context(|| format!("Failed to open audio file: {}", path.display()))?;
-
-    let mss = MediaSourceStream::new(Box::new(file), Default::default());
-
-    let mut hint = Hint::new();
-    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-        hint.with_extension(ext);
+/// Measure the peak and audible range of `chunks` after mixing
+fn analyze(chunks: impl Iterator<Item = Result<Vec<f32>>>, mix: &ChannelMix, config: &AudioConfig) -> Result<Analysis> {
+    let threshold = config
+        .trim_silence
+        .map_or(f32::INFINITY, |trim| 10f32.powf(trim.threshold_db / 20.0));
+    let mut analysis = Analysis::default();
+    for chunk in chunks {
+        analysis.add(&mix.apply(chunk?), mix.channels() as usize, threshold);
     }
+    Ok(analysis)
+}
 
-    let probed = symphonia::default::get_probe()
-        .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())
-        .with_context(|| format!("Failed to probe audio format: {}", path.display()))?;
-
-    let mut format = probed.format;
-
-    let track = format.tracks()
-        .iter()
-        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
-        .ok_or_else(|| anyhow::anyhow!("No audio track found in file"))?;
-
-    let codec_params = &track.codec_params;
-    let channels = codec_params.channels.map(|c| c.count() as u32).unwrap_or(2);
-    let sample_rate = codec_params.sample_rate.unwrap_or(44100);
+/// Work out the pipeline for a source and the format that comes out of it
+fn plan_audio(
+    source: StreamFormat,
+    mix: ChannelMix,
+    frames: Option<u64>,
+    analysis: Option<Analysis>,
+    config: &AudioConfig,
+) -> Result<(AudioPipeline, StreamFormat)> {
+    let frames = analysis.map(|analysis| analysis.frames).or(frames);
+    let mut loop_points = source.loop_points;
+    if config.loop_start.is_some() || config.loop_end.is_some() {
+        let frames = frames.unwrap_or_default();
+        let start = config.loop_start.unwrap_or(0);
+        let end = config.loop_end.unwrap_or(frames);
+        if start >= end || end > frames {
+            bail!("Loop {}..{} doesn't fit in the clip's {} sample frames", start, end, frames);
+        }
+        loop_points = Some(LoopPoints { start, end });
+    }
 
-    let mut decoder = symphonia::default::get_codecs()
-        .make(codec_params, &DecoderOptions::default())
-        .with_context(|| "Failed to create audio decoder")?;
+    let channels = mix.channels();
+
+    // Cut leading and trailing frames where no channel rises above the threshold; clips that
+    // are silent throughout are left as they are
+    let mut range = (0, u64::MAX);
+    if let (Some(trim), Some(analysis)) = (config.trim_silence, analysis) {
+        if let Some((first, last)) = analysis.audible {
+            let padding = trim.padding_ms as u64 * source.sample_rate as u64 / 1000;
+            range = (first.saturating_sub(padding), (last + 1 + padding).min(analysis.frames));
+            // A loop reaching into the trimmed silence keeps only what's left of it
+            loop_points = loop_points.and_then(|loop_points| {
+                let clamp = |frame: u64| frame.clamp(range.0, range.1) - range.0;
+                let (loop_start, loop_end) = (clamp(loop_points.start), clamp(loop_points.end));
+                (loop_start < loop_end).then_some(LoopPoints { start: loop_start, end: loop_end })
+            });
+        }
+    }
 
-    let track_id = track.id;
-    let mut samples: Vec<f32> = Vec::new();
+    // Normalize to a peak of 0.95, leaving some headroom; trimming only drops frames below
+    // the threshold, so the peak of the whole clip is the peak of what's left
+    let gain = analysis
+        .filter(|_| config.normalize)
+        .map(|analysis| analysis.peak)
+        .filter(|&peak| peak > 0.0 && peak != 1.0)
+        .map(|peak| 0.95 / peak);
+
+    let to_frames = |ms: u32| ms as u64 * source.sample_rate as u64 / 1000;
+    let length = range.1.min(frames.unwrap_or(u64::MAX)) - range.0;
+    let fade = Fade { fade_in: to_frames(config.fade_in_ms), fade_out: to_frames(config.fade_out_ms), length };
+    if let Some(loop_points) = loop_points {
+        if loop_points.start < fade.fade_in || loop_points.end > length.saturating_sub(fade.fade_out) {
+            tracing::warn!("Fade overlaps the loop {}..{}; the loop dips in volume each time round", loop_points.start, loop_points.end);
+        }
+    }
 
-    loop {
-        let packet = match format.next_packet() {
-            Ok(packet) => packet,
-            Err(symphonia::core::errors::Error::IoError(ref e))
-                if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
-            Err(e) => return Err(e.into()),
-        };
+    let sample_rate = config
+        .sample_rate
+        .unwrap_or_else(|| config.max_sample_rate.map_or(source.sample_rate, |max| source.sample_rate.min(max)));
+    let resampler = if sample_rate != source.sample_rate {
+        loop_points = loop_points.map(|loop_points| loop_points.rescale(source.sample_rate, sample_rate));
+        Some(Resampler::new(channels, source.sample_rate, sample_rate, config.resample_quality)?)
+    } else {
+        None
+    };
 
-        if packet.track_id() != track_id {
-            continue;
-        }
+    let pipeline = AudioPipeline::new(mix, range, gain, fade, resampler);
+    Ok((pipeline, StreamFormat { channels, sample_rate, loop_points }))
+}
 
-        let decoded = decoder.decode(&packet)?;
-        append_samples(&decoded, &mut samples);
-    }
+/// Loop points stored in the file; only WAV `smpl` chunks are read
+pub(super) fn read_loop(path: &Path) -> Result<Option<LoopPoints>> {
+    let is_wav = path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("wav"));
+    if is_wav { read_wav_loop(path) } else { Ok(None) }
+}
 
-    Ok(AudioData {
-        samples,
-        channels,
-        sample_rate,
-    })
+/// Receives processed audio chunk by chunk and writes it out in one format
+pub(super) trait AudioSink {
+    fn write(&mut self, samples: &[f32]) -> Result<()>;
+    /// Flush the encoder and finish the file
+    fn finish(self: Box<Self>) -> Result<()>;
 }
 
-fn append_samples(buffer: &AudioBufferRef, samples: &mut Vec<f32>) {
-    match buffer {
-        AudioBufferRef::F32(buf) => {
-            for plane in buf.planes().planes() {
-                samples.extend_from_slice(plane);
-            }
+/// Open `output` for audio in `format`, encoded as `config` says
+pub(super) fn create_sink(output: &Path, config: &AudioConfig, format: StreamFormat) -> Result<Box<dyn AudioSink>> {
+    Ok(match config.output_format {
+        AudioFormat::Wav => Box::new(WavSink::create(output, format, config.wav_format)?),
+        AudioFormat::Ogg => ogg_sink(output, format, config.quality)?,
+        AudioFormat::Opus => {
+            let file = create_file(output)?;
+            let writer = OpusWriter::new(
+                file,
+                format.channels,
+                format.sample_rate,
+                format.loop_points,
+                config.bitrate,
+                config.bitrate_mode,
+                config.resample_quality,
+            )?;
+            Box::new(writer)
         }
-        AudioBufferRef::S16(buf) => {
-            for plane in buf.planes().planes() {
-                samples.extend(plane.iter().map(|&s| s as f32 / 32768.0));
-            }
-        }
-        AudioBufferRef::S32(buf) => {
-            for plane in buf.planes().planes() {
-                samples.extend(plane.iter().map(|&s| s as f32 / 2147483648.0));
-            }
+        AudioFormat::Flac => Box::new(FlacSink::create(output, format)?),
+        AudioFormat::Adpcm(codec) => {
+            let block_size = config
+                .adpcm_block_size
+                .unwrap_or_else(|| AdpcmCodec::default_block_size(format.channels, format.sample_rate));
+            let writer = AdpcmWriter::new(create_file(output)?, codec, format.channels, format.sample_rate, block_size)?;
+            Box::new(AdpcmSink { writer, path: output.to_path_buf(), format })
         }
-        AudioBufferRef::U8(buf) => {
-            for plane in buf.planes().planes() {
-                samples.extend(plane.iter().map(|&s| (s as f32 - 128.0) / 128.0));
+        AudioFormat::Mp3 => {
+            if format.loop_points.is_some() {
+                tracing::warn!("{}: MP3 has no loop point tags, the loop is dropped", output.display());
             }
+            mp3_sink(output, format, config.bitrate, config.bitrate_mode)?
         }
-        _ => {
-            // For other formats, try to convert
-            let spec = buffer.spec();
-            let frames = buffer.frames();
-            for frame in 0..frames {
-                for ch in 0..spec.channels.count() {
-                    // Default to silence for unsupported formats
-                    samples.push(0.0);
-                }
-            }
-        }
-    }
+    })
 }
 
-/// Normalize audio to target peak level
-fn normalize_audio(mut audio: AudioData) -> AudioData {
-    if audio.samples.is_empty() {
-        return audio;
-    }
-
-    // Find peak
-    let peak = audio.samples.iter()
-        .map(|s| s.abs())
-        .fold(0.0f32, |a, b| a.max(b));
+fn create_file(output: &Path) -> Result<BufWriter<File>> {
+    let file = File::create(output)
+        .with_context(|| format!("Failed to create audio file: {}", output.display()))?;
+    Ok(BufWriter::new(file))
+}
 
-    if peak > 0.0 && peak != 1.0 {
-        let target_peak = 0.95; // Leave some headroom
-        let gain = target_peak / peak;
+impl AudioSink for OpusWriter<BufWriter<File>> {
+    fn write(&mut self, samples: &[f32]) -> Result<()> {
+        OpusWriter::write(self, samples)
+    }
 
-        for sample in &mut audio.samples {
-            *sample *= gain;
-        }
+    fn finish(self: Box<Self>) -> Result<()> {
+        OpusWriter::finish(*self)?.flush()?;
+        Ok(())
     }
+}
 
-    audio
+/// PCM WAV output
+struct WavSink {
+    writer: WavWriter<BufWriter<File>>,
+    path: PathBuf,
+    format: StreamFormat,
+    sample_format: WavSampleFormat,
 }
 
-/// Simple resampling (linear interpolation)
-fn resample_audio(audio: AudioData, target_rate: u32) -> Result<AudioData> {
-    if audio.sample_rate == target_rate {
-        return Ok(audio);
+impl WavSink {
+    fn create(output: &Path, format: StreamFormat, sample_format: WavSampleFormat) -> Result<Self> {
+        let spec = WavSpec {
+            channels: format.channels as u16,
+            sample_rate: format.sample_rate,
+            bits_per_sample: sample_format.bits(),
+            sample_format: if sample_format == WavSampleFormat::Float32 {
+                hound::SampleFormat::Float
+            } else {
+                hound::SampleFormat::Int
+            },
+        };
+        let writer = WavWriter::create(output, spec)
+            .with_context(|| format!("Failed to create WAV file: {}", output.display()))?;
+        Ok(Self { writer, path: output.to_path_buf(), format, sample_format })
     }
+}
 
-    let ratio = target_rate as f64 / audio.sample_rate as f64;
-    let channels = audio.channels as usize;
-    let input_frames = audio.samples.len() / channels;
-    let output_frames = (input_frames as f64 * ratio).ceil() as usize;
-
-    let mut output = Vec::with_capacity(output_frames * channels);
+impl AudioSink for WavSink {
+    fn write(&mut self, samples: &[f32]) -> Result<()> {
+        for &sample in samples {
+            match self.sample_format {
+                WavSampleFormat::Int8 => self.writer.write_sample((sample * 127.0).clamp(-128.0, 127.0) as i8)?,
+                WavSampleFormat::Int16 => self.writer.write_sample((sample * 32767.0).clamp(-32768.0, 32767.0) as i16)?,
+                WavSampleFormat::Int24 => {
+                    self.writer.write_sample((sample * 8_388_607.0).clamp(-8_388_608.0, 8_388_607.0) as i32)?
+                }
+                WavSampleFormat::Float32 => self.writer.write_sample(sample)?,
+            }
+        }
+        Ok(())
+    }
 
-    for frame in 0..output_frames {
-        let src_pos = frame as f64 / ratio;
-        let src_frame = src_pos.floor() as usize;
-        let frac = (src_pos - src_frame as f64) as f32;
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.writer.finalize()?;
+        if let Some(loop_points) = self.format.loop_points {
+            write_wav_loop(&self.path, loop_points, self.format.sample_rate)?;
+        }
+        Ok(())
+    }
+}
 
-        for ch in 0..channels {
-            let idx0 = src_frame * channels + ch;
-            let idx1 = ((src_frame + 1).min(input_frames - 1)) * channels + ch;
+/// ADPCM WAV output, with the loop in a `smpl` chunk like PCM WAV
+struct AdpcmSink {
+    writer: AdpcmWriter<BufWriter<File>>,
+    path: PathBuf,
+    format: StreamFormat,
+}
 
-            let s0 = audio.samples.get(idx0).copied().unwrap_or(0.0);
-            let s1 = audio.samples.get(idx1).copied().unwrap_or(0.0);
+impl AudioSink for AdpcmSink {
+    fn write(&mut self, samples: &[f32]) -> Result<()> {
+        self.writer.write(samples)
+    }
 
-            // Linear interpolation
-            output.push(s0 + (s1 - s0) * frac);
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.writer.finish()?.flush()?;
+        if let Some(loop_points) = self.format.loop_points {
+            write_wav_loop(&self.path, loop_points, self.format.sample_rate)?;
         }
+        Ok(())
     }
-
-    Ok(AudioData {
-        samples: output,
-        channels: audio.channels,
-        sample_rate: target_rate,
-    })
 }
 
-/// Encode audio to WAV format
-fn encode_wav(audio: &AudioData, output: &Path) -> Result<()> {
-    let spec = WavSpec {
-        channels: audio.channels as u16,
-        sample_rate: audio.sample_rate,
-        bits_per_sample: 16,
-        sample_format: hound::SampleFormat::Int,
-    };
+/// FLAC metadata block type of Vorbis comments
+const VORBIS_COMMENT_BLOCK: u8 = 4;
+
+/// FLAC output at 16 bits, losslessly matching the WAV output
+///
+/// Frames are written as each block fills; the stream info at the front of the file, which
+/// holds the length and MD5 of the audio, is rewritten once the last frame is out.
+struct FlacSink {
+    file: BufWriter<File>,
+    config: flacenc::error::Verified<flacenc::config::Encoder>,
+    /// Metadata blocks only; frames go straight to the file
+    header: flacenc::component::Stream,
+    block: (FrameBuf, flacenc::source::Context),
+    block_size: usize,
+    channels: usize,
+    /// Samples not yet making up a whole block
+    pending: Vec<i32>,
+}
 
-    let mut writer = WavWriter::create(output, spec)
-        .with_context(|| format!("Failed to create WAV file: {}", output.display()))?;
+impl FlacSink {
+    fn create(output: &Path, format: StreamFormat) -> Result<Self> {
+        let config = flacenc::config::Encoder::default()
+            .into_verified()
+            .map_err(|(_, e)| anyhow::anyhow!("Invalid FLAC encoder config: {}", e))?;
+        let channels = format.channels as usize;
+        let block_size = config.block_size;
+        let info = StreamInfo::new(format.sample_rate as usize, channels, 16)
+            .map_err(|e| anyhow::anyhow!("Invalid FLAC stream: {}", e))?;
+        let mut header = flacenc::component::Stream::with_stream_info(info);
+        // Decoders tell fixed-blocksize streams by the minimum block size matching the maximum,
+        // and the shorter last block doesn't count toward it
+        header
+            .stream_info_mut()
+            .set_block_sizes(block_size, block_size)
+            .map_err(|e| anyhow::anyhow!("Invalid FLAC block size: {}", e))?;
+        if let Some(loop_points) = format.loop_points {
+            let comment = MetadataBlockData::new_unknown(VORBIS_COMMENT_BLOCK, &vorbis_comment(&loop_points.comment_tags()))
+                .map_err(|e| anyhow::anyhow!("Invalid FLAC metadata: {}", e))?;
+            header.add_metadata_block(comment);
+        }
+        let block = (
+            FrameBuf::with_size(channels, block_size).map_err(|e| anyhow::anyhow!("Invalid FLAC stream: {}", e))?,
+            flacenc::source::Context::new(16, channels),
+        );
+
+        let mut sink = Self {
+            file: create_file(output)?,
+            config,
+            header,
+            block,
+            block_size,
+            channels,
+            pending: Vec::new(),
+        };
+        // Written again with the final stream info at the end; its size doesn't change
+        sink.write_header()?;
+        Ok(sink)
+    }
 
-    for &sample in &audio.samples {
-        // Convert f32 to i16
-        let s = (sample * 32767.0).clamp(-32768.0, 32767.0) as i16;
-        writer.write_sample(s)?;
+    fn write_header(&mut self) -> Result<()> {
+        let mut bytes = flacenc::bitsink::ByteSink::new();
+        self.header
+            .write(&mut bytes)
+            .map_err(|e| anyhow::anyhow!("Failed to write FLAC stream: {:?}", e))?;
+        self.file.write_all(bytes.as_slice())?;
+        Ok(())
     }
 
-    writer.finalize()?;
-    Ok(())
+    fn encode_block(&mut self, samples: &[i32]) -> Result<()> {
+        self.block
+            .fill_interleaved(samples)
+            .map_err(|e| anyhow::anyhow!("Failed to encode FLAC: {:?}", e))?;
+        let frame_number = self.block.1.current_frame_number().unwrap_or_default();
+        let frame = flacenc::encode_fixed_size_frame(&self.config, &self.block.0, frame_number, self.header.stream_info())
+            .map_err(|e| anyhow::anyhow!("Failed to encode FLAC: {:?}", e))?;
+        self.header.stream_info_mut().update_frame_info(&frame);
+        let mut bytes = flacenc::bitsink::ByteSink::new();
+        frame
+            .write(&mut bytes)
+            .map_err(|e| anyhow::anyhow!("Failed to write FLAC stream: {:?}", e))?;
+        self.file.write_all(bytes.as_slice())?;
+        Ok(())
+    }
 }
 
-/// Encode audio to OGG Vorbis format
-fn encode_ogg(audio: &AudioData, output: &Path, quality: f32) -> Result<()> {
-    let output_file = File::create(output)
-        .with_context(|| format!("Failed to create OGG file: {}", output.display()))?;
+impl AudioSink for FlacSink {
+    fn write(&mut self, samples: &[f32]) -> Result<()> {
+        self.pending
+            .extend(samples.iter().map(|&sample| (sample * 32767.0).clamp(-32768.0, 32767.0) as i32));
+        let len = self.block_size * self.channels;
+        let mut offset = 0;
+        while self.pending.len() - offset >= len {
+            let block = self.pending[offset..offset + len].to_vec();
+            self.encode_block(&block)?;
+            offset += len;
+        }
+        self.pending.drain(..offset);
+        Ok(())
+    }
 
-    // Deinterleave samples for vorbis encoder
-    let channels = audio.channels as usize;
-    let frames = audio.samples.len() / channels;
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        if !self.pending.is_empty() {
+            let block = std::mem::take(&mut self.pending);
+            self.encode_block(&block)?;
+        }
+        let block_size = self.block_size;
+        let md5 = self.block.1.md5_digest();
+        let info = self.header.stream_info_mut();
+        info.set_md5_digest(&md5);
+        info.set_block_sizes(block_size, block_size)
+            .map_err(|e| anyhow::anyhow!("Invalid FLAC block size: {}", e))?;
+        self.file.seek(SeekFrom::Start(0))?;
+        self.write_header()?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
 
-    let mut channel_data: Vec<Vec<f32>> = vec![Vec::with_capacity(frames); channels];
+/// OGG Vorbis output via libvorbis
+#[cfg(feature = "native-audio")]
+struct VorbisSink {
+    encoder: vorbis_rs::VorbisEncoder<BufWriter<File>>,
+    channels: usize,
+    /// Samples short of a whole chunk
+    pending: Vec<f32>,
+}
 
-    for (i, &sample) in audio.samples.iter().enumerate() {
-        let ch = i % channels;
-        channel_data[ch].push(sample);
-    }
+#[cfg(feature = "native-audio")]
+fn ogg_sink(output: &Path, format: StreamFormat, quality: f32) -> Result<Box<dyn AudioSink>> {
+    let output_file = create_file(output)?;
 
     // Create encoder using builder
-    let sample_rate = std::num::NonZeroU32::new(audio.sample_rate)
+    let sample_rate = std::num::NonZeroU32::new(format.sample_rate)
         .ok_or_else(|| anyhow::anyhow!("Invalid sample rate: 0"))?;
-    let num_channels = std::num::NonZeroU8::new(audio.channels as u8)
+    let num_channels = std::num::NonZeroU8::new(format.channels as u8)
         .ok_or_else(|| anyhow::anyhow!("Invalid channel count: 0"))?;
 
-    let mut encoder = VorbisEncoderBuilder::new(
+    let encoder = VorbisEncoderBuilder::new(
         sample_rate,
         num_channels,
         output_file,
     )
     .map_err(|e| anyhow::anyhow!("Failed to create Vorbis encoder builder: {:?}", e))?
     .bitrate_management_strategy(VorbisBitrateManagementStrategy::QualityVbr { target_quality: quality })
+    .comment_tags(format.loop_points.map(|loop_points| loop_points.comment_tags()).unwrap_or_default())
+    .map_err(|e| anyhow::anyhow!("Invalid Vorbis comment: {:?}", e))?
     .build()
     .map_err(|e| anyhow::anyhow!("Failed to build Vorbis encoder: {:?}", e))?;
 
-    // Encode in chunks
-    const CHUNK_SIZE: usize = 4096;
-    let mut pos = 0;
+    Ok(Box::new(VorbisSink { encoder, channels: format.channels as usize, pending: Vec::new() }))
+}
 
-    while pos < frames {
-        let end = (pos + CHUNK_SIZE).min(frames);
+#[cfg(feature = "native-audio")]
+impl VorbisSink {
+    /// libvorbis picks block sizes from what it has buffered, so audio goes in in blocks of
+    /// the same size however it arrives
+    const CHUNK_SIZE: usize = 4096;
 
-        // Prepare channel slices for this chunk
-        let chunk_refs: Vec<&[f32]> = channel_data.iter()
-            .map(|ch| &ch[pos..end])
+    fn encode_pending(&mut self, frames: usize) -> Result<()> {
+        // Deinterleave samples for vorbis encoder
+        let channel_data: Vec<Vec<f32>> = (0..self.channels)
+            .map(|ch| self.pending.iter().skip(ch).step_by(self.channels).take(frames).copied().collect())
             .collect();
-
-        encoder.encode_audio_block(&chunk_refs)
+        self.encoder.encode_audio_block(&channel_data)
             .map_err(|e| anyhow::anyhow!("Failed to encode audio block: {:?}", e))?;
+        self.pending.drain(..frames * self.channels);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "native-audio")]
+impl AudioSink for VorbisSink {
+    fn write(&mut self, samples: &[f32]) -> Result<()> {
+        for chunk in samples.chunks(Self::CHUNK_SIZE * self.channels) {
+            self.pending.extend_from_slice(chunk);
+            if self.pending.len() >= Self::CHUNK_SIZE * self.channels {
+                self.encode_pending(Self::CHUNK_SIZE)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        if !self.pending.is_empty() {
+            self.encode_pending(self.pending.len() / self.channels)?;
+        }
+        self.encoder.finish()
+            .map_err(|e| anyhow::anyhow!("Failed to finalize Vorbis file: {:?}", e))?
+            .flush()?;
+        Ok(())
+    }
+}
+
+/// OGG Vorbis output with the built-in encoder
+#[cfg(not(feature = "native-audio"))]
+fn ogg_sink(output: &Path, format: StreamFormat, quality: f32) -> Result<Box<dyn AudioSink>> {
+    let writer = VorbisWriter::new(create_file(output)?, format.channels, format.sample_rate, format.loop_points, quality)?;
+    Ok(Box::new(writer))
+}
+
+#[cfg(not(feature = "native-audio"))]
+impl AudioSink for VorbisWriter<BufWriter<File>> {
+    fn write(&mut self, samples: &[f32]) -> Result<()> {
+        VorbisWriter::write(self, samples)
+    }
 
-        pos = end;
+    fn finish(self: Box<Self>) -> Result<()> {
+        VorbisWriter::finish(*self)?.flush()?;
+        Ok(())
     }
+}
+
+/// MP3 output with LAME
+///
+/// CBR holds every frame at `bitrate`; the VBR modes use LAME's average bitrate mode,
+/// which varies frames around it.
+#[cfg(feature = "native-audio")]
+struct Mp3Sink {
+    encoder: mp3lame_encoder::Encoder,
+    file: BufWriter<File>,
+    channels: u32,
+    data: Vec<u8>,
+}
 
-    encoder.finish()
-        .map_err(|e| anyhow::anyhow!("Failed to finalize Vorbis file: {:?}", e))?;
+#[cfg(feature = "native-audio")]
+fn mp3_sink(output: &Path, format: StreamFormat, bitrate: Option<u32>, mode: BitrateMode) -> Result<Box<dyn AudioSink>> {
+    let kbps = bitrate.unwrap_or(128);
+    let brate = match kbps {
+        8 => Bitrate::Kbps8,
+        16 => Bitrate::Kbps16,
+        24 => Bitrate::Kbps24,
+        32 => Bitrate::Kbps32,
+        40 => Bitrate::Kbps40,
+        48 => Bitrate::Kbps48,
+        64 => Bitrate::Kbps64,
+        80 => Bitrate::Kbps80,
+        96 => Bitrate::Kbps96,
+        112 => Bitrate::Kbps112,
+        128 => Bitrate::Kbps128,
+        160 => Bitrate::Kbps160,
+        192 => Bitrate::Kbps192,
+        224 => Bitrate::Kbps224,
+        256 => Bitrate::Kbps256,
+        320 => Bitrate::Kbps320,
+        _ => anyhow::bail!(
+            "Unsupported MP3 bitrate {} kbps (use 8, 16, 24, 32, 40, 48, 64, 80, 96, 112, 128, 160, 192, 224, 256 or 320)",
+            kbps
+        ),
+    };
+    if format.channels == 0 || format.channels > 2 {
+        anyhow::bail!("MP3 output supports mono or stereo, not {} channels", format.channels);
+    }
 
-    Ok(())
+    let lame = |e| anyhow::anyhow!("Failed to set up MP3 encoder: {:?}", e);
+    let mut builder = mp3lame_encoder::Builder::new()
+        .ok_or_else(|| anyhow::anyhow!("Failed to create MP3 encoder"))?;
+    builder.set_num_channels(format.channels as u8).map_err(lame)?;
+    builder.set_sample_rate(format.sample_rate).map_err(lame)?;
+    builder.set_brate(brate).map_err(lame)?;
+    builder.set_quality(mp3lame_encoder::Quality::Best).map_err(lame)?;
+    builder
+        .set_vbr_mode(if mode == BitrateMode::Cbr { VbrMode::Off } else { VbrMode::Abr })
+        .map_err(lame)?;
+    let encoder = builder.build().map_err(lame)?;
+
+    Ok(Box::new(Mp3Sink { encoder, file: create_file(output)?, channels: format.channels, data: Vec::new() }))
+}
+
+#[cfg(feature = "native-audio")]
+impl AudioSink for Mp3Sink {
+    fn write(&mut self, samples: &[f32]) -> Result<()> {
+        let frames = samples.len() / self.channels as usize;
+        self.data.clear();
+        self.data.reserve(mp3lame_encoder::max_required_buffer_size(frames));
+        let encoded = if self.channels == 1 {
+            self.encoder.encode_to_vec(MonoPcm(samples), &mut self.data)
+        } else {
+            self.encoder.encode_to_vec(InterleavedPcm(samples), &mut self.data)
+        };
+        encoded.map_err(|e| anyhow::anyhow!("Failed to encode MP3: {:?}", e))?;
+        self.file.write_all(&self.data)?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.data.clear();
+        self.data.reserve(7200);
+        self.encoder
+            .flush_to_vec::<FlushNoGap>(&mut self.data)
+            .map_err(|e| anyhow::anyhow!("Failed to finish MP3: {:?}", e))?;
+        self.file.write_all(&self.data)?;
+
+        // LAME leaves a blank first frame for the tag that holds the frame count and encoder
+        // delay, which players need for exact length and gapless playback
+        let mut tag = Vec::with_capacity(self.encoder.lame_tag_size());
+        if self.encoder.lame_tag_encode_to_vec(&mut tag).is_some() {
+            self.file.seek(SeekFrom::Start(self.encoder.id3v2_tag_size() as u64))?;
+            self.file.write_all(&tag)?;
+        }
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "native-audio"))]
+fn mp3_sink(_output: &Path, _format: StreamFormat, _bitrate: Option<u32>, _mode: BitrateMode) -> Result<Box<dyn AudioSink>> {
+    anyhow::bail!("MP3 output needs the native-audio feature (LAME); use Ogg or Opus instead")
 }
 
 /// Get audio file information
 pub fn get_audio_info(path: &Path) -> Result<AudioInfo> {
-    let audio = decode_audio(path)?;
+    let mut decoder = AudioDecoder::open(path)?;
+    let mut meter = LevelMeter::new(decoder.layout.loudness_weights(), decoder.sample_rate);
+    let mut samples = 0u64;
+    while let Some(chunk) = decoder.next_chunk()? {
+        samples += chunk.len() as u64;
+        meter.add(&chunk);
+    }
+    let frames = samples as f64 / decoder.channels.max(1) as f64;
 
     Ok(AudioInfo {
-        channels: audio.channels,
-        sample_rate: audio.sample_rate,
-        duration_secs: audio.duration_secs(),
+        channels: decoder.channels,
+        layout: decoder.layout.clone(),
+        sample_rate: decoder.sample_rate,
+        duration_secs: frames_to_secs(frames, decoder.sample_rate),
         format: detect_audio_format(path),
+        loop_points: read_loop(path)?,
+        levels: meter.finish(),
     })
 }
 
+/// Get the duration of an audio file in seconds.
+///
+/// Uses the frame count from the container when it is known and only falls
+/// back to decoding the stream for ones that don't report one.
+pub fn probe_audio_duration(path: &Path) -> Result<f64> {
+    let mut decoder = AudioDecoder::open(path)?;
+    if let Some(n_frames) = decoder.frames {
+        if decoder.sample_rate > 0 {
+            return Ok(n_frames as f64 / decoder.sample_rate as f64);
+        }
+    }
+
+    let frames = count_frames(&mut decoder)?;
+    Ok(frames_to_secs(frames, decoder.sample_rate))
+}
+
+/// Decode the rest of the stream, counting sample frames
+pub(super) fn count_frames(decoder: &mut AudioDecoder) -> Result<f64> {
+    let mut samples = 0u64;
+    while let Some(chunk) = decoder.next_chunk()? {
+        samples += chunk.len() as u64;
+    }
+    Ok(samples as f64 / decoder.channels.max(1) as f64)
+}
+
+pub(super) fn frames_to_secs(frames: f64, sample_rate: u32) -> f64 {
+    if sample_rate == 0 { 0.0 } else { frames / sample_rate as f64 }
+}
+
 /// Audio file information
 #[derive(Debug, Clone)]
 pub struct AudioInfo {
     pub channels: u32,
+    pub layout: ChannelLayout,
     pub sample_rate: u32,
     pub duration_secs: f64,
     pub format: String,
+    pub loop_points: Option<LoopPoints>,
+    pub levels: AudioLevels,
 }
 
 fn detect_audio_format(path: &Path) -> String {
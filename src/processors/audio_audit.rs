@@ -0,0 +1,75 @@
+use anyhow::Result;
+use std::path::Path;
+
+use super::audio::read_loop;
+use super::audio_levels::LevelMeter;
+use super::audio_stream::AudioDecoder;
+
+/// Sample-level measurements used by `audit audio`
+#[derive(Debug, Clone)]
+pub struct AudioAnalysis {
+    /// Length in sample frames (one sample per channel)
+    pub frames: u64,
+    pub sample_rate: u32,
+    /// Largest step, in full scale, between where a channel's last samples before the loop
+    /// end were heading and its sample at the loop start; near zero for a seamless loop
+    pub loop_jump: f32,
+    /// Largest per-channel mean, in full scale, with the channel it was found on
+    pub dc_offset: (f32, u32),
+}
+
+impl AudioAnalysis {
+    pub fn duration_secs(&self) -> f64 {
+        if self.sample_rate == 0 {
+            return 0.0;
+        }
+        self.frames as f64 / self.sample_rate as f64
+    }
+}
+
+/// Decode an audio file a packet at a time and measure its length, loop seam and DC offset
+///
+/// The seam is the WAV `smpl` loop when the file has one, otherwise the end of the file
+/// wrapping to its start.
+pub fn analyze_audio(path: &Path) -> Result<AudioAnalysis> {
+    let loop_points = read_loop(path)?;
+    let mut decoder = AudioDecoder::open(path)?;
+    let channels = decoder.channels.max(1) as usize;
+    let mut meter = LevelMeter::new(decoder.layout.loudness_weights(), decoder.sample_rate);
+
+    let (loop_start, loop_end) = loop_points.map_or((0, None), |l| (l.start, Some(l.end)));
+    // The frame at the loop start and the two frames before the loop end
+    let mut start_frame: Option<Vec<f32>> = None;
+    let mut end_frames: [Option<Vec<f32>>; 2] = [None, None];
+    let mut frames = 0u64;
+    while let Some(chunk) = decoder.next_chunk()? {
+        meter.add(&chunk);
+        for frame in chunk.chunks_exact(channels) {
+            if frames == loop_start {
+                start_frame = Some(frame.to_vec());
+            }
+            if loop_end.is_none_or(|end| frames + 2 >= end && frames < end) {
+                end_frames = [end_frames[1].take(), Some(frame.to_vec())];
+            }
+            frames += 1;
+        }
+    }
+
+    let mut loop_jump = 0.0f32;
+    if let (Some(first), [Some(before_last), Some(last)]) = (&start_frame, &end_frames) {
+        if frames > 2 {
+            for channel in 0..channels {
+                // Extrapolate one sample past the end; a smooth waveform would continue into that
+                let expected = 2.0 * last[channel] - before_last[channel];
+                loop_jump = loop_jump.max((first[channel] - expected).abs());
+            }
+        }
+    }
+
+    Ok(AudioAnalysis {
+        frames,
+        sample_rate: decoder.sample_rate,
+        loop_jump,
+        dc_offset: meter.finish().dc_offset,
+    })
+}
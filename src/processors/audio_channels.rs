@@ -0,0 +1,226 @@
+//! Planar to interleaved conversion and channel-layout-aware mixing
+
+use anyhow::{bail, Result};
+use std::f32::consts::FRAC_1_SQRT_2;
+use symphonia::core::audio::{AudioBuffer, AudioBufferRef, Channels, Signal};
+use symphonia::core::conv::IntoSample;
+use symphonia::core::sample::Sample;
+
+/// Append a decoded buffer's samples to `samples`, interleaved frame by frame
+///
+/// Decoders hand out one plane per channel; everything after the decoder works on
+/// interleaved frames.
+pub(super) fn interleave(buffer: &AudioBufferRef, samples: &mut Vec<f32>) {
+    match buffer {
+        AudioBufferRef::U8(buf) => interleave_planes(buf, samples),
+        AudioBufferRef::U16(buf) => interleave_planes(buf, samples),
+        AudioBufferRef::U24(buf) => interleave_planes(buf, samples),
+        AudioBufferRef::U32(buf) => interleave_planes(buf, samples),
+        AudioBufferRef::S8(buf) => interleave_planes(buf, samples),
+        AudioBufferRef::S16(buf) => interleave_planes(buf, samples),
+        AudioBufferRef::S24(buf) => interleave_planes(buf, samples),
+        AudioBufferRef::S32(buf) => interleave_planes(buf, samples),
+        AudioBufferRef::F32(buf) => interleave_planes(buf, samples),
+        AudioBufferRef::F64(buf) => interleave_planes(buf, samples),
+    }
+}
+
+fn interleave_planes<S: Sample + IntoSample<f32>>(buffer: &AudioBuffer<S>, samples: &mut Vec<f32>) {
+    let planes = buffer.planes();
+    let planes = planes.planes();
+    samples.reserve(buffer.frames() * planes.len());
+    for frame in 0..buffer.frames() {
+        samples.extend(planes.iter().map(|plane| plane[frame].into_sample()));
+    }
+}
+
+/// Speaker position of each channel, in stream order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelLayout(Vec<Channels>);
+
+impl ChannelLayout {
+    /// Layout from a container's channel mask
+    pub fn from_channels(channels: Channels) -> Self {
+        Self(channels.iter().collect())
+    }
+
+    /// The usual WAVE/SMPTE layout for a channel count, when the container has no mask
+    pub fn default_for(count: u32) -> Self {
+        let (fl, fr, fc, lfe) = (Channels::FRONT_LEFT, Channels::FRONT_RIGHT, Channels::FRONT_CENTRE, Channels::LFE1);
+        let (rl, rr, rc, sl, sr) =
+            (Channels::REAR_LEFT, Channels::REAR_RIGHT, Channels::REAR_CENTRE, Channels::SIDE_LEFT, Channels::SIDE_RIGHT);
+        let mask = match count {
+            1 => fc,
+            2 => fl | fr,
+            3 => fl | fr | fc,
+            4 => fl | fr | rl | rr,
+            5 => fl | fr | fc | rl | rr,
+            6 => fl | fr | fc | lfe | rl | rr,
+            7 => fl | fr | fc | lfe | rc | sl | sr,
+            8 => fl | fr | fc | lfe | rl | rr | sl | sr,
+            // No standard layout; every channel is treated as unpositioned
+            _ => return Self(vec![Channels::empty(); count as usize]),
+        };
+        Self::from_channels(mask)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Common name for the layout ("stereo", "5.1"), if it has one
+    pub fn name(&self) -> Option<&'static str> {
+        let mask = self.0.iter().fold(Channels::empty(), |mask, &channel| mask | channel);
+        if mask.count() != self.len() {
+            return None;
+        }
+        let (fl, fr, fc, lfe) = (Channels::FRONT_LEFT, Channels::FRONT_RIGHT, Channels::FRONT_CENTRE, Channels::LFE1);
+        let (rl, rr, sl, sr) = (Channels::REAR_LEFT, Channels::REAR_RIGHT, Channels::SIDE_LEFT, Channels::SIDE_RIGHT);
+        Some(match mask {
+            m if m == fc => "mono",
+            m if m == fl | fr => "stereo",
+            m if m == fl | fr | lfe => "2.1",
+            m if m == fl | fr | rl | rr || m == fl | fr | sl | sr => "quad",
+            m if m == fl | fr | fc | rl | rr || m == fl | fr | fc | sl | sr => "5.0",
+            m if m == fl | fr | fc | lfe | rl | rr || m == fl | fr | fc | lfe | sl | sr => "5.1",
+            m if m == fl | fr | fc | lfe | Channels::REAR_CENTRE | sl | sr => "6.1",
+            m if m == fl | fr | fc | lfe | rl | rr | sl | sr => "7.1",
+            _ => return None,
+        })
+    }
+
+    /// Left and right gains of each channel when folded down to stereo
+    ///
+    /// Follows ITU-R BS.775: centre and surround channels go in at -3 dB, rear centre at -6 dB
+    /// to each side, and the LFE channel is dropped. Channels with no known position are
+    /// spread evenly over both sides.
+    fn stereo_gains(&self) -> Vec<(f32, f32)> {
+        const LEFT: Channels = Channels::FRONT_LEFT
+            .union(Channels::FRONT_LEFT_CENTRE)
+            .union(Channels::FRONT_LEFT_WIDE)
+            .union(Channels::FRONT_LEFT_HIGH)
+            .union(Channels::TOP_FRONT_LEFT);
+        const RIGHT: Channels = Channels::FRONT_RIGHT
+            .union(Channels::FRONT_RIGHT_CENTRE)
+            .union(Channels::FRONT_RIGHT_WIDE)
+            .union(Channels::FRONT_RIGHT_HIGH)
+            .union(Channels::TOP_FRONT_RIGHT);
+        const SURROUND_LEFT: Channels = Channels::REAR_LEFT
+            .union(Channels::SIDE_LEFT)
+            .union(Channels::REAR_LEFT_CENTRE)
+            .union(Channels::TOP_REAR_LEFT);
+        const SURROUND_RIGHT: Channels = Channels::REAR_RIGHT
+            .union(Channels::SIDE_RIGHT)
+            .union(Channels::REAR_RIGHT_CENTRE)
+            .union(Channels::TOP_REAR_RIGHT);
+        const REAR_CENTRE: Channels = Channels::REAR_CENTRE.union(Channels::TOP_REAR_CENTRE);
+        const LFE: Channels = Channels::LFE1.union(Channels::LFE2);
+
+        self.0
+            .iter()
+            .map(|&channel| match channel {
+                // Unpositioned channels
+                c if c.is_empty() => (FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+                c if LEFT.contains(c) => (1.0, 0.0),
+                c if RIGHT.contains(c) => (0.0, 1.0),
+                c if SURROUND_LEFT.contains(c) => (FRAC_1_SQRT_2, 0.0),
+                c if SURROUND_RIGHT.contains(c) => (0.0, FRAC_1_SQRT_2),
+                c if REAR_CENTRE.contains(c) => (0.5, 0.5),
+                c if LFE.contains(c) => (0.0, 0.0),
+                // Front centre and overhead centres
+                _ => (FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+            })
+            .collect()
+    }
+}
+
+impl ChannelLayout {
+    /// Weight of each channel in BS.1770 loudness: surrounds count 1.41, LFE not at all
+    pub(super) fn loudness_weights(&self) -> Vec<f64> {
+        let surround = Channels::REAR_LEFT | Channels::REAR_RIGHT | Channels::SIDE_LEFT | Channels::SIDE_RIGHT;
+        let lfe = Channels::LFE1 | Channels::LFE2;
+        self.0
+            .iter()
+            .map(|&channel| match channel {
+                c if c.is_empty() => 1.0,
+                c if lfe.contains(c) => 0.0,
+                c if surround.contains(c) => 1.41,
+                _ => 1.0,
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Display for ChannelLayout {
+    /// Channel count, with the layout's name when it has one: "6 (5.1)"
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{} ({})", self.len(), name),
+            None => write!(f, "{}", self.len()),
+        }
+    }
+}
+
+/// Conversion of interleaved frames from one channel layout to a channel count
+///
+/// Mono sources are copied to both sides of a stereo output. Sources with more than two
+/// channels fold down to stereo by [`ChannelLayout::stereo_gains`], scaled so that no output
+/// channel sums more than unity gain. Mono output takes the stereo fold-down at -3 dB per side
+/// (the -3 dB pan law), which keeps power roughly constant whether a sound was panned to one
+/// side or centered.
+#[derive(Debug, Clone)]
+pub(super) struct ChannelMix {
+    from: usize,
+    to: usize,
+    /// Gain of each source channel in each output channel, row by row; `None` passes frames
+    /// through unchanged
+    matrix: Option<Vec<f32>>,
+}
+
+impl ChannelMix {
+    pub fn new(layout: &ChannelLayout, to: u32) -> Result<Self> {
+        let from = layout.len();
+        let to = to as usize;
+        let matrix = match (from, to) {
+            (0, _) => bail!("Invalid channel count: 0"),
+            (from, to) if from == to => None,
+            (1, 2) => Some(vec![1.0, 1.0]),
+            (_, 1 | 2) => {
+                let (left, right): (Vec<f32>, Vec<f32>) = layout.stereo_gains().into_iter().unzip();
+                let sum = |row: &[f32]| row.iter().sum::<f32>();
+                let scale = 1.0 / sum(&left).max(sum(&right)).max(1.0);
+                let (left, right): (Vec<f32>, Vec<f32>) =
+                    (left.iter().map(|g| g * scale).collect(), right.iter().map(|g| g * scale).collect());
+                if to == 2 {
+                    Some([left, right].concat())
+                } else {
+                    Some(left.iter().zip(&right).map(|(l, r)| (l + r) * FRAC_1_SQRT_2).collect())
+                }
+            }
+            _ => bail!(
+                "Can't convert {} channels to {}; only mono, stereo and mono-to-stereo are supported",
+                from, to
+            ),
+        };
+        Ok(Self { from, to, matrix })
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.to as u32
+    }
+
+    /// Mix interleaved frames of the source layout
+    pub fn apply(&self, samples: Vec<f32>) -> Vec<f32> {
+        let Some(matrix) = &self.matrix else {
+            return samples;
+        };
+        let mut output = Vec::with_capacity(samples.len() / self.from * self.to);
+        for frame in samples.chunks_exact(self.from) {
+            output.extend(matrix.chunks_exact(self.from).map(|row| {
+                let sample: f32 = row.iter().zip(frame).map(|(gain, s)| gain * s).sum();
+                sample.clamp(-1.0, 1.0)
+            }));
+        }
+        output
+    }
+}
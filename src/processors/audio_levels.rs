@@ -0,0 +1,178 @@
+//! Level measurements for audio QA: peak, RMS, loudness, DC offset and clipping
+
+use std::f64::consts::PI;
+
+/// Samples at or beyond the largest 16-bit value count as clipped
+const CLIP_LEVEL: f32 = 32767.0 / 32768.0;
+/// BS.1770 gating blocks are 400 ms long, taken every 100 ms
+const BLOCK_STEPS: usize = 4;
+const ABSOLUTE_GATE: f64 = -70.0;
+const RELATIVE_GATE: f64 = -10.0;
+
+/// Levels of a whole clip, over all channels
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AudioLevels {
+    /// Largest sample magnitude, in full scale (sample peak, not oversampled true peak)
+    pub peak: f32,
+    /// RMS of every sample, in full scale
+    pub rms: f32,
+    /// Integrated loudness in LUFS per ITU-R BS.1770, or `None` for clips shorter than one
+    /// 400 ms gating block or silent throughout
+    pub loudness: Option<f64>,
+    /// Largest per-channel mean, in full scale, with the channel it was found on
+    pub dc_offset: (f32, u32),
+    /// Samples at or beyond full scale
+    pub clipped: u64,
+}
+
+/// Convert a level in full scale to dBFS
+pub fn dbfs(level: f32) -> f64 {
+    20.0 * (level as f64).log10()
+}
+
+/// Second-order IIR section, direct form I
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self { b, a, x: [0.0; 2], y: [0.0; 2] }
+    }
+
+    fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [input, self.x[0]];
+        self.y = [output, self.y[0]];
+        output
+    }
+}
+
+/// The BS.1770 K-weighting filter (a high shelf for the head, then a high-pass) for one
+/// channel, with coefficients derived for any sample rate rather than the 48 kHz tables
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let rate = sample_rate as f64;
+
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad::new([1.0, -2.0, 1.0], [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0]);
+
+    [shelf, high_pass]
+}
+
+/// Accumulates [`AudioLevels`] over interleaved chunks
+pub(super) struct LevelMeter {
+    channels: usize,
+    weights: Vec<f64>,
+    filters: Vec<[Biquad; 2]>,
+    step_frames: usize,
+    /// Weighted K-filtered energy of the current 100 ms step, and frames in it so far
+    step_energy: f64,
+    step_filled: usize,
+    /// Mean weighted energy of every finished step
+    steps: Vec<f64>,
+    peak: f32,
+    sum_squares: f64,
+    channel_sums: Vec<f64>,
+    clipped: u64,
+    frames: u64,
+}
+
+impl LevelMeter {
+    /// `weights` are each channel's share of the loudness, 0 for LFE channels
+    pub fn new(weights: Vec<f64>, sample_rate: u32) -> Self {
+        let channels = weights.len().max(1);
+        Self {
+            channels,
+            filters: vec![k_weighting(sample_rate); channels],
+            weights,
+            step_frames: (sample_rate as usize / 10).max(1),
+            step_energy: 0.0,
+            step_filled: 0,
+            steps: Vec::new(),
+            peak: 0.0,
+            sum_squares: 0.0,
+            channel_sums: vec![0.0; channels],
+            clipped: 0,
+            frames: 0,
+        }
+    }
+
+    pub fn add(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            let mut energy = 0.0;
+            for (channel, &sample) in frame.iter().enumerate() {
+                let magnitude = sample.abs();
+                self.peak = self.peak.max(magnitude);
+                if magnitude >= CLIP_LEVEL {
+                    self.clipped += 1;
+                }
+                self.sum_squares += (sample as f64).powi(2);
+                self.channel_sums[channel] += sample as f64;
+
+                let [shelf, high_pass] = &mut self.filters[channel];
+                let weighted = high_pass.process(shelf.process(sample as f64));
+                energy += self.weights[channel] * weighted * weighted;
+            }
+            self.frames += 1;
+
+            self.step_energy += energy;
+            self.step_filled += 1;
+            if self.step_filled == self.step_frames {
+                self.steps.push(self.step_energy / self.step_frames as f64);
+                self.step_energy = 0.0;
+                self.step_filled = 0;
+            }
+        }
+    }
+
+    pub fn finish(self) -> AudioLevels {
+        let samples = self.frames * self.channels as u64;
+        let rms = if samples > 0 { (self.sum_squares / samples as f64).sqrt() as f32 } else { 0.0 };
+        let mut dc_offset = (0.0f32, 0);
+        if self.frames > 0 {
+            for (channel, sum) in self.channel_sums.iter().enumerate() {
+                let mean = (sum / self.frames as f64) as f32;
+                if mean.abs() > dc_offset.0.abs() {
+                    dc_offset = (mean, channel as u32);
+                }
+            }
+        }
+
+        AudioLevels { peak: self.peak, rms, loudness: integrated_loudness(&self.steps), dc_offset, clipped: self.clipped }
+    }
+}
+
+/// Gated loudness over overlapping 400 ms blocks made of 100 ms steps
+fn integrated_loudness(steps: &[f64]) -> Option<f64> {
+    let loudness = |energy: f64| -0.691 + 10.0 * energy.log10();
+    let blocks: Vec<f64> = steps
+        .windows(BLOCK_STEPS)
+        .map(|window| window.iter().sum::<f64>() / BLOCK_STEPS as f64)
+        .filter(|&energy| energy > 0.0 && loudness(energy) > ABSOLUTE_GATE)
+        .collect();
+    if blocks.is_empty() {
+        return None;
+    }
+    // The loudest block is always above the relative gate, so `gated` is never empty
+    let threshold = loudness(blocks.iter().sum::<f64>() / blocks.len() as f64) + RELATIVE_GATE;
+    let gated: Vec<f64> = blocks.into_iter().filter(|&energy| loudness(energy) > threshold).collect();
+    Some(loudness(gated.iter().sum::<f64>() / gated.len() as f64))
+}
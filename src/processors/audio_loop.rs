@@ -0,0 +1,97 @@
+use anyhow::{Context, Result};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Loop region in sample frames; `end` is exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoopPoints {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl LoopPoints {
+    /// Loop points of the same region at another sample rate
+    pub fn rescale(self, from_rate: u32, to_rate: u32) -> Self {
+        let scale = |frame: u64| (frame as f64 * to_rate as f64 / from_rate as f64).round() as u64;
+        Self { start: scale(self.start), end: scale(self.end) }
+    }
+
+    /// `LOOPSTART` and `LOOPLENGTH` comments, the loop tags RPG Maker and many engines read
+    pub fn comment_tags(&self) -> Vec<(String, String)> {
+        vec![
+            ("LOOPSTART".to_string(), self.start.to_string()),
+            ("LOOPLENGTH".to_string(), (self.end - self.start).to_string()),
+        ]
+    }
+}
+
+/// First loop of a WAV file's `smpl` chunk, if it has one
+pub fn read_wav_loop(path: &Path) -> Result<Option<LoopPoints>> {
+    let mut file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open audio file: {}", path.display()))?;
+    let mut header = [0u8; 12];
+    if file.read_exact(&mut header).is_err() || &header[..4] != b"RIFF" || &header[8..] != b"WAVE" {
+        return Ok(None);
+    }
+
+    let mut chunk = [0u8; 8];
+    while file.read_exact(&mut chunk).is_ok() {
+        let size = u32::from_le_bytes(chunk[4..].try_into().unwrap()) as u64;
+        if &chunk[..4] != b"smpl" {
+            // Chunks are padded to an even length
+            file.seek(SeekFrom::Current((size + size % 2) as i64))?;
+            continue;
+        }
+        let mut data = vec![0u8; size as usize];
+        file.read_exact(&mut data)
+            .with_context(|| format!("Truncated smpl chunk in {}", path.display()))?;
+        let field = |offset: usize| {
+            data.get(offset..offset + 4).map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        };
+        // 36 bytes of sampler fields, then 24-byte loops whose end sample is inclusive
+        return Ok(match (field(28), field(44), field(48)) {
+            (Some(loops), Some(start), Some(end)) if loops > 0 && end >= start => {
+                Some(LoopPoints { start: start as u64, end: end as u64 + 1 })
+            }
+            _ => None,
+        });
+    }
+    Ok(None)
+}
+
+/// Append a `smpl` chunk with one forward loop to a finished WAV file
+pub fn write_wav_loop(path: &Path, loop_points: LoopPoints, sample_rate: u32) -> Result<()> {
+    let fields: [u32; 15] = [
+        0,                                          // manufacturer
+        0,                                          // product
+        1_000_000_000 / sample_rate.max(1),         // sample period in nanoseconds
+        60,                                         // MIDI unity note (middle C)
+        0,                                          // pitch fraction
+        0,                                          // SMPTE format
+        0,                                          // SMPTE offset
+        1,                                          // loop count
+        0,                                          // sampler data size
+        0,                                          // cue point ID
+        0,                                          // forward loop
+        loop_points.start as u32,
+        loop_points.end.saturating_sub(1) as u32,   // inclusive
+        0,                                          // fraction
+        0,                                          // play count (loop forever)
+    ];
+    let mut chunk = b"smpl".to_vec();
+    chunk.extend_from_slice(&(fields.len() as u32 * 4).to_le_bytes());
+    for field in fields {
+        chunk.extend_from_slice(&field.to_le_bytes());
+    }
+
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("Failed to open WAV file: {}", path.display()))?;
+    let len = file.seek(SeekFrom::End(0))?;
+    file.write_all(&chunk)?;
+    file.seek(SeekFrom::Start(4))?;
+    file.write_all(&((len + chunk.len() as u64 - 8) as u32).to_le_bytes())?;
+    Ok(())
+}
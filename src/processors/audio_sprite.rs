@@ -0,0 +1,132 @@
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use super::audio::{create_sink, open_pipeline, StreamFormat};
+use super::audio_stream::AudioDecoder;
+use super::{AssetType, AudioConfig, ProcessingStats};
+
+pub use crate::runtime::{AudioSpriteEntry, AudioSpriteMap};
+
+/// Configuration for audio sprite generation
+#[derive(Debug, Clone, Default)]
+pub struct AudioSpriteConfig {
+    /// Output format and per-sound processing; `sample_rate` and `channels` default to the
+    /// highest of the sources
+    pub audio: AudioConfig,
+    /// Silence between consecutive sounds, in milliseconds
+    pub gap_ms: u32,
+}
+
+/// Result of generating an audio sprite
+pub struct AudioSpriteResult {
+    pub map: AudioSpriteMap,
+    /// Sprite map JSON written next to the audio
+    pub map_path: PathBuf,
+    pub stats: ProcessingStats,
+}
+
+/// Concatenate the audio files in a directory into one file with a map of where each sound is
+///
+/// Sounds are ordered by file name and named by file stem; the [`AudioSpriteMap`] goes next to
+/// the audio (`sfx.ogg` → `sfx.json`). Every sound is converted to one sample rate and channel
+/// count, and sounds are separated by `gap_ms` of silence so playback that overshoots its end
+/// doesn't bleed into the next sound.
+pub fn generate_audio_sprite(input_dir: &Path, output: &Path, config: &AudioSpriteConfig) -> Result<AudioSpriteResult> {
+    let start = Instant::now();
+    let mut audio_paths: Vec<PathBuf> = std::fs::read_dir(input_dir)
+        .with_context(|| format!("Failed to read directory: {}", input_dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && AssetType::from_path(path) == AssetType::Audio)
+        .collect();
+    // Sort for a stable sound order
+    audio_paths.sort();
+
+    if audio_paths.is_empty() {
+        anyhow::bail!("No audio files found in directory: {}", input_dir.display());
+    }
+
+    // Open every sound once for its format, then stream them into the sprite one by one
+    let mut names: HashMap<String, &Path> = HashMap::new();
+    let mut sounds = Vec::with_capacity(audio_paths.len());
+    let mut original_size = 0;
+    let (mut max_rate, mut max_channels) = (0, 0);
+    for path in &audio_paths {
+        let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_string();
+        if let Some(other) = names.insert(name.clone(), path) {
+            anyhow::bail!("{} and {} would both be sound \"{}\"", other.display(), path.display(), name);
+        }
+        original_size += std::fs::metadata(path)?.len();
+        let decoder = AudioDecoder::open(path).with_context(|| format!("Failed to decode {}", path.display()))?;
+        max_rate = max_rate.max(decoder.sample_rate);
+        max_channels = max_channels.max(decoder.channels);
+        sounds.push((name, path));
+    }
+
+    let sample_rate = config.audio.sample_rate.unwrap_or(if max_rate > 0 { max_rate } else { 44100 });
+    let channels = config.audio.channels.unwrap_or(if max_channels > 0 { max_channels } else { 2 });
+    let sound_config = AudioConfig {
+        sample_rate: Some(sample_rate),
+        channels: Some(channels),
+        ..config.audio.clone()
+    };
+
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut sink = create_sink(output, &config.audio, StreamFormat { channels, sample_rate, loop_points: None })?;
+
+    let gap = vec![0.0; (config.gap_ms as u64 * sample_rate as u64 / 1000) as usize * channels as usize];
+    let mut position = 0u64;
+    let mut sprites = BTreeMap::new();
+    for (index, (name, path)) in sounds.into_iter().enumerate() {
+        if index > 0 {
+            sink.write(&gap)?;
+            position += (gap.len() / channels as usize) as u64;
+        }
+        let mut samples = 0;
+        let mut convert = || -> Result<()> {
+            let (mut decoder, mut pipeline, _) = open_pipeline(path, &sound_config)?;
+            while let Some(chunk) = decoder.next_chunk()? {
+                let chunk = pipeline.process(chunk)?;
+                samples += chunk.len();
+                sink.write(&chunk)?;
+            }
+            let rest = pipeline.finish()?;
+            samples += rest.len();
+            sink.write(&rest)
+        };
+        convert().with_context(|| format!("Failed to convert sound \"{}\"", name))?;
+        let frames = (samples / channels as usize) as u64;
+        sprites.insert(
+            name,
+            AudioSpriteEntry {
+                start: position as f64 / sample_rate as f64,
+                duration: frames as f64 / sample_rate as f64,
+            },
+        );
+        position += frames;
+    }
+    sink.finish()?;
+
+    let map = AudioSpriteMap {
+        audio: output
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        sprites,
+    };
+    let map_path = output.with_extension("json");
+    std::fs::write(&map_path, serde_json::to_string_pretty(&map)?)
+        .with_context(|| format!("Failed to write audio sprite map: {}", map_path.display()))?;
+
+    let stats = ProcessingStats {
+        original_size,
+        output_size: std::fs::metadata(output)?.len(),
+        processing_time_ms: start.elapsed().as_millis() as u64,
+        quality: None,
+    };
+    Ok(AudioSpriteResult { map, map_path, stats })
+}
@@ -0,0 +1,425 @@
+//! Chunked audio decoding and processing, so long files never sit in memory whole
+
+use anyhow::{Context, Result};
+use rubato::{
+    calculate_cutoff, Resampler as _, SincFixedIn, SincInterpolationParameters, SincInterpolationType,
+    WindowFunction,
+};
+use std::fs::File;
+use std::path::Path;
+use symphonia::core::codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL, CODEC_TYPE_OPUS};
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use super::audio_channels::{interleave, ChannelLayout, ChannelMix};
+use super::opus::OpusDecoder;
+use crate::cli::QualityPreset;
+
+/// Reads an audio file one decoded packet at a time
+pub struct AudioDecoder {
+    format: Box<dyn FormatReader>,
+    decoder: Codec,
+    track_id: u32,
+    pub channels: u32,
+    pub layout: ChannelLayout,
+    pub sample_rate: u32,
+    /// Length in sample frames, if the container records it
+    pub frames: Option<u64>,
+}
+
+enum Codec {
+    Symphonia(Box<dyn Decoder>),
+    Opus(OpusDecoder),
+}
+
+impl AudioDecoder {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open audio file: {}", path.display()))?;
+
+        let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+        let mut hint = Hint::new();
+        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+            hint.with_extension(ext);
+        }
+
+        let probed = symphonia::default::get_probe()
+            .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())
+            .with_context(|| format!("Failed to probe audio format: {}", path.display()))?;
+
+        let format = probed.format;
+
+        let track = format.tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| anyhow::anyhow!("No audio track found in file"))?;
+
+        let codec_params = &track.codec_params;
+        let channels = codec_params.channels.map(|c| c.count() as u32).unwrap_or(2);
+        let layout = codec_params
+            .channels
+            .map(ChannelLayout::from_channels)
+            .unwrap_or_else(|| ChannelLayout::default_for(channels));
+        let sample_rate = codec_params.sample_rate.unwrap_or(44100);
+        let mut frames = codec_params.n_frames;
+
+        let decoder = if codec_params.codec == CODEC_TYPE_OPUS {
+            // The Ogg reader counts the encoder delay and the last packet's padding in the
+            // stream length
+            let pre_skip = codec_params.delay.unwrap_or(0);
+            let padding = codec_params.padding.unwrap_or(0);
+            frames = frames.map(|frames| frames.saturating_sub(pre_skip as u64 + padding as u64));
+            Codec::Opus(OpusDecoder::new(channels, pre_skip, frames)?)
+        } else {
+            let decoder = symphonia::default::get_codecs()
+                .make(codec_params, &DecoderOptions::default())
+                .with_context(|| "Failed to create audio decoder")?;
+            Codec::Symphonia(decoder)
+        };
+
+        let track_id = track.id;
+        Ok(Self { format, decoder, track_id, channels, layout, sample_rate, frames })
+    }
+
+    /// Samples of the next packet, or `None` at the end of the stream
+    pub fn next_chunk(&mut self) -> Result<Option<Vec<f32>>> {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(symphonia::core::errors::Error::IoError(ref e))
+                    if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e.into()),
+            };
+
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            let samples = match &mut self.decoder {
+                Codec::Symphonia(decoder) => {
+                    let decoded = decoder.decode(&packet)?;
+                    let mut samples = Vec::new();
+                    interleave(&decoded, &mut samples);
+                    samples
+                }
+                Codec::Opus(decoder) => decoder.decode(&packet.data)?,
+            };
+            return Ok(Some(samples));
+        }
+    }
+}
+
+/// Peak level and audible range, measured in a first pass when normalizing or trimming
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct Analysis {
+    pub peak: f32,
+    /// First and last frame where a channel rises above the silence threshold
+    pub audible: Option<(u64, u64)>,
+    pub frames: u64,
+}
+
+impl Analysis {
+    pub fn add(&mut self, samples: &[f32], channels: usize, threshold: f32) {
+        for frame in samples.chunks(channels.max(1)) {
+            let level = frame.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+            self.peak = self.peak.max(level);
+            if level > threshold {
+                let first = self.audible.map_or(self.frames, |(first, _)| first);
+                self.audible = Some((first, self.frames));
+            }
+            self.frames += 1;
+        }
+    }
+}
+
+/// Sample rate conversion over a stream of interleaved chunks
+///
+/// Fast uses linear interpolation, which aliases when downsampling; the other presets use a
+/// windowed-sinc filter that gets longer and more precise with each step. Either way the
+/// output is `ceil(frames * ratio)` frames long.
+pub(super) enum Resampler {
+    Linear(LinearResampler),
+    Sinc(Box<SincResampler>),
+}
+
+impl Resampler {
+    pub fn new(channels: u32, from_rate: u32, to_rate: u32, quality: QualityPreset) -> Result<Self> {
+        let ratio = to_rate as f64 / from_rate as f64;
+        let channels = channels as usize;
+        Ok(match quality {
+            QualityPreset::Fast => Self::Linear(LinearResampler {
+                ratio,
+                channels,
+                buffer: Vec::new(),
+                base: 0,
+                input_frames: 0,
+                next: 0,
+            }),
+            _ => Self::Sinc(Box::new(SincResampler::new(channels, ratio, quality)?)),
+        })
+    }
+
+    pub fn process(&mut self, samples: &[f32]) -> Result<Vec<f32>> {
+        match self {
+            Self::Linear(linear) => Ok(linear.process(samples, false)),
+            Self::Sinc(sinc) => sinc.process(samples),
+        }
+    }
+
+    /// Output still held back at the end of the input
+    pub fn finish(&mut self) -> Result<Vec<f32>> {
+        match self {
+            Self::Linear(linear) => Ok(linear.process(&[], true)),
+            Self::Sinc(sinc) => sinc.finish(),
+        }
+    }
+}
+
+/// Linear interpolation between the two input frames around each output frame
+pub(super) struct LinearResampler {
+    ratio: f64,
+    channels: usize,
+    /// Input frames from `base` on that later output still needs
+    buffer: Vec<f32>,
+    base: u64,
+    input_frames: u64,
+    /// Next output frame
+    next: u64,
+}
+
+impl LinearResampler {
+    fn process(&mut self, samples: &[f32], last: bool) -> Vec<f32> {
+        let channels = self.channels;
+        self.buffer.extend_from_slice(samples);
+        self.input_frames += (samples.len() / channels) as u64;
+        let end = if last {
+            (self.input_frames as f64 * self.ratio).ceil() as u64
+        } else {
+            u64::MAX
+        };
+
+        let mut output = Vec::new();
+        while self.next < end {
+            let src_pos = self.next as f64 / self.ratio;
+            let src_frame = src_pos.floor() as u64;
+            // Wait for the frame after this one, unless the input is over
+            if !last && src_frame + 1 >= self.input_frames {
+                break;
+            }
+            let frac = (src_pos - src_frame as f64) as f32;
+            let next_frame = (src_frame + 1).min(self.input_frames.saturating_sub(1));
+            for ch in 0..channels {
+                let sample = |frame: u64| {
+                    let index = (frame - self.base) as usize * channels + ch;
+                    self.buffer.get(index).copied().unwrap_or(0.0)
+                };
+                let (s0, s1) = (sample(src_frame), sample(next_frame));
+                output.push(s0 + (s1 - s0) * frac);
+            }
+            self.next += 1;
+        }
+
+        // Drop input that no later output frame reads
+        let keep_from = ((self.next as f64 / self.ratio).floor() as u64).clamp(self.base, self.input_frames);
+        self.buffer.drain(..(keep_from - self.base) as usize * channels);
+        self.base = keep_from;
+        output
+    }
+}
+
+/// Band-limited resampling with a windowed-sinc filter
+pub(super) struct SincResampler {
+    resampler: SincFixedIn<f32>,
+    ratio: f64,
+    channels: usize,
+    /// Input not yet fed to the filter, one buffer per channel
+    pending: Vec<Vec<f32>>,
+    input_frames: u64,
+    /// Output frames still to drop before the first one that lines up with the input
+    skip: usize,
+    output_frames: u64,
+}
+
+impl SincResampler {
+    const CHUNK: usize = 4096;
+
+    fn new(channels: usize, ratio: f64, quality: QualityPreset) -> Result<Self> {
+        let (sinc_len, oversampling_factor, interpolation) = match quality {
+            QualityPreset::Fast | QualityPreset::Balanced => (64, 128, SincInterpolationType::Linear),
+            QualityPreset::High => (128, 256, SincInterpolationType::Cubic),
+            QualityPreset::Ultra => (256, 256, SincInterpolationType::Cubic),
+        };
+        let window = WindowFunction::BlackmanHarris2;
+        let parameters = SincInterpolationParameters {
+            sinc_len,
+            f_cutoff: calculate_cutoff(sinc_len, window),
+            oversampling_factor,
+            interpolation,
+            window,
+        };
+        let resampler = SincFixedIn::<f32>::new(ratio, 1.0, parameters, Self::CHUNK, channels)
+            .with_context(|| "Failed to create resampler")?;
+
+        // Rubato places output frame j at input time (j + 1) / ratio - 1 rather than j / ratio;
+        // lead-in input frames or skipped output frames line the two up to within half a frame
+        let (lead_in, skip) = if ratio < 1.0 {
+            ((1.0 / ratio - 1.0).round() as usize, 0)
+        } else {
+            (0, (ratio - 1.0).round() as usize)
+        };
+        Ok(Self {
+            resampler,
+            ratio,
+            channels,
+            pending: vec![vec![0.0; lead_in]; channels],
+            input_frames: 0,
+            skip,
+            output_frames: 0,
+        })
+    }
+
+    fn process(&mut self, samples: &[f32]) -> Result<Vec<f32>> {
+        for (ch, plane) in self.pending.iter_mut().enumerate() {
+            plane.extend(samples.iter().skip(ch).step_by(self.channels));
+        }
+        self.input_frames += (samples.len() / self.channels) as u64;
+
+        let mut output = Vec::new();
+        let mut pos = 0;
+        while self.pending[0].len() - pos >= self.resampler.input_frames_next() {
+            let needed = self.resampler.input_frames_next();
+            let chunk: Vec<&[f32]> = self.pending.iter().map(|plane| &plane[pos..pos + needed]).collect();
+            let out = self.resampler.process(&chunk, None).with_context(|| "Failed to resample audio")?;
+            pos += needed;
+            self.emit(out, u64::MAX, &mut output);
+        }
+        for plane in &mut self.pending {
+            plane.drain(..pos);
+        }
+        Ok(output)
+    }
+
+    fn finish(&mut self) -> Result<Vec<f32>> {
+        // The filter holds back the last few frames; keep feeding (zero-padded) chunks until
+        // the end of the input has come out
+        let total = (self.input_frames as f64 * self.ratio).ceil() as u64;
+        let mut output = Vec::new();
+        while self.output_frames < total {
+            let out = if self.pending[0].is_empty() {
+                self.resampler.process_partial(None::<&[&[f32]]>, None)
+            } else {
+                let needed = self.resampler.input_frames_next().min(self.pending[0].len());
+                let chunk: Vec<Vec<f32>> = self.pending.iter_mut().map(|plane| plane.drain(..needed).collect()).collect();
+                self.resampler.process_partial(Some(&chunk), None)
+            }
+            .with_context(|| "Failed to resample audio")?;
+            self.emit(out, total, &mut output);
+        }
+        Ok(output)
+    }
+
+    /// Interleave filter output after the skipped frames, up to `total` frames in all
+    fn emit(&mut self, planes: Vec<Vec<f32>>, total: u64, output: &mut Vec<f32>) {
+        let frames = planes[0].len();
+        let skip = self.skip.min(frames);
+        self.skip -= skip;
+        let take = ((frames - skip) as u64).min(total - self.output_frames) as usize;
+        for frame in skip..skip + take {
+            output.extend(planes.iter().map(|plane| plane[frame]));
+        }
+        self.output_frames += take as u64;
+    }
+}
+
+/// Linear gain ramps over the first and last frames of a clip
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct Fade {
+    pub fade_in: u64,
+    pub fade_out: u64,
+    /// Frames in the clip, for finding where the fade-out starts
+    pub length: u64,
+}
+
+impl Fade {
+    fn is_active(&self) -> bool {
+        self.fade_in > 0 || self.fade_out > 0
+    }
+
+    /// Gain of the frame at `position`; the first and last frames are silent
+    fn gain(&self, position: u64) -> f32 {
+        let mut gain = 1.0;
+        if position < self.fade_in {
+            gain *= position as f32 / self.fade_in as f32;
+        }
+        let remaining = self.length.saturating_sub(position + 1);
+        if remaining < self.fade_out {
+            gain *= remaining as f32 / self.fade_out as f32;
+        }
+        gain
+    }
+}
+
+/// Channel mixing, silence trimming, gain, fades and resampling applied chunk by chunk
+pub(super) struct AudioPipeline {
+    mix: ChannelMix,
+    /// Frames outside this range (after mixing) are dropped
+    range: (u64, u64),
+    position: u64,
+    gain: Option<f32>,
+    /// Counted from the start of `range`
+    fade: Fade,
+    resampler: Option<Resampler>,
+}
+
+impl AudioPipeline {
+    pub fn new(
+        mix: ChannelMix,
+        range: (u64, u64),
+        gain: Option<f32>,
+        fade: Fade,
+        resampler: Option<Resampler>,
+    ) -> Self {
+        Self { mix, range, position: 0, gain, fade, resampler }
+    }
+
+    pub fn process(&mut self, samples: Vec<f32>) -> Result<Vec<f32>> {
+        let mut samples = self.mix.apply(samples);
+        let channels = self.mix.channels() as usize;
+        let frames = (samples.len() / channels) as u64;
+        let start = self.range.0.clamp(self.position, self.position + frames) - self.position;
+        let end = self.range.1.clamp(self.position, self.position + frames) - self.position;
+        let kept = (self.position + start).saturating_sub(self.range.0);
+        self.position += frames;
+        if (start, end) != (0, frames) {
+            samples = samples[start as usize * channels..end as usize * channels].to_vec();
+        }
+
+        if let Some(gain) = self.gain {
+            for sample in &mut samples {
+                *sample *= gain;
+            }
+        }
+        if self.fade.is_active() {
+            for (index, frame) in samples.chunks_exact_mut(channels).enumerate() {
+                let gain = self.fade.gain(kept + index as u64);
+                if gain < 1.0 {
+                    frame.iter_mut().for_each(|sample| *sample *= gain);
+                }
+            }
+        }
+        match &mut self.resampler {
+            Some(resampler) => resampler.process(&samples),
+            None => Ok(samples),
+        }
+    }
+
+    pub fn finish(&mut self) -> Result<Vec<f32>> {
+        match &mut self.resampler {
+            Some(resampler) => resampler.finish(),
+            None => Ok(Vec::new()),
+        }
+    }
+}
@@ -1,15 +1,20 @@
 use anyhow::{Context, Result};
+#[cfg(feature = "native-textures")]
 use basis_universal::{
     BasisTextureFormat, ColorSpace, Compressor, CompressorParams,
     Transcoder, TranscoderTextureFormat,
     transcoding::TranscodeParameters,
 };
-use image::{DynamicImage, GenericImageView};
+use image::{DynamicImage, GenericImageView, RgbaImage};
 use std::path::Path;
 use std::time::Instant;
 
-use crate::cli::QualityPreset;
-use super::ProcessingStats;
+use crate::cli::{MipFilter, QualityPreset};
+#[cfg(feature = "native-textures")]
+use super::basis_to_ktx2;
+#[cfg(not(feature = "native-textures"))]
+use super::rgba8_to_ktx2;
+use super::{load_normal_map, mip_chain, normal_mip_chain, open_image, open_image_linear, preserve_alpha_coverage, ProcessingStats};
 
 /// Basis Universal compression mode
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -29,10 +34,24 @@ impl Default for BasisCompressionMode {
 /// Configuration for Basis Universal compression
 #[derive(Debug, Clone)]
 pub struct BasisConfig {
+    #[cfg_attr(not(feature = "native-textures"), allow(dead_code))]
     pub mode: BasisCompressionMode,
+    #[cfg_attr(not(feature = "native-textures"), allow(dead_code))]
     pub quality: QualityPreset,
     pub generate_mipmaps: bool,
     pub max_size: Option<u32>,
+    /// Zstandard level for KTX2 supercompression of UASTC data (None = uncompressed)
+    pub zstd_level: Option<i32>,
+    /// Encode as a linear normal map with X in RGB and Y in alpha
+    pub normal_map: bool,
+    /// Store texels as linear data (masks) rather than sRGB color
+    pub linear: bool,
+    /// Rescale normal map vectors to unit length after resizing and in every mip level
+    pub renormalize: bool,
+    /// Downsampling filter for the mip levels, which are generated here rather than by the encoder
+    pub mip_filter: MipFilter,
+    /// Alpha-test threshold (0-1) whose coverage every mip level keeps, for foliage and fences
+    pub alpha_coverage: Option<f32>,
 }
 
 impl Default for BasisConfig {
@@ -42,33 +61,140 @@ impl Default for BasisConfig {
             quality: QualityPreset::Balanced,
             generate_mipmaps: true,
             max_size: None,
+            zstd_level: None,
+            normal_map: false,
+            linear: false,
+            renormalize: false,
+            mip_filter: MipFilter::default(),
+            alpha_coverage: None,
         }
     }
 }
 
-/// Compress an image to Basis Universal format (.basis file)
-pub fn compress_to_basis(
+/// Compress an image to a KTX2 container with a Basis Universal payload
+pub fn compress_to_ktx2(
     input: &Path,
     output: &Path,
     config: &BasisConfig,
+) -> Result<ProcessingStats> {
+    write_texture(&[input], output, config, |layers| encode_ktx2(layers, config))
+}
+
+/// Compress same-sized images into the layers of one KTX2 texture array, in the given order
+pub fn compress_array_to_ktx2(
+    inputs: &[&Path],
+    output: &Path,
+    config: &BasisConfig,
+) -> Result<ProcessingStats> {
+    write_texture(inputs, output, config, |layers| encode_ktx2(layers, config))
+}
+
+/// KTX2 with a Basis Universal payload, one image per layer
+#[cfg(feature = "native-textures")]
+fn encode_ktx2(layers: Vec<Vec<RgbaImage>>, config: &BasisConfig) -> Result<Vec<u8>> {
+    let basis_data = basis_encode(&layers, config)?;
+    basis_to_ktx2(&basis_data, config.normal_map || config.linear, config.zstd_level)
+}
+
+/// Uncompressed RGBA8 KTX2, without the Basis Universal encoder
+#[cfg(not(feature = "native-textures"))]
+fn encode_ktx2(layers: Vec<Vec<RgbaImage>>, config: &BasisConfig) -> Result<Vec<u8>> {
+    let (width, height) = layers[0][0].dimensions();
+    // Each level holds that level of every layer, in layer order
+    let data: Vec<Vec<u8>> = (0..layers[0].len())
+        .map(|level| layers.iter().flat_map(|chain| chain[level].as_raw().iter().copied()).collect())
+        .collect();
+    rgba8_to_ktx2(&data, width, height, layers.len(), config.normal_map || config.linear, config.zstd_level)
+}
+
+/// Build the mip chain of every input and write what `encode` makes of them
+fn write_texture(
+    inputs: &[&Path],
+    output: &Path,
+    config: &BasisConfig,
+    encode: impl FnOnce(Vec<Vec<RgbaImage>>) -> Result<Vec<u8>>,
 ) -> Result<ProcessingStats> {
     let start = Instant::now();
-    let original_size = std::fs::metadata(input)
-        .with_context(|| format!("Failed to read input file: {}", input.display()))?
-        .len();
+    let mut original_size = 0;
+    let mut layers = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        original_size += std::fs::metadata(input)
+            .with_context(|| format!("Failed to read input file: {}", input.display()))?
+            .len();
+        layers.push(texture_levels(input, config)?);
+    }
 
-    // Load and optionally resize image
-    let img = load_and_resize_image(input, config.max_size)?;
-    let (width, height) = img.dimensions();
-    let rgba_data = img.to_rgba8();
+    // Layers of an array share one size
+    let Some(first) = layers.first().map(|chain| chain[0].dimensions()) else {
+        anyhow::bail!("No images to write to {}", output.display());
+    };
+    for (input, chain) in inputs.iter().zip(&layers) {
+        if chain[0].dimensions() != first {
+            anyhow::bail!(
+                "{} is {}x{}, but the first layer ({}) is {}x{}",
+                input.display(),
+                chain[0].width(),
+                chain[0].height(),
+                inputs[0].display(),
+                first.0,
+                first.1
+            );
+        }
+    }
 
     // Create output directory if needed
     if let Some(parent) = output.parent() {
         std::fs::create_dir_all(parent)?;
     }
 
+    let data = encode(layers)?;
+    std::fs::write(output, &data)
+        .with_context(|| format!("Failed to write texture: {}", output.display()))?;
+
+    let output_size = data.len() as u64;
+    let processing_time_ms = start.elapsed().as_millis() as u64;
+
+    Ok(ProcessingStats {
+        original_size,
+        output_size,
+        processing_time_ms,
+        quality: None,
+    })
+}
+
+/// Load and optionally resize an image, and build its mip chain
+fn texture_levels(input: &Path, config: &BasisConfig) -> Result<Vec<RgbaImage>> {
+    if config.normal_map {
+        let base = load_normal_map(input, config.max_size, config.renormalize)?;
+        let chain = if config.generate_mipmaps {
+            normal_mip_chain(base, config.renormalize, config.mip_filter)
+        } else {
+            vec![base]
+        };
+        return Ok(chain.into_iter().map(|mut level| {
+            swizzle_normal_xy(&mut level);
+            level
+        }).collect());
+    }
+
+    let base = load_and_resize_image(input, config.max_size, config.linear)?.to_rgba8();
+    if !config.generate_mipmaps {
+        return Ok(vec![base]);
+    }
+    let mut chain = mip_chain(&base, config.mip_filter, !config.linear);
+    if let Some(threshold) = config.alpha_coverage {
+        preserve_alpha_coverage(&mut chain, threshold);
+    }
+    Ok(chain)
+}
+
+/// Run the Basis encoder over the mip chain of each image, returning the .basis file
+///
+/// Several images make a .basis file the KTX2 writer turns into an array texture.
+#[cfg(feature = "native-textures")]
+fn basis_encode(layers: &[Vec<RgbaImage>], config: &BasisConfig) -> Result<Vec<u8>> {
     // Set up compressor
-    let mut compressor = Compressor::new(1); // 1 image
+    let mut compressor = Compressor::new(1);
     let mut params = CompressorParams::new();
 
     // Configure based on mode
@@ -80,17 +206,30 @@ pub fn compress_to_basis(
         BasisCompressionMode::Uastc => {
             params.set_basis_format(BasisTextureFormat::UASTC4x4);
             params.set_uastc_quality_level(quality_to_uastc_level(config.quality));
-            // Enable RDO (Rate Distortion Optimization) for better compression
-            params.set_rdo_uastc(Some(1.0));
+            // Enable RDO (Rate Distortion Optimization) for better compression;
+            // it trades error for size, which shows up as lighting noise on normal maps
+            params.set_rdo_uastc((!config.normal_map).then_some(1.0));
         }
     }
 
-    params.set_generate_mipmaps(config.generate_mipmaps);
-    params.set_color_space(ColorSpace::Srgb);
+    params.set_generate_mipmaps(false);
+    if config.normal_map {
+        params.tune_for_normal_maps();
+    } else {
+        params.set_color_space(if config.linear { ColorSpace::Linear } else { ColorSpace::Srgb });
+    }
 
-    // Set source image
-    let mut source_image = params.source_image_mut(0);
-    source_image.init(rgba_data.as_raw(), width, height, 4);
+    // Set source images (and precomputed mip levels)
+    for (index, levels) in layers.iter().enumerate() {
+        let index = index as u32;
+        let (width, height) = levels[0].dimensions();
+        let mut source_image = params.source_image_mut(index);
+        source_image.init(levels[0].as_raw(), width, height, 4);
+        for (level, image) in levels.iter().enumerate().skip(1) {
+            let mut mip = params.source_mipmap_image_mut(index, level as u32 - 1);
+            mip.init(image.as_raw(), image.width(), image.height(), 4);
+        }
+    }
 
     // Compress
     // SAFETY: We have properly initialized the params with valid image data
@@ -99,34 +238,11 @@ pub fn compress_to_basis(
         compressor.process().map_err(|e| anyhow::anyhow!("Basis compression failed: {:?}", e))?;
     }
 
-    // Get compressed data and write to file
-    let basis_data = compressor.basis_file();
-    std::fs::write(output, basis_data)
-        .with_context(|| format!("Failed to write basis file: {}", output.display()))?;
-
-    let output_size = std::fs::metadata(output)?.len();
-    let processing_time_ms = start.elapsed().as_millis() as u64;
-
-    Ok(ProcessingStats {
-        original_size,
-        output_size,
-        processing_time_ms,
-    })
-}
-
-/// Compress an image to KTX2 format with Basis Universal compression
-/// Note: Uses .basis format internally, which can be transcoded to any GPU format
-pub fn compress_to_ktx2(
-    input: &Path,
-    output: &Path,
-    config: &BasisConfig,
-) -> Result<ProcessingStats> {
-    // The basis-universal 0.3 crate outputs .basis format
-    // which is universally transcodable to GPU formats at load time
-    compress_to_basis(input, output, config)
+    Ok(compressor.basis_file().to_vec())
 }
 
 /// Transcode a Basis file to a specific GPU format
+#[cfg(feature = "native-textures")]
 #[allow(dead_code)]
 pub fn transcode_basis(
     input: &Path,
@@ -156,9 +272,16 @@ pub fn transcode_basis(
     Ok(transcoded)
 }
 
-fn load_and_resize_image(path: &Path, max_size: Option<u32>) -> Result<DynamicImage> {
-    let img = image::open(path)
-        .with_context(|| format!("Failed to open image: {}", path.display()))?;
+/// Move the normal's X into RGB and Y into alpha, the layout UASTC keeps as two separate channels
+fn swizzle_normal_xy(img: &mut RgbaImage) {
+    for pixel in img.pixels_mut() {
+        let [x, y, _, _] = pixel.0;
+        pixel.0 = [x, x, x, y];
+    }
+}
+
+fn load_and_resize_image(path: &Path, max_size: Option<u32>, linear: bool) -> Result<DynamicImage> {
+    let img = if linear { open_image_linear(path)? } else { open_image(path)? };
 
     if let Some(max) = max_size {
         let (width, height) = img.dimensions();
@@ -173,6 +296,7 @@ fn load_and_resize_image(path: &Path, max_size: Option<u32>) -> Result<DynamicIm
     Ok(img)
 }
 
+#[cfg(feature = "native-textures")]
 fn quality_to_etc1s_level(quality: QualityPreset) -> u32 {
     match quality {
         QualityPreset::Fast => 64,
@@ -182,6 +306,7 @@ fn quality_to_etc1s_level(quality: QualityPreset) -> u32 {
     }
 }
 
+#[cfg(feature = "native-textures")]
 fn quality_to_uastc_level(quality: QualityPreset) -> u32 {
     match quality {
         QualityPreset::Fast => 0,
@@ -192,6 +317,7 @@ fn quality_to_uastc_level(quality: QualityPreset) -> u32 {
 }
 
 /// Get information about supported transcoding formats
+#[cfg(feature = "native-textures")]
 #[allow(dead_code)]
 pub fn get_supported_formats() -> Vec<(&'static str, TranscoderTextureFormat)> {
     vec![
@@ -0,0 +1,434 @@
+//! Built-in BC5, BC6H and BC7 encoders for builds without the native ISPC compressor
+//!
+//! Each uses a single block mode (BC7 mode 6, BC6H mode 11), fitting endpoints along the
+//! principal axis of the block and refining them by least squares. They are lower quality
+//! than the ISPC kernels but portable to any target.
+
+use half::f16;
+use image::{Rgba32FImage, RgbaImage};
+use rayon::prelude::*;
+
+use crate::cli::QualityPreset;
+
+/// Interpolation weights of 4-bit indices (BC6H and BC7)
+const WEIGHTS4: [i32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+/// Largest finite half-float value
+const HALF_MAX: f32 = 65504.0;
+
+/// Encode RGBA as BC7 mode 6 blocks, one row of blocks per task
+pub fn encode_bc7(img: &RgbaImage, quality: QualityPreset, opaque: bool) -> Vec<u8> {
+    let iterations = refine_iterations(quality);
+    encode_blocks(img.width(), img.height(), |x, y| img.get_pixel(x, y).0.map(|c| c as f32), |texels| {
+        bc7_mode6(texels, iterations, opaque)
+    })
+}
+
+/// Encode X and Y as BC5 blocks (two BC4 blocks), one row of blocks per task
+pub fn encode_bc5(img: &RgbaImage) -> Vec<u8> {
+    encode_blocks(img.width(), img.height(), |x, y| img.get_pixel(x, y).0.map(|c| c as f32), |texels| {
+        let mut block = [0u8; 16];
+        block[..8].copy_from_slice(&bc4(&texels.map(|t| t[0])));
+        block[8..].copy_from_slice(&bc4(&texels.map(|t| t[1])));
+        block
+    })
+}
+
+/// Encode BC6H (unsigned float) mode 11 blocks, one row of blocks per task
+pub fn encode_bc6h(img: &Rgba32FImage, quality: QualityPreset) -> Vec<u8> {
+    let iterations = refine_iterations(quality);
+    encode_blocks(
+        img.width(),
+        img.height(),
+        |x, y| {
+            // Work on half-float bit patterns, which is what BC6H interpolates
+            let p = img.get_pixel(x, y).0;
+            let half = |v: f32| {
+                let v = if v.is_nan() { 0.0 } else { v.clamp(0.0, HALF_MAX) };
+                f16::from_f32(v).to_bits() as f32
+            };
+            [half(p[0]), half(p[1]), half(p[2]), 0.0]
+        },
+        |texels| bc6h_mode11(texels, iterations),
+    )
+}
+
+fn refine_iterations(quality: QualityPreset) -> usize {
+    match quality {
+        QualityPreset::Fast => 0,
+        QualityPreset::Balanced => 1,
+        QualityPreset::High => 2,
+        QualityPreset::Ultra => 4,
+    }
+}
+
+/// Gather 4x4 blocks (edge texels repeated) and encode them row by row in parallel
+fn encode_blocks(
+    width: u32,
+    height: u32,
+    texel: impl Fn(u32, u32) -> [f32; 4] + Sync,
+    encode: impl Fn(&[[f32; 4]; 16]) -> [u8; 16] + Sync,
+) -> Vec<u8> {
+    (0..height.div_ceil(4))
+        .into_par_iter()
+        .flat_map_iter(|by| {
+            let blocks: Vec<[u8; 16]> = (0..width.div_ceil(4))
+                .map(|bx| {
+                    let texels = std::array::from_fn(|i| {
+                        let x = (bx * 4 + i as u32 % 4).min(width - 1);
+                        let y = (by * 4 + i as u32 / 4).min(height - 1);
+                        texel(x, y)
+                    });
+                    encode(&texels)
+                })
+                .collect();
+            blocks.into_iter().flatten()
+        })
+        .collect()
+}
+
+/// Mode 6: one subset, 7-bit RGBA endpoints with a shared low bit each, 4-bit indices
+fn bc7_mode6(texels: &[[f32; 4]; 16], iterations: usize, opaque: bool) -> [u8; 16] {
+    let quantize = |e: [f32; 4]| {
+        // Pick the shared low bit that lands closest to the endpoint; opaque alpha needs a 1
+        (opaque as u32..2)
+            .map(|p| {
+                let q = e.map(|v| ((v - p as f32) / 2.0).round().clamp(0.0, 127.0) as u32);
+                let value = q.map(|c| ((c << 1) | p) as f32);
+                let error: f32 = (0..4).map(|c| (value[c] - e[c]).powi(2)).sum();
+                (error, q, p, value)
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, q, p, value)| (q, p, value))
+            .unwrap()
+    };
+    let palette = |a: [f32; 4], b: [f32; 4]| -> [[f32; 4]; 16] {
+        std::array::from_fn(|i| {
+            let w = WEIGHTS4[i];
+            std::array::from_fn(|c| (((64 - w) * a[c] as i32 + w * b[c] as i32 + 32) >> 6) as f32)
+        })
+    };
+
+    let (mut best, mut best_error) = (None, f32::INFINITY);
+    let mut endpoints = principal_endpoints(texels, 4, [255.0; 4]);
+    for _ in 0..=iterations {
+        let (q0, p0, v0) = quantize(endpoints.0);
+        let (q1, p1, v1) = quantize(endpoints.1);
+        let (indices, error) = nearest_indices(texels, &palette(v0, v1), 4);
+        if error < best_error {
+            best_error = error;
+            best = Some((q0, p0, q1, p1, indices));
+        }
+        match least_squares_endpoints(texels, &indices, 4, [255.0; 4]) {
+            Some(refit) => endpoints = refit,
+            None => break,
+        }
+    }
+    let (mut q0, mut p0, mut q1, mut p1, mut indices) = best.unwrap();
+
+    // The first index is stored without its high bit, so it must be below 8
+    if indices[0] >= 8 {
+        std::mem::swap(&mut q0, &mut q1);
+        std::mem::swap(&mut p0, &mut p1);
+        indices = indices.map(|i| 15 - i);
+    }
+
+    let mut bits = BitWriter::default();
+    bits.write(1 << 6, 7);
+    for c in 0..4 {
+        bits.write(q0[c], 7);
+        bits.write(q1[c], 7);
+    }
+    bits.write(p0, 1);
+    bits.write(p1, 1);
+    bits.write(indices[0] as u32, 3);
+    for &index in &indices[1..] {
+        bits.write(index as u32, 4);
+    }
+    bits.finish()
+}
+
+/// Mode 11: one region, 10-bit endpoints without deltas, 4-bit indices
+fn bc6h_mode11(texels: &[[f32; 4]; 16], iterations: usize) -> [u8; 16] {
+    // Endpoints and texels are in the decoder's 16-bit interpolation space, which the
+    // final half-float bits are 31/64 of
+    let texels = texels.map(|t| [t[0] * 64.0 / 31.0, t[1] * 64.0 / 31.0, t[2] * 64.0 / 31.0, 0.0]);
+    let unquantize = |e: u32| match e {
+        0 => 0,
+        1023 => 0xFFFF,
+        _ => ((e << 16) + 0x8000) >> 10,
+    };
+    let quantize = |e: [f32; 4]| {
+        let q: [u32; 3] = std::array::from_fn(|c| ((e[c] - 32.0) / 64.0).round().clamp(0.0, 1023.0) as u32);
+        (q, [unquantize(q[0]) as f32, unquantize(q[1]) as f32, unquantize(q[2]) as f32, 0.0])
+    };
+    let palette = |a: [f32; 4], b: [f32; 4]| -> [[f32; 4]; 16] {
+        std::array::from_fn(|i| {
+            let w = WEIGHTS4[i];
+            let mut value = [0.0; 4];
+            for c in 0..3 {
+                value[c] = (((64 - w) * a[c] as i32 + w * b[c] as i32 + 32) >> 6) as f32;
+            }
+            value
+        })
+    };
+
+    let (mut best, mut best_error) = (None, f32::INFINITY);
+    let mut endpoints = principal_endpoints(&texels, 3, [65535.0; 4]);
+    for _ in 0..=iterations {
+        let (q0, v0) = quantize(endpoints.0);
+        let (q1, v1) = quantize(endpoints.1);
+        let (indices, error) = nearest_indices(&texels, &palette(v0, v1), 3);
+        if error < best_error {
+            best_error = error;
+            best = Some((q0, q1, indices));
+        }
+        match least_squares_endpoints(&texels, &indices, 3, [65535.0; 4]) {
+            Some(refit) => endpoints = refit,
+            None => break,
+        }
+    }
+    let (mut q0, mut q1, mut indices) = best.unwrap();
+    if indices[0] >= 8 {
+        std::mem::swap(&mut q0, &mut q1);
+        indices = indices.map(|i| 15 - i);
+    }
+
+    let mut bits = BitWriter::default();
+    bits.write(0x03, 5);
+    for q in [q0, q1] {
+        for c in q {
+            bits.write(c, 10);
+        }
+    }
+    bits.write(indices[0] as u32, 3);
+    for &index in &indices[1..] {
+        bits.write(index as u32, 4);
+    }
+    bits.finish()
+}
+
+/// BC4: two 8-bit endpoints (first the larger, for 8 interpolated values) and 3-bit indices
+fn bc4(values: &[f32; 16]) -> [u8; 8] {
+    let high = values.iter().fold(0.0f32, |a, &b| a.max(b)).round() as u8;
+    let low = values.iter().fold(255.0f32, |a, &b| a.min(b)).round() as u8;
+    let mut block = [high, low, 0, 0, 0, 0, 0, 0];
+    if high == low {
+        return block;
+    }
+
+    // Index 0 and 1 are the endpoints, 2-7 step from the first towards the second
+    let palette: [f32; 8] = std::array::from_fn(|i| match i {
+        0 => high as f32,
+        1 => low as f32,
+        _ => ((8 - i) as f32 * high as f32 + (i - 1) as f32 * low as f32) / 7.0,
+    });
+    let mut bits = 0u64;
+    for (i, &v) in values.iter().enumerate() {
+        let index = (0..8).min_by(|&a, &b| (palette[a] - v).abs().total_cmp(&(palette[b] - v).abs())).unwrap();
+        bits |= (index as u64) << (3 * i);
+    }
+    block[2..].copy_from_slice(&bits.to_le_bytes()[..6]);
+    block
+}
+
+/// Endpoints at the extremes of the texels' projection on their principal axis
+fn principal_endpoints(texels: &[[f32; 4]; 16], channels: usize, max: [f32; 4]) -> ([f32; 4], [f32; 4]) {
+    let mut mean = [0.0f32; 4];
+    for t in texels {
+        for c in 0..channels {
+            mean[c] += t[c] / 16.0;
+        }
+    }
+    let mut covariance = [[0.0f32; 4]; 4];
+    for t in texels {
+        for i in 0..channels {
+            for j in 0..channels {
+                covariance[i][j] += (t[i] - mean[i]) * (t[j] - mean[j]);
+            }
+        }
+    }
+
+    // Power iteration from the largest-variance channel
+    let start = (0..channels).max_by(|&a, &b| covariance[a][a].total_cmp(&covariance[b][b])).unwrap();
+    let mut axis = [0.0f32; 4];
+    axis[start] = 1.0;
+    for _ in 0..8 {
+        let mut next = [0.0f32; 4];
+        for i in 0..channels {
+            for j in 0..channels {
+                next[i] += covariance[i][j] * axis[j];
+            }
+        }
+        let length = next.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if length < 1e-12 {
+            break;
+        }
+        axis = next.map(|v| v / length);
+    }
+
+    let project = |t: &[f32; 4]| (0..channels).map(|c| (t[c] - mean[c]) * axis[c]).sum::<f32>();
+    let (low, high) = texels
+        .iter()
+        .map(project)
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)));
+    let endpoint = |s: f32| std::array::from_fn(|c| (mean[c] + s * axis[c]).clamp(0.0, max[c]));
+    (endpoint(low), endpoint(high))
+}
+
+/// Index of the closest palette entry for each texel, and the summed squared error
+fn nearest_indices(texels: &[[f32; 4]; 16], palette: &[[f32; 4]; 16], channels: usize) -> ([u8; 16], f32) {
+    let mut indices = [0u8; 16];
+    let mut total = 0.0;
+    for (index, t) in indices.iter_mut().zip(texels) {
+        let (best, error) = palette
+            .iter()
+            .map(|p| (0..channels).map(|c| (p[c] - t[c]).powi(2)).sum::<f32>())
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap();
+        *index = best as u8;
+        total += error;
+    }
+    (indices, total)
+}
+
+/// Endpoints that best reproduce the texels for fixed indices (None when all weights match)
+fn least_squares_endpoints(
+    texels: &[[f32; 4]; 16],
+    indices: &[u8; 16],
+    channels: usize,
+    max: [f32; 4],
+) -> Option<([f32; 4], [f32; 4])> {
+    let (mut aa, mut ab, mut bb) = (0.0f32, 0.0f32, 0.0f32);
+    let mut at = [0.0f32; 4];
+    let mut bt = [0.0f32; 4];
+    for (t, &index) in texels.iter().zip(indices) {
+        let w = WEIGHTS4[index as usize] as f32 / 64.0;
+        let (a, b) = (1.0 - w, w);
+        aa += a * a;
+        ab += a * b;
+        bb += b * b;
+        for c in 0..channels {
+            at[c] += a * t[c];
+            bt[c] += b * t[c];
+        }
+    }
+    let det = aa * bb - ab * ab;
+    if det.abs() < 1e-6 {
+        return None;
+    }
+    let e0 = std::array::from_fn(|c| ((at[c] * bb - bt[c] * ab) / det).clamp(0.0, max[c]));
+    let e1 = std::array::from_fn(|c| ((bt[c] * aa - at[c] * ab) / det).clamp(0.0, max[c]));
+    Some((e0, e1))
+}
+
+/// Packs fields into a 128-bit block, least significant bit first
+#[derive(Default)]
+struct BitWriter {
+    value: u128,
+    position: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, bits: u32) {
+        self.value |= ((value & ((1 << bits) - 1)) as u128) << self.position;
+        self.position += bits;
+    }
+
+    fn finish(self) -> [u8; 16] {
+        debug_assert_eq!(self.position, 128);
+        self.value.to_le_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Smooth color gradient with a diagonal alpha ramp
+    fn gradient(size: u32) -> RgbaImage {
+        RgbaImage::from_fn(size, size, |x, y| {
+            let (u, v) = (x * 255 / (size - 1), y * 255 / (size - 1));
+            image::Rgba([u as u8, v as u8, (255 - u) as u8, ((u + v) / 2) as u8])
+        })
+    }
+
+    /// Decode `blocks` of a `width` x `height` image, with `decode_block` writing one block
+    /// of `channels` values per texel at the given row pitch
+    fn decode<T: Copy + Default>(
+        blocks: &[u8],
+        width: u32,
+        height: u32,
+        channels: usize,
+        decode_block: impl Fn(&[u8], &mut [T], usize),
+    ) -> Vec<T> {
+        let (width, height) = (width as usize, height as usize);
+        let blocks_x = width.div_ceil(4);
+        let pitch = blocks_x * 4 * channels;
+        let mut padded = vec![T::default(); pitch * height.div_ceil(4) * 4];
+        for (i, block) in blocks.chunks_exact(16).enumerate() {
+            let (bx, by) = (i % blocks_x, i / blocks_x);
+            decode_block(block, &mut padded[by * 4 * pitch + bx * 4 * channels..], pitch);
+        }
+        (0..height)
+            .flat_map(|y| padded[y * pitch..y * pitch + width * channels].to_vec())
+            .collect()
+    }
+
+    fn psnr(a: &[u8], b: &[u8]) -> f64 {
+        let squared: f64 = a.iter().zip(b).map(|(&a, &b)| (a as f64 - b as f64).powi(2)).sum();
+        let mse = squared / a.len() as f64;
+        10.0 * (255.0 * 255.0 / mse.max(f64::EPSILON)).log10()
+    }
+
+    #[test]
+    fn test_bc7_round_trip() {
+        // 62 isn't a multiple of 4, so edge blocks repeat texels
+        let img = gradient(62);
+        for quality in [QualityPreset::Fast, QualityPreset::Ultra] {
+            let blocks = encode_bc7(&img, quality, false);
+            assert_eq!(blocks.len(), 16 * 16 * 16);
+            let decoded = decode(&blocks, 62, 62, 4, bcdec_rs::bc7);
+            let psnr = psnr(img.as_raw(), &decoded);
+            assert!(psnr > 38.0, "{:?}: {:.1} dB", quality, psnr);
+        }
+    }
+
+    #[test]
+    fn test_bc7_opaque_keeps_alpha() {
+        let img = RgbaImage::from_fn(16, 16, |x, y| image::Rgba([(x * 16) as u8, (y * 16) as u8, 90, 255]));
+        let blocks = encode_bc7(&img, QualityPreset::Balanced, true);
+        let decoded = decode(&blocks, 16, 16, 4, bcdec_rs::bc7);
+        assert!(decoded.chunks(4).all(|texel| texel[3] == 255));
+    }
+
+    #[test]
+    fn test_bc5_round_trip() {
+        let img = gradient(32);
+        let blocks = encode_bc5(&img);
+        let decoded = decode(&blocks, 32, 32, 2, |block, out, pitch| bcdec_rs::bc5(block, out, pitch, false));
+        let xy: Vec<u8> = img.pixels().flat_map(|p| [p[0], p[1]]).collect();
+        let psnr = psnr(&xy, &decoded);
+        assert!(psnr > 45.0, "{:.1} dB", psnr);
+    }
+
+    #[test]
+    fn test_bc6h_round_trip() {
+        // HDR values from 0.01 to 100, above what an 8-bit format holds
+        let img = Rgba32FImage::from_fn(16, 16, |x, y| {
+            let u = 0.01 * 10f32.powf(4.0 * x as f32 / 15.0);
+            image::Rgba([u, u * (1.0 + y as f32 / 15.0), 0.5, 1.0])
+        });
+        let blocks = encode_bc6h(&img, QualityPreset::Balanced);
+        assert_eq!(blocks.len(), 4 * 4 * 16);
+        let decoded = decode(&blocks, 16, 16, 3, |block, out, pitch| bcdec_rs::bc6h_float(block, out, pitch, false));
+        for (p, d) in img.pixels().zip(decoded.chunks(3)) {
+            for c in 0..3 {
+                // Values within a block span up to a decade, so errors scale with the block
+                let error = (p[c] - d[c]).abs() / p[c].max(0.05);
+                assert!(error < 0.15, "{} decoded as {}", p[c], d[c]);
+            }
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use anyhow::{Context, Result};
+use image::{ColorType, DynamicImage, GenericImageView, ImageDecoder, ImageReader};
+use std::path::Path;
+use std::time::Instant;
+
+use crate::cli::MipFilter;
+
+use super::{is_psd_path, mip_chain_16, open_image_linear, unorm16_to_ktx2, ProcessingStats};
+
+/// 16-bit KTX2 configuration
+#[derive(Debug, Clone)]
+pub struct Unorm16Config {
+    pub max_size: Option<u32>,
+    pub generate_mipmaps: bool,
+    pub mip_filter: MipFilter,
+    /// Zstandard level for KTX2 supercompression (None = uncompressed)
+    pub zstd_level: Option<i32>,
+}
+
+/// Whether a path is a TIFF, which is decoded as a source but never shipped
+pub fn is_tiff_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("tif") || e.eq_ignore_ascii_case("tiff"))
+}
+
+/// Bits per channel of an image file, read without decoding the pixels
+///
+/// PSDs count as 8-bit, since their composite is read at 8 bits.
+pub fn image_bit_depth(path: &Path) -> Result<u8> {
+    if is_psd_path(path) {
+        return Ok(8);
+    }
+    let read = || -> image::ImageResult<ColorType> {
+        Ok(ImageReader::open(path)?.with_guessed_format()?.into_decoder()?.color_type())
+    };
+    let color = read().with_context(|| format!("Failed to open image: {}", path.display()))?;
+    Ok(color.bytes_per_pixel() / color.channel_count() * 8)
+}
+
+/// Whether an image holds 16 bits per channel
+pub fn is_16bit(img: &DynamicImage) -> bool {
+    matches!(img.color(), ColorType::L16 | ColorType::La16 | ColorType::Rgb16 | ColorType::Rgba16)
+}
+
+/// An image at 8 bits per channel, keeping its channels
+pub fn to_8bit(img: DynamicImage) -> DynamicImage {
+    match img.color() {
+        ColorType::L16 => DynamicImage::ImageLuma8(img.to_luma8()),
+        ColorType::La16 => DynamicImage::ImageLumaA8(img.to_luma_alpha8()),
+        ColorType::Rgb16 => DynamicImage::ImageRgb8(img.to_rgb8()),
+        ColorType::Rgba16 => DynamicImage::ImageRgba8(img.to_rgba8()),
+        _ => img,
+    }
+}
+
+/// Store an image as an uncompressed 16-bit linear KTX2 texture
+///
+/// Grayscale images without alpha (heightmaps) become R16, anything else RGBA16. Values are
+/// kept at full precision through resizing and mip generation.
+pub fn compress_to_unorm16_ktx2(input: &Path, output: &Path, config: &Unorm16Config) -> Result<ProcessingStats> {
+    let start = Instant::now();
+    let original_size = std::fs::metadata(input)
+        .with_context(|| format!("Failed to read input file: {}", input.display()))?
+        .len();
+
+    let mut img = open_image_linear(input)?;
+    if let Some(max) = config.max_size {
+        let (width, height) = img.dimensions();
+        if width > max || height > max {
+            let ratio = max as f32 / width.max(height) as f32;
+            let new_width = (width as f32 * ratio) as u32;
+            let new_height = (height as f32 * ratio) as u32;
+            img = img.resize(new_width, new_height, image::imageops::FilterType::Lanczos3);
+        }
+    }
+
+    let channels = if img.color().has_color() || img.color().has_alpha() { 4 } else { 1 };
+    let base = img.to_rgba16();
+    let chain = if config.generate_mipmaps { mip_chain_16(&base, config.mip_filter) } else { vec![base] };
+    let levels: Vec<Vec<u8>> = chain
+        .iter()
+        .map(|level| {
+            level
+                .pixels()
+                .flat_map(|p| p.0[..channels].to_vec())
+                .flat_map(|v| v.to_le_bytes())
+                .collect()
+        })
+        .collect();
+    let data = unorm16_to_ktx2(&levels, img.width(), img.height(), channels, config.zstd_level)?;
+
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(output, &data)
+        .with_context(|| format!("Failed to write texture: {}", output.display()))?;
+
+    Ok(ProcessingStats {
+        original_size,
+        output_size: data.len() as u64,
+        processing_time_ms: start.elapsed().as_millis() as u64,
+        quality: None,
+    })
+}
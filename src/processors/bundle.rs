@@ -0,0 +1,134 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use super::BuildManifest;
+use crate::config::BundleConfig;
+
+pub use crate::runtime::{Bundle, BundleEntry, BundleIndex, PrefetchGroup, BUNDLE_VERSION, ZSTD_COMPRESSION};
+
+/// Compressed data is only kept when it is at most this fraction of the original, so already
+/// compressed formats (PNG, Ogg) aren't stored behind a decompression step for nothing
+const ZSTD_MAX_RATIO: f64 = 0.95;
+
+/// Zstandard-compress `data`, or `None` when that doesn't save enough to be worth it
+pub fn zstd_compress(data: &[u8], level: i32) -> Result<Option<Vec<u8>>> {
+    let compressed = zstd::bulk::compress(data, level).context("Zstandard compression failed")?;
+    Ok((compressed.len() as f64 <= data.len() as f64 * ZSTD_MAX_RATIO).then_some(compressed))
+}
+
+/// Pack the manifest's outputs into one bundle file plus its index
+///
+/// Assets are grouped by the first of their tags listed in `config.prefetch`, groups are
+/// written in that order and untagged assets come last. Within a group assets are sorted by
+/// output path so the bundle is the same for the same build. Virtual textures contribute
+/// their tile index only; the tiles themselves stay loose for streaming. Entries sharing an
+/// output (`--dedup` aliases) share its bytes. With `zstd_level`, each asset is compressed on
+/// its own, so entries stay randomly accessible.
+pub fn write_bundle(
+    manifest: &BuildManifest,
+    output_dir: &Path,
+    tags: &HashMap<String, Vec<String>>,
+    config: &BundleConfig,
+    zstd_level: Option<i32>,
+) -> Result<BundleIndex> {
+    let alignment = config.alignment.max(1);
+
+    let group_of = |source: &str| {
+        let asset_tags = tags.get(source)?;
+        config.prefetch.iter().position(|tag| asset_tags.contains(tag))
+    };
+    let mut assets: Vec<_> = manifest
+        .assets
+        .iter()
+        .filter(|entry| output_dir.join(&entry.output).is_file())
+        .map(|entry| (group_of(&entry.source), entry))
+        .collect();
+    assets.sort_by(|(a_group, a), (b_group, b)| {
+        // None sorts after every group
+        let rank = |group: &Option<usize>| group.unwrap_or(usize::MAX);
+        rank(a_group).cmp(&rank(b_group)).then_with(|| a.output.cmp(&b.output))
+    });
+
+    let bundle_path = output_dir.join(&config.name);
+    if let Some(parent) = bundle_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = std::fs::File::create(&bundle_path)
+        .with_context(|| format!("Failed to create bundle: {}", bundle_path.display()))?;
+    let mut writer = BufWriter::new(file);
+
+    let mut offset = 0u64;
+    let mut entries = Vec::with_capacity(assets.len());
+    let mut groups: Vec<PrefetchGroup> = Vec::new();
+    let mut written: HashMap<&str, BundleEntry> = HashMap::new();
+    for (group, entry) in assets {
+        let group = group.map(|index| config.prefetch[index].clone());
+        if let Some(stored) = written.get(entry.output.as_str()) {
+            entries.push(BundleEntry {
+                source: entry.source.clone(),
+                group,
+                ..stored.clone()
+            });
+            continue;
+        }
+
+        let padding = (alignment - offset % alignment) % alignment;
+        writer.write_all(&vec![0; padding as usize])?;
+        offset += padding;
+
+        let path = output_dir.join(&entry.output);
+        let raw = std::fs::read(&path)
+            .with_context(|| format!("Failed to read output: {}", path.display()))?;
+        let compressed = match zstd_level {
+            Some(level) => zstd_compress(&raw, level)?,
+            None => None,
+        };
+        let data = compressed.as_deref().unwrap_or(&raw);
+        writer.write_all(data)?;
+
+        if let Some(name) = &group {
+            match groups.last_mut() {
+                Some(last) if &last.name == name => last.length = offset + data.len() as u64 - last.offset,
+                _ => groups.push(PrefetchGroup {
+                    name: name.clone(),
+                    offset,
+                    length: data.len() as u64,
+                }),
+            }
+        }
+
+        let stored = BundleEntry {
+            source: entry.source.clone(),
+            output: entry.output.clone(),
+            offset,
+            length: data.len() as u64,
+            compression: compressed.as_ref().map(|_| ZSTD_COMPRESSION.to_string()),
+            raw_length: compressed.as_ref().map(|_| raw.len() as u64),
+            hash: entry.hash.clone(),
+            group,
+        };
+        written.insert(&entry.output, stored.clone());
+        entries.push(stored);
+        offset += data.len() as u64;
+    }
+    writer.flush()?;
+
+    let index = BundleIndex {
+        version: BUNDLE_VERSION,
+        bundle: bundle_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        size: offset,
+        alignment,
+        groups,
+        entries,
+    };
+    let index_path = BundleIndex::index_path(&bundle_path);
+    std::fs::write(&index_path, serde_json::to_string_pretty(&index)?)
+        .with_context(|| format!("Failed to write bundle index: {}", index_path.display()))?;
+
+    Ok(index)
+}
@@ -13,6 +13,9 @@ pub struct CacheEntry {
     pub config_hash: u64,
     /// Path to the cached output file
     pub output_path: PathBuf,
+    /// Hash of the output file content; absent in caches written before it was recorded
+    #[serde(default)]
+    pub output_hash: Option<u64>,
     /// Original file modification time (Unix timestamp)
     pub mtime: u64,
     /// Processing timestamp
@@ -24,6 +27,9 @@ pub struct CacheEntry {
 pub struct BuildCache {
     /// Cache entries keyed by input file path
     pub entries: HashMap<PathBuf, CacheEntry>,
+    /// Hashes of virtual texture tiles keyed by tile path, so unchanged tiles aren't re-encoded
+    #[serde(default)]
+    pub tiles: HashMap<PathBuf, u64>,
     /// Cache version for invalidation on format changes
     pub version: u32,
 }
@@ -31,11 +37,22 @@ pub struct BuildCache {
 const CACHE_VERSION: u32 = 1;
 const CACHE_FILE_NAME: &str = "cache.json";
 
+/// State of a cache file on disk, as seen by `migrate`
+#[derive(Debug)]
+pub enum CacheUpgrade {
+    Missing,
+    /// Readable by this version; lists what saving it again fills in (empty when current)
+    Upgradable(BuildCache, Vec<String>),
+    /// Written in a version with no upgrade path; the next build starts a new cache
+    Unsupported(u32),
+}
+
 impl BuildCache {
     /// Create a new empty cache
     pub fn new() -> Self {
         Self {
             entries: HashMap::new(),
+            tiles: HashMap::new(),
             version: CACHE_VERSION,
         }
     }
@@ -63,6 +80,33 @@ impl BuildCache {
         Ok(cache)
     }
 
+    /// Inspect a cache file for `migrate` without discarding outdated versions
+    pub fn check_upgrade(cache_dir: &Path) -> Result<CacheUpgrade> {
+        let cache_file = cache_dir.join(CACHE_FILE_NAME);
+        if !cache_file.exists() {
+            return Ok(CacheUpgrade::Missing);
+        }
+
+        let content = std::fs::read_to_string(&cache_file)
+            .with_context(|| format!("Failed to read cache file: {}", cache_file.display()))?;
+        let value: serde_json::Value = serde_json::from_str(&content)
+            .with_context(|| "Failed to parse cache file")?;
+
+        let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+        if version != CACHE_VERSION {
+            return Ok(CacheUpgrade::Unsupported(version));
+        }
+
+        let mut changes = Vec::new();
+        if value.get("tiles").is_none() {
+            changes.push("add the virtual texture tile table".to_string());
+        }
+        let cache: BuildCache = serde_json::from_value(value)
+            .with_context(|| "Failed to parse cache file")?;
+
+        Ok(CacheUpgrade::Upgradable(cache, changes))
+    }
+
     /// Save cache to directory
     pub fn save(&self, cache_dir: &Path) -> Result<()> {
         std::fs::create_dir_all(cache_dir)?;
@@ -135,6 +179,7 @@ impl BuildCache {
                 input_hash,
                 config_hash,
                 output_path: output.to_path_buf(),
+                output_hash: hash_file(output).ok(),
                 mtime,
                 processed_at: now,
             },
@@ -146,6 +191,44 @@ impl BuildCache {
     /// Remove stale entries (inputs that no longer exist)
     pub fn cleanup(&mut self) {
         self.entries.retain(|path, _| path.exists());
+        self.tiles.retain(|path, _| path.exists());
+    }
+
+    /// Remove entries whose input or output is gone or whose output was modified, and tiles
+    /// that no longer exist
+    ///
+    /// Returns the number of entries and tiles removed.
+    pub fn prune(&mut self) -> (usize, usize) {
+        let (entries, tiles) = (self.entries.len(), self.tiles.len());
+        self.entries.retain(|path, e| path.exists() && e.output_path.exists() && !e.output_modified());
+        self.tiles.retain(|path, _| path.exists());
+        (entries - self.entries.len(), tiles - self.tiles.len())
+    }
+
+    /// Check every entry against its input and output, sorted by input path
+    ///
+    /// Inputs and outputs are hashed, so this reads every source and output file.
+    pub fn verify(&self) -> Vec<(PathBuf, CacheIssue)> {
+        let mut issues: Vec<(PathBuf, CacheIssue)> = self
+            .entries
+            .iter()
+            .filter_map(|(path, entry)| {
+                let issue = if !path.exists() {
+                    CacheIssue::MissingInput
+                } else if !entry.output_path.exists() {
+                    CacheIssue::MissingOutput
+                } else if entry.output_modified() {
+                    CacheIssue::OutputModified
+                } else if hash_file(path).ok() != Some(entry.input_hash) {
+                    CacheIssue::InputChanged
+                } else {
+                    return None;
+                };
+                Some((path.clone(), issue))
+            })
+            .collect();
+        issues.sort_by(|a, b| a.0.cmp(&b.0));
+        issues
     }
 
     /// Get cache statistics
@@ -154,17 +237,31 @@ impl BuildCache {
         let valid_entries = self.entries.values()
             .filter(|e| e.output_path.exists())
             .count();
+        let missing_inputs = self.entries.keys().filter(|path| !path.exists()).count();
+        let processed = self.entries.values().map(|e| e.processed_at);
 
         CacheStats {
             total_entries,
             valid_entries,
             stale_entries: total_entries - valid_entries,
+            missing_inputs,
+            tiles: self.tiles.len(),
+            oldest: processed.clone().min(),
+            newest: processed.max(),
         }
     }
 
     /// Clear all cache entries
     pub fn clear(&mut self) {
         self.entries.clear();
+        self.tiles.clear();
+    }
+}
+
+impl CacheEntry {
+    /// Whether the output no longer matches the recorded hash, e.g. after truncation or an edit
+    fn output_modified(&self) -> bool {
+        self.output_hash.is_some_and(|hash| hash_file(&self.output_path).ok() != Some(hash))
     }
 }
 
@@ -172,8 +269,40 @@ impl BuildCache {
 #[derive(Debug, Clone)]
 pub struct CacheStats {
     pub total_entries: usize,
+    /// Entries whose output exists
     pub valid_entries: usize,
+    /// Entries whose output is gone
     pub stale_entries: usize,
+    /// Entries whose input is gone
+    pub missing_inputs: usize,
+    pub tiles: usize,
+    /// Earliest and latest `processed_at` (Unix timestamps)
+    pub oldest: Option<u64>,
+    pub newest: Option<u64>,
+}
+
+/// Why a cache entry doesn't match the files on disk
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CacheIssue {
+    /// The source was deleted or moved
+    MissingInput,
+    /// The output was deleted; the next build recreates it
+    MissingOutput,
+    /// The output no longer matches what the build wrote
+    OutputModified,
+    /// The source changed since it was built; the next build rebuilds it
+    InputChanged,
+}
+
+impl std::fmt::Display for CacheIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CacheIssue::MissingInput => write!(f, "input missing"),
+            CacheIssue::MissingOutput => write!(f, "output missing"),
+            CacheIssue::OutputModified => write!(f, "output modified since build"),
+            CacheIssue::InputChanged => write!(f, "input changed since build"),
+        }
+    }
 }
 
 /// Hash a file's contents
@@ -219,6 +348,7 @@ mod tests {
                 input_hash: 12345,
                 config_hash: 67890,
                 output_path: PathBuf::from("output/test.png"),
+                output_hash: None,
                 mtime: 1000,
                 processed_at: 2000,
             },
@@ -230,4 +360,21 @@ mod tests {
         assert_eq!(loaded.entries.len(), 1);
         assert!(loaded.entries.contains_key(&PathBuf::from("test.png")));
     }
+
+    #[test]
+    fn test_verify_detects_modified_output() {
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path().join("test.png");
+        let output = temp_dir.path().join("out.png");
+        std::fs::write(&input, b"source").unwrap();
+        std::fs::write(&output, b"built output").unwrap();
+
+        let mut cache = BuildCache::new();
+        cache.update(&input, &output, 1).unwrap();
+        assert!(cache.verify().is_empty());
+
+        std::fs::write(&output, b"built").unwrap();
+        assert_eq!(cache.verify(), vec![(input, CacheIssue::OutputModified)]);
+        assert_eq!(cache.prune(), (1, 0));
+    }
 }
@@ -0,0 +1,164 @@
+use anyhow::{Context, Result};
+use image::{imageops::FilterType, DynamicImage, GenericImageView, ImageFormat, RgbaImage};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::{open_image_linear, process_image, ImageProcessorConfig, ProcessingStats};
+
+/// Channel of a source image read into a packed channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceChannel {
+    Red,
+    Green,
+    Blue,
+    Alpha,
+    /// Grayscale value of the whole image
+    Luma,
+}
+
+/// What fills one channel of a packed texture
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChannelSource {
+    Image { path: PathBuf, channel: SourceChannel },
+    /// The same value (0-255) for every texel
+    Constant(u8),
+}
+
+impl ChannelSource {
+    /// Parse `path`, `path:<r|g|b|a|l>` or a constant between 0 and 1
+    pub fn parse(spec: &str) -> Result<Self> {
+        if !Path::new(spec).exists() {
+            if let Ok(value) = spec.parse::<f32>() {
+                if !(0.0..=1.0).contains(&value) {
+                    anyhow::bail!("Constant channel value must be between 0 and 1: {}", spec);
+                }
+                return Ok(ChannelSource::Constant((value * 255.0).round() as u8));
+            }
+        }
+
+        let (path, channel) = match spec.rsplit_once(':') {
+            Some((path, suffix)) if !path.is_empty() && suffix.len() == 1 => {
+                let channel = match suffix.to_ascii_lowercase().as_str() {
+                    "r" => SourceChannel::Red,
+                    "g" => SourceChannel::Green,
+                    "b" => SourceChannel::Blue,
+                    "a" => SourceChannel::Alpha,
+                    "l" => SourceChannel::Luma,
+                    _ => anyhow::bail!("Unknown source channel '{}' in {} (use r, g, b, a or l)", suffix, spec),
+                };
+                (path, channel)
+            }
+            _ => (spec, SourceChannel::Luma),
+        };
+        Ok(ChannelSource::Image {
+            path: PathBuf::from(path),
+            channel,
+        })
+    }
+}
+
+/// Channel mapping for a packed texture, in RGBA order
+#[derive(Debug, Clone)]
+pub struct ChannelPackConfig {
+    /// Sources for red, green, blue and alpha; unset color channels are 0 and unset alpha is opaque
+    pub channels: [Option<ChannelSource>; 4],
+    /// Channels stored as 255 minus their source (e.g. gloss into roughness)
+    pub invert: [bool; 4],
+    /// How the packed texture is encoded
+    pub image: ImageProcessorConfig,
+}
+
+/// Merge grayscale inputs into one RGBA texture
+///
+/// Inputs smaller than the largest one are scaled up to its size, so a half-resolution
+/// occlusion map can be packed next to full-resolution roughness.
+pub fn pack_channels(config: &ChannelPackConfig) -> Result<RgbaImage> {
+    let mut images: HashMap<&Path, DynamicImage> = HashMap::new();
+    for source in config.channels.iter().flatten() {
+        if let ChannelSource::Image { path, .. } = source {
+            if !images.contains_key(path.as_path()) {
+                images.insert(path.as_path(), open_image_linear(path)?);
+            }
+        }
+    }
+    if images.is_empty() {
+        anyhow::bail!("At least one channel must come from an image");
+    }
+
+    let width = images.values().map(|img| img.width()).max().unwrap_or(1);
+    let height = images.values().map(|img| img.height()).max().unwrap_or(1);
+    let images: HashMap<&Path, RgbaImage> = images
+        .into_iter()
+        .map(|(path, img)| {
+            let img = if img.dimensions() == (width, height) {
+                img
+            } else {
+                img.resize_exact(width, height, FilterType::Lanczos3)
+            };
+            (path, img.to_rgba8())
+        })
+        .collect();
+
+    let mut packed = RgbaImage::new(width, height);
+    for (c, source) in config.channels.iter().enumerate() {
+        let default = if c == 3 { 255 } else { 0 };
+        for (x, y, pixel) in packed.enumerate_pixels_mut() {
+            let value = match source {
+                None => default,
+                Some(ChannelSource::Constant(value)) => *value,
+                Some(ChannelSource::Image { path, channel }) => {
+                    let p = images[path.as_path()].get_pixel(x, y);
+                    match channel {
+                        SourceChannel::Red => p[0],
+                        SourceChannel::Green => p[1],
+                        SourceChannel::Blue => p[2],
+                        SourceChannel::Alpha => p[3],
+                        // Rec. 709 weights, the same as `to_luma8`
+                        SourceChannel::Luma => {
+                            (0.2126 * p[0] as f32 + 0.7152 * p[1] as f32 + 0.0722 * p[2] as f32)
+                                .round() as u8
+                        }
+                    }
+                }
+            };
+            pixel[c] = if config.invert[c] { 255 - value } else { value };
+        }
+    }
+
+    Ok(packed)
+}
+
+/// Pack the channels and write them through the regular image pipeline
+pub fn write_packed_texture(output: &Path, config: &ChannelPackConfig) -> Result<ProcessingStats> {
+    let packed = pack_channels(config)?;
+
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let temp = output.with_extension("pack.tmp.png");
+    DynamicImage::ImageRgba8(packed)
+        .save_with_format(&temp, ImageFormat::Png)
+        .with_context(|| format!("Failed to save packed texture: {}", temp.display()))?;
+    let result = process_image(&temp, output, &config.image);
+    let _ = std::fs::remove_file(&temp);
+    let mut stats = result?;
+
+    // Report the combined size of the inputs rather than the temporary PNG
+    let mut inputs: Vec<&Path> = config
+        .channels
+        .iter()
+        .flatten()
+        .filter_map(|source| match source {
+            ChannelSource::Image { path, .. } => Some(path.as_path()),
+            ChannelSource::Constant(_) => None,
+        })
+        .collect();
+    inputs.sort();
+    inputs.dedup();
+    stats.original_size = inputs
+        .iter()
+        .map(|path| std::fs::metadata(path).map(|m| m.len()).unwrap_or(0))
+        .sum();
+
+    Ok(stats)
+}
@@ -0,0 +1,553 @@
+use anyhow::{Context, Result};
+use roxmltree::{Document, Node};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Instant;
+
+use super::{process_model, GlbMaterial, GlbMesh, GlbNode, GlbPrimitive, GlbScene, ModelConfig, ProcessingStats};
+
+const IDENTITY: [f32; 16] = [
+    1.0, 0.0, 0.0, 0.0, //
+    0.0, 1.0, 0.0, 0.0, //
+    0.0, 0.0, 1.0, 0.0, //
+    0.0, 0.0, 0.0, 1.0,
+];
+
+/// Convert a COLLADA file to glTF, then optimize it like any other model
+pub fn process_collada(
+    input: &Path,
+    output: &Path,
+    config: &ModelConfig,
+) -> Result<ProcessingStats> {
+    let start = Instant::now();
+    let original_size = std::fs::metadata(input)
+        .with_context(|| format!("Failed to read input file: {}", input.display()))?
+        .len();
+
+    let temp = output.with_extension("dae.tmp.glb");
+    load_collada(input)?.write(&temp)?;
+    let result = process_model(&temp, output, config);
+    let _ = std::fs::remove_file(&temp);
+
+    let stats = result?;
+    Ok(ProcessingStats {
+        original_size,
+        output_size: stats.output_size,
+        processing_time_ms: start.elapsed().as_millis() as u64,
+        quality: None,
+    })
+}
+
+/// Load the geometry, node hierarchy and material colors of a COLLADA file
+pub fn load_collada(input: &Path) -> Result<GlbScene> {
+    let text = std::fs::read_to_string(input)
+        .with_context(|| format!("Failed to read COLLADA file: {}", input.display()))?;
+    let document = Document::parse(&text)
+        .with_context(|| format!("Failed to parse COLLADA file: {}", input.display()))?;
+
+    let mut importer = Importer::new(&document);
+    importer.import()?;
+    Ok(importer.scene)
+}
+
+struct Importer<'a, 'input> {
+    ids: HashMap<&'a str, Node<'a, 'input>>,
+    root: Node<'a, 'input>,
+    scene: GlbScene,
+    /// Converted meshes keyed by geometry id and material bindings
+    meshes: HashMap<String, usize>,
+    /// Material id to glTF material index
+    materials: HashMap<String, usize>,
+}
+
+impl<'a, 'input> Importer<'a, 'input> {
+    fn new(document: &'a Document<'input>) -> Self {
+        let ids = document
+            .descendants()
+            .filter_map(|n| Some((n.attribute("id")?, n)))
+            .collect();
+        Self {
+            ids,
+            root: document.root_element(),
+            scene: GlbScene::default(),
+            meshes: HashMap::new(),
+            materials: HashMap::new(),
+        }
+    }
+
+    fn import(&mut self) -> Result<()> {
+        if !self.root.has_tag_name("COLLADA") {
+            anyhow::bail!("Not a COLLADA document: <{}>", self.root.tag_name().name());
+        }
+
+        let visual_scene = child(self.root, "scene")
+            .and_then(|s| child(s, "instance_visual_scene"))
+            .and_then(|i| self.lookup(i.attribute("url")?))
+            .or_else(|| {
+                child(self.root, "library_visual_scenes").and_then(|l| child(l, "visual_scene"))
+            });
+
+        match visual_scene {
+            Some(visual_scene) => {
+                for node in children(visual_scene, "node") {
+                    let index = self.node(node)?;
+                    self.scene.roots.push(index);
+                }
+            }
+            None => {
+                // Geometry libraries without a scene get one node per geometry
+                let geometries: Vec<Node> = child(self.root, "library_geometries")
+                    .map(|l| children(l, "geometry").collect())
+                    .unwrap_or_default();
+                for geometry in geometries {
+                    let mesh = self.mesh(geometry, &HashMap::new())?;
+                    self.scene.nodes.push(GlbNode {
+                        name: geometry.attribute("name").map(str::to_string),
+                        mesh,
+                        ..Default::default()
+                    });
+                    self.scene.roots.push(self.scene.nodes.len() - 1);
+                }
+            }
+        }
+
+        // glTF is Y-up in meters
+        let correction = self.axis_correction();
+        if correction != IDENTITY {
+            for &root in &self.scene.roots {
+                let node = &mut self.scene.nodes[root];
+                node.matrix = Some(multiply(&correction, &node.matrix.unwrap_or(IDENTITY)));
+            }
+        }
+
+        if self.scene.meshes.is_empty() {
+            tracing::warn!("COLLADA file contains no triangle meshes");
+        }
+        Ok(())
+    }
+
+    fn lookup(&self, url: &str) -> Option<Node<'a, 'input>> {
+        self.ids.get(url.trim_start_matches('#')).copied()
+    }
+
+    fn axis_correction(&self) -> [f32; 16] {
+        let asset = child(self.root, "asset");
+        let meter = asset
+            .and_then(|a| child(a, "unit"))
+            .and_then(|u| u.attribute("meter"))
+            .and_then(|m| m.parse::<f32>().ok())
+            .unwrap_or(1.0);
+        let up_axis = asset
+            .and_then(|a| child(a, "up_axis"))
+            .and_then(|u| u.text())
+            .map(str::trim)
+            .unwrap_or("Y_UP");
+
+        let s = meter;
+        match up_axis {
+            // (x, y, z) -> (x, z, -y)
+            "Z_UP" => [
+                s, 0.0, 0.0, 0.0, //
+                0.0, 0.0, -s, 0.0, //
+                0.0, s, 0.0, 0.0, //
+                0.0, 0.0, 0.0, 1.0,
+            ],
+            // (x, y, z) -> (-y, x, z)
+            "X_UP" => [
+                0.0, s, 0.0, 0.0, //
+                -s, 0.0, 0.0, 0.0, //
+                0.0, 0.0, s, 0.0, //
+                0.0, 0.0, 0.0, 1.0,
+            ],
+            _ => [
+                s, 0.0, 0.0, 0.0, //
+                0.0, s, 0.0, 0.0, //
+                0.0, 0.0, s, 0.0, //
+                0.0, 0.0, 0.0, 1.0,
+            ],
+        }
+    }
+
+    /// Convert a scene node and its children, returning the glTF node index
+    fn node(&mut self, node: Node<'a, 'input>) -> Result<usize> {
+        let mut matrix = IDENTITY;
+        for transform in node.children().filter(|n| n.is_element()) {
+            let values = floats(transform);
+            let local = match (transform.tag_name().name(), values.as_slice()) {
+                ("matrix", &[..]) if values.len() == 16 => transpose(&values),
+                ("translate", &[x, y, z]) => translation(x, y, z),
+                ("rotate", &[x, y, z, angle]) => rotation(x, y, z, angle.to_radians()),
+                ("scale", &[x, y, z]) => scaling(x, y, z),
+                _ => continue,
+            };
+            matrix = multiply(&matrix, &local);
+        }
+
+        let mut gltf_node = GlbNode {
+            name: node.attribute("name").or(node.attribute("id")).map(str::to_string),
+            matrix: (matrix != IDENTITY).then_some(matrix),
+            ..Default::default()
+        };
+
+        // glTF nodes hold one mesh, so extra instances become child nodes
+        let mut instances = Vec::new();
+        for instance in children(node, "instance_geometry") {
+            let Some(geometry) = instance.attribute("url").and_then(|u| self.lookup(u)) else {
+                tracing::warn!("Skipping unresolved geometry instance in COLLADA node");
+                continue;
+            };
+            if let Some(mesh) = self.mesh(geometry, &material_bindings(instance))? {
+                instances.push(mesh);
+            }
+        }
+        if children(node, "instance_controller").next().is_some() {
+            tracing::warn!("Skipping skinned COLLADA controller (not supported)");
+        }
+
+        let mut child_nodes = Vec::new();
+        for (i, mesh) in instances.into_iter().enumerate() {
+            if i == 0 {
+                gltf_node.mesh = Some(mesh);
+            } else {
+                self.scene.nodes.push(GlbNode { mesh: Some(mesh), ..Default::default() });
+                child_nodes.push(self.scene.nodes.len() - 1);
+            }
+        }
+        for child_node in children(node, "node") {
+            child_nodes.push(self.node(child_node)?);
+        }
+        gltf_node.children = child_nodes;
+
+        self.scene.nodes.push(gltf_node);
+        Ok(self.scene.nodes.len() - 1)
+    }
+
+    /// Convert a geometry with its material bindings, reusing earlier conversions
+    fn mesh(
+        &mut self,
+        geometry: Node<'a, 'input>,
+        bindings: &HashMap<String, String>,
+    ) -> Result<Option<usize>> {
+        let Some(mesh) = child(geometry, "mesh") else {
+            tracing::warn!("Skipping non-mesh COLLADA geometry");
+            return Ok(None);
+        };
+
+        let mut sorted: Vec<_> = bindings.iter().collect();
+        sorted.sort();
+        let key = format!("{}{:?}", geometry.attribute("id").unwrap_or_default(), sorted);
+        if let Some(&index) = self.meshes.get(&key) {
+            return Ok(Some(index));
+        }
+
+        let mut primitives = Vec::new();
+        for element in mesh.children().filter(|n| n.is_element()) {
+            let name = element.tag_name().name();
+            let polygons: Vec<Vec<u32>> = match name {
+                "triangles" => child(element, "p")
+                    .map(|p| vec![indices(p)])
+                    .unwrap_or_default(),
+                "polylist" => {
+                    let counts = child(element, "vcount").map(indices).unwrap_or_default();
+                    let stride = input_stride(element);
+                    let p = child(element, "p").map(indices).unwrap_or_default();
+                    let mut offset = 0;
+                    counts
+                        .iter()
+                        .map(|&count| {
+                            let len = count as usize * stride;
+                            let polygon = p.get(offset..offset + len).unwrap_or_default().to_vec();
+                            offset += len;
+                            polygon
+                        })
+                        .collect()
+                }
+                "polygons" => children(element, "p").map(indices).collect(),
+                "lines" | "linestrips" | "tristrips" | "trifans" => {
+                    tracing::warn!("Skipping unsupported COLLADA primitive <{}>", name);
+                    continue;
+                }
+                _ => continue,
+            };
+
+            let is_triangle_list = name == "triangles";
+            let material = match element.attribute("material") {
+                Some(symbol) => {
+                    let target = bindings.get(symbol).map(String::as_str).unwrap_or(symbol);
+                    self.material(target)
+                }
+                None => None,
+            };
+            let primitive = self.primitive(element, &polygons, is_triangle_list, material)?;
+            if !primitive.indices.is_empty() {
+                primitives.push(primitive);
+            }
+        }
+
+        if primitives.is_empty() {
+            return Ok(None);
+        }
+
+        self.scene.meshes.push(GlbMesh {
+            name: geometry
+                .attribute("name")
+                .or(geometry.attribute("id"))
+                .map(str::to_string),
+            primitives,
+        });
+        let index = self.scene.meshes.len() - 1;
+        self.meshes.insert(key, index);
+        Ok(Some(index))
+    }
+
+    /// Build an indexed triangle list from COLLADA's per-input index tuples
+    fn primitive(
+        &self,
+        element: Node<'a, 'input>,
+        polygons: &[Vec<u32>],
+        is_triangle_list: bool,
+        material: Option<usize>,
+    ) -> Result<GlbPrimitive> {
+        let stride = input_stride(element);
+        let mut position = None;
+        let mut normal = None;
+        let mut tex_coord: Option<(u32, usize, Vec<f32>, usize)> = None;
+
+        for input in children(element, "input") {
+            let offset: usize = input.attribute("offset").and_then(|o| o.parse().ok()).unwrap_or(0);
+            let Some(source) = input.attribute("source").and_then(|s| self.lookup(s)) else {
+                continue;
+            };
+            match input.attribute("semantic") {
+                Some("VERTEX") => {
+                    for vertex_input in children(source, "input") {
+                        let Some(vertex_source) =
+                            vertex_input.attribute("source").and_then(|s| self.lookup(s))
+                        else {
+                            continue;
+                        };
+                        match vertex_input.attribute("semantic") {
+                            Some("POSITION") => position = Some((offset, source_data(vertex_source)?)),
+                            Some("NORMAL") if normal.is_none() => {
+                                normal = Some((offset, source_data(vertex_source)?))
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Some("NORMAL") => normal = Some((offset, source_data(source)?)),
+                Some("TEXCOORD") => {
+                    let set: u32 = input.attribute("set").and_then(|s| s.parse().ok()).unwrap_or(0);
+                    if tex_coord.as_ref().is_none_or(|(current, ..)| set < *current) {
+                        let (data, data_stride) = source_data(source)?;
+                        tex_coord = Some((set, offset, data, data_stride));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let Some((position_offset, (positions, position_stride))) = position else {
+            anyhow::bail!("COLLADA primitive has no POSITION input");
+        };
+
+        let mut primitive = GlbPrimitive {
+            material,
+            ..Default::default()
+        };
+        let mut vertices: HashMap<[u32; 3], u32> = HashMap::new();
+
+        for polygon in polygons {
+            let corners: Vec<u32> = polygon
+                .chunks_exact(stride)
+                .map(|tuple| {
+                    let index = |offset: usize| tuple.get(offset).copied().unwrap_or(0);
+                    let key = [
+                        index(position_offset),
+                        normal.as_ref().map_or(0, |(offset, _)| index(*offset)),
+                        tex_coord.as_ref().map_or(0, |(_, offset, ..)| index(*offset)),
+                    ];
+                    *vertices.entry(key).or_insert_with(|| {
+                        let read = |data: &[f32], stride: usize, i: u32, n: usize| -> Vec<f32> {
+                            let start = i as usize * stride;
+                            (0..n).map(|c| data.get(start + c).copied().unwrap_or(0.0)).collect()
+                        };
+                        let p = read(&positions, position_stride, key[0], 3);
+                        primitive.positions.push([p[0], p[1], p[2]]);
+                        if let Some((_, (data, stride))) = &normal {
+                            let n = read(data, *stride, key[1], 3);
+                            primitive.normals.push([n[0], n[1], n[2]]);
+                        }
+                        if let Some((_, _, data, stride)) = &tex_coord {
+                            let t = read(data, *stride, key[2], 2);
+                            // COLLADA UV origin is bottom-left, glTF is top-left
+                            primitive.tex_coords.push([t[0], 1.0 - t[1]]);
+                        }
+                        (primitive.positions.len() - 1) as u32
+                    })
+                })
+                .collect();
+
+            if is_triangle_list {
+                primitive.indices.extend(corners.chunks_exact(3).flatten());
+            } else {
+                // Fan-triangulate polygons
+                for i in 1..corners.len().saturating_sub(1) {
+                    primitive.indices.extend([corners[0], corners[i], corners[i + 1]]);
+                }
+            }
+        }
+
+        Ok(primitive)
+    }
+
+    /// Resolve a material id to a glTF material, taking the diffuse color from its effect
+    fn material(&mut self, id: &str) -> Option<usize> {
+        let id = id.trim_start_matches('#');
+        if let Some(&index) = self.materials.get(id) {
+            return Some(index);
+        }
+
+        let material = self.lookup(id).filter(|n| n.has_tag_name("material"))?;
+        let base_color = child(material, "instance_effect")
+            .and_then(|i| self.lookup(i.attribute("url")?))
+            .and_then(|effect| {
+                effect
+                    .descendants()
+                    .find(|n| n.has_tag_name("diffuse"))
+                    .and_then(|d| child(d, "color"))
+            })
+            .map(floats)
+            .filter(|c| c.len() >= 3)
+            .map(|c| [c[0], c[1], c[2], c.get(3).copied().unwrap_or(1.0)])
+            .unwrap_or([1.0; 4]);
+
+        self.scene.materials.push(GlbMaterial {
+            name: material.attribute("name").or(Some(id)).map(str::to_string),
+            base_color,
+        });
+        let index = self.scene.materials.len() - 1;
+        self.materials.insert(id.to_string(), index);
+        Some(index)
+    }
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children().filter(move |n| n.has_tag_name(name))
+}
+
+/// Map of material symbols to material ids from an instance's <bind_material>
+fn material_bindings(instance: Node) -> HashMap<String, String> {
+    instance
+        .descendants()
+        .filter(|n| n.has_tag_name("instance_material"))
+        .filter_map(|n| {
+            Some((
+                n.attribute("symbol")?.to_string(),
+                n.attribute("target")?.trim_start_matches('#').to_string(),
+            ))
+        })
+        .collect()
+}
+
+/// Number of indices per vertex in a primitive's <p> list
+fn input_stride(element: Node) -> usize {
+    children(element, "input")
+        .filter_map(|i| i.attribute("offset")?.parse::<usize>().ok())
+        .max()
+        .map_or(1, |max| max + 1)
+}
+
+/// Read a <source> float array and its accessor stride
+fn source_data(source: Node) -> Result<(Vec<f32>, usize)> {
+    let array = child(source, "float_array").with_context(|| {
+        format!(
+            "COLLADA source has no float array: {}",
+            source.attribute("id").unwrap_or_default()
+        )
+    })?;
+    let stride = child(source, "technique_common")
+        .and_then(|t| child(t, "accessor"))
+        .and_then(|a| a.attribute("stride"))
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(1);
+    Ok((floats(array), stride))
+}
+
+fn floats(node: Node) -> Vec<f32> {
+    node.text()
+        .unwrap_or_default()
+        .split_ascii_whitespace()
+        .filter_map(|v| v.parse().ok())
+        .collect()
+}
+
+fn indices(node: Node) -> Vec<u32> {
+    node.text()
+        .unwrap_or_default()
+        .split_ascii_whitespace()
+        .filter_map(|v| v.parse().ok())
+        .collect()
+}
+
+/// Column-major product `a * b`
+fn multiply(a: &[f32; 16], b: &[f32; 16]) -> [f32; 16] {
+    let mut out = [0.0; 16];
+    for col in 0..4 {
+        for row in 0..4 {
+            out[col * 4 + row] = (0..4).map(|k| a[k * 4 + row] * b[col * 4 + k]).sum();
+        }
+    }
+    out
+}
+
+/// COLLADA matrices are row-major
+fn transpose(values: &[f32]) -> [f32; 16] {
+    let mut out = [0.0; 16];
+    for row in 0..4 {
+        for col in 0..4 {
+            out[col * 4 + row] = values[row * 4 + col];
+        }
+    }
+    out
+}
+
+fn translation(x: f32, y: f32, z: f32) -> [f32; 16] {
+    let mut m = IDENTITY;
+    m[12] = x;
+    m[13] = y;
+    m[14] = z;
+    m
+}
+
+fn scaling(x: f32, y: f32, z: f32) -> [f32; 16] {
+    let mut m = IDENTITY;
+    m[0] = x;
+    m[5] = y;
+    m[10] = z;
+    m
+}
+
+/// Rotation about an arbitrary axis (Rodrigues)
+fn rotation(x: f32, y: f32, z: f32, angle: f32) -> [f32; 16] {
+    let len = (x * x + y * y + z * z).sqrt();
+    if len == 0.0 {
+        return IDENTITY;
+    }
+    let (x, y, z) = (x / len, y / len, z / len);
+    let (s, c) = angle.sin_cos();
+    let t = 1.0 - c;
+    [
+        t * x * x + c, t * x * y + s * z, t * x * z - s * y, 0.0, //
+        t * x * y - s * z, t * y * y + c, t * y * z + s * x, 0.0, //
+        t * x * z + s * y, t * y * z - s * x, t * z * z + c, 0.0, //
+        0.0, 0.0, 0.0, 1.0,
+    ]
+}
@@ -0,0 +1,388 @@
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::path::Path;
+
+use super::thumbnail::{mul, transform_point, Mat4, IDENTITY};
+use super::{GlbMesh, GlbPrimitive, GlbScene};
+use crate::cli::CollisionShape;
+
+/// Most physics engines cap convex hulls at 255 vertices
+const MAX_HULL_VERTICES: usize = 255;
+
+/// Simplification error allowed for collision meshes, relative to the mesh extent
+const SIMPLIFY_TARGET_ERROR: f32 = 0.05;
+
+/// Summary of a generated collision mesh
+#[derive(Debug, Clone, Copy)]
+pub struct CollisionStats {
+    pub source_triangles: usize,
+    pub vertices: usize,
+    pub triangles: usize,
+    pub output_size: u64,
+}
+
+/// Generate a physics mesh for a glTF/GLB model and write it as a GLB
+///
+/// All meshes are flattened into world space. `Hull` produces a single convex hull,
+/// `Simplified` a heavily decimated copy keeping `ratio` of the triangles.
+pub fn generate_collision_mesh(
+    input: &Path,
+    output: &Path,
+    shape: CollisionShape,
+    ratio: f32,
+) -> Result<CollisionStats> {
+    let (document, buffers, _images) = gltf::import(input)
+        .with_context(|| format!("Failed to import glTF: {}", input.display()))?;
+
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
+    let scene = document.default_scene().or_else(|| document.scenes().next());
+    match scene {
+        Some(scene) => {
+            for node in scene.nodes() {
+                collect_node(&node, IDENTITY, &buffers, &mut positions, &mut indices);
+            }
+        }
+        None => {
+            for mesh in document.meshes() {
+                collect_mesh(&mesh, IDENTITY, &buffers, &mut positions, &mut indices);
+            }
+        }
+    }
+
+    if indices.is_empty() {
+        anyhow::bail!("Model has no triangles for a collision mesh: {}", input.display());
+    }
+    let source_triangles = indices.len() / 3;
+
+    let (positions, indices) = match shape {
+        CollisionShape::Hull => convex_hull(&positions, MAX_HULL_VERTICES)
+            .with_context(|| format!("Failed to build convex hull: {}", input.display()))?,
+        CollisionShape::Simplified => simplify(&positions, &indices, ratio),
+    };
+
+    let name = output
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned());
+    let stats = CollisionStats {
+        source_triangles,
+        vertices: positions.len(),
+        triangles: indices.len() / 3,
+        output_size: 0,
+    };
+    let scene = GlbScene::from_mesh(GlbMesh {
+        name,
+        primitives: vec![GlbPrimitive {
+            positions,
+            indices,
+            ..Default::default()
+        }],
+    });
+    let output_size = scene.write(output)?;
+
+    Ok(CollisionStats { output_size, ..stats })
+}
+
+fn collect_node(
+    node: &gltf::Node,
+    parent: Mat4,
+    buffers: &[gltf::buffer::Data],
+    positions: &mut Vec<[f32; 3]>,
+    indices: &mut Vec<u32>,
+) {
+    let transform = mul(&parent, &node.transform().matrix());
+
+    if let Some(mesh) = node.mesh() {
+        collect_mesh(&mesh, transform, buffers, positions, indices);
+    }
+    for child in node.children() {
+        collect_node(&child, transform, buffers, positions, indices);
+    }
+}
+
+fn collect_mesh(
+    mesh: &gltf::Mesh,
+    transform: Mat4,
+    buffers: &[gltf::buffer::Data],
+    positions: &mut Vec<[f32; 3]>,
+    indices: &mut Vec<u32>,
+) {
+    for primitive in mesh.primitives() {
+        if primitive.mode() != gltf::mesh::Mode::Triangles {
+            continue;
+        }
+
+        let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+        let Some(iter) = reader.read_positions() else { continue };
+        let base = positions.len() as u32;
+        positions.extend(iter.map(|p| transform_point(&transform, p)));
+        let count = positions.len() as u32 - base;
+
+        match reader.read_indices() {
+            Some(iter) => indices.extend(iter.into_u32().map(|i| base + i)),
+            None => indices.extend(base..base + count),
+        }
+    }
+}
+
+/// Weld by position and decimate, keeping roughly `ratio` of the triangles
+fn simplify(positions: &[[f32; 3]], indices: &[u32], ratio: f32) -> (Vec<[f32; 3]>, Vec<u32>) {
+    // Seams in the render mesh (UVs, normals) don't matter for collision
+    let (vertex_count, remap) = meshopt::generate_vertex_remap(positions, Some(indices));
+    let mut welded = vec![[0.0f32; 3]; vertex_count];
+    for (i, &target) in remap.iter().enumerate() {
+        welded[target as usize] = positions[i];
+    }
+    let indices: Vec<u32> = indices.iter().map(|&i| remap[i as usize]).collect();
+
+    let target = ((indices.len() as f32 * ratio) as usize / 3 * 3).max(3);
+    let mut simplified = meshopt::simplify_decoder(
+        &indices,
+        &welded,
+        target,
+        SIMPLIFY_TARGET_ERROR,
+        meshopt::SimplifyOptions::None,
+        None,
+    );
+    // Topology-preserving simplification stalls on disconnected parts
+    if simplified.len() > target * 2 {
+        simplified = meshopt::simplify_sloppy_decoder(
+            &indices,
+            &welded,
+            target,
+            SIMPLIFY_TARGET_ERROR,
+            None,
+        );
+    }
+
+    // Drop vertices no longer referenced
+    let compacted = meshopt::optimize_vertex_fetch(&mut simplified, &welded);
+    (compacted, simplified)
+}
+
+/// Incremental convex hull, adding the farthest outside point first
+///
+/// Stops once `max_vertices` points are on the hull, which yields a slightly
+/// smaller hull that still contains the most prominent features.
+fn convex_hull(positions: &[[f32; 3]], max_vertices: usize) -> Result<(Vec<[f32; 3]>, Vec<u32>)> {
+    let points: Vec<[f64; 3]> = positions
+        .iter()
+        .map(|p| [p[0] as f64, p[1] as f64, p[2] as f64])
+        .collect();
+
+    let (min, max) = points.iter().fold(
+        ([f64::MAX; 3], [f64::MIN; 3]),
+        |(mut min, mut max), p| {
+            for i in 0..3 {
+                min[i] = min[i].min(p[i]);
+                max[i] = max[i].max(p[i]);
+            }
+            (min, max)
+        },
+    );
+    let extent = length(sub(max, min));
+    let eps = extent * 1e-9;
+
+    let simplex = initial_simplex(&points, eps)
+        .context("Model is flat or degenerate")?;
+    let centroid = simplex
+        .iter()
+        .fold([0.0; 3], |acc, &i| add(acc, scale(points[i], 0.25)));
+
+    let mut faces: Vec<Face> = Vec::new();
+    for [a, b, c] in [[0, 1, 2], [0, 1, 3], [0, 2, 3], [1, 2, 3]] {
+        faces.push(Face::new(&points, [simplex[a], simplex[b], simplex[c]], centroid));
+    }
+
+    // Every point starts outside the first face it is in front of
+    for (i, point) in points.iter().enumerate() {
+        if simplex.contains(&i) {
+            continue;
+        }
+        if let Some(face) = faces.iter_mut().find(|f| f.distance(*point) > eps) {
+            face.outside.push(i);
+        }
+    }
+
+    let mut hull_vertices = 4;
+    while hull_vertices < max_vertices {
+        let Some(face_index) = faces.iter().position(|f| f.alive && !f.outside.is_empty()) else {
+            break;
+        };
+
+        // Farthest outside point of this face
+        let face = &faces[face_index];
+        let apex = *face
+            .outside
+            .iter()
+            .max_by(|&&a, &&b| face.distance(points[a]).total_cmp(&face.distance(points[b])))
+            .unwrap();
+        let apex_point = points[apex];
+
+        // Faces the apex can see are replaced by a fan to their horizon
+        let visible: Vec<usize> = (0..faces.len())
+            .filter(|&i| faces[i].alive && faces[i].distance(apex_point) > eps)
+            .collect();
+        let edges: Vec<(usize, usize)> = visible
+            .iter()
+            .flat_map(|&i| faces[i].edges())
+            .collect();
+        let edge_set: HashSet<(usize, usize)> = edges.iter().copied().collect();
+
+        let mut orphans = Vec::new();
+        for &i in &visible {
+            faces[i].alive = false;
+            orphans.append(&mut faces[i].outside);
+        }
+
+        let first_new = faces.len();
+        for &(a, b) in &edges {
+            if !edge_set.contains(&(b, a)) {
+                faces.push(Face::new(&points, [a, b, apex], centroid));
+            }
+        }
+
+        for i in orphans {
+            if i == apex {
+                continue;
+            }
+            if let Some(face) = faces[first_new..].iter_mut().find(|f| f.distance(points[i]) > eps) {
+                face.outside.push(i);
+            }
+        }
+        hull_vertices += 1;
+    }
+
+    // Compact to the vertices the hull actually uses
+    let mut remap = vec![u32::MAX; points.len()];
+    let mut hull_positions = Vec::new();
+    let mut indices = Vec::new();
+    for face in faces.iter().filter(|f| f.alive) {
+        for &v in &face.vertices {
+            if remap[v] == u32::MAX {
+                remap[v] = hull_positions.len() as u32;
+                hull_positions.push(positions[v]);
+            }
+            indices.push(remap[v]);
+        }
+    }
+
+    Ok((hull_positions, indices))
+}
+
+struct Face {
+    /// Counter-clockwise when seen from outside
+    vertices: [usize; 3],
+    normal: [f64; 3],
+    offset: f64,
+    outside: Vec<usize>,
+    alive: bool,
+}
+
+impl Face {
+    /// Build a face, flipping it so `inside` is behind the plane
+    fn new(points: &[[f64; 3]], vertices: [usize; 3], inside: [f64; 3]) -> Self {
+        let [a, b, c] = vertices.map(|v| points[v]);
+        let mut normal = normalize(cross(sub(b, a), sub(c, a)));
+        let mut vertices = vertices;
+        if dot(normal, sub(inside, a)) > 0.0 {
+            normal = scale(normal, -1.0);
+            vertices.swap(1, 2);
+        }
+        Self {
+            vertices,
+            normal,
+            offset: dot(normal, a),
+            outside: Vec::new(),
+            alive: true,
+        }
+    }
+
+    fn distance(&self, p: [f64; 3]) -> f64 {
+        dot(self.normal, p) - self.offset
+    }
+
+    fn edges(&self) -> [(usize, usize); 3] {
+        let [a, b, c] = self.vertices;
+        [(a, b), (b, c), (c, a)]
+    }
+}
+
+/// Four non-coplanar extreme points to start the hull from
+fn initial_simplex(points: &[[f64; 3]], eps: f64) -> Option<[usize; 4]> {
+    // Farthest pair among the axis extremes
+    let mut extremes = Vec::new();
+    for axis in 0..3 {
+        let by_axis = |a: &&[f64; 3], b: &&[f64; 3]| a[axis].total_cmp(&b[axis]);
+        let min = points.iter().enumerate().min_by(|a, b| by_axis(&a.1, &b.1))?.0;
+        let max = points.iter().enumerate().max_by(|a, b| by_axis(&a.1, &b.1))?.0;
+        extremes.extend([min, max]);
+    }
+    let (mut a, mut b, mut best) = (0, 0, 0.0);
+    for &i in &extremes {
+        for &j in &extremes {
+            let d = length(sub(points[i], points[j]));
+            if d > best {
+                (a, b, best) = (i, j, d);
+            }
+        }
+    }
+    if best <= eps {
+        return None;
+    }
+
+    // Farthest from the line a-b
+    let dir = normalize(sub(points[b], points[a]));
+    let line_distance = |p: [f64; 3]| length(cross(dir, sub(p, points[a])));
+    let c = (0..points.len()).max_by(|&i, &j| line_distance(points[i]).total_cmp(&line_distance(points[j])))?;
+    if line_distance(points[c]) <= eps {
+        return None;
+    }
+
+    // Farthest from the plane a-b-c
+    let normal = normalize(cross(sub(points[b], points[a]), sub(points[c], points[a])));
+    let plane_distance = |p: [f64; 3]| dot(normal, sub(p, points[a])).abs();
+    let d = (0..points.len()).max_by(|&i, &j| plane_distance(points[i]).total_cmp(&plane_distance(points[j])))?;
+    if plane_distance(points[d]) <= eps {
+        return None;
+    }
+
+    Some([a, b, c, d])
+}
+
+fn add(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: [f64; 3], s: f64) -> [f64; 3] {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn length(a: [f64; 3]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+fn normalize(a: [f64; 3]) -> [f64; 3] {
+    let len = length(a);
+    if len > 0.0 {
+        scale(a, 1.0 / len)
+    } else {
+        a
+    }
+}
@@ -0,0 +1,210 @@
+use image::{imageops::FilterType, DynamicImage, GenericImageView, Rgba, RgbaImage};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Images are analyzed at most this large; nearest sampling keeps the original colors
+const ANALYSIS_MAX_SIZE: u32 = 512;
+/// Share of opaque pixels a color needs before it counts as one of the image's colors
+const MIN_COLOR_COVERAGE: f32 = 0.005;
+const MAX_COLORS: usize = 24;
+/// Touching pixel pairs needed before two colors count as adjacent
+const MIN_EDGE_PIXELS: u32 = 8;
+
+/// Common color-vision deficiencies (full dichromacy)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorDeficiency {
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+impl ColorDeficiency {
+    pub const ALL: [ColorDeficiency; 3] = [Self::Protanopia, Self::Deuteranopia, Self::Tritanopia];
+
+    /// Machado et al. (2009) simulation matrix at full severity, for linear RGB
+    fn matrix(&self) -> [[f32; 3]; 3] {
+        match self {
+            Self::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            Self::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            Self::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+        }
+    }
+
+    /// Simulate how a color appears with this deficiency
+    pub fn simulate_color(&self, rgb: [u8; 3]) -> [u8; 3] {
+        let linear = rgb.map(srgb_to_linear);
+        let m = self.matrix();
+        let mut out = [0u8; 3];
+        for (i, row) in m.iter().enumerate() {
+            let v = row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2];
+            out[i] = linear_to_srgb(v);
+        }
+        out
+    }
+}
+
+impl fmt::Display for ColorDeficiency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Protanopia => write!(f, "protanopia"),
+            Self::Deuteranopia => write!(f, "deuteranopia"),
+            Self::Tritanopia => write!(f, "tritanopia"),
+        }
+    }
+}
+
+/// Two touching colors that become hard to tell apart with some deficiency
+#[derive(Debug, Clone)]
+pub struct ConfusedColors {
+    pub a: [u8; 3],
+    pub b: [u8; 3],
+    /// CIE76 difference with normal vision
+    pub distance: f32,
+    /// Deficiencies that confuse the pair, with the simulated difference for each
+    pub simulated: Vec<(ColorDeficiency, f32)>,
+}
+
+/// Find pairs of touching colors that stay distinct with normal vision (at least twice
+/// `min_distance` apart) but fall below `min_distance` for a color-blind viewer
+pub fn find_confused_colors(img: &DynamicImage, min_distance: f32) -> Vec<ConfusedColors> {
+    let img = analysis_image(img);
+    let (width, height) = img.dimensions();
+
+    // Bucket opaque pixels at 5 bits per channel, averaging the exact colors in each bucket
+    let bucket_of = |p: &Rgba<u8>| -> Option<usize> {
+        (p[3] >= 128).then(|| ((p[0] as usize >> 3) << 10) | ((p[1] as usize >> 3) << 5) | (p[2] as usize >> 3))
+    };
+    let mut buckets: HashMap<usize, (u32, [u64; 3])> = HashMap::new();
+    let mut opaque = 0u32;
+    for p in img.pixels() {
+        if let Some(bucket) = bucket_of(p) {
+            let entry = buckets.entry(bucket).or_default();
+            entry.0 += 1;
+            for c in 0..3 {
+                entry.1[c] += p[c] as u64;
+            }
+            opaque += 1;
+        }
+    }
+
+    let min_count = ((opaque as f32 * MIN_COLOR_COVERAGE) as u32).max(1);
+    let mut colors: Vec<(usize, u32, [u8; 3])> = buckets
+        .into_iter()
+        .filter(|(_, (count, _))| *count >= min_count)
+        .map(|(bucket, (count, sum))| (bucket, count, sum.map(|s| (s / count as u64) as u8)))
+        .collect();
+    colors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    colors.truncate(MAX_COLORS);
+    let index: HashMap<usize, usize> = colors.iter().enumerate().map(|(i, c)| (c.0, i)).collect();
+
+    // Count touching pixels between colors; looking two pixels out skips antialiased edges
+    let color_at = |x: u32, y: u32| bucket_of(img.get_pixel(x, y)).and_then(|b| index.get(&b).copied());
+    let mut edges: HashMap<(usize, usize), u32> = HashMap::new();
+    for y in 0..height {
+        for x in 0..width {
+            let Some(a) = color_at(x, y) else { continue };
+            for (dx, dy) in [(1, 0), (0, 1), (2, 0), (0, 2)] {
+                if x + dx >= width || y + dy >= height {
+                    continue;
+                }
+                if let Some(b) = color_at(x + dx, y + dy).filter(|&b| b != a) {
+                    *edges.entry((a.min(b), a.max(b))).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    let mut pairs: Vec<(usize, usize)> = edges
+        .into_iter()
+        .filter(|&(_, count)| count >= MIN_EDGE_PIXELS)
+        .map(|(pair, _)| pair)
+        .collect();
+    pairs.sort();
+
+    let mut confused = Vec::new();
+    for (i, j) in pairs {
+        let (a, b) = (colors[i].2, colors[j].2);
+        let distance = delta_e(a, b);
+        if distance < min_distance * 2.0 {
+            continue;
+        }
+
+        let simulated: Vec<(ColorDeficiency, f32)> = ColorDeficiency::ALL
+            .iter()
+            .map(|&d| (d, delta_e(d.simulate_color(a), d.simulate_color(b))))
+            .filter(|&(_, simulated)| simulated < min_distance)
+            .collect();
+        if !simulated.is_empty() {
+            confused.push(ConfusedColors { a, b, distance, simulated });
+        }
+    }
+    confused
+}
+
+/// Render the image as seen with a color-vision deficiency
+pub fn simulate_deficiency(img: &DynamicImage, deficiency: ColorDeficiency) -> RgbaImage {
+    let mut out = img.to_rgba8();
+    let mut cache: HashMap<[u8; 3], [u8; 3]> = HashMap::new();
+    for p in out.pixels_mut() {
+        let rgb = [p[0], p[1], p[2]];
+        let sim = *cache.entry(rgb).or_insert_with(|| deficiency.simulate_color(rgb));
+        p[0] = sim[0];
+        p[1] = sim[1];
+        p[2] = sim[2];
+    }
+    out
+}
+
+fn analysis_image(img: &DynamicImage) -> RgbaImage {
+    let (width, height) = img.dimensions();
+    if width.max(height) > ANALYSIS_MAX_SIZE {
+        img.resize(ANALYSIS_MAX_SIZE, ANALYSIS_MAX_SIZE, FilterType::Nearest).to_rgba8()
+    } else {
+        img.to_rgba8()
+    }
+}
+
+fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let s = if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+    (s * 255.0).round() as u8
+}
+
+/// CIE L*a*b* (D65) of an sRGB color
+fn to_lab(rgb: [u8; 3]) -> [f32; 3] {
+    let [r, g, b] = rgb.map(srgb_to_linear);
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+
+    let f = |t: f32| if t > 0.008856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// CIE76 color difference
+fn delta_e(a: [u8; 3], b: [u8; 3]) -> f32 {
+    let (a, b) = (to_lab(a), to_lab(b));
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
@@ -0,0 +1,93 @@
+use image::{DynamicImage, GrayAlphaImage, GrayImage, RgbImage, RgbaImage};
+use qcms::{DataType, Intent, Profile, Transform};
+
+/// Convert an image with an embedded ICC profile to sRGB
+///
+/// qcms works on 8-bit data, so 16-bit inputs come back as 8-bit. Grayscale profiles keep
+/// the image grayscale. Returns None when the profile can't be used (malformed, CMYK, Lab),
+/// leaving the caller to treat the pixels as sRGB.
+pub fn icc_to_srgb(img: &DynamicImage, icc: &[u8]) -> Option<DynamicImage> {
+    let input = Profile::new_from_slice(icc, false)?;
+    let mut output = Profile::new_sRGB();
+    output.precache_output_transform();
+    let intent = Intent::Perceptual;
+    let (width, height) = (img.width(), img.height());
+    let alpha = img.color().has_alpha();
+
+    match icc_color_space(icc)? {
+        b"GRAY" => {
+            // Gray profiles only transform into RGB; equal channels map straight back to luma
+            if alpha {
+                let gray = img.to_luma_alpha8();
+                let transform = Transform::new_to(&input, &output, DataType::GrayA8, DataType::RGBA8, intent)?;
+                let mut rgba = RgbaImage::new(width, height);
+                transform.convert(&gray, &mut rgba);
+                let luma = GrayAlphaImage::from_fn(width, height, |x, y| {
+                    let p = rgba.get_pixel(x, y);
+                    image::LumaA([p[1], p[3]])
+                });
+                Some(DynamicImage::ImageLumaA8(luma))
+            } else {
+                let gray = img.to_luma8();
+                let transform = Transform::new_to(&input, &output, DataType::Gray8, DataType::RGB8, intent)?;
+                let mut rgb = RgbImage::new(width, height);
+                transform.convert(&gray, &mut rgb);
+                let luma = GrayImage::from_fn(width, height, |x, y| image::Luma([rgb.get_pixel(x, y)[1]]));
+                Some(DynamicImage::ImageLuma8(luma))
+            }
+        }
+        b"RGB " if alpha => {
+            let mut rgba = img.to_rgba8();
+            Transform::new(&input, &output, DataType::RGBA8, intent)?.apply(&mut rgba);
+            Some(DynamicImage::ImageRgba8(rgba))
+        }
+        b"RGB " => {
+            let mut rgb = img.to_rgb8();
+            Transform::new(&input, &output, DataType::RGB8, intent)?.apply(&mut rgb);
+            Some(DynamicImage::ImageRgb8(rgb))
+        }
+        _ => None,
+    }
+}
+
+/// Data color space signature from an ICC header (`RGB `, `GRAY`, `CMYK`, ...)
+pub fn icc_color_space(icc: &[u8]) -> Option<&[u8; 4]> {
+    icc.get(16..20)?.try_into().ok()
+}
+
+/// Profile description from an ICC profile's `desc` tag, for display
+pub fn icc_description(icc: &[u8]) -> Option<String> {
+    let be_u32 = |at: usize| -> Option<usize> {
+        Some(u32::from_be_bytes(icc.get(at..at + 4)?.try_into().ok()?) as usize)
+    };
+
+    let count = be_u32(128)?;
+    let (offset, size) = (0..count.min(256)).find_map(|i| {
+        let entry = 132 + i * 12;
+        (icc.get(entry..entry + 4)? == b"desc").then(|| Some((be_u32(entry + 4)?, be_u32(entry + 8)?)))?
+    })?;
+    let tag = icc.get(offset..offset.checked_add(size)?)?;
+
+    let text = match tag.get(0..4)? {
+        // ICC v2 textDescriptionType: ASCII length then a NUL-terminated string
+        b"desc" => {
+            let length = u32::from_be_bytes(tag.get(8..12)?.try_into().ok()?) as usize;
+            String::from_utf8_lossy(tag.get(12..12 + length)?).into_owned()
+        }
+        // ICC v4 multiLocalizedUnicodeType: first record, UTF-16BE
+        b"mluc" => {
+            let length = u32::from_be_bytes(tag.get(20..24)?.try_into().ok()?) as usize;
+            let start = u32::from_be_bytes(tag.get(24..28)?.try_into().ok()?) as usize;
+            let units: Vec<u16> = tag
+                .get(start..start + length)?
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => return None,
+    };
+
+    let text = text.trim_end_matches('\0').trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
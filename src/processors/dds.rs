@@ -0,0 +1,349 @@
+use anyhow::{bail, Context, Result};
+use image::imageops::FilterType;
+#[cfg(feature = "native-textures")]
+use image::RgbaImage;
+#[cfg(feature = "native-textures")]
+use intel_tex_2::{bc7, RgbaSurface};
+#[cfg(feature = "native-textures")]
+use rayon::prelude::*;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::cli::{MipFilter, QualityPreset};
+#[cfg(not(feature = "native-textures"))]
+use super::encode_bc7;
+use super::{encode_bc5, load_normal_map, mip_chain, normal_mip_chain, open_image, open_image_linear, preserve_alpha_coverage, ProcessingStats};
+
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+const DDS_HEADER_SIZE: u32 = 124;
+const DDS_PIXEL_FORMAT_SIZE: u32 = 32;
+
+const DDSD_CAPS: u32 = 0x1;
+const DDSD_HEIGHT: u32 = 0x2;
+const DDSD_WIDTH: u32 = 0x4;
+const DDSD_PIXELFORMAT: u32 = 0x1000;
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDSD_LINEARSIZE: u32 = 0x80000;
+const DDPF_FOURCC: u32 = 0x4;
+const DDSCAPS_COMPLEX: u32 = 0x8;
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+const DDSCAPS_MIPMAP: u32 = 0x400000;
+
+const DXGI_FORMAT_BC5_UNORM: u32 = 83;
+const DXGI_FORMAT_BC7_UNORM: u32 = 98;
+const DXGI_FORMAT_BC7_UNORM_SRGB: u32 = 99;
+const D3D10_RESOURCE_DIMENSION_TEXTURE2D: u32 = 3;
+
+const DDS_ALPHA_MODE_STRAIGHT: u32 = 1;
+const DDS_ALPHA_MODE_PREMULTIPLIED: u32 = 2;
+/// Offset of the DX10 header's miscFlags2 (alpha mode): magic, header, then four u32s
+const DX10_ALPHA_MODE_OFFSET: usize = 4 + DDS_HEADER_SIZE as usize + 16;
+
+/// Configuration for DDS output
+#[derive(Debug, Clone)]
+pub struct DdsConfig {
+    pub quality: QualityPreset,
+    pub generate_mipmaps: bool,
+    pub max_size: Option<u32>,
+    pub mip_filter: MipFilter,
+    /// Whether texels are sRGB color (false for normal maps and other linear data)
+    pub srgb: bool,
+    /// Store X and Y as BC5 instead of BC7
+    pub normal_map: bool,
+    /// Rescale normal map vectors to unit length after resizing and in every mip level
+    pub renormalize: bool,
+    /// Alpha-test threshold (0-1) whose coverage every mip level keeps, for foliage and fences
+    pub alpha_coverage: Option<f32>,
+}
+
+/// Compress an image to a DDS file: BC7 for color and masks, BC5 for normal maps
+pub fn compress_to_dds(input: &Path, output: &Path, config: &DdsConfig) -> Result<ProcessingStats> {
+    let start = Instant::now();
+    let original_size = std::fs::metadata(input)
+        .with_context(|| format!("Failed to read input file: {}", input.display()))?
+        .len();
+
+    let (format, levels, (width, height)) = if config.normal_map {
+        let base = load_normal_map(input, config.max_size, config.renormalize)?;
+        let size = base.dimensions();
+        let chain = if config.generate_mipmaps {
+            normal_mip_chain(base, config.renormalize, config.mip_filter)
+        } else {
+            vec![base]
+        };
+        (DXGI_FORMAT_BC5_UNORM, chain.iter().map(encode_bc5).collect::<Vec<_>>(), size)
+    } else {
+        let mut img = if config.srgb { open_image(input)? } else { open_image_linear(input)? };
+        if let Some(max) = config.max_size {
+            if img.width() > max || img.height() > max {
+                img = img.resize(max, max, FilterType::Lanczos3);
+            }
+        }
+        let base = img.to_rgba8();
+        let size = base.dimensions();
+        let chain = if config.generate_mipmaps {
+            let mut chain = mip_chain(&base, config.mip_filter, config.srgb);
+            if let Some(threshold) = config.alpha_coverage {
+                preserve_alpha_coverage(&mut chain, threshold);
+            }
+            chain
+        } else {
+            vec![base]
+        };
+        let opaque = chain[0].pixels().all(|p| p[3] == 255);
+        let levels = chain.iter().map(|level| encode_bc7(level, config.quality, opaque)).collect();
+        let format = if config.srgb { DXGI_FORMAT_BC7_UNORM_SRGB } else { DXGI_FORMAT_BC7_UNORM };
+        (format, levels, size)
+    };
+
+    let data = dds_container(&levels, width, height, format);
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(output, &data)
+        .with_context(|| format!("Failed to write texture: {}", output.display()))?;
+
+    Ok(ProcessingStats {
+        original_size,
+        output_size: data.len() as u64,
+        processing_time_ms: start.elapsed().as_millis() as u64,
+        quality: None,
+    })
+}
+
+/// Wrap block-compressed levels (largest first) in a DDS file with a DX10 header
+pub fn dds_container(levels: &[Vec<u8>], width: u32, height: u32, dxgi_format: u32) -> Vec<u8> {
+    let mipmapped = levels.len() > 1;
+    let mut flags = DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT | DDSD_LINEARSIZE;
+    let mut caps = DDSCAPS_TEXTURE;
+    if mipmapped {
+        flags |= DDSD_MIPMAPCOUNT;
+        caps |= DDSCAPS_COMPLEX | DDSCAPS_MIPMAP;
+    }
+
+    let mut header = Vec::with_capacity(148);
+    let mut put = |value: u32| header.extend_from_slice(&value.to_le_bytes());
+    put(DDS_HEADER_SIZE);
+    put(flags);
+    put(height);
+    put(width);
+    put(levels.first().map_or(0, |level| level.len() as u32));
+    put(0); // depth
+    put(levels.len() as u32);
+    for _ in 0..11 {
+        put(0);
+    }
+    put(DDS_PIXEL_FORMAT_SIZE);
+    put(DDPF_FOURCC);
+    put(u32::from_le_bytes(*b"DX10"));
+    for _ in 0..5 {
+        put(0); // RGB bit count and masks
+    }
+    put(caps);
+    for _ in 0..4 {
+        put(0); // caps2-4, reserved
+    }
+    put(dxgi_format);
+    put(D3D10_RESOURCE_DIMENSION_TEXTURE2D);
+    put(0); // misc flags
+    put(1); // array size
+    put(DDS_ALPHA_MODE_STRAIGHT);
+
+    let mut data = DDS_MAGIC.to_vec();
+    data.extend_from_slice(&header);
+    for level in levels {
+        data.extend_from_slice(level);
+    }
+    data
+}
+
+/// Record in a DDS file's DX10 header that its color is premultiplied by alpha
+pub fn mark_dds_premultiplied(data: &mut [u8]) -> Result<()> {
+    if data.len() < DX10_ALPHA_MODE_OFFSET + 4 || &data[..4] != DDS_MAGIC {
+        bail!("Not a DDS file with a DX10 header");
+    }
+    data[DX10_ALPHA_MODE_OFFSET..DX10_ALPHA_MODE_OFFSET + 4]
+        .copy_from_slice(&DDS_ALPHA_MODE_PREMULTIPLIED.to_le_bytes());
+    Ok(())
+}
+
+/// Check that a DDS file's header matches its block data; returns width, height and mip count
+///
+/// Only the block formats written here (BC5, BC7) are sized; other files just need a header.
+pub fn validate_dds(data: &[u8]) -> Result<(u32, u32, u32)> {
+    if data.len() < 4 + DDS_HEADER_SIZE as usize || &data[..4] != DDS_MAGIC {
+        bail!("Not a DDS file");
+    }
+    let field = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+    if field(4) != DDS_HEADER_SIZE {
+        bail!("DDS header has the wrong size");
+    }
+    let (height, width, mips) = (field(12), field(16), field(28).max(1));
+    if width == 0 || height == 0 {
+        bail!("DDS texture has a zero dimension");
+    }
+
+    let dx10 = field(84) == u32::from_le_bytes(*b"DX10");
+    if !dx10 {
+        return Ok((width, height, mips));
+    }
+    let data_offset = DX10_ALPHA_MODE_OFFSET + 4;
+    if data.len() < data_offset {
+        bail!("Truncated DDS DX10 header");
+    }
+    if matches!(field(128), DXGI_FORMAT_BC5_UNORM | DXGI_FORMAT_BC7_UNORM | DXGI_FORMAT_BC7_UNORM_SRGB) {
+        let expected: usize = (0..mips)
+            .map(|level| {
+                let (w, h) = ((width >> level).max(1), (height >> level).max(1));
+                w.div_ceil(4) as usize * h.div_ceil(4) as usize * 16
+            })
+            .sum();
+        if data.len() - data_offset != expected {
+            bail!("DDS data is {} bytes, expected {}", data.len() - data_offset, expected);
+        }
+    }
+    Ok((width, height, mips))
+}
+
+/// Encode RGBA as BC7 blocks, one row of blocks per task
+#[cfg(feature = "native-textures")]
+fn encode_bc7(img: &RgbaImage, quality: QualityPreset, opaque: bool) -> Vec<u8> {
+    let settings = match (quality, opaque) {
+        (QualityPreset::Fast, true) => bc7::opaque_very_fast_settings(),
+        (QualityPreset::Balanced, true) => bc7::opaque_fast_settings(),
+        (QualityPreset::High, true) => bc7::opaque_basic_settings(),
+        (QualityPreset::Ultra, true) => bc7::opaque_slow_settings(),
+        (QualityPreset::Fast, false) => bc7::alpha_very_fast_settings(),
+        (QualityPreset::Balanced, false) => bc7::alpha_fast_settings(),
+        (QualityPreset::High, false) => bc7::alpha_basic_settings(),
+        (QualityPreset::Ultra, false) => bc7::alpha_slow_settings(),
+    };
+
+    // The encoder works on whole blocks, so edge texels are repeated to a multiple of 4
+    let (width, height) = img.dimensions();
+    let padded_width = width.div_ceil(4) * 4;
+    let stride = padded_width as usize * 4;
+
+    (0..height.div_ceil(4))
+        .into_par_iter()
+        .flat_map_iter(|block_row| {
+            let mut texels = Vec::with_capacity(stride * 4);
+            for dy in 0..4 {
+                let y = (block_row * 4 + dy).min(height - 1);
+                for x in 0..padded_width {
+                    texels.extend_from_slice(&img.get_pixel(x.min(width - 1), y).0);
+                }
+            }
+            let surface = RgbaSurface {
+                data: &texels,
+                width: padded_width,
+                height: 4,
+                stride: stride as u32,
+            };
+            bc7::compress_blocks(&settings, &surface)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn config(normal_map: bool, srgb: bool) -> DdsConfig {
+        DdsConfig {
+            quality: QualityPreset::Balanced,
+            generate_mipmaps: true,
+            max_size: None,
+            mip_filter: MipFilter::default(),
+            srgb,
+            normal_map,
+            renormalize: false,
+            alpha_coverage: None,
+        }
+    }
+
+    /// Write a 20x12 image and compress it to DDS
+    fn compress(config: &DdsConfig) -> (Vec<u8>, image::RgbaImage) {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in.png");
+        let output = dir.path().join("out.dds");
+        let img = image::RgbaImage::from_fn(20, 12, |x, y| image::Rgba([(x * 6) as u8, (y * 10) as u8, 128, 200]));
+        img.save(&input).unwrap();
+        compress_to_dds(&input, &output, config).unwrap();
+        (std::fs::read(&output).unwrap(), img)
+    }
+
+    #[test]
+    fn test_dx10_header_fields() {
+        let (data, _) = compress(&config(false, true));
+        assert_eq!(&data[..4], DDS_MAGIC);
+        assert_eq!(field(&data, 4), DDS_HEADER_SIZE);
+        let flags = field(&data, 8);
+        for flag in [DDSD_CAPS, DDSD_HEIGHT, DDSD_WIDTH, DDSD_PIXELFORMAT, DDSD_LINEARSIZE, DDSD_MIPMAPCOUNT] {
+            assert_ne!(flags & flag, 0);
+        }
+        assert_eq!((field(&data, 12), field(&data, 16)), (12, 20));
+        // Linear size of the top level: 5x3 blocks
+        assert_eq!(field(&data, 20), 5 * 3 * 16);
+        // 20x12 down to 1x1
+        assert_eq!(field(&data, 28), 5);
+        assert_eq!(field(&data, 76), DDS_PIXEL_FORMAT_SIZE);
+        assert_eq!(field(&data, 80), DDPF_FOURCC);
+        assert_eq!(&data[84..88], b"DX10");
+        assert_eq!(field(&data, 108), DDSCAPS_TEXTURE | DDSCAPS_COMPLEX | DDSCAPS_MIPMAP);
+        assert_eq!(field(&data, 128), DXGI_FORMAT_BC7_UNORM_SRGB);
+        assert_eq!(field(&data, 132), D3D10_RESOURCE_DIMENSION_TEXTURE2D);
+        assert_eq!(field(&data, 136), 0);
+        assert_eq!(field(&data, 140), 1);
+        assert_eq!(field(&data, 144), DDS_ALPHA_MODE_STRAIGHT);
+        assert_eq!(validate_dds(&data).unwrap(), (20, 12, 5));
+    }
+
+    #[test]
+    fn test_dxgi_format_per_mode() {
+        assert_eq!(field(&compress(&config(false, false)).0, 128), DXGI_FORMAT_BC7_UNORM);
+        assert_eq!(field(&compress(&config(true, false)).0, 128), DXGI_FORMAT_BC5_UNORM);
+    }
+
+    #[test]
+    fn test_top_level_decodes() {
+        let (data, img) = compress(&DdsConfig { generate_mipmaps: false, ..config(false, true) });
+        assert_eq!(field(&data, 28), 1);
+        assert_eq!(field(&data, 108), DDSCAPS_TEXTURE);
+        let blocks = &data[DX10_ALPHA_MODE_OFFSET + 4..];
+        assert_eq!(blocks.len(), 5 * 3 * 16);
+        let mut squared = 0.0;
+        for (i, block) in blocks.chunks_exact(16).enumerate() {
+            let mut texels = [0u8; 4 * 4 * 4];
+            bcdec_rs::bc7(block, &mut texels, 4 * 4);
+            let (bx, by) = (i as u32 % 5, i as u32 / 5);
+            for (j, texel) in texels.chunks_exact(4).enumerate() {
+                let source = img.get_pixel(bx * 4 + j as u32 % 4, by * 4 + j as u32 / 4);
+                for c in 0..4 {
+                    squared += (texel[c] as f64 - source[c] as f64).powi(2);
+                }
+            }
+        }
+        let psnr = 10.0 * (255.0f64.powi(2) / (squared / (20 * 12 * 4) as f64)).log10();
+        assert!(psnr > 35.0, "{:.1} dB", psnr);
+    }
+
+    #[test]
+    fn test_premultiplied_alpha_mode() {
+        let (mut data, _) = compress(&config(false, true));
+        mark_dds_premultiplied(&mut data).unwrap();
+        assert_eq!(field(&data, 144), DDS_ALPHA_MODE_PREMULTIPLIED);
+        assert!(mark_dds_premultiplied(&mut [0u8; 16]).is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_truncated_data() {
+        let (data, _) = compress(&config(false, true));
+        assert!(validate_dds(&data[..data.len() - 16]).is_err());
+        assert!(validate_dds(&data[..100]).is_err());
+    }
+}
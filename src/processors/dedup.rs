@@ -0,0 +1,30 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::hash_file;
+
+/// Groups of files with identical contents, each sorted so the first path is the one to keep
+///
+/// Only files sharing a size are hashed. Groups are sorted by their first path.
+pub fn find_duplicates(files: &[PathBuf]) -> Result<Vec<Vec<PathBuf>>> {
+    let mut by_size: HashMap<u64, Vec<&PathBuf>> = HashMap::new();
+    for file in files {
+        by_size.entry(std::fs::metadata(file)?.len()).or_default().push(file);
+    }
+
+    let mut groups = Vec::new();
+    for same_size in by_size.into_values().filter(|files| files.len() > 1) {
+        let mut by_hash: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+        for file in same_size {
+            by_hash.entry(hash_file(file)?).or_default().push(file.clone());
+        }
+        groups.extend(by_hash.into_values().filter(|files| files.len() > 1));
+    }
+
+    for group in &mut groups {
+        group.sort();
+    }
+    groups.sort();
+    Ok(groups)
+}
@@ -0,0 +1,261 @@
+use image::RgbaImage;
+use std::sync::OnceLock;
+
+use crate::cli::DitherPattern;
+
+use super::rgb_psnr;
+
+/// Dithering applied before a lossy encode
+#[derive(Debug, Clone, Copy)]
+pub struct DitherConfig {
+    pub pattern: DitherPattern,
+    /// Noise amplitude as a fraction of the target format's quantization step (1.0 = one step)
+    pub strength: f32,
+}
+
+/// Error of an encode against its source, in dB
+#[derive(Debug, Clone, Copy)]
+pub struct ImageQuality {
+    /// Pixel-level PSNR; dithering lowers it slightly
+    pub psnr: f64,
+    /// PSNR after a 5x5 box blur, which is what the eye sees of bands; dithering raises it
+    pub lowpass_psnr: f64,
+}
+
+impl ImageQuality {
+    /// Compare RGB of visible pixels
+    pub fn measure(source: &RgbaImage, encoded: &RgbaImage) -> Self {
+        Self {
+            psnr: rgb_psnr(source, encoded),
+            lowpass_psnr: rgb_psnr(&box_blur(source, 2), &box_blur(encoded, 2)),
+        }
+    }
+}
+
+/// 8x8 Bayer threshold matrix
+const BAYER: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+const BLUE_NOISE_SIZE: usize = 64;
+
+/// Window radius in which a pixel's neighbourhood must change for it to be dithered
+const FLAT_RADIUS: usize = 8;
+
+/// Add threshold noise to the smooth gradients of an image before a lossy encode
+///
+/// `step` is the target's quantization step in 8-bit levels, so the same strength suits
+/// a 256-color palette and GPU block formats. Returns the dithered image and the fraction
+/// of pixels that were dithered.
+pub fn dither_image(img: &RgbaImage, config: &DitherConfig, step: f32) -> (RgbaImage, f32) {
+    let (offsets, coverage) = dither_offsets(img, config, step);
+    let mut dithered = img.clone();
+    for (pixel, offset) in dithered.pixels_mut().zip(offsets) {
+        for c in 0..3 {
+            pixel[c] = (pixel[c] as f32 + offset).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+    (dithered, coverage)
+}
+
+/// Per-pixel dither offsets in 8-bit levels, for quantizers that add them before rounding
+///
+/// Flat fills and hard edges get no offset, so UI panels and text stay clean. Also returns
+/// the fraction of pixels that are dithered.
+pub fn dither_offsets(img: &RgbaImage, config: &DitherConfig, step: f32) -> (Vec<f32>, f32) {
+    let (width, height) = img.dimensions();
+    let (w, h) = (width as usize, height as usize);
+    let amplitude = step * config.strength;
+    let contrast = local_contrast(img);
+
+    // Pixels within FLAT_RADIUS of any change, from a summed-area table of changed pixels
+    let mut sums = vec![0u32; (w + 1) * (h + 1)];
+    for y in 0..h {
+        for x in 0..w {
+            sums[(y + 1) * (w + 1) + x + 1] = (contrast[y * w + x] > 0) as u32
+                + sums[y * (w + 1) + x + 1]
+                + sums[(y + 1) * (w + 1) + x]
+                - sums[y * (w + 1) + x];
+        }
+    }
+    let changes_near = |x: usize, y: usize| {
+        let (x0, y0) = (x.saturating_sub(FLAT_RADIUS), y.saturating_sub(FLAT_RADIUS));
+        let (x1, y1) = ((x + FLAT_RADIUS + 1).min(w), (y + FLAT_RADIUS + 1).min(h));
+        sums[y1 * (w + 1) + x1] + sums[y0 * (w + 1) + x0] > sums[y0 * (w + 1) + x1] + sums[y1 * (w + 1) + x0]
+    };
+
+    let mut offsets = vec![0f32; w * h];
+    let mut dithered = 0usize;
+    for (x, y, pixel) in img.enumerate_pixels() {
+        let (x, y) = (x as usize, y as usize);
+        let edge = contrast[y * w + x] as f32 > 3.0 * step;
+        if pixel[3] == 0 || edge || !changes_near(x, y) {
+            continue;
+        }
+        offsets[y * w + x] = (threshold(config.pattern, x, y) - 0.5) * amplitude;
+        dithered += 1;
+    }
+
+    (offsets, dithered as f32 / (w * h).max(1) as f32)
+}
+
+/// Threshold in 0-1 for a pixel
+fn threshold(pattern: DitherPattern, x: usize, y: usize) -> f32 {
+    match pattern {
+        DitherPattern::Ordered => (BAYER[y % 8][x % 8] as f32 + 0.5) / 64.0,
+        DitherPattern::BlueNoise => {
+            let rank = blue_noise()[(y % BLUE_NOISE_SIZE) * BLUE_NOISE_SIZE + x % BLUE_NOISE_SIZE];
+            (rank as f32 + 0.5) / (BLUE_NOISE_SIZE * BLUE_NOISE_SIZE) as f32
+        }
+    }
+}
+
+/// Largest RGB range in each pixel's 3x3 neighbourhood
+fn local_contrast(img: &RgbaImage) -> Vec<u8> {
+    let (width, height) = img.dimensions();
+    let mut contrast = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let mut low = [255u8; 3];
+            let mut high = [0u8; 3];
+            for ny in y.saturating_sub(1)..(y + 2).min(height) {
+                for nx in x.saturating_sub(1)..(x + 2).min(width) {
+                    let p = img.get_pixel(nx, ny);
+                    for c in 0..3 {
+                        low[c] = low[c].min(p[c]);
+                        high[c] = high[c].max(p[c]);
+                    }
+                }
+            }
+            contrast.push((0..3).map(|c| high[c] - low[c]).max().unwrap_or(0));
+        }
+    }
+    contrast
+}
+
+/// Tileable 64x64 blue noise ranks, generated once with void-and-cluster (Ulichney 1993)
+fn blue_noise() -> &'static [u16] {
+    static NOISE: OnceLock<Vec<u16>> = OnceLock::new();
+    NOISE.get_or_init(|| void_and_cluster(BLUE_NOISE_SIZE))
+}
+
+fn void_and_cluster(size: usize) -> Vec<u16> {
+    let n = size * size;
+
+    // Gaussian energy of a point at each toroidal offset
+    let sigma = 1.5f32;
+    let mut kernel = vec![0f32; n];
+    for dy in 0..size {
+        for dx in 0..size {
+            let (wx, wy) = (dx.min(size - dx) as f32, dy.min(size - dy) as f32);
+            kernel[dy * size + dx] = (-(wx * wx + wy * wy) / (2.0 * sigma * sigma)).exp();
+        }
+    }
+    let splat = |energy: &mut [f32], point: usize, sign: f32| {
+        let (px, py) = (point % size, point / size);
+        for y in 0..size {
+            let dy = (y + size - py) % size;
+            for x in 0..size {
+                energy[y * size + x] += sign * kernel[dy * size + (x + size - px) % size];
+            }
+        }
+    };
+    let tightest = |energy: &[f32], set: &[bool]| {
+        (0..n).filter(|&i| set[i]).max_by(|&a, &b| energy[a].total_cmp(&energy[b])).unwrap()
+    };
+    let largest_void = |energy: &[f32], set: &[bool]| {
+        (0..n).filter(|&i| !set[i]).min_by(|&a, &b| energy[a].total_cmp(&energy[b])).unwrap()
+    };
+
+    // Initial pattern: a tenth of the points, placed by a fixed xorshift sequence
+    let mut set = vec![false; n];
+    let mut energy = vec![0f32; n];
+    let mut state = 0x9E37_79B9u32;
+    let mut placed = 0;
+    while placed < n / 10 {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let point = state as usize % n;
+        if !set[point] {
+            set[point] = true;
+            splat(&mut energy, point, 1.0);
+            placed += 1;
+        }
+    }
+
+    // Move points from the tightest cluster into the largest void until it settles
+    for _ in 0..n {
+        let cluster = tightest(&energy, &set);
+        set[cluster] = false;
+        splat(&mut energy, cluster, -1.0);
+        let void = largest_void(&energy, &set);
+        set[void] = true;
+        splat(&mut energy, void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0u16; n];
+
+    // Ranks below the initial pattern: remove points tightest first
+    let (mut phase_set, mut phase_energy) = (set.clone(), energy.clone());
+    for rank in (0..placed).rev() {
+        let cluster = tightest(&phase_energy, &phase_set);
+        phase_set[cluster] = false;
+        splat(&mut phase_energy, cluster, -1.0);
+        ranks[cluster] = rank as u16;
+    }
+
+    // Ranks above it: fill the largest voids
+    for rank in placed..n {
+        let void = largest_void(&energy, &set);
+        set[void] = true;
+        splat(&mut energy, void, 1.0);
+        ranks[void] = rank as u16;
+    }
+
+    ranks
+}
+
+
+/// Mean of each pixel's (2 * radius + 1)² neighbourhood, alpha kept as is
+fn box_blur(img: &RgbaImage, radius: usize) -> RgbaImage {
+    let (width, height) = img.dimensions();
+    let (w, h) = (width as usize, height as usize);
+    let mut sums = vec![[0u64; 3]; (w + 1) * (h + 1)];
+    for y in 0..h {
+        for x in 0..w {
+            let p = img.get_pixel(x as u32, y as u32);
+            for c in 0..3 {
+                sums[(y + 1) * (w + 1) + x + 1][c] = p[c] as u64
+                    + sums[y * (w + 1) + x + 1][c]
+                    + sums[(y + 1) * (w + 1) + x][c]
+                    - sums[y * (w + 1) + x][c];
+            }
+        }
+    }
+
+    let mut blurred = img.clone();
+    for (x, y, pixel) in blurred.enumerate_pixels_mut() {
+        let (x, y) = (x as usize, y as usize);
+        let (x0, y0) = (x.saturating_sub(radius), y.saturating_sub(radius));
+        let (x1, y1) = ((x + radius + 1).min(w), (y + radius + 1).min(h));
+        let count = ((x1 - x0) * (y1 - y0)) as u64;
+        for c in 0..3 {
+            let sum = sums[y1 * (w + 1) + x1][c] + sums[y0 * (w + 1) + x0][c]
+                - sums[y0 * (w + 1) + x1][c]
+                - sums[y1 * (w + 1) + x0][c];
+            pixel[c] = ((sum + count / 2) / count) as u8;
+        }
+    }
+    blurred
+}
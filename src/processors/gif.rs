@@ -0,0 +1,70 @@
+use anyhow::{Context, Result};
+use image::codecs::gif::GifDecoder;
+use image::{AnimationDecoder, RgbaImage};
+use std::io::BufReader;
+use std::path::Path;
+
+use super::{gif_sprites, pack_atlas, AtlasConfig, AtlasResult, ProcessingStats};
+
+/// A decoded frame and its delay in milliseconds
+pub type GifFrame = (RgbaImage, Option<u32>);
+
+/// Delays at or below this are shown for the default frame duration, as browsers do
+const MIN_GIF_DELAY_MS: u32 = 10;
+
+/// Frames of an animated GIF, each composited onto the full canvas, with its delay in
+/// milliseconds (`None` for delays browsers ignore)
+///
+/// Returns `None` for other files and for GIFs with a single frame, which stay static images.
+pub fn animated_gif_frames(path: &Path) -> Result<Option<Vec<GifFrame>>> {
+    let is_gif = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("gif"));
+    if !is_gif {
+        return Ok(None);
+    }
+
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open GIF: {}", path.display()))?;
+    let frames = GifDecoder::new(BufReader::new(file))
+        .and_then(|decoder| decoder.into_frames().collect_frames())
+        .with_context(|| format!("Failed to decode GIF: {}", path.display()))?;
+    if frames.len() < 2 {
+        return Ok(None);
+    }
+
+    Ok(Some(
+        frames
+            .into_iter()
+            .map(|frame| {
+                let (numer, denom) = frame.delay().numer_denom_ms();
+                let delay = (numer as f64 / denom.max(1) as f64).round() as u32;
+                (frame.into_buffer(), Some(delay).filter(|&ms| ms > MIN_GIF_DELAY_MS))
+            })
+            .collect(),
+    ))
+}
+
+/// Pack the frames of an animated GIF into a spritesheet at `output_image`
+///
+/// Frames are keyed `<stem>_000`, `<stem>_001`, ... and form one animation named after the
+/// file, timed by the GIF's frame delays. Returns `None` when the GIF isn't animated.
+pub fn gif_to_spritesheet(input: &Path, output_image: &Path, config: &AtlasConfig) -> Result<Option<AtlasResult>> {
+    let Some(frames) = animated_gif_frames(input)? else {
+        return Ok(None);
+    };
+
+    let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("animation");
+    let name = format!("{}{}{}", config.key_prefix, stem, config.key_suffix);
+    let sprites = gif_sprites(&name, frames, config);
+
+    let result = pack_atlas(&sprites, output_image, config)?;
+    Ok(Some(AtlasResult {
+        stats: ProcessingStats {
+            original_size: std::fs::metadata(input)?.len(),
+            ..result.stats
+        },
+        ..result
+    }))
+}
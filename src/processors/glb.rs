@@ -0,0 +1,298 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::path::Path;
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_VERSION: u32 = 2;
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+pub(super) const COMPONENT_U16: u32 = 5123;
+pub(super) const COMPONENT_U32: u32 = 5125;
+pub(super) const COMPONENT_F32: u32 = 5126;
+pub(super) const TARGET_ARRAY_BUFFER: u32 = 34962;
+pub(super) const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// Triangle list primitive with optional normals and UVs
+#[derive(Debug, Clone, Default)]
+pub struct GlbPrimitive {
+    pub positions: Vec<[f32; 3]>,
+    /// Empty when the primitive has no normals
+    pub normals: Vec<[f32; 3]>,
+    /// Empty when the primitive has no UVs
+    pub tex_coords: Vec<[f32; 2]>,
+    pub indices: Vec<u32>,
+    pub material: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GlbMesh {
+    pub name: Option<String>,
+    pub primitives: Vec<GlbPrimitive>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GlbMaterial {
+    pub name: Option<String>,
+    pub base_color: [f32; 4],
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GlbNode {
+    pub name: Option<String>,
+    pub mesh: Option<usize>,
+    /// Column-major local transform
+    pub matrix: Option<[f32; 16]>,
+    pub children: Vec<usize>,
+}
+
+/// Minimal glTF scene that can be written as a self-contained GLB
+#[derive(Debug, Clone, Default)]
+pub struct GlbScene {
+    pub meshes: Vec<GlbMesh>,
+    pub materials: Vec<GlbMaterial>,
+    pub nodes: Vec<GlbNode>,
+    /// Root nodes of the default scene
+    pub roots: Vec<usize>,
+}
+
+impl GlbScene {
+    /// Scene with a single mesh instanced once at the origin
+    pub fn from_mesh(mesh: GlbMesh) -> Self {
+        Self {
+            nodes: vec![GlbNode {
+                name: mesh.name.clone(),
+                mesh: Some(0),
+                ..Default::default()
+            }],
+            meshes: vec![mesh],
+            materials: Vec::new(),
+            roots: vec![0],
+        }
+    }
+
+    /// Serialize the scene to GLB bytes
+    pub fn to_glb(&self) -> Vec<u8> {
+        let mut writer = BufferWriter::default();
+
+        let meshes: Vec<Value> = self
+            .meshes
+            .iter()
+            .map(|mesh| {
+                let primitives: Vec<Value> = mesh
+                    .primitives
+                    .iter()
+                    .map(|p| writer.primitive(p))
+                    .collect();
+                let mut value = json!({ "primitives": primitives });
+                if let Some(name) = &mesh.name {
+                    value["name"] = json!(name);
+                }
+                value
+            })
+            .collect();
+
+        let materials: Vec<Value> = self
+            .materials
+            .iter()
+            .map(|material| {
+                let mut value = json!({
+                    "pbrMetallicRoughness": {
+                        "baseColorFactor": material.base_color,
+                        "metallicFactor": 0.0,
+                    },
+                });
+                if let Some(name) = &material.name {
+                    value["name"] = json!(name);
+                }
+                if material.base_color[3] < 1.0 {
+                    value["alphaMode"] = json!("BLEND");
+                }
+                value
+            })
+            .collect();
+
+        let nodes: Vec<Value> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let mut value = json!({});
+                if let Some(name) = &node.name {
+                    value["name"] = json!(name);
+                }
+                if let Some(mesh) = node.mesh {
+                    value["mesh"] = json!(mesh);
+                }
+                if let Some(matrix) = node.matrix {
+                    value["matrix"] = json!(matrix);
+                }
+                if !node.children.is_empty() {
+                    value["children"] = json!(node.children);
+                }
+                value
+            })
+            .collect();
+
+        let mut document = json!({
+            "asset": {
+                "version": "2.0",
+                "generator": concat!("asset-forge ", env!("CARGO_PKG_VERSION")),
+            },
+            "scene": 0,
+            "scenes": [{ "nodes": self.roots }],
+            "nodes": nodes,
+            "meshes": meshes,
+        });
+        if !materials.is_empty() {
+            document["materials"] = json!(materials);
+        }
+        if !writer.data.is_empty() {
+            document["buffers"] = json!([{ "byteLength": writer.data.len() }]);
+            document["bufferViews"] = json!(writer.views);
+            document["accessors"] = json!(writer.accessors);
+        }
+
+        glb_bytes(&document, writer.data)
+    }
+
+    /// Write the scene as a GLB file, returning the number of bytes written
+    pub fn write(&self, path: &Path) -> Result<u64> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let glb = self.to_glb();
+        std::fs::write(path, &glb)
+            .with_context(|| format!("Failed to write GLB: {}", path.display()))?;
+        Ok(glb.len() as u64)
+    }
+}
+
+/// GLB container holding a glTF document and its binary chunk
+pub fn glb_bytes(document: &Value, mut bin_chunk: Vec<u8>) -> Vec<u8> {
+    let mut json_chunk = serde_json::to_vec(document).unwrap_or_default();
+    pad(&mut json_chunk, b' ');
+    pad(&mut bin_chunk, 0);
+
+    let mut length = 12 + 8 + json_chunk.len();
+    if !bin_chunk.is_empty() {
+        length += 8 + bin_chunk.len();
+    }
+
+    let mut glb = Vec::with_capacity(length);
+    glb.extend_from_slice(&GLB_MAGIC.to_le_bytes());
+    glb.extend_from_slice(&GLB_VERSION.to_le_bytes());
+    glb.extend_from_slice(&(length as u32).to_le_bytes());
+    glb.extend_from_slice(&(json_chunk.len() as u32).to_le_bytes());
+    glb.extend_from_slice(&CHUNK_JSON.to_le_bytes());
+    glb.extend_from_slice(&json_chunk);
+    if !bin_chunk.is_empty() {
+        glb.extend_from_slice(&(bin_chunk.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&CHUNK_BIN.to_le_bytes());
+        glb.extend_from_slice(&bin_chunk);
+    }
+    glb
+}
+
+/// Accumulates the binary chunk along with its buffer views and accessors
+#[derive(Default)]
+struct BufferWriter {
+    data: Vec<u8>,
+    views: Vec<Value>,
+    accessors: Vec<Value>,
+}
+
+impl BufferWriter {
+    fn primitive(&mut self, primitive: &GlbPrimitive) -> Value {
+        let vertex_count = primitive.positions.len();
+        let mut attributes = json!({
+            "POSITION": self.vec3(&primitive.positions, true),
+        });
+        if primitive.normals.len() == vertex_count && vertex_count > 0 {
+            attributes["NORMAL"] = json!(self.vec3(&primitive.normals, false));
+        }
+        if primitive.tex_coords.len() == vertex_count && vertex_count > 0 {
+            let data: Vec<f32> = primitive.tex_coords.iter().flatten().copied().collect();
+            let view = self.view(bytemuck::cast_slice(&data), TARGET_ARRAY_BUFFER);
+            attributes["TEXCOORD_0"] = json!(self.accessor(view, COMPONENT_F32, vertex_count, "VEC2", None));
+        }
+
+        let mut value = json!({
+            "attributes": attributes,
+            "indices": self.indices(&primitive.indices, vertex_count),
+            "mode": 4,
+        });
+        if let Some(material) = primitive.material {
+            value["material"] = json!(material);
+        }
+        value
+    }
+
+    fn vec3(&mut self, values: &[[f32; 3]], bounds: bool) -> usize {
+        let bounds = bounds.then(|| {
+            let mut min = [f32::MAX; 3];
+            let mut max = [f32::MIN; 3];
+            for v in values {
+                for i in 0..3 {
+                    min[i] = min[i].min(v[i]);
+                    max[i] = max[i].max(v[i]);
+                }
+            }
+            (min, max)
+        });
+        let view = self.view(bytemuck::cast_slice(values), TARGET_ARRAY_BUFFER);
+        self.accessor(view, COMPONENT_F32, values.len(), "VEC3", bounds)
+    }
+
+    fn indices(&mut self, indices: &[u32], vertex_count: usize) -> usize {
+        let view = if vertex_count <= u16::MAX as usize {
+            let narrow: Vec<u16> = indices.iter().map(|&i| i as u16).collect();
+            self.view(bytemuck::cast_slice(&narrow), TARGET_ELEMENT_ARRAY_BUFFER)
+        } else {
+            self.view(bytemuck::cast_slice(indices), TARGET_ELEMENT_ARRAY_BUFFER)
+        };
+        let component = if vertex_count <= u16::MAX as usize { COMPONENT_U16 } else { COMPONENT_U32 };
+        self.accessor(view, component, indices.len(), "SCALAR", None)
+    }
+
+    fn view(&mut self, bytes: &[u8], target: u32) -> usize {
+        pad(&mut self.data, 0);
+        let view = json!({
+            "buffer": 0,
+            "byteOffset": self.data.len(),
+            "byteLength": bytes.len(),
+            "target": target,
+        });
+        self.data.extend_from_slice(bytes);
+        self.views.push(view);
+        self.views.len() - 1
+    }
+
+    fn accessor(
+        &mut self,
+        view: usize,
+        component_type: u32,
+        count: usize,
+        kind: &str,
+        bounds: Option<([f32; 3], [f32; 3])>,
+    ) -> usize {
+        let mut accessor = json!({
+            "bufferView": view,
+            "componentType": component_type,
+            "count": count,
+            "type": kind,
+        });
+        if let Some((min, max)) = bounds.filter(|_| count > 0) {
+            accessor["min"] = json!(min);
+            accessor["max"] = json!(max);
+        }
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+}
+
+/// Pad to the 4-byte alignment GLB chunks and buffer views require
+pub(super) fn pad(data: &mut Vec<u8>, byte: u8) {
+    while !data.len().is_multiple_of(4) {
+        data.push(byte);
+    }
+}
@@ -0,0 +1,199 @@
+use anyhow::{Context, Result};
+use half::f16;
+use image::{imageops::FilterType, DynamicImage, ImageFormat, Rgba32FImage, RgbaImage};
+#[cfg(feature = "native-textures")]
+use intel_tex_2::{bc6h, RgbaSurface};
+#[cfg(feature = "native-textures")]
+use rayon::prelude::*;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::cli::{HdrEncoding, QualityPreset, Tonemap};
+#[cfg(not(feature = "native-textures"))]
+use super::encode_bc6h;
+use super::{hdr_to_ktx2, open_image, ProcessingStats};
+
+/// Largest finite half-float value
+const HALF_MAX: f32 = 65504.0;
+
+/// Configuration for HDR (.hdr/.exr) inputs
+#[derive(Debug, Clone)]
+pub struct HdrConfig {
+    pub encoding: HdrEncoding,
+    pub tonemap: Tonemap,
+    /// Exposure adjustment in stops, applied before tonemapping
+    pub exposure: f32,
+    pub quality: QualityPreset,
+    pub generate_mipmaps: bool,
+    pub max_size: Option<u32>,
+    /// Zstandard level for KTX2 supercompression (None = uncompressed)
+    pub zstd_level: Option<i32>,
+}
+
+/// Whether a path is a Radiance HDR or OpenEXR image
+pub fn is_hdr_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| matches!(e.to_lowercase().as_str(), "hdr" | "exr"))
+        .unwrap_or(false)
+}
+
+/// Write an HDR image as an 8-bit PNG, tonemapped or RGBE-packed depending on the encoding
+pub fn convert_hdr_to_ldr(input: &Path, output: &Path, config: &HdrConfig) -> Result<()> {
+    let img = load_hdr(input, config.max_size)?;
+    let ldr = match config.encoding {
+        HdrEncoding::Rgbe => encode_rgbe(&img),
+        _ => tonemap_image(&img, config.tonemap, config.exposure),
+    };
+    ldr.save_with_format(output, ImageFormat::Png)
+        .with_context(|| format!("Failed to save image: {}", output.display()))
+}
+
+/// Compress an HDR image to a half-float or BC6H KTX2 container
+pub fn compress_hdr_to_ktx2(input: &Path, output: &Path, config: &HdrConfig) -> Result<ProcessingStats> {
+    let start = Instant::now();
+    let original_size = std::fs::metadata(input)
+        .with_context(|| format!("Failed to read input file: {}", input.display()))?
+        .len();
+
+    let img = load_hdr(input, config.max_size)?;
+    let encode = |level: &Rgba32FImage| match config.encoding {
+        HdrEncoding::Bc6h => encode_bc6h(level, config.quality),
+        _ => level.as_raw().iter().flat_map(|&v| half_bits(v, true)).collect(),
+    };
+
+    let mut levels = vec![encode(&img)];
+    if config.generate_mipmaps {
+        let (mut width, mut height) = img.dimensions();
+        while width > 1 || height > 1 {
+            width = (width / 2).max(1);
+            height = (height / 2).max(1);
+            let mip = image::imageops::resize(&img, width, height, FilterType::Triangle);
+            levels.push(encode(&mip));
+        }
+    }
+    let data = hdr_to_ktx2(&levels, img.width(), img.height(), config.encoding, config.zstd_level)?;
+
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(output, &data)
+        .with_context(|| format!("Failed to write texture: {}", output.display()))?;
+
+    Ok(ProcessingStats {
+        original_size,
+        output_size: data.len() as u64,
+        processing_time_ms: start.elapsed().as_millis() as u64,
+        quality: None,
+    })
+}
+
+/// Tonemap linear HDR colors into an sRGB 8-bit image; alpha is clamped
+pub fn tonemap_image(img: &Rgba32FImage, tonemap: Tonemap, exposure: f32) -> RgbaImage {
+    let scale = exposure.exp2();
+    RgbaImage::from_fn(img.width(), img.height(), |x, y| {
+        let p = img.get_pixel(x, y).0;
+        let mut out = [0u8; 4];
+        for c in 0..3 {
+            let v = (p[c] * scale).max(0.0);
+            let mapped = match tonemap {
+                // Narkowicz's fit of the ACES filmic curve
+                Tonemap::Aces => (v * (2.51 * v + 0.03)) / (v * (2.43 * v + 0.59) + 0.14),
+                Tonemap::Reinhard => v / (1.0 + v),
+                Tonemap::Clamp => v,
+            };
+            out[c] = to_u8(linear_to_srgb(mapped.clamp(0.0, 1.0)));
+        }
+        out[3] = to_u8(p[3].clamp(0.0, 1.0));
+        image::Rgba(out)
+    })
+}
+
+/// Pack linear colors as Radiance RGBE: 8-bit mantissas with a shared exponent in alpha
+pub fn encode_rgbe(img: &Rgba32FImage) -> RgbaImage {
+    RgbaImage::from_fn(img.width(), img.height(), |x, y| {
+        let p = img.get_pixel(x, y).0;
+        let rgb = [p[0].max(0.0), p[1].max(0.0), p[2].max(0.0)];
+        let max = rgb[0].max(rgb[1]).max(rgb[2]);
+        if !max.is_finite() || max < 1e-32 {
+            return image::Rgba([0, 0, 0, 0]);
+        }
+        // max = mantissa * 2^exponent with mantissa in [0.5, 1)
+        let exponent = (max.log2().floor() as i32 + 1).clamp(-128, 127);
+        let scale = 256.0 / (exponent as f32).exp2();
+        image::Rgba([
+            (rgb[0] * scale).min(255.0) as u8,
+            (rgb[1] * scale).min(255.0) as u8,
+            (rgb[2] * scale).min(255.0) as u8,
+            (exponent + 128) as u8,
+        ])
+    })
+}
+
+/// Encode BC6H (unsigned float) blocks, one row of blocks per task
+#[cfg(feature = "native-textures")]
+fn encode_bc6h(img: &Rgba32FImage, quality: QualityPreset) -> Vec<u8> {
+    let settings = match quality {
+        QualityPreset::Fast => bc6h::very_fast_settings(),
+        QualityPreset::Balanced => bc6h::basic_settings(),
+        QualityPreset::High => bc6h::slow_settings(),
+        QualityPreset::Ultra => bc6h::very_slow_settings(),
+    };
+
+    // The encoder works on whole blocks, so edge texels are repeated to a multiple of 4
+    let (width, height) = img.dimensions();
+    let padded_width = width.div_ceil(4) * 4;
+    let stride = padded_width as usize * 8;
+
+    (0..height.div_ceil(4))
+        .into_par_iter()
+        .flat_map_iter(|block_row| {
+            let mut texels = Vec::with_capacity(stride * 4);
+            for dy in 0..4 {
+                let y = (block_row * 4 + dy).min(height - 1);
+                for x in 0..padded_width {
+                    let p = img.get_pixel(x.min(width - 1), y).0;
+                    for v in p {
+                        texels.extend_from_slice(&half_bits(v, false));
+                    }
+                }
+            }
+            let surface = RgbaSurface {
+                data: &texels,
+                width: padded_width,
+                height: 4,
+                stride: stride as u32,
+            };
+            bc6h::compress_blocks(&settings, &surface)
+        })
+        .collect()
+}
+
+fn load_hdr(path: &Path, max_size: Option<u32>) -> Result<Rgba32FImage> {
+    let mut img = open_image(path)?;
+    if let Some(max) = max_size {
+        if img.width() > max || img.height() > max {
+            img = img.resize(max, max, FilterType::Lanczos3);
+        }
+    }
+    Ok(DynamicImage::to_rgba32f(&img))
+}
+
+/// Little-endian half-float bits, clamped to the finite range (and to zero when unsigned)
+fn half_bits(value: f32, signed: bool) -> [u8; 2] {
+    let min = if signed { -HALF_MAX } else { 0.0 };
+    let value = if value.is_nan() { 0.0 } else { value.clamp(min, HALF_MAX) };
+    f16::from_f32(value).to_le_bytes()
+}
+
+fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn to_u8(v: f32) -> u8 {
+    (v * 255.0).round() as u8
+}
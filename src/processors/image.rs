@@ -1,12 +1,27 @@
 use anyhow::{Context, Result};
-use image::{DynamicImage, GenericImageView, ImageFormat};
-use oxipng::{InFile, Options, OutFile};
-use std::path::Path;
+use image::{DynamicImage, GenericImageView, ImageDecoder, ImageFormat, ImageReader, RgbaImage};
+use oxipng::{InFile, Options, OutFile, StripChunks};
+use zune_core::bit_depth::BitDepth;
+use zune_core::colorspace::ColorSpace;
+use zune_core::options::EncoderOptions;
+use zune_jpegxl::JxlSimpleEncoder;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 
-use crate::cli::{OutputFormat, QualityPreset};
+use crate::cli::{
+    AstcBlockSize, HdrEncoding, MipFilter, NormalEncoding, OutputFormat, QualityPreset, TextureColorSpace,
+    TextureType, Tonemap,
+};
+use crate::utils::format_size;
 
-use super::{compress_to_ktx2, BasisCompressionMode, BasisConfig, ProcessingStats};
+use super::{
+    compress_hdr_to_ktx2, compress_normal_map_to_ktx2, compress_to_astc, compress_to_astc_ktx2,
+    compress_to_dds, compress_to_ktx2, compress_to_unorm16_ktx2, convert_hdr_to_ldr, dither_image, dither_offsets, icc_description,
+    icc_to_srgb, image_bit_depth, is_16bit, is_hdr_path, measure_quality, ssim, is_psd_path, load_normal_map, psd_icc_profile, read_psd_composite, mark_dds_premultiplied, mark_ktx2_premultiplied,
+    to_8bit, AstcConfig, DdsConfig, Unorm16Config,
+    quantize_image, quantize_settings, BasisCompressionMode, BasisConfig, DitherConfig, HdrConfig,
+    ImageQuality, NormalMapConfig, PaletteImage, ProcessingStats, QualityMetrics,
+};
 
 /// Image processor configuration
 #[derive(Debug, Clone)]
@@ -15,6 +30,43 @@ pub struct ImageProcessorConfig {
     pub quality: QualityPreset,
     pub max_size: Option<u32>,
     pub generate_mipmaps: bool,
+    /// Zstandard level for KTX2 supercompression (None = uncompressed)
+    pub ktx2_zstd_level: Option<i32>,
+    /// ASTC block size for `.astc` output; for KTX2 it switches the payload from UASTC to ASTC
+    pub astc_block: Option<AstcBlockSize>,
+    /// How HDR (.hdr/.exr) inputs are stored
+    pub hdr_encoding: HdrEncoding,
+    pub tonemap: Tonemap,
+    /// Exposure adjustment in stops for tonemapped HDR inputs
+    pub exposure: f32,
+    /// Normal maps and masks are kept linear; normal maps also get XY-channel GPU encodings
+    pub texture_type: TextureType,
+    /// Rescale normal map vectors to unit length after resizing and in every mip level
+    pub renormalize: bool,
+    pub normal_encoding: NormalEncoding,
+    /// Reduce PNG output to a palette first (fast and balanced quality only)
+    pub quantize: bool,
+    /// Dither gradients before lossy encoding (palette PNG, JPEG, lossy JPEG XL, KTX2, ASTC)
+    pub dither: Option<DitherConfig>,
+    /// Multiply color by alpha before encoding (color textures only); KTX2 records it in its DFD
+    pub premultiply_alpha: bool,
+    /// Override of the transfer function implied by `texture_type` (normal maps are always linear)
+    pub colorspace: Option<TextureColorSpace>,
+    /// Downsampling filter for generated mip levels (KTX2, ASTC and DDS)
+    pub mip_filter: MipFilter,
+    /// Alpha-test threshold (0-1) whose coverage generated mip levels keep (color textures)
+    pub alpha_coverage: Option<f32>,
+    /// SSIM to reach with the smallest JPEG quality or JPEG XL precision, instead of `quality`
+    pub target_ssim: Option<f64>,
+    /// Largest output in bytes; quality and then resolution are lowered until it fits
+    pub max_output_size: Option<u64>,
+    /// Drop EXIF, text and other chunks that don't affect display from PNG output (JPEG and
+    /// WebP are re-encoded without metadata either way)
+    pub strip_metadata: bool,
+    /// Bits per channel of PNG and KTX2 output: 16 keeps 16-bit sources at full precision
+    /// (16-bit PNG, uncompressed R16/RGBA16 KTX2), 8 reduces them; None keeps PNG at the source
+    /// depth and stores other formats at 8 bits
+    pub bit_depth: Option<u8>,
 }
 
 impl Default for ImageProcessorConfig {
@@ -24,54 +76,251 @@ impl Default for ImageProcessorConfig {
             quality: QualityPreset::Balanced,
             max_size: None,
             generate_mipmaps: false,
+            ktx2_zstd_level: None,
+            astc_block: None,
+            hdr_encoding: HdrEncoding::Tonemap,
+            tonemap: Tonemap::Aces,
+            exposure: 0.0,
+            texture_type: TextureType::Color,
+            renormalize: false,
+            normal_encoding: NormalEncoding::Uastc,
+            quantize: false,
+            dither: None,
+            premultiply_alpha: false,
+            colorspace: None,
+            mip_filter: MipFilter::default(),
+            alpha_coverage: None,
+            target_ssim: None,
+            max_output_size: None,
+            strip_metadata: false,
+            bit_depth: None,
         }
     }
 }
 
+impl ImageProcessorConfig {
+    /// Whether values are stored linear: no ICC conversion, and KTX2/ASTC are tagged linear
+    pub fn is_linear(&self) -> bool {
+        match (self.texture_type, self.colorspace) {
+            (TextureType::NormalMap, _) => true,
+            (_, Some(colorspace)) => colorspace == TextureColorSpace::Linear,
+            (texture_type, None) => texture_type == TextureType::Mask,
+        }
+    }
+
+    /// Whether `target_ssim` applies to an image written to `output`; only JPEG and JPEG XL
+    /// have a quality to search, the other encoders ignore it
+    pub fn searches_ssim(&self, output: &Path) -> bool {
+        matches!(output_format_for(output, self), OutputFormat::Jpeg | OutputFormat::Jxl)
+    }
+}
+
 /// Process an image file
 pub fn process_image(
     input: &Path,
     output: &Path,
     config: &ImageProcessorConfig,
 ) -> Result<ProcessingStats> {
+    if let Some(budget) = config.max_output_size {
+        return process_image_within_budget(input, output, config, budget);
+    }
+
     let start = Instant::now();
     let original_size = std::fs::metadata(input)
         .with_context(|| format!("Failed to read input file: {}", input.display()))?
         .len();
 
-    // Determine output format
-    let output_format = config.output_format.unwrap_or_else(|| {
-        output
-            .extension()
-            .and_then(|e| e.to_str())
-            .map(|e| match e.to_lowercase().as_str() {
-                "jpg" | "jpeg" => OutputFormat::Jpeg,
-                "webp" => OutputFormat::Webp,
-                "ktx2" => OutputFormat::Ktx2,
-                _ => OutputFormat::Png,
-            })
-            .unwrap_or(OutputFormat::Png)
-    });
+    // HDR inputs are stored as float KTX2, or become an 8-bit PNG that runs through
+    // the regular pipeline
+    if is_hdr_path(input) {
+        let hdr = hdr_config(config);
+        if matches!(hdr.encoding, HdrEncoding::Half | HdrEncoding::Bc6h) {
+            return compress_hdr_to_ktx2(input, output, &hdr);
+        }
+
+        let temp = output.with_extension("hdr.tmp.png");
+        if let Some(parent) = temp.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        convert_hdr_to_ldr(input, &temp, &hdr)?;
+        let ldr_config = ImageProcessorConfig {
+            output_format: hdr.encoding.output_format().or(config.output_format),
+            max_size: None,
+            ..config.clone()
+        };
+        let result = process_image(&temp, output, &ldr_config);
+        let _ = std::fs::remove_file(&temp);
+
+        return Ok(ProcessingStats {
+            original_size,
+            processing_time_ms: start.elapsed().as_millis() as u64,
+            ..result?
+        });
+    }
+
+    if let Some(target) = config.target_ssim.filter(|t| !(*t > 0.0 && *t <= 1.0)) {
+        anyhow::bail!("Target SSIM must be in (0, 1], got {}", target);
+    }
+
+    let output_format = output_format_for(output, config);
+
+    match config.bit_depth {
+        Some(8) | None => {}
+        Some(16) if matches!(output_format, OutputFormat::Png | OutputFormat::Ktx2) => {}
+        Some(16) => anyhow::bail!("16-bit output needs PNG or KTX2, not {}", output_format),
+        Some(bits) => anyhow::bail!("Bit depth must be 8 or 16, got {}", bits),
+    }
+    if config.bit_depth.is_none() && !matches!(output_format, OutputFormat::Png) && image_bit_depth(input).is_ok_and(|bits| bits == 16) {
+        tracing::info!(
+            "{}: 16-bit source stored at 8 bits as {}; set bit_depth = 16 for 16-bit PNG or KTX2",
+            input.display(),
+            output_format
+        );
+    }
 
     // Create output directory if needed
     if let Some(parent) = output.parent() {
         std::fs::create_dir_all(parent)?;
     }
 
-    match output_format {
-        OutputFormat::Png => process_png(input, output, config)?,
-        OutputFormat::Jpeg => process_jpeg(input, output, config)?,
-        OutputFormat::Webp => process_webp(input, output, config)?,
+    // Premultiplied before resizing and mip generation, so filtering never pulls the color
+    // of transparent texels into visible edges
+    if config.premultiply_alpha && config.texture_type == TextureType::Color {
+        let mut img = if config.is_linear() { open_image_linear(input)? } else { open_image(input)? }.to_rgba8();
+        premultiply_alpha(&mut img);
+
+        let temp = output.with_extension("premultiplied.tmp.png");
+        DynamicImage::ImageRgba8(img)
+            .save_with_format(&temp, ImageFormat::Png)
+            .with_context(|| format!("Failed to save premultiplied texture: {}", temp.display()))?;
+        let premultiplied_config = ImageProcessorConfig {
+            premultiply_alpha: false,
+            ..config.clone()
+        };
+        let result = process_image(&temp, output, &premultiplied_config);
+        let _ = std::fs::remove_file(&temp);
+        let result = result?;
+
+        let mark = match output_format {
+            OutputFormat::Ktx2 => Some(mark_ktx2_premultiplied as fn(&mut [u8]) -> Result<()>),
+            OutputFormat::Dds => Some(mark_dds_premultiplied as fn(&mut [u8]) -> Result<()>),
+            _ => None,
+        };
+        if let Some(mark) = mark {
+            let mut data = std::fs::read(output)?;
+            mark(&mut data).with_context(|| format!("Failed to flag premultiplied alpha: {}", output.display()))?;
+            std::fs::write(output, data)?;
+        }
+
+        return Ok(ProcessingStats {
+            original_size,
+            processing_time_ms: start.elapsed().as_millis() as u64,
+            ..result
+        });
+    }
+
+    // GPU encoders read the file themselves, so they get a dithered copy of the resized image
+    if let (Some(dither), Some(step), false) =
+        (&config.dither, gpu_dither_step(output_format, config), config.bit_depth == Some(16))
+    {
+        let img = load_and_resize(input, config)?.to_rgba8();
+        let (dithered, coverage) = dither_image(&img, dither, step);
+        log_dither(input, coverage, None);
+
+        let temp = output.with_extension("dither.tmp.png");
+        DynamicImage::ImageRgba8(dithered)
+            .save_with_format(&temp, ImageFormat::Png)
+            .with_context(|| format!("Failed to save dithered texture: {}", temp.display()))?;
+        let dithered_config = ImageProcessorConfig {
+            max_size: None,
+            dither: None,
+            ..config.clone()
+        };
+        let result = process_image(&temp, output, &dithered_config);
+        let _ = std::fs::remove_file(&temp);
+
+        return Ok(ProcessingStats {
+            original_size,
+            processing_time_ms: start.elapsed().as_millis() as u64,
+            ..result?
+        });
+    }
+
+    let quality = match output_format {
+        OutputFormat::Png => {
+            process_png(input, output, config)?;
+            // Only a palette or dithering makes PNG lossy
+            if config.quantize || config.dither.is_some() {
+                measure_output(input, output, config)?
+            } else {
+                None
+            }
+        }
+        OutputFormat::Jpeg => {
+            process_jpeg(input, output, config)?;
+            measure_output(input, output, config)?
+        }
+        OutputFormat::Webp => {
+            process_webp(input, output, config)?;
+            None
+        }
+        OutputFormat::Jxl => process_jxl(input, output, config)?,
+        OutputFormat::Astc => return compress_to_astc(input, output, &astc_config(config)),
+        OutputFormat::Ktx2 if config.bit_depth == Some(16) => {
+            if !config.is_linear() {
+                anyhow::bail!("16-bit KTX2 is linear only; use it for masks, heightmaps and other data");
+            }
+            let unorm16_config = Unorm16Config {
+                max_size: config.max_size,
+                generate_mipmaps: config.generate_mipmaps,
+                mip_filter: config.mip_filter,
+                zstd_level: config.ktx2_zstd_level,
+            };
+            return compress_to_unorm16_ktx2(input, output, &unorm16_config);
+        }
+        OutputFormat::Ktx2 if config.astc_block.is_some() => {
+            return compress_to_astc_ktx2(input, output, &astc_config(config));
+        }
+        OutputFormat::Ktx2 if config.texture_type == TextureType::NormalMap => {
+            let normal_config = NormalMapConfig {
+                encoding: config.normal_encoding,
+                quality: config.quality,
+                generate_mipmaps: config.generate_mipmaps,
+                max_size: config.max_size,
+                renormalize: config.renormalize,
+                zstd_level: config.ktx2_zstd_level,
+                mip_filter: config.mip_filter,
+            };
+            return compress_normal_map_to_ktx2(input, output, &normal_config);
+        }
         OutputFormat::Ktx2 => {
             let basis_config = BasisConfig {
                 mode: BasisCompressionMode::Uastc,
                 quality: config.quality,
                 generate_mipmaps: config.generate_mipmaps,
                 max_size: config.max_size,
+                zstd_level: config.ktx2_zstd_level,
+                linear: config.is_linear(),
+                mip_filter: config.mip_filter,
+                alpha_coverage: config.alpha_coverage,
+                ..Default::default()
             };
             return compress_to_ktx2(input, output, &basis_config);
         }
-    }
+        OutputFormat::Dds => {
+            let dds_config = DdsConfig {
+                quality: config.quality,
+                generate_mipmaps: config.generate_mipmaps,
+                max_size: config.max_size,
+                mip_filter: config.mip_filter,
+                srgb: !config.is_linear(),
+                normal_map: config.texture_type == TextureType::NormalMap,
+                renormalize: config.renormalize,
+                alpha_coverage: config.alpha_coverage.filter(|_| config.texture_type != TextureType::NormalMap),
+            };
+            return compress_to_dds(input, output, &dds_config);
+        }
+    };
 
     let output_size = std::fs::metadata(output)
         .with_context(|| format!("Failed to read output file: {}", output.display()))?
@@ -83,16 +332,166 @@ pub fn process_image(
         original_size,
         output_size,
         processing_time_ms,
+        quality,
     })
 }
 
+/// Quality of an output the image crate decodes, against the source as it was encoded
+fn measure_output(input: &Path, output: &Path, config: &ImageProcessorConfig) -> Result<Option<QualityMetrics>> {
+    let source = load_and_resize(input, config)?.to_rgba8();
+    let encoded = image::open(output)
+        .with_context(|| format!("Failed to decode output: {}", output.display()))?
+        .to_rgba8();
+    Ok(measure_quality(&source, &encoded))
+}
+
+/// Naming template for resolution variants: `hero.png` at 0.5 becomes `hero@0.5x.png`
+pub const DEFAULT_VARIANT_NAME: &str = "{name}@{scale}x";
+
+/// Path of the `scale` variant of `output`, named by a template with `{name}` (the file
+/// stem) and `{scale}` placeholders; the extension is kept
+pub fn variant_path(output: &Path, template: &str, scale: f32) -> PathBuf {
+    let name = output.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+    let mut file_name = template.replace("{name}", name).replace("{scale}", &scale.to_string());
+    if let Some(ext) = output.extension().and_then(|e| e.to_str()) {
+        file_name = format!("{}.{}", file_name, ext);
+    }
+    output.with_file_name(file_name)
+}
+
+/// Process an image once per scale factor, writing each variant to `variant_path`
+///
+/// Variants are resized from the source (not from each other) and then run through the
+/// regular pipeline, so `max_size` still caps every variant.
+pub fn process_image_variants(
+    input: &Path,
+    output: &Path,
+    config: &ImageProcessorConfig,
+    scales: &[f32],
+    template: &str,
+) -> Result<Vec<(PathBuf, f32, ProcessingStats)>> {
+    if let Some(scale) = scales.iter().find(|s| !s.is_finite() || **s <= 0.0) {
+        anyhow::bail!("Invalid variant scale {}: scales must be positive", scale);
+    }
+    if scales.len() > 1 && !template.contains("{scale}") {
+        anyhow::bail!("Variant name template \"{}\" must contain {{scale}}", template);
+    }
+
+    let mut variants = Vec::with_capacity(scales.len());
+    for &scale in scales {
+        let path = variant_path(output, template, scale);
+        if scale == 1.0 {
+            variants.push((path.clone(), scale, process_image(input, &path, config)?));
+            continue;
+        }
+
+        let img = if config.is_linear() { open_image_linear(input)? } else { open_image(input)? };
+        let (width, height) = img.dimensions();
+        let new_width = ((width as f32 * scale).round() as u32).max(1);
+        let new_height = ((height as f32 * scale).round() as u32).max(1);
+        let mut resized = img.resize_exact(new_width, new_height, image::imageops::FilterType::Lanczos3);
+        if config.texture_type == TextureType::NormalMap && config.renormalize {
+            let mut normals = resized.to_rgba8();
+            super::renormalize_normals(&mut normals);
+            resized = DynamicImage::ImageRgba8(normals);
+        }
+
+        // Float sources stay float so HDR encodings still apply
+        let (temp, format) = if is_hdr_path(input) {
+            (path.with_extension("variant.tmp.exr"), ImageFormat::OpenExr)
+        } else {
+            (path.with_extension("variant.tmp.png"), ImageFormat::Png)
+        };
+        if let Some(parent) = temp.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        resized
+            .save_with_format(&temp, format)
+            .with_context(|| format!("Failed to save scaled variant: {}", temp.display()))?;
+        let result = process_image(&temp, &path, config);
+        let _ = std::fs::remove_file(&temp);
+
+        let stats = ProcessingStats {
+            original_size: std::fs::metadata(input)?.len(),
+            ..result?
+        };
+        variants.push((path, scale, stats));
+    }
+
+    Ok(variants)
+}
+
+/// Quantization step (8-bit levels) dithering is scaled to for GPU block formats
+fn gpu_dither_step(format: OutputFormat, config: &ImageProcessorConfig) -> Option<f32> {
+    if config.texture_type == TextureType::NormalMap {
+        return None;
+    }
+    match format {
+        OutputFormat::Astc => Some(8.0),
+        OutputFormat::Ktx2 if config.astc_block.is_some() => Some(8.0),
+        OutputFormat::Ktx2 | OutputFormat::Dds => Some(4.0),
+        _ => None,
+    }
+}
+
+/// Dithering applies to color and mask textures; normal maps would lose unit length
+fn dither_for(config: &ImageProcessorConfig) -> Option<&DitherConfig> {
+    config.dither.as_ref().filter(|_| config.texture_type != TextureType::NormalMap)
+}
+
+/// Report how much of an image was dithered and, where the encode can be checked, the
+/// quality without and with dithering
+fn log_dither(input: &Path, coverage: f32, quality: Option<(ImageQuality, ImageQuality)>) {
+    match quality {
+        Some((before, after)) => tracing::info!(
+            "{}: dithered {:.0}% of pixels, PSNR {:.1} → {:.1} dB, low-pass PSNR {:.1} → {:.1} dB",
+            input.display(),
+            coverage * 100.0,
+            before.psnr,
+            after.psnr,
+            before.lowpass_psnr,
+            after.lowpass_psnr
+        ),
+        None => tracing::info!("{}: dithered {:.0}% of pixels", input.display(), coverage * 100.0),
+    }
+}
+
+fn hdr_config(config: &ImageProcessorConfig) -> HdrConfig {
+    HdrConfig {
+        encoding: config.hdr_encoding,
+        tonemap: config.tonemap,
+        exposure: config.exposure,
+        quality: config.quality,
+        generate_mipmaps: config.generate_mipmaps,
+        max_size: config.max_size,
+        zstd_level: config.ktx2_zstd_level,
+    }
+}
+
+fn astc_config(config: &ImageProcessorConfig) -> AstcConfig {
+    AstcConfig {
+        block: config.astc_block,
+        quality: config.quality,
+        generate_mipmaps: config.generate_mipmaps,
+        max_size: config.max_size,
+        zstd_level: config.ktx2_zstd_level,
+        srgb: !config.is_linear(),
+        renormalize: config.renormalize && config.texture_type == TextureType::NormalMap,
+        mip_filter: config.mip_filter,
+        alpha_coverage: config.alpha_coverage.filter(|_| config.texture_type != TextureType::NormalMap),
+    }
+}
+
 /// Process PNG using oxipng
 fn process_png(input: &Path, output: &Path, config: &ImageProcessorConfig) -> Result<()> {
     // Load and resize if needed
-    let img = load_and_resize(input, config.max_size)?;
+    let img = load_and_resize(input, config)?;
+    let reduce_depth = config.bit_depth == Some(8) && is_16bit(&img);
+    let img = if reduce_depth { to_8bit(img) } else { img };
 
-    // Save as PNG first (if resized or input wasn't PNG)
-    let temp_path = if config.max_size.is_some() || !is_png(input) {
+    // Save as PNG first (if resized or reduced, input wasn't PNG, or its ICC profile was applied
+    // and must go)
+    let temp_path = if config.max_size.is_some() || reduce_depth || !is_png(input) || image_icc_profile(input)?.is_some() {
         let temp = output.with_extension("tmp.png");
         img.save_with_format(&temp, ImageFormat::Png)?;
         Some(temp)
@@ -104,12 +503,15 @@ fn process_png(input: &Path, output: &Path, config: &ImageProcessorConfig) -> Re
     let input_path = temp_path.as_ref().unwrap_or(&default_path);
 
     // Configure oxipng based on quality preset
-    let options = match config.quality {
+    let mut options = match config.quality {
         QualityPreset::Fast => Options::from_preset(1),
         QualityPreset::Balanced => Options::from_preset(3),
         QualityPreset::High => Options::from_preset(5),
         QualityPreset::Ultra => Options::from_preset(6),
     };
+    if config.strip_metadata {
+        options.strip = StripChunks::Safe;
+    }
 
     // Run oxipng optimization
     oxipng::optimize(
@@ -119,6 +521,17 @@ fn process_png(input: &Path, output: &Path, config: &ImageProcessorConfig) -> Re
     )
     .with_context(|| format!("Failed to optimize PNG: {}", input.display()))?;
 
+    // The palette version is only kept when it beats the lossless file (like pngquant's
+    // --skip-if-larger); smooth art often compresses better without it
+    if let Some(palette) = quantize_png(input, &img, config) {
+        let quantized = oxipng::optimize_from_memory(&palette.to_png()?, &options)
+            .with_context(|| format!("Failed to optimize PNG: {}", input.display()))?;
+        if (quantized.len() as u64) < std::fs::metadata(output)?.len() {
+            std::fs::write(output, &quantized)
+                .with_context(|| format!("Failed to write PNG: {}", output.display()))?;
+        }
+    }
+
     // Clean up temp file
     if let Some(temp) = temp_path {
         let _ = std::fs::remove_file(temp);
@@ -127,28 +540,243 @@ fn process_png(input: &Path, output: &Path, config: &ImageProcessorConfig) -> Re
     Ok(())
 }
 
+/// Palette version of an image when quantization is enabled and good enough
+///
+/// Like pngquant's minimum quality, palettes that fall below the preset's PSNR (smooth
+/// gradients, photos) are rejected and the PNG stays lossless.
+fn quantize_png(input: &Path, img: &DynamicImage, config: &ImageProcessorConfig) -> Option<PaletteImage> {
+    // A palette would reduce a 16-bit image to 8 bits
+    if !config.quantize || config.texture_type != TextureType::Color || is_16bit(img) {
+        return None;
+    }
+    let (max_colors, sample_factor, min_psnr) = quantize_settings(config.quality)?;
+
+    let rgba = img.to_rgba8();
+    let palette = quantize_image(&rgba, max_colors, sample_factor);
+    let psnr = palette.psnr(&rgba);
+    if psnr < min_psnr {
+        tracing::info!(
+            "{}: palette too lossy ({:.1} dB < {:.1} dB), keeping lossless PNG",
+            input.display(),
+            psnr,
+            min_psnr
+        );
+        return None;
+    }
+
+    // The undithered palette decides whether quantizing is worth it at all
+    let Some(dither) = dither_for(config) else {
+        return Some(palette);
+    };
+    let (dithered, coverage) = dither_image(&rgba, dither, PALETTE_DITHER_STEP);
+    let dithered = quantize_image(&dithered, max_colors, sample_factor);
+    log_dither(
+        input,
+        coverage,
+        Some((
+            ImageQuality::measure(&rgba, &palette.to_rgba()),
+            ImageQuality::measure(&rgba, &dithered.to_rgba()),
+        )),
+    );
+    Some(dithered)
+}
+
+/// Output format: the configured one, or the one the output extension names
+fn output_format_for(output: &Path, config: &ImageProcessorConfig) -> OutputFormat {
+    config.output_format.unwrap_or_else(|| {
+        output
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| match e.to_lowercase().as_str() {
+                "jpg" | "jpeg" => OutputFormat::Jpeg,
+                "webp" => OutputFormat::Webp,
+                "ktx2" => OutputFormat::Ktx2,
+                "jxl" => OutputFormat::Jxl,
+                "astc" => OutputFormat::Astc,
+                "dds" => OutputFormat::Dds,
+                _ => OutputFormat::Png,
+            })
+            .unwrap_or(OutputFormat::Png)
+    })
+}
+
+/// SSIM targets a size budget steps JPEG and JPEG XL down through, best first
+const BUDGET_SSIM_STEPS: [f64; 5] = [0.99, 0.97, 0.95, 0.9, 0.85];
+
+/// Smallest longest side a size budget shrinks a texture to
+const MIN_BUDGET_SIZE: u32 = 32;
+
+/// Process an image, lowering quality and then resolution until the output fits in `budget` bytes
+///
+/// Each resolution, from the configured one down by halves, is tried at the configured
+/// quality and then at lower ones (smaller SSIM targets for JPEG and JPEG XL, a palette for
+/// PNG); the first output within budget is kept. Fails and removes the output if nothing fits.
+fn process_image_within_budget(
+    input: &Path,
+    output: &Path,
+    config: &ImageProcessorConfig,
+    budget: u64,
+) -> Result<ProcessingStats> {
+    let start = Instant::now();
+    let config = ImageProcessorConfig {
+        max_output_size: None,
+        ..config.clone()
+    };
+
+    let mut qualities = vec![config.clone()];
+    match output_format_for(output, &config) {
+        OutputFormat::Jpeg | OutputFormat::Jxl => qualities.extend(
+            BUDGET_SSIM_STEPS
+                .iter()
+                .filter(|&&step| config.target_ssim.is_none_or(|target| step < target))
+                .map(|&step| ImageProcessorConfig {
+                    target_ssim: Some(step),
+                    ..config.clone()
+                }),
+        ),
+        OutputFormat::Png if !config.quantize && config.texture_type == TextureType::Color => {
+            qualities.push(ImageProcessorConfig {
+                quantize: true,
+                ..config.clone()
+            });
+        }
+        _ => {}
+    }
+
+    let (width, height) = get_image_dimensions(input).or_else(|_| open_image(input).map(|img| img.dimensions()))?;
+    let full_size = width.max(height);
+    let initial_size = config.max_size.map_or(full_size, |max| max.min(full_size));
+    let mut size = initial_size;
+    let mut smallest = u64::MAX;
+    loop {
+        for (step, quality) in qualities.iter().enumerate() {
+            let attempt = ImageProcessorConfig {
+                max_size: (size < full_size).then_some(size),
+                ..quality.clone()
+            };
+            let stats = process_image(input, output, &attempt)?;
+            if stats.output_size <= budget {
+                if step > 0 || size < initial_size {
+                    tracing::info!(
+                        "{}: lowered to {}px{} to fit the {} budget",
+                        input.display(),
+                        size,
+                        attempt.target_ssim.map(|t| format!(" at SSIM {}", t)).unwrap_or_default(),
+                        format_size(budget)
+                    );
+                }
+                return Ok(ProcessingStats {
+                    processing_time_ms: start.elapsed().as_millis() as u64,
+                    ..stats
+                });
+            }
+            smallest = smallest.min(stats.output_size);
+        }
+
+        if size / 2 < MIN_BUDGET_SIZE {
+            break;
+        }
+        size /= 2;
+    }
+
+    let _ = std::fs::remove_file(output);
+    anyhow::bail!(
+        "Output is over its {} budget even at {}px and the lowest quality (smallest {})",
+        format_size(budget),
+        size,
+        format_size(smallest)
+    )
+}
+
+/// Typical spacing (8-bit levels) between neighbouring palette colors along a gradient
+const PALETTE_DITHER_STEP: f32 = 8.0;
+
+/// Quantization step (8-bit levels) dithering is scaled to for JPEG
+const JPEG_DITHER_STEP: f32 = 4.0;
+
+/// Lowest JPEG quality a target SSIM search considers
+const MIN_JPEG_QUALITY: u8 = 10;
+
+/// Most low bits of color precision JPEG XL drops to reach a target SSIM
+const MAX_DROPPED_BITS: u32 = 3;
+
 /// Process JPEG
 fn process_jpeg(input: &Path, output: &Path, config: &ImageProcessorConfig) -> Result<()> {
-    let img = load_and_resize(input, config.max_size)?;
+    let img = load_and_resize(input, config)?;
 
-    let quality = match config.quality {
+    let preset_quality = match config.quality {
         QualityPreset::Fast => 70,
         QualityPreset::Balanced => 80,
         QualityPreset::High => 90,
         QualityPreset::Ultra => 95,
     };
 
-    // Use image crate for JPEG encoding
-    let mut output_file = std::fs::File::create(output)?;
-    let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut output_file, quality);
-    img.write_with_encoder(encoder)?;
+    // JPEG has no alpha channel, so quality is judged on color alone
+    let source = DynamicImage::ImageRgb8(img.to_rgb8()).to_rgba8();
+    let dithered = dither_for(config).map(|dither| dither_image(&source, dither, JPEG_DITHER_STEP));
+    let dithered_img = dithered.as_ref().map(|(d, _)| DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(d.clone()).to_rgb8()));
+    let encoded_img = dithered_img.as_ref().unwrap_or(&img);
 
+    let quality = match config.target_ssim {
+        Some(target) => jpeg_quality_for_ssim(input, &source, encoded_img, target)?,
+        None => preset_quality,
+    };
+    let data = encode_jpeg(encoded_img, quality)?;
+
+    // Both versions are decoded again to report what dithering bought
+    if let Some((_, coverage)) = dithered {
+        let plain = image::load_from_memory(&encode_jpeg(&img, quality)?)?.to_rgba8();
+        let decoded = image::load_from_memory(&data)?.to_rgba8();
+        log_dither(
+            input,
+            coverage,
+            Some((ImageQuality::measure(&source, &plain), ImageQuality::measure(&source, &decoded))),
+        );
+    }
+
+    std::fs::write(output, data)
+        .with_context(|| format!("Failed to write JPEG: {}", output.display()))?;
     Ok(())
 }
 
+fn encode_jpeg(img: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut data, quality);
+    img.write_with_encoder(encoder)?;
+    Ok(data)
+}
+
+/// Lowest JPEG quality whose encode of `img` reaches `target` SSIM against `source`
+///
+/// SSIM rises with quality, so this is a binary search of about seven encodes.
+fn jpeg_quality_for_ssim(input: &Path, source: &RgbaImage, img: &DynamicImage, target: f64) -> Result<u8> {
+    let ssim_at = |quality: u8| -> Result<f64> {
+        let decoded = image::load_from_memory(&encode_jpeg(img, quality)?)?.to_rgba8();
+        Ok(ssim(source, &decoded))
+    };
+
+    let best = ssim_at(100)?;
+    if best < target {
+        tracing::warn!("{}: SSIM {:.4} at JPEG quality 100 is below the target {}", input.display(), best, target);
+        return Ok(100);
+    }
+
+    let (mut low, mut high) = (MIN_JPEG_QUALITY, 100);
+    while low < high {
+        let mid = (low + high) / 2;
+        if ssim_at(mid)? >= target {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    tracing::debug!("{}: JPEG quality {} reaches SSIM {}", input.display(), high, target);
+    Ok(high)
+}
+
 /// Process WebP
 fn process_webp(input: &Path, output: &Path, config: &ImageProcessorConfig) -> Result<()> {
-    let img = load_and_resize(input, config.max_size)?;
+    let img = load_and_resize(input, config)?;
 
     // image crate supports WebP encoding
     img.save_with_format(output, ImageFormat::WebP)?;
@@ -156,12 +784,203 @@ fn process_webp(input: &Path, output: &Path, config: &ImageProcessorConfig) -> R
     Ok(())
 }
 
+/// Process JPEG XL
+///
+/// The encoder is lossless-only, so lower quality presets first drop low bits of
+/// color precision, which the lossless coder then stores more compactly. Returns the
+/// quality of the reduced pixels when bits were dropped.
+fn process_jxl(input: &Path, output: &Path, config: &ImageProcessorConfig) -> Result<Option<QualityMetrics>> {
+    let img = load_and_resize(input, config)?;
+    let (width, height) = img.dimensions();
+
+    let (mut dropped_bits, effort) = match config.quality {
+        QualityPreset::Fast => (2, 3),
+        QualityPreset::Balanced => (1, 5),
+        QualityPreset::High => (1, 7),
+        QualityPreset::Ultra => (0, 9),
+    };
+    // A target SSIM picks the most bits that can go instead
+    if let Some(target) = config.target_ssim {
+        dropped_bits = jxl_dropped_bits_for_ssim(input, &img.to_rgba8(), target);
+    }
+
+    let has_alpha = img.color().has_alpha();
+    let (mut pixels, colorspace, channels) = if has_alpha {
+        (img.to_rgba8().into_raw(), ColorSpace::RGBA, 4)
+    } else {
+        (img.to_rgb8().into_raw(), ColorSpace::RGB, 3)
+    };
+
+    // Dither offsets are added before rounding rather than to the 8-bit image, which
+    // would round most of them away at this step size
+    let mut offsets = None;
+    if let Some(dither) = dither_for(config).filter(|_| dropped_bits > 0) {
+        let source = img.to_rgba8();
+        let (dither_offsets, coverage) = dither_offsets(&source, dither, (1u32 << dropped_bits) as f32);
+        log_dither(
+            input,
+            coverage,
+            Some((
+                ImageQuality::measure(&source, &drop_low_bits(&source, None, dropped_bits)),
+                ImageQuality::measure(&source, &drop_low_bits(&source, Some(&dither_offsets), dropped_bits)),
+            )),
+        );
+        offsets = Some(dither_offsets);
+    }
+
+    if dropped_bits > 0 {
+        for (i, value) in pixels.iter_mut().enumerate() {
+            // Alpha edges are kept exact
+            if has_alpha && i % channels == 3 {
+                continue;
+            }
+            let offset = offsets.as_ref().map_or(0.0, |o| o[i / channels]);
+            *value = round_low_bits(*value, offset, dropped_bits);
+        }
+    }
+
+    let quality = if dropped_bits > 0 {
+        let reduced = if has_alpha {
+            RgbaImage::from_raw(width, height, pixels.clone()).map(DynamicImage::ImageRgba8)
+        } else {
+            image::RgbImage::from_raw(width, height, pixels.clone()).map(DynamicImage::ImageRgb8)
+        };
+        reduced.and_then(|reduced| measure_quality(&img.to_rgba8(), &reduced.to_rgba8()))
+    } else {
+        None
+    };
+
+    let options = EncoderOptions::new(width as usize, height as usize, colorspace, BitDepth::Eight)
+        .set_effort(effort);
+    let mut encoded = Vec::new();
+    JxlSimpleEncoder::new(&pixels, options)
+        .encode(&mut encoded)
+        .map_err(|e| anyhow::anyhow!("JPEG XL encoding failed: {:?}", e))?;
+
+    std::fs::write(output, encoded)
+        .with_context(|| format!("Failed to write JPEG XL: {}", output.display()))?;
+
+    Ok(quality)
+}
+
+/// Most low bits JPEG XL can drop from `source` while still reaching `target` SSIM
+///
+/// Alpha is kept exact by the encoder, so only color is reduced here as well.
+fn jxl_dropped_bits_for_ssim(input: &Path, source: &RgbaImage, target: f64) -> u32 {
+    let bits = (1..=MAX_DROPPED_BITS)
+        .rev()
+        .find(|&bits| ssim(source, &drop_low_bits(source, None, bits)) >= target)
+        .unwrap_or(0);
+    tracing::debug!("{}: JPEG XL drops {} bits to reach SSIM {}", input.display(), bits, target);
+    bits
+}
+
+/// Multiply each texel's color by its alpha, in the stored (sRGB) encoding
+pub fn premultiply_alpha(img: &mut RgbaImage) {
+    for pixel in img.pixels_mut() {
+        let alpha = pixel[3] as u16;
+        for c in 0..3 {
+            pixel[c] = ((pixel[c] as u16 * alpha + 127) / 255) as u8;
+        }
+    }
+}
+
+/// Round a channel value plus a dither offset to a multiple of `2^bits`
+fn round_low_bits(value: u8, offset: f32, bits: u32) -> u8 {
+    let half = (1u32 << (bits - 1)) as f32;
+    ((value as f32 + offset + half).clamp(0.0, 255.0) as u8) & (0xFFu8 << bits)
+}
+
+/// RGB of an image as the lossy JPEG XL path stores it
+fn drop_low_bits(img: &RgbaImage, offsets: Option<&[f32]>, bits: u32) -> RgbaImage {
+    let mut dropped = img.clone();
+    for (i, pixel) in dropped.pixels_mut().enumerate() {
+        let offset = offsets.map_or(0.0, |o| o[i]);
+        for c in 0..3 {
+            pixel[c] = round_low_bits(pixel[c], offset, bits);
+        }
+    }
+    dropped
+}
+
+/// Open an image upright, applying its EXIF orientation and converting it to sRGB when it
+/// embeds an ICC profile
+///
+/// TGA row and column order (origin flags) is handled by the decoder itself. Float images
+/// (HDR, EXR) are scene-linear and left alone.
+pub fn open_image(path: &Path) -> Result<DynamicImage> {
+    let (img, icc) = decode_image(path)?;
+    let Some(icc) = icc.filter(|_| !matches!(img.color(), image::ColorType::Rgb32F | image::ColorType::Rgba32F))
+    else {
+        return Ok(img);
+    };
+
+    match icc_to_srgb(&img, &icc) {
+        Some(converted) => {
+            tracing::debug!(
+                "{}: converted from ICC profile {} to sRGB",
+                path.display(),
+                icc_description(&icc).unwrap_or_else(|| "(unnamed)".to_string())
+            );
+            if is_16bit(&img) {
+                tracing::info!(
+                    "{}: ICC conversion reduced 16-bit color to 8 bits; mark data textures linear to keep it",
+                    path.display()
+                );
+            }
+            Ok(converted)
+        }
+        None => {
+            tracing::warn!("{}: unsupported ICC profile, treating pixels as sRGB", path.display());
+            Ok(img)
+        }
+    }
+}
+
+/// Open a data texture upright, ignoring any embedded ICC profile since its values aren't colors
+pub fn open_image_linear(path: &Path) -> Result<DynamicImage> {
+    decode_image(path).map(|(img, _)| img)
+}
+
+/// Embedded ICC profile of an image file, read without decoding the pixels
+pub fn image_icc_profile(path: &Path) -> Result<Option<Vec<u8>>> {
+    if is_psd_path(path) {
+        return psd_icc_profile(path);
+    }
+    let read = || -> image::ImageResult<Option<Vec<u8>>> {
+        ImageReader::open(path)?.with_guessed_format()?.into_decoder()?.icc_profile()
+    };
+    read().with_context(|| format!("Failed to open image: {}", path.display()))
+}
+
+fn decode_image(path: &Path) -> Result<(DynamicImage, Option<Vec<u8>>)> {
+    // PSDs are flattened to the merged image Photoshop stores with the layers
+    if is_psd_path(path) {
+        let (img, icc) = read_psd_composite(path)?;
+        return Ok((DynamicImage::ImageRgba8(img), icc));
+    }
+    let open = || -> image::ImageResult<(DynamicImage, Option<Vec<u8>>)> {
+        let mut decoder = ImageReader::open(path)?.with_guessed_format()?.into_decoder()?;
+        let orientation = decoder.orientation()?;
+        // A profile that fails to parse is no reason to reject the image
+        let icc = decoder.icc_profile().unwrap_or(None);
+        let mut img = DynamicImage::from_decoder(decoder)?;
+        img.apply_orientation(orientation);
+        Ok((img, icc))
+    };
+    open().with_context(|| format!("Failed to open image: {}", path.display()))
+}
+
 /// Load an image and optionally resize it
-fn load_and_resize(path: &Path, max_size: Option<u32>) -> Result<DynamicImage> {
-    let img = image::open(path)
-        .with_context(|| format!("Failed to open image: {}", path.display()))?;
+fn load_and_resize(path: &Path, config: &ImageProcessorConfig) -> Result<DynamicImage> {
+    if config.texture_type == TextureType::NormalMap {
+        let img = load_normal_map(path, config.max_size, config.renormalize)?;
+        return Ok(DynamicImage::ImageRgba8(img));
+    }
+
+    let img = if config.is_linear() { open_image_linear(path)? } else { open_image(path)? };
 
-    if let Some(max) = max_size {
+    if let Some(max) = config.max_size {
         let (width, height) = img.dimensions();
         if width > max || height > max {
             let ratio = max as f32 / width.max(height) as f32;
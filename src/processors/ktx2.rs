@@ -0,0 +1,796 @@
+use anyhow::{bail, Context, Result};
+
+use crate::cli::{AstcBlockSize, HdrEncoding};
+
+const KTX2_IDENTIFIER: [u8; 12] = [
+    0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n',
+];
+const KTX2_HEADER_SIZE: usize = 80;
+const KTX2_LEVEL_INDEX_SIZE: usize = 24;
+
+const SUPERCOMPRESSION_NONE: u32 = 0;
+const SUPERCOMPRESSION_BASIS_LZ: u32 = 1;
+const SUPERCOMPRESSION_ZSTD: u32 = 2;
+
+const DF_MODEL_RGBSDA: u8 = 1;
+const DF_MODEL_BC5: u8 = 132;
+const DF_MODEL_BC6H: u8 = 133;
+const DF_MODEL_ASTC: u8 = 162;
+#[cfg(feature = "native-textures")]
+const DF_MODEL_ETC1S: u8 = 163;
+#[cfg(feature = "native-textures")]
+const DF_MODEL_UASTC: u8 = 166;
+const DF_PRIMARIES_BT709: u8 = 1;
+const DF_TRANSFER_LINEAR: u8 = 1;
+const DF_TRANSFER_SRGB: u8 = 2;
+const DF_CHANNEL_ASTC_DATA: u8 = 0;
+#[cfg(feature = "native-textures")]
+const DF_CHANNEL_RGB: u8 = 0;
+#[cfg(feature = "native-textures")]
+const DF_CHANNEL_RGBA: u8 = 3;
+#[cfg(feature = "native-textures")]
+const DF_CHANNEL_AAA: u8 = 15;
+const DF_CHANNEL_R: u8 = 0;
+const DF_CHANNEL_G: u8 = 1;
+const DF_CHANNEL_B: u8 = 2;
+const DF_CHANNEL_A: u8 = 15;
+const DF_CHANNEL_BC6H_COLOR: u8 = 0;
+const DF_SAMPLE_SIGNED: u8 = 0x40;
+const DF_SAMPLE_FLOAT: u8 = 0x80;
+#[cfg(not(feature = "native-textures"))]
+const DF_SAMPLE_LINEAR: u8 = 0x10;
+
+const DF_FLAG_ALPHA_PREMULTIPLIED: u8 = 1;
+
+#[cfg(not(feature = "native-textures"))]
+const VK_FORMAT_R8G8B8A8_UNORM: u32 = 37;
+#[cfg(not(feature = "native-textures"))]
+const VK_FORMAT_R8G8B8A8_SRGB: u32 = 43;
+const VK_FORMAT_R16_UNORM: u32 = 70;
+const VK_FORMAT_R16G16B16A16_UNORM: u32 = 91;
+const VK_FORMAT_R16G16B16A16_SFLOAT: u32 = 97;
+const VK_FORMAT_BC5_UNORM_BLOCK: u32 = 141;
+const VK_FORMAT_BC6H_UFLOAT_BLOCK: u32 = 131;
+
+#[cfg(feature = "native-textures")]
+const BASIS_SIGNATURE: u64 = 0x4273;
+#[cfg(feature = "native-textures")]
+const BASIS_HEADER_SIZE: usize = 77;
+#[cfg(feature = "native-textures")]
+const BASIS_SLICE_DESC_SIZE: usize = 23;
+#[cfg(feature = "native-textures")]
+const BASIS_FORMAT_ETC1S: u64 = 0;
+#[cfg(feature = "native-textures")]
+const BASIS_FORMAT_UASTC: u64 = 1;
+#[cfg(feature = "native-textures")]
+const BASIS_FLAG_HAS_ALPHA_SLICES: u64 = 4;
+#[cfg(feature = "native-textures")]
+const BASIS_FLAG_SRGB: u64 = 16;
+#[cfg(feature = "native-textures")]
+const BASIS_SLICE_HAS_ALPHA: u64 = 1;
+#[cfg(feature = "native-textures")]
+const BASIS_TEX_TYPE_2D: u64 = 0;
+#[cfg(feature = "native-textures")]
+const BASIS_TEX_TYPE_2D_ARRAY: u64 = 1;
+
+/// One compressed slice of a .basis file
+#[derive(Debug, Clone, Copy)]
+#[cfg(feature = "native-textures")]
+struct BasisSlice {
+    image: usize,
+    level: usize,
+    alpha: bool,
+    width: u32,
+    height: u32,
+    offset: usize,
+    size: usize,
+}
+
+/// The parts of a .basis file needed to rebuild it as KTX2
+#[cfg(feature = "native-textures")]
+struct BasisFile<'a> {
+    data: &'a [u8],
+    uastc: bool,
+    srgb: bool,
+    has_alpha: bool,
+    images: usize,
+    slices: Vec<BasisSlice>,
+    endpoint_count: u16,
+    endpoints: &'a [u8],
+    selector_count: u16,
+    selectors: &'a [u8],
+    tables: &'a [u8],
+}
+
+#[cfg(feature = "native-textures")]
+impl<'a> BasisFile<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self> {
+        if data.len() < BASIS_HEADER_SIZE || read_le(data, 0, 2)? != BASIS_SIGNATURE {
+            bail!("Not a Basis Universal file");
+        }
+
+        let total_slices = read_le(data, 14, 3)? as usize;
+        let images = read_le(data, 17, 3)? as usize;
+        let format = read_le(data, 20, 1)?;
+        let flags = read_le(data, 21, 2)?;
+        let tex_type = read_le(data, 23, 1)?;
+        if tex_type != BASIS_TEX_TYPE_2D && tex_type != BASIS_TEX_TYPE_2D_ARRAY {
+            bail!(
+                "Unsupported Basis texture type {} for KTX2 output",
+                tex_type
+            );
+        }
+        let uastc = match format {
+            BASIS_FORMAT_ETC1S => false,
+            BASIS_FORMAT_UASTC => true,
+            other => bail!("Unsupported Basis texture format {}", other),
+        };
+
+        let section = |offset_at: usize, size_at: usize, size_len: usize| -> Result<&'a [u8]> {
+            let offset = read_le(data, offset_at, 4)? as usize;
+            let size = read_le(data, size_at, size_len)? as usize;
+            slice(data, offset, size)
+        };
+        let endpoints = section(41, 45, 3)?;
+        let selectors = section(50, 54, 3)?;
+        let tables = section(57, 61, 4)?;
+
+        let slice_descs = read_le(data, 65, 4)? as usize;
+        let mut slices = Vec::with_capacity(total_slices);
+        for i in 0..total_slices {
+            let desc = slice_descs + i * BASIS_SLICE_DESC_SIZE;
+            let basis_slice = BasisSlice {
+                image: read_le(data, desc, 3)? as usize,
+                level: read_le(data, desc + 3, 1)? as usize,
+                alpha: read_le(data, desc + 4, 1)? & BASIS_SLICE_HAS_ALPHA != 0,
+                width: read_le(data, desc + 5, 2)? as u32,
+                height: read_le(data, desc + 7, 2)? as u32,
+                offset: read_le(data, desc + 13, 4)? as usize,
+                size: read_le(data, desc + 17, 4)? as usize,
+            };
+            slice(data, basis_slice.offset, basis_slice.size)?;
+            slices.push(basis_slice);
+        }
+        if slices.is_empty() {
+            bail!("Basis file contains no slices");
+        }
+
+        // ETC1S stores alpha as separate slices; UASTC flags each slice that carries alpha
+        let has_alpha = if uastc {
+            slices.iter().any(|s| s.alpha)
+        } else {
+            flags & BASIS_FLAG_HAS_ALPHA_SLICES != 0
+        };
+
+        Ok(Self {
+            data,
+            uastc,
+            srgb: flags & BASIS_FLAG_SRGB != 0,
+            has_alpha,
+            images: images.max(1),
+            slices,
+            endpoint_count: read_le(data, 39, 2)? as u16,
+            endpoints,
+            selector_count: read_le(data, 48, 2)? as u16,
+            selectors,
+            tables,
+        })
+    }
+
+    fn slice_data(&self, slice: &BasisSlice) -> &'a [u8] {
+        &self.data[slice.offset..slice.offset + slice.size]
+    }
+}
+
+/// Rewrap a .basis file as a KTX2 container
+///
+/// UASTC levels are stored as-is, or Zstandard supercompressed when `zstd_level` is set;
+/// ETC1S always uses BasisLZ supercompression with the codebooks in the global data.
+/// `linear` marks the texture as linear data; the encoder flags every UASTC file as sRGB.
+#[cfg(feature = "native-textures")]
+pub fn basis_to_ktx2(basis: &[u8], linear: bool, zstd_level: Option<i32>) -> Result<Vec<u8>> {
+    let file = BasisFile::parse(basis)?;
+
+    let first = file
+        .slices
+        .iter()
+        .find(|s| s.image == 0 && s.level == 0)
+        .context("Basis file has no base level")?;
+    let (width, height) = (first.width, first.height);
+    let level_count = file
+        .slices
+        .iter()
+        .filter(|s| s.image == 0)
+        .map(|s| s.level + 1)
+        .max()
+        .unwrap_or(1);
+    let layers = file.images;
+
+    let scheme = match (file.uastc, zstd_level) {
+        (false, _) => SUPERCOMPRESSION_BASIS_LZ,
+        (true, Some(_)) => SUPERCOMPRESSION_ZSTD,
+        (true, None) => SUPERCOMPRESSION_NONE,
+    };
+
+    // Level data in layer order, plus each slice's offset within its level
+    let mut levels: Vec<Vec<u8>> = vec![Vec::new(); level_count];
+    let mut slice_offsets = Vec::with_capacity(file.slices.len());
+    for s in &file.slices {
+        let level = levels
+            .get_mut(s.level)
+            .context("Basis slice level out of range")?;
+        slice_offsets.push(level.len());
+        level.extend_from_slice(file.slice_data(s));
+    }
+
+    let global_data = (!file.uastc).then(|| etc1s_global_data(&file, &slice_offsets, level_count));
+
+    let compressed: Vec<Vec<u8>> = match zstd_level.filter(|_| scheme == SUPERCOMPRESSION_ZSTD) {
+        Some(level) => levels
+            .iter()
+            .map(|data| zstd::bulk::compress(data, level).context("Zstandard compression failed"))
+            .collect::<Result<_>>()?,
+        None => levels.clone(),
+    };
+
+    // (bit offset, bit length - 1, channel) per sample
+    let samples: Vec<(u16, u8, u8)> = match (file.uastc, file.has_alpha) {
+        (true, alpha) => vec![(
+            0,
+            127,
+            if alpha {
+                DF_CHANNEL_RGBA
+            } else {
+                DF_CHANNEL_RGB
+            },
+        )],
+        (false, false) => vec![(0, 63, DF_CHANNEL_RGB)],
+        (false, true) => vec![(0, 63, DF_CHANNEL_RGB), (64, 63, DF_CHANNEL_AAA)],
+    };
+    // bytesPlane0 is the block size, unless the data is supercompressed
+    let plane0 = if file.uastc && scheme == SUPERCOMPRESSION_NONE {
+        16
+    } else {
+        0
+    };
+    let model = if file.uastc {
+        DF_MODEL_UASTC
+    } else {
+        DF_MODEL_ETC1S
+    };
+    let dfd = data_format_descriptor(model, file.srgb && !linear, (4, 4), plane0, &samples);
+
+    let levels: Vec<(Vec<u8>, usize)> = compressed
+        .into_iter()
+        .zip(&levels)
+        .map(|(data, raw)| (data, if file.uastc { raw.len() } else { 0 }))
+        .collect();
+
+    Ok(write_container(
+        (0, 1), // vkFormat: VK_FORMAT_UNDEFINED for Basis payloads
+        (width, height, layers),
+        scheme,
+        &dfd,
+        global_data.as_deref(),
+        &levels,
+    ))
+}
+
+/// Wrap ASTC blocks (one buffer per mip level, largest first) in a KTX2 container
+pub fn astc_to_ktx2(
+    levels: &[Vec<u8>],
+    width: u32,
+    height: u32,
+    block: AstcBlockSize,
+    srgb: bool,
+    zstd_level: Option<i32>,
+) -> Result<Vec<u8>> {
+    let (block_width, block_height) = block.dimensions();
+    let scheme = if zstd_level.is_some() {
+        SUPERCOMPRESSION_ZSTD
+    } else {
+        SUPERCOMPRESSION_NONE
+    };
+    let plane0 = if scheme == SUPERCOMPRESSION_NONE {
+        16
+    } else {
+        0
+    };
+    let dfd = data_format_descriptor(
+        DF_MODEL_ASTC,
+        srgb,
+        (block_width, block_height),
+        plane0,
+        &[(0, 127, DF_CHANNEL_ASTC_DATA)],
+    );
+
+    Ok(write_container(
+        (block.vk_format(srgb), 1),
+        (width, height, 1),
+        scheme,
+        &dfd,
+        None,
+        &supercompress(levels, zstd_level)?,
+    ))
+}
+
+/// Wrap half-float RGBA texels or BC6H blocks (one buffer per mip level, largest first)
+/// in a KTX2 container
+pub fn hdr_to_ktx2(
+    levels: &[Vec<u8>],
+    width: u32,
+    height: u32,
+    encoding: HdrEncoding,
+    zstd_level: Option<i32>,
+) -> Result<Vec<u8>> {
+    let scheme = if zstd_level.is_some() {
+        SUPERCOMPRESSION_ZSTD
+    } else {
+        SUPERCOMPRESSION_NONE
+    };
+    let uncompressed = scheme == SUPERCOMPRESSION_NONE;
+    let half = DF_SAMPLE_FLOAT | DF_SAMPLE_SIGNED;
+
+    let (format, dfd) = match encoding {
+        HdrEncoding::Half => (
+            (VK_FORMAT_R16G16B16A16_SFLOAT, 2),
+            data_format_descriptor(
+                DF_MODEL_RGBSDA,
+                false,
+                (1, 1),
+                if uncompressed { 8 } else { 0 },
+                &[
+                    (0, 15, DF_CHANNEL_R | half),
+                    (16, 15, DF_CHANNEL_G | half),
+                    (32, 15, DF_CHANNEL_B | half),
+                    (48, 15, DF_CHANNEL_A | half),
+                ],
+            ),
+        ),
+        HdrEncoding::Bc6h => (
+            (VK_FORMAT_BC6H_UFLOAT_BLOCK, 1),
+            data_format_descriptor(
+                DF_MODEL_BC6H,
+                false,
+                (4, 4),
+                if uncompressed { 16 } else { 0 },
+                &[(0, 127, DF_CHANNEL_BC6H_COLOR | DF_SAMPLE_FLOAT)],
+            ),
+        ),
+        _ => bail!("{:?} is not a KTX2 HDR encoding", encoding),
+    };
+
+    Ok(write_container(
+        format,
+        (width, height, 1),
+        scheme,
+        &dfd,
+        None,
+        &supercompress(levels, zstd_level)?,
+    ))
+}
+
+/// Wrap RGBA8 texels (one buffer per mip level, largest first, holding every layer in
+/// turn) in a KTX2 container
+///
+/// Used in place of UASTC when the Basis Universal encoder is not built in.
+#[cfg(not(feature = "native-textures"))]
+pub fn rgba8_to_ktx2(
+    levels: &[Vec<u8>],
+    width: u32,
+    height: u32,
+    layers: usize,
+    linear: bool,
+    zstd_level: Option<i32>,
+) -> Result<Vec<u8>> {
+    let scheme = if zstd_level.is_some() {
+        SUPERCOMPRESSION_ZSTD
+    } else {
+        SUPERCOMPRESSION_NONE
+    };
+    let plane0 = if scheme == SUPERCOMPRESSION_NONE {
+        4
+    } else {
+        0
+    };
+    // Alpha is never sRGB-encoded
+    let alpha = if linear {
+        DF_CHANNEL_A
+    } else {
+        DF_CHANNEL_A | DF_SAMPLE_LINEAR
+    };
+    let dfd = data_format_descriptor(
+        DF_MODEL_RGBSDA,
+        !linear,
+        (1, 1),
+        plane0,
+        &[
+            (0, 7, DF_CHANNEL_R),
+            (8, 7, DF_CHANNEL_G),
+            (16, 7, DF_CHANNEL_B),
+            (24, 7, alpha),
+        ],
+    );
+    let format = if linear {
+        VK_FORMAT_R8G8B8A8_UNORM
+    } else {
+        VK_FORMAT_R8G8B8A8_SRGB
+    };
+
+    Ok(write_container(
+        (format, 1),
+        (width, height, layers),
+        scheme,
+        &dfd,
+        None,
+        &supercompress(levels, zstd_level)?,
+    ))
+}
+
+/// Wrap 16-bit linear texels (one buffer per mip level, largest first, little-endian) in a
+/// KTX2 container: R16 for one channel, RGBA16 for four
+pub fn unorm16_to_ktx2(
+    levels: &[Vec<u8>],
+    width: u32,
+    height: u32,
+    channels: usize,
+    zstd_level: Option<i32>,
+) -> Result<Vec<u8>> {
+    let scheme = if zstd_level.is_some() {
+        SUPERCOMPRESSION_ZSTD
+    } else {
+        SUPERCOMPRESSION_NONE
+    };
+    let uncompressed = scheme == SUPERCOMPRESSION_NONE;
+
+    let (format, samples) = match channels {
+        1 => (VK_FORMAT_R16_UNORM, vec![(0, 15, DF_CHANNEL_R)]),
+        4 => (
+            VK_FORMAT_R16G16B16A16_UNORM,
+            vec![
+                (0, 15, DF_CHANNEL_R),
+                (16, 15, DF_CHANNEL_G),
+                (32, 15, DF_CHANNEL_B),
+                (48, 15, DF_CHANNEL_A),
+            ],
+        ),
+        _ => bail!(
+            "16-bit KTX2 textures have 1 or 4 channels, not {}",
+            channels
+        ),
+    };
+    let plane0 = if uncompressed { 2 * channels as u8 } else { 0 };
+    let dfd = data_format_descriptor(DF_MODEL_RGBSDA, false, (1, 1), plane0, &samples);
+
+    Ok(write_container(
+        (format, 2),
+        (width, height, 1),
+        scheme,
+        &dfd,
+        None,
+        &supercompress(levels, zstd_level)?,
+    ))
+}
+
+/// Wrap BC5 blocks (one buffer per mip level, largest first) in a linear KTX2 container
+pub fn bc5_to_ktx2(
+    levels: &[Vec<u8>],
+    width: u32,
+    height: u32,
+    zstd_level: Option<i32>,
+) -> Result<Vec<u8>> {
+    let scheme = if zstd_level.is_some() {
+        SUPERCOMPRESSION_ZSTD
+    } else {
+        SUPERCOMPRESSION_NONE
+    };
+    let plane0 = if scheme == SUPERCOMPRESSION_NONE {
+        16
+    } else {
+        0
+    };
+    let dfd = data_format_descriptor(
+        DF_MODEL_BC5,
+        false,
+        (4, 4),
+        plane0,
+        &[(0, 63, DF_CHANNEL_R), (64, 63, DF_CHANNEL_G)],
+    );
+
+    Ok(write_container(
+        (VK_FORMAT_BC5_UNORM_BLOCK, 1),
+        (width, height, 1),
+        scheme,
+        &dfd,
+        None,
+        &supercompress(levels, zstd_level)?,
+    ))
+}
+
+/// Zstandard-compress each level when a level is given, keeping its uncompressed length
+fn supercompress(levels: &[Vec<u8>], zstd_level: Option<i32>) -> Result<Vec<(Vec<u8>, usize)>> {
+    levels
+        .iter()
+        .map(|data| {
+            let stored = match zstd_level {
+                Some(level) => {
+                    zstd::bulk::compress(data, level).context("Zstandard compression failed")?
+                }
+                None => data.clone(),
+            };
+            Ok((stored, data.len()))
+        })
+        .collect()
+}
+
+/// Flag a KTX2 file's color as premultiplied by alpha in its data format descriptor
+pub fn mark_ktx2_premultiplied(data: &mut [u8]) -> Result<()> {
+    if data.len() < KTX2_HEADER_SIZE || data[..12] != KTX2_IDENTIFIER {
+        bail!("Not a KTX2 file");
+    }
+    let dfd_offset = u32::from_le_bytes(data[48..52].try_into()?) as usize;
+    // totalSize, vendorId/descriptorType, versionNumber/descriptorBlockSize, model,
+    // primaries, transfer, then flags
+    let flags = data
+        .get_mut(dfd_offset + 15)
+        .context("Truncated KTX2 data format descriptor")?;
+    *flags |= DF_FLAG_ALPHA_PREMULTIPLIED;
+    Ok(())
+}
+
+/// Check that a KTX2 file's header, descriptor and levels are consistent, as a loader would
+///
+/// Zstandard levels are decompressed, and UASTC levels must hold one 16-byte block per 4x4
+/// texels. Returns the width, height and level count.
+pub fn validate_ktx2(data: &[u8]) -> Result<(u32, u32, usize)> {
+    if data.len() < KTX2_HEADER_SIZE || data[..12] != KTX2_IDENTIFIER {
+        bail!("Not a KTX2 file");
+    }
+    let field = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+    let (vk_format, width, height) = (field(12), field(20), field(24));
+    let layers = field(32).max(1) as usize;
+    let level_count = field(40).max(1) as usize;
+    let scheme = field(44);
+    let (dfd_offset, dfd_length) = (field(48) as usize, field(52) as usize);
+    if width == 0 {
+        bail!("KTX2 texture has zero width");
+    }
+
+    let dfd = data
+        .get(dfd_offset..dfd_offset + dfd_length)
+        .context("KTX2 data format descriptor lies outside the file")?;
+    if dfd.len() < 4 || u32::from_le_bytes(dfd[..4].try_into()?) as usize != dfd_length {
+        bail!("KTX2 data format descriptor size mismatch");
+    }
+
+    let index = data
+        .get(KTX2_HEADER_SIZE..KTX2_HEADER_SIZE + KTX2_LEVEL_INDEX_SIZE * level_count)
+        .context("Truncated KTX2 level index")?;
+    for (level, entry) in index.chunks_exact(KTX2_LEVEL_INDEX_SIZE).enumerate() {
+        let value =
+            |i: usize| u64::from_le_bytes(entry[i * 8..i * 8 + 8].try_into().unwrap()) as usize;
+        let (offset, length, uncompressed) = (value(0), value(1), value(2));
+        let stored = data
+            .get(offset..offset.saturating_add(length))
+            .with_context(|| format!("KTX2 level {} lies outside the file", level))?;
+        if length == 0 {
+            bail!("KTX2 level {} is empty", level);
+        }
+
+        let size = match scheme {
+            SUPERCOMPRESSION_ZSTD => zstd::bulk::decompress(stored, uncompressed)
+                .with_context(|| format!("KTX2 level {} fails to decompress", level))?
+                .len(),
+            _ => length,
+        };
+        if scheme != SUPERCOMPRESSION_BASIS_LZ && size != uncompressed {
+            bail!(
+                "KTX2 level {} is {} bytes, index says {}",
+                level,
+                size,
+                uncompressed
+            );
+        }
+        if vk_format == 0 && scheme != SUPERCOMPRESSION_BASIS_LZ {
+            let (w, h) = ((width >> level).max(1), (height >> level).max(1));
+            let expected = w.div_ceil(4) as usize * h.max(1).div_ceil(4) as usize * 16 * layers;
+            if size != expected {
+                bail!(
+                    "KTX2 UASTC level {} is {} bytes, expected {}",
+                    level,
+                    size,
+                    expected
+                );
+            }
+        }
+    }
+
+    Ok((width, height, level_count))
+}
+
+/// Lay out a KTX2 file: header, level index, DFD, key/value data, global data, then levels
+///
+/// `levels` holds each level's stored bytes and its uncompressed length, largest level first.
+fn write_container(
+    (vk_format, type_size): (u32, u32),
+    (width, height, layers): (u32, u32, usize),
+    scheme: u32,
+    dfd: &[u8],
+    global_data: Option<&[u8]>,
+    levels: &[(Vec<u8>, usize)],
+) -> Vec<u8> {
+    let level_count = levels.len();
+    let kvd = key_value_data();
+
+    let mut out = vec![0u8; KTX2_HEADER_SIZE + KTX2_LEVEL_INDEX_SIZE * level_count];
+    let dfd_offset = out.len();
+    out.extend_from_slice(dfd);
+    let kvd_offset = out.len();
+    out.extend_from_slice(&kvd);
+
+    let (sgd_offset, sgd_length) = match global_data {
+        Some(sgd) => {
+            align(&mut out, 8);
+            let offset = out.len();
+            out.extend_from_slice(sgd);
+            (offset, sgd.len())
+        }
+        None => (0, 0),
+    };
+
+    // Smallest level first, so a loader can stream and show low mips early
+    let mut level_index = vec![(0usize, 0usize, 0usize); level_count];
+    for level in (0..level_count).rev() {
+        // Uncompressed block data must be aligned to the 16-byte block size
+        if scheme == SUPERCOMPRESSION_NONE {
+            align(&mut out, 16);
+        }
+        let (data, uncompressed) = &levels[level];
+        level_index[level] = (out.len(), data.len(), *uncompressed);
+        out.extend_from_slice(data);
+    }
+
+    let mut header = Vec::with_capacity(KTX2_HEADER_SIZE);
+    header.extend_from_slice(&KTX2_IDENTIFIER);
+    for value in [
+        vk_format,
+        type_size,
+        width,
+        height,
+        0, // pixelDepth
+        if layers > 1 { layers as u32 } else { 0 },
+        1, // faceCount
+        level_count as u32,
+        scheme,
+        dfd_offset as u32,
+        dfd.len() as u32,
+        kvd_offset as u32,
+        kvd.len() as u32,
+    ] {
+        header.extend_from_slice(&value.to_le_bytes());
+    }
+    header.extend_from_slice(&(sgd_offset as u64).to_le_bytes());
+    header.extend_from_slice(&(sgd_length as u64).to_le_bytes());
+    for (offset, length, uncompressed) in level_index {
+        header.extend_from_slice(&(offset as u64).to_le_bytes());
+        header.extend_from_slice(&(length as u64).to_le_bytes());
+        header.extend_from_slice(&(uncompressed as u64).to_le_bytes());
+    }
+    out[..header.len()].copy_from_slice(&header);
+
+    out
+}
+
+/// BasisLZ global data: codebook sizes, per-image slice locations, then the codebooks
+#[cfg(feature = "native-textures")]
+fn etc1s_global_data(file: &BasisFile, slice_offsets: &[usize], level_count: usize) -> Vec<u8> {
+    // imageFlags, rgbSliceByteOffset, rgbSliceByteLength, alphaSliceByteOffset, alphaSliceByteLength
+    let mut images = vec![[0u32; 5]; level_count * file.images];
+    for (s, &offset) in file.slices.iter().zip(slice_offsets) {
+        let desc = &mut images[s.level * file.images + s.image];
+        let at = if s.alpha { 3 } else { 1 };
+        desc[at] = offset as u32;
+        desc[at + 1] = s.size as u32;
+    }
+
+    let mut sgd = Vec::new();
+    sgd.extend_from_slice(&file.endpoint_count.to_le_bytes());
+    sgd.extend_from_slice(&file.selector_count.to_le_bytes());
+    sgd.extend_from_slice(&(file.endpoints.len() as u32).to_le_bytes());
+    sgd.extend_from_slice(&(file.selectors.len() as u32).to_le_bytes());
+    sgd.extend_from_slice(&(file.tables.len() as u32).to_le_bytes());
+    sgd.extend_from_slice(&0u32.to_le_bytes()); // extendedByteLength
+    for desc in images {
+        for value in desc {
+            sgd.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    sgd.extend_from_slice(file.endpoints);
+    sgd.extend_from_slice(file.selectors);
+    sgd.extend_from_slice(file.tables);
+    sgd
+}
+
+/// Khronos basic data format descriptor; samples are (bit offset, bit length - 1, channel)
+fn data_format_descriptor(
+    model: u8,
+    srgb: bool,
+    (block_width, block_height): (u32, u32),
+    plane0: u8,
+    samples: &[(u16, u8, u8)],
+) -> Vec<u8> {
+    let block_size = 24 + 16 * samples.len();
+
+    let mut dfd = Vec::with_capacity(4 + block_size);
+    dfd.extend_from_slice(&((4 + block_size) as u32).to_le_bytes());
+    dfd.extend_from_slice(&0u32.to_le_bytes()); // vendorId, descriptorType
+    dfd.extend_from_slice(&2u16.to_le_bytes()); // versionNumber
+    dfd.extend_from_slice(&(block_size as u16).to_le_bytes());
+    dfd.push(model);
+    dfd.push(DF_PRIMARIES_BT709);
+    dfd.push(if srgb {
+        DF_TRANSFER_SRGB
+    } else {
+        DF_TRANSFER_LINEAR
+    });
+    dfd.push(0); // flags: straight alpha
+    dfd.extend_from_slice(&[block_width as u8 - 1, block_height as u8 - 1, 0, 0]);
+    dfd.extend_from_slice(&[plane0, 0, 0, 0, 0, 0, 0, 0]);
+
+    for &(bit_offset, bit_length, channel) in samples {
+        // Float samples are normalized to 0..1 (or -1..1 when signed)
+        let (lower, upper) = if channel & DF_SAMPLE_FLOAT != 0 {
+            let lower = if channel & DF_SAMPLE_SIGNED != 0 {
+                -1.0f32
+            } else {
+                0.0
+            };
+            (lower.to_bits(), 1.0f32.to_bits())
+        } else if bit_length < 31 {
+            (0, (1u32 << (bit_length + 1)) - 1)
+        } else {
+            (0, u32::MAX)
+        };
+        dfd.extend_from_slice(&bit_offset.to_le_bytes());
+        dfd.push(bit_length);
+        dfd.push(channel);
+        dfd.extend_from_slice(&[0; 4]); // samplePosition
+        dfd.extend_from_slice(&lower.to_le_bytes()); // sampleLower
+        dfd.extend_from_slice(&upper.to_le_bytes()); // sampleUpper
+    }
+    dfd
+}
+
+/// Key/value data identifying the writer
+fn key_value_data() -> Vec<u8> {
+    let key = b"KTXwriter\0";
+    let value = concat!("asset-forge ", env!("CARGO_PKG_VERSION"), "\0").as_bytes();
+
+    let mut kvd = Vec::new();
+    kvd.extend_from_slice(&((key.len() + value.len()) as u32).to_le_bytes());
+    kvd.extend_from_slice(key);
+    kvd.extend_from_slice(value);
+    align(&mut kvd, 4);
+    kvd
+}
+
+fn align(data: &mut Vec<u8>, alignment: usize) {
+    while !data.len().is_multiple_of(alignment) {
+        data.push(0);
+    }
+}
+
+#[cfg(feature = "native-textures")]
+fn slice(data: &[u8], offset: usize, size: usize) -> Result<&[u8]> {
+    data.get(offset..offset + size)
+        .context("Truncated Basis file")
+}
+
+#[cfg(feature = "native-textures")]
+fn read_le(data: &[u8], offset: usize, len: usize) -> Result<u64> {
+    let bytes = slice(data, offset, len)?;
+    Ok(bytes
+        .iter()
+        .rev()
+        .fold(0u64, |value, &b| (value << 8) | b as u64))
+}
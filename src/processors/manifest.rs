@@ -0,0 +1,100 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use super::hash_file;
+
+pub use crate::runtime::{BuildManifest, ManifestEntry, MANIFEST_FILE_NAME, MANIFEST_VERSION};
+
+impl BuildManifest {
+    /// Create a new empty manifest
+    pub fn new() -> Self {
+        Self {
+            version: MANIFEST_VERSION,
+            preset: None,
+            assets: Vec::new(),
+        }
+    }
+
+    /// Load the previous manifest from an output directory (empty if missing or outdated)
+    pub fn load(output_dir: &Path) -> Result<Self> {
+        let manifest_file = output_dir.join(MANIFEST_FILE_NAME);
+
+        if !manifest_file.exists() {
+            return Ok(Self::new());
+        }
+
+        let content = std::fs::read_to_string(&manifest_file)
+            .with_context(|| format!("Failed to read manifest: {}", manifest_file.display()))?;
+
+        let manifest: BuildManifest = serde_json::from_str(&content)
+            .with_context(|| "Failed to parse manifest")?;
+
+        if manifest.version != MANIFEST_VERSION {
+            tracing::info!("Manifest version mismatch, creating new manifest");
+            return Ok(Self::new());
+        }
+
+        Ok(manifest)
+    }
+
+    /// Save the manifest to an output directory
+    pub fn save(&self, output_dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(output_dir)?;
+
+        let manifest_file = output_dir.join(MANIFEST_FILE_NAME);
+        let content = serde_json::to_string_pretty(self)?;
+
+        std::fs::write(&manifest_file, content)
+            .with_context(|| format!("Failed to write manifest: {}", manifest_file.display()))?;
+
+        Ok(())
+    }
+
+    /// Record sizes and hashes missing from manifests written by older versions
+    ///
+    /// Hashes come from the outputs in `output_dir`; entries whose output is gone are left
+    /// as they are. Returns the outputs that were filled in.
+    pub fn fill_missing_hashes(&mut self, output_dir: &Path) -> Result<Vec<String>> {
+        let mut filled = Vec::new();
+        for entry in &mut self.assets {
+            if entry.hash.is_some() {
+                continue;
+            }
+            let path = output_dir.join(&entry.output);
+            if !path.is_file() {
+                continue;
+            }
+            entry.size = std::fs::metadata(&path)?.len();
+            entry.hash = Some(output_hash(&path)?);
+            filled.push(entry.output.clone());
+        }
+        Ok(filled)
+    }
+
+    /// Sort entries by source path for stable output
+    pub fn sort(&mut self) {
+        self.assets.sort_by(|a, b| a.source.cmp(&b.source));
+    }
+}
+
+/// Hash of an output file as recorded in the manifest
+pub fn output_hash(path: &Path) -> Result<String> {
+    Ok(format!("{:016x}", hash_file(path)?))
+}
+
+/// Manifest path with a content hash before the extension: `ui/logo.png` → `ui/logo.<hash>.png`
+pub fn fingerprint_path(output: &str, hash: &str) -> String {
+    let name_start = output.rfind('/').map_or(0, |slash| slash + 1);
+    match output[name_start..].rfind('.').filter(|&dot| dot > 0) {
+        Some(dot) => {
+            let (stem, extension) = output.split_at(name_start + dot);
+            format!("{}.{}{}", stem, hash, extension)
+        }
+        None => format!("{}.{}", output, hash),
+    }
+}
+
+/// Convert a relative path to the forward-slash form stored in the manifest
+pub fn manifest_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
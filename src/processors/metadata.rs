@@ -0,0 +1,80 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// PNG chunks holding metadata rather than pixels or color information
+const PNG_METADATA_CHUNKS: [&[u8; 4]; 5] = [b"tEXt", b"zTXt", b"iTXt", b"eXIf", b"tIME"];
+
+/// JPEG markers of EXIF/XMP (APP1), IPTC (APP13) and comment segments
+const JPEG_METADATA_MARKERS: [u8; 3] = [0xE1, 0xED, 0xFE];
+
+/// Bytes of metadata in a PNG, JPEG or WebP file: what stripping it saves
+///
+/// Counts text, EXIF and time chunks in PNG, EXIF/XMP, IPTC and comment segments in JPEG,
+/// and EXIF and XMP chunks in WebP. ICC profiles aren't counted, since they are converted on
+/// load anyway. Other files, and files that don't parse, count as 0.
+pub fn metadata_size(path: &Path) -> Result<u64> {
+    let data = std::fs::read(path).with_context(|| format!("Failed to read image: {}", path.display()))?;
+
+    let size = if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png_metadata_size(&data)
+    } else if data.starts_with(&[0xFF, 0xD8]) {
+        jpeg_metadata_size(&data)
+    } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        webp_metadata_size(&data)
+    } else {
+        0
+    };
+    Ok(size)
+}
+
+fn png_metadata_size(data: &[u8]) -> u64 {
+    let mut size = 0;
+    let mut pos = 8;
+    // Length, type, data and CRC
+    while pos + 12 <= data.len() {
+        let length = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]) as usize;
+        let kind = &data[pos + 4..pos + 8];
+        if PNG_METADATA_CHUNKS.iter().any(|chunk| chunk.as_slice() == kind) {
+            size += 12 + length as u64;
+        }
+        if kind == b"IEND" {
+            break;
+        }
+        pos += 12 + length;
+    }
+    size
+}
+
+fn jpeg_metadata_size(data: &[u8]) -> u64 {
+    let mut size = 0;
+    let mut pos = 2;
+    // Marker segments up to the start of scan, each with a length that includes itself
+    while pos + 4 <= data.len() && data[pos] == 0xFF {
+        let marker = data[pos + 1];
+        if marker == 0xDA || marker == 0xD9 {
+            break;
+        }
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        if JPEG_METADATA_MARKERS.contains(&marker) {
+            size += 2 + length as u64;
+        }
+        pos += 2 + length;
+    }
+    size
+}
+
+fn webp_metadata_size(data: &[u8]) -> u64 {
+    let mut size = 0;
+    let mut pos = 12;
+    // FourCC, little-endian size and data padded to an even length
+    while pos + 8 <= data.len() {
+        let kind = &data[pos..pos + 4];
+        let length = u32::from_le_bytes([data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]]) as usize;
+        let padded = length + length % 2;
+        if kind == b"EXIF" || kind == b"XMP " {
+            size += 8 + padded as u64;
+        }
+        pos += 8 + padded;
+    }
+    size
+}
@@ -0,0 +1,92 @@
+use image::RgbaImage;
+
+pub use crate::runtime::QualityMetrics;
+
+/// SSIM below which a texture is flagged as likely over-compressed
+pub const LOW_SSIM: f64 = 0.95;
+/// PSNR reported for identical images, so the value stays a finite JSON number
+const MAX_PSNR: f64 = 100.0;
+/// SSIM window size and the step between windows, in pixels
+const SSIM_WINDOW: u32 = 8;
+const SSIM_STEP: u32 = 4;
+
+/// PSNR and SSIM of an encode against the image it was encoded from, or `None` if their
+/// sizes differ
+pub fn measure_quality(source: &RgbaImage, encoded: &RgbaImage) -> Option<QualityMetrics> {
+    if source.dimensions() != encoded.dimensions() {
+        return None;
+    }
+    Some(QualityMetrics {
+        psnr: rgb_psnr(source, encoded).min(MAX_PSNR),
+        ssim: ssim(source, encoded),
+    })
+}
+
+/// PSNR in dB of the color channels over the pixels visible in the source (infinite when
+/// they are identical)
+pub fn rgb_psnr(source: &RgbaImage, encoded: &RgbaImage) -> f64 {
+    let mut squared_error = 0u64;
+    let mut samples = 0u64;
+    for (a, b) in source.pixels().zip(encoded.pixels()) {
+        if a[3] == 0 {
+            continue;
+        }
+        for c in 0..3 {
+            let diff = a[c] as i64 - b[c] as i64;
+            squared_error += (diff * diff) as u64;
+        }
+        samples += 3;
+    }
+    if squared_error == 0 {
+        return f64::INFINITY;
+    }
+    let mse = squared_error as f64 / samples as f64;
+    10.0 * (255.0f64 * 255.0 / mse).log10()
+}
+
+/// Mean SSIM of luma over 8x8 windows, with each image's colors premultiplied by its own
+/// alpha so hidden texels don't count but lost transparency does
+pub fn ssim(source: &RgbaImage, encoded: &RgbaImage) -> f64 {
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    let (width, height) = source.dimensions();
+    let (a, b) = (luma(source), luma(encoded));
+    let (window_w, window_h) = (SSIM_WINDOW.min(width), SSIM_WINDOW.min(height));
+    if window_w == 0 || window_h == 0 {
+        return 1.0;
+    }
+
+    let mut total = 0.0;
+    let mut windows = 0;
+    for y in (0..=height - window_h).step_by(SSIM_STEP as usize) {
+        for x in (0..=width - window_w).step_by(SSIM_STEP as usize) {
+            let texels = || {
+                (y..y + window_h).flat_map(move |wy| (x..x + window_w).map(move |wx| (wy * width + wx) as usize))
+            };
+            let n = (window_w * window_h) as f64;
+            let mean_a = texels().map(|i| a[i]).sum::<f64>() / n;
+            let mean_b = texels().map(|i| b[i]).sum::<f64>() / n;
+            let (mut var_a, mut var_b, mut covariance) = (0.0, 0.0, 0.0);
+            for i in texels() {
+                let (da, db) = (a[i] - mean_a, b[i] - mean_b);
+                var_a += da * da;
+                var_b += db * db;
+                covariance += da * db;
+            }
+            let (var_a, var_b, covariance) = (var_a / n, var_b / n, covariance / n);
+
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+            windows += 1;
+        }
+    }
+    total / windows as f64
+}
+
+/// Rec. 601 luma of each pixel, premultiplied by alpha
+fn luma(img: &RgbaImage) -> Vec<f64> {
+    img.pixels()
+        .map(|p| (0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64) * p[3] as f64 / 255.0)
+        .collect()
+}
@@ -0,0 +1,237 @@
+use image::{ImageBuffer, Rgba, RgbaImage};
+use rayon::prelude::*;
+use std::sync::OnceLock;
+
+use crate::cli::MipFilter;
+
+/// Kaiser window shape (NVTT's default)
+const KAISER_ALPHA: f32 = 4.0;
+
+/// Full mip chain of an image, largest first, down to 1x1
+///
+/// Each level is filtered from the previous one kept at float precision, so rounding
+/// doesn't build up. sRGB color is decoded to linear light before filtering and encoded
+/// again afterwards, which keeps bright details from darkening in small mips; alpha and
+/// linear data are filtered as stored.
+pub fn mip_chain(base: &RgbaImage, filter: MipFilter, srgb: bool) -> Vec<RgbaImage> {
+    let decode = srgb_to_linear_table();
+    let mut level: Vec<[f32; 4]> = base
+        .pixels()
+        .map(|p| {
+            let color = |c: u8| if srgb { decode[c as usize] } else { c as f32 / 255.0 };
+            [color(p[0]), color(p[1]), color(p[2]), p[3] as f32 / 255.0]
+        })
+        .collect();
+    let (mut width, mut height) = base.dimensions();
+
+    let mut chain = vec![base.clone()];
+    while width > 1 || height > 1 {
+        let (next_width, next_height) = ((width / 2).max(1), (height / 2).max(1));
+        level = downsample(&level, (width, height), (next_width, next_height), filter);
+        (width, height) = (next_width, next_height);
+
+        let mut mip = RgbaImage::new(width, height);
+        for (pixel, texel) in mip.pixels_mut().zip(&level) {
+            let color = |v: f32| if srgb { linear_to_srgb(v) } else { v };
+            pixel.0 = [
+                to_u8(color(texel[0])),
+                to_u8(color(texel[1])),
+                to_u8(color(texel[2])),
+                to_u8(texel[3]),
+            ];
+        }
+        chain.push(mip);
+    }
+    chain
+}
+
+/// Full mip chain of a 16-bit linear image, largest first, down to 1x1
+///
+/// Filtered like [`mip_chain`] on linear data, so heightmaps keep their precision.
+pub fn mip_chain_16(base: &ImageBuffer<Rgba<u16>, Vec<u16>>, filter: MipFilter) -> Vec<ImageBuffer<Rgba<u16>, Vec<u16>>> {
+    let mut level: Vec<[f32; 4]> = base.pixels().map(|p| p.0.map(|c| c as f32 / 65535.0)).collect();
+    let (mut width, mut height) = base.dimensions();
+
+    let mut chain = vec![base.clone()];
+    while width > 1 || height > 1 {
+        let (next_width, next_height) = ((width / 2).max(1), (height / 2).max(1));
+        level = downsample(&level, (width, height), (next_width, next_height), filter);
+        (width, height) = (next_width, next_height);
+
+        let mut mip = ImageBuffer::new(width, height);
+        for (pixel, texel) in mip.pixels_mut().zip(&level) {
+            *pixel = Rgba(texel.map(|v| (v.clamp(0.0, 1.0) * 65535.0).round() as u16));
+        }
+        chain.push(mip);
+    }
+    chain
+}
+
+/// Rescale alpha in every level after the first so the fraction of texels passing an alpha
+/// test at `threshold` (0-1) matches the base level
+///
+/// Filtering averages thin alpha-tested shapes (leaves, grass, fences) towards the
+/// threshold, so without this they thin out and vanish with distance. Each level's scale is
+/// found by bisection (Castaño's coverage-preserving mips).
+pub fn preserve_alpha_coverage(chain: &mut [RgbaImage], threshold: f32) {
+    let Some((base, mips)) = chain.split_first_mut() else {
+        return;
+    };
+    let cutoff = threshold.clamp(0.0, 1.0) * 255.0;
+    let target = alpha_coverage(base, cutoff, 1.0);
+
+    for mip in mips {
+        let (mut low, mut high) = (0.0f32, 4.0f32);
+        let mut scale = 1.0;
+        for _ in 0..16 {
+            scale = (low + high) / 2.0;
+            if alpha_coverage(mip, cutoff, scale) < target {
+                low = scale;
+            } else {
+                high = scale;
+            }
+        }
+        for pixel in mip.pixels_mut() {
+            pixel[3] = to_u8(pixel[3] as f32 * scale / 255.0);
+        }
+    }
+}
+
+/// Fraction of texels whose scaled alpha is above the cutoff (in 8-bit levels)
+fn alpha_coverage(img: &RgbaImage, cutoff: f32, scale: f32) -> f32 {
+    let passing = img.pixels().filter(|p| p[3] as f32 * scale > cutoff).count();
+    passing as f32 / (img.width() * img.height()).max(1) as f32
+}
+
+/// Separable resample of float texels, horizontal pass first
+fn downsample(src: &[[f32; 4]], (sw, sh): (u32, u32), (dw, dh): (u32, u32), filter: MipFilter) -> Vec<[f32; 4]> {
+    let (sw, sh, dw, dh) = (sw as usize, sh as usize, dw as usize, dh as usize);
+
+    let columns = taps(sw, dw, filter);
+    let mut horizontal = vec![[0f32; 4]; dw * sh];
+    horizontal.par_chunks_mut(dw).enumerate().for_each(|(y, row)| {
+        let source = &src[y * sw..(y + 1) * sw];
+        for (texel, taps) in row.iter_mut().zip(&columns) {
+            *texel = weighted_sum(taps.iter().map(|&(x, w)| (&source[x], w)));
+        }
+    });
+
+    let rows = taps(sh, dh, filter);
+    let mut vertical = vec![[0f32; 4]; dw * dh];
+    vertical.par_chunks_mut(dw).zip(&rows).for_each(|(row, taps)| {
+        for (x, texel) in row.iter_mut().enumerate() {
+            *texel = weighted_sum(taps.iter().map(|&(y, w)| (&horizontal[y * dw + x], w)));
+        }
+    });
+    vertical
+}
+
+fn weighted_sum<'a>(taps: impl Iterator<Item = (&'a [f32; 4], f32)>) -> [f32; 4] {
+    let mut sum = [0f32; 4];
+    for (texel, weight) in taps {
+        for c in 0..4 {
+            sum[c] += texel[c] * weight;
+        }
+    }
+    // Negative lobes of Lanczos and Kaiser can overshoot
+    sum.map(|v| v.clamp(0.0, 1.0))
+}
+
+/// Normalized source taps for each destination texel, edges clamped
+fn taps(src: usize, dst: usize, filter: MipFilter) -> Vec<Vec<(usize, f32)>> {
+    let scale = src as f32 / dst as f32;
+    let radius = filter_support(filter) * scale;
+    (0..dst)
+        .map(|i| {
+            let center = (i as f32 + 0.5) * scale;
+            let first = (center - radius).floor() as i64;
+            let last = (center + radius).ceil() as i64;
+            let mut taps: Vec<(usize, f32)> = (first..=last)
+                .filter_map(|s| {
+                    let weight = filter_weight(filter, (s as f32 + 0.5 - center) / scale);
+                    (weight != 0.0).then(|| (s.clamp(0, src as i64 - 1) as usize, weight))
+                })
+                .collect();
+            let total: f32 = taps.iter().map(|&(_, w)| w).sum();
+            for tap in &mut taps {
+                tap.1 /= total;
+            }
+            taps
+        })
+        .collect()
+}
+
+/// Filter radius in destination texels
+fn filter_support(filter: MipFilter) -> f32 {
+    match filter {
+        MipFilter::Box => 0.5,
+        MipFilter::Triangle => 1.0,
+        MipFilter::Lanczos | MipFilter::Kaiser => 3.0,
+    }
+}
+
+fn filter_weight(filter: MipFilter, x: f32) -> f32 {
+    let support = filter_support(filter);
+    if x.abs() >= support {
+        return 0.0;
+    }
+    match filter {
+        MipFilter::Box => 1.0,
+        MipFilter::Triangle => 1.0 - x.abs(),
+        MipFilter::Lanczos => sinc(x) * sinc(x / support),
+        MipFilter::Kaiser => {
+            let t = x / support;
+            sinc(x) * bessel_i0(KAISER_ALPHA * (1.0 - t * t).sqrt()) / bessel_i0(KAISER_ALPHA)
+        }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-6 {
+        1.0
+    } else {
+        let x = x * std::f32::consts::PI;
+        x.sin() / x
+    }
+}
+
+/// Modified Bessel function of the first kind, order zero (power series)
+fn bessel_i0(x: f32) -> f32 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half = x / 2.0;
+    for k in 1..32 {
+        term *= half / k as f32;
+        sum += term * term;
+        if term * term < sum * 1e-9 {
+            break;
+        }
+    }
+    sum
+}
+
+fn srgb_to_linear_table() -> &'static [f32; 256] {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        std::array::from_fn(|i| {
+            let v = i as f32 / 255.0;
+            if v <= 0.04045 {
+                v / 12.92
+            } else {
+                ((v + 0.055) / 1.055).powf(2.4)
+            }
+        })
+    })
+}
+
+fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn to_u8(v: f32) -> u8 {
+    (v.clamp(0.0, 1.0) * 255.0).round() as u8
+}
@@ -1,16 +1,108 @@
 mod image;
+mod quantize;
+mod dither;
+mod colorspace;
 mod atlas;
+mod atlas_export;
+mod rect_pack;
 mod basis;
+// Also built for tests so the fallback encoders are covered in default builds
+#[cfg(any(test, not(feature = "native-textures")))]
+mod bc;
+mod mipmap;
+mod dds;
+mod ktx2;
+mod astc;
+mod hdr;
+mod bit_depth;
+mod normal_map;
+mod channel_pack;
 mod audio;
+mod audio_channels;
+mod audio_stream;
+mod audio_levels;
+mod adpcm;
+mod ogg;
+mod opus;
+// Also built for tests so the fallback encoder is covered in default builds
+#[cfg(any(test, not(feature = "native-audio")))]
+mod vorbis;
+mod audio_audit;
+mod audio_loop;
+mod audio_sprite;
 mod model;
+mod glb;
+mod collada;
+mod collision;
 mod cache;
+mod manifest;
+mod report;
+mod thumbnail;
+mod nine_slice;
+mod trim;
+mod psd;
+mod metrics;
+mod metadata;
+mod colorblind;
+mod placeholder;
+mod animation;
+mod gif;
+mod tiles;
+mod texture_array;
+mod bundle;
+mod dedup;
+mod verify;
 
 pub use self::image::*;
+pub use quantize::*;
+pub use dither::*;
+pub use colorspace::*;
 pub use atlas::*;
+pub use atlas_export::*;
+pub use rect_pack::*;
 pub use basis::*;
+#[cfg(not(feature = "native-textures"))]
+pub use bc::*;
+pub use mipmap::*;
+pub use dds::*;
+pub use ktx2::*;
+pub use astc::*;
+pub use hdr::*;
+pub use bit_depth::*;
+pub use normal_map::*;
+pub use channel_pack::*;
 pub use audio::*;
+pub use audio_channels::*;
+pub use audio_levels::*;
+pub use adpcm::*;
+pub use opus::*;
+#[cfg(not(feature = "native-audio"))]
+pub use vorbis::*;
+pub use audio_audit::*;
+pub use audio_loop::*;
+pub use audio_sprite::*;
 pub use model::*;
+pub use glb::*;
+pub use collada::*;
+pub use collision::*;
 pub use cache::*;
+pub use manifest::*;
+pub use report::*;
+pub use thumbnail::*;
+pub use nine_slice::*;
+pub use trim::*;
+pub use psd::*;
+pub use metrics::*;
+pub use metadata::*;
+pub use colorblind::*;
+pub use placeholder::*;
+pub use animation::*;
+pub use gif::*;
+pub use tiles::*;
+pub use texture_array::*;
+pub use bundle::*;
+pub use dedup::*;
+pub use verify::*;
 
 use anyhow::Result;
 use std::path::Path;
@@ -21,6 +113,8 @@ pub struct ProcessingStats {
     pub original_size: u64,
     pub output_size: u64,
     pub processing_time_ms: u64,
+    /// PSNR and SSIM against the source, for lossy outputs whose pixels can be read back
+    pub quality: Option<QualityMetrics>,
 }
 
 impl ProcessingStats {
@@ -54,18 +148,31 @@ impl AssetType {
 
         match extension.as_deref() {
             // Images (including compressed texture formats)
-            Some("png" | "jpg" | "jpeg" | "webp" | "bmp" | "gif" | "tga" | "ktx2" | "basis") => {
+            Some(
+                "png" | "jpg" | "jpeg" | "webp" | "bmp" | "gif" | "tga" | "tif" | "tiff" | "hdr" | "exr"
+                | "ktx2" | "basis" | "astc" | "psd",
+            ) => {
                 AssetType::Image
             }
             // 3D Models
-            Some("gltf" | "glb" | "obj" | "fbx") => AssetType::Model,
+            Some("gltf" | "glb" | "obj" | "fbx" | "dae") => AssetType::Model,
             // Audio
-            Some("wav" | "mp3" | "ogg" | "flac" | "aac" | "m4a") => AssetType::Audio,
+            Some("wav" | "mp3" | "ogg" | "oga" | "opus" | "flac" | "aac" | "m4a") => AssetType::Audio,
             // Unknown
             _ => AssetType::Unknown,
         }
     }
 
+    /// Name recorded as the asset `type` in the build manifest
+    pub fn manifest_name(&self) -> Option<&'static str> {
+        match self {
+            AssetType::Image => Some("image"),
+            AssetType::Model => Some("model"),
+            AssetType::Audio => Some("audio"),
+            AssetType::Unknown => None,
+        }
+    }
+
     pub fn description(&self) -> &'static str {
         match self {
             AssetType::Image => "Image/Texture",
@@ -2,15 +2,18 @@ use anyhow::{Context, Result};
 use gltf::Gltf;
 use meshopt::{
     optimize::{optimize_vertex_cache, optimize_overdraw_in_place, optimize_vertex_fetch_remap},
-    simplify::{simplify, SimplifyOptions},
+    simplify::SimplifyOptions,
     encoding::{encode_vertex_buffer, encode_index_buffer},
-    remap::{remap_index_buffer, remap_vertex_buffer},
-    VertexDataAdapter,
+    remap::{generate_vertex_remap_multi, remap_index_buffer},
+    VertexDataAdapter, VertexStream,
 };
+use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::path::Path;
 use std::time::Instant;
 
-use super::ProcessingStats;
+use super::glb::{pad, COMPONENT_F32, COMPONENT_U16, COMPONENT_U32, TARGET_ARRAY_BUFFER, TARGET_ELEMENT_ARRAY_BUFFER};
+use super::{glb_bytes, ProcessingStats};
 
 /// Configuration for model processing
 #[derive(Debug, Clone)]
@@ -27,17 +30,38 @@ pub struct ModelConfig {
     pub lod_ratio: f32,
     /// Generate binary GLB output
     pub output_glb: bool,
+    /// Lock vertices displaced by morph targets during LOD simplification
+    pub lock_morph_vertices: bool,
+    /// Keep vertices on the topological mesh border in place (LODs won't tear open)
+    pub lock_border: bool,
+    /// Faster simplification for index buffers referencing a sparse subset of vertices
+    pub sparse: bool,
+    /// Weight of normal deviation in the simplification error (0 = ignore normals)
+    pub normal_weight: f32,
+    /// Weight of UV deviation in the simplification error (0 = ignore UVs)
+    pub uv_weight: f32,
+    /// Weld duplicate vertices before optimization
+    pub weld_vertices: bool,
+    /// Attribute values closer than this are considered equal when welding (0 = exact)
+    pub weld_epsilon: f32,
 }
 
 impl Default for ModelConfig {
     fn default() -> Self {
         Self {
             optimize_meshes: true,
-            encode_buffers: true,
+            encode_buffers: false,
             generate_lods: false,
             lod_count: 3,
             lod_ratio: 0.5,
             output_glb: true,
+            lock_morph_vertices: true,
+            lock_border: false,
+            sparse: false,
+            normal_weight: 0.0,
+            uv_weight: 0.0,
+            weld_vertices: true,
+            weld_epsilon: 0.0,
         }
     }
 }
@@ -52,6 +76,24 @@ pub struct ModelInfo {
     pub nodes: usize,
     pub total_vertices: usize,
     pub total_indices: usize,
+    /// Per-primitive breakdown, in document order
+    pub primitives: Vec<PrimitiveInfo>,
+}
+
+/// Geometry statistics for a single mesh primitive
+#[derive(Debug, Clone)]
+pub struct PrimitiveInfo {
+    pub mesh_index: usize,
+    pub mesh_name: Option<String>,
+    pub primitive_index: usize,
+    pub vertices: usize,
+    pub triangles: usize,
+    pub material: Option<String>,
+    /// Attribute semantics as named in glTF (POSITION, TEXCOORD_0, ...)
+    pub attributes: Vec<String>,
+    /// Index component type (u8/u16/u32), or None for non-indexed primitives
+    pub index_type: Option<&'static str>,
+    pub morph_targets: usize,
 }
 
 impl std::fmt::Display for ModelInfo {
@@ -73,15 +115,52 @@ pub fn get_model_info(path: &Path) -> Result<ModelInfo> {
 
     let mut total_vertices = 0;
     let mut total_indices = 0;
+    let mut primitives = Vec::new();
 
     for mesh in document.meshes() {
         for primitive in mesh.primitives() {
-            if let Some(accessor) = primitive.get(&gltf::Semantic::Positions) {
-                total_vertices += accessor.count();
-            }
+            let vertices = primitive
+                .get(&gltf::Semantic::Positions)
+                .map(|a| a.count())
+                .unwrap_or(0);
+            total_vertices += vertices;
             if let Some(indices) = primitive.indices() {
                 total_indices += indices.count();
             }
+
+            let element_count = primitive
+                .indices()
+                .map(|a| a.count())
+                .unwrap_or(vertices);
+            let triangles = match primitive.mode() {
+                gltf::mesh::Mode::Triangles => element_count / 3,
+                gltf::mesh::Mode::TriangleStrip | gltf::mesh::Mode::TriangleFan => {
+                    element_count.saturating_sub(2)
+                }
+                _ => 0,
+            };
+
+            let mut attributes: Vec<String> = primitive
+                .attributes()
+                .map(|(semantic, _)| semantic.to_string())
+                .collect();
+            attributes.sort();
+
+            primitives.push(PrimitiveInfo {
+                mesh_index: mesh.index(),
+                mesh_name: mesh.name().map(str::to_string),
+                primitive_index: primitive.index(),
+                vertices,
+                triangles,
+                material: primitive.material().name().map(str::to_string),
+                attributes,
+                index_type: primitive.indices().map(|a| match a.data_type() {
+                    gltf::accessor::DataType::U8 => "u8",
+                    gltf::accessor::DataType::U16 => "u16",
+                    _ => "u32",
+                }),
+                morph_targets: primitive.morph_targets().count(),
+            });
         }
     }
 
@@ -93,38 +172,205 @@ pub fn get_model_info(path: &Path) -> Result<ModelInfo> {
         nodes: document.nodes().count(),
         total_vertices,
         total_indices,
+        primitives,
     })
 }
 
+/// A single vertex attribute stream (one value of `components` floats per vertex)
+#[derive(Debug, Clone)]
+pub struct VertexAttribute {
+    pub semantic: gltf::Semantic,
+    pub components: usize,
+    pub data: Vec<f32>,
+}
+
+impl VertexAttribute {
+    /// Reorder this stream using a vertex remap table
+    fn remap(&self, remap: &[u32], new_vertex_count: usize) -> VertexAttribute {
+        let c = self.components;
+        let mut data = vec![0.0; new_vertex_count * c];
+
+        for (old, &new) in remap.iter().enumerate() {
+            if new == u32::MAX {
+                continue; // Unreferenced vertex
+            }
+            let new = new as usize;
+            data[new * c..(new + 1) * c].copy_from_slice(&self.data[old * c..(old + 1) * c]);
+        }
+
+        VertexAttribute {
+            semantic: self.semantic.clone(),
+            components: c,
+            data,
+        }
+    }
+
+}
+
+/// Morph target (blend shape) displacements, one xyz delta per vertex
+#[derive(Debug, Clone)]
+pub struct MorphTarget {
+    pub positions: Option<VertexAttribute>,
+    pub normals: Option<VertexAttribute>,
+    pub tangents: Option<VertexAttribute>,
+}
+
+impl MorphTarget {
+    fn remap(&self, remap: &[u32], new_vertex_count: usize) -> MorphTarget {
+        MorphTarget {
+            positions: self.positions.as_ref().map(|a| a.remap(remap, new_vertex_count)),
+            normals: self.normals.as_ref().map(|a| a.remap(remap, new_vertex_count)),
+            tangents: self.tangents.as_ref().map(|a| a.remap(remap, new_vertex_count)),
+        }
+    }
+}
+
 /// Mesh data extracted from glTF for optimization
 #[derive(Debug, Clone)]
 pub struct MeshData {
-    pub vertices: Vec<f32>,  // Interleaved position data (x, y, z per vertex)
+    /// Vertex streams keyed by semantic; positions are always first
+    pub attributes: Vec<VertexAttribute>,
+    pub morph_targets: Vec<MorphTarget>,
     pub indices: Vec<u32>,
     pub vertex_count: usize,
-    pub vertex_stride: usize, // Bytes per vertex
 }
 
-/// Optimized mesh result
+impl MeshData {
+    /// Get the stream for a semantic
+    pub fn attribute(&self, semantic: &gltf::Semantic) -> Option<&VertexAttribute> {
+        self.attributes.iter().find(|a| &a.semantic == semantic)
+    }
+
+    /// Vertex positions as xyz triples
+    pub fn positions(&self) -> Vec<[f32; 3]> {
+        self.attribute(&gltf::Semantic::Positions)
+            .map(|a| a.data.chunks(3).map(|c| [c[0], c[1], c[2]]).collect())
+            .unwrap_or_default()
+    }
+
+    /// Flag vertices that any morph target moves by more than `threshold`
+    pub fn morph_locks(&self, threshold: f32) -> Vec<bool> {
+        let mut locks = vec![false; self.vertex_count];
+        let threshold_sq = threshold * threshold;
+
+        for target in &self.morph_targets {
+            let Some(deltas) = &target.positions else { continue };
+            for (lock, d) in locks.iter_mut().zip(deltas.data.chunks(3)) {
+                if d[0] * d[0] + d[1] * d[1] + d[2] * d[2] > threshold_sq {
+                    *lock = true;
+                }
+            }
+        }
+
+        locks
+    }
+
+    /// Length of the bounding box diagonal
+    fn extent(&self) -> f32 {
+        let positions = self.positions();
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for p in &positions {
+            for i in 0..3 {
+                min[i] = min[i].min(p[i]);
+                max[i] = max[i].max(p[i]);
+            }
+        }
+        if positions.is_empty() {
+            return 0.0;
+        }
+        (0..3).map(|i| (max[i] - min[i]).powi(2)).sum::<f32>().sqrt()
+    }
+}
+
+/// Morph displacement (relative to the mesh extent) above which a vertex is locked
+///
+/// Smaller movements are left to the simplifier's attribute error instead, since on a
+/// face nearly every vertex moves a little and locking them all stops any reduction.
+const MORPH_LOCK_THRESHOLD: f32 = 0.05;
+
+/// Weight of morph target deltas (relative to the mesh extent) in the simplification error
+const MORPH_ATTRIBUTE_WEIGHT: f32 = 1.0;
+
+/// Attribute floats the simplifier accepts per vertex
+const MAX_SIMPLIFY_ATTRIBUTES: usize = 16;
+
+/// Achieved over target index count at which a LOD is reported as barely simplified
+const LOD_OVERSHOOT_WARNING: f32 = 2.0;
+
+/// Optimized mesh result, written over the source primitive's accessors
 #[derive(Debug, Clone)]
 pub struct OptimizedMesh {
-    pub vertices: Vec<f32>,
+    pub attributes: Vec<VertexAttribute>,
+    pub morph_targets: Vec<MorphTarget>,
     pub indices: Vec<u32>,
-    pub encoded_vertices: Option<Vec<u8>>,
-    pub encoded_indices: Option<Vec<u8>>,
+    /// Index buffers of LOD 1 and below, over the same vertex streams
+    pub lods: Vec<Vec<u32>>,
 }
 
-/// LOD mesh with simplification
+/// LOD mesh with simplification (vertex streams are shared with the source mesh)
 #[derive(Debug, Clone)]
 pub struct LodMesh {
     pub level: u32,
-    pub vertices: Vec<f32>,
     pub indices: Vec<u32>,
     pub vertex_count: usize,
     pub index_count: usize,
     pub target_error: f32,
 }
 
+/// Weld duplicate vertices (all streams and morph deltas equal within `epsilon`)
+///
+/// Exporters often emit fully unindexed meshes, which defeats the vertex cache
+/// and fetch optimizations, so this runs before them.
+pub fn weld_vertices(mesh: &MeshData, epsilon: f32) -> MeshData {
+    let quantize = |v: f32| {
+        let v = if epsilon > 0.0 { (v / epsilon).round() * epsilon } else { v };
+        v + 0.0 // Fold -0.0 into 0.0 so they compare equal
+    };
+
+    let streams: Vec<&VertexAttribute> = mesh.attributes
+        .iter()
+        .chain(mesh.morph_targets.iter().flat_map(|t| {
+            [&t.positions, &t.normals, &t.tangents].into_iter().flatten()
+        }))
+        .collect();
+
+    let keys: Vec<(usize, Vec<f32>)> = streams
+        .iter()
+        .map(|a| (a.components, a.data.iter().map(|&v| quantize(v)).collect()))
+        .collect();
+
+    let vertex_streams: Vec<VertexStream<'_>> = keys
+        .iter()
+        .filter_map(|(components, data)| {
+            let stride = components * std::mem::size_of::<f32>();
+            match components {
+                1 => Some(VertexStream::new_with_stride::<[f32; 1], f32>(data.as_ptr(), stride)),
+                2 => Some(VertexStream::new_with_stride::<[f32; 2], f32>(data.as_ptr(), stride)),
+                3 => Some(VertexStream::new_with_stride::<[f32; 3], f32>(data.as_ptr(), stride)),
+                4 => Some(VertexStream::new_with_stride::<[f32; 4], f32>(data.as_ptr(), stride)),
+                _ => None,
+            }
+        })
+        .collect();
+
+    let (unique_count, remap) =
+        generate_vertex_remap_multi(mesh.vertex_count, &vertex_streams, Some(&mesh.indices));
+
+    MeshData {
+        attributes: mesh.attributes
+            .iter()
+            .map(|a| a.remap(&remap, unique_count))
+            .collect(),
+        morph_targets: mesh.morph_targets
+            .iter()
+            .map(|t| t.remap(&remap, unique_count))
+            .collect(),
+        indices: remap_index_buffer(Some(&mesh.indices), mesh.vertex_count, &remap),
+        vertex_count: unique_count,
+    }
+}
+
 /// Optimize a mesh using meshoptimizer
 pub fn optimize_mesh(mesh: &MeshData, config: &ModelConfig) -> Result<OptimizedMesh> {
     let mut indices = mesh.indices.clone();
@@ -132,14 +378,11 @@ pub fn optimize_mesh(mesh: &MeshData, config: &ModelConfig) -> Result<OptimizedM
 
     if config.optimize_meshes && !indices.is_empty() {
         // Step 1: Optimize vertex cache (improves GPU vertex cache utilization)
-        optimize_vertex_cache(&mut indices, vertex_count);
+        indices = optimize_vertex_cache(&indices, vertex_count);
 
         // Step 2: Optimize overdraw (reduces pixel overdraw)
         // Create vertex adapter for position data
-        let positions: Vec<[f32; 3]> = mesh.vertices
-            .chunks(3)
-            .map(|chunk| [chunk[0], chunk[1], chunk[2]])
-            .collect();
+        let positions = mesh.positions();
 
         let vertex_adapter = VertexDataAdapter::new(
             bytemuck::cast_slice(&positions),
@@ -150,41 +393,39 @@ pub fn optimize_mesh(mesh: &MeshData, config: &ModelConfig) -> Result<OptimizedM
         optimize_overdraw_in_place(&mut indices, &vertex_adapter, 1.05);
 
         // Step 3: Optimize vertex fetch (improves memory access patterns)
-        // This reorders vertices, so we need to remap
+        // This reorders vertices, so every stream has to be remapped
         let remap = optimize_vertex_fetch_remap(&indices, vertex_count);
         let remapped_indices: Vec<u32> = remap_index_buffer(Some(&indices), vertex_count, &remap);
 
-        // Remap vertices
-        let remapped_positions: Vec<[f32; 3]> = remap_vertex_buffer(&positions, vertex_count, &remap);
-        let vertices: Vec<f32> = remapped_positions.iter()
-            .flat_map(|p| p.iter().copied())
+        let new_vertex_count = remap
+            .iter()
+            .filter(|&&r| r != u32::MAX)
+            .map(|&r| r as usize + 1)
+            .max()
+            .unwrap_or(0);
+
+        let attributes: Vec<VertexAttribute> = mesh.attributes
+            .iter()
+            .map(|a| a.remap(&remap, new_vertex_count))
             .collect();
 
-        let new_vertex_count = remapped_positions.len();
-
-        // Step 4: Encode buffers if requested
-        let (encoded_vertices, encoded_indices) = if config.encode_buffers {
-            // Convert positions to bytes for encoding
-            let positions_bytes: &[u8] = bytemuck::cast_slice(&remapped_positions);
-            let encoded_verts = encode_vertex_buffer(positions_bytes).ok();
-            let encoded_idx = encode_index_buffer(&remapped_indices, new_vertex_count).ok();
-            (encoded_verts, encoded_idx)
-        } else {
-            (None, None)
-        };
+        let morph_targets: Vec<MorphTarget> = mesh.morph_targets
+            .iter()
+            .map(|t| t.remap(&remap, new_vertex_count))
+            .collect();
 
         Ok(OptimizedMesh {
-            vertices,
+            attributes,
+            morph_targets,
             indices: remapped_indices,
-            encoded_vertices,
-            encoded_indices,
+            lods: Vec::new(),
         })
     } else {
         Ok(OptimizedMesh {
-            vertices: mesh.vertices.clone(),
+            attributes: mesh.attributes.clone(),
+            morph_targets: mesh.morph_targets.clone(),
             indices: mesh.indices.clone(),
-            encoded_vertices: None,
-            encoded_indices: None,
+            lods: Vec::new(),
         })
     }
 }
@@ -196,7 +437,6 @@ pub fn generate_lods(mesh: &MeshData, config: &ModelConfig) -> Result<Vec<LodMes
     // LOD 0 is the original mesh
     lods.push(LodMesh {
         level: 0,
-        vertices: mesh.vertices.clone(),
         indices: mesh.indices.clone(),
         vertex_count: mesh.vertex_count,
         index_count: mesh.indices.len(),
@@ -207,17 +447,35 @@ pub fn generate_lods(mesh: &MeshData, config: &ModelConfig) -> Result<Vec<LodMes
         return Ok(lods);
     }
 
-    // Create vertex adapter for simplification
-    let positions: Vec<[f32; 3]> = mesh.vertices
-        .chunks(3)
-        .map(|chunk| [chunk[0], chunk[1], chunk[2]])
-        .collect();
+    let positions = mesh.positions();
 
-    let vertex_adapter = VertexDataAdapter::new(
-        bytemuck::cast_slice(&positions),
-        std::mem::size_of::<[f32; 3]>(),
-        0,
-    ).map_err(|e| anyhow::anyhow!("Failed to create vertex adapter: {:?}", e))?;
+    let mut options = SimplifyOptions::None;
+    if config.lock_border {
+        options |= SimplifyOptions::LockBorder;
+    }
+    if config.sparse {
+        options |= SimplifyOptions::Sparse;
+    }
+
+    // Attribute-aware error keeps normal and UV seams, and the shapes morph targets
+    // deform into, from collapsing
+    let attributes = simplify_attributes(mesh, config);
+
+    // Every LOD shares the base index buffer with all morph targets, so collapsing
+    // vertices that blend shapes move far would pop when the target is applied.
+    // Lock those vertices so all targets stay consistent across LODs.
+    let locks = if config.lock_morph_vertices && !mesh.morph_targets.is_empty() {
+        let locks = mesh.morph_locks(mesh.extent() * MORPH_LOCK_THRESHOLD);
+        tracing::debug!(
+            "Locking {} of {} vertices moved by {} morph targets",
+            locks.iter().filter(|&&l| l).count(),
+            mesh.vertex_count,
+            mesh.morph_targets.len()
+        );
+        Some(locks)
+    } else {
+        None
+    };
 
     let mut current_indices = mesh.indices.clone();
     let mut current_target_count = mesh.indices.len();
@@ -230,22 +488,39 @@ pub fn generate_lods(mesh: &MeshData, config: &ModelConfig) -> Result<Vec<LodMes
         // Simplify the mesh
         let target_error = 0.01 * level as f32; // Increase error tolerance for lower LODs
 
-        let simplified = simplify(
+        let simplified = simplify_indices(
             &current_indices,
-            &vertex_adapter,
+            &positions,
+            attributes.as_ref(),
+            locks.as_deref(),
             current_target_count,
             target_error,
-            SimplifyOptions::None,
-            None,
+            options,
         );
 
         if simplified.is_empty() {
             break; // Can't simplify further
         }
 
+        tracing::debug!(
+            "LOD {}: {} -> {} indices (target {})",
+            level,
+            current_indices.len(),
+            simplified.len(),
+            current_target_count
+        );
+        if simplified.len() as f32 > current_target_count as f32 * LOD_OVERSHOOT_WARNING {
+            tracing::warn!(
+                "LOD {}: {} triangles, {:.1}x the target {}; locked vertices or the error limit stopped simplification",
+                level,
+                simplified.len() / 3,
+                simplified.len() as f32 / current_target_count as f32,
+                current_target_count / 3
+            );
+        }
+
         lods.push(LodMesh {
             level,
-            vertices: mesh.vertices.clone(), // Vertices are shared
             indices: simplified.clone(),
             vertex_count: mesh.vertex_count,
             index_count: simplified.len(),
@@ -263,6 +538,109 @@ pub fn generate_lods(mesh: &MeshData, config: &ModelConfig) -> Result<Vec<LodMes
     Ok(lods)
 }
 
+/// Interleaved per-vertex attributes and their weights for the simplifier
+struct SimplifyAttributes {
+    data: Vec<f32>,
+    weights: Vec<f32>,
+}
+
+/// Collect the weighted attribute streams (normals, first UV set, then morph target
+/// position deltas while they fit) for simplification
+fn simplify_attributes(mesh: &MeshData, config: &ModelConfig) -> Option<SimplifyAttributes> {
+    let mut streams = Vec::new();
+    if config.normal_weight > 0.0 {
+        if let Some(normals) = mesh.attribute(&gltf::Semantic::Normals) {
+            streams.push((normals, config.normal_weight));
+        }
+    }
+    if config.uv_weight > 0.0 {
+        if let Some(uvs) = mesh.attribute(&gltf::Semantic::TexCoords(0)) {
+            streams.push((uvs, config.uv_weight));
+        }
+    }
+    if config.lock_morph_vertices {
+        // Deltas are in model units, so their weight is scaled to the mesh size
+        let weight = MORPH_ATTRIBUTE_WEIGHT / mesh.extent().max(f32::EPSILON);
+        let mut used: usize = streams.iter().map(|(a, _)| a.components).sum();
+        let deltas = mesh.morph_targets.iter().filter_map(|t| t.positions.as_ref());
+        for deltas in deltas {
+            if used + deltas.components > MAX_SIMPLIFY_ATTRIBUTES {
+                tracing::debug!("Only the first morph targets weigh in the LOD error; the rest rely on locks");
+                break;
+            }
+            used += deltas.components;
+            streams.push((deltas, weight));
+        }
+    }
+
+    if streams.is_empty() {
+        return None;
+    }
+
+    let weights: Vec<f32> = streams
+        .iter()
+        .flat_map(|(a, w)| std::iter::repeat_n(*w, a.components))
+        .collect();
+
+    let mut data = Vec::with_capacity(mesh.vertex_count * weights.len());
+    for v in 0..mesh.vertex_count {
+        for (a, _) in &streams {
+            data.extend_from_slice(&a.data[v * a.components..(v + 1) * a.components]);
+        }
+    }
+
+    Some(SimplifyAttributes { data, weights })
+}
+
+/// Simplify an index buffer with optional attribute metric and vertex locks
+fn simplify_indices(
+    indices: &[u32],
+    positions: &[[f32; 3]],
+    attributes: Option<&SimplifyAttributes>,
+    locks: Option<&[bool]>,
+    target_count: usize,
+    target_error: f32,
+    options: SimplifyOptions,
+) -> Vec<u32> {
+    let mut result: Vec<u32> = vec![0; indices.len()];
+
+    let (attr_ptr, attr_stride, weight_ptr, attr_count) = match attributes {
+        Some(a) => (
+            a.data.as_ptr(),
+            a.weights.len() * std::mem::size_of::<f32>(),
+            a.weights.as_ptr(),
+            a.weights.len(),
+        ),
+        None => (std::ptr::null(), 0, std::ptr::null(), 0),
+    };
+    let lock_ptr = locks.map_or(std::ptr::null(), |l| l.as_ptr().cast::<u8>());
+
+    // SAFETY: every buffer holds `positions.len()` vertices with the stride passed,
+    // and `result` is large enough for any simplification of `indices`
+    let index_count = unsafe {
+        meshopt::ffi::meshopt_simplifyWithAttributes(
+            result.as_mut_ptr(),
+            indices.as_ptr(),
+            indices.len(),
+            positions.as_ptr().cast::<f32>(),
+            positions.len(),
+            std::mem::size_of::<[f32; 3]>(),
+            attr_ptr,
+            attr_stride,
+            weight_ptr,
+            attr_count,
+            lock_ptr,
+            target_count,
+            target_error,
+            options.bits(),
+            std::ptr::null_mut(),
+        )
+    };
+
+    result.truncate(index_count);
+    result
+}
+
 /// Process a glTF/GLB model with optimization
 pub fn process_model(
     input: &Path,
@@ -299,35 +677,64 @@ pub fn process_model(
 
     for mesh in document.meshes() {
         for primitive in mesh.primitives() {
-            if let Some(mesh_data) = extract_mesh_data(&primitive, &buffers)? {
+            // Strips, fans, lines and points are written as they are
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                continue;
+            }
+            if let Some(mut mesh_data) = extract_mesh_data(&primitive, &buffers)? {
                 total_original_indices += mesh_data.indices.len();
 
+                // Weld duplicate vertices first so later passes see an indexed mesh
+                if config.weld_vertices {
+                    let welded = weld_vertices(&mesh_data, config.weld_epsilon);
+                    tracing::debug!(
+                        "Welded vertices: {} -> {}",
+                        mesh_data.vertex_count,
+                        welded.vertex_count
+                    );
+                    mesh_data = welded;
+                }
+
                 // Optimize the mesh
-                let optimized = optimize_mesh(&mesh_data, config)?;
+                let mut optimized = optimize_mesh(&mesh_data, config)?;
                 total_optimized_indices += optimized.indices.len();
 
-                // Generate LODs if requested
+                // Generate LODs if requested, from the optimized vertex order so every
+                // level shares the written vertex streams
                 if config.generate_lods {
-                    let lods = generate_lods(&mesh_data, config)?;
+                    let base = MeshData {
+                        attributes: optimized.attributes.clone(),
+                        morph_targets: optimized.morph_targets.clone(),
+                        indices: optimized.indices.clone(),
+                        vertex_count: optimized.attributes.first().map_or(0, |a| a.data.len() / a.components.max(1)),
+                    };
+                    let lods = generate_lods(&base, config)?;
                     tracing::debug!(
                         "Generated {} LOD levels for mesh",
                         lods.len()
                     );
+                    optimized.lods = lods
+                        .into_iter()
+                        .skip(1)
+                        .map(|lod| {
+                            if config.optimize_meshes {
+                                optimize_vertex_cache(&lod.indices, base.vertex_count)
+                            } else {
+                                lod.indices
+                            }
+                        })
+                        .collect();
                 }
 
-                optimized_meshes.push(optimized);
+                optimized_meshes.push(((mesh.index(), primitive.index()), optimized));
             }
         }
     }
 
-    // For now, copy the original file
-    // Full GLB export with optimized data would require a GLB writer
-    // which is beyond the scope of the gltf crate (read-only)
-    std::fs::copy(input, output)?;
-
-    let output_size = std::fs::metadata(output)
-        .with_context(|| format!("Failed to read output file: {}", output.display()))?
-        .len();
+    let (glb, encoded_views) = optimized_glb(input, &buffers, &optimized_meshes, config.encode_buffers)?;
+    std::fs::write(output, &glb)
+        .with_context(|| format!("Failed to write GLB: {}", output.display()))?;
+    let output_size = glb.len() as u64;
 
     let processing_time_ms = start.elapsed().as_millis() as u64;
 
@@ -342,91 +749,523 @@ pub fn process_model(
     }
 
     // Log encoding stats
-    if config.encode_buffers {
-        let encoded_count = optimized_meshes.iter()
-            .filter(|m| m.encoded_vertices.is_some())
-            .count();
-        if encoded_count > 0 {
-            tracing::info!(
-                "Encoded {} mesh buffers with meshopt compression",
-                encoded_count
-            );
-        }
+    if encoded_views > 0 {
+        tracing::info!(
+            "Encoded {} mesh buffers with meshopt compression (EXT_meshopt_compression)",
+            encoded_views
+        );
     }
 
     Ok(ProcessingStats {
         original_size,
         output_size,
         processing_time_ms,
+        quality: None,
     })
 }
 
-/// Extract mesh data from a glTF primitive
-fn extract_mesh_data(
-    primitive: &gltf::Primitive,
+/// A new buffer view: tightly packed elements, meshopt-encoded on write when enabled
+struct StreamView {
+    data: Vec<u8>,
+    stride: usize,
+    count: usize,
+    target: u32,
+}
+
+impl StreamView {
+    /// EXT_meshopt_compression mode for this view
+    fn mode(&self) -> &'static str {
+        if self.target == TARGET_ELEMENT_ARRAY_BUFFER { "TRIANGLES" } else { "ATTRIBUTES" }
+    }
+
+    /// Meshopt encoding of the view, for strides the vertex codec accepts
+    fn encode(&self) -> Option<Vec<u8>> {
+        if self.target == TARGET_ELEMENT_ARRAY_BUFFER {
+            let indices: Vec<u32> = match self.stride {
+                2 => bytemuck::cast_slice::<u8, u16>(&self.data).iter().map(|&i| i as u32).collect(),
+                _ => bytemuck::cast_slice::<u8, u32>(&self.data).to_vec(),
+            };
+            let vertex_count = indices.iter().max().map_or(0, |&i| i as usize + 1);
+            return encode_index_buffer(&indices, vertex_count).ok();
+        }
+        match self.stride {
+            4 => encode_vertex_buffer::<[u8; 4]>(bytemuck::cast_slice(&self.data)).ok(),
+            8 => encode_vertex_buffer::<[u8; 8]>(bytemuck::cast_slice(&self.data)).ok(),
+            12 => encode_vertex_buffer::<[u8; 12]>(bytemuck::cast_slice(&self.data)).ok(),
+            16 => encode_vertex_buffer::<[u8; 16]>(bytemuck::cast_slice(&self.data)).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Accessor for a vertex stream, appending its data as a new view after the `first_view` existing ones
+fn stream_accessor(views: &mut Vec<StreamView>, first_view: usize, stream: &VertexAttribute) -> Value {
+    let count = stream.data.len() / stream.components.max(1);
+    // Joint indices have to stay integers
+    let (data, component_type, size) = if matches!(stream.semantic, gltf::Semantic::Joints(_)) {
+        let joints: Vec<u16> = stream.data.iter().map(|&j| j as u16).collect();
+        (bytemuck::cast_slice(&joints).to_vec(), COMPONENT_U16, 2)
+    } else {
+        (bytemuck::cast_slice(&stream.data).to_vec(), COMPONENT_F32, 4)
+    };
+    views.push(StreamView { data, stride: stream.components * size, count, target: TARGET_ARRAY_BUFFER });
+
+    let kind = match stream.components {
+        1 => "SCALAR",
+        2 => "VEC2",
+        3 => "VEC3",
+        _ => "VEC4",
+    };
+    let mut accessor = json!({
+        "bufferView": first_view + views.len() - 1,
+        "componentType": component_type,
+        "count": count,
+        "type": kind,
+    });
+    // Positions, including morph target deltas, must declare their bounds
+    if stream.semantic == gltf::Semantic::Positions && count > 0 {
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for p in stream.data.chunks(3) {
+            for i in 0..3 {
+                min[i] = min[i].min(p[i]);
+                max[i] = max[i].max(p[i]);
+            }
+        }
+        accessor["min"] = json!(min);
+        accessor["max"] = json!(max);
+    }
+    accessor
+}
+
+/// Accessor for a triangle index buffer, 16-bit when the vertex count allows
+fn index_accessor(views: &mut Vec<StreamView>, first_view: usize, indices: &[u32]) -> Value {
+    let narrow = indices.iter().all(|&i| i <= u16::MAX as u32);
+    let (data, component_type, stride) = if narrow {
+        let narrow: Vec<u16> = indices.iter().map(|&i| i as u16).collect();
+        (bytemuck::cast_slice(&narrow).to_vec(), COMPONENT_U16, 2)
+    } else {
+        (bytemuck::cast_slice(indices).to_vec(), COMPONENT_U32, 4)
+    };
+    views.push(StreamView { data, stride, count: indices.len(), target: TARGET_ELEMENT_ARRAY_BUFFER });
+    json!({
+        "bufferView": first_view + views.len() - 1,
+        "componentType": component_type,
+        "count": indices.len(),
+        "type": "SCALAR",
+    })
+}
+
+/// Accessors referenced by each slot that can hold one: primitive attributes, indices and
+/// morph targets, skins, animation samplers and GPU instancing
+fn accessor_uses(root: &Value) -> HashMap<u64, usize> {
+    let mut uses = HashMap::new();
+    let mut count = |value: &Value| {
+        if let Some(index) = value.as_u64() {
+            *uses.entry(index).or_insert(0) += 1;
+        }
+    };
+    let array = |value: &Value| value.as_array().cloned().unwrap_or_default();
+    let values = |value: &Value| -> Vec<Value> { value.as_object().map(|o| o.values().cloned().collect()).unwrap_or_default() };
+
+    for mesh in array(&root["meshes"]) {
+        for primitive in array(&mesh["primitives"]) {
+            values(&primitive["attributes"]).iter().for_each(&mut count);
+            count(&primitive["indices"]);
+            for target in array(&primitive["targets"]) {
+                values(&target).iter().for_each(&mut count);
+            }
+        }
+    }
+    for skin in array(&root["skins"]) {
+        count(&skin["inverseBindMatrices"]);
+    }
+    for animation in array(&root["animations"]) {
+        for sampler in array(&animation["samplers"]) {
+            count(&sampler["input"]);
+            count(&sampler["output"]);
+        }
+    }
+    for node in array(&root["nodes"]) {
+        values(&node["extensions"]["EXT_mesh_gpu_instancing"]["attributes"]).iter().for_each(&mut count);
+    }
+    uses
+}
+
+/// Call `f` with every `bufferView` index in the document, at any depth
+fn visit_view_refs(value: &mut Value, f: &mut impl FnMut(&mut Value)) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map.iter_mut() {
+                if key == "bufferView" && child.is_u64() {
+                    f(child);
+                } else {
+                    visit_view_refs(child, f);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| visit_view_refs(item, f)),
+        _ => {}
+    }
+}
+
+/// The glTF JSON of a .gltf or .glb file, with everything the gltf crate would drop
+fn source_json(input: &Path) -> Result<Value> {
+    let bytes = std::fs::read(input)
+        .with_context(|| format!("Failed to read input file: {}", input.display()))?;
+    let json = if bytes.starts_with(b"glTF") {
+        gltf::Glb::from_slice(&bytes)
+            .with_context(|| format!("Failed to parse GLB: {}", input.display()))?
+            .json
+            .into_owned()
+    } else {
+        bytes
+    };
+    serde_json::from_slice(&json).with_context(|| format!("Failed to parse glTF JSON: {}", input.display()))
+}
+
+/// The source document as GLB bytes, with each optimized primitive's streams in place of
+/// its accessors and every buffer merged into the binary chunk
+///
+/// Accessors only a replaced slot used are overwritten in place so no other index
+/// shifts; shared ones get a new accessor. Buffer views nothing refers to any more are
+/// dropped. LOD levels are added as MSFT_lod alternatives of the nodes using each mesh.
+/// With `encode`, the new views are meshopt-encoded under
+/// EXT_meshopt_compression, which then becomes a required extension. Returns the GLB and
+/// the number of encoded views.
+fn optimized_glb(
+    input: &Path,
     buffers: &[gltf::buffer::Data],
-) -> Result<Option<MeshData>> {
-    // Get positions
-    let positions_accessor = match primitive.get(&gltf::Semantic::Positions) {
-        Some(acc) => acc,
-        None => return Ok(None),
+    meshes: &[((usize, usize), OptimizedMesh)],
+    encode: bool,
+) -> Result<(Vec<u8>, usize)> {
+    let mut root = source_json(input)?;
+    let old_views = root["bufferViews"].as_array().cloned().unwrap_or_default();
+    let mut accessors = root["accessors"].as_array().cloned().unwrap_or_default();
+    let uses = accessor_uses(&root);
+    let mut views = Vec::new();
+
+    // Overwrite an accessor when the slot was its only user, keeping its name
+    let place = |accessors: &mut Vec<Value>, old: Option<u64>, mut accessor: Value| -> usize {
+        match old.filter(|index| uses.get(index) == Some(&1) && (*index as usize) < accessors.len()) {
+            Some(index) => {
+                let index = index as usize;
+                if let Some(name) = accessors[index].get("name").cloned() {
+                    accessor["name"] = name;
+                }
+                accessors[index] = accessor;
+                index
+            }
+            None => {
+                accessors.push(accessor);
+                accessors.len() - 1
+            }
+        }
     };
 
-    let positions_view = positions_accessor.view()
-        .ok_or_else(|| anyhow::anyhow!("Position accessor has no buffer view"))?;
-    let positions_buffer = &buffers[positions_view.buffer().index()];
+    for ((mesh, primitive), optimized) in meshes {
+        let slot = &mut root["meshes"][*mesh]["primitives"][*primitive];
+
+        // Attributes the extraction skipped no longer match the vertex order, so they go
+        let mut attributes = serde_json::Map::new();
+        for stream in &optimized.attributes {
+            let name = stream.semantic.to_string();
+            let accessor = stream_accessor(&mut views, old_views.len(), stream);
+            let index = place(&mut accessors, slot["attributes"][&name].as_u64(), accessor);
+            attributes.insert(name, json!(index));
+        }
+        slot["attributes"] = Value::Object(attributes);
+
+        let accessor = index_accessor(&mut views, old_views.len(), &optimized.indices);
+        slot["indices"] = json!(place(&mut accessors, slot["indices"].as_u64(), accessor));
+
+        if !optimized.morph_targets.is_empty() {
+            let mut targets = Vec::new();
+            for (i, target) in optimized.morph_targets.iter().enumerate() {
+                let mut deltas = serde_json::Map::new();
+                for (name, stream) in [("POSITION", &target.positions), ("NORMAL", &target.normals), ("TANGENT", &target.tangents)] {
+                    let Some(stream) = stream else { continue };
+                    let accessor = stream_accessor(&mut views, old_views.len(), stream);
+                    let index = place(&mut accessors, slot["targets"][i][name].as_u64(), accessor);
+                    deltas.insert(name.to_string(), json!(index));
+                }
+                targets.push(Value::Object(deltas));
+            }
+            slot["targets"] = json!(targets);
+        }
+    }
+    let lod_meshes = append_lod_meshes(&mut root, &mut accessors, &mut views, old_views.len(), meshes);
+    if !accessors.is_empty() {
+        root["accessors"] = json!(accessors);
+    }
 
-    let positions_offset = positions_view.offset() + positions_accessor.offset();
-    let positions_len = positions_accessor.count() * 3 * 4; // 3 floats * 4 bytes
+    // Keep only the views something still refers to, in their original order
+    let mut used = vec![false; old_views.len() + views.len()];
+    visit_view_refs(&mut root, &mut |view| {
+        if let Some(flag) = view.as_u64().and_then(|v| used.get_mut(v as usize)) {
+            *flag = true;
+        }
+    });
 
-    let positions_data = &positions_buffer[positions_offset..positions_offset + positions_len];
-    let positions: Vec<f32> = positions_data
-        .chunks(4)
-        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-        .collect();
+    let mut bin = Vec::new();
+    let mut fallback_len = 0;
+    let mut encoded = 0;
+    let mut remap = vec![0; used.len()];
+    let mut written = Vec::new();
+    for (index, _) in used.iter().enumerate().filter(|(_, used)| **used) {
+        pad(&mut bin, 0);
+        let view = match index.checked_sub(old_views.len()).map(|i| &views[i]) {
+            None => {
+                let mut view = old_views[index].clone();
+                let buffer = view["buffer"].as_u64().unwrap_or(0) as usize;
+                let offset = view["byteOffset"].as_u64().unwrap_or(0) as usize;
+                let length = view["byteLength"].as_u64().unwrap_or(0) as usize;
+                let data = buffers
+                    .get(buffer)
+                    .and_then(|data| data.get(offset..offset + length))
+                    .with_context(|| format!("Buffer view {} is out of range of buffer {}", index, buffer))?;
+                view["buffer"] = json!(0);
+                view["byteOffset"] = json!(bin.len());
+                bin.extend_from_slice(data);
+                view
+            }
+            Some(stream) => match stream.encode().filter(|_| encode) {
+                Some(data) => {
+                    // The view itself points into a fallback buffer that holds no data
+                    let view = json!({
+                        "buffer": 1,
+                        "byteOffset": fallback_len,
+                        "byteLength": stream.data.len(),
+                        "target": stream.target,
+                        "extensions": { "EXT_meshopt_compression": {
+                            "buffer": 0,
+                            "byteOffset": bin.len(),
+                            "byteLength": data.len(),
+                            "byteStride": stream.stride,
+                            "count": stream.count,
+                            "mode": stream.mode(),
+                        }},
+                    });
+                    fallback_len += stream.data.len().next_multiple_of(4);
+                    bin.extend_from_slice(&data);
+                    encoded += 1;
+                    view
+                }
+                None => {
+                    let view = json!({
+                        "buffer": 0,
+                        "byteOffset": bin.len(),
+                        "byteLength": stream.data.len(),
+                        "target": stream.target,
+                    });
+                    bin.extend_from_slice(&stream.data);
+                    view
+                }
+            },
+        };
+        remap[index] = written.len();
+        written.push(view);
+    }
+    visit_view_refs(&mut root, &mut |view| {
+        if let Some(index) = view.as_u64() {
+            *view = json!(remap[index as usize]);
+        }
+    });
+    pad(&mut bin, 0);
+
+    let root_map = root.as_object_mut().context("glTF document is not an object")?;
+    root_map.remove("bufferViews");
+    root_map.remove("buffers");
+    if lod_meshes {
+        // Loaders without MSFT_lod still show the full-detail nodes
+        add_extension(root_map, "MSFT_lod", false);
+    }
+    if !written.is_empty() {
+        let mut buffers = vec![json!({ "byteLength": bin.len() })];
+        if encoded > 0 {
+            buffers.push(json!({
+                "byteLength": fallback_len,
+                "extensions": { "EXT_meshopt_compression": { "fallback": true } },
+            }));
+            add_extension(root_map, "EXT_meshopt_compression", true);
+        }
+        root_map.insert("buffers".to_string(), json!(buffers));
+        root_map.insert("bufferViews".to_string(), json!(written));
+    }
+
+    Ok((glb_bytes(&root, bin), encoded))
+}
 
-    // Get indices
-    let indices = if let Some(indices_accessor) = primitive.indices() {
-        let indices_view = indices_accessor.view()
-            .ok_or_else(|| anyhow::anyhow!("Index accessor has no buffer view"))?;
-        let indices_buffer = &buffers[indices_view.buffer().index()];
-
-        let indices_offset = indices_view.offset() + indices_accessor.offset();
-
-        match indices_accessor.data_type() {
-            gltf::accessor::DataType::U16 => {
-                let indices_len = indices_accessor.count() * 2;
-                let indices_data = &indices_buffer[indices_offset..indices_offset + indices_len];
-                indices_data
-                    .chunks(2)
-                    .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]) as u32)
-                    .collect()
+/// Append each LOD level of the optimized meshes as a copy of its mesh with the simplified
+/// index buffers, and list nodes of those copies under every node using the mesh (MSFT_lod)
+///
+/// A primitive with fewer levels than its mesh repeats its last one. Returns whether any
+/// LOD was added.
+fn append_lod_meshes(
+    root: &mut Value,
+    accessors: &mut Vec<Value>,
+    views: &mut Vec<StreamView>,
+    first_view: usize,
+    meshes: &[((usize, usize), OptimizedMesh)],
+) -> bool {
+    let mut lod_meshes: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mesh_count = root["meshes"].as_array().map_or(0, Vec::len);
+    for mesh in 0..mesh_count {
+        let primitives: Vec<_> = meshes
+            .iter()
+            .filter(|((m, _), optimized)| *m == mesh && !optimized.lods.is_empty())
+            .collect();
+        let levels = primitives.iter().map(|(_, optimized)| optimized.lods.len()).max().unwrap_or(0);
+        let mut last_accessor: HashMap<usize, usize> = HashMap::new();
+        for level in 0..levels {
+            let mut lod = root["meshes"][mesh].clone();
+            for ((_, primitive), optimized) in &primitives {
+                let accessor = match optimized.lods.get(level) {
+                    Some(indices) => {
+                        accessors.push(index_accessor(views, first_view, indices));
+                        accessors.len() - 1
+                    }
+                    None => last_accessor[primitive],
+                };
+                last_accessor.insert(*primitive, accessor);
+                lod["primitives"][*primitive]["indices"] = json!(accessor);
             }
-            gltf::accessor::DataType::U32 => {
-                let indices_len = indices_accessor.count() * 4;
-                let indices_data = &indices_buffer[indices_offset..indices_offset + indices_len];
-                indices_data
-                    .chunks(4)
-                    .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-                    .collect()
+            if let Some(name) = lod["name"].as_str() {
+                lod["name"] = json!(format!("{}_LOD{}", name, level + 1));
             }
-            gltf::accessor::DataType::U8 => {
-                let indices_len = indices_accessor.count();
-                let indices_data = &indices_buffer[indices_offset..indices_offset + indices_len];
-                indices_data.iter().map(|&i| i as u32).collect()
+            if let Some(list) = root["meshes"].as_array_mut() {
+                list.push(lod);
+                lod_meshes.entry(mesh).or_default().push(list.len() - 1);
             }
-            _ => return Ok(None),
         }
-    } else {
-        // Non-indexed mesh - generate sequential indices
-        (0..positions_accessor.count() as u32).collect()
+    }
+    if lod_meshes.is_empty() {
+        return false;
+    }
+
+    let node_count = root["nodes"].as_array().map_or(0, Vec::len);
+    for node in 0..node_count {
+        let Some(ids) = root["nodes"][node]["mesh"].as_u64().and_then(|m| lod_meshes.get(&(m as usize))) else {
+            continue;
+        };
+        let mut lod_nodes = Vec::new();
+        for (level, &mesh) in ids.iter().enumerate() {
+            let source = &root["nodes"][node];
+            let mut lod = json!({ "mesh": mesh });
+            // Skinned and morphed meshes need the same skin and default weights
+            for key in ["skin", "weights"] {
+                if let Some(value) = source.get(key) {
+                    lod[key] = value.clone();
+                }
+            }
+            if let Some(name) = source["name"].as_str() {
+                lod["name"] = json!(format!("{}_LOD{}", name, level + 1));
+            }
+            if let Some(list) = root["nodes"].as_array_mut() {
+                list.push(lod);
+                lod_nodes.push(list.len() - 1);
+            }
+        }
+        root["nodes"][node]["extensions"]["MSFT_lod"] = json!({ "ids": lod_nodes });
+    }
+    true
+}
+
+/// List `name` in extensionsUsed, and in extensionsRequired when `required`
+fn add_extension(root: &mut serde_json::Map<String, Value>, name: &str, required: bool) {
+    let keys: &[&str] = if required { &["extensionsUsed", "extensionsRequired"] } else { &["extensionsUsed"] };
+    for key in keys {
+        let list = root.entry(*key).or_insert_with(|| json!([]));
+        if let Some(list) = list.as_array_mut().filter(|l| !l.iter().any(|e| e == name)) {
+            list.push(json!(name));
+        }
+    }
+}
+
+/// Extract mesh data (all supported vertex streams) from a glTF primitive
+fn extract_mesh_data(
+    primitive: &gltf::Primitive,
+    buffers: &[gltf::buffer::Data],
+) -> Result<Option<MeshData>> {
+    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+
+    // Positions are required
+    let positions: Vec<f32> = match reader.read_positions() {
+        Some(iter) => iter.flatten().collect(),
+        None => return Ok(None),
+    };
+    let vertex_count = positions.len() / 3;
+
+    let mut attributes = vec![VertexAttribute {
+        semantic: gltf::Semantic::Positions,
+        components: 3,
+        data: positions,
+    }];
+
+    for (semantic, _accessor) in primitive.attributes() {
+        let stream: Option<(usize, Vec<f32>)> = match &semantic {
+            gltf::Semantic::Positions => continue,
+            gltf::Semantic::Normals => reader
+                .read_normals()
+                .map(|iter| (3, iter.flatten().collect())),
+            gltf::Semantic::Tangents => reader
+                .read_tangents()
+                .map(|iter| (4, iter.flatten().collect())),
+            gltf::Semantic::Colors(set) => reader
+                .read_colors(*set)
+                .map(|iter| (4, iter.into_rgba_f32().flatten().collect())),
+            gltf::Semantic::TexCoords(set) => reader
+                .read_tex_coords(*set)
+                .map(|iter| (2, iter.into_f32().flatten().collect())),
+            gltf::Semantic::Joints(set) => reader
+                .read_joints(*set)
+                .map(|iter| (4, iter.into_u16().flatten().map(|j| j as f32).collect())),
+            gltf::Semantic::Weights(set) => reader
+                .read_weights(*set)
+                .map(|iter| (4, iter.into_f32().flatten().collect())),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        };
+
+        match stream {
+            Some((components, data)) if data.len() == vertex_count * components => {
+                attributes.push(VertexAttribute { semantic, components, data });
+            }
+            _ => tracing::warn!("Dropping unsupported vertex attribute: {:?}", semantic),
+        }
+    }
+
+    // Morph targets (blend shapes) carry per-vertex deltas
+    let morph_targets: Vec<MorphTarget> = reader
+        .read_morph_targets()
+        .map(|(positions, normals, tangents)| {
+            let delta = |semantic, iter: Option<gltf::accessor::Iter<'_, [f32; 3]>>| {
+                iter.map(|iter| VertexAttribute {
+                    semantic,
+                    components: 3,
+                    data: iter.flatten().collect(),
+                })
+                .filter(|a| a.data.len() == vertex_count * 3)
+            };
+            MorphTarget {
+                positions: delta(gltf::Semantic::Positions, positions),
+                normals: delta(gltf::Semantic::Normals, normals),
+                tangents: delta(gltf::Semantic::Tangents, tangents),
+            }
+        })
+        .collect();
+
+    // Get indices (non-indexed meshes get sequential indices)
+    let indices: Vec<u32> = match reader.read_indices() {
+        Some(iter) => iter.into_u32().collect(),
+        None => (0..vertex_count as u32).collect(),
     };
 
     Ok(Some(MeshData {
-        vertex_count: positions_accessor.count(),
-        vertex_stride: 12, // 3 floats * 4 bytes
-        vertices: positions,
+        attributes,
+        morph_targets,
         indices,
+        vertex_count,
     }))
 }
 
@@ -466,6 +1305,7 @@ pub fn detect_model_format(path: &Path) -> Option<ModelFormat> {
         "glb" => Some(ModelFormat::GLB),
         "obj" => Some(ModelFormat::OBJ),
         "fbx" => Some(ModelFormat::FBX),
+        "dae" => Some(ModelFormat::Collada),
         _ => None,
     }
 }
@@ -476,6 +1316,7 @@ pub enum ModelFormat {
     GLB,
     OBJ,
     FBX,
+    Collada,
 }
 
 impl std::fmt::Display for ModelFormat {
@@ -485,6 +1326,7 @@ impl std::fmt::Display for ModelFormat {
             ModelFormat::GLB => write!(f, "GLB"),
             ModelFormat::OBJ => write!(f, "OBJ"),
             ModelFormat::FBX => write!(f, "FBX"),
+            ModelFormat::Collada => write!(f, "COLLADA"),
         }
     }
 }
@@ -0,0 +1,160 @@
+use anyhow::{Context, Result};
+use image::{DynamicImage, Rgba, RgbaImage};
+use std::path::{Path, PathBuf};
+
+use crate::config::RuleConfig;
+
+use super::open_image;
+
+pub use crate::runtime::{NineSlice, NineSliceSidecar};
+
+/// Largest per-channel difference for two columns (or rows) to count as the same
+const TOLERANCE: u8 = 2;
+
+/// Nine-slice borders for an asset: its `slice_borders` setting, or detected from the image
+/// when `nine_slice` is set
+///
+/// `image` is the decoded source if the caller already has it; otherwise it is opened only
+/// when the settings ask for a nine-slice. Returns `None` when neither is set or nothing
+/// stretchable is found.
+pub fn nine_slice_for(
+    input: &Path,
+    settings: &RuleConfig,
+    image: Option<&DynamicImage>,
+) -> Result<Option<NineSliceSidecar>> {
+    if settings.slice_borders.is_none() && settings.nine_slice != Some(true) {
+        return Ok(None);
+    }
+
+    let opened;
+    let image = match image {
+        Some(image) => image,
+        None => {
+            opened = open_image(input)?;
+            &opened
+        }
+    };
+    let (width, height) = (image.width(), image.height());
+
+    let borders = match settings.slice_borders.as_deref() {
+        Some(&[left, top, right, bottom]) => {
+            if left + right > width || top + bottom > height {
+                anyhow::bail!(
+                    "{}: slice borders [{}, {}, {}, {}] exceed the {}x{} image",
+                    input.display(),
+                    left,
+                    top,
+                    right,
+                    bottom,
+                    width,
+                    height
+                );
+            }
+            NineSlice { left, top, right, bottom }
+        }
+        Some(values) => anyhow::bail!(
+            "{}: slice_borders needs 4 values [left, top, right, bottom], got {}",
+            input.display(),
+            values.len()
+        ),
+        None => match detect_nine_slice(&image.to_rgba8()) {
+            Some(borders) => borders,
+            None => {
+                tracing::warn!("{}: no stretchable rows or columns found, no nine-slice written", input.display());
+                return Ok(None);
+            }
+        },
+    };
+
+    Ok(Some(NineSliceSidecar { width, height, borders }))
+}
+
+/// Detect nine-slice borders from the longest runs of identical columns and rows
+///
+/// The stretchable center of a nine-slice image repeats the same column (and row) of pixels,
+/// so the longest such run along each axis is taken as the center. Fully transparent columns
+/// and rows never count, so padding around a sprite isn't mistaken for its center. An axis
+/// without a run gets zero borders; `None` means neither axis has one.
+pub fn detect_nine_slice(img: &RgbaImage) -> Option<NineSlice> {
+    let (width, height) = img.dimensions();
+    let columns = longest_run(
+        width,
+        |a, b| (0..height).all(|y| same_pixel(img.get_pixel(a, y), img.get_pixel(b, y))),
+        |x| (0..height).any(|y| img.get_pixel(x, y)[3] > 0),
+    );
+    let rows = longest_run(
+        height,
+        |a, b| (0..width).all(|x| same_pixel(img.get_pixel(x, a), img.get_pixel(x, b))),
+        |y| (0..width).any(|x| img.get_pixel(x, y)[3] > 0),
+    );
+
+    let (left, right) = columns.map_or((0, 0), |(start, end)| (start, width - end));
+    let (top, bottom) = rows.map_or((0, 0), |(start, end)| (start, height - end));
+    let borders = NineSlice { left, top, right, bottom };
+    (borders != NineSlice { left: 0, top: 0, right: 0, bottom: 0 }).then_some(borders)
+}
+
+/// Rescale a nine-slice sidecar to a texture resized to `width` x `height`
+pub fn scale_nine_slice(sidecar: &NineSliceSidecar, width: u32, height: u32) -> NineSliceSidecar {
+    let scale = |value: u32, from: u32, to: u32| (value as f64 * to as f64 / from.max(1) as f64).round() as u32;
+    let borders = &sidecar.borders;
+    NineSliceSidecar {
+        width,
+        height,
+        borders: NineSlice {
+            left: scale(borders.left, sidecar.width, width),
+            top: scale(borders.top, sidecar.height, height),
+            right: scale(borders.right, sidecar.width, width),
+            bottom: scale(borders.bottom, sidecar.height, height),
+        },
+    }
+}
+
+/// Write the nine-slice sidecar of a processed texture (`button.ktx2` → `button.slice.json`)
+pub fn write_nine_slice(sidecar: &NineSliceSidecar, output: &Path) -> Result<PathBuf> {
+    let path = output.with_extension("slice.json");
+    let json = serde_json::to_string_pretty(sidecar)?;
+    std::fs::write(&path, json)
+        .with_context(|| format!("Failed to write nine-slice sidecar: {}", path.display()))?;
+    Ok(path)
+}
+
+/// Write the sidecar next to every output, scaled to the size it was written at: `scale` is
+/// the output's resolution variant (1.0 for a plain output) and `max_size` the preset's cap
+pub fn write_nine_slices(sidecar: &NineSliceSidecar, outputs: &[(PathBuf, f32)], max_size: Option<u32>) -> Result<()> {
+    for (output, scale) in outputs {
+        let mut width = ((sidecar.width as f32 * scale).round() as u32).max(1);
+        let mut height = ((sidecar.height as f32 * scale).round() as u32).max(1);
+        if let Some(max) = max_size.filter(|&max| width > max || height > max) {
+            let ratio = max as f32 / width.max(height) as f32;
+            width = ((width as f32 * ratio) as u32).max(1);
+            height = ((height as f32 * ratio) as u32).max(1);
+        }
+        write_nine_slice(&scale_nine_slice(sidecar, width, height), output)?;
+    }
+    Ok(())
+}
+
+/// Longest run `[start, end)` of at least two visible indices where each neighbour is the same
+fn longest_run(
+    len: u32,
+    same: impl Fn(u32, u32) -> bool,
+    visible: impl Fn(u32) -> bool,
+) -> Option<(u32, u32)> {
+    let mut best: Option<(u32, u32)> = None;
+    let mut start = 0;
+    for i in 1..=len {
+        let continues = i < len && visible(i) && visible(i - 1) && same(i - 1, i);
+        if !continues {
+            if i - start >= 2 && best.is_none_or(|(s, e)| i - start > e - s) {
+                best = Some((start, i));
+            }
+            start = i;
+        }
+    }
+    best
+}
+
+fn same_pixel(a: &Rgba<u8>, b: &Rgba<u8>) -> bool {
+    a.0.iter().zip(b.0).all(|(x, y)| x.abs_diff(y) <= TOLERANCE)
+}
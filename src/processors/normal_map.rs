@@ -0,0 +1,151 @@
+use anyhow::{Context, Result};
+use image::{imageops::FilterType, GenericImageView, RgbaImage};
+#[cfg(feature = "native-textures")]
+use intel_tex_2::{bc5, RgSurface};
+#[cfg(feature = "native-textures")]
+use rayon::prelude::*;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::cli::{MipFilter, NormalEncoding, QualityPreset};
+#[cfg(not(feature = "native-textures"))]
+use super::encode_bc5;
+use super::{bc5_to_ktx2, compress_to_ktx2, mip_chain, open_image_linear, BasisCompressionMode, BasisConfig, ProcessingStats};
+
+/// Configuration for normal map KTX2 output
+#[derive(Debug, Clone)]
+pub struct NormalMapConfig {
+    pub encoding: NormalEncoding,
+    pub quality: QualityPreset,
+    pub generate_mipmaps: bool,
+    pub max_size: Option<u32>,
+    /// Rescale vectors to unit length after resizing and in every mip level
+    pub renormalize: bool,
+    /// Zstandard level for KTX2 supercompression (None = uncompressed)
+    pub zstd_level: Option<i32>,
+    pub mip_filter: MipFilter,
+}
+
+/// Compress a normal map to KTX2 as linear UASTC (X in RGB, Y in alpha) or BC5 (X in R, Y in G)
+pub fn compress_normal_map_to_ktx2(
+    input: &Path,
+    output: &Path,
+    config: &NormalMapConfig,
+) -> Result<ProcessingStats> {
+    if config.encoding == NormalEncoding::Uastc {
+        let basis_config = BasisConfig {
+            mode: BasisCompressionMode::Uastc,
+            quality: config.quality,
+            generate_mipmaps: config.generate_mipmaps,
+            max_size: config.max_size,
+            zstd_level: config.zstd_level,
+            normal_map: true,
+            linear: true,
+            renormalize: config.renormalize,
+            mip_filter: config.mip_filter,
+            alpha_coverage: None,
+        };
+        return compress_to_ktx2(input, output, &basis_config);
+    }
+
+    let start = Instant::now();
+    let original_size = std::fs::metadata(input)
+        .with_context(|| format!("Failed to read input file: {}", input.display()))?
+        .len();
+
+    let base = load_normal_map(input, config.max_size, config.renormalize)?;
+    let (width, height) = base.dimensions();
+    let chain = if config.generate_mipmaps {
+        normal_mip_chain(base, config.renormalize, config.mip_filter)
+    } else {
+        vec![base]
+    };
+    let levels: Vec<Vec<u8>> = chain.iter().map(encode_bc5).collect();
+    let data = bc5_to_ktx2(&levels, width, height, config.zstd_level)?;
+
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(output, &data)
+        .with_context(|| format!("Failed to write texture: {}", output.display()))?;
+
+    Ok(ProcessingStats {
+        original_size,
+        output_size: data.len() as u64,
+        processing_time_ms: start.elapsed().as_millis() as u64,
+        quality: None,
+    })
+}
+
+/// Open a normal map, downscaling it to `max_size` (and renormalizing if it was resized)
+pub fn load_normal_map(path: &Path, max_size: Option<u32>, renormalize: bool) -> Result<RgbaImage> {
+    let img = open_image_linear(path)?;
+    let (width, height) = img.dimensions();
+    match max_size {
+        Some(max) if width > max || height > max => {
+            let mut resized = img.resize(max, max, FilterType::Lanczos3).to_rgba8();
+            if renormalize {
+                renormalize_normals(&mut resized);
+            }
+            Ok(resized)
+        }
+        _ => Ok(img.to_rgba8()),
+    }
+}
+
+/// Full mip chain of a normal map, largest first, filtered on the encoded (linear) values
+pub fn normal_mip_chain(base: RgbaImage, renormalize: bool, filter: MipFilter) -> Vec<RgbaImage> {
+    let mut chain = mip_chain(&base, filter, false);
+    if renormalize {
+        chain.iter_mut().skip(1).for_each(renormalize_normals);
+    }
+    chain[0] = base;
+    chain
+}
+
+/// Rescale every texel's XYZ vector to unit length; alpha is kept
+///
+/// Averaging unit vectors shortens them, which darkens lighting in lower mips and after
+/// downscaling. Texels that average to (nearly) zero are left as they are.
+pub fn renormalize_normals(img: &mut RgbaImage) {
+    for pixel in img.pixels_mut() {
+        let v = [0, 1, 2].map(|c| pixel[c] as f32 / 255.0 * 2.0 - 1.0);
+        let length = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+        if length < 1e-4 {
+            continue;
+        }
+        for c in 0..3 {
+            pixel[c] = ((v[c] / length * 0.5 + 0.5) * 255.0).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+/// Encode X and Y as BC5 blocks, one row of blocks per task
+#[cfg(feature = "native-textures")]
+pub fn encode_bc5(img: &RgbaImage) -> Vec<u8> {
+    // The encoder works on whole blocks, so edge texels are repeated to a multiple of 4
+    let (width, height) = img.dimensions();
+    let padded_width = width.div_ceil(4) * 4;
+    let stride = padded_width as usize * 2;
+
+    (0..height.div_ceil(4))
+        .into_par_iter()
+        .flat_map_iter(|block_row| {
+            let mut texels = Vec::with_capacity(stride * 4);
+            for dy in 0..4 {
+                let y = (block_row * 4 + dy).min(height - 1);
+                for x in 0..padded_width {
+                    let p = img.get_pixel(x.min(width - 1), y);
+                    texels.extend_from_slice(&[p[0], p[1]]);
+                }
+            }
+            let surface = RgSurface {
+                data: &texels,
+                width: padded_width,
+                height: 4,
+                stride: stride as u32,
+            };
+            bc5::compress_blocks(&surface)
+        })
+        .collect()
+}
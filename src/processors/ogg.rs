@@ -0,0 +1,116 @@
+//! Ogg page writer and Vorbis comments for the built-in encoders
+
+/// Target Ogg page body size
+const PAGE_SIZE: usize = 4096;
+
+/// Lays packets out in Ogg pages of about PAGE_SIZE bytes
+#[derive(Default)]
+pub(super) struct OggWriter {
+    data: Vec<u8>,
+    sequence: u32,
+    segments: Vec<u8>,
+    body: Vec<u8>,
+    /// Granule position of the last packet finished on the current page
+    granule: Option<u64>,
+    /// The current page starts inside a packet
+    continued: bool,
+}
+
+impl OggWriter {
+    const SERIAL: u32 = 0x6166_6f67;
+
+    pub(super) fn packet(&mut self, packet: &[u8], granule: u64) {
+        if self.body.len() >= PAGE_SIZE {
+            self.flush(false);
+        }
+
+        // 255-byte lacing values, ended by a shorter one (possibly zero)
+        let mut offset = 0;
+        loop {
+            if self.segments.len() == 255 {
+                self.flush(false);
+                self.continued = true;
+            }
+            let lace = (packet.len() - offset).min(255);
+            self.segments.push(lace as u8);
+            self.body.extend_from_slice(&packet[offset..offset + lace]);
+            offset += lace;
+            if lace < 255 {
+                break;
+            }
+        }
+        self.granule = Some(granule);
+    }
+
+    pub(super) fn flush(&mut self, last: bool) {
+        if self.segments.is_empty() && !last {
+            return;
+        }
+        let mut flags = 0u8;
+        if self.continued {
+            flags |= 0x01;
+        }
+        if self.sequence == 0 {
+            flags |= 0x02;
+        }
+        if last {
+            flags |= 0x04;
+        }
+
+        let start = self.data.len();
+        self.data.extend_from_slice(b"OggS");
+        self.data.push(0);
+        self.data.push(flags);
+        self.data.extend_from_slice(&self.granule.map_or(-1, |g| g as i64).to_le_bytes());
+        self.data.extend_from_slice(&Self::SERIAL.to_le_bytes());
+        self.data.extend_from_slice(&self.sequence.to_le_bytes());
+        self.data.extend_from_slice(&[0; 4]);
+        self.data.push(self.segments.len() as u8);
+        self.data.extend_from_slice(&self.segments);
+        self.data.extend_from_slice(&self.body);
+        let crc = ogg_crc(&self.data[start..]);
+        self.data[start + 22..start + 26].copy_from_slice(&crc.to_le_bytes());
+
+        self.sequence += 1;
+        self.segments.clear();
+        self.body.clear();
+        self.granule = None;
+        self.continued = false;
+    }
+
+    /// Pages completed since the last call, so long streams can be written out as they go
+    pub(super) fn take_pages(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.data)
+    }
+
+    /// Write the last page and return the stream, less any pages already taken
+    pub(super) fn finish(mut self) -> Vec<u8> {
+        self.flush(true);
+        self.data
+    }
+}
+
+/// CRC-32 with polynomial 0x04c11db7, unreflected, as Ogg page checksums use
+fn ogg_crc(data: &[u8]) -> u32 {
+    data.iter().fold(0u32, |mut crc, &byte| {
+        crc ^= (byte as u32) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 { (crc << 1) ^ 0x04c1_1db7 } else { crc << 1 };
+        }
+        crc
+    })
+}
+
+/// Vorbis comment block: vendor string and `TAG=value` fields, as used by Vorbis, Opus and FLAC
+pub(super) fn vorbis_comment(tags: &[(String, String)]) -> Vec<u8> {
+    let vendor = concat!("asset-forge ", env!("CARGO_PKG_VERSION"));
+    let mut block = (vendor.len() as u32).to_le_bytes().to_vec();
+    block.extend_from_slice(vendor.as_bytes());
+    block.extend_from_slice(&(tags.len() as u32).to_le_bytes());
+    for (tag, value) in tags {
+        let field = format!("{}={}", tag, value);
+        block.extend_from_slice(&(field.len() as u32).to_le_bytes());
+        block.extend_from_slice(field.as_bytes());
+    }
+    block
+}
@@ -0,0 +1,268 @@
+//! Ogg Opus encoding and decoding (RFC 7845)
+
+use anyhow::{bail, Result};
+use std::io::Write;
+use unsafe_libopus::{
+    opus_decode_float, opus_decoder_create, opus_decoder_destroy, opus_encode_float, opus_encoder_create,
+    opus_encoder_ctl, opus_encoder_destroy, OpusDecoder as LibOpusDecoder, OpusEncoder, OPUS_APPLICATION_AUDIO, OPUS_AUTO, OPUS_GET_LOOKAHEAD_REQUEST, OPUS_OK,
+    OPUS_SET_BITRATE_REQUEST, OPUS_SET_VBR_CONSTRAINT_REQUEST, OPUS_SET_VBR_REQUEST,
+};
+
+use crate::cli::{BitrateMode, QualityPreset};
+
+use super::audio_stream::Resampler;
+use super::ogg::{vorbis_comment, OggWriter};
+use super::LoopPoints;
+
+/// Opus always decodes at 48 kHz, and granule positions count 48 kHz samples
+const OPUS_RATE: u32 = 48000;
+/// 20 ms frames
+const FRAME: usize = 960;
+/// Largest packet libopus produces for one frame
+const MAX_PACKET: usize = 4000;
+/// Longest packet a decoder can receive: 120 ms
+const MAX_PACKET_FRAMES: usize = 5760;
+
+/// Owns a libopus encoder and frees it on drop
+struct Encoder(*mut OpusEncoder);
+
+impl Drop for Encoder {
+    fn drop(&mut self) {
+        unsafe { opus_encoder_destroy(self.0) }
+    }
+}
+
+/// Ogg Opus encoder for interleaved mono or stereo audio, taken in chunks and written out
+/// page by page
+///
+/// Other sample rates are resampled to 48 kHz at `resample_quality`; the original rate is
+/// kept in the header. `bitrate` is in kbps, `None` lets libopus pick one for the channel count.
+pub struct OpusWriter<W: Write> {
+    writer: W,
+    encoder: Encoder,
+    ogg: OggWriter,
+    channels: usize,
+    lookahead: usize,
+    resampler: Option<Resampler>,
+    /// 48 kHz samples not yet encoded, starting at frame `position`
+    buffer: Vec<f32>,
+    position: usize,
+    frames: usize,
+}
+
+impl<W: Write> OpusWriter<W> {
+    pub fn new(
+        writer: W,
+        channels: u32,
+        sample_rate: u32,
+        loop_points: Option<LoopPoints>,
+        bitrate: Option<u32>,
+        mode: BitrateMode,
+        resample_quality: QualityPreset,
+    ) -> Result<Self> {
+        if channels == 0 || channels > 2 {
+            bail!("Opus output supports mono or stereo, not {} channels", channels);
+        }
+        if sample_rate == 0 {
+            bail!("Invalid sample rate: 0");
+        }
+        let resampler = if sample_rate == OPUS_RATE {
+            None
+        } else {
+            Some(Resampler::new(channels, sample_rate, OPUS_RATE, resample_quality)?)
+        };
+
+        let mut error = 0;
+        let encoder = Encoder(unsafe {
+            opus_encoder_create(OPUS_RATE as i32, channels as i32, OPUS_APPLICATION_AUDIO, &mut error)
+        });
+        if encoder.0.is_null() || error != OPUS_OK {
+            bail!("Failed to create Opus encoder (error {})", error);
+        }
+        let bits = bitrate.map_or(OPUS_AUTO, |kbps| kbps as i32 * 1000);
+        let (vbr, constrained) = match mode {
+            BitrateMode::Vbr => (1, 0),
+            BitrateMode::Cvbr => (1, 1),
+            BitrateMode::Cbr => (0, 0),
+        };
+        let mut lookahead = 0i32;
+        unsafe {
+            let results = [
+                opus_encoder_ctl!(encoder.0, OPUS_SET_BITRATE_REQUEST, bits),
+                opus_encoder_ctl!(encoder.0, OPUS_SET_VBR_REQUEST, vbr),
+                opus_encoder_ctl!(encoder.0, OPUS_SET_VBR_CONSTRAINT_REQUEST, constrained),
+                opus_encoder_ctl!(encoder.0, OPUS_GET_LOOKAHEAD_REQUEST, &mut lookahead),
+            ];
+            if let Some(error) = results.into_iter().find(|&result| result != OPUS_OK) {
+                bail!("Failed to configure Opus encoder (error {})", error);
+            }
+        }
+
+        let mut ogg = OggWriter::default();
+        ogg.packet(&opus_head(channels as u8, lookahead as u16, sample_rate), 0);
+        ogg.flush(false);
+        let tags = loop_points
+            .map(|loop_points| loop_points.rescale(sample_rate, OPUS_RATE).comment_tags())
+            .unwrap_or_default();
+        ogg.packet(&opus_tags(&tags), 0);
+        ogg.flush(false);
+
+        Ok(Self {
+            writer,
+            encoder,
+            ogg,
+            channels: channels as usize,
+            lookahead: lookahead as usize,
+            resampler,
+            buffer: Vec::new(),
+            position: 0,
+            frames: 0,
+        })
+    }
+
+    /// Encode interleaved samples, holding back any partial frame
+    pub fn write(&mut self, samples: &[f32]) -> Result<()> {
+        match &mut self.resampler {
+            Some(resampler) => {
+                let resampled = resampler.process(samples)?;
+                self.buffer.extend(resampled);
+            }
+            None => self.buffer.extend_from_slice(samples),
+        }
+        self.frames = self.position + self.buffer.len() / self.channels;
+        // A full frame ends at or before the last input sample, so its granule position
+        // needs no trimming
+        let len = FRAME * self.channels;
+        let mut offset = 0;
+        while self.buffer.len() - offset >= len {
+            let granule = self.position + FRAME;
+            encode_frame(&self.encoder, &mut self.ogg, &self.buffer[offset..offset + len], self.channels, granule)?;
+            self.position += FRAME;
+            offset += len;
+        }
+        self.buffer.drain(..offset);
+        self.writer.write_all(&self.ogg.take_pages())?;
+        Ok(())
+    }
+
+    /// Encode what's left and return the writer
+    pub fn finish(mut self) -> Result<W> {
+        if let Some(resampler) = &mut self.resampler {
+            let rest = resampler.finish()?;
+            self.buffer.extend(rest);
+            self.frames = self.position + self.buffer.len() / self.channels;
+        }
+
+        // The decoder drops `lookahead` samples of encoder delay, so the input runs that much
+        // longer, and the last granule position trims the padding of the final frame
+        let end = self.lookahead + self.frames;
+        let len = FRAME * self.channels;
+        let mut offset = 0;
+        while self.position < end.max(1) {
+            let input = &self.buffer[offset.min(self.buffer.len())..(offset + len).min(self.buffer.len())];
+            encode_frame(&self.encoder, &mut self.ogg, input, self.channels, (self.position + FRAME).min(end))?;
+            self.position += FRAME;
+            offset += len;
+        }
+        self.writer.write_all(&self.ogg.finish())?;
+        Ok(self.writer)
+    }
+}
+
+/// Encode one frame of samples, padded with silence to FRAME frames
+fn encode_frame(encoder: &Encoder, ogg: &mut OggWriter, samples: &[f32], channels: usize, granule: usize) -> Result<()> {
+    let mut input = samples.to_vec();
+    input.resize(FRAME * channels, 0.0);
+    let mut packet = vec![0u8; MAX_PACKET];
+    let len = unsafe {
+        opus_encode_float(encoder.0, input.as_ptr(), FRAME as i32, packet.as_mut_ptr(), MAX_PACKET as i32)
+    };
+    if len < 0 {
+        bail!("Failed to encode Opus frame (error {})", len);
+    }
+    ogg.packet(&packet[..len as usize], granule as u64);
+    Ok(())
+}
+
+/// Identification header: channel mapping family 0 (mono or stereo), no output gain
+fn opus_head(channels: u8, pre_skip: u16, input_rate: u32) -> Vec<u8> {
+    let mut header = b"OpusHead".to_vec();
+    header.push(1);
+    header.push(channels);
+    header.extend_from_slice(&pre_skip.to_le_bytes());
+    header.extend_from_slice(&input_rate.to_le_bytes());
+    header.extend_from_slice(&0i16.to_le_bytes());
+    header.push(0);
+    header
+}
+
+/// Comment header; loop points count 48 kHz samples, like granule positions
+fn opus_tags(tags: &[(String, String)]) -> Vec<u8> {
+    let mut header = b"OpusTags".to_vec();
+    header.extend_from_slice(&vorbis_comment(tags));
+    header
+}
+
+/// Decodes the packets of an Ogg Opus stream with libopus, which Symphonia has no decoder for
+///
+/// Output is 48 kHz with the encoder delay (`pre_skip`) dropped, and cut at `frames` when the
+/// stream length is known so the padding of the last packet doesn't come out.
+pub(super) struct OpusDecoder {
+    decoder: *mut LibOpusDecoder,
+    channels: usize,
+    /// Frames still to drop from the start
+    skip: usize,
+    /// Frames still to output
+    remaining: Option<u64>,
+}
+
+impl OpusDecoder {
+    pub fn new(channels: u32, pre_skip: u32, frames: Option<u64>) -> Result<Self> {
+        if channels == 0 || channels > 2 {
+            bail!("Opus decoding supports mono or stereo, not {} channels", channels);
+        }
+        let mut error = 0;
+        let decoder = unsafe { opus_decoder_create(OPUS_RATE as i32, channels as i32, &mut error) };
+        if decoder.is_null() || error != OPUS_OK {
+            bail!("Failed to create Opus decoder (error {})", error);
+        }
+        Ok(Self { decoder, channels: channels as usize, skip: pre_skip as usize, remaining: frames })
+    }
+
+    /// Interleaved samples of one packet
+    pub fn decode(&mut self, packet: &[u8]) -> Result<Vec<f32>> {
+        let mut samples = vec![0.0f32; MAX_PACKET_FRAMES * self.channels];
+        let frames = unsafe {
+            opus_decode_float(
+                self.decoder,
+                packet.as_ptr(),
+                packet.len() as i32,
+                samples.as_mut_ptr(),
+                MAX_PACKET_FRAMES as i32,
+                0,
+            )
+        };
+        if frames < 0 {
+            bail!("Failed to decode Opus packet (error {})", frames);
+        }
+        let mut frames = frames as usize;
+        samples.truncate(frames * self.channels);
+
+        let skip = self.skip.min(frames);
+        samples.drain(..skip * self.channels);
+        self.skip -= skip;
+        frames -= skip;
+        if let Some(remaining) = &mut self.remaining {
+            let keep = frames.min(*remaining as usize);
+            samples.truncate(keep * self.channels);
+            *remaining -= keep as u64;
+        }
+        Ok(samples)
+    }
+}
+
+impl Drop for OpusDecoder {
+    fn drop(&mut self) {
+        unsafe { opus_decoder_destroy(self.decoder) }
+    }
+}
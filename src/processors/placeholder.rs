@@ -0,0 +1,136 @@
+use anyhow::{Context, Result};
+use hound::{WavSpec, WavWriter};
+use image::{Rgba, RgbaImage};
+use std::path::{Path, PathBuf};
+
+use super::{AssetType, GlbMaterial, GlbMesh, GlbPrimitive, GlbScene};
+
+const TEXTURE_SIZE: u32 = 64;
+const CHECKER_SIZE: u32 = 8;
+/// Magenta, the conventional "missing asset" color
+const MISSING_COLOR: [u8; 4] = [255, 0, 255, 255];
+
+const AUDIO_SAMPLE_RATE: u32 = 44100;
+const AUDIO_SECS: f32 = 0.5;
+const BEEP_HZ: f32 = 880.0;
+
+/// Read the logical assets a game expects: a JSON array of source paths, a manifest-style
+/// `{"assets": [{"source": ...}]}` object, or a text file with one path per line
+pub fn load_expected_assets(path: &Path) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read expected asset list: {}", path.display()))?;
+
+    let sources: Vec<String> = match serde_json::from_str::<serde_json::Value>(&content) {
+        Ok(serde_json::Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| item.as_str().map(str::to_string))
+            .collect(),
+        Ok(value) => value["assets"]
+            .as_array()
+            .with_context(|| format!("Expected an array or \"assets\" list in {}", path.display()))?
+            .iter()
+            .filter_map(|asset| asset["source"].as_str().map(str::to_string))
+            .collect(),
+        Err(_) => content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect(),
+    };
+
+    Ok(sources
+        .into_iter()
+        .map(|source| source.replace('\\', "/").trim_start_matches("./").to_string())
+        .collect())
+}
+
+/// Write a placeholder for a missing asset into the output directory
+///
+/// Textures become a magenta checkerboard PNG, audio a short beep WAV and models a
+/// magenta cube GLB. Returns the output path, or None for unsupported asset types.
+pub fn write_placeholder(source: &str, output_dir: &Path) -> Result<Option<PathBuf>> {
+    let source_path = Path::new(source);
+    let (extension, write): (&str, fn(&Path) -> Result<()>) = match AssetType::from_path(source_path) {
+        AssetType::Image => ("png", write_texture),
+        AssetType::Audio => ("wav", write_audio),
+        AssetType::Model => ("glb", write_model),
+        AssetType::Unknown => return Ok(None),
+    };
+
+    let output = output_dir.join(source_path).with_extension(extension);
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    write(&output).with_context(|| format!("Failed to write placeholder: {}", output.display()))?;
+    Ok(Some(output))
+}
+
+fn write_texture(path: &Path) -> Result<()> {
+    let img = RgbaImage::from_fn(TEXTURE_SIZE, TEXTURE_SIZE, |x, y| {
+        if (x / CHECKER_SIZE + y / CHECKER_SIZE).is_multiple_of(2) {
+            Rgba(MISSING_COLOR)
+        } else {
+            Rgba([0, 0, 0, 255])
+        }
+    });
+    img.save(path)?;
+    Ok(())
+}
+
+fn write_audio(path: &Path) -> Result<()> {
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: AUDIO_SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = WavWriter::create(path, spec)?;
+
+    let total = (AUDIO_SAMPLE_RATE as f32 * AUDIO_SECS) as u32;
+    for i in 0..total {
+        let t = i as f32 / AUDIO_SAMPLE_RATE as f32;
+        // Quiet beep with a linear fade out so it doesn't click
+        let envelope = 0.25 * (1.0 - i as f32 / total as f32);
+        let sample = (t * BEEP_HZ * std::f32::consts::TAU).sin() * envelope;
+        writer.write_sample((sample * i16::MAX as f32) as i16)?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+fn write_model(path: &Path) -> Result<()> {
+    let mut primitive = GlbPrimitive {
+        material: Some(0),
+        ..Default::default()
+    };
+    // Unit cube, four vertices per face so each face gets a flat normal
+    for axis in 0..3 {
+        for sign in [1.0f32, -1.0] {
+            let mut normal = [0.0; 3];
+            normal[axis] = sign;
+            let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+            let base = primitive.positions.len() as u32;
+            for (du, dv) in [(-0.5, -0.5), (0.5, -0.5), (0.5, 0.5), (-0.5, 0.5)] {
+                let mut position = [0.0; 3];
+                position[axis] = 0.5 * sign;
+                position[u] = du;
+                position[v] = dv * sign;
+                primitive.positions.push(position);
+                primitive.normals.push(normal);
+            }
+            primitive.indices.extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+        }
+    }
+
+    let mut scene = GlbScene::from_mesh(GlbMesh {
+        name: Some("placeholder".to_string()),
+        primitives: vec![primitive],
+    });
+    scene.materials.push(GlbMaterial {
+        name: Some("missing".to_string()),
+        base_color: MISSING_COLOR.map(|c| c as f32 / 255.0),
+    });
+    scene.write(path)?;
+    Ok(())
+}
@@ -0,0 +1,608 @@
+use anyhow::{bail, Context, Result};
+use globset::{Glob, GlobSetBuilder};
+use image::{DynamicImage, ImageFormat, RgbaImage};
+use std::path::{Path, PathBuf};
+
+use super::{icc_to_srgb, process_image, ImageProcessorConfig, ProcessingStats};
+
+/// Image resource holding the document's ICC profile
+const ICC_RESOURCE_ID: u16 = 1039;
+
+/// Whether a path is a Photoshop document
+pub fn is_psd_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("psd"))
+        .unwrap_or(false)
+}
+
+/// A layer of a PSD, or a group of layers
+#[derive(Debug, Clone)]
+pub struct PsdLayer {
+    /// Names of the enclosing groups and the layer, joined by `/`
+    pub path: String,
+    pub visible: bool,
+    pub opacity: u8,
+    /// Pixels and their `(left, top)` on the canvas; `None` for a group
+    pub image: Option<(i32, i32, RgbaImage)>,
+    /// Paths of the hidden groups enclosing this layer
+    hidden_groups: Vec<String>,
+}
+
+/// A decoded 8-bit RGB or grayscale PSD
+#[derive(Debug, Clone)]
+pub struct PsdDocument {
+    pub width: u32,
+    pub height: u32,
+    /// The merged image Photoshop stores alongside the layers
+    pub composite: RgbaImage,
+    pub icc: Option<Vec<u8>>,
+    /// Layers and groups from bottom to top, each group after its children
+    pub layers: Vec<PsdLayer>,
+}
+
+impl PsdDocument {
+    /// Render a layer, or a group's visible layers, onto a transparent canvas the size of the
+    /// document
+    pub fn render(&self, index: usize) -> RgbaImage {
+        let mut canvas = RgbaImage::new(self.width, self.height);
+        let layer = &self.layers[index];
+        if layer.image.is_some() {
+            blend_over(&mut canvas, layer);
+            return canvas;
+        }
+
+        let prefix = format!("{}/", layer.path);
+        for child in &self.layers {
+            let shown = child.visible
+                && !child
+                    .hidden_groups
+                    .iter()
+                    .any(|group| group.starts_with(&prefix));
+            if shown && child.path.starts_with(&prefix) {
+                blend_over(&mut canvas, child);
+            }
+        }
+        canvas
+    }
+}
+
+/// Read a PSD with its layers
+pub fn read_psd(path: &Path) -> Result<PsdDocument> {
+    let data =
+        std::fs::read(path).with_context(|| format!("Failed to read PSD: {}", path.display()))?;
+    parse_psd(&data, true).with_context(|| format!("Failed to decode PSD: {}", path.display()))
+}
+
+/// The flattened image of a PSD and its ICC profile, without decoding the layers
+pub fn read_psd_composite(path: &Path) -> Result<(RgbaImage, Option<Vec<u8>>)> {
+    let data =
+        std::fs::read(path).with_context(|| format!("Failed to read PSD: {}", path.display()))?;
+    let document = parse_psd(&data, false)
+        .with_context(|| format!("Failed to decode PSD: {}", path.display()))?;
+    Ok((document.composite, document.icc))
+}
+
+/// Embedded ICC profile of a PSD, read without decoding the pixels
+pub fn psd_icc_profile(path: &Path) -> Result<Option<Vec<u8>>> {
+    let data =
+        std::fs::read(path).with_context(|| format!("Failed to read PSD: {}", path.display()))?;
+    let read = |data: &[u8]| -> Result<Option<Vec<u8>>> {
+        let mut reader = Reader::new(data);
+        read_header(&mut reader)?;
+        reader.section()?;
+        read_icc(reader.section()?)
+    };
+    read(&data).with_context(|| format!("Failed to decode PSD: {}", path.display()))
+}
+
+/// Output path of an exported layer (`hero.png` with layer `UI/Health Bar` →
+/// `hero_UI_Health_Bar.png`)
+pub fn psd_layer_path(output: &Path, layer: &str) -> PathBuf {
+    let stem = output
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("layer");
+    let slug: String = layer
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let mut path = output.with_file_name(format!("{}_{}", stem, slug));
+    if let Some(extension) = output.extension() {
+        path.set_extension(extension);
+    }
+    path
+}
+
+/// Export the layers and groups of a PSD whose paths match `patterns` as separate textures
+/// next to `output`
+///
+/// Each is rendered on the full canvas so the exports line up. A layer or group selected by
+/// pattern is exported even if hidden; hidden layers inside an exported group are left out.
+pub fn process_psd_layers(
+    input: &Path,
+    output: &Path,
+    config: &ImageProcessorConfig,
+    patterns: &[String],
+) -> Result<Vec<(PathBuf, String, ProcessingStats)>> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern)
+            .with_context(|| format!("Invalid psd_layers pattern: {}", pattern))?;
+        builder.add(glob);
+    }
+    let matcher = builder.build()?;
+
+    let document = read_psd(input)?;
+    let selected: Vec<usize> = (0..document.layers.len())
+        .filter(|&i| matcher.is_match(&document.layers[i].path))
+        .collect();
+    if selected.is_empty() {
+        bail!(
+            "{}: no layers match psd_layers {:?}",
+            input.display(),
+            patterns
+        );
+    }
+
+    let mut written: Vec<(PathBuf, String, ProcessingStats)> = Vec::new();
+    for index in selected {
+        let path = &document.layers[index].path;
+        let layer_output = psd_layer_path(output, path);
+        if written.iter().any(|(other, _, _)| *other == layer_output) {
+            bail!(
+                "{}: more than one layer exports to {}",
+                input.display(),
+                layer_output.display()
+            );
+        }
+
+        let mut image = DynamicImage::ImageRgba8(document.render(index));
+        if let Some(icc) = document.icc.as_deref().filter(|_| !config.is_linear()) {
+            match icc_to_srgb(&image, icc) {
+                Some(converted) => image = converted,
+                None => tracing::warn!(
+                    "{}: unsupported ICC profile, treating pixels as sRGB",
+                    input.display()
+                ),
+            }
+        }
+
+        if let Some(parent) = layer_output.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let temp = layer_output.with_extension("layer.tmp.png");
+        image
+            .save_with_format(&temp, ImageFormat::Png)
+            .with_context(|| format!("Failed to save layer image: {}", temp.display()))?;
+        let stats = process_image(&temp, &layer_output, config);
+        let _ = std::fs::remove_file(&temp);
+        written.push((layer_output, path.clone(), stats?));
+    }
+    Ok(written)
+}
+
+/// Color mode and channel layout from the file header
+struct Header {
+    width: u32,
+    height: u32,
+    channels: usize,
+    grayscale: bool,
+}
+
+/// Layer record fields needed to place and name a layer
+struct LayerRecord {
+    left: i32,
+    top: i32,
+    width: usize,
+    height: usize,
+    channels: Vec<(i16, usize)>,
+    opacity: u8,
+    hidden: bool,
+    name: String,
+    /// Section divider type: 1 or 2 close a group (its record), 3 opens one
+    divider: Option<u32>,
+}
+
+fn parse_psd(data: &[u8], with_layers: bool) -> Result<PsdDocument> {
+    let mut reader = Reader::new(data);
+    let header = read_header(&mut reader)?;
+    reader.section()?; // color mode data
+    let icc = read_icc(reader.section()?)?;
+
+    let (merged_alpha, layers) = read_layers(reader.section()?, &header, with_layers)?;
+    let composite = read_composite(&mut reader, &header, merged_alpha)?;
+
+    Ok(PsdDocument {
+        width: header.width,
+        height: header.height,
+        composite,
+        icc,
+        layers,
+    })
+}
+
+fn read_header(reader: &mut Reader) -> Result<Header> {
+    if reader.take(4)? != b"8BPS" {
+        bail!("Not a Photoshop document");
+    }
+    match reader.u16()? {
+        1 => {}
+        2 => bail!("Large documents (PSB) are not supported"),
+        version => bail!("Unsupported PSD version {}", version),
+    }
+    reader.take(6)?;
+    let channels = reader.u16()? as usize;
+    let height = reader.u32()?;
+    let width = reader.u32()?;
+    let depth = reader.u16()?;
+    let mode = reader.u16()?;
+
+    if depth != 8 {
+        bail!("{}-bit PSDs are not supported (only 8-bit)", depth);
+    }
+    let grayscale = match mode {
+        1 => true,
+        3 => false,
+        _ => bail!(
+            "Unsupported PSD color mode {} (only RGB and grayscale)",
+            mode
+        ),
+    };
+    Ok(Header {
+        width,
+        height,
+        channels,
+        grayscale,
+    })
+}
+
+fn read_icc(mut resources: Reader) -> Result<Option<Vec<u8>>> {
+    while resources.remaining() > 0 {
+        if resources.take(4)? != b"8BIM" {
+            bail!("Invalid image resource");
+        }
+        let id = resources.u16()?;
+        let name_len = resources.u8()? as usize;
+        // The name and its length byte are padded to an even size
+        resources.take(name_len + (name_len + 1) % 2)?;
+        let size = resources.u32()? as usize;
+        let data = resources.take(size)?;
+        resources.take(size % 2)?;
+        if id == ICC_RESOURCE_ID {
+            return Ok(Some(data.to_vec()));
+        }
+    }
+    Ok(None)
+}
+
+/// Whether the merged image has an alpha channel, and the layers if asked for
+fn read_layers(
+    mut section: Reader,
+    header: &Header,
+    with_layers: bool,
+) -> Result<(bool, Vec<PsdLayer>)> {
+    if section.remaining() == 0 {
+        return Ok((false, Vec::new()));
+    }
+    let mut info = section.section()?;
+    if info.remaining() == 0 {
+        return Ok((false, Vec::new()));
+    }
+    // A negative count means the first alpha channel is the merged image's transparency
+    let count = info.i16()?;
+    let merged_alpha = count < 0;
+    if !with_layers {
+        return Ok((merged_alpha, Vec::new()));
+    }
+
+    let records = (0..count.unsigned_abs())
+        .map(|_| read_layer_record(&mut info))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut images = Vec::with_capacity(records.len());
+    for record in &records {
+        let mut planes: [Option<Vec<u8>>; 4] = Default::default();
+        for &(id, len) in &record.channels {
+            let mut channel = Reader::new(info.take(len)?);
+            let compression = channel.u16()?;
+            let plane = decode_rows(&mut channel, compression, record.width, record.height)?;
+            let slot = match (id, header.grayscale) {
+                (-1, _) => 3,
+                (0, true) => 0,
+                (0..=2, false) => id as usize,
+                _ => continue,
+            };
+            planes[slot] = Some(plane);
+        }
+        images.push(interleave(
+            &planes,
+            record.width,
+            record.height,
+            header.grayscale,
+        ));
+    }
+
+    // Walking from the top, a group's record comes before its layers and the divider that
+    // closes it after them
+    let mut groups: Vec<(String, bool)> = Vec::new();
+    let mut layers = Vec::new();
+    for (record, image) in records.into_iter().zip(images).rev() {
+        let parent = groups.last().map(|(path, _)| path.clone());
+        let path = match parent {
+            Some(parent) => format!("{}/{}", parent, record.name),
+            None => record.name.clone(),
+        };
+        let hidden_groups = groups
+            .iter()
+            .filter(|(_, visible)| !visible)
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        match record.divider {
+            Some(3) => {
+                groups.pop();
+                continue;
+            }
+            Some(1 | 2) => {
+                layers.push(PsdLayer {
+                    path: path.clone(),
+                    visible: !record.hidden,
+                    opacity: record.opacity,
+                    image: None,
+                    hidden_groups,
+                });
+                groups.push((path, !record.hidden));
+            }
+            _ => layers.push(PsdLayer {
+                path,
+                visible: !record.hidden,
+                opacity: record.opacity,
+                image: Some((record.left, record.top, image)),
+                hidden_groups,
+            }),
+        }
+    }
+    layers.reverse();
+    Ok((merged_alpha, layers))
+}
+
+fn read_layer_record(info: &mut Reader) -> Result<LayerRecord> {
+    let top = info.i32()?;
+    let left = info.i32()?;
+    let bottom = info.i32()?;
+    let right = info.i32()?;
+    let channel_count = info.u16()?;
+    let channels = (0..channel_count)
+        .map(|_| Ok((info.i16()?, info.u32()? as usize)))
+        .collect::<Result<Vec<_>>>()?;
+
+    if info.take(4)? != b"8BIM" {
+        bail!("Invalid layer record");
+    }
+    info.take(4)?; // blend mode
+    let opacity = info.u8()?;
+    info.u8()?; // clipping
+    let flags = info.u8()?;
+    info.u8()?;
+
+    let mut extra = info.section()?;
+    extra.section()?; // layer mask
+    extra.section()?; // blending ranges
+    let name_len = extra.u8()? as usize;
+    let mut name: String = extra.take(name_len)?.iter().map(|&b| b as char).collect();
+    // The name and its length byte are padded to a multiple of 4
+    extra.take((4 - (name_len + 1) % 4) % 4)?;
+
+    let mut divider = None;
+    while extra.remaining() >= 12 {
+        extra.take(4)?; // signature
+        let key = extra.take(4)?;
+        let mut block = extra.section()?;
+        match key {
+            b"luni" => {
+                let chars = block.u32()? as usize;
+                let units: Vec<u16> = block
+                    .take(chars * 2)?
+                    .chunks_exact(2)
+                    .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                    .collect();
+                name = String::from_utf16_lossy(&units)
+                    .trim_end_matches('\0')
+                    .to_string();
+            }
+            b"lsct" => divider = Some(block.u32()?),
+            _ => {}
+        }
+    }
+
+    Ok(LayerRecord {
+        left,
+        top,
+        width: (right - left).max(0) as usize,
+        height: (bottom - top).max(0) as usize,
+        channels,
+        opacity,
+        hidden: flags & 0x02 != 0,
+        name,
+        divider,
+    })
+}
+
+fn read_composite(reader: &mut Reader, header: &Header, merged_alpha: bool) -> Result<RgbaImage> {
+    let (width, height) = (header.width as usize, header.height as usize);
+    let compression = reader.u16()?;
+    let data = decode_rows(reader, compression, width, height * header.channels)?;
+    let plane = |i: usize| {
+        data.get(i * width * height..(i + 1) * width * height)
+            .map(<[u8]>::to_vec)
+    };
+
+    let color_channels = if header.grayscale { 1 } else { 3 };
+    let mut planes: [Option<Vec<u8>>; 4] = Default::default();
+    for (i, slot) in planes.iter_mut().take(color_channels).enumerate() {
+        *slot = plane(i);
+    }
+    if merged_alpha {
+        planes[3] = plane(color_channels);
+    }
+    Ok(interleave(&planes, width, height, header.grayscale))
+}
+
+/// Decode `rows` rows of `width` bytes, raw or PackBits compressed
+fn decode_rows(
+    reader: &mut Reader,
+    compression: u16,
+    width: usize,
+    rows: usize,
+) -> Result<Vec<u8>> {
+    match compression {
+        0 => Ok(reader.take(width * rows)?.to_vec()),
+        1 => {
+            let counts = (0..rows)
+                .map(|_| reader.u16())
+                .collect::<Result<Vec<_>>>()?;
+            let mut out = Vec::with_capacity(width * rows);
+            for count in counts {
+                unpack_bits(reader.take(count as usize)?, width, &mut out)?;
+            }
+            Ok(out)
+        }
+        2 | 3 => bail!("ZIP-compressed PSD channels are not supported"),
+        _ => bail!("Unknown PSD compression {}", compression),
+    }
+}
+
+/// Append one PackBits row of `width` bytes to `out`
+fn unpack_bits(src: &[u8], width: usize, out: &mut Vec<u8>) -> Result<()> {
+    let start = out.len();
+    let mut i = 0;
+    while i < src.len() && out.len() - start < width {
+        let n = src[i] as i8;
+        i += 1;
+        if n >= 0 {
+            let count = n as usize + 1;
+            let literal = src.get(i..i + count).context("Corrupt RLE data")?;
+            out.extend_from_slice(literal);
+            i += count;
+        } else if n != -128 {
+            let value = *src.get(i).context("Corrupt RLE data")?;
+            out.extend(std::iter::repeat_n(value, (1 - n as isize) as usize));
+            i += 1;
+        }
+    }
+    out.resize(start + width, 0);
+    Ok(())
+}
+
+/// Combine color and alpha planes into RGBA; missing color is black, missing alpha opaque
+fn interleave(
+    planes: &[Option<Vec<u8>>; 4],
+    width: usize,
+    height: usize,
+    grayscale: bool,
+) -> RgbaImage {
+    let sample = |slot: usize, i: usize, default: u8| {
+        planes[slot]
+            .as_ref()
+            .and_then(|p| p.get(i).copied())
+            .unwrap_or(default)
+    };
+    let mut pixels = Vec::with_capacity(width * height * 4);
+    for i in 0..width * height {
+        if grayscale {
+            let gray = sample(0, i, 0);
+            pixels.extend_from_slice(&[gray, gray, gray]);
+        } else {
+            pixels.extend_from_slice(&[sample(0, i, 0), sample(1, i, 0), sample(2, i, 0)]);
+        }
+        pixels.push(sample(3, i, 255));
+    }
+    RgbaImage::from_raw(width as u32, height as u32, pixels)
+        .expect("pixel buffer matches dimensions")
+}
+
+/// Composite a layer over the canvas with normal blending and the layer's opacity
+fn blend_over(canvas: &mut RgbaImage, layer: &PsdLayer) {
+    let Some((left, top, image)) = &layer.image else {
+        return;
+    };
+    let opacity = layer.opacity as f32 / 255.0;
+    for (x, y, src) in image.enumerate_pixels() {
+        let (cx, cy) = (*left as i64 + x as i64, *top as i64 + y as i64);
+        if cx < 0 || cy < 0 || cx >= canvas.width() as i64 || cy >= canvas.height() as i64 {
+            continue;
+        }
+        let src_alpha = src[3] as f32 / 255.0 * opacity;
+        if src_alpha <= 0.0 {
+            continue;
+        }
+        let dst = canvas.get_pixel_mut(cx as u32, cy as u32);
+        let dst_alpha = dst[3] as f32 / 255.0;
+        let alpha = src_alpha + dst_alpha * (1.0 - src_alpha);
+        for c in 0..3 {
+            let color = src[c] as f32 * src_alpha + dst[c] as f32 * dst_alpha * (1.0 - src_alpha);
+            dst[c] = (color / alpha).round() as u8;
+        }
+        dst[3] = (alpha * 255.0).round() as u8;
+    }
+}
+
+/// Big-endian cursor over the document bytes
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .context("Truncated PSD")?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// A block prefixed by its 32-bit length
+    fn section(&mut self) -> Result<Reader<'a>> {
+        let len = self.u32()? as usize;
+        Ok(Reader::new(self.take(len)?))
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into()?))
+    }
+
+    fn i16(&mut self) -> Result<i16> {
+        Ok(i16::from_be_bytes(self.take(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into()?))
+    }
+}
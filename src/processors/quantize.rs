@@ -0,0 +1,121 @@
+use anyhow::{Context, Result};
+use color_quant::NeuQuant;
+use image::RgbaImage;
+use std::collections::HashMap;
+
+use crate::cli::QualityPreset;
+
+/// An image reduced to a palette of at most 256 RGBA colors
+#[derive(Debug, Clone)]
+pub struct PaletteImage {
+    pub width: u32,
+    pub height: u32,
+    pub palette: Vec<[u8; 4]>,
+    /// One palette index per pixel, row by row
+    pub indices: Vec<u8>,
+}
+
+impl PaletteImage {
+    /// Peak signal-to-noise ratio against the source over visible RGBA, in dB (infinite when exact)
+    pub fn psnr(&self, source: &RgbaImage) -> f64 {
+        let mut squared_error = 0u64;
+        for (pixel, &index) in source.pixels().zip(&self.indices) {
+            let mapped = self.palette[index as usize];
+            // The color of invisible pixels doesn't matter
+            let channels = if pixel[3] == 0 && mapped[3] == 0 { 3..4 } else { 0..4 };
+            for c in channels {
+                let diff = pixel[c] as i64 - mapped[c] as i64;
+                squared_error += (diff * diff) as u64;
+            }
+        }
+        if squared_error == 0 {
+            return f64::INFINITY;
+        }
+        let mse = squared_error as f64 / (source.as_raw().len() as f64);
+        10.0 * (255.0f64 * 255.0 / mse).log10()
+    }
+
+    /// Expand back to RGBA
+    pub fn to_rgba(&self) -> RgbaImage {
+        let pixels = self.indices.iter().flat_map(|&i| self.palette[i as usize]).collect();
+        RgbaImage::from_raw(self.width, self.height, pixels).expect("palette image size")
+    }
+
+    /// Encode as an 8-bit indexed PNG (oxipng reduces the bit depth afterwards)
+    pub fn to_png(&self) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut data, self.width, self.height);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(self.palette.iter().flat_map(|c| [c[0], c[1], c[2]]).collect::<Vec<u8>>());
+        if self.palette.iter().any(|c| c[3] < 255) {
+            encoder.set_trns(self.palette.iter().map(|c| c[3]).collect::<Vec<u8>>());
+        }
+        let mut writer = encoder.write_header()?;
+        writer
+            .write_image_data(&self.indices)
+            .context("Failed to encode indexed PNG")?;
+        writer.finish()?;
+        Ok(data)
+    }
+}
+
+/// Palette size, NeuQuant sampling factor (1 = best, 30 = fastest) and lowest acceptable PSNR
+/// for a quality preset, or None for presets that stay lossless
+pub fn quantize_settings(quality: QualityPreset) -> Option<(usize, i32, f64)> {
+    match quality {
+        QualityPreset::Fast => Some((128, 10, 30.0)),
+        QualityPreset::Balanced => Some((256, 3, 34.0)),
+        QualityPreset::High | QualityPreset::Ultra => None,
+    }
+}
+
+/// Reduce an image to at most `max_colors` colors, pngquant-style
+///
+/// Images that already fit are indexed exactly. Fully transparent pixels share one reserved
+/// entry so they stay invisible, and the rest are mapped through a NeuQuant palette.
+pub fn quantize_image(img: &RgbaImage, max_colors: usize, sample_factor: i32) -> PaletteImage {
+    let (width, height) = img.dimensions();
+    let clear = |p: &image::Rgba<u8>| if p[3] == 0 { [0, 0, 0, 0] } else { p.0 };
+
+    let mut exact: HashMap<[u8; 4], u8> = HashMap::new();
+    for pixel in img.pixels() {
+        let color = clear(pixel);
+        if !exact.contains_key(&color) {
+            if exact.len() == max_colors {
+                break;
+            }
+            exact.insert(color, exact.len() as u8);
+        }
+    }
+    if exact.len() < max_colors || img.pixels().all(|p| exact.contains_key(&clear(p))) {
+        let mut palette = vec![[0u8; 4]; exact.len()];
+        for (color, &index) in &exact {
+            palette[index as usize] = *color;
+        }
+        let indices = img.pixels().map(|p| exact[&clear(p)]).collect();
+        return PaletteImage { width, height, palette, indices };
+    }
+
+    let has_clear = img.pixels().any(|p| p[3] == 0);
+    let visible: Vec<u8> = img
+        .pixels()
+        .filter(|p| p[3] > 0)
+        .flat_map(|p| p.0)
+        .collect();
+    let colors = if has_clear { max_colors - 1 } else { max_colors };
+    let quant = NeuQuant::new(sample_factor, colors, &visible);
+
+    let offset = has_clear as usize;
+    let mut palette = Vec::with_capacity(max_colors);
+    if has_clear {
+        palette.push([0, 0, 0, 0]);
+    }
+    palette.extend(quant.color_map_rgba().chunks_exact(4).map(|c| [c[0], c[1], c[2], c[3]]));
+    let indices = img
+        .pixels()
+        .map(|p| if p[3] == 0 { 0 } else { (quant.index_of(&p.0) + offset) as u8 })
+        .collect();
+
+    PaletteImage { width, height, palette, indices }
+}
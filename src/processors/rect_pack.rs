@@ -0,0 +1,276 @@
+use crate::cli::AtlasPacker;
+
+/// Where a rectangle was placed
+///
+/// Rotated rectangles are turned 90° clockwise, so `width` and `height` are the input's
+/// height and width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub rotated: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+impl Rect {
+    fn right(&self) -> u32 {
+        self.x + self.w
+    }
+
+    fn bottom(&self) -> u32 {
+        self.y + self.h
+    }
+
+    fn contains(&self, other: &Rect) -> bool {
+        other.x >= self.x && other.y >= self.y && other.right() <= self.right() && other.bottom() <= self.bottom()
+    }
+
+    fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.right() && other.x < self.right() && self.y < other.bottom() && other.y < self.bottom()
+    }
+}
+
+/// A horizontal segment of the skyline's top edge
+#[derive(Debug, Clone, Copy)]
+struct Skyline {
+    x: u32,
+    y: u32,
+    w: u32,
+}
+
+enum PackerState {
+    /// Top edge of the packed area, sorted by x
+    Skyline(Vec<Skyline>),
+    /// Maximal free rectangles, which may overlap
+    MaxRects(Vec<Rect>),
+    /// Disjoint free rectangles left by guillotine cuts
+    Guillotine(Vec<Rect>),
+}
+
+/// Places rectangles one at a time into a fixed area
+pub struct RectPacker {
+    width: u32,
+    height: u32,
+    allow_rotation: bool,
+    /// Right and bottom edges of everything placed so far
+    used: (u32, u32),
+    state: PackerState,
+}
+
+impl RectPacker {
+    pub fn new(algorithm: AtlasPacker, width: u32, height: u32, allow_rotation: bool) -> Self {
+        let area = Rect { x: 0, y: 0, w: width, h: height };
+        let state = match algorithm {
+            AtlasPacker::Skyline => PackerState::Skyline(vec![Skyline { x: 0, y: 0, w: width }]),
+            AtlasPacker::MaxRects => PackerState::MaxRects(vec![area]),
+            AtlasPacker::Guillotine => PackerState::Guillotine(vec![area]),
+        };
+        Self { width, height, allow_rotation, used: (0, 0), state }
+    }
+
+    /// Mark an area as taken before anything is inserted
+    ///
+    /// Only MaxRects can keep free space around arbitrary taken areas; the other packers
+    /// ignore reservations.
+    pub fn reserve(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        if let PackerState::MaxRects(free) = &mut self.state {
+            maxrects_place(free, &Rect { x, y, w: width, h: height });
+        }
+        self.used = (self.used.0.max(x + width), self.used.1.max(y + height));
+    }
+
+    /// Place a `width` x `height` rectangle, or `None` if there's no room left for it
+    pub fn insert(&mut self, width: u32, height: u32) -> Option<Placement> {
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let orientations: &[(u32, u32, bool)] = if self.allow_rotation && width != height {
+            &[(width, height, false), (height, width, true)]
+        } else {
+            &[(width, height, false)]
+        };
+
+        let (area_width, area_height) = (self.width, self.height);
+        let used = self.used;
+        let placed = match &mut self.state {
+            PackerState::Skyline(skylines) => {
+                // Lowest bottom edge, then the narrowest segment
+                let mut best: Option<((u32, u32), usize, Rect, bool)> = None;
+                for i in 0..skylines.len() {
+                    for &(w, h, rotated) in orientations {
+                        let Some(rect) = skyline_fit(skylines, i, w, h, (area_width, area_height)) else {
+                            continue;
+                        };
+                        let score = (rect.bottom(), skylines[i].w);
+                        if best.is_none_or(|(best_score, ..)| score < best_score) {
+                            best = Some((score, i, rect, rotated));
+                        }
+                    }
+                }
+                let (_, index, rect, rotated) = best?;
+                skyline_place(skylines, index, &rect);
+                (rect, rotated)
+            }
+            PackerState::MaxRects(free) => {
+                let (_, rect, rotated) = best_area_fit(free, orientations, used)?;
+                maxrects_place(free, &rect);
+                (rect, rotated)
+            }
+            PackerState::Guillotine(free) => {
+                let (index, rect, rotated) = best_area_fit(free, orientations, used)?;
+                let split = free.swap_remove(index);
+                guillotine_split(free, &split, &rect);
+                (rect, rotated)
+            }
+        };
+
+        let (rect, rotated) = placed;
+        self.used = (self.used.0.max(rect.right()), self.used.1.max(rect.bottom()));
+        Some(Placement { x: rect.x, y: rect.y, width: rect.w, height: rect.h, rotated })
+    }
+}
+
+/// Where a rectangle would sit on the skyline starting at segment `i`, if it fits
+fn skyline_fit(skylines: &[Skyline], i: usize, w: u32, h: u32, (width, height): (u32, u32)) -> Option<Rect> {
+    let x = skylines[i].x;
+    if x + w > width {
+        return None;
+    }
+    let mut y = 0;
+    let mut covered = 0;
+    for skyline in &skylines[i..] {
+        y = y.max(skyline.y);
+        if y + h > height {
+            return None;
+        }
+        covered += skyline.w;
+        if covered >= w {
+            return Some(Rect { x, y, w, h });
+        }
+    }
+    None
+}
+
+/// Raise the skyline over a placed rectangle and join segments at the same height
+fn skyline_place(skylines: &mut Vec<Skyline>, index: usize, rect: &Rect) {
+    skylines.insert(index, Skyline { x: rect.x, y: rect.bottom(), w: rect.w });
+
+    let i = index + 1;
+    while i < skylines.len() {
+        let shadow = skylines[i - 1].x + skylines[i - 1].w;
+        if skylines[i].x >= shadow {
+            break;
+        }
+        let shrink = shadow - skylines[i].x;
+        if skylines[i].w <= shrink {
+            skylines.remove(i);
+        } else {
+            skylines[i].x += shrink;
+            skylines[i].w -= shrink;
+            break;
+        }
+    }
+
+    let mut i = 1;
+    while i < skylines.len() {
+        if skylines[i - 1].y == skylines[i].y {
+            skylines[i - 1].w += skylines[i].w;
+            skylines.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+}
+
+/// Bounds growth, wasted area and short leftover side of a spot: lower is better
+type FitScore = (u64, u64, u32);
+
+/// Free rectangle leaving the least area unused (then the smallest leftover side)
+///
+/// Spots that grow the bounds of what is already `used` the least come first, so the page
+/// stays compact instead of scattering sprites across the whole area.
+fn best_area_fit(free: &[Rect], orientations: &[(u32, u32, bool)], used: (u32, u32)) -> Option<(usize, Rect, bool)> {
+    let used_area = used.0 as u64 * used.1 as u64;
+    let mut best: Option<(FitScore, usize, Rect, bool)> = None;
+    for (index, space) in free.iter().enumerate() {
+        for &(w, h, rotated) in orientations {
+            if w > space.w || h > space.h {
+                continue;
+            }
+            let bounds = used.0.max(space.x + w) as u64 * used.1.max(space.y + h) as u64;
+            let score = (
+                bounds - used_area,
+                space.w as u64 * space.h as u64 - w as u64 * h as u64,
+                (space.w - w).min(space.h - h),
+            );
+            if best.is_none_or(|(best_score, ..)| score < best_score) {
+                best = Some((score, index, Rect { x: space.x, y: space.y, w, h }, rotated));
+            }
+        }
+    }
+    best.map(|(_, index, rect, rotated)| (index, rect, rotated))
+}
+
+/// Cut a placed rectangle out of every free rectangle it overlaps, keeping only maximal ones
+fn maxrects_place(free: &mut Vec<Rect>, used: &Rect) {
+    let mut split = Vec::new();
+    free.retain(|space| {
+        if !space.intersects(used) {
+            return true;
+        }
+        if used.x > space.x {
+            split.push(Rect { w: used.x - space.x, ..*space });
+        }
+        if used.right() < space.right() {
+            split.push(Rect { x: used.right(), w: space.right() - used.right(), ..*space });
+        }
+        if used.y > space.y {
+            split.push(Rect { h: used.y - space.y, ..*space });
+        }
+        if used.bottom() < space.bottom() {
+            split.push(Rect { y: used.bottom(), h: space.bottom() - used.bottom(), ..*space });
+        }
+        false
+    });
+    free.extend(split);
+
+    // Drop free rectangles another one contains (the first of two equal ones is kept)
+    let mut i = 0;
+    while i < free.len() {
+        let contained = (0..free.len()).any(|j| j != i && free[j].contains(&free[i]) && (free[j] != free[i] || j < i));
+        if contained {
+            free.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+}
+
+/// Split the rest of a free rectangle in two, keeping the larger piece as large as possible
+fn guillotine_split(free: &mut Vec<Rect>, space: &Rect, used: &Rect) {
+    let leftover_w = space.w - used.w;
+    let leftover_h = space.h - used.h;
+    let (right, bottom) = if used.w as u64 * leftover_h as u64 > leftover_w as u64 * used.h as u64 {
+        // The bottom piece spans the full width
+        (
+            Rect { x: used.right(), y: space.y, w: leftover_w, h: used.h },
+            Rect { x: space.x, y: used.bottom(), w: space.w, h: leftover_h },
+        )
+    } else {
+        // The right piece spans the full height
+        (
+            Rect { x: used.right(), y: space.y, w: leftover_w, h: space.h },
+            Rect { x: space.x, y: used.bottom(), w: used.w, h: leftover_h },
+        )
+    };
+    free.extend([right, bottom].into_iter().filter(|rect| rect.w > 0 && rect.h > 0));
+}
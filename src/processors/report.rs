@@ -0,0 +1,409 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::utils::format_size;
+
+use super::{BuildManifest, ManifestEntry, QualityMetrics, LOW_SSIM};
+
+/// Number of rows shown in the growers, shrinkers and quality tables
+const TOP_CHANGES: usize = 10;
+
+/// Size change of an asset present in both manifests
+struct SizeChange<'a> {
+    source: &'a str,
+    before: u64,
+    after: u64,
+}
+
+impl SizeChange<'_> {
+    fn delta(&self) -> i64 {
+        self.after as i64 - self.before as i64
+    }
+}
+
+/// Render a Markdown build report comparing two manifests, suitable for a PR comment
+///
+/// `errors` holds `(source, message)` pairs for assets that failed to build.
+pub fn markdown_report(
+    previous: &BuildManifest,
+    current: &BuildManifest,
+    errors: &[(String, String)],
+) -> String {
+    let before: HashMap<&str, &ManifestEntry> =
+        previous.assets.iter().map(|e| (e.source.as_str(), e)).collect();
+    let after: HashMap<&str, &ManifestEntry> =
+        current.assets.iter().map(|e| (e.source.as_str(), e)).collect();
+
+    let mut changes: Vec<SizeChange> = current
+        .assets
+        .iter()
+        .filter_map(|e| {
+            let old = before.get(e.source.as_str())?;
+            Some(SizeChange { source: &e.source, before: old.size, after: e.size })
+        })
+        .filter(|c| c.delta() != 0)
+        .collect();
+
+    let added: Vec<&ManifestEntry> = current
+        .assets
+        .iter()
+        .filter(|e| !before.contains_key(e.source.as_str()))
+        .collect();
+    // Failed assets are listed under errors, not as removed
+    let removed: Vec<&ManifestEntry> = previous
+        .assets
+        .iter()
+        .filter(|e| !after.contains_key(e.source.as_str()))
+        .filter(|e| !errors.iter().any(|(source, _)| *source == e.source))
+        .collect();
+
+    // Aliased duplicates share an output, so count it once
+    let total_before: u64 = previous.assets.iter().filter(|e| e.alias_of.is_none()).map(|e| e.size).sum();
+    let total_after: u64 = current.assets.iter().filter(|e| e.alias_of.is_none()).map(|e| e.size).sum();
+
+    let mut md = String::new();
+    md.push_str("## Asset build report\n\n");
+    md.push_str("| | Before | After | Change |\n|---|---:|---:|---:|\n");
+    md.push_str(&format!(
+        "| Total size | {} | {} | {} |\n",
+        format_size(total_before),
+        format_size(total_after),
+        format_delta(total_before, total_after)
+    ));
+    md.push_str(&format!(
+        "| Assets | {} | {} | {:+} |\n",
+        previous.assets.len(),
+        current.assets.len(),
+        current.assets.len() as i64 - previous.assets.len() as i64
+    ));
+
+    // Largest absolute changes first
+    changes.sort_by(|a, b| b.delta().cmp(&a.delta()).then(a.source.cmp(b.source)));
+    let growers: Vec<&SizeChange> = changes.iter().filter(|c| c.delta() > 0).take(TOP_CHANGES).collect();
+    let shrinkers: Vec<&SizeChange> = changes.iter().rev().filter(|c| c.delta() < 0).take(TOP_CHANGES).collect();
+
+    push_changes(&mut md, "Top growers", &growers);
+    push_changes(&mut md, "Top shrinkers", &shrinkers);
+
+    if !added.is_empty() {
+        md.push_str(&format!("\n### New assets ({})\n\n", added.len()));
+        md.push_str("| Asset | Size |\n|---|---:|\n");
+        for entry in &added {
+            md.push_str(&format!("| `{}` | {} |\n", entry.source, format_size(entry.size)));
+        }
+    }
+
+    if !removed.is_empty() {
+        md.push_str(&format!("\n### Removed assets ({})\n\n", removed.len()));
+        md.push_str("| Asset | Size |\n|---|---:|\n");
+        for entry in &removed {
+            md.push_str(&format!("| `{}` | {} |\n", entry.source, format_size(entry.size)));
+        }
+    }
+
+    // Lowest SSIM first; outputs rather than sources, since variants share a source
+    let mut low_quality: Vec<(&ManifestEntry, QualityMetrics)> = current
+        .assets
+        .iter()
+        .filter_map(|e| Some((e, e.quality?)))
+        .filter(|(_, quality)| quality.ssim < LOW_SSIM)
+        .collect();
+    low_quality.sort_by(|a, b| a.1.ssim.total_cmp(&b.1.ssim).then(a.0.output.cmp(&b.0.output)));
+    if !low_quality.is_empty() {
+        md.push_str(&format!(
+            "\n### Possibly over-compressed ({} below {} SSIM)\n\n",
+            low_quality.len(),
+            LOW_SSIM
+        ));
+        md.push_str("| Output | PSNR | SSIM |\n|---|---:|---:|\n");
+        for (entry, quality) in low_quality.iter().take(TOP_CHANGES) {
+            md.push_str(&format!("| `{}` | {:.1} dB | {:.4} |\n", entry.output, quality.psnr, quality.ssim));
+        }
+    }
+
+    if !errors.is_empty() {
+        md.push_str(&format!("\n### Errors ({})\n\n", errors.len()));
+        for (source, message) in errors {
+            md.push_str(&format!("- `{}`: {}\n", source, message.replace('\n', " ")));
+        }
+    }
+
+    if changes.is_empty() && added.is_empty() && removed.is_empty() && errors.is_empty() {
+        md.push_str("\nNo asset changes.\n");
+    }
+
+    md
+}
+
+fn push_changes(md: &mut String, title: &str, changes: &[&SizeChange]) {
+    if changes.is_empty() {
+        return;
+    }
+
+    md.push_str(&format!("\n### {}\n\n", title));
+    md.push_str("| Asset | Before | After | Change |\n|---|---:|---:|---:|\n");
+    for change in changes {
+        md.push_str(&format!(
+            "| `{}` | {} | {} | {} |\n",
+            change.source,
+            format_size(change.before),
+            format_size(change.after),
+            format_delta(change.before, change.after)
+        ));
+    }
+}
+
+/// Signed size change with percentage, e.g. `+1.20 KB (+4.5%)`
+fn format_delta(before: u64, after: u64) -> String {
+    let sign = if after >= before { "+" } else { "-" };
+    let size = format_size(after.abs_diff(before));
+
+    if before == 0 {
+        return format!("{}{}", sign, size);
+    }
+
+    let percent = (after as f64 - before as f64) / before as f64 * 100.0;
+    format!("{}{} ({:+.1}%)", sign, size, percent)
+}
+
+/// Rows in each table of the HTML report
+const HTML_TOP: usize = 25;
+
+/// Structured build report (`build --report-json`), for CI dashboards instead of console output
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildReport {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Wall-clock time of the whole build
+    pub elapsed_secs: f64,
+    pub totals: ReportTotals,
+    /// One row per source asset, sorted by source path
+    pub assets: Vec<AssetReport>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReportTotals {
+    pub built: usize,
+    pub cached: usize,
+    pub failed: usize,
+    pub input_size: u64,
+    /// Output bytes, counting outputs shared by aliases once
+    pub output_size: u64,
+}
+
+/// What the build did with a source asset
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetStatus {
+    Built,
+    /// Skipped because the cache entry was still valid
+    Cached,
+    /// Shares the output of an identical file (`--dedup`)
+    Aliased,
+    /// Stand-in generated for a missing expected asset
+    Placeholder,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetReport {
+    pub source: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub asset_type: Option<String>,
+    pub status: AssetStatus,
+    pub input_size: u64,
+    /// Total size of the asset's outputs
+    pub output_size: u64,
+    /// `output_size / input_size`; below 1 means the output is smaller
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ratio: Option<f64>,
+    /// Processing time, for assets built on their own this run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secs: Option<f64>,
+    pub outputs: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl BuildReport {
+    /// Assemble the report from the manifest, the build's errors and its per-source results
+    ///
+    /// `input_sizes` and `timings` are keyed by source path; sources in `cache_hits` were
+    /// skipped.
+    pub fn new(
+        manifest: &BuildManifest,
+        errors: &[(String, String)],
+        input_sizes: &HashMap<String, u64>,
+        timings: &HashMap<String, f64>,
+        cache_hits: &HashSet<String>,
+    ) -> Self {
+        let mut assets: BTreeMap<&str, AssetReport> = BTreeMap::new();
+        for entry in &manifest.assets {
+            let status = if entry.placeholder {
+                AssetStatus::Placeholder
+            } else if entry.alias_of.is_some() {
+                AssetStatus::Aliased
+            } else if cache_hits.contains(&entry.source) {
+                AssetStatus::Cached
+            } else {
+                AssetStatus::Built
+            };
+            let asset = assets.entry(&entry.source).or_insert_with(|| AssetReport {
+                source: entry.source.clone(),
+                asset_type: entry.asset_type.clone(),
+                status,
+                input_size: input_sizes.get(&entry.source).copied().unwrap_or(0),
+                output_size: 0,
+                ratio: None,
+                secs: timings.get(&entry.source).copied(),
+                outputs: Vec::new(),
+                error: None,
+            });
+            if !asset.outputs.contains(&entry.output) {
+                asset.output_size += entry.size;
+                asset.outputs.push(entry.output.clone());
+            }
+        }
+        // A failed post-processing step (e.g. compression) still fails an asset with outputs
+        for (source, message) in errors {
+            let asset = assets.entry(source).or_insert_with(|| AssetReport {
+                source: source.clone(),
+                asset_type: None,
+                status: AssetStatus::Failed,
+                input_size: input_sizes.get(source).copied().unwrap_or(0),
+                output_size: 0,
+                ratio: None,
+                secs: timings.get(source).copied(),
+                outputs: Vec::new(),
+                error: None,
+            });
+            asset.status = AssetStatus::Failed;
+            asset.error = Some(message.clone());
+        }
+
+        let mut totals = ReportTotals::default();
+        let mut counted: HashSet<&str> = HashSet::new();
+        for entry in &manifest.assets {
+            if counted.insert(&entry.output) {
+                totals.output_size += entry.size;
+            }
+        }
+        let assets: Vec<AssetReport> = assets
+            .into_values()
+            .map(|mut asset| {
+                asset.ratio = (asset.input_size > 0 && asset.output_size > 0)
+                    .then(|| asset.output_size as f64 / asset.input_size as f64);
+                match asset.status {
+                    AssetStatus::Built => totals.built += 1,
+                    AssetStatus::Cached => totals.cached += 1,
+                    AssetStatus::Failed => totals.failed += 1,
+                    AssetStatus::Aliased | AssetStatus::Placeholder => {}
+                }
+                if asset.status != AssetStatus::Aliased {
+                    totals.input_size += asset.input_size;
+                }
+                asset
+            })
+            .collect();
+
+        Self { preset: None, profile: None, elapsed_secs: 0.0, totals, assets }
+    }
+}
+
+/// Render a standalone HTML summary: totals, the largest outputs, the worst compression
+/// ratios, the slowest assets and every error
+pub fn html_report(report: &BuildReport) -> String {
+    let built: Vec<&AssetReport> = report.assets.iter().filter(|a| a.output_size > 0).collect();
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Asset build report</title>\n");
+    html.push_str(
+        "<style>\nbody { font: 14px sans-serif; margin: 2em; }\n\
+         table { border-collapse: collapse; margin-bottom: 2em; }\n\
+         th, td { padding: 4px 12px; border-bottom: 1px solid #ddd; text-align: left; }\n\
+         td.num, th.num { text-align: right; }\n.failed { color: #b00; }\n</style>\n",
+    );
+    html.push_str("</head>\n<body>\n<h1>Asset build report</h1>\n");
+
+    let setting = |name: &str, value: &Option<String>| {
+        value.as_ref().map(|v| format!("{}: <code>{}</code> ", name, escape_html(v))).unwrap_or_default()
+    };
+    html.push_str(&format!(
+        "<p>{}{}{:.1}s</p>\n",
+        setting("Preset", &report.preset),
+        setting("Profile", &report.profile),
+        report.elapsed_secs
+    ));
+
+    let totals = &report.totals;
+    html.push_str("<table>\n");
+    for (label, value) in [
+        ("Built", totals.built.to_string()),
+        ("Cached", totals.cached.to_string()),
+        ("Failed", totals.failed.to_string()),
+        ("Input size", format_size(totals.input_size)),
+        ("Output size", format_size(totals.output_size)),
+    ] {
+        html.push_str(&format!("<tr><th>{}</th><td class=\"num\">{}</td></tr>\n", label, value));
+    }
+    html.push_str("</table>\n");
+
+    let mut largest = built.clone();
+    largest.sort_by(|a, b| b.output_size.cmp(&a.output_size).then(a.source.cmp(&b.source)));
+    push_html_table(&mut html, "Largest outputs", &largest);
+
+    let mut worst_ratio: Vec<&AssetReport> = built.iter().copied().filter(|a| a.ratio.is_some()).collect();
+    worst_ratio.sort_by(|a, b| b.ratio.unwrap_or(0.0).total_cmp(&a.ratio.unwrap_or(0.0)).then(a.source.cmp(&b.source)));
+    push_html_table(&mut html, "Worst compression", &worst_ratio);
+
+    let mut slowest: Vec<&AssetReport> = report.assets.iter().filter(|a| a.secs.is_some()).collect();
+    slowest.sort_by(|a, b| b.secs.unwrap_or(0.0).total_cmp(&a.secs.unwrap_or(0.0)).then(a.source.cmp(&b.source)));
+    push_html_table(&mut html, "Slowest assets", &slowest);
+
+    let failed: Vec<&AssetReport> = report.assets.iter().filter(|a| a.status == AssetStatus::Failed).collect();
+    if !failed.is_empty() {
+        html.push_str(&format!("<h2>Errors ({})</h2>\n<ul>\n", failed.len()));
+        for asset in failed {
+            html.push_str(&format!(
+                "<li class=\"failed\"><code>{}</code>: {}</li>\n",
+                escape_html(&asset.source),
+                escape_html(asset.error.as_deref().unwrap_or_default())
+            ));
+        }
+        html.push_str("</ul>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn push_html_table(html: &mut String, title: &str, assets: &[&AssetReport]) {
+    if assets.is_empty() {
+        return;
+    }
+
+    html.push_str(&format!("<h2>{}</h2>\n<table>\n", title));
+    html.push_str(
+        "<tr><th>Asset</th><th>Status</th><th class=\"num\">Input</th><th class=\"num\">Output</th>\
+         <th class=\"num\">Ratio</th><th class=\"num\">Time</th></tr>\n",
+    );
+    for asset in assets.iter().take(HTML_TOP) {
+        html.push_str(&format!(
+            "<tr><td><code>{}</code></td><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td>\
+             <td class=\"num\">{}</td><td class=\"num\">{}</td></tr>\n",
+            escape_html(&asset.source),
+            serde_json::to_value(asset.status).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default(),
+            format_size(asset.input_size),
+            format_size(asset.output_size),
+            asset.ratio.map(|r| format!("{:.2}", r)).unwrap_or_default(),
+            asset.secs.map(|s| format!("{:.2}s", s)).unwrap_or_default()
+        ));
+    }
+    html.push_str("</table>\n");
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
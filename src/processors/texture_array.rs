@@ -0,0 +1,64 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::{compress_array_to_ktx2, is_hdr_path, validate_ktx2, AssetType, BasisConfig, ProcessingStats};
+
+pub use crate::runtime::TextureArrayIndex;
+
+/// Result of packing a texture array
+pub struct TextureArrayResult {
+    pub index: TextureArrayIndex,
+    /// Layer index JSON written next to the texture
+    pub index_path: PathBuf,
+    pub stats: ProcessingStats,
+}
+
+/// Pack the images in a directory into the layers of one KTX2 texture array
+///
+/// Layers are ordered by file name and named by file stem; their names go to a
+/// [`TextureArrayIndex`] next to the texture (`terrain.ktx2` → `terrain.json`). Every image
+/// must have the same size. HDR images are skipped, since the array is 8-bit.
+pub fn generate_texture_array(input_dir: &Path, output: &Path, config: &BasisConfig) -> Result<TextureArrayResult> {
+    let mut image_paths: Vec<PathBuf> = std::fs::read_dir(input_dir)
+        .with_context(|| format!("Failed to read directory: {}", input_dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && AssetType::from_path(path) == AssetType::Image && !is_hdr_path(path))
+        .collect();
+    // Sort for a stable layer order
+    image_paths.sort();
+
+    if image_paths.is_empty() {
+        anyhow::bail!("No image files found in directory: {}", input_dir.display());
+    }
+
+    let mut layers = Vec::with_capacity(image_paths.len());
+    let mut names: HashMap<String, &Path> = HashMap::new();
+    for path in &image_paths {
+        let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_string();
+        if let Some(other) = names.insert(name.clone(), path) {
+            anyhow::bail!("{} and {} would both be layer \"{}\"", other.display(), path.display(), name);
+        }
+        layers.push(name);
+    }
+
+    let inputs: Vec<&Path> = image_paths.iter().map(PathBuf::as_path).collect();
+    let stats = compress_array_to_ktx2(&inputs, output, config)?;
+    let (width, height, _) = validate_ktx2(&std::fs::read(output)?)?;
+
+    let index = TextureArrayIndex {
+        texture: output
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        width,
+        height,
+        layers,
+    };
+    let index_path = output.with_extension("json");
+    std::fs::write(&index_path, serde_json::to_string_pretty(&index)?)
+        .with_context(|| format!("Failed to write texture array index: {}", index_path.display()))?;
+
+    Ok(TextureArrayResult { index, index_path, stats })
+}
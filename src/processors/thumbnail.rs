@@ -0,0 +1,248 @@
+use anyhow::{Context, Result};
+use image::{imageops::FilterType, Rgba, RgbaImage};
+use std::path::Path;
+
+/// Supersampling factor used to anti-alias thumbnails
+const SUPERSAMPLE: u32 = 2;
+
+/// Direction the camera looks from (three-quarter view, slightly above)
+const VIEW_DIR: [f32; 3] = [0.6, 0.45, 0.75];
+/// Direction towards the key light
+const LIGHT_DIR: [f32; 3] = [0.3, 0.8, 0.5];
+
+/// A world-space triangle with a flat color
+struct Triangle {
+    positions: [[f32; 3]; 3],
+    color: [f32; 4],
+}
+
+/// Render a small preview of a glTF/GLB model with a software rasterizer
+///
+/// The model is fitted to the frame with an orthographic three-quarter camera,
+/// flat shaded with its base color factors, on a transparent background.
+pub fn render_model_thumbnail(input: &Path, output: &Path, size: u32) -> Result<()> {
+    let (document, buffers, _images) = gltf::import(input)
+        .with_context(|| format!("Failed to import glTF: {}", input.display()))?;
+
+    let mut triangles = Vec::new();
+    let scene = document.default_scene().or_else(|| document.scenes().next());
+    match scene {
+        Some(scene) => {
+            for node in scene.nodes() {
+                collect_triangles(&node, IDENTITY, &buffers, &mut triangles);
+            }
+        }
+        // No scene: draw every mesh untransformed
+        None => {
+            for mesh in document.meshes() {
+                collect_mesh(&mesh, IDENTITY, &buffers, &mut triangles);
+            }
+        }
+    }
+
+    if triangles.is_empty() {
+        anyhow::bail!("Model has no triangles to render: {}", input.display());
+    }
+
+    let image = rasterize(&triangles, size.max(1) * SUPERSAMPLE);
+    let image = image::imageops::resize(&image, size.max(1), size.max(1), FilterType::Triangle);
+
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    image.save(output)
+        .with_context(|| format!("Failed to save thumbnail: {}", output.display()))?;
+
+    Ok(())
+}
+
+pub(super) type Mat4 = [[f32; 4]; 4];
+
+pub(super) const IDENTITY: Mat4 = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+/// Multiply column-major matrices (`a * b`)
+pub(super) fn mul(a: &Mat4, b: &Mat4) -> Mat4 {
+    let mut out = [[0.0; 4]; 4];
+    for (col, out_col) in out.iter_mut().enumerate() {
+        for (row, value) in out_col.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][row] * b[col][k]).sum();
+        }
+    }
+    out
+}
+
+pub(super) fn transform_point(m: &Mat4, p: [f32; 3]) -> [f32; 3] {
+    let mut out = [0.0; 3];
+    for (row, value) in out.iter_mut().enumerate() {
+        *value = m[0][row] * p[0] + m[1][row] * p[1] + m[2][row] * p[2] + m[3][row];
+    }
+    out
+}
+
+fn collect_triangles(
+    node: &gltf::Node,
+    parent: Mat4,
+    buffers: &[gltf::buffer::Data],
+    triangles: &mut Vec<Triangle>,
+) {
+    let transform = mul(&parent, &node.transform().matrix());
+
+    if let Some(mesh) = node.mesh() {
+        collect_mesh(&mesh, transform, buffers, triangles);
+    }
+    for child in node.children() {
+        collect_triangles(&child, transform, buffers, triangles);
+    }
+}
+
+fn collect_mesh(
+    mesh: &gltf::Mesh,
+    transform: Mat4,
+    buffers: &[gltf::buffer::Data],
+    triangles: &mut Vec<Triangle>,
+) {
+    for primitive in mesh.primitives() {
+        if primitive.mode() != gltf::mesh::Mode::Triangles {
+            continue;
+        }
+
+        let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+        let Some(positions) = reader.read_positions() else { continue };
+        let positions: Vec<[f32; 3]> = positions.map(|p| transform_point(&transform, p)).collect();
+
+        let indices: Vec<u32> = match reader.read_indices() {
+            Some(iter) => iter.into_u32().collect(),
+            None => (0..positions.len() as u32).collect(),
+        };
+
+        let color = primitive.material().pbr_metallic_roughness().base_color_factor();
+
+        for tri in indices.chunks_exact(3) {
+            let fetch = |i: u32| positions.get(i as usize).copied();
+            if let (Some(a), Some(b), Some(c)) = (fetch(tri[0]), fetch(tri[1]), fetch(tri[2])) {
+                triangles.push(Triangle { positions: [a, b, c], color });
+            }
+        }
+    }
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let len = dot(v, v).sqrt();
+    if len > 0.0 {
+        [v[0] / len, v[1] / len, v[2] / len]
+    } else {
+        v
+    }
+}
+
+/// Rasterize triangles into a square image with a z-buffer
+fn rasterize(triangles: &[Triangle], size: u32) -> RgbaImage {
+    // Camera basis: forward looks towards the origin from VIEW_DIR
+    let back = normalize(VIEW_DIR);
+    let right = normalize(cross([0.0, 1.0, 0.0], back));
+    let up = cross(back, right);
+    let light = normalize(LIGHT_DIR);
+
+    // Fit the bounding box in view space
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    let to_view = |p: [f32; 3]| [dot(p, right), dot(p, up), dot(p, back)];
+    for tri in triangles {
+        for p in tri.positions {
+            let v = to_view(p);
+            for i in 0..3 {
+                min[i] = min[i].min(v[i]);
+                max[i] = max[i].max(v[i]);
+            }
+        }
+    }
+
+    let extent = (max[0] - min[0]).max(max[1] - min[1]).max(f32::EPSILON);
+    let scale = size as f32 * 0.9 / extent;
+    let center = [(min[0] + max[0]) * 0.5, (min[1] + max[1]) * 0.5];
+    let half = size as f32 * 0.5;
+    let to_screen = |p: [f32; 3]| {
+        let v = to_view(p);
+        [
+            half + (v[0] - center[0]) * scale,
+            half - (v[1] - center[1]) * scale,
+            v[2], // Larger is closer to the camera
+        ]
+    };
+
+    let mut image = RgbaImage::from_pixel(size, size, Rgba([0, 0, 0, 0]));
+    let mut depth = vec![f32::MIN; (size * size) as usize];
+
+    for tri in triangles {
+        let [a, b, c] = tri.positions;
+        let normal = normalize(cross(sub(b, a), sub(c, a)));
+
+        // Light both sides so open meshes and flipped winding still read well
+        let facing = if dot(normal, back) < 0.0 { -1.0 } else { 1.0 };
+        let diffuse = (dot(normal, light) * facing).max(0.0);
+        let shade = 0.35 + 0.65 * diffuse;
+        let color = Rgba([
+            (tri.color[0] * shade * 255.0).clamp(0.0, 255.0) as u8,
+            (tri.color[1] * shade * 255.0).clamp(0.0, 255.0) as u8,
+            (tri.color[2] * shade * 255.0).clamp(0.0, 255.0) as u8,
+            (tri.color[3] * 255.0).clamp(0.0, 255.0) as u8,
+        ]);
+
+        let s = [to_screen(a), to_screen(b), to_screen(c)];
+        let area = edge(s[0], s[1], s[2]);
+        if area.abs() < f32::EPSILON {
+            continue;
+        }
+
+        let x0 = s.iter().map(|p| p[0]).fold(f32::MAX, f32::min).floor().max(0.0) as u32;
+        let x1 = s.iter().map(|p| p[0]).fold(f32::MIN, f32::max).ceil().min(size as f32 - 1.0) as u32;
+        let y0 = s.iter().map(|p| p[1]).fold(f32::MAX, f32::min).floor().max(0.0) as u32;
+        let y1 = s.iter().map(|p| p[1]).fold(f32::MIN, f32::max).ceil().min(size as f32 - 1.0) as u32;
+
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let p = [x as f32 + 0.5, y as f32 + 0.5, 0.0];
+                let w0 = edge(s[1], s[2], p) / area;
+                let w1 = edge(s[2], s[0], p) / area;
+                let w2 = edge(s[0], s[1], p) / area;
+                if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                    continue;
+                }
+
+                let z = w0 * s[0][2] + w1 * s[1][2] + w2 * s[2][2];
+                let index = (y * size + x) as usize;
+                if z > depth[index] {
+                    depth[index] = z;
+                    image.put_pixel(x, y, color);
+                }
+            }
+        }
+    }
+
+    image
+}
+
+fn edge(a: [f32; 3], b: [f32; 3], p: [f32; 3]) -> f32 {
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
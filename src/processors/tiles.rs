@@ -0,0 +1,229 @@
+use anyhow::{Context, Result};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, ImageFormat, RgbaImage};
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use super::{open_image, open_image_linear, process_image, ImageProcessorConfig};
+use crate::cli::OutputFormat;
+use crate::utils::hash_inputs;
+
+pub use crate::runtime::{TileEntry, TileLevel, VirtualTextureIndex, TILE_INDEX_FILE_NAME};
+
+/// Virtual texture tiling options
+#[derive(Debug, Clone)]
+pub struct TileConfig {
+    /// Texels per tile side, excluding the border
+    pub tile_size: u32,
+    /// Texels repeated from neighbouring tiles on each side
+    pub border: u32,
+    /// How each tile is encoded (format, quality, KTX2/ASTC settings)
+    pub image: ImageProcessorConfig,
+}
+
+impl Default for TileConfig {
+    fn default() -> Self {
+        Self {
+            tile_size: 256,
+            border: 4,
+            image: ImageProcessorConfig {
+                output_format: Some(OutputFormat::Png),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Result of tiling a texture
+#[derive(Debug)]
+pub struct TileResult {
+    pub index: VirtualTextureIndex,
+    pub index_path: PathBuf,
+    /// Tiles encoded in this run
+    pub tiles_written: usize,
+    /// Tiles whose hash matched a known tile on disk
+    pub tiles_reused: usize,
+    /// Hash of every tile in the pyramid keyed by tile path, for the build cache
+    pub hashes: Vec<(PathBuf, u64)>,
+    /// Total size of the tiles and index in bytes
+    pub output_size: u64,
+}
+
+struct TileOutcome {
+    entry: TileEntry,
+    path: PathBuf,
+    hash: u64,
+    reused: bool,
+    size: u64,
+}
+
+/// Slice a texture into a tile pyramid under `output_dir` and write its index
+///
+/// Level 0 is the full-resolution texture and each following level halves it until it fits
+/// in a single tile. Tiles are written to `<level>/<x>_<y>.<ext>`; a tile whose hash matches
+/// `known` and whose file still exists is kept as is, so editing part of a texture only
+/// re-encodes the tiles it touches. Tiles left over from a previous index are deleted.
+pub fn generate_tiles(
+    input: &Path,
+    output_dir: &Path,
+    config: &TileConfig,
+    known: &HashMap<PathBuf, u64>,
+) -> Result<TileResult> {
+    if config.tile_size == 0 {
+        anyhow::bail!("Tile size must be greater than zero");
+    }
+
+    let mut level_image = if config.image.is_linear() { open_image_linear(input)? } else { open_image(input)? }.to_rgba8();
+    let (width, height) = level_image.dimensions();
+
+    let format = config.image.output_format.unwrap_or_default();
+    // Encoder settings are part of the tile hash so changing them re-encodes every tile
+    let settings = format!("{}|{:?}", config.border, config.image);
+
+    std::fs::create_dir_all(output_dir)
+        .with_context(|| format!("Failed to create tile directory: {}", output_dir.display()))?;
+    let index_path = output_dir.join(TILE_INDEX_FILE_NAME);
+    let previous = VirtualTextureIndex::open(&index_path).ok();
+
+    let mut levels = Vec::new();
+    let mut outcomes = Vec::new();
+    loop {
+        let level = levels.len() as u32;
+        let (level_width, level_height) = level_image.dimensions();
+        let columns = level_width.div_ceil(config.tile_size);
+        let rows = level_height.div_ceil(config.tile_size);
+        levels.push(TileLevel {
+            width: level_width,
+            height: level_height,
+            columns,
+            rows,
+        });
+
+        let coords: Vec<(u32, u32)> = (0..rows)
+            .flat_map(|y| (0..columns).map(move |x| (x, y)))
+            .collect();
+        let tiles: Vec<Option<TileOutcome>> = coords
+            .par_iter()
+            .map(|&(x, y)| {
+                write_tile(&level_image, (level, x, y), format, &settings, output_dir, config, known)
+            })
+            .collect::<Result<_>>()?;
+        outcomes.extend(tiles.into_iter().flatten());
+
+        if columns <= 1 && rows <= 1 {
+            break;
+        }
+        level_image = imageops::resize(
+            &level_image,
+            level_width.div_ceil(2),
+            level_height.div_ceil(2),
+            FilterType::Triangle,
+        );
+    }
+
+    let current: HashSet<&str> = outcomes.iter().map(|t| t.entry.file.as_str()).collect();
+    if let Some(previous) = &previous {
+        for stale in previous.tiles.iter().filter(|t| !current.contains(t.file.as_str())) {
+            let stale = output_dir.join(&stale.file);
+            let _ = std::fs::remove_file(&stale);
+            // Drops the level directory once its last tile is gone
+            if let Some(parent) = stale.parent() {
+                let _ = std::fs::remove_dir(parent);
+            }
+        }
+    }
+
+    let tiles_reused = outcomes.iter().filter(|t| t.reused).count();
+    let tiles_written = outcomes.len() - tiles_reused;
+    let mut output_size: u64 = outcomes.iter().map(|t| t.size).sum();
+    let hashes = outcomes.iter().map(|t| (t.path.clone(), t.hash)).collect();
+
+    let index = VirtualTextureIndex {
+        width,
+        height,
+        tile_size: config.tile_size,
+        border: config.border,
+        format: format.to_string(),
+        levels,
+        tiles: outcomes.into_iter().map(|t| t.entry).collect(),
+    };
+    let json = serde_json::to_string_pretty(&index)?;
+    std::fs::write(&index_path, &json)
+        .with_context(|| format!("Failed to write tile index: {}", index_path.display()))?;
+    output_size += json.len() as u64;
+
+    Ok(TileResult {
+        index,
+        index_path,
+        tiles_written,
+        tiles_reused,
+        hashes,
+        output_size,
+    })
+}
+
+/// Cut one bordered tile out of a level and encode it, or None if the tile is fully transparent
+fn write_tile(
+    level_image: &RgbaImage,
+    (level, x, y): (u32, u32, u32),
+    format: OutputFormat,
+    settings: &str,
+    output_dir: &Path,
+    config: &TileConfig,
+    known: &HashMap<PathBuf, u64>,
+) -> Result<Option<TileOutcome>> {
+    let (width, height) = level_image.dimensions();
+    let x0 = x * config.tile_size;
+    let y0 = y * config.tile_size;
+    let x1 = (x0 + config.tile_size).min(width);
+    let y1 = (y0 + config.tile_size).min(height);
+
+    let empty = (y0..y1).all(|py| (x0..x1).all(|px| level_image.get_pixel(px, py)[3] == 0));
+    if empty {
+        return Ok(None);
+    }
+
+    // Texels outside the level are clamped to its edge, so every tile has the same size
+    let border = config.border as i64;
+    let side = config.tile_size + 2 * config.border;
+    let tile = RgbaImage::from_fn(side, side, |px, py| {
+        let sx = (x0 as i64 + px as i64 - border).clamp(0, width as i64 - 1);
+        let sy = (y0 as i64 + py as i64 - border).clamp(0, height as i64 - 1);
+        *level_image.get_pixel(sx as u32, sy as u32)
+    });
+
+    let hash = hash_inputs(&[tile.as_raw(), settings.as_bytes()]);
+    let file = format!("{}/{}_{}.{}", level, x, y, format);
+    let path = output_dir.join(&file);
+
+    let reused = known.get(&path) == Some(&hash) && path.exists();
+    if !reused {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Run the tile through the regular image pipeline (oxipng, WebP, KTX2, ...)
+        let temp = path.with_extension("tile.tmp.png");
+        DynamicImage::ImageRgba8(tile)
+            .save_with_format(&temp, ImageFormat::Png)
+            .with_context(|| format!("Failed to save tile: {}", temp.display()))?;
+        let result = process_image(&temp, &path, &config.image);
+        let _ = std::fs::remove_file(&temp);
+        result?;
+    }
+    let size = std::fs::metadata(&path)?.len();
+
+    Ok(Some(TileOutcome {
+        entry: TileEntry {
+            level,
+            x,
+            y,
+            file,
+            hash: format!("{:016x}", hash),
+        },
+        path,
+        hash,
+        reused,
+        size,
+    }))
+}
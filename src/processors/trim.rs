@@ -0,0 +1,71 @@
+use anyhow::{Context, Result};
+use image::{GenericImageView, ImageFormat, RgbaImage};
+use std::path::{Path, PathBuf};
+
+use super::{is_hdr_path, open_image, open_image_linear};
+
+pub use crate::runtime::TrimSidecar;
+
+/// Bounds `(x, y, width, height)` of the texels with alpha above `threshold`, or `None` if
+/// there are none
+pub fn opaque_bounds(img: &RgbaImage, threshold: u8) -> Option<(u32, u32, u32, u32)> {
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (u32::MAX, u32::MAX, 0, 0);
+    for (x, y, pixel) in img.enumerate_pixels() {
+        if pixel[3] > threshold {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+    }
+    (min_x <= max_x).then(|| (min_x, min_y, max_x - min_x + 1, max_y - min_y + 1))
+}
+
+/// Crop the fully transparent borders of an image into a temporary file next to `output`
+///
+/// The caller processes the returned file (PNG, or EXR for HDR sources) in place of the
+/// input and removes it. Fully transparent images are kept whole.
+pub fn trim_image(input: &Path, output: &Path, linear: bool) -> Result<(PathBuf, TrimSidecar)> {
+    let img = if linear { open_image_linear(input)? } else { open_image(input)? };
+    let (source_width, source_height) = img.dimensions();
+    let (trim_x, trim_y, width, height) = opaque_bounds(&img.to_rgba8(), 0).unwrap_or_else(|| {
+        tracing::warn!("{}: fully transparent, nothing to trim", input.display());
+        (0, 0, source_width, source_height)
+    });
+    let cropped = img.crop_imm(trim_x, trim_y, width, height);
+
+    let (temp, format) = if is_hdr_path(input) {
+        (output.with_extension("trim.tmp.exr"), ImageFormat::OpenExr)
+    } else {
+        (output.with_extension("trim.tmp.png"), ImageFormat::Png)
+    };
+    if let Some(parent) = temp.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    cropped
+        .save_with_format(&temp, format)
+        .with_context(|| format!("Failed to save trimmed image: {}", temp.display()))?;
+
+    Ok((temp, TrimSidecar { width, height, source_width, source_height, trim_x, trim_y }))
+}
+
+/// Write the trim sidecar next to every output (`sprite.webp` → `sprite.trim.json`), scaled
+/// by the output's resolution variant (1.0 for a plain output)
+pub fn write_trim(sidecar: &TrimSidecar, outputs: &[(PathBuf, f32)]) -> Result<()> {
+    for (output, scale) in outputs {
+        let scaled = |value: u32| (value as f32 * scale).round() as u32;
+        let sidecar = TrimSidecar {
+            width: scaled(sidecar.width).max(1),
+            height: scaled(sidecar.height).max(1),
+            source_width: scaled(sidecar.source_width).max(1),
+            source_height: scaled(sidecar.source_height).max(1),
+            trim_x: scaled(sidecar.trim_x),
+            trim_y: scaled(sidecar.trim_y),
+        };
+        let path = output.with_extension("trim.json");
+        let json = serde_json::to_string_pretty(&sidecar)?;
+        std::fs::write(&path, json)
+            .with_context(|| format!("Failed to write trim sidecar: {}", path.display()))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use anyhow::{Context, Result};
+use image::ImageReader;
+use std::path::Path;
+
+use super::audio::{count_frames, frames_to_secs};
+use super::audio_stream::AudioDecoder;
+use super::{validate_astc, validate_dds, validate_ktx2, AssetType};
+
+/// JPEG XL bare codestream and ISOBMFF container signatures
+const JXL_CODESTREAM: [u8; 2] = [0xFF, 0x0A];
+const JXL_CONTAINER: [u8; 12] = [0, 0, 0, 0x0C, b'J', b'X', b'L', b' ', 0x0D, 0x0A, 0x87, 0x0A];
+
+/// Re-open a build output the way a game would load it and describe what was found
+///
+/// Images are fully decoded, GPU containers have every level checked against their
+/// headers, glTF files are parsed with their buffers and every primitive's positions read,
+/// audio is decoded to the end and JSON sidecars are parsed. JPEG XL has no decoder here,
+/// so only its signature is checked. Returns None for outputs with no check (copied files).
+pub fn verify_output(path: &Path) -> Result<Option<String>> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+
+    let summary = match extension.as_str() {
+        "ktx2" => {
+            let (width, height, levels) = validate_ktx2(&read(path)?)?;
+            format!("{}x{} KTX2, {} levels", width, height, levels)
+        }
+        "dds" => {
+            let (width, height, levels) = validate_dds(&read(path)?)?;
+            format!("{}x{} DDS, {} levels", width, height, levels)
+        }
+        "astc" => {
+            let (width, height) = validate_astc(&read(path)?)?;
+            format!("{}x{} ASTC", width, height)
+        }
+        "jxl" => {
+            let data = read(path)?;
+            if !data.starts_with(&JXL_CODESTREAM) && !data.starts_with(&JXL_CONTAINER) {
+                anyhow::bail!("Not a JPEG XL file");
+            }
+            "JPEG XL signature".to_string()
+        }
+        "zst" => {
+            let raw = zstd::stream::decode_all(std::fs::File::open(path)?)
+                .with_context(|| format!("Invalid Zstandard frame: {}", path.display()))?;
+            format!("Zstandard, {} bytes raw", raw.len())
+        }
+        "json" => {
+            serde_json::from_slice::<serde_json::Value>(&read(path)?)
+                .with_context(|| format!("Invalid JSON: {}", path.display()))?;
+            "JSON".to_string()
+        }
+        _ => match AssetType::from_path(path) {
+            AssetType::Image => {
+                let img = ImageReader::open(path)?
+                    .with_guessed_format()?
+                    .decode()
+                    .with_context(|| format!("Failed to decode image: {}", path.display()))?;
+                format!("{}x{} image", img.width(), img.height())
+            }
+            AssetType::Model => verify_gltf(path)?,
+            AssetType::Audio => {
+                let mut decoder = AudioDecoder::open(path)?;
+                let frames = count_frames(&mut decoder)?;
+                if frames == 0.0 {
+                    anyhow::bail!("Audio decodes to no samples");
+                }
+                format!("{:.2}s audio", frames_to_secs(frames, decoder.sample_rate))
+            }
+            AssetType::Unknown => return Ok(None),
+        },
+    };
+
+    Ok(Some(summary))
+}
+
+fn verify_gltf(path: &Path) -> Result<String> {
+    let (document, buffers, _) = gltf::import(path)
+        .with_context(|| format!("Failed to load glTF: {}", path.display()))?;
+
+    let mut primitives = 0;
+    for mesh in document.meshes() {
+        for primitive in mesh.primitives() {
+            let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data[..]));
+            let expected = primitive.get(&gltf::Semantic::Positions).map_or(0, |a| a.count());
+            let positions = reader.read_positions().map_or(0, |p| p.count());
+            if positions != expected || positions == 0 {
+                anyhow::bail!("Mesh {} has unreadable positions", mesh.index());
+            }
+            if let Some(indices) = reader.read_indices() {
+                if indices.into_u32().any(|i| i as usize >= positions) {
+                    anyhow::bail!("Mesh {} has out-of-range indices", mesh.index());
+                }
+            }
+            primitives += 1;
+        }
+    }
+
+    Ok(format!("glTF, {} primitives", primitives))
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("Failed to read output: {}", path.display()))
+}
@@ -0,0 +1,860 @@
+//! Built-in Ogg Vorbis encoder for builds without libvorbis
+//!
+//! Uses a small subset of Vorbis I: a single 2048-sample block size, a floor 1 curve on
+//! fixed posts, and type 1 residue quantized against the floor with fixed codebooks. There
+//! is no psychoacoustic model, so files are larger than libvorbis output at the same quality,
+//! but they play in any Vorbis decoder.
+
+use anyhow::{bail, Result};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::f32::consts::PI;
+use std::io::Write;
+
+use super::ogg::{vorbis_comment, OggWriter};
+use super::LoopPoints;
+
+const BLOCK_EXP: u8 = 11;
+const BLOCK: usize = 1 << BLOCK_EXP;
+const HALF: usize = BLOCK / 2;
+
+/// Floor 1 posts between 0 and HALF, coded in partitions of FLOOR_DIM, denser at low frequencies
+const FLOOR_POSTS: [u16; 40] = [
+    1, 2, 3, 4, 5, 6, 7, 8, 10, 12, 14, 16, 20, 24, 28, 32, 40, 48, 56, 64, 80, 96, 112, 128,
+    160, 192, 224, 256, 320, 384, 448, 512, 576, 640, 704, 768, 832, 896, 960, 992,
+];
+const FLOOR_DIM: usize = 4;
+/// Floor amplitudes are 0-127 in steps of two inverse-dB table entries (about 1.1 dB)
+const FLOOR_MULTIPLIER: i32 = 2;
+const FLOOR_RANGE: i32 = 128;
+/// Each inverse-dB table entry is this many dB above the previous
+const FLOOR_DB_STEP: f32 = 0.546_88;
+
+/// Residue partition length and value classes: silent, ±1, ±8, then ±263 in two passes
+const PARTITION: usize = 16;
+const CLASSES: usize = 4;
+const FINE_LIMIT: i32 = 8;
+const COARSE_STEP: i32 = 17;
+const COARSE_LIMIT: i32 = 15;
+
+/// Codebook numbers in the setup header
+const BOOK_FLOOR: usize = 0;
+const BOOK_CLASS: usize = 1;
+const BOOK_UNIT: usize = 2;
+const BOOK_FINE: usize = 3;
+const BOOK_COARSE: usize = 4;
+
+/// Ogg Vorbis encoder for interleaved audio, taken in chunks and written out page by page;
+/// `quality` is 0-1 like libvorbis VBR
+pub struct VorbisWriter<W: Write> {
+    writer: W,
+    ogg: OggWriter,
+    books: Vec<Codebook>,
+    window: Vec<f32>,
+    mdct: Mdct,
+    levels: (f32, f32),
+    channels: usize,
+    /// Samples from frame `base` on, which the next block still needs
+    buffer: Vec<f32>,
+    base: usize,
+    frames: usize,
+    /// Next block to encode
+    block: usize,
+}
+
+impl<W: Write> VorbisWriter<W> {
+    pub fn new(writer: W, channels: u32, sample_rate: u32, loop_points: Option<LoopPoints>, quality: f32) -> Result<Self> {
+        if channels == 0 || channels > 255 {
+            bail!("Vorbis supports 1 to 255 channels, not {}", channels);
+        }
+        if sample_rate == 0 {
+            bail!("Invalid sample rate: 0");
+        }
+
+        let books = codebooks();
+        let mut ogg = OggWriter::default();
+        ogg.packet(&identification_header(channels, sample_rate), 0);
+        ogg.flush(false);
+        ogg.packet(&comment_header(loop_points), 0);
+        ogg.packet(&setup_header(&books), 0);
+        ogg.flush(false);
+
+        // The floor, which is also the quantization step, sits this far below each band's level,
+        // and no lower than this far below the block's loudest band
+        let quality = quality.clamp(0.0, 1.0);
+        let levels = (4.0 + 20.0 * quality, 40.0 + 40.0 * quality);
+        let window: Vec<f32> = (0..BLOCK)
+            .map(|i| (PI / 2.0 * ((i as f32 + 0.5) / BLOCK as f32 * PI).sin().powi(2)).sin())
+            .collect();
+
+        Ok(Self {
+            writer,
+            ogg,
+            books,
+            window,
+            mdct: Mdct::new(),
+            levels,
+            channels: channels as usize,
+            buffer: Vec::new(),
+            base: 0,
+            frames: 0,
+            block: 0,
+        })
+    }
+
+    /// Encode every block that `samples` completes
+    pub fn write(&mut self, samples: &[f32]) -> Result<()> {
+        self.buffer.extend_from_slice(samples);
+        self.frames += samples.len() / self.channels;
+        // A block ending inside the input is never the last one, which ends past it
+        while (self.block + 1) * HALF <= self.frames {
+            self.encode_block((self.block * HALF) as u64);
+        }
+        self.drain();
+        self.writer.write_all(&self.ogg.take_pages())?;
+        Ok(())
+    }
+
+    /// Encode the blocks that run past the end of the input and return the writer
+    pub fn finish(mut self) -> Result<W> {
+        // Block j covers samples (j - 1) * HALF .. (j + 1) * HALF, so decoding starts at sample 0
+        let blocks = self.frames.div_ceil(HALF) + 1;
+        while self.block < blocks {
+            let granule = if self.block + 1 == blocks { self.frames } else { self.block * HALF };
+            self.encode_block(granule as u64);
+        }
+        self.writer.write_all(&self.ogg.finish())?;
+        Ok(self.writer)
+    }
+
+    fn encode_block(&mut self, granule: u64) {
+        let start = self.block as isize * HALF as isize - HALF as isize;
+        let channels = self.channels;
+        let mut input = vec![0.0f32; BLOCK];
+        let mut encoded = Vec::with_capacity(channels);
+        for channel in 0..channels {
+            for (i, value) in input.iter_mut().enumerate() {
+                let frame = start + i as isize;
+                *value = if frame >= 0 && (frame as usize) < self.frames {
+                    self.buffer[(frame as usize - self.base) * channels + channel] * self.window[i]
+                } else {
+                    0.0
+                };
+            }
+            encoded.push(encode_channel(&self.mdct.forward(&input), self.levels));
+        }
+
+        let mut bits = BitWriter::default();
+        bits.write(0, 1); // audio packet; the only mode needs no bits
+        for channel in &encoded {
+            write_floor(&mut bits, &self.books, channel);
+        }
+        write_residue(&mut bits, &self.books, &encoded);
+
+        self.ogg.packet(&bits.finish(), granule);
+        self.block += 1;
+    }
+
+    /// Drop samples before the next block's start
+    fn drain(&mut self) {
+        let start = (self.block * HALF).saturating_sub(HALF).min(self.frames);
+        if start > self.base {
+            self.buffer.drain(..(start - self.base) * self.channels);
+            self.base = start;
+        }
+    }
+}
+
+/// One channel of one block: its floor posts (None when silent) and quantized residue
+struct EncodedChannel {
+    posts: Option<Vec<i32>>,
+    residue: Vec<i32>,
+}
+
+fn encode_channel(spectrum: &[f32], (offset_db, range_db): (f32, f32)) -> EncodedChannel {
+    let xs = floor_xs();
+
+    // Each post takes the level of the bins closer to it than to its neighbours
+    let mut sorted: Vec<usize> = (0..xs.len()).collect();
+    sorted.sort_by_key(|&i| xs[i]);
+    let mut band_db = vec![0.0f32; xs.len()];
+    for (rank, &post) in sorted.iter().enumerate() {
+        let low = if rank == 0 { 0 } else { (xs[sorted[rank - 1]] + xs[post]) / 2 };
+        let high = match sorted.get(rank + 1) {
+            Some(&next) => (xs[post] + xs[next]).div_ceil(2),
+            None => HALF,
+        };
+        let (low, high) = (low.min(HALF - 1), high.clamp(low + 1, HALF));
+        let power = spectrum[low..high].iter().map(|v| v * v).sum::<f32>() / (high - low) as f32;
+        band_db[post] = 10.0 * power.max(1e-30).log10();
+    }
+
+    // Nothing is coded below the loudest band's range or 16-bit noise
+    let loudest = band_db.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+    let posts: Vec<i32> = band_db
+        .iter()
+        .map(|&db| {
+            let db = (db - offset_db).max(loudest - range_db).max(-96.0);
+            let index = 255.0 + db / FLOOR_DB_STEP;
+            (index / FLOOR_MULTIPLIER as f32).round().clamp(0.0, (FLOOR_RANGE - 1) as f32) as i32
+        })
+        .collect();
+
+    let floor = floor_curve(&posts);
+    let residue: Vec<i32> = spectrum
+        .iter()
+        .zip(&floor)
+        .map(|(v, f)| {
+            let limit = COARSE_LIMIT * COARSE_STEP + FINE_LIMIT;
+            (v / f).round().clamp(-limit as f32, limit as f32) as i32
+        })
+        .collect();
+
+    // A channel with nothing to code is flagged unused, which the decoder plays as silence
+    let silent = residue.iter().all(|&q| q == 0);
+    EncodedChannel {
+        posts: (!silent).then_some(posts),
+        residue,
+    }
+}
+
+/// Post X positions in coding order: the two ends, then the partitions
+fn floor_xs() -> Vec<usize> {
+    [0, HALF].into_iter().chain(FLOOR_POSTS.iter().map(|&x| x as usize)).collect()
+}
+
+/// Neighbouring earlier posts of post `i`: closest below and above in X
+fn neighbors(xs: &[usize], i: usize) -> (usize, usize) {
+    let low = (0..i).filter(|&j| xs[j] < xs[i]).max_by_key(|&j| xs[j]).unwrap();
+    let high = (0..i).filter(|&j| xs[j] > xs[i]).min_by_key(|&j| xs[j]).unwrap();
+    (low, high)
+}
+
+fn render_point(x0: usize, y0: i32, x1: usize, y1: i32, x: usize) -> i32 {
+    let dy = y1 - y0;
+    let offset = dy.abs() * (x - x0) as i32 / (x1 - x0) as i32;
+    if dy < 0 {
+        y0 - offset
+    } else {
+        y0 + offset
+    }
+}
+
+/// Per-post values as written: 0 means "as predicted", so the decoder skips that post's vertex
+fn floor_values(posts: &[i32]) -> Vec<i32> {
+    let xs = floor_xs();
+    let mut values = vec![0i32; posts.len()];
+    values[0] = posts[0];
+    values[1] = posts[1];
+    for i in 2..posts.len() {
+        let (low, high) = neighbors(&xs, i);
+        let predicted = render_point(xs[low], posts[low], xs[high], posts[high], xs[i]);
+        let (high_room, low_room) = (FLOOR_RANGE - predicted, predicted);
+        let room = high_room.min(low_room) * 2;
+        let delta = posts[i] - predicted;
+        values[i] = match delta {
+            0 => 0,
+            d if d > 0 && 2 * d < room => 2 * d,
+            d if d < 0 && -2 * d - 1 < room => -2 * d - 1,
+            _ if high_room > low_room => posts[i],
+            _ => FLOOR_RANGE - 1 - posts[i],
+        };
+    }
+    values
+}
+
+/// The floor the decoder renders from the posts, as linear amplitudes per bin
+fn floor_curve(posts: &[i32]) -> Vec<f32> {
+    let xs = floor_xs();
+    let values = floor_values(posts);
+
+    // Posts written as predicted are not vertices, unless a later post needed them
+    let mut vertex = vec![false; posts.len()];
+    vertex[0] = true;
+    vertex[1] = true;
+    for i in 2..posts.len() {
+        if values[i] != 0 {
+            let (low, high) = neighbors(&xs, i);
+            vertex[low] = true;
+            vertex[high] = true;
+            vertex[i] = true;
+        }
+    }
+
+    let mut order: Vec<usize> = (0..posts.len()).collect();
+    order.sort_by_key(|&i| xs[i]);
+    let mut floor = vec![0.0f32; HALF];
+    let (mut lx, mut ly) = (0, posts[order[0]] * FLOOR_MULTIPLIER);
+    for &i in &order[1..] {
+        if vertex[i] {
+            let (hx, hy) = (xs[i], posts[i] * FLOOR_MULTIPLIER);
+            render_line(lx, ly, hx, hy, &mut floor);
+            (lx, ly) = (hx, hy);
+        }
+    }
+    floor
+}
+
+/// Bresenham-style integer line through the inverse-dB table, exactly as decoders draw it
+fn render_line(x0: usize, y0: i32, x1: usize, y1: i32, floor: &mut [f32]) {
+    let dy = y1 - y0;
+    let adx = (x1 - x0) as i32;
+    let base = dy / adx;
+    let step = if dy < 0 { base - 1 } else { base + 1 };
+    let ady = dy.abs() - base.abs() * adx;
+    let (mut y, mut err) = (y0, 0);
+    for x in x0..x1.min(floor.len()) {
+        if x > x0 {
+            err += ady;
+            if err >= adx {
+                err -= adx;
+                y += step;
+            } else {
+                y += base;
+            }
+        }
+        floor[x] = inverse_db(y);
+    }
+}
+
+/// Floor table entry `index`: -140 dB at 0 up to 1.0 at 255
+fn inverse_db(index: i32) -> f32 {
+    10f32.powf((index - 255) as f32 * FLOOR_DB_STEP / 20.0)
+}
+
+fn write_floor(bits: &mut BitWriter, books: &[Codebook], channel: &EncodedChannel) {
+    let Some(posts) = &channel.posts else {
+        bits.write(0, 1);
+        return;
+    };
+    bits.write(1, 1);
+    let values = floor_values(posts);
+    bits.write(values[0] as u32, 7);
+    bits.write(values[1] as u32, 7);
+    for &value in &values[2..] {
+        books[BOOK_FLOOR].write(bits, value as usize);
+    }
+}
+
+/// Residue class of one partition: the smallest whose books reach its largest value
+fn partition_class(values: &[i32]) -> usize {
+    match values.iter().map(|q| q.abs()).max().unwrap_or(0) {
+        0 => 0,
+        1 => 1,
+        m if m <= FINE_LIMIT => 2,
+        _ => 3,
+    }
+}
+
+fn write_residue(bits: &mut BitWriter, books: &[Codebook], channels: &[EncodedChannel]) {
+    let coded: Vec<&EncodedChannel> = channels.iter().filter(|c| c.posts.is_some()).collect();
+    let partitions = HALF / PARTITION;
+    let classes: Vec<Vec<usize>> = coded
+        .iter()
+        .map(|c| c.residue.chunks(PARTITION).map(partition_class).collect())
+        .collect();
+    let per_word = books[BOOK_CLASS].dimensions;
+
+    for pass in 0..2 {
+        for first in (0..partitions).step_by(per_word) {
+            if pass == 0 {
+                for channel_classes in &classes {
+                    let word = (0..per_word)
+                        .fold(0, |word, i| word * CLASSES + channel_classes.get(first + i).copied().unwrap_or(0));
+                    books[BOOK_CLASS].write(bits, word);
+                }
+            }
+            for partition in first..(first + per_word).min(partitions) {
+                for (channel, channel_classes) in coded.iter().zip(&classes) {
+                    let values = &channel.residue[partition * PARTITION..(partition + 1) * PARTITION];
+                    let book = match (channel_classes[partition], pass) {
+                        (1, 0) => BOOK_UNIT,
+                        (2, 0) | (3, 1) => BOOK_FINE,
+                        (3, 0) => BOOK_COARSE,
+                        _ => continue,
+                    };
+                    let book = &books[book];
+                    for vector in values.chunks(book.dimensions) {
+                        let vector: Vec<i32> = match (channel_classes[partition], pass) {
+                            (3, 0) => vector.iter().map(|&q| coarse(q) * COARSE_STEP).collect(),
+                            (3, 1) => vector.iter().map(|&q| q - coarse(q) * COARSE_STEP).collect(),
+                            _ => vector.to_vec(),
+                        };
+                        book.write(bits, book.entry(&vector));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// First-pass multiple of COARSE_STEP for a large value, leaving at most FINE_LIMIT
+fn coarse(q: i32) -> i32 {
+    ((q as f32 / COARSE_STEP as f32).round() as i32).clamp(-COARSE_LIMIT, COARSE_LIMIT)
+}
+
+/// A Vorbis codebook with entry-order Huffman codewords and an optional lattice lookup
+struct Codebook {
+    dimensions: usize,
+    lengths: Vec<u8>,
+    codewords: Vec<u32>,
+    /// Lattice values per dimension: minimum, step and count
+    lookup: Option<(i32, i32, usize)>,
+}
+
+impl Codebook {
+    /// Scalar book of `weights.len()` entries
+    fn scalar(dimensions: usize, weights: &[f64]) -> Self {
+        let lengths = huffman_lengths(weights);
+        let codewords = codewords(&lengths);
+        Self {
+            dimensions,
+            lengths,
+            codewords,
+            lookup: None,
+        }
+    }
+
+    /// Lattice book of `count` values `minimum + i * step` per dimension, shorter codes near zero
+    fn lattice(dimensions: usize, (minimum, step, count): (i32, i32, usize), falloff: f64) -> Self {
+        let entries = count.pow(dimensions as u32);
+        let weights: Vec<f64> = (0..entries)
+            .map(|entry| {
+                let magnitude: i32 = (0..dimensions)
+                    .map(|k| (minimum + (entry / count.pow(k as u32) % count) as i32 * step).abs() / step)
+                    .sum();
+                (-falloff * magnitude as f64).exp()
+            })
+            .collect();
+        Self {
+            lookup: Some((minimum, step, count)),
+            ..Self::scalar(dimensions, &weights)
+        }
+    }
+
+    /// Entry whose lattice vector is `values`; the first value is the lowest digit
+    fn entry(&self, values: &[i32]) -> usize {
+        let (minimum, step, count) = self.lookup.unwrap();
+        values
+            .iter()
+            .rev()
+            .fold(0, |entry, &v| entry * count + ((v - minimum) / step) as usize)
+    }
+
+    fn write(&self, bits: &mut BitWriter, entry: usize) {
+        // Codewords are read from their most significant bit
+        let length = self.lengths[entry] as u32;
+        bits.write(self.codewords[entry].reverse_bits() >> (32 - length), length);
+    }
+
+    fn write_header(&self, bits: &mut BitWriter) {
+        bits.write(0x564342, 24);
+        bits.write(self.dimensions as u32, 16);
+        bits.write(self.lengths.len() as u32, 24);
+        bits.write(0, 1); // not ordered
+        bits.write(0, 1); // not sparse
+        for &length in &self.lengths {
+            bits.write(length as u32 - 1, 5);
+        }
+        match self.lookup {
+            None => bits.write(0, 4),
+            Some((minimum, step, count)) => {
+                let value_bits = usize::BITS - (count - 1).leading_zeros();
+                bits.write(1, 4);
+                bits.write(vorbis_float(minimum), 32);
+                bits.write(vorbis_float(step), 32);
+                bits.write(value_bits - 1, 4);
+                bits.write(0, 1); // values are not cumulative
+                for multiplicand in 0..count {
+                    bits.write(multiplicand as u32, value_bits);
+                }
+            }
+        }
+    }
+}
+
+fn codebooks() -> Vec<Codebook> {
+    let floor_weights: Vec<f64> = (0..FLOOR_RANGE).map(|v| (1.0 + v as f64).powf(-1.3)).collect();
+    vec![
+        Codebook::scalar(1, &floor_weights),
+        Codebook::scalar(2, &[1.0; CLASSES * CLASSES]),
+        Codebook::lattice(4, (-1, 1, 3), 0.7),
+        Codebook::lattice(2, (-FINE_LIMIT, 1, (2 * FINE_LIMIT + 1) as usize), 0.35),
+        Codebook::lattice(
+            2,
+            (-COARSE_LIMIT * COARSE_STEP, COARSE_STEP, (2 * COARSE_LIMIT + 1) as usize),
+            0.5,
+        ),
+    ]
+}
+
+/// Integers in Vorbis's float format: 21-bit mantissa, exponent biased by 788, sign
+fn vorbis_float(value: i32) -> u32 {
+    let sign = if value < 0 { 0x8000_0000 } else { 0 };
+    sign | (788 << 21) | value.unsigned_abs()
+}
+
+/// Huffman code lengths for the weights, kept within Vorbis's 32-bit limit
+fn huffman_lengths(weights: &[f64]) -> Vec<u8> {
+    // Rare entries get a floor weight so no code grows past about 24 bits
+    let max = weights.iter().cloned().fold(0.0, f64::max);
+    let mut heap: BinaryHeap<Reverse<(u64, usize)>> = weights
+        .iter()
+        .enumerate()
+        .map(|(i, &w)| Reverse((w.max(max * 1e-6).to_bits(), i)))
+        .collect();
+    let mut parent = vec![usize::MAX; weights.len()];
+    while heap.len() > 1 {
+        let Reverse((a, i)) = heap.pop().unwrap();
+        let Reverse((b, j)) = heap.pop().unwrap();
+        let node = parent.len();
+        parent.push(usize::MAX);
+        parent[i] = node;
+        parent[j] = node;
+        heap.push(Reverse(((f64::from_bits(a) + f64::from_bits(b)).to_bits(), node)));
+    }
+    (0..weights.len())
+        .map(|mut node| {
+            let mut depth = 0;
+            while parent[node] != usize::MAX {
+                node = parent[node];
+                depth += 1;
+            }
+            depth.max(1)
+        })
+        .collect()
+}
+
+/// Codewords the decoder assigns to the lengths: each entry in turn takes the lowest free code
+fn codewords(lengths: &[u8]) -> Vec<u32> {
+    let mut marker = [0u32; 33];
+    let mut codewords = Vec::with_capacity(lengths.len());
+    for &length in lengths {
+        let length = length as usize;
+        let mut entry = marker[length];
+        codewords.push(entry);
+
+        // Advance the markers above the taken node
+        for j in (1..=length).rev() {
+            if marker[j] & 1 != 0 {
+                marker[j] = if j == 1 { marker[1] + 1 } else { marker[j - 1] << 1 };
+                break;
+            }
+            marker[j] += 1;
+        }
+        // Longer markers that hung off the taken node move to the new one
+        for j in length + 1..33 {
+            if marker[j] >> 1 != entry {
+                break;
+            }
+            entry = marker[j];
+            marker[j] = marker[j - 1] << 1;
+        }
+    }
+    codewords
+}
+
+fn identification_header(channels: u32, sample_rate: u32) -> Vec<u8> {
+    let mut header = vec![1];
+    header.extend_from_slice(b"vorbis");
+    header.extend_from_slice(&0u32.to_le_bytes());
+    header.push(channels as u8);
+    header.extend_from_slice(&sample_rate.to_le_bytes());
+    header.extend_from_slice(&[0; 12]); // no bitrate hints
+    header.push((BLOCK_EXP << 4) | BLOCK_EXP);
+    header.push(1);
+    header
+}
+
+fn comment_header(loop_points: Option<LoopPoints>) -> Vec<u8> {
+    let tags = loop_points.map(|loop_points| loop_points.comment_tags()).unwrap_or_default();
+    let mut header = vec![3];
+    header.extend_from_slice(b"vorbis");
+    header.extend_from_slice(&vorbis_comment(&tags));
+    header.push(1);
+    header
+}
+
+fn setup_header(books: &[Codebook]) -> Vec<u8> {
+    let mut bits = BitWriter::default();
+    for byte in b"\x05vorbis" {
+        bits.write(*byte as u32, 8);
+    }
+    bits.write(books.len() as u32 - 1, 8);
+    for book in books {
+        book.write_header(&mut bits);
+    }
+
+    // Time domain transforms: one placeholder
+    bits.write(0, 6);
+    bits.write(0, 16);
+
+    // Floor 1: FLOOR_POSTS in partitions of one class, values from the floor book
+    bits.write(0, 6);
+    bits.write(1, 16);
+    let partitions = FLOOR_POSTS.len() / FLOOR_DIM;
+    bits.write(partitions as u32, 5);
+    for _ in 0..partitions {
+        bits.write(0, 4);
+    }
+    bits.write(FLOOR_DIM as u32 - 1, 3);
+    bits.write(0, 2); // no subclasses
+    bits.write(BOOK_FLOOR as u32 + 1, 8);
+    bits.write(FLOOR_MULTIPLIER as u32 - 1, 2);
+    bits.write(BLOCK_EXP as u32 - 1, 4);
+    for &x in &FLOOR_POSTS {
+        bits.write(x as u32, BLOCK_EXP as u32 - 1);
+    }
+
+    // Residue type 1 over the whole spectrum
+    bits.write(0, 6);
+    bits.write(1, 16);
+    bits.write(0, 24);
+    bits.write(HALF as u32, 24);
+    bits.write(PARTITION as u32 - 1, 24);
+    bits.write(CLASSES as u32 - 1, 6);
+    bits.write(BOOK_CLASS as u32, 8);
+    let cascades = [0b00u32, 0b01, 0b01, 0b11];
+    for cascade in cascades {
+        bits.write(cascade, 3);
+        bits.write(0, 1);
+    }
+    for book in [BOOK_UNIT, BOOK_FINE, BOOK_COARSE, BOOK_FINE] {
+        bits.write(book as u32, 8);
+    }
+
+    // Mapping 0: one submap, no channel coupling
+    bits.write(0, 6);
+    bits.write(0, 16);
+    bits.write(0, 1);
+    bits.write(0, 1);
+    bits.write(0, 2);
+    bits.write(0, 8);
+    bits.write(0, 8);
+    bits.write(0, 8);
+
+    // One mode with the single block size
+    bits.write(0, 6);
+    bits.write(0, 1);
+    bits.write(0, 16);
+    bits.write(0, 16);
+    bits.write(0, 8);
+
+    bits.write(1, 1);
+    bits.finish()
+}
+
+/// MDCT of BLOCK windowed samples via a HALF / 2-point complex FFT
+struct Mdct {
+    twiddle: Vec<(f32, f32)>,
+    fft: Vec<(f32, f32)>,
+}
+
+impl Mdct {
+    fn new() -> Self {
+        let quarter = HALF / 2;
+        Self {
+            twiddle: (0..quarter)
+                .map(|n| {
+                    let angle = -PI * (n as f32 + 0.25) / HALF as f32;
+                    (angle.cos(), angle.sin())
+                })
+                .collect(),
+            fft: (0..quarter / 2)
+                .map(|k| {
+                    let angle = -2.0 * PI * k as f32 / quarter as f32;
+                    (angle.cos(), angle.sin())
+                })
+                .collect(),
+        }
+    }
+
+    /// Spectrum scaled so the decoder's unnormalized inverse gives back the input
+    fn forward(&self, input: &[f32]) -> Vec<f32> {
+        let quarter = HALF / 2;
+
+        // Fold the four quarters into a DCT-IV input: (-c_r - d, a - b_r)
+        let folded: Vec<f32> = (0..HALF)
+            .map(|n| {
+                if n < quarter {
+                    -input[3 * quarter - 1 - n] - input[3 * quarter + n]
+                } else {
+                    let m = n - quarter;
+                    input[m] - input[HALF - 1 - m]
+                }
+            })
+            .collect();
+
+        let mut z: Vec<(f32, f32)> = (0..quarter)
+            .map(|n| multiply((folded[2 * n], folded[HALF - 1 - 2 * n]), self.twiddle[n]))
+            .collect();
+        self.transform(&mut z);
+
+        let scale = 2.0 / HALF as f32;
+        let mut spectrum = vec![0.0f32; HALF];
+        for (k, &value) in z.iter().enumerate() {
+            let angle = -PI * k as f32 / HALF as f32;
+            let (re, im) = multiply(value, (angle.cos(), angle.sin()));
+            spectrum[2 * k] = re * scale;
+            spectrum[HALF - 1 - 2 * k] = -im * scale;
+        }
+        spectrum
+    }
+
+    /// In-place iterative radix-2 FFT
+    fn transform(&self, data: &mut [(f32, f32)]) {
+        let n = data.len();
+        let mut j = 0;
+        for i in 1..n {
+            let mut bit = n >> 1;
+            while j & bit != 0 {
+                j ^= bit;
+                bit >>= 1;
+            }
+            j |= bit;
+            if i < j {
+                data.swap(i, j);
+            }
+        }
+        let mut size = 2;
+        while size <= n {
+            let stride = n / size;
+            for start in (0..n).step_by(size) {
+                for k in 0..size / 2 {
+                    let t = multiply(data[start + k + size / 2], self.fft[k * stride]);
+                    let u = data[start + k];
+                    data[start + k] = (u.0 + t.0, u.1 + t.1);
+                    data[start + k + size / 2] = (u.0 - t.0, u.1 - t.1);
+                }
+            }
+            size *= 2;
+        }
+    }
+}
+
+fn multiply(a: (f32, f32), b: (f32, f32)) -> (f32, f32) {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+/// Packs fields least significant bit first, as Vorbis reads them
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    accumulator: u64,
+    filled: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, bits: u32) {
+        let mask = if bits == 32 { u32::MAX } else { (1 << bits) - 1 };
+        self.accumulator |= ((value & mask) as u64) << self.filled;
+        self.filled += bits;
+        while self.filled >= 8 {
+            self.bytes.push(self.accumulator as u8);
+            self.accumulator >>= 8;
+            self.filled -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.filled > 0 {
+            self.bytes.push(self.accumulator as u8);
+        }
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processors::audio_stream::AudioDecoder;
+
+    const RATE: u32 = 48_000;
+
+    /// Interleaved stereo: a 440 Hz tone on the left, a swept 200-2000 Hz chirp on the right
+    fn signal(frames: usize) -> Vec<f32> {
+        (0..frames)
+            .flat_map(|i| {
+                let t = i as f32 / RATE as f32;
+                let sweep = 200.0 * t + 900.0 * t * t;
+                [0.5 * (2.0 * PI * 440.0 * t).sin(), 0.4 * (2.0 * PI * sweep).sin()]
+            })
+            .collect()
+    }
+
+    /// Encode `samples` in uneven chunks
+    fn encode(samples: &[f32], channels: u32, quality: f32) -> Vec<u8> {
+        let mut writer = VorbisWriter::new(Vec::new(), channels, RATE, None, quality).unwrap();
+        for chunk in samples.chunks(channels as usize * 3001) {
+            writer.write(chunk).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    /// Encode `samples` and decode the file with symphonia
+    fn round_trip(samples: &[f32], channels: u32, quality: f32) -> (AudioDecoder, Vec<f32>) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.ogg");
+        std::fs::write(&path, encode(samples, channels, quality)).unwrap();
+
+        let mut decoder = AudioDecoder::open(&path).unwrap();
+        let mut decoded = Vec::new();
+        while let Some(chunk) = decoder.next_chunk().unwrap() {
+            decoded.extend(chunk);
+        }
+        (decoder, decoded)
+    }
+
+    /// Signal-to-noise ratio in dB of `decoded` against `reference`, one channel of `channels`
+    fn snr(reference: &[f32], decoded: &[f32], channels: usize, channel: usize) -> f32 {
+        let (mut signal, mut noise) = (0.0f64, 0.0f64);
+        for (a, b) in reference.iter().zip(decoded).skip(channel).step_by(channels) {
+            signal += (*a as f64).powi(2);
+            noise += (*a as f64 - *b as f64).powi(2);
+        }
+        (10.0 * (signal / noise.max(f64::EPSILON)).log10()) as f32
+    }
+
+    #[test]
+    fn test_round_trip_matches_input() {
+        // Not a multiple of the block size, so the last packet is trimmed by its granule
+        let frames = RATE as usize + 777;
+        let input = signal(frames);
+        let (decoder, decoded) = round_trip(&input, 2, 0.5);
+
+        assert_eq!(decoder.channels, 2);
+        assert_eq!(decoder.sample_rate, RATE);
+        // The decoder returns whole blocks; the last page's granule position has the length
+        assert_eq!(decoded.len(), 2 * frames.next_multiple_of(HALF));
+        let data = encode(&input, 2, 0.5);
+        let last_page = data.windows(4).rposition(|w| w == b"OggS").unwrap();
+        let granule = u64::from_le_bytes(data[last_page + 6..last_page + 14].try_into().unwrap());
+        assert_eq!(granule, frames as u64);
+        for channel in 0..2 {
+            let snr = snr(&input, &decoded, 2, channel);
+            assert!(snr > 24.0, "channel {}: {:.1} dB", channel, snr);
+        }
+    }
+
+    #[test]
+    fn test_higher_quality_is_more_accurate() {
+        let input: Vec<f32> = signal(RATE as usize / 2).into_iter().step_by(2).collect();
+        let low = snr(&input, &round_trip(&input, 1, 0.0).1, 1, 0);
+        let high = snr(&input, &round_trip(&input, 1, 1.0).1, 1, 0);
+        assert!(high > low + 10.0, "quality 0: {:.1} dB, quality 1: {:.1} dB", low, high);
+    }
+
+    #[test]
+    fn test_silence_stays_silent() {
+        let input = vec![0.0; 2 * 10_000];
+        let (_, decoded) = round_trip(&input, 2, 0.5);
+        assert!(decoded.len() >= input.len());
+        assert!(decoded.iter().all(|s| s.abs() < 1e-4));
+    }
+
+    #[test]
+    fn test_rejects_invalid_format() {
+        assert!(VorbisWriter::new(Vec::new(), 0, RATE, None, 0.5).is_err());
+        assert!(VorbisWriter::new(Vec::new(), 2, 0, None, 0.5).is_err());
+    }
+}
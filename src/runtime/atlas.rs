@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::RuntimeResult;
+
+/// Metadata for a sprite in the atlas
+///
+/// `x`, `y`, `width` and `height` are the region the sprite covers on its page. A `rotated`
+/// sprite is stored turned 90° clockwise, so its region is the sprite's height wide and its
+/// width high; [`SpriteFrame::size`] and [`SpriteFrame::page_position`] undo the rotation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpriteFrame {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub rotated: bool,
+    /// Index of the page the sprite was packed into
+    #[serde(default)]
+    pub page: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_height: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trim_x: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trim_y: Option<u32>,
+    /// Hash of the source sprite's decoded pixels (hex), for detecting changed frames
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// Nine-slice borders in pixels of the untrimmed sprite
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nine_slice: Option<NineSlice>,
+    /// Anchor `[x, y]` normalized in the untrimmed sprite, from its top left
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pivot: Option<[f32; 2]>,
+}
+
+impl SpriteFrame {
+    /// Size of the (trimmed) sprite as displayed, before rotation on the page
+    pub fn size(&self) -> (u32, u32) {
+        match self.rotated {
+            true => (self.height, self.width),
+            false => (self.width, self.height),
+        }
+    }
+
+    /// Page pixel holding the sprite's pixel (`x`, `y`), measured in the trimmed sprite
+    pub fn page_position(&self, x: u32, y: u32) -> (u32, u32) {
+        match self.rotated {
+            true => (self.x + self.width - 1 - y, self.y + x),
+            false => (self.x + x, self.y + y),
+        }
+    }
+}
+
+/// Nine-slice borders in pixels: corners keep their size, edges stretch along one axis
+/// and the center along both
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NineSlice {
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+}
+
+/// Nine-slice sidecar written next to a processed texture (`button.png` → `button.slice.json`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NineSliceSidecar {
+    /// Size of the texture the borders are measured in
+    pub width: u32,
+    pub height: u32,
+    #[serde(flatten)]
+    pub borders: NineSlice,
+}
+
+impl NineSliceSidecar {
+    /// Read a nine-slice sidecar from a JSON file
+    pub fn open(path: &Path) -> RuntimeResult<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+/// Trim sidecar written next to a texture cropped with `optimize --trim` (`sprite.trim.json`)
+///
+/// Like a trimmed atlas frame: the texture is the `width` x `height` region at
+/// (`trim_x`, `trim_y`) of the `source_width` x `source_height` original.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrimSidecar {
+    pub width: u32,
+    pub height: u32,
+    pub source_width: u32,
+    pub source_height: u32,
+    pub trim_x: u32,
+    pub trim_y: u32,
+}
+
+impl TrimSidecar {
+    /// Read a trim sidecar from a JSON file
+    pub fn open(path: &Path) -> RuntimeResult<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+/// Frames of a sprite animation, in playback order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpriteAnimation {
+    pub frames: Vec<String>,
+    /// Display time of each frame in milliseconds
+    pub durations: Vec<u32>,
+}
+
+/// A single atlas page image
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AtlasPage {
+    pub image: String,
+    pub width: u32,
+    pub height: u32,
+    /// Encoded file size in bytes
+    pub size: u64,
+}
+
+/// Atlas metadata (JSON output)
+///
+/// `image`, `width` and `height` describe the first page; `pages` lists every page. Frames
+/// and animations are sorted by name, so the same atlas always serializes the same way.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AtlasMetadata {
+    pub image: String,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub pages: Vec<AtlasPage>,
+    pub frames: BTreeMap<String, SpriteFrame>,
+    /// Animations grouped from numbered frame keys (`walk_01`, `walk_02`, ...)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub animations: BTreeMap<String, SpriteAnimation>,
+    /// Page colors are premultiplied by alpha
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub premultiplied_alpha: bool,
+}
+
+impl AtlasMetadata {
+    /// Parse atlas metadata JSON
+    pub fn from_json(json: &str) -> RuntimeResult<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Read atlas metadata from a JSON file
+    pub fn open(path: &Path) -> RuntimeResult<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// Get a sprite frame by name
+    pub fn frame(&self, name: &str) -> Option<&SpriteFrame> {
+        self.frames.get(name)
+    }
+
+    /// Get an animation by name
+    pub fn animation(&self, name: &str) -> Option<&SpriteAnimation> {
+        self.animations.get(name)
+    }
+
+    /// Image file of the page a frame lives on
+    pub fn page_image(&self, frame: &SpriteFrame) -> &str {
+        self.pages
+            .get(frame.page)
+            .map(|p| p.image.as_str())
+            .unwrap_or(&self.image)
+    }
+}
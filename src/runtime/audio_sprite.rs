@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::RuntimeResult;
+
+/// Where one sound sits in an audio sprite, in seconds from the start of the file
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AudioSpriteEntry {
+    pub start: f64,
+    pub duration: f64,
+}
+
+/// Sounds concatenated into one audio file (JSON next to it, `sfx.ogg` → `sfx.json`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioSpriteMap {
+    /// Audio file name, relative to the map
+    pub audio: String,
+    /// Sounds by name (source file stem)
+    pub sprites: BTreeMap<String, AudioSpriteEntry>,
+}
+
+impl AudioSpriteMap {
+    /// Parse an audio sprite map JSON
+    pub fn from_json(json: &str) -> RuntimeResult<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Read an audio sprite map from a JSON file
+    pub fn open(path: &Path) -> RuntimeResult<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// Position of a named sound
+    pub fn sprite(&self, name: &str) -> Option<&AudioSpriteEntry> {
+        self.sprites.get(name)
+    }
+}
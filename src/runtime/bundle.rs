@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use super::{RuntimeError, RuntimeResult};
+
+/// Current bundle index format version
+pub const BUNDLE_VERSION: u32 = 1;
+/// Default file name of the bundle inside the output directory
+pub const BUNDLE_FILE_NAME: &str = "assets.bundle";
+/// Suffix of the index written next to a bundle (`assets.bundle` → `assets.bundle.json`)
+pub const BUNDLE_INDEX_SUFFIX: &str = ".json";
+/// `compression` of entries stored as a Zstandard frame
+pub const ZSTD_COMPRESSION: &str = "zstd";
+
+/// Index of a bundle: where every output lives inside the archive (JSON output)
+///
+/// The bundle itself is the outputs back to back, each starting at a multiple of
+/// `alignment`. Prefetch groups are laid out first and in order, so each group is one
+/// contiguous byte range a game can read (or map and prefault) before anything else.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleIndex {
+    pub version: u32,
+    /// Bundle file name, relative to the index
+    pub bundle: String,
+    /// Total size of the bundle in bytes
+    pub size: u64,
+    pub alignment: u64,
+    /// Prefetch groups in file order
+    #[serde(default)]
+    pub groups: Vec<PrefetchGroup>,
+    /// Assets in file order
+    pub entries: Vec<BundleEntry>,
+}
+
+/// Assets sharing a prefetch tag, stored as one byte range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrefetchGroup {
+    /// Tag that selected the group's assets
+    pub name: String,
+    pub offset: u64,
+    pub length: u64,
+}
+
+/// One asset inside a bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleEntry {
+    /// Source path relative to the input directory
+    pub source: String,
+    /// Output path relative to the output directory, as in the manifest
+    pub output: String,
+    pub offset: u64,
+    /// Stored length, compressed when `compression` is set
+    pub length: u64,
+    /// How the stored bytes are compressed (`zstd`); `None` when stored as-is
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<String>,
+    /// Length after decompression
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_length: Option<u64>,
+    /// Hash of the output's contents (hex), as in the manifest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// Prefetch group the asset belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+}
+
+impl BundleIndex {
+    /// Parse a bundle index, rejecting unsupported versions
+    pub fn from_json(json: &str) -> RuntimeResult<Self> {
+        let index: BundleIndex = serde_json::from_str(json)?;
+
+        if index.version != BUNDLE_VERSION {
+            return Err(RuntimeError::UnsupportedBundleVersion {
+                found: index.version,
+                expected: BUNDLE_VERSION,
+            });
+        }
+
+        Ok(index)
+    }
+
+    /// Read a bundle index from a JSON file
+    pub fn open(path: &Path) -> RuntimeResult<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// Index path for a bundle path
+    pub fn index_path(bundle: &Path) -> PathBuf {
+        let mut path = bundle.as_os_str().to_owned();
+        path.push(BUNDLE_INDEX_SUFFIX);
+        PathBuf::from(path)
+    }
+
+    /// Find an entry by output or source path
+    pub fn find(&self, path: &str) -> Option<&BundleEntry> {
+        self.entries
+            .iter()
+            .find(|e| e.output == path)
+            .or_else(|| self.entries.iter().find(|e| e.source == path))
+    }
+
+    /// Find a prefetch group by name
+    pub fn group(&self, name: &str) -> Option<&PrefetchGroup> {
+        self.groups.iter().find(|g| g.name == name)
+    }
+
+    /// Stored and decompressed size of the assets, counting shared outputs once
+    pub fn asset_sizes(&self) -> (u64, u64) {
+        let mut seen = std::collections::HashSet::new();
+        self.entries
+            .iter()
+            .filter(|e| seen.insert(e.offset))
+            .fold((0, 0), |(stored, raw), e| (stored + e.length, raw + e.raw_length.unwrap_or(e.length)))
+    }
+}
+
+impl BundleEntry {
+    /// Decompress the entry's stored bytes, as read from the bundle
+    ///
+    /// Zstandard needs the `runtime-zstd` feature; without it compressed entries are an error.
+    pub fn decode(&self, data: Vec<u8>) -> RuntimeResult<Vec<u8>> {
+        match self.compression.as_deref() {
+            None => Ok(data),
+            #[cfg(any(feature = "cli", feature = "runtime-zstd"))]
+            Some(ZSTD_COMPRESSION) => Ok(zstd::stream::decode_all(&data[..])?),
+            Some(other) => Err(RuntimeError::UnsupportedCompression(other.to_string())),
+        }
+    }
+}
+
+/// An open bundle file and its index
+#[derive(Debug)]
+pub struct Bundle {
+    pub index: BundleIndex,
+    file: File,
+}
+
+impl Bundle {
+    /// Open a bundle and the index next to it
+    pub fn open(bundle: &Path) -> RuntimeResult<Self> {
+        let index = BundleIndex::open(&BundleIndex::index_path(bundle))?;
+        let file = File::open(bundle)?;
+        Ok(Self { index, file })
+    }
+
+    /// Read one asset by output or source path, decompressed
+    pub fn read(&mut self, path: &str) -> RuntimeResult<Option<Vec<u8>>> {
+        let Some(entry) = self.index.find(path).cloned() else {
+            return Ok(None);
+        };
+        let data = self.read_range(entry.offset, entry.length)?;
+        entry.decode(data).map(Some)
+    }
+
+    /// Read a whole prefetch group in one request; entries are at their offset minus the group's,
+    /// still compressed (see [`BundleEntry::decode`])
+    pub fn read_group(&mut self, name: &str) -> RuntimeResult<Option<Vec<u8>>> {
+        let Some(group) = self.index.group(name) else {
+            return Ok(None);
+        };
+        let (offset, length) = (group.offset, group.length);
+        self.read_range(offset, length).map(Some)
+    }
+
+    fn read_range(&mut self, offset: u64, length: u64) -> RuntimeResult<Vec<u8>> {
+        self.file.seek(SeekFrom::Start(offset))?;
+        let mut data = vec![0; length as usize];
+        self.file.read_exact(&mut data)?;
+        Ok(data)
+    }
+}
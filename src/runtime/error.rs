@@ -0,0 +1,22 @@
+use thiserror::Error;
+
+/// Errors returned when loading build output
+#[derive(Debug, Error)]
+pub enum RuntimeError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to parse JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Unsupported manifest version {found} (expected {expected})")]
+    UnsupportedVersion { found: u32, expected: u32 },
+
+    #[error("Unsupported bundle index version {found} (expected {expected})")]
+    UnsupportedBundleVersion { found: u32, expected: u32 },
+
+    #[error("Unsupported bundle compression: {0}")]
+    UnsupportedCompression(String),
+}
+
+pub type RuntimeResult<T> = std::result::Result<T, RuntimeError>;
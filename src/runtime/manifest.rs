@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use super::{RuntimeError, RuntimeResult};
+
+/// Current manifest format version
+pub const MANIFEST_VERSION: u32 = 1;
+/// File name of the manifest inside the output directory
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// Build manifest describing every asset written to the output directory
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct BuildManifest {
+    /// Manifest version for invalidation on format changes
+    pub version: u32,
+    /// Platform preset the assets were built with; `None` for the default settings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    /// Output assets, sorted by source path
+    pub assets: Vec<ManifestEntry>,
+}
+
+/// A single processed asset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Source path relative to the input directory
+    pub source: String,
+    /// Output path relative to the output directory
+    pub output: String,
+    /// Output path before `--fingerprint` added the content hash to the file name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unhashed_output: Option<String>,
+    /// Output format (file extension)
+    pub format: String,
+    /// Kind of asset: `image`, `audio` or `model`
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub asset_type: Option<String>,
+    /// Output file size in bytes
+    #[serde(default)]
+    pub size: u64,
+    /// How the output file is compressed (`zstd`, as `<name>.<format>.zst`); `None` when stored as-is
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<String>,
+    /// Size after decompression
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_size: Option<u64>,
+    /// Hash of the output file's contents (hex), for verifying downloaded outputs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// Hash of the preset and rule settings the output was built with (hex); it changes
+    /// whenever a setting that affects this asset does
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings_hash: Option<String>,
+    /// Rule and sidecar settings applied on top of the preset, as written in the config
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub settings: serde_json::Map<String, serde_json::Value>,
+    /// Policy that picked the output format instead of the preset default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format_policy: Option<String>,
+    /// Audio meant to be streamed from disk rather than decoded into memory, for clips over
+    /// the preset's `music_threshold`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub streaming: bool,
+    /// GPU block format to transcode KTX2 textures into on the target platform
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gpu_format: Option<String>,
+    /// Texture colors are premultiplied by alpha
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub premultiplied_alpha: bool,
+    /// Generated stand-in for an expected asset that has no source yet
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub placeholder: bool,
+    /// Resolution scale of this variant relative to the source, for assets built with `variants`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<f32>,
+    /// PSD layer or group (`Group/Layer`) this output was exported from, for `psd_layers` rules
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layer: Option<String>,
+    /// Frame key of the source in the atlas metadata `output`, for sprites packed by an
+    /// `atlas` rule
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame: Option<String>,
+    /// Page of an atlas or animated GIF spritesheet; the metadata output of the same atlas
+    /// refers to the page by file name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    /// Quality of a lossy texture against its source
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality: Option<QualityMetrics>,
+    /// Source with identical contents and settings whose output this entry shares, for builds
+    /// with `--dedup`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias_of: Option<String>,
+}
+
+/// PSNR and SSIM of a lossy texture measured against its source when it was built
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QualityMetrics {
+    /// Peak signal-to-noise ratio of the visible pixels in dB, 100 for identical images
+    pub psnr: f64,
+    /// Mean structural similarity of luma, 1.0 for identical images
+    pub ssim: f64,
+}
+
+impl BuildManifest {
+    /// Parse a manifest, rejecting unsupported versions
+    pub fn from_json(json: &str) -> RuntimeResult<Self> {
+        let manifest: BuildManifest = serde_json::from_str(json)?;
+
+        if manifest.version != MANIFEST_VERSION {
+            return Err(RuntimeError::UnsupportedVersion {
+                found: manifest.version,
+                expected: MANIFEST_VERSION,
+            });
+        }
+
+        Ok(manifest)
+    }
+
+    /// Read `manifest.json` from a build output directory
+    pub fn open(output_dir: &Path) -> RuntimeResult<Self> {
+        let content = std::fs::read_to_string(output_dir.join(MANIFEST_FILE_NAME))?;
+        Self::from_json(&content)
+    }
+
+    /// Find the entry for a source path
+    pub fn find(&self, source: &str) -> Option<&ManifestEntry> {
+        self.assets.iter().find(|e| e.source == source)
+    }
+
+    /// Find the resolution variant of a source path written at `scale`
+    pub fn find_variant(&self, source: &str, scale: f32) -> Option<&ManifestEntry> {
+        self.assets.iter().find(|e| e.source == source && e.scale == Some(scale))
+    }
+
+    /// Find the output exported from a PSD layer or group
+    pub fn find_layer(&self, source: &str, layer: &str) -> Option<&ManifestEntry> {
+        self.assets.iter().find(|e| e.source == source && e.layer.as_deref() == Some(layer))
+    }
+
+    /// Entries that are generated placeholders rather than real assets
+    pub fn placeholders(&self) -> impl Iterator<Item = &ManifestEntry> {
+        self.assets.iter().filter(|e| e.placeholder)
+    }
+
+    /// Resolve a source path to its output file inside `output_dir`
+    pub fn resolve(&self, output_dir: &Path, source: &str) -> Option<PathBuf> {
+        self.find(source).map(|e| output_dir.join(&e.output))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_version_check() {
+        let json = r#"{"version":1,"assets":[{"source":"a.png","output":"a.png","format":"png","size":10}]}"#;
+        let manifest = BuildManifest::from_json(json).unwrap();
+        assert_eq!(manifest.find("a.png").unwrap().format, "png");
+
+        let outdated = r#"{"version":0,"assets":[]}"#;
+        assert!(matches!(
+            BuildManifest::from_json(outdated),
+            Err(RuntimeError::UnsupportedVersion { found: 0, .. })
+        ));
+    }
+}
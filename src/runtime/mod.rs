@@ -0,0 +1,21 @@
+//! Runtime types for loading asset-forge build output in games
+//!
+//! These are the same serde definitions the CLI writes with, so games don't
+//! need to keep mirror structs in sync by hand. Enable with the `runtime`
+//! feature (and `default-features = false` to skip the CLI dependencies).
+
+mod atlas;
+mod audio_sprite;
+mod bundle;
+mod error;
+mod manifest;
+mod texture_array;
+mod tiles;
+
+pub use atlas::*;
+pub use audio_sprite::*;
+pub use bundle::*;
+pub use error::*;
+pub use manifest::*;
+pub use texture_array::*;
+pub use tiles::*;
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::RuntimeResult;
+
+/// Layer names of a KTX2 texture array (JSON next to the texture, `terrain.ktx2` → `terrain.json`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextureArrayIndex {
+    /// Texture file name, relative to the index
+    pub texture: String,
+    /// Size of every layer
+    pub width: u32,
+    pub height: u32,
+    /// Layer names (source file stems) in layer order
+    pub layers: Vec<String>,
+}
+
+impl TextureArrayIndex {
+    /// Parse a texture array index JSON
+    pub fn from_json(json: &str) -> RuntimeResult<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Read a texture array index from a JSON file
+    pub fn open(path: &Path) -> RuntimeResult<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// Layer index of a named layer
+    pub fn layer(&self, name: &str) -> Option<usize> {
+        self.layers.iter().position(|layer| layer == name)
+    }
+}
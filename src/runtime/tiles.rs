@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::RuntimeResult;
+
+/// File name of the index inside a virtual texture's tile directory
+pub const TILE_INDEX_FILE_NAME: &str = "index.json";
+
+/// One level of the tile pyramid (level 0 is full resolution)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TileLevel {
+    pub width: u32,
+    pub height: u32,
+    /// Tiles across and down this level
+    pub columns: u32,
+    pub rows: u32,
+}
+
+/// A tile written to disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TileEntry {
+    pub level: u32,
+    pub x: u32,
+    pub y: u32,
+    /// Tile file relative to the index
+    pub file: String,
+    /// Hash of the tile's pixels and encode settings (hex), for detecting changed tiles
+    pub hash: String,
+}
+
+/// Index of a virtual texture tile pyramid (JSON output)
+///
+/// Every tile image is `tile_size + 2 * border` pixels square; the border repeats the
+/// neighbouring texels so filtering across tile edges matches the source texture.
+/// Fully transparent tiles are left out, so a missing entry means an empty tile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VirtualTextureIndex {
+    pub width: u32,
+    pub height: u32,
+    pub tile_size: u32,
+    pub border: u32,
+    /// Tile file format (extension)
+    pub format: String,
+    pub levels: Vec<TileLevel>,
+    pub tiles: Vec<TileEntry>,
+}
+
+impl VirtualTextureIndex {
+    /// Parse a tile index JSON
+    pub fn from_json(json: &str) -> RuntimeResult<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Read a tile index from a JSON file
+    pub fn open(path: &Path) -> RuntimeResult<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// Get the tile at a level and tile coordinate, if it isn't empty
+    pub fn tile(&self, level: u32, x: u32, y: u32) -> Option<&TileEntry> {
+        self.tiles
+            .iter()
+            .find(|t| t.level == level && t.x == x && t.y == y)
+    }
+}
@@ -1,6 +1,10 @@
 // Utility functions for asset-forge
 // This module will be expanded in future phases
 
+pub mod format;
 pub mod hash;
+pub mod output;
 
+pub use format::*;
 pub use hash::*;
+pub use output::*;
@@ -0,0 +1,164 @@
+//! Console output, switched to JSON events on stdout by the global `--json` flag
+//!
+//! In JSON mode stdout carries one event object per line and the usual human-readable
+//! output moves to stderr, so build logs stay readable while tools parse stdout.
+
+use indicatif::{ProgressBar, ProgressDrawTarget};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::io::Write;
+use std::path::Path;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+static SUMMARY: Mutex<Option<Map<String, Value>>> = Mutex::new(None);
+
+/// Print a line of human-readable output: stdout normally, stderr in JSON mode
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if $crate::utils::json_output() {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+pub(crate) use say;
+
+pub fn set_json_output(enabled: bool) {
+    JSON_OUTPUT.store(enabled, Ordering::Relaxed);
+}
+
+pub fn json_output() -> bool {
+    JSON_OUTPUT.load(Ordering::Relaxed)
+}
+
+/// What happened to one input file
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    Built,
+    /// Skipped because the existing output is still current
+    Cached,
+    Restored,
+    Failed,
+}
+
+/// One line of `--json` output
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum Event<'a> {
+    /// Work items finished so far
+    Progress { done: u64, total: u64 },
+    /// Result of one input file
+    File(FileEvent<'a>),
+    /// Last event of every command, with the fields the command recorded via [`summary`]
+    Summary {
+        command: &'a str,
+        success: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        #[serde(flatten)]
+        details: Map<String, Value>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileEvent<'a> {
+    pub path: &'a str,
+    pub status: FileStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<&'a str>,
+}
+
+impl<'a> FileEvent<'a> {
+    /// A file event without sizes or output
+    pub fn new(path: &'a str, status: FileStatus) -> Self {
+        Self { path, status, output: None, input_size: None, output_size: None, error: None }
+    }
+}
+
+/// Emit the `built` event of a command that writes one output
+pub fn emit_output(input: &Path, output: &Path, input_size: u64, output_size: u64) {
+    let (path, output) = (input.display().to_string(), output.display().to_string());
+    emit(&Event::File(FileEvent {
+        output: Some(&output),
+        input_size: Some(input_size),
+        output_size: Some(output_size),
+        ..FileEvent::new(&path, FileStatus::Built)
+    }));
+}
+
+/// Write an event to stdout; a no-op unless JSON mode is on
+pub fn emit(event: &Event) {
+    if !json_output() {
+        return;
+    }
+    if let Ok(line) = serde_json::to_string(event) {
+        let mut stdout = std::io::stdout().lock();
+        let _ = writeln!(stdout, "{}", line);
+        let _ = stdout.flush();
+    }
+}
+
+/// A progress bar plus the atomic count reported in progress events
+pub struct Progress {
+    bar: ProgressBar,
+    done: AtomicU64,
+}
+
+impl Deref for Progress {
+    type Target = ProgressBar;
+
+    fn deref(&self) -> &ProgressBar {
+        &self.bar
+    }
+}
+
+/// A progress bar over `len` work items, hidden in JSON mode in favor of progress events
+pub fn progress_bar(len: u64) -> Progress {
+    let bar = if json_output() {
+        ProgressBar::with_draw_target(Some(len), ProgressDrawTarget::hidden())
+    } else {
+        ProgressBar::new(len)
+    };
+    Progress { bar, done: AtomicU64::new(0) }
+}
+
+/// Advance a bar from [`progress_bar`] by `delta` items and emit a progress event
+///
+/// The count comes from a single `fetch_add`, so concurrent callers never report the same value.
+pub fn advance(pb: &Progress, delta: u64) {
+    let done = pb.done.fetch_add(delta, Ordering::Relaxed) + delta;
+    pb.bar.inc(delta);
+    emit(&Event::Progress { done, total: pb.bar.length().unwrap_or(0) });
+}
+
+/// Record a field of the command's summary event
+pub fn summary(key: &str, value: impl Serialize) {
+    if !json_output() {
+        return;
+    }
+    if let Ok(value) = serde_json::to_value(value) {
+        SUMMARY.lock().unwrap().get_or_insert_with(Map::new).insert(key.to_string(), value);
+    }
+}
+
+/// Emit the summary event once the command has finished
+pub fn emit_summary(command: &str, result: &anyhow::Result<()>) {
+    let details = SUMMARY.lock().unwrap().take().unwrap_or_default();
+    emit(&Event::Summary {
+        command,
+        success: result.is_ok(),
+        error: result.as_ref().err().map(|e| format!("{:#}", e)),
+        details,
+    });
+}
@@ -0,0 +1,185 @@
+//! `build` manifest and bundle contents, checked against the binary
+
+use assert_cmd::Command;
+use image::codecs::gif::GifEncoder;
+use image::{Delay, Frame, Rgba, RgbaImage};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+fn write_png(path: &Path, width: u32, height: u32, color: [u8; 4]) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    RgbaImage::from_pixel(width, height, Rgba(color))
+        .save(path)
+        .unwrap();
+}
+
+fn write_gif(path: &Path, frames: u8) {
+    let mut encoder = GifEncoder::new(fs::File::create(path).unwrap());
+    for i in 0..frames {
+        let image = RgbaImage::from_pixel(8, 8, Rgba([i * 80, 0, 0, 255]));
+        let frame = Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(100, 1));
+        encoder.encode_frame(frame).unwrap();
+    }
+}
+
+fn read_json(path: &Path) -> Value {
+    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+/// Manifest entries of `source` in file order
+fn entries<'a>(manifest: &'a Value, source: &str) -> Vec<&'a Value> {
+    manifest["assets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|e| e["source"] == source)
+        .collect()
+}
+
+#[test]
+fn test_atlas_pages_in_manifest_and_bundle() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("assets");
+    write_png(&input.join("sprites/a.png"), 16, 16, [255, 0, 0, 255]);
+    write_png(&input.join("sprites/b.png"), 8, 8, [0, 255, 0, 255]);
+    fs::write(
+        dir.path().join("asset-forge.toml"),
+        "[rules]\n\"sprites/*.png\" = { atlas = true }\n",
+    )
+    .unwrap();
+    let output = dir.path().join("out");
+
+    Command::new(env!("CARGO_BIN_EXE_asset-forge"))
+        .current_dir(dir.path())
+        .args([
+            "build", "assets", "--output", "out", "--preset", "desktop", "--bundle",
+        ])
+        .assert()
+        .success();
+
+    let manifest = read_json(&output.join("manifest.json"));
+    for source in ["sprites/a.png", "sprites/b.png"] {
+        let sprite = entries(&manifest, source);
+        assert_eq!(sprite.len(), 1);
+        assert_eq!(sprite[0]["output"], "sprites.json");
+        assert_eq!(sprite[0]["type"], "image");
+    }
+    let pages = entries(&manifest, "sprites");
+    assert_eq!(pages.len(), 1);
+    assert_eq!(pages[0]["output"], "sprites.png");
+    assert_eq!(pages[0]["page"], 0);
+    assert_eq!(pages[0]["type"], "image");
+    assert_eq!(
+        pages[0]["size"],
+        fs::metadata(output.join("sprites.png")).unwrap().len()
+    );
+    assert!(pages[0]["hash"].is_string());
+
+    let index = read_json(&output.join("assets.bundle.json"));
+    let bundled: Vec<&Value> = index["entries"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| &e["output"])
+        .collect();
+    assert!(bundled.contains(&&Value::from("sprites.png")));
+    assert!(bundled.contains(&&Value::from("sprites.json")));
+}
+
+#[test]
+fn test_gif_spritesheet_in_manifest() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("assets");
+    fs::create_dir_all(&input).unwrap();
+    write_gif(&input.join("walk.gif"), 3);
+    let output = dir.path().join("out");
+
+    Command::new(env!("CARGO_BIN_EXE_asset-forge"))
+        .current_dir(dir.path())
+        .args(["build", "assets", "--output", "out", "--preset", "desktop"])
+        .assert()
+        .success();
+
+    let manifest = read_json(&output.join("manifest.json"));
+    let gif = entries(&manifest, "walk.gif");
+    assert_eq!(gif.len(), 2);
+    assert_eq!(gif[0]["output"], "walk.json");
+    assert!(gif[0]["page"].is_null());
+    assert_eq!(gif[1]["output"], "walk.png");
+    assert_eq!(gif[1]["page"], 0);
+    assert_eq!(
+        gif[1]["size"],
+        fs::metadata(output.join("walk.png")).unwrap().len()
+    );
+    assert!(gif[1]["hash"].is_string());
+}
+
+#[test]
+fn test_fingerprint_switched_off_restores_plain_names() {
+    let dir = tempfile::tempdir().unwrap();
+    write_png(&dir.path().join("assets/a.png"), 8, 8, [255, 0, 0, 255]);
+    let output = dir.path().join("out");
+    let build = |extra: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_asset-forge"))
+            .current_dir(dir.path())
+            .args(["build", "assets", "--output", "out"])
+            .args(extra)
+            .assert()
+            .success();
+        let manifest = read_json(&output.join("manifest.json"));
+        entries(&manifest, "a.png")[0]["output"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    };
+
+    let fingerprinted = build(&["--fingerprint"]);
+    assert_ne!(fingerprinted, "a.png");
+    assert!(output.join(&fingerprinted).exists());
+
+    assert_eq!(build(&[]), "a.png");
+    assert!(output.join("a.png").exists());
+    assert!(!output.join(&fingerprinted).exists());
+}
+
+#[test]
+fn test_failed_output_compression_is_retried() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("assets");
+    fs::create_dir_all(&input).unwrap();
+    fs::write(input.join("notes.txt"), "compress me ".repeat(1024)).unwrap();
+    fs::write(
+        dir.path().join("asset-forge.toml"),
+        concat!(
+            "[presets.desktop]\n",
+            "output_zstd_level = 3\n",
+            "output_zstd_min_size = \"1KB\"\n",
+            "[processors.custom]\n",
+            "\"*.txt\" = { cmd = \"cp {input} {output}\" }\n",
+        ),
+    )
+    .unwrap();
+    let output = dir.path().join("out");
+    let build = || {
+        Command::new(env!("CARGO_BIN_EXE_asset-forge"))
+            .current_dir(dir.path())
+            .args(["build", "assets", "--output", "out", "--preset", "desktop"])
+            .assert()
+    };
+
+    // A directory in the way of the compressed output fails the asset
+    fs::create_dir_all(output.join("notes.txt.zst")).unwrap();
+    build().failure();
+    let manifest = read_json(&output.join("manifest.json"));
+    assert!(entries(&manifest, "notes.txt").is_empty());
+
+    fs::remove_dir(output.join("notes.txt.zst")).unwrap();
+    build().success();
+    let manifest = read_json(&output.join("manifest.json"));
+    let notes = entries(&manifest, "notes.txt");
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0]["output"], "notes.txt.zst");
+    assert_eq!(notes[0]["compression"], "zstd");
+    assert!(output.join("notes.txt.zst").is_file());
+}
@@ -0,0 +1,234 @@
+//! Global flags, exit codes and output verification, checked against the binary
+
+use assert_cmd::Command;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+fn asset_forge(dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_asset-forge"));
+    command.current_dir(dir);
+    command
+}
+
+fn write_png(path: &Path) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    image::RgbaImage::from_pixel(8, 8, image::Rgba([255, 0, 0, 255]))
+        .save(path)
+        .unwrap();
+}
+
+/// One second of a 440 Hz tone
+fn write_wav(path: &Path) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: 48_000,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec).unwrap();
+    for i in 0..48_000 {
+        let t = i as f32 / 48_000.0;
+        let sample = (t * 440.0 * std::f32::consts::TAU).sin() * 0.5;
+        writer
+            .write_sample((sample * i16::MAX as f32) as i16)
+            .unwrap();
+    }
+    writer.finalize().unwrap();
+}
+
+/// A single-triangle COLLADA scene
+fn write_dae(path: &Path) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(
+        path,
+        r##"<?xml version="1.0" encoding="utf-8"?>
+<COLLADA xmlns="http://www.collada.org/2005/11/COLLADASchema" version="1.4.1">
+  <asset><unit meter="1"/><up_axis>Y_UP</up_axis></asset>
+  <library_geometries>
+    <geometry id="tri">
+      <mesh>
+        <source id="tri-positions">
+          <float_array id="tri-positions-array" count="9">0 0 0 1 0 0 0 1 0</float_array>
+          <technique_common>
+            <accessor source="#tri-positions-array" count="3" stride="3"/>
+          </technique_common>
+        </source>
+        <vertices id="tri-vertices">
+          <input semantic="POSITION" source="#tri-positions"/>
+        </vertices>
+        <triangles count="1">
+          <input semantic="VERTEX" source="#tri-vertices" offset="0"/>
+          <p>0 1 2</p>
+        </triangles>
+      </mesh>
+    </geometry>
+  </library_geometries>
+  <library_visual_scenes>
+    <visual_scene id="scene">
+      <node id="tri-node"><instance_geometry url="#tri"/></node>
+    </visual_scene>
+  </library_visual_scenes>
+  <scene><instance_visual_scene url="#scene"/></scene>
+</COLLADA>"##,
+    )
+    .unwrap();
+}
+
+/// Events written to stdout in `--json` mode
+fn events(stdout: &[u8]) -> Vec<Value> {
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("stdout holds only JSON events"))
+        .collect()
+}
+
+#[test]
+fn test_global_json_flag_emits_events() {
+    let dir = tempfile::tempdir().unwrap();
+    write_png(&dir.path().join("assets/a.png"));
+
+    // Global flags go before or after the subcommand
+    for args in [
+        ["--json", "build", "assets", "--output", "out"],
+        ["build", "--json", "assets", "--output", "out"],
+    ] {
+        let output = asset_forge(dir.path())
+            .args(args)
+            .args(["--force"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let events = events(&output.stdout);
+        let file = events.iter().find(|e| e["event"] == "file").unwrap();
+        assert_eq!(file["path"], "a.png");
+        assert_eq!(file["status"], "built");
+        let summary = events.last().unwrap();
+        assert_eq!(summary["event"], "summary");
+        assert_eq!(summary["command"], "build");
+        assert_eq!(summary["success"], true);
+    }
+}
+
+#[test]
+fn test_json_model_event_names_collada_source() {
+    let dir = tempfile::tempdir().unwrap();
+    write_dae(&dir.path().join("tri.dae"));
+
+    let output = asset_forge(dir.path())
+        .args(["--json", "model", "tri.dae", "--output", "tri.glb"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let events = events(&output.stdout);
+    let file = events.iter().find(|e| e["event"] == "file").unwrap();
+    // The source the user passed, not the temporary GLB it was converted to
+    assert_eq!(file["path"], "tri.dae");
+    assert_eq!(file["output"], "tri.glb");
+    assert_eq!(
+        file["input_size"],
+        fs::metadata(dir.path().join("tri.dae")).unwrap().len()
+    );
+    assert!(dir.path().join("tri.glb").exists());
+}
+
+#[test]
+fn test_exit_code_for_failed_asset() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("assets")).unwrap();
+    fs::write(dir.path().join("assets/broken.png"), b"not a png").unwrap();
+
+    asset_forge(dir.path())
+        .args(["build", "assets", "--output", "out"])
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn test_exit_code_for_invalid_config() {
+    let dir = tempfile::tempdir().unwrap();
+    write_png(&dir.path().join("assets/a.png"));
+    fs::write(
+        dir.path().join("asset-forge.toml"),
+        "[rules]\n\"sprites/[*.png\" = { atlas = true }\n",
+    )
+    .unwrap();
+
+    asset_forge(dir.path())
+        .args(["build", "assets", "--output", "out"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_verify_outputs_decodes_opus() {
+    let dir = tempfile::tempdir().unwrap();
+    write_wav(&dir.path().join("assets/music.wav"));
+    fs::write(
+        dir.path().join("asset-forge.toml"),
+        "[rules.\"**/*.wav\"]\nformat = \"opus\"\n",
+    )
+    .unwrap();
+
+    let output = asset_forge(dir.path())
+        .args([
+            "--json",
+            "build",
+            "assets",
+            "--output",
+            "out",
+            "--verify-outputs",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(dir.path().join("out/music.opus").exists());
+    let summary = events(&output.stdout).pop().unwrap();
+    assert_eq!(summary["success"], true);
+    assert_eq!(summary["verify_failures"], Value::Array(Vec::new()));
+    assert_eq!(summary["verify_unchecked"], Value::Array(Vec::new()));
+}
+
+/// Subcommands listed under `Commands:` in the help of `args`, which must print it
+fn subcommands(args: &[String]) -> Vec<String> {
+    let output = Command::new(env!("CARGO_BIN_EXE_asset-forge"))
+        .args(args)
+        .arg("--help")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{} --help failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .skip_while(|line| *line != "Commands:")
+        .skip(1)
+        .take_while(|line| !line.is_empty())
+        // Wrapped descriptions are indented further than the names
+        .filter(|line| line.starts_with("  ") && !line.starts_with("   "))
+        .filter_map(|line| line.split_whitespace().next())
+        .filter(|name| *name != "help")
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn test_help_for_every_subcommand() {
+    let mut pending = vec![Vec::new()];
+    let mut checked = 0;
+    while let Some(args) = pending.pop() {
+        for name in subcommands(&args) {
+            let mut nested = args.clone();
+            nested.push(name);
+            pending.push(nested);
+        }
+        checked += 1;
+    }
+    // The top level, every command and the nested ones under cache, bundle, rules and audit
+    assert!(checked > 25, "only {} help pages found", checked);
+}
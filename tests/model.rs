@@ -0,0 +1,262 @@
+//! `model` output, checked against the binary
+
+use assert_cmd::Command;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+/// A glTF quad grid with duplicated vertices, one morph target and an external buffer
+fn write_grid(path: &Path, size: u32) {
+    let mut positions = Vec::new();
+    let mut deltas = Vec::new();
+    for y in 0..size {
+        for x in 0..size {
+            // Two triangles per cell, unindexed
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 0), (1, 1), (0, 1)] {
+                positions.extend([(x + dx) as f32, (y + dy) as f32, 0.0]);
+                deltas.extend([0.0, 0.0, if x + dx == 0 { 0.5 } else { 0.0 }]);
+            }
+        }
+    }
+    let count = positions.len() / 3;
+    let mut bin: Vec<u8> = positions
+        .iter()
+        .flat_map(|v: &f32| v.to_le_bytes())
+        .collect();
+    bin.extend(deltas.iter().flat_map(|v: &f32| v.to_le_bytes()));
+    fs::write(path.with_extension("bin"), &bin).unwrap();
+
+    let half = bin.len() / 2;
+    let document = json!({
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0, "name": "grid" }],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "targets": [{ "POSITION": 1 }] }] }],
+        "buffers": [{ "byteLength": bin.len(), "uri": "grid.bin" }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": half },
+            { "buffer": 0, "byteOffset": half, "byteLength": half },
+        ],
+        "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": count, "type": "VEC3",
+              "min": [0.0, 0.0, 0.0], "max": [size as f32, size as f32, 0.0] },
+            { "bufferView": 1, "componentType": 5126, "count": count, "type": "VEC3",
+              "min": [0.0, 0.0, 0.0], "max": [0.0, 0.0, 0.5] },
+        ],
+    });
+    fs::write(path, serde_json::to_vec(&document).unwrap()).unwrap();
+}
+
+/// JSON chunk of a GLB file
+fn glb_json(path: &Path) -> Value {
+    glb_chunks(path).0
+}
+
+/// JSON and binary chunks of a GLB file
+fn glb_chunks(path: &Path) -> (Value, Vec<u8>) {
+    let data = fs::read(path).unwrap();
+    assert_eq!(&data[..4], b"glTF");
+    let length = u32::from_le_bytes(data[12..16].try_into().unwrap()) as usize;
+    let json = serde_json::from_slice(&data[20..20 + length]).unwrap();
+    (json, data[20 + length + 8..].to_vec())
+}
+
+/// Bytes of an uncompressed accessor, tightly packed
+fn accessor_bytes<'a>(document: &Value, bin: &'a [u8], accessor: &Value) -> &'a [u8] {
+    let view = &document["bufferViews"][accessor["bufferView"].as_u64().unwrap() as usize];
+    let offset = view["byteOffset"].as_u64().unwrap_or(0) as usize;
+    &bin[offset..offset + view["byteLength"].as_u64().unwrap() as usize]
+}
+
+/// Index buffer and vertex positions of each LOD of the first node, full detail first
+fn lod_levels(path: &Path) -> Vec<(Vec<u32>, Vec<[f32; 3]>)> {
+    let (document, bin) = glb_chunks(path);
+    let node = &document["nodes"][0];
+    let mut nodes = vec![node];
+    if let Some(ids) = node["extensions"]["MSFT_lod"]["ids"].as_array() {
+        nodes.extend(
+            ids.iter()
+                .map(|id| &document["nodes"][id.as_u64().unwrap() as usize]),
+        );
+    }
+    nodes
+        .iter()
+        .map(|node| {
+            let mesh = &document["meshes"][node["mesh"].as_u64().unwrap() as usize];
+            let primitive = &mesh["primitives"][0];
+            let accessor = |key: &Value| &document["accessors"][key.as_u64().unwrap() as usize];
+            let indices = accessor(&primitive["indices"]);
+            let data = accessor_bytes(&document, &bin, indices);
+            let indices: Vec<u32> = match indices["componentType"].as_u64().unwrap() {
+                5123 => data
+                    .chunks(2)
+                    .map(|c| u16::from_le_bytes([c[0], c[1]]) as u32)
+                    .collect(),
+                _ => data
+                    .chunks(4)
+                    .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+                    .collect(),
+            };
+            let data = accessor_bytes(
+                &document,
+                &bin,
+                accessor(&primitive["attributes"]["POSITION"]),
+            );
+            let positions = data
+                .chunks(12)
+                .map(|c| {
+                    let f = |i: usize| f32::from_le_bytes(c[i * 4..i * 4 + 4].try_into().unwrap());
+                    [f(0), f(1), f(2)]
+                })
+                .collect();
+            (indices, positions)
+        })
+        .collect()
+}
+
+/// Run `model --lod` on an 8x8 grid with extra arguments
+fn write_lods(dir: &Path, args: &[&str]) -> Vec<(Vec<u32>, Vec<[f32; 3]>)> {
+    let input = dir.join("grid.gltf");
+    write_grid(&input, 8);
+    let output = dir.join("grid.glb");
+    Command::new(env!("CARGO_BIN_EXE_asset-forge"))
+        .arg("model")
+        .arg(&input)
+        .arg("--output")
+        .arg(&output)
+        .args(["--lod", "--lod-count", "3"])
+        .args(args)
+        .assert()
+        .success();
+    lod_levels(&output)
+}
+
+#[test]
+fn test_model_writes_optimized_glb() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("grid.gltf");
+    write_grid(&input, 4);
+    let output = dir.path().join("out/grid.glb");
+
+    Command::new(env!("CARGO_BIN_EXE_asset-forge"))
+        .arg("model")
+        .arg(&input)
+        .arg("--output")
+        .arg(&output)
+        .assert()
+        .success();
+
+    let document = glb_json(&output);
+    let primitive = &document["meshes"][0]["primitives"][0];
+    let accessor = |index: &Value| &document["accessors"][index.as_u64().unwrap() as usize];
+    // Welding leaves the 5x5 grid's 25 vertices, indexed
+    assert_eq!(accessor(&primitive["attributes"]["POSITION"])["count"], 25);
+    assert_eq!(accessor(&primitive["targets"][0]["POSITION"])["count"], 25);
+    assert_eq!(accessor(&primitive["indices"])["count"], 4 * 4 * 6);
+    assert!(accessor(&primitive["targets"][0]["POSITION"])["min"].is_array());
+    assert_eq!(document["nodes"][0]["name"], "grid");
+    // The external buffer is merged into the binary chunk
+    assert_eq!(document["buffers"].as_array().unwrap().len(), 1);
+    assert!(document["buffers"][0].get("uri").is_none());
+}
+
+#[test]
+fn test_model_compress_requires_meshopt_extension() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("grid.gltf");
+    write_grid(&input, 4);
+    let output = dir.path().join("grid.glb");
+
+    Command::new(env!("CARGO_BIN_EXE_asset-forge"))
+        .arg("model")
+        .arg(&input)
+        .arg("--output")
+        .arg(&output)
+        .arg("--compress")
+        .assert()
+        .success();
+
+    let document = glb_json(&output);
+    assert_eq!(
+        document["extensionsRequired"],
+        json!(["EXT_meshopt_compression"])
+    );
+    let fallback = &document["buffers"][1]["extensions"]["EXT_meshopt_compression"];
+    assert_eq!(fallback["fallback"], true);
+    for view in document["bufferViews"].as_array().unwrap() {
+        assert_eq!(view["buffer"], 1);
+        assert_eq!(view["extensions"]["EXT_meshopt_compression"]["buffer"], 0);
+    }
+}
+
+#[test]
+fn test_collision_mesh_named_after_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("grid.gltf");
+    write_grid(&input, 2);
+    let output = dir.path().join("level.glb");
+
+    Command::new(env!("CARGO_BIN_EXE_asset-forge"))
+        .arg("model")
+        .arg(&input)
+        .arg("--output")
+        .arg(&output)
+        .args(["--collision", "simplified"])
+        .assert()
+        .success();
+
+    assert!(dir.path().join("level_collision.glb").exists());
+    assert!(!dir.path().join("grid_collision.glb").exists());
+}
+
+#[test]
+fn test_model_writes_lod_levels() {
+    let dir = tempfile::tempdir().unwrap();
+    let levels = write_lods(dir.path(), &[]);
+
+    assert_eq!(levels.len(), 4);
+    assert_eq!(levels[0].0.len(), 8 * 8 * 6);
+    for pair in levels.windows(2) {
+        assert!(pair[1].0.len() < pair[0].0.len());
+    }
+    let document = glb_json(&dir.path().join("grid.glb"));
+    assert!(document["extensionsUsed"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("MSFT_lod")));
+    // LOD nodes are alternatives, not part of the scene
+    assert_eq!(document["scenes"][0]["nodes"], json!([0]));
+}
+
+#[test]
+fn test_lock_border_keeps_border_vertices() {
+    let on_border = |p: &[f32; 3]| p[0] == 0.0 || p[0] == 8.0 || p[1] == 0.0 || p[1] == 8.0;
+    let border_kept = |(indices, positions): &(Vec<u32>, Vec<[f32; 3]>)| {
+        let used: Vec<[f32; 3]> = indices.iter().map(|&i| positions[i as usize]).collect();
+        positions
+            .iter()
+            .filter(|p| on_border(p))
+            .all(|p| used.contains(p))
+    };
+
+    let dir = tempfile::tempdir().unwrap();
+    let levels = write_lods(dir.path(), &["--lock-border"]);
+    assert_eq!(levels[0].1.iter().filter(|p| on_border(p)).count(), 32);
+    assert!(levels.len() > 1);
+    assert!(levels.iter().all(border_kept));
+
+    // Without the lock the flat grid's border collapses
+    let dir = tempfile::tempdir().unwrap();
+    let levels = write_lods(dir.path(), &[]);
+    assert!(!levels[1..].iter().all(border_kept));
+
+    // LOD tuning without LODs is rejected
+    let input = dir.path().join("grid.gltf");
+    Command::new(env!("CARGO_BIN_EXE_asset-forge"))
+        .arg("model")
+        .arg(&input)
+        .arg("--lock-border")
+        .assert()
+        .code(2);
+}
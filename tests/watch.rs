@@ -0,0 +1,130 @@
+//! `watch` builds changed files the way `build` does, checked against the binary
+
+use image::{Rgba, RgbaImage};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+fn write_png(path: &Path, width: u32, height: u32, color: [u8; 4]) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    RgbaImage::from_pixel(width, height, Rgba(color))
+        .save(path)
+        .unwrap();
+}
+
+/// Kills the watcher when a test ends, passing or not
+struct Watcher(Child);
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn watch(dir: &Path) -> Watcher {
+    let child = Command::new(env!("CARGO_BIN_EXE_asset-forge"))
+        .current_dir(dir)
+        .args([
+            "watch",
+            "assets",
+            "--output",
+            "out",
+            "--preset",
+            "desktop",
+            "--debounce",
+            "50",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    // Give the watcher time to register before files change
+    std::thread::sleep(Duration::from_millis(500));
+    Watcher(child)
+}
+
+/// Poll until `done` holds, for at most 30 seconds
+fn wait_for(what: &str, done: impl Fn() -> bool) {
+    let start = Instant::now();
+    while !done() {
+        assert!(
+            start.elapsed() < Duration::from_secs(30),
+            "timed out waiting for {}",
+            what
+        );
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+fn atlas_frames(path: &Path) -> Vec<String> {
+    let Ok(text) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let Ok(metadata) = serde_json::from_str::<Value>(&text) else {
+        return Vec::new();
+    };
+    let mut frames: Vec<String> = metadata["frames"]
+        .as_object()
+        .map(|frames| frames.keys().cloned().collect())
+        .unwrap_or_default();
+    frames.sort();
+    frames
+}
+
+#[test]
+fn test_watch_repacks_atlas_of_changed_sprite() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("assets");
+    fs::create_dir_all(input.join("sprites")).unwrap();
+    fs::write(
+        dir.path().join("asset-forge.toml"),
+        "[rules]\n\"sprites/*.png\" = { atlas = true }\n",
+    )
+    .unwrap();
+    let output = dir.path().join("out");
+    let _watcher = watch(dir.path());
+
+    write_png(&input.join("sprites/a.png"), 16, 16, [255, 0, 0, 255]);
+    write_png(&input.join("sprites/b.png"), 8, 8, [0, 255, 0, 255]);
+
+    let metadata = output.join("sprites.json");
+    wait_for("both sprites in the atlas", || {
+        atlas_frames(&metadata) == ["a", "b"]
+    });
+    assert!(output.join("sprites.png").exists());
+    // Sprites go into the atlas rather than being built one by one
+    assert!(!output.join("sprites/a.png").exists());
+    assert!(!output.join("sprites/b.png").exists());
+}
+
+#[test]
+fn test_watch_uses_custom_processors_and_config_presets() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("assets");
+    fs::create_dir_all(&input).unwrap();
+    fs::write(
+        dir.path().join("asset-forge.toml"),
+        concat!(
+            "[presets.desktop]\n",
+            "texture_format = \"webp\"\n",
+            "[processors.custom]\n",
+            "\"*.txt\" = { cmd = \"cp {input} {output}\", extension = \"out\" }\n",
+        ),
+    )
+    .unwrap();
+    let output = dir.path().join("out");
+    let _watcher = watch(dir.path());
+
+    fs::write(input.join("notes.txt"), "hello").unwrap();
+    write_png(&input.join("photo.png"), 8, 8, [0, 0, 255, 255]);
+
+    wait_for("the custom processor output", || {
+        fs::read_to_string(output.join("notes.out")).is_ok_and(|text| text == "hello")
+    });
+    wait_for("the image in the config preset's format", || {
+        output.join("photo.webp").exists()
+    });
+}